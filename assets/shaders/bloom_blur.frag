@@ -0,0 +1,23 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 vTexCoords;
+
+uniform sampler2D image;
+
+// Blur direction. (1, 0) for horizontal, (0, 1) for vertical pass
+uniform vec2 direction = vec2(1.0, 0.0);
+
+// 9-tap separable gaussian kernel
+const float weight[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+  vec2 texel = direction / vec2(textureSize(image, 0));
+  vec3 result = texture(image, vTexCoords).rgb * weight[0];
+  for (int i = 1; i < 5; ++i) {
+    result += texture(image, vTexCoords + texel * float(i)).rgb * weight[i];
+    result += texture(image, vTexCoords - texel * float(i)).rgb * weight[i];
+  }
+  FragColor = vec4(result, 1.0);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 vTexCoords;
+
+uniform sampler2D screenTexture;
+
+// Luminance above which fragments start contributing to bloom
+uniform float threshold = 0.8;
+// Width of the soft transition around the threshold
+uniform float soft_knee = 0.1;
+
+void main()
+{
+  vec3 color = texture(screenTexture, vTexCoords).rgb;
+  float brightness = dot(color, vec3(0.2126, 0.7152, 0.0722));
+  // Soft threshold to avoid hard edges popping in and out of the bright pass
+  float contribution = smoothstep(threshold - soft_knee, threshold + soft_knee, brightness);
+  FragColor = vec4(color * contribution, 1.0);
+}
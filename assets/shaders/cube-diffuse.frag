@@ -1,36 +1,23 @@
 #version 330 core
 
-// Simple ambient + diffuse lighting with a **single** light source
-// Support both point lights (uLightPos) and directional lights (uLightDir)
-// By default will use a point light at the origin
+#include "frame_uniforms.glsl"
+
+// Simple ambient + diffuse lighting with the lights of the shared frame uniforms
+// Supports point, spot & directional lights
 
 in vec3 vNormal;
 in vec3 vPos;
 in vec2 vTexCoord;
 out vec4 FragColor;
 
-// Light settings
-uniform vec3 uAmbientLightColor = vec3(0.15);
-// Position of point light in **world** coordinates
-uniform vec3 uLightPos = vec3(0.0);
-// Direction of directional light in **world** coordinates
-uniform vec3 uLightDir = vec3(1.0, 0.0, 0.0);
-uniform vec3 uLightColor = vec3(1);
-
 uniform sampler2D diffuseMap;
 uniform vec3 uColor = vec3(0.0);
 
 // Calc lighting color in **World** space
 void main() {
   // Diffuse lighting
-  vec3 lightDir = uLightDir;
-  // Calculate light dir for point light if no lightDir explicitly specified
-  if (dot(lightDir, lightDir) < 1e-8) {
-    lightDir = normalize(uLightPos - vPos);
-  }
   vec3 norm = normalize(vNormal);
-  float diff = max(dot(norm, lightDir), 0.0);
-  vec3 diffuse = diff * uLightColor;
+  vec3 diffuse = diffuseLight(norm, vPos);
 
   // Sample object color either from flat color or diffuse map sample
   vec3 objectColor = uColor;
@@ -39,6 +26,6 @@ void main() {
   }
 
   // Combine diffuse with ambient light
-  vec3 result = (uAmbientLightColor + diffuse) * objectColor;
+  vec3 result = (u_ambient_light.rgb + diffuse) * objectColor;
   gl_FragColor = vec4(result, 1.0);
 }
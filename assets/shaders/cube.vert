@@ -1,13 +1,13 @@
 #version 330 core
 
+#include "frame_uniforms.glsl"
+
 layout(location = 0) in vec3 aPos;
 layout(location = 1) in vec3 aNormal;
 layout(location = 2) in vec2 aTexCoord;
 
-uniform mat4 uModel;
-uniform mat3 uModelIV;
-uniform mat4 uView;
-uniform mat4 uProjection;
+// Model matrix per instance, streamed by the ECS renderer
+layout(location = 4) in mat4 aModel;
 
 out vec3 vNormal;
 out vec3 vPos;
@@ -15,9 +15,9 @@ out vec2 vTexCoord;
 
 // Lighting calculation in **WORLD** space
 void main() {
-  vPos = vec3(uModel * vec4(aPos, 1.0));
+  vPos = vec3(aModel * vec4(aPos, 1.0));
   // Calculate normals with inverse transpose
-  vNormal = uModelIV * aNormal;
+  vNormal = transpose(inverse(mat3(aModel))) * aNormal;
   vTexCoord = aTexCoord;
-  gl_Position = uProjection * uView * vec4(vPos, 1.0);
+  gl_Position = u_projection * u_view * vec4(vPos, 1.0);
 }
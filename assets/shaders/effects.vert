@@ -0,0 +1,13 @@
+#version 330 core
+
+#include "frame_uniforms.glsl"
+
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+
+out vec4 color;
+
+void main() {
+  color = aColor;
+  gl_Position = u_projection * u_view * vec4(aPos, 1.0);
+}
@@ -5,9 +5,20 @@ in vec2 vTexCoords;
 
 uniform sampler2D screenTexture;
 uniform sampler2D depthTexture;
+uniform sampler2D bloomTexture;
+uniform sampler2D aoTexture;
 
-float near = 0.1;
-float far  = 1000.0;
+// Additive bloom composite. 0 disables bloom
+uniform float bloom_intensity = 0.0;
+// Screen space ambient occlusion strength. 0 disables SSAO
+uniform float ao_strength = 0.0;
+// Color vision deficiency correction. Identity disables it
+uniform mat3 uColorFilter = mat3(1.0);
+
+// Reverse-Z depth reconstruction
+uniform mat4 uInvProjection;
+// True if glClipControl switched clip depth to [0, 1]
+uniform bool uDepthZeroToOne = false;
 
 // "Sea blue"
 uniform vec3 fog_color = vec3(0.0, 0.411, 0.58);
@@ -21,8 +32,11 @@ uniform float fog_density = -0.06;
 
 float LinearizeDepth(float depth)
 {
-    float z = depth * 2.0 - 1.0; // back to NDC
-    return (2.0 * near * far) / (far + near - z * (far - near));
+    float z = uDepthZeroToOne ? depth : depth * 2.0 - 1.0; // back to NDC
+    // Cleared depth (0) lies behind the far plane without clip control
+    z = max(z, 0.0);
+    vec4 view = uInvProjection * vec4(0.0, 0.0, z, 1.0);
+    return -view.z / view.w;
 }
 
 float calc_fog_factor(float linear_depth) {
@@ -41,8 +55,15 @@ void main()
 
   // sample frame color
   vec3 frame_color = texture(screenTexture, vTexCoords).rgb;
+  // Darken occluded areas before fog is applied
+  float ao = texture(aoTexture, vTexCoords).r;
+  frame_color *= mix(1.0, ao, ao_strength);
 
   // blend frame color with fog color
   vec3 final_color = mix(frame_color, fog_color, fog_factor);
+
+  // Composite bloom on top so glowing objects shine through fog
+  final_color += texture(bloomTexture, vTexCoords).rgb * bloom_intensity;
+  final_color = clamp(uColorFilter * final_color, 0.0, 1.0);
   FragColor = vec4(final_color, 1.0);
 }
@@ -0,0 +1,36 @@
+// Shared per-frame data bound once per frame at binding point 0
+// NOTE: Layout has to match FrameUniformData in src/renderer/frame_uniforms.rs
+#define MAX_LIGHTS 4
+
+layout(std140) uniform FrameUniforms {
+    mat4 u_view;
+    mat4 u_projection;
+    // xyz: Camera position in world space
+    vec4 u_camera_position;
+    float u_time;
+    int u_light_count;
+    // rgb: Ambient light color
+    vec4 u_ambient_light;
+    // xyz: Direction towards light (w = 0) or light position (w = 1) in world space
+    vec4 u_light_positions[MAX_LIGHTS];
+    // rgb: Light color
+    vec4 u_light_colors[MAX_LIGHTS];
+    // xyz: Direction of spot light cones, w: Cosine of the cone half angle (-1 without cone)
+    vec4 u_light_cones[MAX_LIGHTS];
+};
+
+// Summed diffuse light of all lights at a world space position with a normalized normal
+vec3 diffuseLight(vec3 norm, vec3 pos) {
+  vec3 diffuse = vec3(0.0);
+  for (int i = 0; i < min(u_light_count, MAX_LIGHTS); ++i) {
+    vec4 light = u_light_positions[i];
+    // Directional lights store direction, point lights position
+    vec3 lightDir = light.w == 0.0 ? normalize(light.xyz) : normalize(light.xyz - pos);
+    float diff = max(dot(norm, lightDir), 0.0);
+    // Spot lights only reach fragments within their cone
+    vec4 cone = u_light_cones[i];
+    diff *= step(cone.w, dot(-lightDir, cone.xyz));
+    diffuse += diff * u_light_colors[i].rgb;
+  }
+  return diffuse;
+}
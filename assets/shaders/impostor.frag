@@ -0,0 +1,25 @@
+#version 330 core
+
+#include "frame_uniforms.glsl"
+
+// Billboard shaded like the sphere it stands in for, lit by the lights of the frame uniforms
+
+in vec3 vPos;
+in vec2 vCorner;
+in vec3 vColor;
+out vec4 FragColor;
+
+void main() {
+  float distanceSquared = dot(vCorner, vCorner);
+  if (distanceSquared > 1.0) {
+    discard;
+  }
+  // Rows of the view matrix: Camera right, up & direction towards the camera in world space
+  vec3 right = vec3(u_view[0][0], u_view[1][0], u_view[2][0]);
+  vec3 up = vec3(u_view[0][1], u_view[1][1], u_view[2][1]);
+  vec3 back = vec3(u_view[0][2], u_view[1][2], u_view[2][2]);
+  vec3 norm = normalize(right * vCorner.x + up * vCorner.y + back * sqrt(1.0 - distanceSquared));
+
+  vec3 diffuse = diffuseLight(norm, vPos);
+  FragColor = vec4((u_ambient_light.rgb + diffuse) * vColor, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+
+#include "frame_uniforms.glsl"
+
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aCorner;
+layout (location = 2) in vec3 aColor;
+
+out vec3 vPos;
+out vec2 vCorner;
+out vec3 vColor;
+
+// Billboards are expanded on the CPU, already facing the camera in world space
+void main() {
+  vPos = aPos;
+  vCorner = aCorner;
+  vColor = aColor;
+  gl_Position = u_projection * u_view * vec4(aPos, 1.0);
+}
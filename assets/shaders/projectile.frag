@@ -1,7 +1,18 @@
 #version 330 core
 
+#include "frame_uniforms.glsl"
+
+// Ambient + diffuse lighting with a flat color, red unless the entity sets its own
+
+in vec3 vNormal;
+in vec3 vPos;
+in vec2 vTexCoord;
 out vec4 FragColor;
 
+uniform vec3 uColor = vec3(1.0, 0.0, 0.0);
+
 void main() {
-    FragColor = vec4(1.0, 0.0, 0.0, 1.0);
+  vec3 norm = normalize(vNormal);
+  vec3 diffuse = diffuseLight(norm, vPos);
+  FragColor = vec4((u_ambient_light.rgb + diffuse) * uColor, 1.0);
 }
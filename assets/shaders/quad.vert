@@ -1,15 +1,15 @@
 #version 330 core
 
-uniform mat4 uModel;
-uniform mat4 uView;
-uniform mat4 uProjection;
+#include "frame_uniforms.glsl"
 
 layout(location = 0) in vec2 aPos;
+// Model matrix per instance, streamed by the ECS renderer
+layout(location = 4) in mat4 aModel;
 
 out vec2 vUV;
 
 void main() {
-  mat4 mvp = uProjection * uView * uModel;
+  mat4 mvp = u_projection * u_view * aModel;
   vUV = (aPos.xy + 1.0) * 0.5;
   gl_Position = mvp * vec4(aPos, 0.0, 1.0);
 }
@@ -0,0 +1,11 @@
+#version 330 core
+
+// Shows a texture on a quad, e.g. the image of a texture camera on a monitor
+in vec2 vUV;
+out vec4 FragColor;
+
+uniform sampler2D uScreen;
+
+void main() {
+  FragColor = vec4(texture(uScreen, vUV).rgb, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 430 core
+
+in vec3 worldPos;
+in vec3 normal;
+
+out vec4 fragColor;
+
+uniform vec3 sphereColor = vec3(1.0, 0.0, 0.0);
+
+// Arbitrary light direction
+vec3 lightDir = vec3(1.0);
+
+void main() {
+    // Simple lighting, with a bit of ambient so the unlit side keeps its shape
+    float diff = max(dot(normalize(normal), normalize(lightDir)), 0.0);
+    fragColor = vec4(sphereColor * (0.2 + 0.8 * diff), 1.0);
+}
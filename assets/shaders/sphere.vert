@@ -1,15 +1,19 @@
 #version 430 core
 
 layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
 
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 perspective;
 
 out vec3 worldPos;
+out vec3 normal;
 
 void main() {
     vec4 wPos = model * vec4(aPos, 1.0);
     worldPos = vec3(wPos);
+    // Spheres are only scaled uniformly, so the model matrix keeps normals perpendicular
+    normal = mat3(model) * aNormal;
     gl_Position = perspective * view * wPos;
 }
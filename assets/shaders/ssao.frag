@@ -0,0 +1,78 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 vTexCoords;
+
+uniform sampler2D depthTexture;
+
+uniform mat4 uProjection;
+uniform mat4 uInvProjection;
+// True if glClipControl switched clip depth to [0, 1]
+uniform bool uDepthZeroToOne = false;
+
+// Quality preset controlled. Upper bound of 64 samples
+uniform int sample_count = 16;
+// View space sampling radius
+uniform float radius = 1.0;
+// Depth bias to avoid self-occlusion acne
+uniform float bias = 0.025;
+
+const float GOLDEN_ANGLE = 2.3999632;
+
+vec3 view_position(vec2 uv) {
+  float depth = texture(depthTexture, uv).r;
+  // Reverse-Z: cleared depth (0) lies behind the far plane without clip control
+  float z = max(uDepthZeroToOne ? depth : depth * 2.0 - 1.0, 0.0);
+  vec4 ndc = vec4(uv * 2.0 - 1.0, z, 1.0);
+  vec4 view = uInvProjection * ndc;
+  return view.xyz / view.w;
+}
+
+// Cheap per-pixel hash to rotate the sample kernel & trade banding for noise
+float hash(vec2 p) {
+  return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main()
+{
+  float depth = texture(depthTexture, vTexCoords).r;
+  // Reverse-Z: far plane at depth 0
+  if (depth <= 0.0) {
+    // Nothing rendered here (sky). No occlusion
+    FragColor = vec4(1.0);
+    return;
+  }
+  vec3 position = view_position(vTexCoords);
+  // Reconstruct face normal from screen space derivatives. Voxels are flat shaded anyway
+  vec3 normal = normalize(cross(dFdx(position), dFdy(position)));
+
+  // Build tangent space around the reconstructed normal
+  vec3 helper = abs(normal.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+  vec3 tangent = normalize(cross(helper, normal));
+  vec3 bitangent = cross(normal, tangent);
+  mat3 tbn = mat3(tangent, bitangent, normal);
+
+  float rotation = hash(gl_FragCoord.xy) * 6.2831853;
+  float occlusion = 0.0;
+  for (int i = 0; i < sample_count; ++i) {
+    // Fibonacci distribution on the hemisphere, denser close to the origin
+    float t = (float(i) + 0.5) / float(sample_count);
+    float phi = float(i) * GOLDEN_ANGLE + rotation;
+    float cos_theta = 1.0 - t;
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 direction = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    float scale = mix(0.1, 1.0, t * t);
+    vec3 sample_position = position + tbn * direction * radius * scale;
+
+    // Project sample into screen space
+    vec4 offset = uProjection * vec4(sample_position, 1.0);
+    vec2 sample_uv = (offset.xy / offset.w) * 0.5 + 0.5;
+    float sample_depth = view_position(sample_uv).z;
+
+    // Fade out occluders far outside the sampling radius
+    float range_check = smoothstep(0.0, 1.0, radius / abs(position.z - sample_depth));
+    occlusion += (sample_depth >= sample_position.z + bias ? 1.0 : 0.0) * range_check;
+  }
+  float ao = 1.0 - occlusion / float(sample_count);
+  FragColor = vec4(vec3(ao), 1.0);
+}
@@ -0,0 +1,19 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 vTexCoords;
+
+uniform sampler2D image;
+
+// Simple 4x4 box blur to remove the noise introduced by kernel rotation
+void main()
+{
+  vec2 texel = 1.0 / vec2(textureSize(image, 0));
+  float result = 0.0;
+  for (int x = -2; x < 2; ++x) {
+    for (int y = -2; y < 2; ++y) {
+      result += texture(image, vTexCoords + vec2(float(x), float(y)) * texel).r;
+    }
+  }
+  FragColor = vec4(vec3(result / 16.0), 1.0);
+}
@@ -0,0 +1,44 @@
+#version 330 core
+
+#include "frame_uniforms.glsl"
+
+// Ambient + diffuse lighting of voxel chunks, darkened by the baked sky & bounce light
+
+in vec3 vNormal;
+in vec3 vPos;
+in vec2 vTexCoord;
+in float vShade;
+out vec4 FragColor;
+
+uniform sampler2D diffuseMap;
+// Opacity of newly streamed geometry. Dithered, so depth stays correct without blending
+uniform float uFade = 1.0;
+// How soaked surfaces open to the sky are. Wet voxels get darker
+uniform float uWetness = 0.0;
+
+const float BAYER_4X4[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0);
+// Darkening of fully wet voxels
+const float WET_DARKENING = 0.4;
+
+// Calc lighting color in **World** space
+void main() {
+  if (uFade < 1.0) {
+    ivec2 cell = ivec2(gl_FragCoord.xy) % 4;
+    if ((BAYER_4X4[cell.y * 4 + cell.x] + 0.5) / 16.0 > uFade) {
+      discard;
+    }
+  }
+
+  // Diffuse lighting
+  vec3 norm = normalize(vNormal);
+  vec3 diffuse = diffuseLight(norm, vPos);
+
+  vec3 objectColor = texture(diffuseMap, vTexCoord).xyz;
+  objectColor *= 1.0 - WET_DARKENING * uWetness * (1.0 - vShade);
+  vec3 result = (u_ambient_light.rgb + diffuse) * (1.0 - vShade) * objectColor;
+  FragColor = vec4(result, 1.0);
+}
@@ -1,19 +1,29 @@
 #version 330 core
 
+#include "frame_uniforms.glsl"
+
 layout(location = 0) in vec3 aPos;
 layout(location = 1) in vec3 aNormal;
 // Cube location in world space
 layout(location = 2) in vec3 aTranslation;
 layout(location = 3) in vec2 aTexCoord;
 layout(location = 4) in int aMaterialIndex;
+// Baked light that does not reach the voxel. Defaults to 0 if the mesh has no such attribute
+layout(location = 5) in float aShade;
 
 uniform int u_atlasSize = 2;
-uniform mat4 uView;
-uniform mat4 uProjection;
+// Bit per material index that sways in the wind, e.g. leaves & grass
+uniform int uVegetationMask = 0;
+// Offset of the top of vegetation voxels at full sway in world space
+uniform vec3 uWindSway = vec3(0.0);
+
+// Sways per second at the base of a gust
+const float SWAY_FREQUENCY = 1.7;
 
 out vec3 vPos;
 out vec3 vNormal;
 out vec2 vTexCoord;
+out float vShade;
 
 // Calculate uv coord based on material position within atlas
 vec2 vertex_uv_to_atlas_uv(vec2 uv) {
@@ -26,8 +36,19 @@ vec2 vertex_uv_to_atlas_uv(vec2 uv) {
   return baseUV + clamped_uv * tileScale;
 }
 
+// Moves the top vertices of vegetation voxels with the wind, the bottom stays in place
+vec3 vegetation_sway(vec3 localPos) {
+  bool vegetation = aMaterialIndex < 32 && (uVegetationMask & (1 << aMaterialIndex)) != 0;
+  if (!vegetation || localPos.y <= 0.0) {
+    return vec3(0.0);
+  }
+  // Phase varies with position, so neighboring voxels do not move in lockstep
+  float phase = dot(aTranslation.xz, vec2(0.37, 0.71));
+  return uWindSway * (0.75 + 0.25 * sin(u_time * SWAY_FREQUENCY + phase));
+}
+
 void main() {
-  mat4 vp = uProjection * uView;
+  mat4 vp = u_projection * u_view;
   mat4 model = mat4(
       1.0, 0.0, 0.0, 0.0,  // Column 0
       0.0, 1.0, 0.0, 0.0,  // Column 1
@@ -35,10 +56,11 @@ void main() {
       aTranslation.x, aTranslation.y, aTranslation.z, 1.0  // Column 3
   );
 
-  vPos = vec3(model * vec4(aPos, 1.0));
+  vPos = vec3(model * vec4(aPos, 1.0)) + vegetation_sway(aPos);
   // Calculate normals with inverse transpose
   mat3 modelInverseTranspose = mat3(transpose(inverse(model)));
   vNormal = modelInverseTranspose * aNormal;
   vTexCoord = vertex_uv_to_atlas_uv(aTexCoord);
-  gl_Position = uProjection * uView * vec4(vPos, 1.0);
+  vShade = aShade;
+  gl_Position = u_projection * u_view * vec4(vPos, 1.0);
 }
@@ -3,43 +3,123 @@ use std::{
     cell::RefCell,
     collections::VecDeque,
     error::Error,
+    ffi::CString,
     num::NonZeroU32,
     rc::Rc,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use glow::HasContext;
 use glutin::{
     config::ConfigTemplateBuilder,
-    context::{ContextAttributesBuilder, NotCurrentGlContext, PossiblyCurrentContext},
+    context::{
+        ContextApi, ContextAttributesBuilder, GlProfile, NotCurrentContext, NotCurrentGlContext,
+        PossiblyCurrentContext, Version,
+    },
     display::{GetGlDisplay, GlDisplay},
     surface::{GlSurface, Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface},
 };
 use imgui::Context;
 use imgui_glow_renderer::AutoRenderer;
 use imgui_winit_support::{
-    WinitPlatform,
+    HiDpiMode, WinitPlatform,
     winit::{
-        dpi::LogicalSize,
-        event_loop::EventLoop,
-        window::{Window, WindowAttributes},
+        dpi::{LogicalSize, PhysicalSize},
+        event_loop::{ControlFlow, EventLoop},
+        window::{Icon, Window, WindowAttributes},
     },
 };
-use log::{error, info};
+use log::{error, info, warn};
 use raw_window_handle::HasWindowHandle;
 use winit::{application::ApplicationHandler, keyboard::KeyCode};
 
 use crate::{
     config::{RESOLUTION_HEIGHT, RESOLUTION_WIDTH, SIMULATION_DT, USE_VSYNC},
     input::InputState,
-    renderer::{ECSRenderer, metrics::RenderMetrics},
+    log_err,
+    renderer::{
+        ECSRenderer,
+        capabilities::{self, GlCapabilities, GlVersion},
+        debug, depth,
+        feature_flags::Feature,
+        indirect,
+        metrics::RenderMetrics,
+        render_state::RenderState,
+        settings::RenderSettings,
+        spikes::SpikeSnapshot,
+    },
     scenes::GuiScene,
+    util::{FileWatcher, FrameLimitStrategy, FrameLimiter, render_memory_ui, set_tracking_enabled},
 };
 
+pub use crate::renderer::feature_flags::FeatureFlags;
+pub use crate::util::{TrackingAllocator, init_logger};
+
+const WINDOW_ICON_PATH: &str = "assets/icon.png";
+// Updating the title every frame is slow on some window managers
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+/// Directory watched for changed meshes & textures
+const ASSET_DIR: &str = "assets";
+const ASSET_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Size of the default imgui font in UI units
+const UI_FONT_SIZE: f64 = 13.0;
+// Event loop wake-ups while the window is unfocused or minimized
+const BACKGROUND_DT: Duration = Duration::from_millis(200);
+
+enum SceneErrorAction {
+    Resume,
+    Leave,
+}
+
+/// Recoverable error returned by the active scene. Shown until the user resumes or leaves
+struct SceneError {
+    scene: String,
+    message: String,
+}
+
+impl SceneError {
+    fn new(scene: &str, err: &dyn Error) -> SceneError {
+        error!("Scene {scene} failed: {err}");
+        Self {
+            scene: scene.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    fn render_ui(&self, ui: &imgui::Ui) -> Option<SceneErrorAction> {
+        let mut action = None;
+        let display_size = ui.io().display_size;
+        ui.window("Error")
+            .size([400.0, 160.0], imgui::Condition::FirstUseEver)
+            .position(
+                [display_size[0] * 0.5, display_size[1] * 0.5],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .collapsible(false)
+            .build(|| {
+                ui.text(format!("{} failed:", self.scene));
+                ui.text_wrapped(&self.message);
+                ui.separator();
+                if ui.button("Resume") {
+                    action = Some(SceneErrorAction::Resume);
+                }
+                ui.same_line();
+                if ui.button("Leave scene") {
+                    action = Some(SceneErrorAction::Leave);
+                }
+            });
+        action
+    }
+}
+
 pub struct Application {
     // Low level application loop context
     event_loop: Option<EventLoop<()>>,
     window: Window,
     surface: Surface<WindowSurface>,
+    // Physical size of the surface. Applied as viewport before every scene render
+    surface_size: PhysicalSize<u32>,
     winit_platform: WinitPlatform,
     glutin_context: PossiblyCurrentContext,
     imgui_context: Context,
@@ -48,10 +128,28 @@ pub struct Application {
     // Scene switching
     active_scene: Option<Box<dyn GuiScene>>,
     active_scene_started_at: Option<Instant>,
+    // Window unfocused, occluded or app suspended
+    active_scene_paused: bool,
+    window_focused: bool,
+    window_occluded: bool,
+    suspended: bool,
+    // Next event loop wake-up while in the background
+    next_background_wake: Instant,
     available_scenes: VecDeque<Box<dyn GuiScene>>,
     pub max_scene_duration_secs: f32,
+    // Scene is neither ticked nor rendered while its last error is shown
+    scene_error: Option<SceneError>,
+
+    // Window title, followed by scene name & live stats
+    title: String,
+    title_updated_at: Instant,
+    pub show_stats_in_title: bool,
 
     metrics: RenderMetrics,
+    render_settings: RenderSettings,
+    frame_limiter: FrameLimiter,
+    // Hot-reload of changed assets. None if disabled
+    asset_watcher: Option<FileWatcher>,
 
     pub input_state: Rc<RefCell<InputState>>,
 
@@ -66,7 +164,19 @@ pub struct Application {
 }
 
 impl ApplicationHandler for Application {
-    fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {}
+    fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.suspended = false;
+        self.update_scene_paused();
+    }
+
+    fn suspended(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.suspended = true;
+        self.update_scene_paused();
+    }
+
+    fn exiting(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.exit_active_scene();
+    }
 
     fn new_events(
         &mut self,
@@ -82,7 +192,19 @@ impl ApplicationHandler for Application {
         self.current_frame_start = now
     }
 
-    fn about_to_wait(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        // Throttle to a few wake-ups per second in the background, instead of redrawing at full speed
+        if self.active_scene_paused {
+            let (next_wake, frame_due) = background_wake(Instant::now(), self.next_background_wake);
+            self.next_background_wake = next_wake;
+            event_loop.set_control_flow(ControlFlow::WaitUntil(next_wake));
+            if !frame_due {
+                return;
+            }
+        } else {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        }
+
         self.winit_platform
             .prepare_frame(self.imgui_context.io_mut(), &self.window)
             .unwrap();
@@ -92,16 +214,27 @@ impl ApplicationHandler for Application {
         let frame_time = now - self.last_update;
         self.last_update = now;
         self.accumulator += frame_time;
+        if self.simulation_paused() {
+            // Resume where the player left, instead of catching up
+            self.accumulator = Duration::ZERO;
+        }
         while self.accumulator >= SIMULATION_DT {
-            if let Some(scene) = self.active_scene.as_mut() {
+            if let Some(scene) = self.active_scene.as_mut()
+                && self.scene_error.is_none()
+            {
                 let start_tick = Instant::now();
-                scene.tick(SIMULATION_DT.as_secs_f32());
+                if let Err(err) = scene.tick(SIMULATION_DT.as_secs_f32()) {
+                    self.scene_error = Some(SceneError::new(&scene.get_title(), err.as_ref()));
+                }
                 self.metrics.sma_tick_time.add_elapsed(start_tick);
             }
             self.accumulator -= SIMULATION_DT;
         }
 
-        self.window.request_redraw();
+        // Nothing to see while minimized or covered
+        if !self.window_occluded {
+            self.window.request_redraw();
+        }
     }
 
     fn device_event(
@@ -141,10 +274,40 @@ impl ApplicationHandler for Application {
                 let dt = self
                     .current_frame_start
                     .duration_since(self.prev_frame_start);
-                self.metrics.sma_dt.add(dt.as_secs_f32());
+                if self.metrics.record_frame(dt) {
+                    let snapshot = SpikeSnapshot::new(
+                        self.metrics.spikes.frame(),
+                        dt,
+                        self.metrics.averages(),
+                        scene.diagnostics(),
+                    );
+                    match self.metrics.spikes.capture(&snapshot) {
+                        Ok(path) => warn!(
+                            "Frame took {:.1} ms. Diagnostics written to {}",
+                            dt.as_secs_f32() * 1e3,
+                            path.display()
+                        ),
+                        Err(err) => error!("Unable to write frame spike diagnostics: {err}"),
+                    }
+                }
+
+                // ASSET HOT-RELOAD
+                if let Some(watcher) = self.asset_watcher.as_mut() {
+                    let changed = watcher.poll();
+                    if !changed.is_empty() {
+                        info!("Assets changed: {changed:?}");
+                        self.ecs_renderer.reload_assets(&changed);
+                        scene.reload_assets(&changed);
+                    }
+                }
 
                 // SCENE RENDER
                 let start_render = Instant::now();
+                // Scenes must not depend on state left behind by previous scenes or the UI
+                scene
+                    .render_state()
+                    .with_surface_size(self.surface_size.width, self.surface_size.height)
+                    .apply(self.ig_renderer.gl_context().as_ref());
                 if let Some(world) = scene.get_world() {
                     // If scene exposes ecs world, use the default simple render pipeline
                     self.ecs_renderer.render(
@@ -154,28 +317,60 @@ impl ApplicationHandler for Application {
                             .elapsed()
                             .as_secs_f32(),
                     );
+                } else if self.scene_error.is_some() {
+                    let gl = self.ig_renderer.gl_context();
+                    unsafe {
+                        gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+                    }
                 } else {
                     // Scene will define it's own render pipeline
-                    scene.render(self.ig_renderer.gl_context().as_ref(), dt);
+                    if let Err(err) = scene.render(self.ig_renderer.gl_context().as_ref(), dt) {
+                        self.scene_error = Some(SceneError::new(&scene.get_title(), err.as_ref()));
+                    }
                 }
                 self.metrics.sma_render_time.add_elapsed(start_render);
 
                 // UI Renders
                 let ui = self.imgui_context.frame();
-                scene.render_ui(ui);
+                let error_action = match &self.scene_error {
+                    Some(error) => error.render_ui(ui),
+                    None => {
+                        scene.render_ui(ui);
+                        None
+                    }
+                };
                 self.metrics.render_ui(ui);
+                self.metrics.render_overlay(ui, &scene.overlay_stats());
+                debug::render_ui(ui);
+                capabilities::render_ui(ui);
+                render_memory_ui(ui);
+                let settings_changed = self.render_settings.render_ui(ui);
+                if settings_changed {
+                    set_tracking_enabled(
+                        self.render_settings
+                            .features
+                            .is_enabled(Feature::MemoryTracking),
+                    );
+                    log_err!(
+                        scene.apply_render_settings(&self.render_settings),
+                        "Unable to apply render settings: {err}"
+                    );
+                }
 
                 // IMGUI Render logic
                 self.winit_platform.prepare_render(ui, &self.window);
                 let draw_data = self.imgui_context.render();
+                let scene_state = RenderState::capture(self.ig_renderer.gl_context().as_ref());
                 self.ig_renderer
                     .render(draw_data)
                     .expect("error rendering imgui");
+                scene_state.apply(self.ig_renderer.gl_context().as_ref());
                 let start_swap_time = Instant::now();
                 self.surface
                     .swap_buffers(&self.glutin_context)
                     .expect("Failed to swap buffers");
                 self.metrics.sma_swap_time.add_elapsed(start_swap_time);
+                self.frame_limiter.wait(self.render_settings.max_fps);
 
                 // Automatic scene swap
                 if self.max_scene_duration_secs > 0.0
@@ -208,11 +403,44 @@ impl ApplicationHandler for Application {
                         self.start_next_scene().expect("Could not start next scene");
                     }
                 }
+                if let Some(next_scene) = self
+                    .active_scene
+                    .as_mut()
+                    .and_then(|scene| scene.take_next_scene())
+                {
+                    self.available_scenes.push_front(next_scene);
+                    log_err!(self.start_next_scene(), "Could not switch scene: {err}");
+                }
+                match error_action {
+                    Some(SceneErrorAction::Resume) => self.scene_error = None,
+                    Some(SceneErrorAction::Leave) if self.available_scenes.is_empty() => {
+                        event_loop.exit()
+                    }
+                    Some(SceneErrorAction::Leave) => {
+                        log_err!(self.start_next_scene(), "Could not switch scene: {err}");
+                    }
+                    None => {}
+                }
+                // Fonts can only be rebuilt outside of an imgui frame
+                if settings_changed {
+                    self.update_ui_scale();
+                }
                 self.metrics.sma_render_loop.add_elapsed(start_render_loop);
+                if self.title_updated_at.elapsed() >= TITLE_UPDATE_INTERVAL {
+                    self.update_window_title();
+                }
             }
             winit::event::WindowEvent::CloseRequested => {
                 event_loop.exit();
             }
+            winit::event::WindowEvent::Focused(focused) => {
+                self.window_focused = focused;
+                self.update_scene_paused();
+            }
+            winit::event::WindowEvent::Occluded(occluded) => {
+                self.window_occluded = occluded;
+                self.update_scene_paused();
+            }
             winit::event::WindowEvent::MouseInput {
                 device_id: _device_id,
                 state,
@@ -236,6 +464,12 @@ impl ApplicationHandler for Application {
                         error!("User hit ESCAPE. Exiting program");
                         event_loop.exit();
                     }
+                    if code == KeyCode::F3
+                        && event.state == winit::event::ElementState::Pressed
+                        && !event.repeat
+                    {
+                        self.metrics.show_overlay = !self.metrics.show_overlay;
+                    }
                     match event.state {
                         winit::event::ElementState::Pressed => {
                             self.input_state.borrow_mut().key_pressed(code)
@@ -249,7 +483,13 @@ impl ApplicationHandler for Application {
                     error!("Unknwown key pressed");
                 }
             },
+            winit::event::WindowEvent::ScaleFactorChanged { .. } => {
+                // E.g. moved to another monitor
+                self.update_ui_scale();
+                self.surface_size = self.window.inner_size();
+            }
             winit::event::WindowEvent::Resized(new_size) => {
+                self.surface_size = new_size;
                 if new_size.width > 0 && new_size.height > 0 {
                     self.surface.resize(
                         &self.glutin_context,
@@ -270,10 +510,24 @@ impl Application {
         // since it will give us access to a GL context
         let (event_loop, window, surface, context) =
             create_window(title, RESOLUTION_WIDTH, RESOLUTION_HEIGHT);
+        log_err!(
+            set_window_icon(&window, WINDOW_ICON_PATH),
+            "Unable to set window icon: {err}"
+        );
         let (winit_platform, mut imgui_context) = imgui_init(&window);
 
         // OpenGL context from glow
-        let gl = glow_context(&context);
+        let mut gl = glow_context(&context);
+        GlCapabilities::init(&gl);
+        debug::setup_debug_output(&mut gl);
+        depth::setup_clip_control(&gl, |s| {
+            let name = CString::new(s).expect("Invalid GL function name");
+            context.display().get_proc_address(&name).cast()
+        });
+        indirect::setup_multi_draw_indirect(&gl, |s| {
+            let name = CString::new(s).expect("Invalid GL function name");
+            context.display().get_proc_address(&name).cast()
+        });
 
         // OpenGL renderer from this crate
         let ig_renderer = imgui_glow_renderer::AutoRenderer::new(gl, &mut imgui_context)?;
@@ -281,19 +535,34 @@ impl Application {
         let ecs_renderer = ECSRenderer::new(ig_renderer.gl_context())?;
         Ok(Self {
             active_scene: None,
+            active_scene_paused: false,
+            window_focused: true,
+            window_occluded: false,
+            suspended: false,
+            next_background_wake: Instant::now(),
             active_scene_started_at: None,
             available_scenes: VecDeque::new(),
+            scene_error: None,
             current_frame_start: Instant::now(),
             ecs_renderer,
             event_loop: Some(event_loop),
             glutin_context: context,
             ig_renderer,
             metrics: RenderMetrics::new(),
+            render_settings: RenderSettings::default(),
+            frame_limiter: FrameLimiter::new(FrameLimitStrategy::default()),
+            // Only useful while iterating on assets
+            asset_watcher: cfg!(debug_assertions)
+                .then(|| FileWatcher::new(ASSET_DIR, ASSET_POLL_INTERVAL)),
             imgui_context,
             input_state: Rc::new(RefCell::new(InputState::new())),
             max_scene_duration_secs: 0.0,
+            title: title.to_string(),
+            title_updated_at: Instant::now(),
+            show_stats_in_title: true,
             prev_frame_start: Instant::now(),
             surface,
+            surface_size: window.inner_size(),
             window,
             winit_platform,
             accumulator: Duration::ZERO,
@@ -305,10 +574,128 @@ impl Application {
         self.ig_renderer.gl_context()
     }
 
+    /// Caps the frame rate independent of vsync. None or 0 disables the limit
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.render_settings.max_fps = max_fps.filter(|fps| *fps > 0);
+    }
+
+    /// Initial state of the render features, e.g. parsed from the command line
+    pub fn set_features(&mut self, features: FeatureFlags) {
+        self.render_settings.features = features;
+    }
+
+    pub fn set_frame_limit_strategy(&mut self, strategy: FrameLimitStrategy) {
+        self.frame_limiter.strategy = strategy;
+    }
+
+    /// Re-uploads meshes & textures when files in the asset directory change.
+    /// Enabled by default in debug builds
+    pub fn set_asset_hot_reload(&mut self, enabled: bool) {
+        self.asset_watcher = enabled.then(|| FileWatcher::new(ASSET_DIR, ASSET_POLL_INTERVAL));
+    }
+
+    /// Sets the base window title. Scene name & stats are appended
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.update_window_title();
+    }
+
+    fn update_window_title(&mut self) {
+        self.title_updated_at = Instant::now();
+        let Some(scene) = self.active_scene.as_ref() else {
+            self.window.set_title(&self.title);
+            return;
+        };
+        let mut title = format!("{} - {}", self.title, scene.get_title());
+        if self.show_stats_in_title {
+            title.push_str(&format!(
+                " - {:.0} FPS, tick {:.0} micro-s",
+                1.0 / self.metrics.sma_dt.get(),
+                self.metrics.sma_tick_time.get()
+            ));
+        }
+        self.window.set_title(&title);
+    }
+
     pub fn add_scene(&mut self, scene: Box<dyn GuiScene>) {
         self.available_scenes.push_back(scene);
     }
 
+    // Lets the scene clean up & drops it, releasing its GPU resources right away
+    fn exit_active_scene(&mut self) {
+        if let Some(mut scene) = self.active_scene.take() {
+            info!("Exiting scene {}", scene.get_title());
+            scene.on_exit();
+        }
+        self.active_scene_started_at = None;
+        self.active_scene_paused = false;
+        self.scene_error = None;
+    }
+
+    fn update_scene_paused(&mut self) {
+        let paused = is_in_background(self.window_focused, self.window_occluded, self.suspended);
+        if self.active_scene_paused == paused {
+            return;
+        }
+        let Some(scene) = self.active_scene.as_mut() else {
+            return;
+        };
+        self.active_scene_paused = paused;
+        match paused {
+            true => scene.on_pause(),
+            false => scene.on_resume(),
+        }
+    }
+
+    // UI units are logical pixels scaled by the UI scale setting. Imgui only knows about the
+    // combined factor, so the whole layout & the HUD scale together
+    fn update_ui_scale(&mut self) {
+        let scale_factor =
+            ui_scale_factor(self.window.scale_factor(), self.render_settings.ui_scale);
+        if scale_factor == self.winit_platform.hidpi_factor() {
+            return;
+        }
+        info!("Scaling UI by {scale_factor:.2}");
+        self.winit_platform.attach_window(
+            self.imgui_context.io_mut(),
+            &self.window,
+            HiDpiMode::Locked(scale_factor),
+        );
+        let tex_id = self.imgui_context.fonts().tex_id;
+        load_fonts(&mut self.imgui_context, scale_factor);
+        let fonts = self.imgui_context.fonts();
+        fonts.tex_id = tex_id;
+        let texture = fonts.build_rgba32_texture();
+        let Some(atlas) = self.ig_renderer.renderer().font_atlas_texture else {
+            return;
+        };
+        let gl = self.ig_renderer.gl_context();
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(atlas));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::SRGB8_ALPHA8 as i32,
+                texture.width as i32,
+                texture.height as i32,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                Some(texture.data),
+            );
+        }
+    }
+
+    // Local scenes can be stopped in the background, networked scenes would fall out of sync
+    fn simulation_paused(&self) -> bool {
+        self.active_scene_paused
+            && self.render_settings.pause_in_background
+            && self
+                .active_scene
+                .as_ref()
+                .is_some_and(|scene| !scene.is_networked())
+    }
+
     fn start_next_scene(&mut self) -> Result<(), Box<dyn Error>> {
         let mut next_scene = self
             .available_scenes
@@ -316,9 +703,18 @@ impl Application {
             .ok_or(std::io::Error::other(
                 "No more scenes available. Did you forget to add them?",
             ))?;
-        next_scene.start();
+        self.exit_active_scene();
+        next_scene.on_enter();
+        set_tracking_enabled(
+            self.render_settings
+                .features
+                .is_enabled(Feature::MemoryTracking),
+        );
+        next_scene.apply_render_settings(&self.render_settings)?;
         self.active_scene = Some(next_scene);
         self.active_scene_started_at = Some(Instant::now());
+        self.update_scene_paused();
+        self.update_window_title();
         Ok(())
     }
 
@@ -335,6 +731,58 @@ impl Application {
     }
 }
 
+// Unfocused, minimized or covered windows & suspended apps don't need full speed
+fn is_in_background(window_focused: bool, window_occluded: bool, suspended: bool) -> bool {
+    !window_focused || window_occluded || suspended
+}
+
+// Event loop wake-up in the background. Returns the next wake-up & whether a frame is due now
+fn background_wake(now: Instant, next_wake: Instant) -> (Instant, bool) {
+    if now < next_wake {
+        return (next_wake, false);
+    }
+    (now + BACKGROUND_DT, true)
+}
+
+// Requests a core profile context of the version, falling back to lower versions & finally the
+// driver default
+fn create_context(
+    cfg: &glutin::config::Config,
+    window: &Window,
+    requested: GlVersion,
+) -> NotCurrentContext {
+    let raw_handle = window.window_handle().unwrap().as_raw();
+    // Debug contexts report more driver messages
+    let attributes = || ContextAttributesBuilder::new().with_debug(cfg!(debug_assertions));
+    for version in requested.with_fallbacks() {
+        let (major, minor) = version.major_minor();
+        let context_attribs = attributes()
+            .with_profile(GlProfile::Core)
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(major, minor))))
+            .build(Some(raw_handle));
+        match unsafe { cfg.display().create_context(cfg, &context_attribs) } {
+            Ok(context) => {
+                info!("Created OpenGL {major}.{minor} core context");
+                return context;
+            }
+            Err(err) => warn!("Unable to create OpenGL {major}.{minor} core context: {err}"),
+        }
+    }
+    warn!("Falling back to default OpenGL context");
+    unsafe {
+        cfg.display()
+            .create_context(cfg, &attributes().build(Some(raw_handle)))
+            .expect("Failed to create OpenGL context")
+    }
+}
+
+fn set_window_icon(window: &Window, path: &str) -> Result<(), Box<dyn Error>> {
+    let image = image::open(path)?.to_rgba8();
+    let (width, height) = image.dimensions();
+    window.set_window_icon(Some(Icon::from_rgba(image.into_raw(), width, height)?));
+    Ok(())
+}
+
 fn create_window(
     title: &str,
     width: u32,
@@ -363,13 +811,7 @@ fn create_window(
         .set_cursor_grab(winit::window::CursorGrabMode::Confined)
         .expect("Failed to grab cursor");
 
-    let context_attribs =
-        ContextAttributesBuilder::new().build(Some(window.window_handle().unwrap().as_raw()));
-    let context = unsafe {
-        cfg.display()
-            .create_context(&cfg, &context_attribs)
-            .expect("Failed to create OpenGL context")
-    };
+    let context = create_context(&cfg, &window, GlVersion::requested());
 
     let surface_attribs = SurfaceAttributesBuilder::<WindowSurface>::new()
         .with_srgb(Some(true))
@@ -407,18 +849,86 @@ fn imgui_init(window: &Window) -> (WinitPlatform, imgui::Context) {
     let mut imgui_context = imgui::Context::create();
     imgui_context.set_ini_filename(None);
 
+    // Locked, so scale factor changes go through Application::update_ui_scale
     let mut winit_platform = WinitPlatform::new(&mut imgui_context);
     winit_platform.attach_window(
         imgui_context.io_mut(),
         window,
-        imgui_winit_support::HiDpiMode::Rounded,
+        HiDpiMode::Locked(window.scale_factor()),
     );
+    load_fonts(&mut imgui_context, window.scale_factor());
+
+    (winit_platform, imgui_context)
+}
 
-    imgui_context
-        .fonts()
-        .add_font(&[imgui::FontSource::DefaultFontData { config: None }]);
+// Physical pixels per UI unit
+fn ui_scale_factor(window_scale_factor: f64, ui_scale: f32) -> f64 {
+    window_scale_factor * ui_scale as f64
+}
 
-    imgui_context.io_mut().font_global_scale = (1.0 / winit_platform.hidpi_factor()) as f32;
+// Rasterizes the font at its size in physical pixels, so text stays sharp on high-DPI displays
+fn load_fonts(imgui_context: &mut imgui::Context, scale_factor: f64) {
+    let fonts = imgui_context.fonts();
+    fonts.clear();
+    fonts.add_font(&[imgui::FontSource::DefaultFontData {
+        config: Some(imgui::FontConfig {
+            size_pixels: (UI_FONT_SIZE * scale_factor) as f32,
+            ..imgui::FontConfig::default()
+        }),
+    }]);
+    imgui_context.io_mut().font_global_scale = (1.0 / scale_factor) as f32;
+}
 
-    (winit_platform, imgui_context)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_throttling() {
+        assert!(!is_in_background(true, false, false));
+        assert!(is_in_background(false, false, false));
+        assert!(is_in_background(true, true, false));
+        assert!(is_in_background(true, false, true));
+
+        // Frame due on entering the background, then at most one per interval
+        let start = Instant::now();
+        let (next_wake, frame_due) = background_wake(start, start);
+        assert!(frame_due);
+        assert_eq!(next_wake, start + BACKGROUND_DT);
+        let early = start + BACKGROUND_DT / 2;
+        assert_eq!(background_wake(early, next_wake), (next_wake, false));
+        let late = start + BACKGROUND_DT * 3;
+        assert_eq!(
+            background_wake(late, next_wake),
+            (late + BACKGROUND_DT, true)
+        );
+    }
+
+    #[test]
+    fn test_fonts_follow_ui_scale() {
+        // UI scale setting of 150% on a 2x display
+        let scale_factor = ui_scale_factor(2.0, 1.5);
+        assert_eq!(scale_factor, 3.0);
+
+        let mut imgui_context = imgui::Context::create();
+        imgui_context.set_ini_filename(None);
+        load_fonts(&mut imgui_context, scale_factor);
+        let fonts = imgui_context.fonts();
+        fonts.build_rgba32_texture();
+        let font_ids = fonts.fonts();
+        assert_eq!(font_ids.len(), 1);
+        // Rasterized at physical size, drawn at the same size in UI units
+        let font_size = fonts.get_font(font_ids[0]).unwrap().font_size;
+        assert_eq!(font_size, 39.0);
+        let global_scale = imgui_context.io().font_global_scale;
+        assert!((font_size * global_scale - UI_FONT_SIZE as f32).abs() < 1e-4);
+
+        // Rebuilt on scale factor changes, e.g. moving to another monitor
+        load_fonts(&mut imgui_context, ui_scale_factor(1.0, 1.5));
+        let fonts = imgui_context.fonts();
+        fonts.build_rgba32_texture();
+        let font_ids = fonts.fonts();
+        assert_eq!(font_ids.len(), 1);
+        assert_eq!(fonts.get_font(font_ids[0]).unwrap().font_size, 19.5);
+    }
 }
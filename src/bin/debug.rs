@@ -2,15 +2,19 @@ use std::{env, sync::mpsc};
 
 use log::{error, info};
 use rs_voxie::{
-    application::Application,
+    application::{Application, FeatureFlags},
     network::{NetworkServer, ServerUpstreamPayload},
     pong::{BincodeCodec, ServerProtocol, server::scene::PongServerScene},
-    scenes::{BenchmarkScene, LightingScene, collision::CollisionScene},
+    scenes::{
+        BenchmarkScene, LightingScene, chunk_storage::ChunkStorageBenchmarkScene,
+        collision::CollisionScene,
+    },
 };
 
 #[derive(Debug)]
 enum SceneSelection {
     Benchmark,
+    ChunkStorage,
     Collision,
     Lighting,
     PongServer,
@@ -20,6 +24,7 @@ impl SceneSelection {
     fn from_str(s: &str) -> Option<Self> {
         match s {
             "benchmark" => Some(SceneSelection::Benchmark),
+            "chunk-storage" => Some(SceneSelection::ChunkStorage),
             "collision" => Some(SceneSelection::Collision),
             "lighting" => Some(SceneSelection::Lighting),
             "pong-server" => Some(SceneSelection::PongServer),
@@ -30,12 +35,17 @@ impl SceneSelection {
 
 struct CliArgs {
     scene: Option<SceneSelection>,
+    // 0 disables the limit
+    max_fps: Option<u32>,
+    features: FeatureFlags,
 }
 
 impl CliArgs {
     pub fn default() -> Self {
         Self {
             scene: Some(SceneSelection::Lighting),
+            max_fps: None,
+            features: FeatureFlags::default(),
         }
     }
 }
@@ -52,7 +62,7 @@ fn parse_args() -> CliArgs {
                     result.scene = Some(parsed_scene);
                 } else {
                     error!(
-                        "Invalid scene: '{}'. Valid options are: benchmark, game, collision, lighting",
+                        "Invalid scene: '{}'. Valid options are: benchmark, chunk-storage, game, collision, lighting",
                         args[i + 1]
                     );
                     std::process::exit(1);
@@ -62,6 +72,25 @@ fn parse_args() -> CliArgs {
                 error!("Expected value after --scene");
                 std::process::exit(1);
             }
+        } else if args[i] == "--max-fps" {
+            match args.get(i + 1).map(|value| value.parse::<u32>()) {
+                Some(Ok(max_fps)) => result.max_fps = Some(max_fps),
+                _ => {
+                    error!("Expected frame rate after --max-fps, e.g. --max-fps 60");
+                    std::process::exit(1);
+                }
+            }
+            i += 1; // skip next
+        } else if args[i] == "--feature" {
+            let Some(value) = args.get(i + 1) else {
+                error!("Expected value after --feature, e.g. --feature ssao=on");
+                std::process::exit(1);
+            };
+            if let Err(err) = result.features.parse_override(value) {
+                error!("{err}");
+                std::process::exit(1);
+            }
+            i += 1; // skip next
         }
         i += 1;
     }
@@ -76,6 +105,10 @@ fn main() {
     let scene = cli_args.scene.expect("No scene selected");
     // Setup application
     let mut app = Application::new("Voxie").expect("Could not setup application");
+    if cli_args.max_fps.is_some() {
+        app.set_max_fps(cli_args.max_fps);
+    }
+    app.set_features(cli_args.features);
     let gl_ctx = app.gl_context().clone();
 
     // Setup scene(s) to render
@@ -92,6 +125,11 @@ fn main() {
                 app.add_scene(Box::new(scene));
             }
         }
+        SceneSelection::ChunkStorage => {
+            let scene = ChunkStorageBenchmarkScene::new(&gl_ctx, 32)
+                .expect("Could not init chunk storage benchmark");
+            app.add_scene(Box::new(scene));
+        }
         SceneSelection::Collision => {
             let scene = CollisionScene::new(&gl_ctx).expect("Could not init collision scene");
             app.add_scene(Box::new(scene));
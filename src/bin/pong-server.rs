@@ -1,24 +1,46 @@
 use std::sync::mpsc;
 
-use rs_voxie::network::{HeadlessSimulation, NetworkServer, ServerUpstreamPayload};
+use log::error;
+use rs_voxie::network::{
+    DEFAULT_MAX_SPECTATORS, HeadlessSimulation, NetworkServer, PreSharedKey, ServerUpstreamPayload,
+};
 use rs_voxie::pong::server::scene::PongServerScene;
 use rs_voxie::pong::{BincodeCodec, ServerProtocol};
 
 fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    let max_spectators = std::env::var("MAX_SPECTATORS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_SPECTATORS);
+
     // Setup transport layer
     let mut server = NetworkServer::new();
+    server.set_max_spectators(max_spectators);
+    server.set_max_send_kbps(
+        std::env::var("MAX_SEND_KBPS")
+            .ok()
+            .and_then(|v| v.parse().ok()),
+    );
+    if let Ok(key) = std::env::var("PRE_SHARED_KEY") {
+        server.set_pre_shared_key(PreSharedKey::new(key));
+    }
     let (upstream_tx, upstream_rx) = mpsc::channel::<ServerUpstreamPayload>();
     server
         .serve("0.0.0.0:7777", upstream_tx)
         .expect("Could not serve");
+    let server_name = std::env::var("SERVER_NAME").unwrap_or("Pong server".to_string());
+    if let Err(err) = server.announce_on_lan(&server_name, "pong") {
+        error!("LAN discovery unavailable: {err}");
+    }
 
     // Setup protocol layer
     let protocol =
         ServerProtocol::<BincodeCodec>::new(server, upstream_rx).expect("Could not init protocol");
 
     let scene = PongServerScene::new(protocol).expect("Could not initialize pong scene");
+    // Default tick rate. Clients derive game time from server ticks & SIMULATION_DT
     let mut simulation = HeadlessSimulation::new(Box::new(scene));
     simulation.run();
 }
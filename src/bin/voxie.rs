@@ -1,16 +1,80 @@
-use log::info;
-use rs_voxie::{application::Application, voxie::scene::GameScene};
+use log::{error, info};
+use rs_voxie::{
+    application::{Application, FeatureFlags, TrackingAllocator, init_logger},
+    voxie::{scene::GameScene, weather::WeatherKind},
+};
+
+// Heap usage per subsystem is only counted while the memory-tracking feature is on
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
 
 fn main() {
     // Config setup
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    init_logger(env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("info"),
+    ));
+    if std::env::args().any(|arg| arg == "--verify-worldgen") {
+        verify_worldgen();
+        return;
+    }
+    let features = parse_features().unwrap_or_else(|err| {
+        error!("{err}");
+        std::process::exit(1);
+    });
+    let weather = parse_weather().unwrap_or_else(|err| {
+        error!("{err}");
+        std::process::exit(1);
+    });
     info!("Starting voxie game scene...");
 
     // Setup scene
     let mut app = Application::new("Voxie").expect("Could not setup application");
-    let scene = GameScene::new(&app.gl_context().clone(), app.input_state.clone())
+    app.set_features(features);
+    let mut scene = GameScene::new(&app.gl_context().clone(), app.input_state.clone())
         .expect("Unable to init voxie scene");
+    scene.set_weather_override(weather);
     app.add_scene(Box::new(scene));
 
     app.run().expect("Failed to run application");
 }
+
+// Applies all `--feature <name>=on|off` arguments
+fn parse_features() -> Result<FeatureFlags, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let mut features = FeatureFlags::default();
+    for (i, arg) in args.iter().enumerate() {
+        if arg == "--feature" {
+            let value = args
+                .get(i + 1)
+                .ok_or("Expected value after --feature, e.g. --feature ssao=on")?;
+            features.parse_override(value)?;
+        }
+    }
+    Ok(features)
+}
+
+// Weather forced by `--weather clear|rain|snow`. None follows the schedule
+fn parse_weather() -> Result<Option<WeatherKind>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(i) = args.iter().position(|arg| arg == "--weather") else {
+        return Ok(None);
+    };
+    let name = args
+        .get(i + 1)
+        .ok_or("Expected value after --weather, e.g. --weather rain")?;
+    WeatherKind::from_name(name).map(Some).ok_or(format!(
+        "Unknown weather '{name}'. Valid options are: clear, rain, snow"
+    ))
+}
+
+// Exits with a non-zero code if world generation is not deterministic
+fn verify_worldgen() {
+    info!("Verifying world generation...");
+    match GameScene::verify_world_generation() {
+        Ok(chunks) => info!("World generation is deterministic: {chunks} chunks verified"),
+        Err(err) => {
+            error!("World generation is not deterministic: {err}");
+            std::process::exit(1);
+        }
+    }
+}
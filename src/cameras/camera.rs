@@ -1,10 +1,83 @@
-use glam::{Mat4, Quat, Vec3};
+use std::f32::consts::{PI, TAU};
+
+use glam::{EulerRot, Mat4, Quat, Vec2, Vec3};
 
 use crate::octree::IAabb;
 
+pub const DEFAULT_FOV_Y_DEGREES: f32 = 60.0;
+pub const DEFAULT_NEAR: f32 = 0.1;
+pub const DEFAULT_FAR: f32 = 1000.0;
+/// Pitch is clamped to ~89.4 degrees. Looking straight up or down makes yaw ambiguous
+pub const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Roll-free rotation: Yaw around world Y first, then pitch around the local X axis
+pub fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0)
+}
+
+/// Yaw & pitch looking along the direction. Default orientation looks down -Z
+pub fn yaw_pitch_from_direction(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize();
+    let yaw = (-direction.x).atan2(-direction.z);
+    let pitch = direction.y.clamp(-1.0, 1.0).asin();
+    (yaw, pitch.clamp(-MAX_PITCH, MAX_PITCH))
+}
+
+/// Wraps the angle into [-PI, PI)
+pub fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// Pose followed by camera controllers. Orientation is kept as yaw & pitch end-to-end, so no
+/// roll can build up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraTarget {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl CameraTarget {
+    pub fn rotation(&self) -> Quat {
+        yaw_pitch_rotation(self.yaw, self.pitch)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::NEG_Z
+    }
+}
+
+/// Reverse-Z perspective projection with [0, 1] clip depth.
+/// Maps near plane to depth 1 & far plane to depth 0, which distributes float precision
+/// far more evenly across large view distances than the default GL projection
+pub fn perspective_reverse_z(
+    fov_y_radians: f32,
+    aspect_ratio: f32,
+    z_near: f32,
+    z_far: f32,
+) -> Mat4 {
+    // Swapping near & far of a [0, 1] depth projection yields reversed depth
+    Mat4::perspective_rh(fov_y_radians, aspect_ratio, z_far, z_near)
+}
+
+/// Reverse-Z orthographic projection with [0, 1] clip depth
+pub fn orthographic_reverse_z(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    z_near: f32,
+    z_far: f32,
+) -> Mat4 {
+    Mat4::orthographic_rh(left, right, bottom, top, z_far, z_near)
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub position: Vec3,
-    rotation: Quat,
+    // Rotation is derived from yaw & pitch, so the camera never rolls
+    yaw: f32,
+    pitch: f32,
     projection: Mat4,
 }
 
@@ -56,25 +129,44 @@ impl Camera {
         let h = 1080.0;
         Self {
             position: Vec3::ZERO,
-            rotation: Quat::IDENTITY,
-            projection: Mat4::perspective_rh_gl(60f32.to_radians(), w / h, 0.1, 1000.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            projection: perspective_reverse_z(
+                DEFAULT_FOV_Y_DEGREES.to_radians(),
+                w / h,
+                DEFAULT_NEAR,
+                DEFAULT_FAR,
+            ),
         }
     }
 
+    /// Looks along the forward axis of the rotation. Any roll is dropped
     pub fn set_rotation(&mut self, rot: Quat) {
-        self.rotation = rot;
+        let (yaw, pitch) = yaw_pitch_from_direction(rot * Vec3::NEG_Z);
+        self.set_yaw_pitch(yaw, pitch);
     }
 
     pub fn get_rotation(&self) -> Quat {
-        self.rotation
+        yaw_pitch_rotation(self.yaw, self.pitch)
+    }
+
+    /// Yaw is wrapped, pitch clamped to [MAX_PITCH]
+    pub fn set_yaw_pitch(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = wrap_angle(yaw);
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
     }
 
     pub fn look_at(&mut self, target_position: Vec3) {
-        let view_matrix = Mat4::look_at_rh(self.position, target_position, Vec3::Y);
-        let transform = view_matrix.inverse();
-        self.position = transform.w_axis.truncate();
-        let rotation = Quat::from_mat4(&transform);
-        self.rotation = rotation;
+        let (yaw, pitch) = yaw_pitch_from_direction(target_position - self.position);
+        self.set_yaw_pitch(yaw, pitch);
     }
 
     pub fn get_view_projection_matrix(&self) -> Mat4 {
@@ -83,7 +175,7 @@ impl Camera {
 
     // NOTE: Equal to inverse of camera transform
     pub fn get_view_matrix(&self) -> Mat4 {
-        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+        Mat4::from_rotation_translation(self.get_rotation(), self.position).inverse()
     }
 
     pub fn get_projection_matrix(&self) -> Mat4 {
@@ -94,7 +186,39 @@ impl Camera {
         self.projection = projection;
     }
 
+    /// Ray through a point of the view given in normalized device coordinates, e.g. the mouse
+    /// cursor. Starts on the near plane. Returns origin & normalized direction
+    pub fn ndc_ray(&self, ndc: Vec2) -> (Vec3, Vec3) {
+        let inverse = self.get_view_projection_matrix().inverse();
+        // Reverse-Z: Near plane at depth 1, far plane at depth 0
+        let near = inverse.project_point3(ndc.extend(1.0));
+        let far = inverse.project_point3(ndc.extend(0.0));
+        (near, (far - near).normalize())
+    }
+
+    /// Corners of the view volume, near plane first, each counter-clockwise from the bottom left.
+    /// The far plane is pulled in to max_distance along the view direction, so the volume can
+    /// be drawn with the regular far plane at 1000 units
+    pub fn frustum_corners(&self, max_distance: f32) -> [Vec3; 8] {
+        let inverse = self.get_view_projection_matrix().inverse();
+        let forward = self.get_rotation() * Vec3::NEG_Z;
+        let ndc = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        let mut corners = [Vec3::ZERO; 8];
+        for (index, (x, y)) in ndc.into_iter().enumerate() {
+            // Reverse-Z: Near plane at depth 1, far plane at depth 0
+            let near = inverse.project_point3(Vec3::new(x, y, 1.0));
+            let far = inverse.project_point3(Vec3::new(x, y, 0.0));
+            let near_depth = (near - self.position).dot(forward);
+            let far_depth = (far - self.position).dot(forward);
+            let t = ((max_distance - near_depth) / (far_depth - near_depth)).clamp(0.0, 1.0);
+            corners[index] = near;
+            corners[index + 4] = near.lerp(far, t);
+        }
+        corners
+    }
+
     // Extract planes from the combined view-projection matrix
+    // NOTE: Assumes reverse-Z projection with [0, 1] clip depth (near: z = w, far: z = 0)
     pub fn get_frustum(&self) -> Frustum {
         // Helper to extract a plane from combinations of rows
         fn make_plane(a: [f32; 4], b: [f32; 4]) -> Plane {
@@ -121,13 +245,220 @@ impl Camera {
                 make_plane(r3, [-r0[0], -r0[1], -r0[2], -r0[3]]), // right
                 make_plane(r3, r1),                               // bottom
                 make_plane(r3, [-r1[0], -r1[1], -r1[2], -r1[3]]), // top
-                make_plane(r3, r2),                               // near
-                make_plane(r3, [-r2[0], -r2[1], -r2[2], -r2[3]]), // far
+                make_plane(r3, [-r2[0], -r2[1], -r2[2], -r2[3]]), // near
+                make_plane([0.0; 4], r2),                         // far
             ],
         }
     }
 }
 
 pub trait CameraController {
-    fn tick(&mut self, dt: f32, camera: &mut Camera, target_transform: &Mat4);
+    fn tick(&mut self, dt: f32, camera: &mut Camera, target: &CameraTarget);
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{IVec3, Vec4};
+
+    const FOVS_DEGREES: [f32; 4] = [40.0, 60.0, 90.0, 110.0];
+    const ASPECT_RATIOS: [f32; 4] = [0.5, 1.0, 16.0 / 9.0, 2.5];
+
+    use super::*;
+
+    fn ndc_depth(projection: &Mat4, view_z: f32) -> f32 {
+        let clip = *projection * Vec4::new(0.0, 0.0, view_z, 1.0);
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn test_perspective_reverse_z_maps_near_to_one_far_to_zero() {
+        let projection = perspective_reverse_z(1.0, 16.0 / 9.0, 0.1, 1000.0);
+        assert!((ndc_depth(&projection, -0.1) - 1.0).abs() < 1e-5);
+        assert!(ndc_depth(&projection, -1000.0).abs() < 1e-5);
+        // Closer fragments must have greater depth
+        assert!(ndc_depth(&projection, -10.0) > ndc_depth(&projection, -100.0));
+    }
+
+    #[test]
+    fn test_orthographic_reverse_z_maps_near_to_one_far_to_zero() {
+        let projection = orthographic_reverse_z(-1.0, 1.0, -1.0, 1.0, -5.0, 5.0);
+        assert!((ndc_depth(&projection, 5.0) - 1.0).abs() < 1e-5);
+        assert!(ndc_depth(&projection, -5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_rotation_is_roll_free() {
+        let mut cam = Camera::new();
+        let rolled =
+            Quat::from_rotation_y(0.7) * Quat::from_rotation_x(-0.3) * Quat::from_rotation_z(0.4);
+        cam.set_rotation(rolled);
+        assert!((cam.yaw() - 0.7).abs() < 1e-5);
+        assert!((cam.pitch() + 0.3).abs() < 1e-5);
+        // Forward is kept, right stays horizontal
+        let forward = cam.get_rotation() * Vec3::NEG_Z;
+        assert!(forward.distance(rolled * Vec3::NEG_Z) < 1e-5);
+        assert!((cam.get_rotation() * Vec3::X).y.abs() < 1e-6);
+
+        cam.set_yaw_pitch(3.0 * PI, 2.0);
+        assert!((cam.yaw().abs() - PI).abs() < 1e-5);
+        assert_eq!(cam.pitch(), MAX_PITCH);
+
+        cam.position = Vec3::ZERO;
+        cam.look_at(Vec3::new(1.0, 0.0, 0.0));
+        assert!((cam.get_rotation() * Vec3::NEG_Z).distance(Vec3::X) < 1e-5);
+    }
+
+    #[test]
+    fn test_frustum_near_far_planes() {
+        // Default camera looks down -Z
+        let cam = Camera::new();
+        let frustum = cam.get_frustum();
+        let in_front = IAabb::new(&IVec3::new(0, 0, -20), 2);
+        assert!(frustum.contains_aabb(&in_front));
+        let behind = IAabb::new(&IVec3::new(0, 0, 10), 2);
+        assert!(!frustum.contains_aabb(&behind));
+        let beyond_far = IAabb::new(&IVec3::new(0, 0, -1100), 2);
+        assert!(!frustum.contains_aabb(&beyond_far));
+    }
+
+    fn perspective_camera(fov_degrees: f32, aspect_ratio: f32) -> Camera {
+        let mut cam = Camera::new();
+        cam.set_projection(perspective_reverse_z(
+            fov_degrees.to_radians(),
+            aspect_ratio,
+            DEFAULT_NEAR,
+            DEFAULT_FAR,
+        ));
+        cam
+    }
+
+    // 2x2x2 box centered on the axis at x or y, 50 units in front of the default camera
+    fn box_at(axis: usize, min: i32) -> IAabb {
+        let mut lower = IVec3::new(-1, -1, -51);
+        lower[axis] = min;
+        IAabb::new(&lower, 2)
+    }
+
+    #[test]
+    fn test_frustum_side_planes_across_fovs_and_aspects() {
+        for fov in FOVS_DEGREES {
+            for aspect in ASPECT_RATIOS {
+                let frustum = perspective_camera(fov, aspect).get_frustum();
+                let tan = (fov.to_radians() / 2.0).tan();
+                // Half extents of the view at the front & back face of the boxes
+                for (axis, scale) in [(0, tan * aspect), (1, tan)] {
+                    let inner_edge = scale * 49.0;
+                    let outer_edge = scale * 51.0;
+                    for sign in [1, -1] {
+                        // Straddles the edge of the screen
+                        let straddling = match sign {
+                            1 => box_at(axis, inner_edge.floor() as i32 - 1),
+                            _ => box_at(axis, -inner_edge.floor() as i32 - 1),
+                        };
+                        assert!(
+                            frustum.contains_aabb(&straddling),
+                            "fov {fov} aspect {aspect} axis {axis} sign {sign}"
+                        );
+                        let outside = match sign {
+                            1 => box_at(axis, outer_edge.ceil() as i32 + 1),
+                            _ => box_at(axis, -outer_edge.ceil() as i32 - 3),
+                        };
+                        assert!(
+                            !frustum.contains_aabb(&outside),
+                            "fov {fov} aspect {aspect} axis {axis} sign {sign}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Tiny LCG, enough to scatter cameras & boxes deterministically
+    fn next_unit(state: &mut u64) -> f32 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*state >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    #[test]
+    fn test_frustum_matches_clip_space() {
+        let mut state = 7;
+        let mut rng = move |min: f32, max: f32| min + (max - min) * next_unit(&mut state);
+        let (mut visible, mut culled) = (0, 0);
+        for fov in FOVS_DEGREES {
+            for aspect in ASPECT_RATIOS {
+                let mut cam = perspective_camera(fov, aspect);
+                for _ in 0..200 {
+                    cam.position = Vec3::new(rng(-50.0, 50.0), rng(-50.0, 50.0), rng(-50.0, 50.0));
+                    cam.set_yaw_pitch(rng(-PI, PI), rng(-MAX_PITCH, MAX_PITCH));
+                    let min = (cam.position + cam.get_rotation() * Vec3::NEG_Z * rng(-10.0, 80.0))
+                        + Vec3::new(rng(-40.0, 40.0), rng(-40.0, 40.0), rng(-40.0, 40.0));
+                    let aabb = IAabb::new(&min.floor().as_ivec3(), rng(1.0, 16.0) as usize);
+                    let vp = cam.get_view_projection_matrix();
+                    let corners: Vec<Vec4> = (0..8)
+                        .map(|i| {
+                            let corner = IVec3::new(
+                                [aabb.min.x, aabb.max.x][i & 1],
+                                [aabb.min.y, aabb.max.y][(i >> 1) & 1],
+                                [aabb.min.z, aabb.max.z][(i >> 2) & 1],
+                            );
+                            vp * corner.as_vec3().extend(1.0)
+                        })
+                        .collect();
+                    // Clip space half-spaces of reverse-Z: -w <= x, y <= w & 0 <= z <= w
+                    let inside =
+                        |c: &Vec4| [c.x + c.w, c.w - c.x, c.y + c.w, c.w - c.y, c.w - c.z, c.z];
+                    let contained = cam.get_frustum().contains_aabb(&aabb);
+                    if corners.iter().any(|c| inside(c).iter().all(|d| *d > 1e-3)) {
+                        assert!(contained, "Visible box culled: {aabb:?} fov {fov}");
+                        visible += 1;
+                    }
+                    let fully_outside =
+                        (0..6).any(|plane| corners.iter().all(|c| inside(c)[plane] < -1e-3));
+                    if fully_outside {
+                        assert!(!contained, "Hidden box kept: {aabb:?} fov {fov}");
+                        culled += 1;
+                    }
+                }
+            }
+        }
+        // Both branches were exercised
+        assert!(visible > 100 && culled > 100, "{visible} {culled}");
+    }
+
+    #[test]
+    fn test_ndc_ray_hits_projected_point() {
+        let mut cam = perspective_camera(70.0, 16.0 / 9.0);
+        cam.position = Vec3::new(-4.0, 6.0, 2.0);
+        cam.look_at(Vec3::new(5.0, 0.0, -8.0));
+        let target = Vec3::new(7.0, 1.0, -9.0);
+        let ndc = cam.get_view_projection_matrix().project_point3(target);
+        let (origin, direction) = cam.ndc_ray(ndc.truncate());
+        let to_target = target - origin;
+        assert!(direction.is_normalized());
+        assert!(
+            to_target.normalize().distance(direction) < 1e-4,
+            "{direction}"
+        );
+        assert!((origin - cam.position).length() < DEFAULT_NEAR * 2.0);
+    }
+
+    #[test]
+    fn test_frustum_corners_project_onto_screen_corners() {
+        let mut cam = perspective_camera(90.0, 2.0);
+        cam.position = Vec3::new(3.0, 4.0, 5.0);
+        cam.set_yaw_pitch(0.5, -0.2);
+        let corners = cam.frustum_corners(20.0);
+        let vp = cam.get_view_projection_matrix();
+        let forward = cam.get_rotation() * Vec3::NEG_Z;
+        for (index, corner) in corners.iter().enumerate() {
+            let ndc = vp.project_point3(*corner);
+            assert!((ndc.x.abs() - 1.0).abs() < 1e-3, "{index} {ndc}");
+            assert!((ndc.y.abs() - 1.0).abs() < 1e-3, "{index} {ndc}");
+            let depth = (*corner - cam.position).dot(forward);
+            let expected = if index < 4 { DEFAULT_NEAR } else { 20.0 };
+            assert!((depth - expected).abs() < 1e-2, "{index} {depth}");
+        }
+    }
 }
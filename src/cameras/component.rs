@@ -2,6 +2,7 @@ use glam::Mat4;
 use hecs::{Entity, World};
 
 use crate::{
+    cameras::camera::{DEFAULT_FAR, DEFAULT_FOV_Y_DEGREES, DEFAULT_NEAR, perspective_reverse_z},
     config::{RESOLUTION_HEIGHT, RESOLUTION_WIDTH},
     systems::physics::Transform,
 };
@@ -14,11 +15,11 @@ pub fn spawn_camera(world: &mut World, transform: Mat4) -> Entity {
     world.spawn((
         Transform(transform),
         CameraComponent {
-            projection: Mat4::perspective_rh_gl(
-                60f32.to_radians(),
+            projection: perspective_reverse_z(
+                DEFAULT_FOV_Y_DEGREES.to_radians(),
                 RESOLUTION_WIDTH as f32 / RESOLUTION_HEIGHT as f32,
-                0.1,
-                1000.0,
+                DEFAULT_NEAR,
+                DEFAULT_FAR,
             ),
         },
     ))
@@ -1,6 +1,4 @@
-use glam::{Mat4, Quat};
-
-use super::camera::{Camera, CameraController};
+use super::camera::{Camera, CameraController, CameraTarget};
 
 pub struct FirstPersonCam {}
 
@@ -11,10 +9,8 @@ impl FirstPersonCam {
 }
 
 impl CameraController for FirstPersonCam {
-    fn tick(&mut self, _dt: f32, camera: &mut Camera, target_transform: &Mat4) {
-        let translation = target_transform.w_axis.truncate();
-        let rotation = Quat::from_mat4(target_transform);
-        camera.position = translation;
-        camera.set_rotation(rotation);
+    fn tick(&mut self, _dt: f32, camera: &mut Camera, target: &CameraTarget) {
+        camera.position = target.position;
+        camera.set_yaw_pitch(target.yaw, target.pitch);
     }
 }
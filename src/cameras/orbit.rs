@@ -1,5 +1,7 @@
 use glam::{Mat4, Quat, Vec3};
 
+use super::camera::Camera;
+
 pub struct BlenderOrbitCamera {
     /// World-space pivot (orbit center)
     pub pivot: Vec3,
@@ -40,6 +42,12 @@ impl BlenderOrbitCamera {
         self.pitch = self.pitch.clamp(-self.max_pitch, self.max_pitch);
     }
 
+    /// Yaw around world Y & pitch, clamped to max_pitch
+    pub fn set_yaw_pitch(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.clamp(-self.max_pitch, self.max_pitch);
+    }
+
     /// Scroll wheel (dolly)
     pub fn dolly(&mut self, delta: f32) {
         self.distance = (self.distance + delta).clamp(0.1, 1000.0);
@@ -71,6 +79,12 @@ impl BlenderOrbitCamera {
         Mat4::from_rotation_translation(orientation, position)
     }
 
+    /// Moves & rotates the camera onto the orbit, looking at the pivot
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.camera_transform().w_axis.truncate();
+        camera.set_yaw_pitch(self.yaw, self.pitch);
+    }
+
     /// View matrix (world → camera)
     pub fn view_matrix(&self) -> Mat4 {
         self.camera_transform().inverse()
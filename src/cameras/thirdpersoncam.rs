@@ -1,8 +1,8 @@
-use glam::{Mat3, Mat4, Quat, Vec3, Vec4Swizzles};
+use glam::Vec3;
 
 use crate::util::smooth_damp;
 
-use super::camera::{Camera, CameraController};
+use super::camera::{Camera, CameraController, CameraTarget, wrap_angle};
 
 pub struct ThirdPersonCam {
     distance: f32,
@@ -21,11 +21,9 @@ impl ThirdPersonCam {
 }
 
 impl CameraController for ThirdPersonCam {
-    fn tick(&mut self, dt: f32, camera: &mut Camera, target_transform: &Mat4) {
+    fn tick(&mut self, dt: f32, camera: &mut Camera, target: &CameraTarget) {
         // Smoothen position towards aligned with target forward + distance
-        let target_position = target_transform.w_axis.xyz();
-        let forward = (-target_transform.z_axis.xyz()).normalize();
-        let target_camera_pos = target_position - self.distance * forward;
+        let target_camera_pos = target.position - self.distance * target.forward();
         let mut velocity = Vec3::ZERO;
         camera.position = smooth_damp(
             camera.position,
@@ -35,21 +33,53 @@ impl CameraController for ThirdPersonCam {
             dt,
         );
 
-        // Smoothen rotation towards aligned rotation with target
-        let up = target_transform.y_axis.truncate().normalize();
-        let right = forward.cross(up).normalize();
-        let rotation_matrix = Mat3::from_cols(right, up, -forward);
-        let target_quat = Quat::from_mat3(&rotation_matrix);
-        camera.set_rotation(quat_exp_smooth(
-            camera.get_rotation(),
-            target_quat,
-            self.rotation_smooth_time,
-            dt,
-        ));
+        // Smoothen yaw & pitch separately. Never produces roll, unlike blending rotations
+        let t = 1.0 - (-dt / self.rotation_smooth_time).exp();
+        camera.set_yaw_pitch(
+            angle_lerp(camera.yaw(), target.yaw, t),
+            camera.pitch() + (target.pitch - camera.pitch()) * t,
+        );
     }
 }
 
-fn quat_exp_smooth(current: Quat, target: Quat, smooth_time: f32, dt: f32) -> Quat {
-    let t = 1.0 - (-dt / smooth_time).exp();
-    Quat::slerp(current, target, t)
+// Interpolates along the shorter way around the circle
+fn angle_lerp(current: f32, target: f32, t: f32) -> f32 {
+    current + wrap_angle(target - current) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follow_stays_roll_free() {
+        let mut controller = ThirdPersonCam::new();
+        let mut camera = Camera::new();
+        let mut target = CameraTarget {
+            position: Vec3::new(10.0, 5.0, 10.0),
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        // Fast mouse movement across the yaw wrap & up to the pitch limit
+        for step in 0..500 {
+            target.yaw = wrap_angle(target.yaw + 0.37);
+            target.pitch = ((step as f32) * 0.21).sin() * 1.6;
+            controller.tick(1.0 / 60.0, &mut camera, &target);
+            let right = camera.get_rotation() * Vec3::X;
+            assert!(right.y.abs() < 1e-5, "Camera rolled: {right}");
+        }
+        // Settles on the target
+        target.pitch = 0.5;
+        for _ in 0..200 {
+            controller.tick(1.0 / 60.0, &mut camera, &target);
+        }
+        assert!(wrap_angle(camera.yaw() - target.yaw).abs() < 1e-3);
+        assert!((camera.pitch() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_angle_lerp_takes_short_way() {
+        let halfway = angle_lerp(3.0, -3.0, 0.5);
+        assert!((wrap_angle(halfway).abs() - std::f32::consts::PI).abs() < 1e-4);
+    }
 }
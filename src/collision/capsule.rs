@@ -126,7 +126,7 @@ pub fn get_capsule_aabb_collision_info(capsule: &Capsule, aabb: &AABB) -> Option
     let capsule_center = (capsule.endpoint_a + capsule.endpoint_b) * 0.5;
 
     // First, get closest point on AABB to capsule center
-    let closest_on_aabb = capsule_center.clamp(aabb.min, aabb.max);
+    let closest_on_aabb = aabb.closest_point(&capsule_center);
 
     // Then find closest point on capsule to that AABB point
     let closest_on_capsule =
@@ -11,6 +11,7 @@ pub use model::ColliderBody;
 pub use model::CollisionEvent;
 pub use model::CollisionInfo;
 pub use query::get_collision_info;
+pub(crate) use ray::Ray;
 pub(super) use sphere::get_sphere_aabb_collision_info;
 pub(super) use sphere::get_sphere_sphere_collision_info;
 pub use system::system_collisions;
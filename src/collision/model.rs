@@ -1,5 +1,6 @@
 use glam::Vec3;
 use hecs::Entity;
+use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone, Debug)]
 pub struct CollisionInfo {
@@ -16,7 +17,7 @@ pub struct CollisionEvent {
     pub b: Option<Entity>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ColliderBody {
     // assumes rect center equal to transform. Does not support offset
     AabbCollider { scale: Vec3 },
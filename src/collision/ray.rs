@@ -4,13 +4,13 @@ use crate::octree::AABB;
 
 use super::CollisionInfo;
 
-pub(super) struct Ray {
+pub(crate) struct Ray {
     origin: Vec3,
     direction: Vec3,
 }
 
 impl Ray {
-    pub(super) fn new(origin: Vec3, direction: Vec3) -> Ray {
+    pub(crate) fn new(origin: Vec3, direction: Vec3) -> Ray {
         Self { origin, direction }
     }
 
@@ -36,7 +36,7 @@ pub fn get_sphere_aabb_collision_info(
     b: &AABB,
 ) -> Option<CollisionInfo> {
     // Closest point on AABB to the sphere center
-    let closest = center.clamp(b.min, b.max);
+    let closest = b.closest_point(center);
     let offset = *center - closest;
     let dist_sq = offset.length_squared();
     let radius_sq = radius * radius;
@@ -91,6 +91,19 @@ pub fn get_sphere_aabb_collision_info(
     })
 }
 
+/// Distance along the ray at which the sphere starts touching the AABB (approximated by
+/// inflating the AABB by the radius, same as sphere_cast). Lower bound for any hit within it
+pub fn sphere_cast_entry_distance(
+    origin: Vec3,
+    radius: f32,
+    direction: Vec3,
+    aabb: &AABB,
+) -> Option<f32> {
+    let inflated = aabb.inflate(radius);
+    let (t, _) = Ray::new(origin, direction).intersect_aabb(&inflated)?;
+    Some(t)
+}
+
 pub fn sphere_cast<I>(
     origin: Vec3,
     radius: f32,
@@ -110,7 +123,7 @@ where
     let ray = Ray::new(origin, direction);
     for aabb in boxes {
         // Inflate AABB by sphere radius
-        let inflated = AABB::new(aabb.min - Vec3::ONE * radius, aabb.max + Vec3::ONE * radius);
+        let inflated = aabb.inflate(radius);
         if let Some(collision_info) = ray.intersects_aabb_within_t(&inflated, max_distance) {
             if closest_hit.is_none()
                 || collision_info.penetration_depth < closest_hit.unwrap().penetration_depth
@@ -123,6 +136,29 @@ where
     closest_hit
 }
 
+/// Distance along the ray at which it enters the sphere. 0 if the origin lies inside the sphere,
+/// None if the ray misses it. Direction needs to be normalized
+#[cfg(feature = "gui")]
+pub fn ray_sphere_intersection(
+    origin: Vec3,
+    direction: Vec3,
+    center: Vec3,
+    radius: f32,
+) -> Option<f32> {
+    let to_origin = origin - center;
+    let b = to_origin.dot(direction);
+    let c = to_origin.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = b * b - c;
+    // Sphere is behind the origin or missed entirely
+    if b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    Some(-b - discriminant.sqrt())
+}
+
 #[cfg(test)]
 mod tests {
     use glam::Vec3;
@@ -226,6 +262,25 @@ mod tests {
             hit.contact_point.z
         );
     }
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_ray_sphere_intersection() {
+        use super::ray_sphere_intersection;
+
+        let center = Vec3::new(0.0, 0.0, -10.0);
+        let t = ray_sphere_intersection(Vec3::ZERO, Vec3::NEG_Z, center, 2.0).unwrap();
+        assert!((t - 8.0).abs() < 1e-5);
+        let grazing = Vec3::new(1.9, 0.0, 0.0);
+        assert!(ray_sphere_intersection(grazing, Vec3::NEG_Z, center, 2.0).is_some());
+        let beside = Vec3::new(2.1, 0.0, 0.0);
+        assert!(ray_sphere_intersection(beside, Vec3::NEG_Z, center, 2.0).is_none());
+        assert!(ray_sphere_intersection(Vec3::ZERO, Vec3::Z, center, 2.0).is_none());
+        assert_eq!(
+            ray_sphere_intersection(center, Vec3::Z, center, 2.0),
+            Some(0.0)
+        );
+    }
+
     #[test]
     fn test_sphere_cast_center_missese_shell_hits() {
         let bb = AABB::new_center(&Vec3::new(2.0, 0.0, 5.0), 1.0);
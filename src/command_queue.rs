@@ -22,5 +22,16 @@ impl CommandQueue {
 
 #[derive(Debug)]
 pub enum Command {
-    SpawnProjectile { transform: Mat4, velocity: Vec3 },
+    SpawnProjectile {
+        transform: Mat4,
+        velocity: Vec3,
+    },
+    ThrowGrenade {
+        position: Vec3,
+        velocity: Vec3,
+    },
+    /// Moves the player, generating the destination first
+    Teleport {
+        position: Vec3,
+    },
 }
@@ -5,3 +5,5 @@ pub const RESOLUTION_HEIGHT: u32 = 1080;
 pub const SIMULATION_DT: Duration = Duration::from_nanos(1_000_000_000 / 60); // 60Hz
 pub const BROADCAST_DT: Duration = Duration::from_nanos(1_000_000_000 / 20); // 20Hz
 pub const USE_VSYNC: bool = true;
+// Frame rate cap applied by default when vsync is off
+pub const MAX_FPS_WITHOUT_VSYNC: u32 = 240;
@@ -11,7 +11,7 @@ use std::{
     time::Instant,
 };
 
-use glam::{Quat, Vec3};
+use glam::Vec3;
 use glow::{HasContext, NativeBuffer};
 use log::{debug, error, trace};
 
@@ -19,7 +19,11 @@ use crate::{
     cameras::camera::Camera,
     meshes::objmesh::ObjMesh,
     octree::IAabb,
-    renderer::{shader::Shader, texture::Texture},
+    renderer::{
+        frame_uniforms::{FrameUniforms, SceneLighting},
+        shader::Shader,
+        texture::Texture,
+    },
     scenes::Renderer,
     voxels::{CHUNK_SIZE, VoxelChunk, VoxelKind, VoxelWorld},
 };
@@ -127,6 +131,8 @@ impl Drop for CubeRenderBatch {
 pub struct CubeRenderer {
     gl: Rc<glow::Context>,
     shader: Shader,
+    frame_uniforms: FrameUniforms,
+    lighting: SceneLighting,
     // vertex vbos will be shared across batches
     vertex_position_vbo: NativeBuffer,
     vertex_normal_vbo: NativeBuffer,
@@ -189,7 +195,9 @@ impl CubeRenderer {
                 batch_thread_receiver: None,
                 batches: vec![],
                 color,
+                frame_uniforms: FrameUniforms::new(gl)?,
                 gl: Rc::clone(gl),
+                lighting: SceneLighting::default(),
                 is_dirty: true,
                 shader,
                 vertex_count,
@@ -253,10 +261,11 @@ impl CubeRenderer {
         Ok(())
     }
 
-    pub fn tick(&mut self, _dt: f32, camera_fov: &IAabb) {
+    pub fn tick(&mut self, _dt: f32, camera_fov: &IAabb) -> Result<(), Box<dyn Error>> {
         if self.is_dirty {
-            self.update(camera_fov).expect("Could not update");
+            self.update(camera_fov)?;
         }
+        Ok(())
     }
 }
 
@@ -305,21 +314,12 @@ fn generate_position_vecs(chunks: &[Arc<VoxelChunk>]) -> Vec<Vec<Vec3>> {
 
 impl Renderer for CubeRenderer {
     fn render(&mut self, cam: &Camera) {
-        let view = cam.get_view_matrix();
-        let projection = cam.get_projection_matrix();
-
-        // Calculate light attributes
-        let world_space_light_dir = Quat::from_rotation_x(20.0) * Vec3::Y;
-        // NOTE: Could use self.color here for debugging
-        let ambient_light_col = Vec3::ONE * 0.5;
-
+        // NOTE: Could use self.color as ambient light for debugging
+        // Single view per frame
+        self.frame_uniforms.begin_frame();
+        self.frame_uniforms
+            .update(&self.gl, cam, 0.0, &self.lighting);
         self.shader.use_program();
-        self.shader.set_uniform_mat4("uView", &view);
-        self.shader.set_uniform_mat4("uProjection", &projection);
-        self.shader
-            .set_uniform_vec3("uLightDir", &world_space_light_dir);
-        self.shader
-            .set_uniform_vec3("uAmbientLightColor", &ambient_light_col);
 
         for batch in &mut self.batches {
             batch.render(self.vertex_count);
@@ -0,0 +1,222 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::{Mat4, Vec3};
+
+use super::objmesh::VertexBuffers;
+
+/// Subdivisions of the detail levels built for sphere meshes, most detailed first
+pub const SPHERE_LOD_SUBDIVISIONS: [usize; 4] = [3, 2, 1, 0];
+/// Share of the viewport height below which the next detail level is used. One entry per level
+/// after the first
+pub const SPHERE_LOD_SCREEN_SIZES: [f32; 3] = [0.1, 0.03, 0.01];
+
+/// How sphere meshes are tessellated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SphereGeometry {
+    /// Evenly sized triangles, the closest to a sphere for a given triangle count
+    Icosphere,
+    /// Rings of latitude, e.g. for gizmos where the poles show the orientation
+    Uv,
+}
+
+impl SphereGeometry {
+    /// Sphere of the given detail. Each subdivision quadruples the triangle count
+    pub fn vertices(self, radius: f32, subdivisions: usize) -> VertexBuffers {
+        match self {
+            SphereGeometry::Icosphere => icosphere_vertices(radius, subdivisions),
+            SphereGeometry::Uv => {
+                let segments = 6 << subdivisions;
+                uv_sphere_vertices(radius, segments, segments / 2)
+            }
+        }
+    }
+}
+
+/// Sphere centered at the origin built by subdividing an icosahedron, as a non-indexed triangle
+/// list. Triangles are evenly sized, 20 * 4^subdivisions of them
+pub fn icosphere_vertices(radius: f32, subdivisions: usize) -> VertexBuffers {
+    // Corners of the icosahedron lie on 3 orthogonal golden rectangles
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let corners = [
+        Vec3::new(-1.0, t, 0.0),
+        Vec3::new(1.0, t, 0.0),
+        Vec3::new(-1.0, -t, 0.0),
+        Vec3::new(1.0, -t, 0.0),
+        Vec3::new(0.0, -1.0, t),
+        Vec3::new(0.0, 1.0, t),
+        Vec3::new(0.0, -1.0, -t),
+        Vec3::new(0.0, 1.0, -t),
+        Vec3::new(t, 0.0, -1.0),
+        Vec3::new(t, 0.0, 1.0),
+        Vec3::new(-t, 0.0, -1.0),
+        Vec3::new(-t, 0.0, 1.0),
+    ]
+    .map(Vec3::normalize);
+    let faces = [
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+    let mut triangles: Vec<[Vec3; 3]> = faces
+        .iter()
+        .map(|face| face.map(|index| corners[index]))
+        .collect();
+    for _ in 0..subdivisions {
+        // Every triangle is split into 4 at its edge midpoints, pushed back onto the sphere
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let ab = (a + b).normalize();
+                let bc = (b + c).normalize();
+                let ca = (c + a).normalize();
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut buffers = empty_buffers();
+    for normal in triangles.into_iter().flatten() {
+        buffers.position_buffer.extend((normal * radius).to_array());
+        buffers.normal_buffer.extend(normal.to_array());
+    }
+    buffers
+}
+
+/// Sphere centered at the origin built from rings of latitude, as a non-indexed triangle list.
+/// Triangles get smaller towards the poles
+pub fn uv_sphere_vertices(radius: f32, segments: usize, rings: usize) -> VertexBuffers {
+    let vertex = |ring: usize, segment: usize| {
+        let polar = ring as f32 / rings as f32 * PI;
+        let azimuth = segment as f32 / segments as f32 * TAU;
+        Vec3::new(
+            polar.sin() * azimuth.cos(),
+            polar.cos(),
+            polar.sin() * azimuth.sin(),
+        )
+    };
+
+    let mut buffers = empty_buffers();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let top = vertex(ring, segment);
+            let bottom = vertex(ring + 1, segment);
+            let bottom_next = vertex(ring + 1, segment + 1);
+            let top_next = vertex(ring, segment + 1);
+            // Counter-clockwise seen from outside
+            for normal in [top, bottom_next, bottom, top, top_next, bottom_next] {
+                buffers.position_buffer.extend((normal * radius).to_array());
+                buffers.normal_buffer.extend(normal.to_array());
+            }
+        }
+    }
+    buffers
+}
+
+/// Share of the viewport height covered by a sphere at the given distance from the camera
+pub fn projected_screen_size(radius: f32, distance: f32, projection: &Mat4) -> f32 {
+    // Perspective projections divide by the distance, orthographic ones do not
+    let perspective = projection.w_axis.w == 0.0;
+    let depth = match perspective {
+        true => distance.max(f32::EPSILON),
+        false => 1.0,
+    };
+    radius * projection.y_axis.y / depth
+}
+
+/// Index of the detail level to draw, given the thresholds of each level after the first
+pub fn select_lod(screen_size: f32, thresholds: impl IntoIterator<Item = f32>) -> usize {
+    thresholds
+        .into_iter()
+        .take_while(|threshold| screen_size < *threshold)
+        .count()
+}
+
+fn empty_buffers() -> VertexBuffers {
+    VertexBuffers {
+        position_buffer: Vec::new(),
+        tex_coord_buffer: Vec::new(),
+        normal_buffer: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cameras::camera::perspective_reverse_z;
+
+    use super::*;
+
+    fn triangles(buffers: &VertexBuffers) -> Vec<[Vec3; 3]> {
+        buffers
+            .position_buffer
+            .chunks(9)
+            .map(|triangle| {
+                [
+                    Vec3::from_slice(&triangle[0..3]),
+                    Vec3::from_slice(&triangle[3..6]),
+                    Vec3::from_slice(&triangle[6..9]),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sphere_vertices_on_surface_facing_outwards() {
+        for (buffers, count) in [
+            (icosphere_vertices(0.5, 0), 20),
+            (icosphere_vertices(0.5, 2), 20 * 16),
+            (uv_sphere_vertices(0.5, 12, 6), 12 * 6 * 2),
+        ] {
+            let triangles = triangles(&buffers);
+            assert_eq!(triangles.len(), count);
+            assert_eq!(buffers.normal_buffer.len(), buffers.position_buffer.len());
+            for [a, b, c] in triangles {
+                assert!([a, b, c].iter().all(|p| (p.length() - 0.5).abs() < 1e-5));
+                let face = (b - a).cross(c - a);
+                // Degenerate triangles at the poles of the uv sphere have no face normal
+                assert!(face.dot(a + b + c) >= -1e-6, "{a} {b} {c}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_lod_follows_screen_size() {
+        let projection = perspective_reverse_z(60f32.to_radians(), 16.0 / 9.0, 0.1, 1000.0);
+        let near = projected_screen_size(0.5, 2.0, &projection);
+        let far = projected_screen_size(0.5, 200.0, &projection);
+        assert!((near / far - 100.0).abs() < 1e-3);
+        // Filling the view vertically: Radius equals distance * tan(fov / 2)
+        let filling = projected_screen_size(30f32.to_radians().tan(), 1.0, &projection);
+        assert!((filling - 1.0).abs() < 1e-5);
+
+        assert_eq!(select_lod(near, SPHERE_LOD_SCREEN_SIZES), 0);
+        assert_eq!(select_lod(0.05, SPHERE_LOD_SCREEN_SIZES), 1);
+        assert_eq!(select_lod(far, SPHERE_LOD_SCREEN_SIZES), 3);
+        let uv = SphereGeometry::Uv.vertices(1.0, 1);
+        let uv_detailed = SphereGeometry::Uv.vertices(1.0, 2);
+        assert_eq!(
+            uv_detailed.position_buffer.len(),
+            uv.position_buffer.len() * 4
+        );
+        assert_eq!(
+            SPHERE_LOD_SCREEN_SIZES.len() + 1,
+            SPHERE_LOD_SUBDIVISIONS.len()
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod cubemesh;
+pub mod icosphere;
 pub mod objmesh;
 pub mod sphere;
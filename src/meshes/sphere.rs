@@ -5,15 +5,20 @@ use glow::HasContext;
 
 use crate::{renderer::shader::Shader, scenes::Renderer};
 
-use super::objmesh::ObjMesh;
+use super::icosphere::{
+    SPHERE_LOD_SCREEN_SIZES, SPHERE_LOD_SUBDIVISIONS, SphereGeometry, projected_screen_size,
+    select_lod,
+};
+
+type VertexArray = <glow::Context as HasContext>::VertexArray;
 
 pub struct SphereMesh {
     pub position: Vec3,
-    // WARN: Not yet fully supported!
     pub radius: f32,
     pub color: Vec3,
     shader: Shader,
-    vao: <glow::Context as HasContext>::VertexArray,
+    // Vertex array & vertex count of every detail level, most detailed first
+    lods: Vec<(VertexArray, i32)>,
     gl: Rc<glow::Context>,
 }
 
@@ -22,66 +27,79 @@ impl SphereMesh {
         let shader = Shader::new(
             gl,
             "assets/shaders/sphere.vert",
-            "assets/shaders/sphere_rt.frag",
+            "assets/shaders/sphere.frag",
         )?;
-        // Load vertex data from mesh
-        let mut mesh = ObjMesh::new();
-        mesh.load("assets/cube_github.obj")
-            .expect("Could not load mesh");
-        let vertex_positions = mesh.get_vertex_buffers().position_buffer;
-        let vertex_bytes: &[u8] = unsafe {
-            std::slice::from_raw_parts(
-                vertex_positions.as_ptr() as *const u8,
-                vertex_positions.len() * std::mem::size_of::<f32>(),
-            )
-        };
-        unsafe {
-            // Setup vertex & index array and buffer
-            let vao = gl.create_vertex_array()?;
-            gl.bind_vertex_array(Some(vao));
-            // Bind vertex data
-            let vbo = gl.create_buffer()?;
-            gl.bind_buffer(gl::ARRAY_BUFFER, Some(vbo));
-            gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, vertex_bytes, gl::STATIC_DRAW);
-            // Setup position attribute
-            gl.vertex_attrib_pointer_f32(
-                0,
-                3,
-                gl::FLOAT,
-                false,
-                3 * std::mem::size_of::<f32>() as i32,
-                0,
-            );
-            gl.enable_vertex_array_attrib(vao, 0);
-            Ok(Self {
-                color: Vec3::new(1.0, 0.0, 0.0),
-                gl: Rc::clone(gl),
-                position: Vec3::ZERO,
-                radius: 0.5,
-                shader,
-                vao,
-            })
-        }
+        Ok(Self {
+            color: Vec3::new(1.0, 0.0, 0.0),
+            gl: Rc::clone(gl),
+            position: Vec3::ZERO,
+            radius: 0.5,
+            shader,
+            lods: sphere_vertex_arrays(gl, SphereGeometry::Icosphere, 1.0)?,
+        })
     }
 }
 
 impl Renderer for SphereMesh {
     fn render(&mut self, cam: &crate::cameras::camera::Camera) {
+        let projection = cam.get_projection_matrix();
+        let screen_size = projected_screen_size(
+            self.radius,
+            self.position.distance(cam.position),
+            &projection,
+        );
+        let (vao, count) = self.lods[select_lod(screen_size, SPHERE_LOD_SCREEN_SIZES)];
+
         self.shader.use_program();
-        self.shader
-            .set_uniform_mat4("model", &Mat4::from_translation(self.position));
+        let model =
+            Mat4::from_translation(self.position) * Mat4::from_scale(Vec3::splat(self.radius));
+        self.shader.set_uniform_mat4("model", &model);
         self.shader.set_uniform_mat4("view", &cam.get_view_matrix());
-        self.shader
-            .set_uniform_mat4("perspective", &cam.get_projection_matrix());
-        self.shader.set_uniform_vec3("camPos", &cam.position);
-        self.shader.set_uniform_vec3("sphereCenter", &self.position);
-        self.shader.set_uniform_f32("sphereRadius", self.radius);
+        self.shader.set_uniform_mat4("perspective", &projection);
         self.shader.set_uniform_vec3("sphereColor", &self.color);
         let gl = &self.gl;
         unsafe {
-            gl.bind_vertex_array(Some(self.vao));
-            gl.draw_arrays(gl::TRIANGLES, 0, 36);
+            gl.bind_vertex_array(Some(vao));
+            gl.draw_arrays(gl::TRIANGLES, 0, count);
+            gl.bind_vertex_array(None);
+        }
+    }
+}
+
+/// Uploads a sphere for every level of [SPHERE_LOD_SUBDIVISIONS], with positions at attribute 0
+/// & normals at attribute 1
+pub fn sphere_vertex_arrays(
+    gl: &Rc<glow::Context>,
+    geometry: SphereGeometry,
+    radius: f32,
+) -> Result<Vec<(VertexArray, i32)>, Box<dyn Error>> {
+    let mut lods = Vec::with_capacity(SPHERE_LOD_SUBDIVISIONS.len());
+    for subdivisions in SPHERE_LOD_SUBDIVISIONS {
+        let vertex_buffers = geometry.vertices(radius, subdivisions);
+        // NOTE: /3 because we have 3 coordinates per vertex
+        let vertex_count = vertex_buffers.position_buffer.len() / 3;
+        let positions_bytes: &[u8] = bytemuck::cast_slice(&vertex_buffers.position_buffer);
+        let normals_bytes: &[u8] = bytemuck::cast_slice(&vertex_buffers.normal_buffer);
+        unsafe {
+            let vao = gl.create_vertex_array()?;
+            gl.bind_vertex_array(Some(vao));
+            // Buffer position data
+            let positions_vbo = gl.create_buffer()?;
+            gl.bind_buffer(gl::ARRAY_BUFFER, Some(positions_vbo));
+            gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, positions_bytes, gl::STATIC_DRAW);
+            gl.vertex_attrib_pointer_f32(0, 3, gl::FLOAT, false, 0, 0);
+            gl.enable_vertex_array_attrib(vao, 0);
+            // Buffer normal data
+            let normals_vbo = gl.create_buffer()?;
+            gl.bind_buffer(gl::ARRAY_BUFFER, Some(normals_vbo));
+            gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, normals_bytes, gl::STATIC_DRAW);
+            gl.vertex_attrib_pointer_f32(1, 3, gl::FLOAT, false, 0, 0);
+            gl.enable_vertex_array_attrib(vao, 1);
+            // Cleanup
+            gl.bind_buffer(gl::ARRAY_BUFFER, None);
             gl.bind_vertex_array(None);
+            lods.push((vao, vertex_count as i32));
         }
     }
+    Ok(lods)
 }
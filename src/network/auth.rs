@@ -0,0 +1,115 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::message::NetworkMessage;
+
+/// Issued by the server once the handshake is accepted. Included in every packet afterwards
+pub type SessionToken = u64;
+
+/// Sent before a session was issued
+pub const NO_SESSION: SessionToken = 0;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Secret shared by server & clients. Packets without a valid signature are dropped, so random
+/// UDP traffic cannot inject commands
+#[derive(Clone)]
+pub struct PreSharedKey(Vec<u8>);
+
+impl PreSharedKey {
+    pub fn new(key: impl Into<Vec<u8>>) -> PreSharedKey {
+        Self(key.into())
+    }
+
+    fn mac(&self, session: SessionToken, message: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.0).expect("HMAC accepts keys of any size");
+        mac.update(&session.to_le_bytes());
+        mac.update(message);
+        mac
+    }
+}
+
+// Wire format of all transport messages
+#[derive(Serialize, Deserialize)]
+struct SealedPacket {
+    session: SessionToken,
+    message: Vec<u8>,
+    // HMAC-SHA256 of session & message. Empty without pre-shared key
+    signature: Vec<u8>,
+}
+
+/// Random token, never NO_SESSION
+pub fn new_session_token() -> Result<SessionToken, String> {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).map_err(|err| format!("No randomness available: {err}"))?;
+    Ok(SessionToken::from_le_bytes(bytes).max(1))
+}
+
+pub(super) fn seal(
+    message: &NetworkMessage,
+    session: SessionToken,
+    key: Option<&PreSharedKey>,
+) -> Result<Vec<u8>, String> {
+    let message =
+        bincode::serialize(message).map_err(|err| format!("Failed to serialize: {err}"))?;
+    let signature = key.map_or(Vec::new(), |key| {
+        key.mac(session, &message).finalize().into_bytes().to_vec()
+    });
+    bincode::serialize(&SealedPacket {
+        session,
+        message,
+        signature,
+    })
+    .map_err(|err| format!("Failed to seal: {err}"))
+}
+
+/// Decodes a packet. Fails if the signature does not match the key
+pub(super) fn open(
+    bytes: &[u8],
+    key: Option<&PreSharedKey>,
+) -> Result<(SessionToken, NetworkMessage), String> {
+    let packet: SealedPacket =
+        bincode::deserialize(bytes).map_err(|err| format!("Malformed packet: {err}"))?;
+    if let Some(key) = key {
+        key.mac(packet.session, &packet.message)
+            .verify_slice(&packet.signature)
+            .map_err(|_| "Invalid packet signature".to_string())?;
+    }
+    let message = bincode::deserialize(&packet.message)
+        .map_err(|err| format!("Failed to decode into NetworkMessage: {err}"))?;
+    Ok((packet.session, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_packets() {
+        let key = PreSharedKey::new("secret");
+        let bytes = seal(&NetworkMessage::Disconnect, 7, Some(&key)).unwrap();
+        let (session, message) = open(&bytes, Some(&key)).unwrap();
+        assert_eq!(session, 7);
+        assert!(matches!(message, NetworkMessage::Disconnect));
+
+        assert!(open(&bytes, Some(&PreSharedKey::new("other"))).is_err());
+        // Unsigned packets are rejected once a key is configured
+        let unsigned = seal(&NetworkMessage::Disconnect, 7, None).unwrap();
+        assert!(open(&unsigned, None).is_ok());
+        assert!(open(&unsigned, Some(&key)).is_err());
+        // Session cannot be swapped without the key
+        let mut tampered = bytes.clone();
+        tampered[0] ^= 1;
+        assert!(open(&tampered, Some(&key)).is_err());
+        // Noise
+        assert!(open(&[1, 2, 3], Some(&key)).is_err());
+    }
+
+    #[test]
+    fn test_session_tokens() {
+        let first = new_session_token().unwrap();
+        assert_ne!(first, NO_SESSION);
+        assert_ne!(first, new_session_token().unwrap());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::VecDeque, time::Instant};
+
+use super::meter::TrafficMeter;
+
+/// Decides which packets are sacrificed once a client exceeds its send budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketPriority {
+    /// Events the client has to receive, e.g. match start. Delayed while over budget
+    Reliable,
+    /// Superseded by the next update, e.g. entity snapshots. Dropped while over budget
+    Unreliable,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BandwidthUsage {
+    pub bytes_per_second: u64,
+    pub dropped_packets: u64,
+    pub queued_packets: usize,
+}
+
+/// Converts a rate in kilobits per second
+pub fn kbps_to_bytes_per_second(kbps: u32) -> u64 {
+    kbps as u64 * 1000 / 8
+}
+
+/// Outgoing packets of a single client. Sent in order of priority, limited by a token bucket
+/// holding at most one second worth of bytes
+pub(super) struct SendQueue {
+    reliable: VecDeque<Vec<u8>>,
+    unreliable: Vec<Vec<u8>>,
+    budget: f64,
+    refilled_at: Instant,
+    dropped_packets: u64,
+    meter: TrafficMeter,
+}
+
+impl SendQueue {
+    pub fn new() -> SendQueue {
+        Self {
+            reliable: VecDeque::new(),
+            unreliable: Vec::new(),
+            // Full burst until the first flush
+            budget: f64::INFINITY,
+            refilled_at: Instant::now(),
+            dropped_packets: 0,
+            meter: TrafficMeter::new(),
+        }
+    }
+
+    pub fn push(&mut self, bytes: Vec<u8>, priority: PacketPriority) {
+        match priority {
+            PacketPriority::Reliable => self.reliable.push_back(bytes),
+            PacketPriority::Unreliable => self.unreliable.push(bytes),
+        }
+    }
+
+    /// Packets to send now. Reliable packets go first & stay queued until budget is available.
+    /// Unreliable packets that do not fit are dropped. No limit if max rate is None
+    pub fn flush(&mut self, now: Instant, max_bytes_per_second: Option<u64>) -> Vec<Vec<u8>> {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.refilled_at = now;
+        let mut sent = Vec::new();
+        match max_bytes_per_second {
+            None => {
+                sent.extend(self.reliable.drain(..));
+                sent.append(&mut self.unreliable);
+            }
+            Some(max_rate) => {
+                let max_rate = max_rate as f64;
+                self.budget = (self.budget + elapsed.as_secs_f64() * max_rate).min(max_rate);
+                // Sending while any budget is left, so packets larger than the budget still pass
+                while self.budget > 0.0
+                    && let Some(bytes) = self.reliable.pop_front()
+                {
+                    self.budget -= bytes.len() as f64;
+                    sent.push(bytes);
+                }
+                for bytes in self.unreliable.drain(..) {
+                    if self.reliable.is_empty() && self.budget > 0.0 {
+                        self.budget -= bytes.len() as f64;
+                        sent.push(bytes);
+                    } else {
+                        self.dropped_packets += 1;
+                    }
+                }
+            }
+        }
+        for bytes in &sent {
+            self.meter.track_downstream(bytes.len());
+        }
+        sent
+    }
+
+    pub fn usage(&self) -> BandwidthUsage {
+        BandwidthUsage {
+            bytes_per_second: self.meter.downstream_bps(),
+            dropped_packets: self.dropped_packets,
+            queued_packets: self.reliable.len() + self.unreliable.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn packet(size: usize) -> Vec<u8> {
+        vec![0; size]
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let mut queue = SendQueue::new();
+        queue.push(packet(10_000), PacketPriority::Unreliable);
+        queue.push(packet(10_000), PacketPriority::Reliable);
+        let sent = queue.flush(Instant::now(), None);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(queue.usage().dropped_packets, 0);
+    }
+
+    #[test]
+    fn test_unreliable_dropped_before_reliable() {
+        let start = Instant::now();
+        let mut queue = SendQueue::new();
+        // Drains the initial burst
+        queue.push(packet(100), PacketPriority::Reliable);
+        assert_eq!(queue.flush(start, Some(100)).len(), 1);
+
+        queue.push(packet(60), PacketPriority::Unreliable);
+        queue.push(packet(60), PacketPriority::Reliable);
+        queue.push(packet(60), PacketPriority::Reliable);
+        let later = start + Duration::from_millis(500);
+        // 50 bytes refilled: One reliable packet, the rest waits
+        let sent = queue.flush(later, Some(100));
+        assert_eq!(sent.len(), 1);
+        let usage = queue.usage();
+        assert_eq!(usage.dropped_packets, 1);
+        assert_eq!(usage.queued_packets, 1);
+
+        // Queued reliable packet is sent once the budget recovered
+        let sent = queue.flush(later + Duration::from_secs(1), Some(100));
+        assert_eq!(sent.len(), 1);
+        assert_eq!(queue.usage().queued_packets, 0);
+    }
+
+    #[test]
+    fn test_kbps_conversion() {
+        assert_eq!(kbps_to_bytes_per_second(64), 8000);
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     net::UdpSocket,
     sync::{
         Arc, Mutex, RwLock,
-        atomic::AtomicBool,
+        atomic::{AtomicBool, AtomicU64},
         mpsc::{self, Sender},
     },
     thread,
@@ -12,9 +12,33 @@ use std::{
 
 use log::{debug, error, info};
 
-use crate::{network::message::NetworkMessage, util::SimpleMovingAverage};
+use crate::{
+    network::message::NetworkMessage,
+    util::{MemoryTag, SimpleMovingAverage},
+};
 
-use super::{ClientId, meter::TrafficMeter};
+use super::{
+    ClientId, HandshakeState, ProtocolInfo,
+    auth::{self, NO_SESSION, PreSharedKey},
+    clock_sync::ClockSync,
+    meter::TrafficMeter,
+};
+
+/// Game packet received from the server
+pub struct ClientDownstreamPayload {
+    pub bytes: Vec<u8>,
+    /// Server clock time the packet was sent at
+    pub server_time: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpectateState {
+    // Client participates as regular player
+    None,
+    Requested,
+    Accepted,
+    Rejected(String),
+}
 
 /// Networking transport layer. Manages UDP connection
 /// Needs to be enhanced with game specific protocol layer
@@ -29,15 +53,33 @@ pub struct NetworkClient {
     // Ping information
     initialized_at: Instant,
     ping_sma: Arc<RwLock<SimpleMovingAverage>>,
+    clock: Arc<RwLock<ClockSync>>,
 
     connected: Arc<AtomicBool>,
+    spectate_state: Arc<RwLock<SpectateState>>,
+    // None until the first handshake was sent
+    handshake_state: Arc<RwLock<Option<HandshakeState>>>,
+    // Issued by the server on handshake. Included in all packets
+    session: Arc<AtomicU64>,
+    pre_shared_key: Option<PreSharedKey>,
+    // Transport thread stops & releases its socket once false
+    running: Arc<AtomicBool>,
 }
 
 impl NetworkClient {
     pub fn new(
         server_address: &str,
         // Channel to pass incoming bytes to protocol layer
-        downstream_tx: Sender<Vec<u8>>,
+        downstream_tx: Sender<ClientDownstreamPayload>,
+    ) -> Result<NetworkClient, Box<dyn Error>> {
+        Self::with_pre_shared_key(server_address, downstream_tx, None)
+    }
+
+    /// Signs all packets with the key. Has to match the key of the server
+    pub fn with_pre_shared_key(
+        server_address: &str,
+        downstream_tx: Sender<ClientDownstreamPayload>,
+        pre_shared_key: Option<PreSharedKey>,
     ) -> Result<NetworkClient, Box<dyn Error>> {
         // Bind to 0.0.0.0:0 to let OS pick an available port
         let socket = UdpSocket::bind("0.0.0.0:0")?;
@@ -58,18 +100,35 @@ impl NetworkClient {
         let ping_sma_thread = Arc::clone(&ping_sma);
         let initialized_at = Instant::now();
         let initialized_at_thread = initialized_at;
+        let clock = Arc::new(RwLock::new(ClockSync::new()));
+        let clock_thread = Arc::clone(&clock);
         let client_id = Arc::new(RwLock::new(None));
         let client_id_thread = Arc::clone(&client_id);
         let connected = Arc::new(AtomicBool::new(false));
         let connected_thread = Arc::clone(&connected);
+        let spectate_state = Arc::new(RwLock::new(SpectateState::None));
+        let spectate_state_thread = Arc::clone(&spectate_state);
+        let handshake_state = Arc::new(RwLock::new(None));
+        let handshake_state_thread = Arc::clone(&handshake_state);
+        let session = Arc::new(AtomicU64::new(NO_SESSION));
+        let session_thread = Arc::clone(&session);
+        let key_thread = pre_shared_key.clone();
+        let running = Arc::new(AtomicBool::new(true));
+        let running_thread = Arc::clone(&running);
         let address = server_address.to_string();
         thread::spawn(move || {
+            let _memory = MemoryTag::Network.enter();
             let mut buf = [0u8; 1024];
-            loop {
+            while running_thread.load(std::sync::atomic::Ordering::Acquire) {
                 // Send queued messages
                 while let Ok(packet) = upstream_rx.try_recv() {
                     // Convert to network message
-                    match bincode::serialize(&NetworkMessage::GamePacket { payload: packet }) {
+                    let packet = NetworkMessage::GamePacket {
+                        payload: packet,
+                        sent_at: initialized_at_thread.elapsed().as_nanos(),
+                    };
+                    let session = session_thread.load(std::sync::atomic::Ordering::Acquire);
+                    match auth::seal(&packet, session, key_thread.as_ref()) {
                         Ok(msg) => {
                             if let Err(e) = socket.send(&msg) {
                                 error!("Error sending message from client to server: {e}");
@@ -88,8 +147,8 @@ impl NetworkClient {
                             let payload = &buf[..n];
                             // Deserialize to network message
                             // Special cases for non-game packets
-                            match bincode::deserialize::<NetworkMessage>(payload) {
-                                Ok(network_msg) => match network_msg {
+                            match auth::open(payload, key_thread.as_ref()) {
+                                Ok((_, network_msg)) => match network_msg {
                                     NetworkMessage::Ping { .. } => {
                                         error!("Client received ping, this should not happen");
                                     }
@@ -107,10 +166,53 @@ impl NetworkClient {
                                         let recv_time = initialized_at_thread.elapsed().as_nanos();
                                         let delta = recv_time - client_timestamp;
                                         ping_sma_thread.write().unwrap().add(delta as f32);
+                                        clock_thread.write().unwrap().add_sample(
+                                            client_timestamp,
+                                            server_uptime,
+                                            recv_time,
+                                        );
                                         *client_id_thread.write().unwrap() = Some(client_id);
                                     }
-                                    NetworkMessage::GamePacket { payload } => {
+                                    NetworkMessage::SpectateResponse { accepted, reason } => {
+                                        info!("Spectate request accepted: {accepted}");
+                                        *spectate_state_thread.write().unwrap() = if accepted {
+                                            SpectateState::Accepted
+                                        } else {
+                                            SpectateState::Rejected(reason.unwrap_or_default())
+                                        };
+                                    }
+                                    NetworkMessage::HandshakeResponse {
+                                        accepted,
+                                        reason,
+                                        session,
+                                    } => {
+                                        let state = if accepted {
+                                            info!("Handshake accepted");
+                                            session_thread.store(
+                                                session.unwrap_or(NO_SESSION),
+                                                std::sync::atomic::Ordering::Release,
+                                            );
+                                            HandshakeState::Accepted
+                                        } else {
+                                            let reason = reason.unwrap_or_default();
+                                            error!("Server rejected handshake: {reason}");
+                                            HandshakeState::Rejected(reason)
+                                        };
+                                        *handshake_state_thread.write().unwrap() = Some(state);
+                                    }
+                                    NetworkMessage::SpectateRequest
+                                    | NetworkMessage::Handshake { .. }
+                                    | NetworkMessage::Disconnect => {
+                                        error!(
+                                            "Client received client message, this should not happen"
+                                        );
+                                    }
+                                    NetworkMessage::GamePacket { payload, sent_at } => {
                                         let size = payload.len();
+                                        let payload = ClientDownstreamPayload {
+                                            bytes: payload,
+                                            server_time: Duration::from_nanos(sent_at as u64),
+                                        };
                                         if let Err(e) = downstream_tx.send(payload) {
                                             error!(
                                                 "Failed to forward payload to protocol layer: {e}"
@@ -146,14 +248,34 @@ impl NetworkClient {
         Ok(NetworkClient {
             client_id,
             connected,
-            initialized_at: Instant::now(),
+            spectate_state,
+            handshake_state,
+            session,
+            pre_shared_key,
+            initialized_at,
             ping_sma,
+            clock,
             socket: socket_clone,
             traffic_meter,
             upstream_tx,
+            running,
         })
     }
 
+    /// Notifies the server & stops the transport thread. No packets are sent or received after
+    pub fn disconnect(&self) {
+        if !self
+            .running
+            .swap(false, std::sync::atomic::Ordering::AcqRel)
+        {
+            return;
+        }
+        self.send_message(&NetworkMessage::Disconnect, "disconnect");
+        self.connected
+            .store(false, std::sync::atomic::Ordering::Release);
+        info!("Disconnected from server");
+    }
+
     pub fn get_client_id(&self) -> Option<ClientId> {
         self.client_id.read().ok().and_then(|g| *g)
     }
@@ -174,21 +296,69 @@ impl NetworkClient {
         let ping = NetworkMessage::Ping {
             client_timestamp: self.initialized_at.elapsed().as_nanos(),
         };
-        match bincode::serialize(&ping) {
+        self.send_message(&ping, "ping");
+    }
+
+    /// Ask server to join as spectator. Response is reflected in [Self::spectate_state]
+    pub fn request_spectate(&self) {
+        *self.spectate_state.write().unwrap() = SpectateState::Requested;
+        self.send_message(&NetworkMessage::SpectateRequest, "spectate request");
+    }
+
+    pub fn spectate_state(&self) -> SpectateState {
+        self.spectate_state.read().unwrap().clone()
+    }
+
+    /// Announces protocol & player name. Has to be repeated until the server responds, as the
+    /// packet might get lost. Response is reflected in [Self::handshake_state]
+    pub fn handshake(&self, protocol: &ProtocolInfo, player_name: &str) {
+        self.handshake_state
+            .write()
+            .unwrap()
+            .get_or_insert(HandshakeState::Pending);
+        let handshake = NetworkMessage::Handshake {
+            protocol: protocol.clone(),
+            player_name: player_name.to_string(),
+        };
+        self.send_message(&handshake, "handshake");
+    }
+
+    // Sends directly, bypassing the upstream queue
+    fn send_message(&self, message: &NetworkMessage, description: &str) {
+        let session = self.session.load(std::sync::atomic::Ordering::Acquire);
+        match auth::seal(message, session, self.pre_shared_key.as_ref()) {
             Ok(bytes) => {
                 if let Err(err) = self.socket.send(&bytes) {
-                    error!("Failed to send ping: {err}");
+                    error!("Failed to send {description}: {err}");
                 }
             }
-            Err(err) => error!("Failed to serialize ping: {err}"),
+            Err(err) => error!("Failed to serialize {description}: {err}"),
         }
     }
 
+    /// None if no handshake was sent yet
+    pub fn handshake_state(&self) -> Option<HandshakeState> {
+        self.handshake_state.read().unwrap().clone()
+    }
+
+    /// Current time on the server clock. None until the first ping round trip
+    pub fn server_time_at(&self, now: Instant) -> Option<Duration> {
+        let local_time = now.saturating_duration_since(self.initialized_at);
+        self.clock.read().unwrap().server_time(local_time)
+    }
+
+    pub fn is_clock_synced(&self) -> bool {
+        self.clock.read().unwrap().is_synced()
+    }
+
     pub fn get_ping(&self) -> f32 {
         self.ping_sma.read().unwrap().get()
     }
 
     pub fn send_game_packet(&self, bytes: Vec<u8>) -> Result<(), String> {
+        if self.spectate_state() == SpectateState::Accepted {
+            return Err("Spectators cannot send game packets".to_string());
+        }
         self.upstream_tx
             .send(bytes)
             .map_err(|_| "Failed to send bytes".to_string())
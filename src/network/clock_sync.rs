@@ -0,0 +1,119 @@
+use std::{collections::VecDeque, time::Duration};
+
+use log::debug;
+
+// Number of recent ping samples considered. Sample with lowest RTT is the most accurate
+const CLOCK_SAMPLE_WINDOW: usize = 8;
+// Fraction of the remaining error corrected per sample
+const CLOCK_SMOOTHING: f64 = 0.2;
+// Offset errors larger than this are applied instantly, e.g. on first sync
+const CLOCK_SNAP_THRESHOLD: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy)]
+struct ClockSample {
+    offset_nanos: i128,
+    rtt_nanos: u128,
+}
+
+/// Estimates offset between local & server clock from ping round trips (NTP style):
+/// server time at receive is approximated by the server timestamp + half the round trip.
+/// The best sample of a sliding window is used and approached smoothly to avoid jitter
+#[derive(Debug, Default)]
+pub struct ClockSync {
+    samples: VecDeque<ClockSample>,
+    offset_nanos: Option<f64>,
+}
+
+impl ClockSync {
+    pub fn new() -> ClockSync {
+        Self::default()
+    }
+
+    /// All timestamps in nanoseconds. Client timestamps on local clock, server timestamp on
+    /// server clock
+    pub fn add_sample(&mut self, client_sent: u128, server_time: u128, client_received: u128) {
+        let rtt_nanos = client_received.saturating_sub(client_sent);
+        let offset_nanos = server_time as i128 + (rtt_nanos / 2) as i128 - client_received as i128;
+        if self.samples.len() == CLOCK_SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ClockSample {
+            offset_nanos,
+            rtt_nanos,
+        });
+
+        let best = self
+            .samples
+            .iter()
+            .min_by_key(|sample| sample.rtt_nanos)
+            .expect("Sample window cannot be empty")
+            .offset_nanos as f64;
+        let offset = match self.offset_nanos {
+            Some(current) if (best - current).abs() < CLOCK_SNAP_THRESHOLD.as_nanos() as f64 => {
+                current + (best - current) * CLOCK_SMOOTHING
+            }
+            _ => best,
+        };
+        debug!(
+            "Clock sync sample: rtt {:?}, offset {:?}",
+            Duration::from_nanos(rtt_nanos as u64),
+            Duration::from_nanos(offset.abs() as u64)
+        );
+        self.offset_nanos = Some(offset);
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.offset_nanos.is_some()
+    }
+
+    /// Converts local clock to server clock. None until the first sample
+    pub fn server_time(&self, local_time: Duration) -> Option<Duration> {
+        let server_nanos = local_time.as_nanos() as f64 + self.offset_nanos?;
+        Some(Duration::from_nanos(server_nanos.max(0.0) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u128 = 1_000_000;
+
+    #[test]
+    fn test_offset_from_symmetric_round_trip() {
+        let mut clock = ClockSync::new();
+        assert_eq!(clock.server_time(Duration::ZERO), None);
+        // Server clock is 1s ahead, 20ms one way latency
+        clock.add_sample(0, 1000 * MS + 20 * MS, 40 * MS);
+        assert!(clock.is_synced());
+        assert_eq!(
+            clock.server_time(Duration::from_millis(40)),
+            Some(Duration::from_millis(1040))
+        );
+    }
+
+    #[test]
+    fn test_prefers_low_latency_samples() {
+        let mut clock = ClockSync::new();
+        clock.add_sample(0, 1020 * MS, 40 * MS);
+        // Delayed response on the way back overestimates latency: Ignored in favour of first sample
+        clock.add_sample(100 * MS, 1120 * MS, 300 * MS);
+        assert_eq!(
+            clock.server_time(Duration::ZERO),
+            Some(Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn test_smooths_small_corrections() {
+        let mut clock = ClockSync::new();
+        clock.add_sample(0, 1020 * MS, 40 * MS);
+        for _ in 0..CLOCK_SAMPLE_WINDOW {
+            // Server clock drifted by 10ms, lower latency
+            clock.add_sample(0, 1020 * MS, 30 * MS);
+        }
+        let server_time = clock.server_time(Duration::ZERO).unwrap();
+        assert!(server_time > Duration::from_millis(1000));
+        assert!(server_time <= Duration::from_millis(1005));
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{HashMap, VecDeque};
+
+use glam::{IVec3, Mat4, Quat};
+use log::trace;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::physics::Transform;
+
+use super::{ClientId, EntitySnapshot, NetEntityId};
+
+/// Snapshots kept as potential delta baseline. 1.6s at 20Hz broadcast rate
+const SNAPSHOT_HISTORY_SIZE: usize = 32;
+// Positions & scales are quantized to 1/1024 units
+const POSITION_PRECISION: f32 = 1024.0;
+const ROTATION_PRECISION: f32 = i16::MAX as f32;
+
+/// Transform reduced to fixed point translation, scale & rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedTransform {
+    translation: IVec3,
+    rotation: [i16; 4],
+    scale: IVec3,
+}
+
+impl QuantizedTransform {
+    pub fn from_transform(transform: &Transform) -> QuantizedTransform {
+        let (scale, rotation, translation) = transform.0.to_scale_rotation_translation();
+        let rotation = rotation.normalize().to_array().map(|component| {
+            (component * ROTATION_PRECISION)
+                .round()
+                .clamp(-ROTATION_PRECISION, ROTATION_PRECISION) as i16
+        });
+        Self {
+            translation: (translation * POSITION_PRECISION).round().as_ivec3(),
+            rotation,
+            scale: (scale * POSITION_PRECISION).round().as_ivec3(),
+        }
+    }
+
+    pub fn to_transform(self) -> Transform {
+        let rotation =
+            Quat::from_array(self.rotation.map(|c| c as f32 / ROTATION_PRECISION)).normalize();
+        Transform(Mat4::from_scale_rotation_translation(
+            self.scale.as_vec3() / POSITION_PRECISION,
+            rotation,
+            self.translation.as_vec3() / POSITION_PRECISION,
+        ))
+    }
+}
+
+/// Changed components of an entity. Unchanged components are None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntityDelta {
+    pub net_entity_id: NetEntityId,
+    translation: Option<IVec3>,
+    rotation: Option<[i16; 4]>,
+    scale: Option<IVec3>,
+}
+
+/// Entity state relative to a snapshot acknowledged by the client.
+/// Without baseline all entities are sent in full
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeltaSnapshot {
+    pub baseline_tick: Option<u32>,
+    // Entities not listed are unchanged since baseline
+    changed: Vec<EntityDelta>,
+    removed: Vec<NetEntityId>,
+}
+
+type QuantizedSnapshot = Vec<(NetEntityId, QuantizedTransform)>;
+
+/// Ring buffer of sent / received snapshots by server tick
+#[derive(Default)]
+struct SnapshotHistory {
+    snapshots: VecDeque<(u32, QuantizedSnapshot)>,
+}
+
+impl SnapshotHistory {
+    fn get(&self, tick: u32) -> Option<&QuantizedSnapshot> {
+        self.snapshots
+            .iter()
+            .find_map(|(t, snapshot)| (*t == tick).then_some(snapshot))
+    }
+
+    fn push(&mut self, tick: u32, snapshot: QuantizedSnapshot) {
+        if self.snapshots.len() == SNAPSHOT_HISTORY_SIZE {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((tick, snapshot));
+    }
+}
+
+fn encode(current: &QuantizedSnapshot, baseline: Option<&QuantizedSnapshot>) -> DeltaSnapshot {
+    let baseline_state: HashMap<NetEntityId, &QuantizedTransform> = baseline
+        .map(|b| b.iter().map(|(id, state)| (*id, state)).collect())
+        .unwrap_or_default();
+    let changed = current
+        .iter()
+        .filter_map(|(net_entity_id, state)| {
+            let previous = baseline_state.get(net_entity_id);
+            let delta = EntityDelta {
+                net_entity_id: *net_entity_id,
+                translation: changed(state.translation, previous.map(|p| p.translation)),
+                rotation: changed(state.rotation, previous.map(|p| p.rotation)),
+                scale: changed(state.scale, previous.map(|p| p.scale)),
+            };
+            (delta.translation.is_some() || delta.rotation.is_some() || delta.scale.is_some())
+                .then_some(delta)
+        })
+        .collect();
+    let removed = baseline
+        .into_iter()
+        .flatten()
+        .filter(|(id, _)| !current.iter().any(|(current_id, _)| current_id == id))
+        .map(|(id, _)| *id)
+        .collect();
+    DeltaSnapshot {
+        baseline_tick: None,
+        changed,
+        removed,
+    }
+}
+
+fn changed<T: PartialEq>(value: T, previous: Option<T>) -> Option<T> {
+    (previous.as_ref() != Some(&value)).then_some(value)
+}
+
+fn decode(
+    delta: &DeltaSnapshot,
+    baseline: Option<&QuantizedSnapshot>,
+) -> Result<QuantizedSnapshot, String> {
+    let mut state: HashMap<NetEntityId, QuantizedTransform> =
+        baseline.into_iter().flatten().copied().collect();
+    for id in &delta.removed {
+        state.remove(id);
+    }
+    for entity in &delta.changed {
+        // Entities new since baseline carry all components
+        let transform = match state.get(&entity.net_entity_id) {
+            Some(previous) => QuantizedTransform {
+                translation: entity.translation.unwrap_or(previous.translation),
+                rotation: entity.rotation.unwrap_or(previous.rotation),
+                scale: entity.scale.unwrap_or(previous.scale),
+            },
+            None => QuantizedTransform {
+                translation: entity.translation.ok_or("Missing translation")?,
+                rotation: entity.rotation.ok_or("Missing rotation")?,
+                scale: entity.scale.ok_or("Missing scale")?,
+            },
+        };
+        state.insert(entity.net_entity_id, transform);
+    }
+    let mut snapshot: QuantizedSnapshot = state.into_iter().collect();
+    snapshot.sort_unstable_by_key(|(id, _)| *id);
+    Ok(snapshot)
+}
+
+/// Server side: Encodes snapshots per client against the last snapshot it acknowledged
+#[derive(Default)]
+pub struct DeltaEncoder {
+    clients: HashMap<ClientId, SnapshotHistory>,
+}
+
+impl DeltaEncoder {
+    pub fn new() -> DeltaEncoder {
+        Self::default()
+    }
+
+    /// Falls back to a full snapshot if the acknowledged tick is no longer in history
+    pub fn encode(
+        &mut self,
+        client: ClientId,
+        server_tick: u32,
+        snapshots: &[EntitySnapshot],
+        acked_tick: Option<u32>,
+    ) -> DeltaSnapshot {
+        let current: QuantizedSnapshot = snapshots
+            .iter()
+            .map(|s| {
+                (
+                    s.net_entity_id,
+                    QuantizedTransform::from_transform(&s.transform),
+                )
+            })
+            .collect();
+        let history = self.clients.entry(client).or_default();
+        let baseline = acked_tick.and_then(|tick| Some((tick, history.get(tick)?)));
+        let mut delta = encode(&current, baseline.map(|(_, snapshot)| snapshot));
+        delta.baseline_tick = baseline.map(|(tick, _)| tick);
+        trace!(
+            "Encoded snapshot {server_tick} for {client} against {:?}: {} changed, {} removed",
+            delta.baseline_tick,
+            delta.changed.len(),
+            delta.removed.len()
+        );
+        history.push(server_tick, current);
+        delta
+    }
+
+    pub fn remove_client(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+}
+
+/// Client side: Reconstructs full snapshots from deltas
+#[derive(Default)]
+pub struct DeltaDecoder {
+    history: SnapshotHistory,
+    last_received_tick: Option<u32>,
+}
+
+impl DeltaDecoder {
+    pub fn new() -> DeltaDecoder {
+        Self::default()
+    }
+
+    pub fn decode(
+        &mut self,
+        server_tick: u32,
+        delta: &DeltaSnapshot,
+    ) -> Result<Vec<EntitySnapshot>, String> {
+        let baseline = match delta.baseline_tick {
+            Some(tick) => Some(
+                self.history
+                    .get(tick)
+                    .ok_or(format!("Baseline snapshot {tick} not available"))?,
+            ),
+            None => None,
+        };
+        let snapshot = decode(delta, baseline)?;
+        let entities = snapshot
+            .iter()
+            .map(|(net_entity_id, state)| EntitySnapshot {
+                net_entity_id: *net_entity_id,
+                transform: state.to_transform(),
+            })
+            .collect();
+        self.history.push(server_tick, snapshot);
+        self.last_received_tick = Some(server_tick);
+        Ok(entities)
+    }
+
+    /// Tick to acknowledge to the server as baseline for future deltas
+    pub fn last_received_tick(&self) -> Option<u32> {
+        self.last_received_tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::network::test_client;
+
+    fn snapshot(net_entity_id: NetEntityId, translation: Vec3) -> EntitySnapshot {
+        EntitySnapshot {
+            net_entity_id,
+            transform: Transform(Mat4::from_scale_rotation_translation(
+                Vec3::new(0.2, 1.0, 0.2),
+                Quat::from_rotation_y(1.0),
+                translation,
+            )),
+        }
+    }
+
+    #[test]
+    fn test_quantization_roundtrip() {
+        let original = snapshot(0, Vec3::new(12.345, -3.2, 100.0)).transform;
+        let restored = QuantizedTransform::from_transform(&original).to_transform();
+        assert!(original.0.abs_diff_eq(restored.0, 1e-3));
+    }
+
+    #[test]
+    fn test_delta_only_contains_changes() {
+        let mut encoder = DeltaEncoder::new();
+        let mut decoder = DeltaDecoder::new();
+        let first = [snapshot(0, Vec3::ZERO), snapshot(1, Vec3::X)];
+        let full = encoder.encode(test_client(1), 1, &first, None);
+        assert_eq!(full.changed.len(), 2);
+        decoder.decode(1, &full).unwrap();
+
+        // Entity 1 moved, entity 0 did not
+        let second = [snapshot(0, Vec3::ZERO), snapshot(1, Vec3::Y)];
+        let delta = encoder.encode(test_client(1), 2, &second, decoder.last_received_tick());
+        assert_eq!(delta.baseline_tick, Some(1));
+        assert_eq!(delta.changed.len(), 1);
+        assert!(delta.changed[0].rotation.is_none());
+        let decoded = decoder.decode(2, &delta).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert!(
+            decoded[1]
+                .transform
+                .0
+                .abs_diff_eq(second[1].transform.0, 1e-3)
+        );
+
+        // Entity 0 removed
+        let delta = encoder.encode(test_client(1), 3, &second[1..], Some(2));
+        assert_eq!(delta.removed, vec![0]);
+        assert!(delta.changed.is_empty());
+        let decoded = decoder.decode(3, &delta).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].net_entity_id, 1);
+    }
+
+    #[test]
+    fn test_unknown_baseline() {
+        let mut encoder = DeltaEncoder::new();
+        // Server no longer knows baseline -> full snapshot
+        let delta = encoder.encode(test_client(1), 5, &[snapshot(0, Vec3::ZERO)], Some(1));
+        assert_eq!(delta.baseline_tick, None);
+        // Client misses baseline -> error instead of corrupt state
+        let delta = encoder.encode(test_client(1), 6, &[snapshot(0, Vec3::X)], Some(5));
+        assert!(DeltaDecoder::new().decode(6, &delta).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+
+/// Port servers listen on for discovery requests
+pub const DISCOVERY_PORT: u16 = 7778;
+/// Interval in which the browser asks the LAN for servers
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(1);
+/// Servers that stopped answering for this long are removed from the list
+const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+// Prefix of discovery packets, so unrelated broadcasts on the port are ignored
+const DISCOVERY_MAGIC: &[u8; 4] = b"VXLD";
+
+/// Sent by servers in response to discovery requests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerAnnouncement {
+    pub name: String,
+    pub players: usize,
+    /// Game mode, e.g. pong
+    pub mode: String,
+    /// Port the game is served on. Discovery uses its own port
+    pub game_port: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum DiscoveryMessage {
+    Request {
+        // Browser clock in nanoseconds. Echoed by servers to measure the ping
+        sent_at: u128,
+    },
+    Announcement {
+        sent_at: u128,
+        announcement: ServerAnnouncement,
+    },
+}
+
+fn encode(message: &DiscoveryMessage) -> Result<Vec<u8>, String> {
+    let mut bytes = DISCOVERY_MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, message)
+        .map_err(|err| format!("Failed to serialize discovery message: {err}"))?;
+    Ok(bytes)
+}
+
+fn decode(bytes: &[u8]) -> Result<DiscoveryMessage, String> {
+    let payload = bytes
+        .strip_prefix(DISCOVERY_MAGIC)
+        .ok_or("Not a discovery packet".to_string())?;
+    bincode::deserialize(payload).map_err(|err| format!("Malformed discovery packet: {err}"))
+}
+
+/// Server side. Answers discovery requests of browsers in the LAN
+pub(super) struct DiscoveryResponder {
+    socket: UdpSocket,
+}
+
+impl DiscoveryResponder {
+    pub fn bind(address: SocketAddr) -> std::io::Result<DiscoveryResponder> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// Answers all pending requests with the announcement
+    pub fn poll(&self, announcement: &ServerAnnouncement) {
+        let mut buf = [0u8; 512];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((n, browser)) => {
+                    let Ok(DiscoveryMessage::Request { sent_at }) = decode(&buf[..n]) else {
+                        debug!("Ignoring discovery packet of {browser}");
+                        continue;
+                    };
+                    let response = DiscoveryMessage::Announcement {
+                        sent_at,
+                        announcement: announcement.clone(),
+                    };
+                    match encode(&response) {
+                        Ok(bytes) => {
+                            if let Err(err) = self.socket.send_to(&bytes, browser) {
+                                error!("Failed to answer discovery request of {browser}: {err}");
+                            }
+                        }
+                        Err(err) => error!("{err}"),
+                    }
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    error!("Discovery socket error: {err}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscoveredServer {
+    /// Address to connect the game client to
+    pub address: SocketAddr,
+    pub announcement: ServerAnnouncement,
+    /// Round trip of the last discovery request
+    pub ping: Duration,
+    last_seen: Instant,
+}
+
+/// Client side. Broadcasts discovery requests & lists the servers that answered
+pub struct LanBrowser {
+    socket: UdpSocket,
+    target: SocketAddr,
+    initialized_at: Instant,
+    last_request_at: Option<Instant>,
+    servers: Vec<DiscoveredServer>,
+}
+
+impl LanBrowser {
+    pub fn new() -> std::io::Result<LanBrowser> {
+        Self::with_target(SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT)))
+    }
+
+    /// Sends requests to the given address instead of broadcasting them
+    pub fn with_target(target: SocketAddr) -> std::io::Result<LanBrowser> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            target,
+            initialized_at: Instant::now(),
+            last_request_at: None,
+            servers: Vec::new(),
+        })
+    }
+
+    /// Asks for servers once the interval elapsed & collects answers. Call every frame
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        if self
+            .last_request_at
+            .is_none_or(|at| now.duration_since(at) >= DISCOVERY_INTERVAL)
+        {
+            self.refresh();
+        }
+        let mut buf = [0u8; 512];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((n, server)) => match decode(&buf[..n]) {
+                    Ok(DiscoveryMessage::Announcement {
+                        sent_at,
+                        announcement,
+                    }) => self.add_server(server, sent_at, announcement),
+                    Ok(DiscoveryMessage::Request { .. }) => {}
+                    Err(err) => debug!("Ignoring packet of {server}: {err}"),
+                },
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    error!("Discovery socket error: {err}");
+                    break;
+                }
+            }
+        }
+        self.servers
+            .retain(|server| server.last_seen.elapsed() < SERVER_TIMEOUT);
+    }
+
+    /// Sends a discovery request right away
+    pub fn refresh(&mut self) {
+        self.last_request_at = Some(Instant::now());
+        let request = DiscoveryMessage::Request {
+            sent_at: self.initialized_at.elapsed().as_nanos(),
+        };
+        match encode(&request) {
+            Ok(bytes) => {
+                if let Err(err) = self.socket.send_to(&bytes, self.target) {
+                    error!("Failed to send discovery request: {err}");
+                }
+            }
+            Err(err) => error!("{err}"),
+        }
+    }
+
+    fn add_server(&mut self, from: SocketAddr, sent_at: u128, announcement: ServerAnnouncement) {
+        let ping = Duration::from_nanos(
+            self.initialized_at
+                .elapsed()
+                .as_nanos()
+                .saturating_sub(sent_at) as u64,
+        );
+        let address = SocketAddr::new(from.ip(), announcement.game_port);
+        let discovered = DiscoveredServer {
+            address,
+            announcement,
+            ping,
+            last_seen: Instant::now(),
+        };
+        match self
+            .servers
+            .iter_mut()
+            .find(|server| server.address == address)
+        {
+            Some(server) => *server = discovered,
+            None => self.servers.push(discovered),
+        }
+    }
+
+    /// Servers that answered recently, in order of discovery
+    pub fn servers(&self) -> &[DiscoveredServer] {
+        &self.servers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn announcement(players: usize) -> ServerAnnouncement {
+        ServerAnnouncement {
+            name: "Test".to_string(),
+            players,
+            mode: "pong".to_string(),
+            game_port: 7777,
+        }
+    }
+
+    #[test]
+    fn test_ignores_foreign_packets() {
+        assert!(decode(b"hello").is_err());
+        let bytes = encode(&DiscoveryMessage::Request { sent_at: 3 }).unwrap();
+        assert!(matches!(
+            decode(&bytes),
+            Ok(DiscoveryMessage::Request { sent_at: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_discover_server() {
+        let responder = DiscoveryResponder::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let mut browser = LanBrowser::with_target(responder.socket.local_addr().unwrap()).unwrap();
+        for players in [1, 2] {
+            browser.refresh();
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while Instant::now() < deadline
+                && browser
+                    .servers()
+                    .first()
+                    .is_none_or(|server| server.announcement.players != players)
+            {
+                responder.poll(&announcement(players));
+                browser.poll();
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        // Repeated answers update the entry
+        assert_eq!(browser.servers().len(), 1);
+        let server = &browser.servers()[0];
+        assert_eq!(server.announcement, announcement(2));
+        assert_eq!(server.address, SocketAddr::from(([127, 0, 0, 1], 7777)));
+        assert!(server.ping < Duration::from_secs(2));
+    }
+}
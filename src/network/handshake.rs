@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+/// Player names longer than this are rejected
+pub const MAX_PLAYER_NAME_LENGTH: usize = 24;
+
+/// Game protocol spoken on top of the transport. Clients are only accepted if theirs matches
+/// the one of the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolInfo {
+    /// Incremented whenever game messages change incompatibly
+    pub version: u32,
+    /// Encoding of game packets, e.g. bincode
+    pub codec: String,
+}
+
+impl ProtocolInfo {
+    pub fn new(version: u32, codec: &str) -> ProtocolInfo {
+        Self {
+            version,
+            codec: codec.to_string(),
+        }
+    }
+
+    /// Readable reason, if a client speaking the given protocol cannot be served
+    pub fn check_client(&self, client: &ProtocolInfo) -> Result<(), String> {
+        if client.version != self.version {
+            let outdated = if client.version < self.version {
+                "client"
+            } else {
+                "server"
+            };
+            return Err(format!(
+                "Server speaks protocol version {}, client version {}. Please update the {outdated}",
+                self.version, client.version
+            ));
+        }
+        if client.codec != self.codec {
+            return Err(format!(
+                "Server encodes packets as {}, client as {}",
+                self.codec, client.codec
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn check_player_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Player name must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_PLAYER_NAME_LENGTH {
+        return Err(format!(
+            "Player name is longer than {MAX_PLAYER_NAME_LENGTH} characters"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandshakeState {
+    // Sent, no response yet
+    Pending,
+    Accepted,
+    Rejected(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_client_protocol() {
+        let server = ProtocolInfo::new(2, "bincode");
+        assert!(
+            server
+                .check_client(&ProtocolInfo::new(2, "bincode"))
+                .is_ok()
+        );
+        let outdated = server
+            .check_client(&ProtocolInfo::new(1, "bincode"))
+            .unwrap_err();
+        assert!(outdated.contains("update the client"), "{outdated}");
+        let newer = server
+            .check_client(&ProtocolInfo::new(3, "bincode"))
+            .unwrap_err();
+        assert!(newer.contains("update the server"), "{newer}");
+        assert!(server.check_client(&ProtocolInfo::new(2, "json")).is_err());
+    }
+
+    #[test]
+    fn test_check_player_name() {
+        assert!(check_player_name("Squid").is_ok());
+        assert!(check_player_name(" ").is_err());
+        assert!(check_player_name(&"a".repeat(MAX_PLAYER_NAME_LENGTH + 1)).is_err());
+    }
+}
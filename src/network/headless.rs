@@ -3,48 +3,293 @@ use std::{
     time::{Duration, Instant},
 };
 
-use log::info;
+use log::{error, info, warn};
 
-use crate::{
-    config::{BROADCAST_DT, SIMULATION_DT},
-    scenes::scene::BaseScene,
-};
+use crate::{config::SIMULATION_DT, scenes::scene::BaseScene};
+
+/// Interval in which the achieved tick rate is reported
+const TICK_RATE_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+// Warn if achieved tick rate drops below this fraction of the target
+const TICK_RATE_WARN_THRESHOLD: f32 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimulationSpeed {
+    /// Wall clock scaled by factor. 1.0 is realtime
+    Scaled(f32),
+    /// Ticks back to back without sleeping, e.g. for tests
+    Unthrottled,
+}
+
+/// Counts ticks per wall clock interval
+struct TickRateMeter {
+    window_start: Instant,
+    ticks: u32,
+}
+
+impl TickRateMeter {
+    fn new(now: Instant) -> TickRateMeter {
+        Self {
+            window_start: now,
+            ticks: 0,
+        }
+    }
+
+    /// Returns ticks per second once the report interval elapsed
+    fn tick(&mut self, now: Instant) -> Option<f32> {
+        self.ticks += 1;
+        let elapsed = now - self.window_start;
+        if elapsed < TICK_RATE_REPORT_INTERVAL {
+            return None;
+        }
+        let rate = self.ticks as f32 / elapsed.as_secs_f32();
+        self.window_start = now;
+        self.ticks = 0;
+        Some(rate)
+    }
+}
+
+/// Converts scaled wall clock time into due ticks
+struct TickPacer {
+    tick_duration: Duration,
+    factor: f32,
+    last_instant: Instant,
+    // Simulated time not yet consumed by ticks
+    accumulator: Duration,
+}
+
+impl TickPacer {
+    fn new(now: Instant, tick_duration: Duration, factor: f32) -> TickPacer {
+        Self {
+            tick_duration,
+            factor,
+            last_instant: now,
+            accumulator: Duration::ZERO,
+        }
+    }
 
-/// Runs simulation of scene without rendering
+    /// Returns whether a tick is due at `now` & consumes it
+    fn consume_tick(&mut self, now: Instant) -> bool {
+        self.accumulator += (now - self.last_instant).mul_f32(self.factor);
+        self.last_instant = now;
+        if self.accumulator < self.tick_duration {
+            return false;
+        }
+        self.accumulator -= self.tick_duration;
+        true
+    }
+
+    /// Wall clock time until the next tick is due
+    fn time_until_next_tick(&self) -> Duration {
+        self.tick_duration
+            .saturating_sub(self.accumulator)
+            .div_f32(self.factor)
+    }
+}
+
+/// Runs simulation of scene without rendering at a fixed tick rate
 pub struct HeadlessSimulation {
     scene: Box<dyn BaseScene>,
+    tick_duration: Duration,
+    speed: SimulationSpeed,
+    ticks: u64,
+    entered: bool,
 }
 
 impl HeadlessSimulation {
     pub fn new(scene: Box<dyn BaseScene>) -> Self {
-        Self { scene }
+        Self {
+            scene,
+            tick_duration: SIMULATION_DT,
+            speed: SimulationSpeed::Scaled(1.0),
+            ticks: 0,
+            entered: false,
+        }
     }
 
-    /// Sleep for broadcast tick duration, then simulate multiple ticks
-    /// in one go
+    /// Simulation steps per second of simulated time. Defaults to SIMULATION_DT.
+    /// Pong requires the default: Its clients map server ticks to game time via SIMULATION_DT
+    pub fn with_tick_rate(mut self, ticks_per_second: u32) -> Self {
+        debug_assert!(ticks_per_second > 0);
+        self.tick_duration = Duration::from_secs(1) / ticks_per_second;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: SimulationSpeed) -> Self {
+        if let SimulationSpeed::Scaled(factor) = speed {
+            debug_assert!(factor > 0.0, "Simulation speed has to be positive");
+        }
+        self.speed = speed;
+        self
+    }
+
+    /// Number of ticks simulated so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Simulates until the process is terminated
     pub fn run(&mut self) {
-        info!("Starting headless simulation: {}", self.scene.get_title());
-        let mut last_instant = Instant::now();
-        let tick_duration = SIMULATION_DT;
-        let broadcast_duration = BROADCAST_DT;
+        self.run_ticks(None);
+    }
+
+    /// Simulates a fixed number of ticks, e.g. for tests
+    pub fn run_for(&mut self, ticks: u64) {
+        self.run_ticks(Some(ticks));
+    }
 
-        let mut tick_accumulator = Duration::ZERO;
+    fn run_ticks(&mut self, max_ticks: Option<u64>) {
+        if !self.entered {
+            self.scene.on_enter();
+            self.entered = true;
+        }
+        info!(
+            "Starting headless simulation: {} at {:.1}Hz, speed {:?}",
+            self.scene.get_title(),
+            1.0 / self.tick_duration.as_secs_f32(),
+            self.speed
+        );
+        let target_rate = match self.speed {
+            SimulationSpeed::Scaled(factor) => Some(factor / self.tick_duration.as_secs_f32()),
+            SimulationSpeed::Unthrottled => None,
+        };
+        let mut meter = TickRateMeter::new(Instant::now());
+        let mut pacer = match self.speed {
+            SimulationSpeed::Scaled(factor) => {
+                Some(TickPacer::new(Instant::now(), self.tick_duration, factor))
+            }
+            SimulationSpeed::Unthrottled => None,
+        };
+        let remaining = |ticks: u64| max_ticks.is_none_or(|max| ticks < max);
 
-        loop {
-            let now = Instant::now();
-            let delta = now - last_instant;
-            last_instant = now;
+        while remaining(self.ticks) {
+            if let Some(pacer) = &mut pacer
+                && !pacer.consume_tick(Instant::now())
+            {
+                // Sleep until next tick is due to avoid busy waiting
+                thread::sleep(pacer.time_until_next_tick());
+                continue;
+            }
 
-            tick_accumulator += delta;
+            if let Err(err) = self.scene.tick(self.tick_duration.as_secs_f32()) {
+                error!("Tick {} failed: {err}", self.ticks);
+            }
+            self.ticks += 1;
 
-            // Run simulation ticks for every tick_duration that has passed
-            while tick_accumulator >= tick_duration {
-                self.scene.tick(tick_duration.as_secs_f32());
-                tick_accumulator -= tick_duration;
+            if let Some(rate) = meter.tick(Instant::now()) {
+                match target_rate {
+                    Some(target) if rate < target * TICK_RATE_WARN_THRESHOLD => {
+                        warn!("Simulation falling behind: {rate:.1}Hz of {target:.1}Hz")
+                    }
+                    _ => info!("Simulation running at {rate:.1}Hz"),
+                }
+                self.scene.on_tick_rate_report(rate);
             }
+        }
+    }
+}
+
+impl Drop for HeadlessSimulation {
+    fn drop(&mut self) {
+        if self.entered {
+            self.scene.on_exit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use hecs::World;
+
+    use super::*;
+
+    struct CountingScene {
+        dts: Rc<RefCell<Vec<f32>>>,
+        lifecycle: Rc<RefCell<Vec<&'static str>>>,
+    }
 
-            // Sleep until next broadcast to avoid busy waiting
-            thread::sleep(broadcast_duration);
+    impl BaseScene for CountingScene {
+        fn get_world(&self) -> Option<&World> {
+            None
         }
+        fn get_title(&self) -> String {
+            "Counting".to_string()
+        }
+        fn tick(&mut self, dt: f32) -> Result<(), Box<dyn std::error::Error>> {
+            self.dts.borrow_mut().push(dt);
+            Ok(())
+        }
+        fn on_enter(&mut self) {
+            self.lifecycle.borrow_mut().push("enter");
+        }
+        fn on_exit(&mut self) {
+            self.lifecycle.borrow_mut().push("exit");
+        }
+    }
+
+    fn simulation(dts: &Rc<RefCell<Vec<f32>>>) -> HeadlessSimulation {
+        HeadlessSimulation::new(Box::new(CountingScene {
+            dts: Rc::clone(dts),
+            lifecycle: Rc::default(),
+        }))
+    }
+
+    #[test]
+    fn test_unthrottled_fixed_tick_rate() {
+        let dts = Rc::new(RefCell::new(Vec::new()));
+        let mut simulation = simulation(&dts)
+            .with_tick_rate(30)
+            .with_speed(SimulationSpeed::Unthrottled);
+        // 10s of simulated time
+        simulation.run_for(300);
+        assert_eq!(simulation.ticks(), 300);
+        assert!(dts.borrow().iter().all(|dt| (dt - 1.0 / 30.0).abs() < 1e-6));
+    }
+
+    fn due_ticks(pacer: &mut TickPacer, now: Instant) -> usize {
+        std::iter::from_fn(|| pacer.consume_tick(now).then_some(())).count()
+    }
+
+    #[test]
+    fn test_scaled_speed() {
+        // 100Hz at 2x speed
+        let start = Instant::now();
+        let mut pacer = TickPacer::new(start, Duration::from_millis(10), 2.0);
+        assert_eq!(due_ticks(&mut pacer, start), 0);
+        assert!(pacer.time_until_next_tick() <= Duration::from_millis(5));
+        // 100ms of simulated time
+        assert_eq!(due_ticks(&mut pacer, start + Duration::from_millis(50)), 10);
+        // Partial ticks carry over
+        assert_eq!(due_ticks(&mut pacer, start + Duration::from_millis(53)), 0);
+        assert_eq!(due_ticks(&mut pacer, start + Duration::from_millis(56)), 1);
+    }
+
+    #[test]
+    fn test_tick_rate_report() {
+        let start = Instant::now();
+        let mut meter = TickRateMeter::new(start);
+        for tick in 1..100 {
+            let now = start + TICK_RATE_REPORT_INTERVAL * tick / 100;
+            assert_eq!(meter.tick(now), None);
+        }
+        assert_eq!(meter.tick(start + TICK_RATE_REPORT_INTERVAL), Some(20.0));
+        // Next window starts at the report
+        assert_eq!(meter.tick(start + TICK_RATE_REPORT_INTERVAL * 2), Some(0.2));
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let lifecycle = Rc::new(RefCell::new(Vec::new()));
+        let mut simulation = HeadlessSimulation::new(Box::new(CountingScene {
+            dts: Rc::default(),
+            lifecycle: Rc::clone(&lifecycle),
+        }))
+        .with_speed(SimulationSpeed::Unthrottled);
+        simulation.run_for(2);
+        simulation.run_for(2);
+        assert_eq!(*lifecycle.borrow(), ["enter"]);
+        drop(simulation);
+        assert_eq!(*lifecycle.borrow(), ["enter", "exit"]);
     }
 }
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+use log::trace;
+
+use super::{ClientId, NetEntityId};
+
+pub const DEFAULT_INTEREST_RADIUS: f32 = 64.0;
+/// Entities have to move this much further than the radius to leave the interest area again
+pub const DEFAULT_INTEREST_HYSTERESIS: f32 = 8.0;
+
+/// Entities that became relevant / irrelevant for a client during the last update
+#[derive(Debug, Default, PartialEq)]
+pub struct InterestChanges {
+    pub entered: Vec<NetEntityId>,
+    pub left: Vec<NetEntityId>,
+}
+
+struct ClientInterest {
+    center: Vec3,
+    relevant: HashSet<NetEntityId>,
+}
+
+/// Per-client interest areas around their player. Only entities & chunk data within the area
+/// are sent to the client. Clients without interest area (e.g. spectators) receive everything
+pub struct InterestManager {
+    pub radius: f32,
+    pub hysteresis: f32,
+    clients: HashMap<ClientId, ClientInterest>,
+}
+
+impl InterestManager {
+    pub fn new(radius: f32, hysteresis: f32) -> InterestManager {
+        Self {
+            radius,
+            hysteresis,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Moves interest area of client, e.g. to the position of its player
+    pub fn set_center(&mut self, client: ClientId, center: Vec3) {
+        self.clients
+            .entry(client)
+            .and_modify(|interest| interest.center = center)
+            .or_insert_with(|| ClientInterest {
+                center,
+                relevant: HashSet::new(),
+            });
+    }
+
+    pub fn remove_client(&mut self, client: ClientId) {
+        self.clients.remove(&client);
+    }
+
+    /// Re-evaluates which entities are relevant for the client. Entities missing from the
+    /// iterator are considered despawned
+    pub fn update(
+        &mut self,
+        client: ClientId,
+        entities: impl IntoIterator<Item = (NetEntityId, Vec3)>,
+    ) -> InterestChanges {
+        let mut changes = InterestChanges::default();
+        let Some(interest) = self.clients.get_mut(&client) else {
+            return changes;
+        };
+        let enter_distance_sq = self.radius * self.radius;
+        let leave_distance = self.radius + self.hysteresis;
+        let leave_distance_sq = leave_distance * leave_distance;
+
+        let mut relevant = HashSet::with_capacity(interest.relevant.len());
+        for (net_entity_id, position) in entities {
+            let distance_sq = position.distance_squared(interest.center);
+            // Between enter & leave distance entities keep their previous state
+            let threshold = if interest.relevant.contains(&net_entity_id) {
+                leave_distance_sq
+            } else {
+                enter_distance_sq
+            };
+            if distance_sq <= threshold {
+                relevant.insert(net_entity_id);
+            }
+        }
+        changes.entered = relevant.difference(&interest.relevant).copied().collect();
+        changes.left = interest.relevant.difference(&relevant).copied().collect();
+        if !changes.entered.is_empty() || !changes.left.is_empty() {
+            trace!("Interest of {client} changed: {changes:?}");
+        }
+        interest.relevant = relevant;
+        changes
+    }
+
+    /// Whether updates of an entity should be sent to the client
+    pub fn is_relevant(&self, client: ClientId, net_entity_id: NetEntityId) -> bool {
+        self.clients
+            .get(&client)
+            .is_none_or(|interest| interest.relevant.contains(&net_entity_id))
+    }
+
+    /// Whether data of a region, e.g. a chunk or voxel edit, should be sent to the client.
+    /// Extent is the radius of the region's bounding sphere
+    pub fn is_region_relevant(&self, client: ClientId, center: Vec3, extent: f32) -> bool {
+        self.clients.get(&client).is_none_or(|interest| {
+            interest.center.distance(center) - extent <= self.radius + self.hysteresis
+        })
+    }
+}
+
+impl Default for InterestManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTEREST_RADIUS, DEFAULT_INTEREST_HYSTERESIS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test_client;
+
+    #[test]
+    fn test_enter_and_leave_with_hysteresis() {
+        let mut interest = InterestManager::new(10.0, 2.0);
+        interest.set_center(test_client(1), Vec3::ZERO);
+
+        let changes = interest.update(test_client(1), [(0, Vec3::X * 5.0), (1, Vec3::X * 11.0)]);
+        assert_eq!(changes.entered, vec![0]);
+        assert!(changes.left.is_empty());
+
+        // Entity 0 moves into the hysteresis band & stays relevant,
+        // entity 1 stays outside since it has not been relevant before
+        let changes = interest.update(test_client(1), [(0, Vec3::X * 11.0), (1, Vec3::X * 11.0)]);
+        assert_eq!(changes, InterestChanges::default());
+        assert!(interest.is_relevant(test_client(1), 0));
+        assert!(!interest.is_relevant(test_client(1), 1));
+
+        let changes = interest.update(test_client(1), [(0, Vec3::X * 13.0)]);
+        assert_eq!(changes.left, vec![0]);
+        assert!(!interest.is_relevant(test_client(1), 0));
+    }
+
+    #[test]
+    fn test_client_without_interest_area_receives_everything() {
+        let mut interest = InterestManager::default();
+        assert!(interest.is_relevant(test_client(1), 42));
+        assert!(interest.is_region_relevant(test_client(1), Vec3::splat(1e6), 1.0));
+        assert_eq!(
+            interest.update(test_client(1), [(0, Vec3::ZERO)]),
+            InterestChanges::default()
+        );
+    }
+
+    #[test]
+    fn test_region_relevance_includes_extent() {
+        let mut interest = InterestManager::new(10.0, 0.0);
+        interest.set_center(test_client(1), Vec3::ZERO);
+        assert!(!interest.is_region_relevant(test_client(1), Vec3::X * 20.0, 5.0));
+        assert!(interest.is_region_relevant(test_client(1), Vec3::X * 20.0, 10.0));
+    }
+}
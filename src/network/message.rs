@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::ClientId;
+use super::{ClientId, ProtocolInfo, SessionToken};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) enum NetworkMessage {
@@ -14,5 +14,27 @@ pub(super) enum NetworkMessage {
     },
     GamePacket {
         payload: Vec<u8>,
+        // Sender clock in nanoseconds
+        sent_at: u128,
+    },
+    /// Client asks to observe instead of play. Spectators receive game packets, but the server
+    /// drops any they send
+    SpectateRequest,
+    SpectateResponse {
+        accepted: bool,
+        reason: Option<String>,
+    },
+    /// Client leaves. Lets the server drop it without waiting for the inactivity timeout
+    Disconnect,
+    /// Sent by clients until answered. Game packets are dropped until the server accepted it
+    Handshake {
+        protocol: ProtocolInfo,
+        player_name: String,
+    },
+    HandshakeResponse {
+        accepted: bool,
+        reason: Option<String>,
+        // Has to be included in all following packets of the client
+        session: Option<SessionToken>,
     },
 }
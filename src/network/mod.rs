@@ -1,7 +1,15 @@
+mod auth;
+mod bandwidth;
 mod client;
+mod clock_sync;
+mod delta;
+mod discovery;
+mod handshake;
 mod headless;
+mod interest;
 mod message;
 mod meter;
+mod rewind;
 mod server;
 mod snapshot;
 mod time_sync;
@@ -15,9 +23,38 @@ pub struct NetworkReplicated {
     pub authority: Authority,
 }
 
+/// Loopback address of a test client, distinguished by port
+#[cfg(test)]
+pub(crate) fn test_client(port: u16) -> ClientId {
+    ClientId::from(([127, 0, 0, 1], port))
+}
+
+pub use auth::PreSharedKey;
+pub use auth::SessionToken;
+pub use bandwidth::BandwidthUsage;
+pub use bandwidth::PacketPriority;
+pub use client::ClientDownstreamPayload;
 pub use client::NetworkClient;
-pub use headless::HeadlessSimulation;
+pub use client::SpectateState;
+pub use clock_sync::ClockSync;
+pub use delta::DeltaDecoder;
+pub use delta::DeltaEncoder;
+pub use delta::DeltaSnapshot;
+pub use discovery::DISCOVERY_PORT;
+pub use discovery::DiscoveredServer;
+pub use discovery::LanBrowser;
+pub use discovery::ServerAnnouncement;
+pub use handshake::HandshakeState;
+pub use handshake::MAX_PLAYER_NAME_LENGTH;
+pub use handshake::ProtocolInfo;
+pub use headless::{HeadlessSimulation, SimulationSpeed};
+pub use interest::InterestChanges;
+pub use interest::InterestManager;
+pub use rewind::DEFAULT_REWIND_TICKS;
+pub use rewind::RewindHistory;
+pub use rewind::RewindHit;
 pub use server::ClientId;
+pub use server::DEFAULT_MAX_SPECTATORS;
 pub use server::NetworkServer;
 pub use server::ServerDownstreamPayload;
 pub use server::ServerEvent;
@@ -27,3 +64,5 @@ pub use snapshot::SnapshotManager;
 pub use time_sync::TimeSync;
 pub use world::NetEntityId;
 pub use world::NetworkWorld;
+// Entity serialization is shared with save games
+pub use crate::systems::serialization::{ComponentRegistry, SerializedEntity, WorldSnapshot};
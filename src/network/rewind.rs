@@ -0,0 +1,215 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+use crate::{
+    collision::{ColliderBody, Ray},
+    octree::AABB,
+    systems::physics::Transform,
+};
+
+use super::{NetEntityId, NetworkReplicated, NetworkWorld};
+
+/// Ticks kept by default. One second at the simulation rate
+pub const DEFAULT_REWIND_TICKS: usize = 60;
+
+struct RecordedTick {
+    server_tick: u32,
+    bounds: Vec<(NetEntityId, AABB)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RewindHit {
+    pub net_entity_id: NetEntityId,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+/// Server side ring buffer of past collider bounds of replicated entities. Hit queries are
+/// rewound to the tick the firing client saw, so high latency players hit what they aimed at
+pub struct RewindHistory {
+    capacity: usize,
+    ticks: VecDeque<RecordedTick>,
+}
+
+impl RewindHistory {
+    pub fn new(capacity: usize) -> RewindHistory {
+        Self {
+            capacity: capacity.max(1),
+            ticks: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Stores the current bounds of all replicated colliders. Drops the oldest tick if full
+    pub fn record(&mut self, world: &NetworkWorld, server_tick: u32) {
+        let bounds = world
+            .get_world()
+            .query::<(&Transform, &ColliderBody)>()
+            .with::<&NetworkReplicated>()
+            .iter()
+            .filter_map(|(entity, (transform, collider))| {
+                let net_entity_id = world.get_net_entity_id(&entity)?;
+                Some((*net_entity_id, collider_bounds(transform, collider)))
+            })
+            .collect();
+        if self.ticks.len() == self.capacity {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(RecordedTick {
+            server_tick,
+            bounds,
+        });
+    }
+
+    /// E.g. once the server tick restarts
+    pub fn clear(&mut self) {
+        self.ticks.clear();
+    }
+
+    /// Latest recorded tick at or before the requested one. Requests older than the history are
+    /// clamped to the oldest tick, limiting how far clients can reach into the past
+    fn rewound(&self, server_tick: u32) -> Option<&RecordedTick> {
+        self.ticks
+            .iter()
+            .rev()
+            .find(|recorded| recorded.server_tick <= server_tick)
+            .or(self.ticks.front())
+    }
+
+    /// Closest entity hit by the ray as positioned at the given tick
+    pub fn raycast(
+        &self,
+        server_tick: u32,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        ignore: Option<NetEntityId>,
+    ) -> Option<RewindHit> {
+        let recorded = self.rewound(server_tick)?;
+        let direction = direction.try_normalize()?;
+        let ray = Ray::new(origin, direction);
+        recorded
+            .bounds
+            .iter()
+            .filter(|(net_entity_id, _)| Some(*net_entity_id) != ignore)
+            .filter_map(|(net_entity_id, bounds)| {
+                let (distance, _normal) = ray.intersect_aabb(bounds)?;
+                // Origin inside the collider
+                let distance = distance.max(0.0);
+                (distance <= max_distance).then_some(RewindHit {
+                    net_entity_id: *net_entity_id,
+                    distance,
+                    point: origin + direction * distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+// Bounding box of the collider. Spheres & capsules are approximated by their enclosing box
+fn collider_bounds(transform: &Transform, collider: &ColliderBody) -> AABB {
+    let center = transform.0.w_axis.truncate();
+    let size = match *collider {
+        ColliderBody::AabbCollider { scale } => scale,
+        ColliderBody::SphereCollider { radius } => Vec3::splat(radius * 2.0),
+        ColliderBody::CapsuleCollider { radius, height } => {
+            Vec3::new(radius * 2.0, height + radius * 2.0, radius * 2.0)
+        }
+    };
+    AABB::from_center_and_scale(&center, &size)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+    use hecs::Entity;
+
+    use crate::network::Authority;
+
+    use super::*;
+
+    fn spawn_target(world: &mut NetworkWorld, position: Vec3) -> (NetEntityId, Entity) {
+        world.spawn(
+            (
+                Transform(Mat4::from_translation(position)),
+                ColliderBody::SphereCollider { radius: 0.5 },
+                NetworkReplicated {
+                    authority: Authority::Server,
+                },
+            ),
+            None,
+        )
+    }
+
+    fn move_to(world: &mut NetworkWorld, entity: Entity, position: Vec3) {
+        world
+            .get_world_mut()
+            .get::<&mut Transform>(entity)
+            .unwrap()
+            .0 = Mat4::from_translation(position);
+    }
+
+    #[test]
+    fn test_raycast_hits_past_position() {
+        let mut world = NetworkWorld::new();
+        let (target_id, target) = spawn_target(&mut world, Vec3::new(10.0, 0.0, 0.0));
+        let mut history = RewindHistory::new(DEFAULT_REWIND_TICKS);
+        for tick in 0..10 {
+            move_to(&mut world, target, Vec3::new(10.0, tick as f32, 0.0));
+            history.record(&world, tick);
+        }
+        let shoot = |tick| history.raycast(tick, Vec3::new(0.0, 2.0, 0.0), Vec3::X, 50.0, None);
+
+        // Client saw the target at tick 2, by now it moved away
+        let hit = shoot(2).unwrap();
+        assert_eq!(hit.net_entity_id, target_id);
+        assert_eq!(hit.distance, 9.5);
+        assert_eq!(hit.point, Vec3::new(9.5, 2.0, 0.0));
+        assert!(shoot(9).is_none());
+        // Ignoring the shooter
+        assert!(
+            history
+                .raycast(2, Vec3::new(0.0, 2.0, 0.0), Vec3::X, 50.0, Some(target_id))
+                .is_none()
+        );
+        // Out of range
+        assert!(
+            history
+                .raycast(2, Vec3::new(0.0, 2.0, 0.0), Vec3::X, 5.0, None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_rewind_limited_to_history() {
+        let mut world = NetworkWorld::new();
+        let (_, target) = spawn_target(&mut world, Vec3::ZERO);
+        let mut history = RewindHistory::new(3);
+        assert!(
+            history
+                .raycast(0, Vec3::NEG_X, Vec3::X, 5.0, None)
+                .is_none()
+        );
+        for tick in 0..5 {
+            move_to(&mut world, target, Vec3::Y * tick as f32);
+            history.record(&world, tick);
+        }
+        // Tick 0 & 1 were dropped, clamped to tick 2
+        assert!(
+            history
+                .raycast(0, Vec3::NEG_X, Vec3::X, 5.0, None)
+                .is_none()
+        );
+        assert!(
+            history
+                .raycast(0, Vec3::new(-1.0, 2.0, 0.0), Vec3::X, 5.0, None)
+                .is_some()
+        );
+        history.clear();
+        assert!(
+            history
+                .raycast(4, Vec3::new(-1.0, 4.0, 0.0), Vec3::X, 5.0, None)
+                .is_none()
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     net::{SocketAddr, UdpSocket},
     sync::{
         Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, Sender},
     },
     thread,
@@ -11,24 +12,46 @@ use std::{
 
 use log::{debug, error, info, trace};
 
-use crate::{log_err, network::message::NetworkMessage};
+use crate::{log_err, network::message::NetworkMessage, util::MemoryTag};
+
+use super::{
+    ProtocolInfo,
+    auth::{self, NO_SESSION, PreSharedKey, SessionToken, new_session_token},
+    bandwidth::{BandwidthUsage, PacketPriority, SendQueue, kbps_to_bytes_per_second},
+    discovery::{DISCOVERY_PORT, DiscoveryResponder, ServerAnnouncement},
+    handshake::check_player_name,
+};
 
 /// Interval in which the server checks for inactive clients
 const INACTIVE_CLIENT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 /// Duration elapsed since the last successful ping to an active client for it to be considered
 /// inactive
 const INACTIVE_CLIENT_TIMEOUT_DURATION: Duration = Duration::from_secs(3);
+/// Spectators accepted at the same time unless configured otherwise
+pub const DEFAULT_MAX_SPECTATORS: usize = 4;
+/// Interval in which the bandwidth usage of all clients is logged
+const BANDWIDTH_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 pub type ClientId = SocketAddr;
 
 pub struct ServerDownstreamPayload {
     bytes: Vec<u8>,
     client: Option<ClientId>,
+    priority: PacketPriority,
 }
 
 impl ServerDownstreamPayload {
     pub fn new(bytes: Vec<u8>, client: Option<ClientId>) -> ServerDownstreamPayload {
-        Self { bytes, client }
+        Self {
+            bytes,
+            client,
+            priority: PacketPriority::Reliable,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: PacketPriority) -> ServerDownstreamPayload {
+        self.priority = priority;
+        self
     }
 }
 
@@ -47,28 +70,322 @@ impl ServerUpstreamPayload {
 pub enum ServerEvent {
     ClientConnected(ClientId),
     ClientDisconnected(ClientId),
+    /// Client observes the game. Either newly connected or previously connected as player
+    SpectatorJoined(ClientId),
+    SpectatorLeft(ClientId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClientRole {
+    Player,
+    // Receives game packets, game packets sent by spectators are dropped
+    Spectator,
 }
 
 struct ClientInfo {
     last_ping_received: Instant,
+    role: ClientRole,
+    // Set once the handshake was accepted
+    player_name: Option<String>,
+    // Issued on handshake. Packets carrying a different token are dropped
+    session: Option<SessionToken>,
+    // Outgoing game packets, limited by the max send rate
+    send_queue: SendQueue,
+}
+
+impl ClientInfo {
+    fn new(role: ClientRole) -> ClientInfo {
+        Self {
+            last_ping_received: Instant::now(),
+            role,
+            player_name: None,
+            session: None,
+            send_queue: SendQueue::new(),
+        }
+    }
+}
+
+/// Connected clients & their roles. Shared between server and communication thread
+struct ClientRegistry {
+    clients: HashMap<ClientId, ClientInfo>,
+    max_spectators: usize,
+    // Clients have to speak this protocol. Any client is accepted if None
+    protocol: Option<ProtocolInfo>,
+    // Send budget per client. Unlimited if None
+    max_bytes_per_second: Option<u64>,
+}
+
+impl ClientRegistry {
+    fn new(max_spectators: usize) -> ClientRegistry {
+        Self {
+            clients: HashMap::new(),
+            max_spectators,
+            protocol: None,
+            max_bytes_per_second: None,
+        }
+    }
+
+    /// Queues sealed packet for the next flush. False if the client is unknown
+    fn enqueue(&mut self, client: ClientId, bytes: Vec<u8>, priority: PacketPriority) -> bool {
+        match self.clients.get_mut(&client) {
+            Some(info) => {
+                info.send_queue.push(bytes, priority);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Packets of all clients that fit into their send budgets
+    fn flush(&mut self, now: Instant) -> Vec<(ClientId, Vec<u8>)> {
+        let max_bytes_per_second = self.max_bytes_per_second;
+        self.clients
+            .iter_mut()
+            .flat_map(|(client, info)| {
+                info.send_queue
+                    .flush(now, max_bytes_per_second)
+                    .into_iter()
+                    .map(|bytes| (*client, bytes))
+            })
+            .collect()
+    }
+
+    fn bandwidth_usage(&self) -> Vec<(ClientId, BandwidthUsage)> {
+        let mut usage: Vec<(ClientId, BandwidthUsage)> = self
+            .clients
+            .iter()
+            .map(|(client, info)| (*client, info.send_queue.usage()))
+            .collect();
+        usage.sort_by_key(|(client, _)| *client);
+        usage
+    }
+
+    /// Accepts a client speaking the server protocol. Unknown clients are registered as players.
+    /// Returns the session of the client. Repeated handshakes keep the issued session
+    fn register_handshake(
+        &mut self,
+        client: ClientId,
+        protocol: &ProtocolInfo,
+        player_name: String,
+    ) -> Result<SessionToken, String> {
+        if let Some(expected) = &self.protocol {
+            expected.check_client(protocol)?;
+        }
+        check_player_name(&player_name)?;
+        let info = self
+            .clients
+            .entry(client)
+            .or_insert_with(|| ClientInfo::new(ClientRole::Player));
+        info.player_name = Some(player_name);
+        let session = match info.session {
+            Some(session) => session,
+            None => new_session_token()?,
+        };
+        info.session = Some(session);
+        Ok(session)
+    }
+
+    /// Clients without issued session may send any token
+    fn check_session(&self, client: ClientId, session: SessionToken) -> Result<(), String> {
+        match self.clients.get(&client).and_then(|info| info.session) {
+            Some(expected) if expected != session => {
+                Err(format!("Invalid session token of {client}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn session(&self, client: ClientId) -> SessionToken {
+        self.clients
+            .get(&client)
+            .and_then(|info| info.session)
+            .unwrap_or(NO_SESSION)
+    }
+
+    /// Players that completed the handshake, unless the server accepts any client
+    fn accepts_game_packets(&self, client: ClientId) -> bool {
+        match self.clients.get(&client) {
+            Some(info) if info.role == ClientRole::Player => {
+                self.protocol.is_none() || info.player_name.is_some()
+            }
+            _ => self.protocol.is_none(),
+        }
+    }
+
+    fn player_name(&self, client: ClientId) -> Option<String> {
+        self.clients.get(&client)?.player_name.clone()
+    }
+
+    /// Refreshes last ping of client. Unknown clients are registered as players.
+    /// Returns true if the client is new
+    fn register_ping(&mut self, client: ClientId) -> bool {
+        match self.clients.get_mut(&client) {
+            Some(info) => {
+                info.last_ping_received = Instant::now();
+                false
+            }
+            None => {
+                self.clients
+                    .insert(client, ClientInfo::new(ClientRole::Player));
+                true
+            }
+        }
+    }
+
+    /// Registers client as spectator, unless spectator limit is reached.
+    /// Returns true if the client was not a spectator before
+    fn register_spectator(&mut self, client: ClientId) -> Result<bool, String> {
+        if self.role(client) == Some(ClientRole::Spectator) {
+            return Ok(false);
+        }
+        if self.spectators().len() >= self.max_spectators {
+            return Err(format!(
+                "Spectator limit of {} reached",
+                self.max_spectators
+            ));
+        }
+        // Keeps the player name of the handshake
+        self.clients
+            .entry(client)
+            .or_insert_with(|| ClientInfo::new(ClientRole::Spectator))
+            .role = ClientRole::Spectator;
+        Ok(true)
+    }
+
+    fn role(&self, client: ClientId) -> Option<ClientRole> {
+        self.clients.get(&client).map(|info| info.role)
+    }
+
+    fn player_count(&self) -> usize {
+        self.clients
+            .values()
+            .filter(|info| info.role == ClientRole::Player)
+            .count()
+    }
+
+    fn spectators(&self) -> Vec<ClientId> {
+        self.clients
+            .iter()
+            .filter(|(_, info)| info.role == ClientRole::Spectator)
+            .map(|(client, _)| *client)
+            .collect()
+    }
+
+    /// Removes a client that left. Returns its role if it was connected
+    fn remove(&mut self, client: ClientId) -> Option<ClientRole> {
+        self.clients.remove(&client).map(|info| info.role)
+    }
+
+    /// Removes clients that have not pinged within timeout
+    fn remove_inactive(&mut self, timeout: Duration) -> Vec<(ClientId, ClientRole)> {
+        let inactive: Vec<(ClientId, ClientRole)> = self
+            .clients
+            .iter()
+            .filter(|(_, info)| info.last_ping_received.elapsed() > timeout)
+            .map(|(client, info)| (*client, info.role))
+            .collect();
+        for (client, _) in &inactive {
+            self.clients.remove(client);
+        }
+        inactive
+    }
 }
 
 /// Transport layer for server-client communication
 pub struct NetworkServer {
-    connected_clients: Arc<Mutex<HashMap<ClientId, ClientInfo>>>,
+    connected_clients: Arc<Mutex<ClientRegistry>>,
+    pre_shared_key: Option<PreSharedKey>,
     downstream_tx: Option<Sender<ServerDownstreamPayload>>,
     event_rx: Option<Receiver<ServerEvent>>,
+    // Port game packets are served on. Set by serve
+    game_port: Option<u16>,
+    // Communication thread stops & releases the socket once false
+    running: Arc<AtomicBool>,
 }
 
 impl NetworkServer {
     pub fn new() -> Self {
         Self {
-            connected_clients: Arc::new(Mutex::new(HashMap::new())),
+            connected_clients: Arc::new(Mutex::new(ClientRegistry::new(DEFAULT_MAX_SPECTATORS))),
+            pre_shared_key: None,
             downstream_tx: None,
             event_rx: None,
+            game_port: None,
+            running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Stops the communication thread. The socket is closed once the thread exits
+    pub fn shutdown(&self) {
+        if self.running.swap(false, Ordering::AcqRel) {
+            info!("Server shutting down");
         }
     }
 
+    /// Maximum number of concurrent spectators. Does not affect already joined spectators
+    pub fn set_max_spectators(&mut self, max_spectators: usize) {
+        self.connected_clients.lock().unwrap().max_spectators = max_spectators;
+    }
+
+    /// Clients currently observing the game
+    pub fn spectators(&self) -> Vec<ClientId> {
+        self.connected_clients.lock().unwrap().spectators()
+    }
+
+    /// Clients speaking a different protocol are rejected during the handshake
+    pub fn set_protocol(&mut self, protocol: ProtocolInfo) {
+        self.connected_clients.lock().unwrap().protocol = Some(protocol);
+    }
+
+    /// Limits the bytes sent to each client. Entity updates are dropped first once a client
+    /// exceeds the limit. Unlimited if None
+    pub fn set_max_send_kbps(&mut self, kbps: Option<u32>) {
+        self.connected_clients.lock().unwrap().max_bytes_per_second =
+            kbps.map(kbps_to_bytes_per_second);
+    }
+
+    /// Bytes sent per second, dropped & queued packets of each client
+    pub fn bandwidth_usage(&self) -> Vec<(ClientId, BandwidthUsage)> {
+        self.connected_clients.lock().unwrap().bandwidth_usage()
+    }
+
+    /// Packets not signed with this key are dropped. Has to be set before [Self::serve]
+    pub fn set_pre_shared_key(&mut self, key: PreSharedKey) {
+        self.pre_shared_key = Some(key);
+    }
+
+    /// Name sent by the client during the handshake
+    pub fn player_name(&self, client: ClientId) -> Option<String> {
+        self.connected_clients.lock().unwrap().player_name(client)
+    }
+
+    /// Answers discovery requests of LAN browsers with name, mode & player count until the
+    /// server shuts down. Has to be called after [Self::serve]
+    pub fn announce_on_lan(&self, name: &str, mode: &str) -> std::io::Result<()> {
+        let game_port = self.game_port.ok_or(std::io::Error::other(
+            "Server has to serve before announcing",
+        ))?;
+        let responder = DiscoveryResponder::bind(SocketAddr::from(([0, 0, 0, 0], DISCOVERY_PORT)))?;
+        info!("Announcing {name} on LAN discovery port {DISCOVERY_PORT}");
+        let mut announcement = ServerAnnouncement {
+            name: name.to_string(),
+            players: 0,
+            mode: mode.to_string(),
+            game_port,
+        };
+        let clients = Arc::clone(&self.connected_clients);
+        let running = Arc::clone(&self.running);
+        thread::spawn(move || {
+            let _memory = MemoryTag::Network.enter();
+            while running.load(Ordering::Acquire) {
+                announcement.players = clients.lock().unwrap().player_count();
+                responder.poll(&announcement);
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        Ok(())
+    }
+
     pub fn send_game_packet(&self, payload: ServerDownstreamPayload) -> Result<(), String> {
         debug_assert!(
             self.downstream_tx.is_some(),
@@ -97,6 +414,7 @@ impl NetworkServer {
     ) -> std::io::Result<()> {
         let socket = UdpSocket::bind(server_address)?;
         socket.set_nonblocking(true)?;
+        self.game_port = Some(socket.local_addr()?.port());
         info!("Server listening at {server_address}");
 
         // Communication thread
@@ -107,40 +425,66 @@ impl NetworkServer {
         self.downstream_tx = Some(downstream_tx);
         let upstream_tx_thread = upstream_tx.clone();
         self.event_rx = Some(event_rx);
+        self.running.store(true, Ordering::Release);
+        let running = Arc::clone(&self.running);
+        let socket = SealedSocket {
+            socket,
+            key: self.pre_shared_key.clone(),
+        };
         thread::spawn(move || {
+            let _memory = MemoryTag::Network.enter();
             let mut buf = [0u8; 1024];
             let mut last_inactive_client_check_at = Instant::now();
-            loop {
+            let mut last_bandwidth_log_at = Instant::now();
+            while running.load(Ordering::Acquire) {
                 // Encode & Send queued downstream game packets
                 while let Ok(payload) = downstream_rx.try_recv() {
                     // Wrap into network message
                     let packet = NetworkMessage::GamePacket {
                         payload: payload.bytes,
+                        sent_at: initialized_at.elapsed().as_nanos(),
                     };
-                    match bincode::serialize(&packet) {
-                        Ok(bytes) => match payload.client {
-                            Some(client) => {
-                                trace!("Sending message to single client {client}");
-                                socket.send_to(&bytes, client).unwrap();
-                            }
-                            None => {
-                                trace!("Broadcasting message");
-                                for (client_id, ..) in clients.lock().unwrap().iter() {
-                                    socket.send_to(&bytes, client_id).unwrap();
-                                }
+                    let mut registry = clients.lock().unwrap();
+                    let receivers = match payload.client {
+                        Some(client) => {
+                            trace!("Sending message to single client {client}");
+                            vec![client]
+                        }
+                        None => {
+                            trace!("Broadcasting message");
+                            registry.clients.keys().copied().collect()
+                        }
+                    };
+                    // Sealed per client, as each has its own session
+                    for client in receivers {
+                        let bytes = match socket.seal(&packet, registry.session(client)) {
+                            Ok(bytes) => bytes,
+                            Err(err) => {
+                                error!("Failed to seal game packet: {err}");
+                                continue;
                             }
-                        },
-                        Err(err) => {
-                            error!("Failed to serialize game packet: {err}");
-                            continue;
+                        };
+                        if !registry.enqueue(client, bytes.clone(), payload.priority) {
+                            // Not registered yet, so no budget to respect
+                            log_err!(
+                                socket.send_bytes(&bytes, client),
+                                "Failed to send game packet: {err}"
+                            );
                         }
                     }
                 }
+                let flushed = clients.lock().unwrap().flush(Instant::now());
+                for (client, bytes) in flushed {
+                    log_err!(
+                        socket.send_bytes(&bytes, client),
+                        "Failed to send game packet: {err}"
+                    );
+                }
 
                 // Upstream communication: Packets that a client has sent to the server
                 // Read network packages: Client -> Server = upstream communication
                 loop {
-                    match socket.recv_from(&mut buf) {
+                    match socket.socket.recv_from(&mut buf) {
                         Ok((n, client_address)) => {
                             let payload = &buf[..n];
                             log_err!(
@@ -169,23 +513,31 @@ impl NetworkServer {
 
                 // Check for inactive clients
                 if last_inactive_client_check_at.elapsed() > INACTIVE_CLIENT_CHECK_INTERVAL {
-                    let mut clients_mutex = clients.lock().unwrap();
-                    let mut inactive_clients: Vec<ClientId> = vec![];
-                    for (k, v) in clients_mutex.iter() {
-                        if v.last_ping_received.elapsed() > INACTIVE_CLIENT_TIMEOUT_DURATION {
-                            inactive_clients.push(*k);
-                        }
-                    }
-                    for client in inactive_clients {
-                        debug!("Removing inactive client {client}");
-                        clients_mutex.remove(&client);
+                    let inactive_clients = clients
+                        .lock()
+                        .unwrap()
+                        .remove_inactive(INACTIVE_CLIENT_TIMEOUT_DURATION);
+                    for (client, role) in inactive_clients {
+                        debug!("Removed inactive client {client}");
                         event_tx
-                            .send(ServerEvent::ClientDisconnected(client))
+                            .send(leave_event(client, role))
                             .expect("Unable to send disconnect event");
                     }
                     last_inactive_client_check_at = Instant::now();
                 }
 
+                if last_bandwidth_log_at.elapsed() > BANDWIDTH_LOG_INTERVAL {
+                    for (client, usage) in clients.lock().unwrap().bandwidth_usage() {
+                        info!(
+                            "Client {client}: {:.1} kbps, {} dropped, {} queued packets",
+                            usage.bytes_per_second as f32 * 8.0 / 1000.0,
+                            usage.dropped_packets,
+                            usage.queued_packets
+                        );
+                    }
+                    last_bandwidth_log_at = Instant::now();
+                }
+
                 // Throttle CPU
                 thread::sleep(Duration::from_millis(1));
             }
@@ -201,61 +553,291 @@ impl Default for NetworkServer {
     }
 }
 
+fn leave_event(client: ClientId, role: ClientRole) -> ServerEvent {
+    match role {
+        ClientRole::Player => ServerEvent::ClientDisconnected(client),
+        ClientRole::Spectator => ServerEvent::SpectatorLeft(client),
+    }
+}
+
+// Signs outgoing & verifies incoming packets, if a pre-shared key is configured
+struct SealedSocket {
+    socket: UdpSocket,
+    key: Option<PreSharedKey>,
+}
+
+impl SealedSocket {
+    fn seal(&self, message: &NetworkMessage, session: SessionToken) -> Result<Vec<u8>, String> {
+        auth::seal(message, session, self.key.as_ref())
+    }
+
+    fn send_to(
+        &self,
+        message: &NetworkMessage,
+        client: ClientId,
+        session: SessionToken,
+    ) -> Result<(), String> {
+        self.send_bytes(&self.seal(message, session)?, client)
+    }
+
+    // Sealed bytes
+    fn send_bytes(&self, bytes: &[u8], client: ClientId) -> Result<(), String> {
+        self.socket
+            .send_to(bytes, client)
+            .map_err(|err| format!("Failed to send to {client}: {err}"))?;
+        Ok(())
+    }
+
+    fn open(&self, bytes: &[u8]) -> Result<(SessionToken, NetworkMessage), String> {
+        auth::open(bytes, self.key.as_ref())
+    }
+}
+
 /// Wrapper layer around network packets to separate concerns of
 /// - Network packets such as ping-pong and
 /// - Game packets -> Handed to channel and game implementation to process
 fn process_received_bytes(
-    socket: &UdpSocket,
+    socket: &SealedSocket,
     initialized_at: &Instant,
     payload: &[u8],
-    clients: &Arc<Mutex<HashMap<ClientId, ClientInfo>>>,
+    clients: &Arc<Mutex<ClientRegistry>>,
     client_address: SocketAddr,
     upstream_tx: &Sender<ServerUpstreamPayload>,
     server_event_tx: &Sender<ServerEvent>,
 ) -> Result<(), String> {
-    let network_message: NetworkMessage = bincode::deserialize(payload)
-        .map_err(|err| format!("Failed to decode into NetworkMessage: {err}"))?;
+    let (session, network_message) = socket.open(payload)?;
+    // Handshakes are accepted without session, as they issue it
+    if !matches!(network_message, NetworkMessage::Handshake { .. }) {
+        clients
+            .lock()
+            .unwrap()
+            .check_session(client_address, session)?;
+    }
     match network_message {
         NetworkMessage::Ping { client_timestamp } => {
-            {
-                let mut lock = clients.lock().unwrap();
-                match lock.get_mut(&client_address) {
-                    Some(info) => info.last_ping_received = Instant::now(),
-                    None => {
-                        lock.insert(
-                            client_address,
-                            ClientInfo {
-                                last_ping_received: Instant::now(),
-                            },
-                        );
-                        server_event_tx
-                            .send(ServerEvent::ClientConnected(client_address))
-                            .expect("Unable to send client connect event");
-                    }
-                }
+            if clients.lock().unwrap().register_ping(client_address) {
+                server_event_tx
+                    .send(ServerEvent::ClientConnected(client_address))
+                    .expect("Unable to send client connect event");
             }
             let response = NetworkMessage::Pong {
                 client_id: client_address,
                 client_timestamp,
                 server_uptime: initialized_at.elapsed().as_nanos(),
             };
-            let encoded = bincode::serialize(&response)
-                .map_err(|err| format!("Unable to serialize pong: {err}"))?;
             socket
-                .send_to(&encoded, client_address)
-                .map_err(|err| format!("Unable to send pong: {err}"))?;
-            Ok(())
+                .send_to(&response, client_address, session)
+                .map_err(|err| format!("Unable to send pong: {err}"))
         }
         NetworkMessage::Pong { .. } => {
             Err("Server received pong. This should never happen".to_string())
         }
-        NetworkMessage::GamePacket { payload } => {
+        NetworkMessage::GamePacket { payload, .. } => {
+            if clients.lock().unwrap().role(client_address) == Some(ClientRole::Spectator) {
+                return Err(format!(
+                    "Dropping game packet of spectator {client_address}"
+                ));
+            }
+            if !clients.lock().unwrap().accepts_game_packets(client_address) {
+                return Err(format!(
+                    "Dropping game packet of {client_address}: No accepted handshake"
+                ));
+            }
             // Game packets are handed to upstream channel
             upstream_tx
                 .send(ServerUpstreamPayload::new(payload.to_vec(), client_address))
                 .map_err(|err| format!("Unable to forward upstream payload: {err}"))?;
             Ok(())
         }
+        NetworkMessage::SpectateRequest => {
+            let result = clients.lock().unwrap().register_spectator(client_address);
+            if let Ok(true) = result {
+                server_event_tx
+                    .send(ServerEvent::SpectatorJoined(client_address))
+                    .expect("Unable to send spectator joined event");
+            }
+            let response = NetworkMessage::SpectateResponse {
+                accepted: result.is_ok(),
+                reason: result.err(),
+            };
+            socket
+                .send_to(&response, client_address, session)
+                .map_err(|err| format!("Unable to send spectate response: {err}"))
+        }
+        NetworkMessage::SpectateResponse { .. } => {
+            Err("Server received spectate response. This should never happen".to_string())
+        }
+        NetworkMessage::Handshake {
+            protocol,
+            player_name,
+        } => {
+            let result = clients.lock().unwrap().register_handshake(
+                client_address,
+                &protocol,
+                player_name.clone(),
+            );
+            match &result {
+                Ok(_) => info!("Client {client_address} joined as {player_name}"),
+                Err(reason) => info!("Rejected client {client_address}: {reason}"),
+            }
+            let session = *result.as_ref().unwrap_or(&NO_SESSION);
+            let response = NetworkMessage::HandshakeResponse {
+                accepted: result.is_ok(),
+                session: result.as_ref().ok().copied(),
+                reason: result.err(),
+            };
+            socket
+                .send_to(&response, client_address, session)
+                .map_err(|err| format!("Unable to send handshake response: {err}"))
+        }
+        NetworkMessage::HandshakeResponse { .. } => {
+            Err("Server received handshake response. This should never happen".to_string())
+        }
+        NetworkMessage::Disconnect => {
+            if let Some(role) = clients.lock().unwrap().remove(client_address) {
+                debug!("Client {client_address} disconnected");
+                server_event_tx
+                    .send(leave_event(client_address, role))
+                    .expect("Unable to send disconnect event");
+            }
+            Ok(())
+        }
     }?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test_client;
+
+    #[test]
+    fn test_spectator_limit() {
+        let mut registry = ClientRegistry::new(1);
+        assert_eq!(registry.register_spectator(test_client(1)), Ok(true));
+        // Repeated request is accepted without counting twice
+        assert_eq!(registry.register_spectator(test_client(1)), Ok(false));
+        assert!(registry.register_spectator(test_client(2)).is_err());
+        assert_eq!(registry.spectators(), vec![test_client(1)]);
+    }
+
+    #[test]
+    fn test_player_becomes_spectator() {
+        let mut registry = ClientRegistry::new(1);
+        assert!(registry.register_ping(test_client(1)));
+        assert!(!registry.register_ping(test_client(1)));
+        assert_eq!(registry.role(test_client(1)), Some(ClientRole::Player));
+        assert_eq!(registry.register_spectator(test_client(1)), Ok(true));
+        assert_eq!(registry.role(test_client(1)), Some(ClientRole::Spectator));
+        // Pings keep role
+        registry.register_ping(test_client(1));
+        assert_eq!(registry.role(test_client(1)), Some(ClientRole::Spectator));
+    }
+
+    #[test]
+    fn test_remove_inactive_reports_role() {
+        let mut registry = ClientRegistry::new(1);
+        registry.register_ping(test_client(1));
+        registry.register_spectator(test_client(2)).unwrap();
+        assert!(registry.remove_inactive(Duration::from_secs(60)).is_empty());
+        let mut removed = registry.remove_inactive(Duration::ZERO);
+        removed.sort_by_key(|(client, _)| client.port());
+        assert_eq!(
+            removed,
+            vec![
+                (test_client(1), ClientRole::Player),
+                (test_client(2), ClientRole::Spectator)
+            ]
+        );
+        assert!(registry.spectators().is_empty());
+    }
+
+    #[test]
+    fn test_handshake() {
+        let mut registry = ClientRegistry::new(1);
+        registry.register_ping(test_client(1));
+        // Servers without protocol accept anyone
+        assert!(registry.accepts_game_packets(test_client(1)));
+
+        registry.protocol = Some(ProtocolInfo::new(1, "bincode"));
+        assert!(!registry.accepts_game_packets(test_client(1)));
+        assert!(
+            registry
+                .register_handshake(test_client(1), &ProtocolInfo::new(0, "bincode"), "a".into())
+                .is_err()
+        );
+        assert!(!registry.accepts_game_packets(test_client(1)));
+        let session = registry
+            .register_handshake(test_client(1), &ProtocolInfo::new(1, "bincode"), "a".into())
+            .unwrap();
+        assert!(registry.accepts_game_packets(test_client(1)));
+        // Resent handshake keeps the session
+        assert_eq!(
+            registry.register_handshake(
+                test_client(1),
+                &ProtocolInfo::new(1, "bincode"),
+                "a".into()
+            ),
+            Ok(session)
+        );
+        assert_eq!(registry.player_name(test_client(1)), Some("a".to_string()));
+
+        // Name is kept when switching to spectating
+        registry.register_spectator(test_client(1)).unwrap();
+        assert!(!registry.accepts_game_packets(test_client(1)));
+        assert_eq!(registry.player_name(test_client(1)), Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_session_check() {
+        let mut registry = ClientRegistry::new(1);
+        // Unknown & clients without session are not checked
+        assert!(registry.check_session(test_client(1), NO_SESSION).is_ok());
+        registry.register_ping(test_client(1));
+        assert!(registry.check_session(test_client(1), NO_SESSION).is_ok());
+
+        let session = registry
+            .register_handshake(test_client(1), &ProtocolInfo::new(1, "bincode"), "a".into())
+            .unwrap();
+        assert_eq!(registry.session(test_client(1)), session);
+        assert!(registry.check_session(test_client(1), session).is_ok());
+        assert!(registry.check_session(test_client(1), NO_SESSION).is_err());
+        assert!(registry.check_session(test_client(1), session ^ 1).is_err());
+        assert_eq!(registry.session(test_client(2)), NO_SESSION);
+    }
+
+    #[test]
+    fn test_send_budget_per_client() {
+        let mut registry = ClientRegistry::new(1);
+        assert!(!registry.enqueue(test_client(1), vec![0; 10], PacketPriority::Reliable));
+        registry.register_ping(test_client(1));
+        registry.register_ping(test_client(2));
+        registry.max_bytes_per_second = Some(100);
+        let now = Instant::now();
+        for client in [test_client(1), test_client(2)] {
+            assert!(registry.enqueue(client, vec![0; 120], PacketPriority::Reliable));
+            assert!(registry.enqueue(client, vec![0; 80], PacketPriority::Unreliable));
+        }
+        // Each client has its own budget
+        let mut flushed: Vec<ClientId> = registry
+            .flush(now)
+            .into_iter()
+            .map(|(client, _)| client)
+            .collect();
+        flushed.sort();
+        assert_eq!(flushed, vec![test_client(1), test_client(2)]);
+        let usage = registry.bandwidth_usage();
+        assert_eq!(usage.len(), 2);
+        assert!(usage.iter().all(|(_, usage)| usage.dropped_packets == 1));
+    }
+
+    #[test]
+    fn test_remove_disconnected_client() {
+        let mut registry = ClientRegistry::new(1);
+        registry.register_spectator(test_client(1)).unwrap();
+        assert_eq!(registry.remove(test_client(1)), Some(ClientRole::Spectator));
+        // Repeated disconnect is ignored
+        assert_eq!(registry.remove(test_client(1)), None);
+        assert!(registry.spectators().is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::{collections::HashSet, time::Duration};
 
 use glam::Mat4;
 use log::{debug, error, trace, warn};
@@ -10,7 +10,7 @@ use crate::{
 };
 
 use super::{
-    Authority, ClientId, NetEntityId, NetworkReplicated, NetworkWorld, time_sync::TimeSync,
+    Authority, ClientId, DeltaDecoder, DeltaSnapshot, NetEntityId, NetworkReplicated, NetworkWorld,
 };
 
 #[derive(Debug)]
@@ -42,6 +42,9 @@ pub struct SnapshotManager {
     snapshot_buffer: [Option<Snapshot>; SNAP_BUFFER_SIZE],
     head: usize,
     render_server_time: Duration,
+    decoder: DeltaDecoder,
+    // Dropped from the snapshots, i.e. outside of the interest area of this client
+    out_of_interest: HashSet<NetEntityId>,
 }
 
 impl SnapshotManager {
@@ -50,11 +53,38 @@ impl SnapshotManager {
             snapshot_buffer: std::array::from_fn(|_| None),
             head: 0,
             render_server_time: Duration::ZERO,
+            decoder: DeltaDecoder::new(),
+            out_of_interest: HashSet::new(),
         }
     }
 
+    /// Reconstructs full snapshot from delta & stores it for interpolation
+    pub fn store_delta(&mut self, frame: u32, delta: &DeltaSnapshot) -> Result<(), String> {
+        let data = self.decoder.decode(frame, delta)?;
+        self.store_snapshot(frame, data);
+        Ok(())
+    }
+
+    /// Server tick of the latest snapshot. Acknowledged to the server as delta baseline
+    pub fn last_received_tick(&self) -> Option<u32> {
+        self.decoder.last_received_tick()
+    }
+
     pub fn store_snapshot(&mut self, frame: u32, data: Vec<EntitySnapshot>) {
         debug!("Storing snapshot at {frame}");
+        // Snapshots contain all entities relevant for the client
+        let latest = (self.head + SNAP_BUFFER_SIZE - 1) % SNAP_BUFFER_SIZE;
+        if let Some(previous) = &self.snapshot_buffer[latest] {
+            let left = previous.snapshots.iter().filter(|previous| {
+                data.binary_search_by_key(&previous.net_entity_id, |e| e.net_entity_id)
+                    .is_err()
+            });
+            self.out_of_interest
+                .extend(left.map(|snapshot| snapshot.net_entity_id));
+        }
+        for snapshot in &data {
+            self.out_of_interest.remove(&snapshot.net_entity_id);
+        }
         let server_ingame_time = frame * SIMULATION_DT;
         self.snapshot_buffer[self.head] = Some(Snapshot::new(server_ingame_time, data));
         self.head = (self.head + 1) % SNAP_BUFFER_SIZE;
@@ -86,19 +116,18 @@ impl SnapshotManager {
         Some((a, b, alpha.clamp(0.0, 1.0)))
     }
 
-    /// Update interpolated entities (marked with NetworkReplicated) with snapshot data available
+    /// Update interpolated entities (marked with NetworkReplicated) with snapshot data available.
+    /// Server time is the current game time on the shared timeline, see [super::TimeSync]
     pub fn tick(
         &mut self,
         world: &mut NetworkWorld,
         client_id: ClientId,
-        time_sync: &TimeSync,
+        server_time: Duration,
         dt: Duration,
     ) {
         // Increase render server time linear with dt timestep size & snap back if goes out of sync
         // too far with estimated server time
-        let target_server_time = time_sync
-            .server_time_at(Instant::now())
-            .saturating_sub(INTERPOLATION_DELAY);
+        let target_server_time = server_time.saturating_sub(INTERPOLATION_DELAY);
         if self.render_server_time.abs_diff(target_server_time) >= BROADCAST_DT * 2 {
             warn!(
                 "Render time ({:?}+) too far off from estimated server time ({:?}) -> Snapping back",
@@ -138,6 +167,12 @@ impl SnapshotManager {
                         trace!("Updating transform for net {net_entity_id} to {snap}");
                         transform.0 = snap;
                     }
+                    None if self.out_of_interest.contains(net_entity_id) => {
+                        // Entity outside of interest area: Keep last known transform
+                        trace!(
+                            "No snapshot information for entity {entity:?}, net_entity_id {net_entity_id}"
+                        );
+                    }
                     None => {
                         error!(
                             "Could not interpolate transform. Probably missing snapshot information for entity {entity:?}, net_entity_id {net_entity_id}"
@@ -183,3 +218,35 @@ fn lerp_optional(a: Option<Mat4>, b: Option<Mat4>, t: f32) -> Option<Mat4> {
 fn lerp_mat4(a: Mat4, b: Mat4, t: f32) -> Mat4 {
     a + (b - a) * t
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn snapshots(ids: &[NetEntityId]) -> Vec<EntitySnapshot> {
+        ids.iter()
+            .map(|&net_entity_id| EntitySnapshot {
+                net_entity_id,
+                transform: Transform(Mat4::from_translation(Vec3::X * net_entity_id as f32)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_entities_dropped_from_snapshots_are_out_of_interest() {
+        let mut manager = SnapshotManager::new();
+        manager.store_snapshot(1, snapshots(&[0, 1]));
+        assert!(manager.out_of_interest.is_empty());
+
+        // Entity 1 left the interest area
+        manager.store_snapshot(2, snapshots(&[0]));
+        assert!(manager.out_of_interest.contains(&1));
+        assert!(!manager.out_of_interest.contains(&0));
+
+        // & entered it again
+        manager.store_snapshot(3, snapshots(&[0, 1]));
+        assert!(manager.out_of_interest.is_empty());
+    }
+}
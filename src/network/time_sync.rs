@@ -1,40 +1,68 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::debug;
 
+// Offset errors larger than this are applied instantly, e.g. after the server restarted its ticks
+const RESYNC_THRESHOLD: Duration = Duration::from_millis(100);
+// Exp smoothing to avoid jitter
+const SMOOTHING: f64 = 0.1;
+
+/// Maps the game timeline (server tick * SIMULATION_DT) onto the server clock.
+/// Game packets carry the server clock time they were sent at, so no latency estimation is
+/// required here. Combined with [super::ClockSync] this yields the current game time on clients
 pub struct TimeSync {
-    last_update_server_time: Duration,
-    last_update_client_instant: Instant,
+    // Game time - server clock time in nanoseconds
+    offset_nanos: Option<f64>,
 }
 
 impl TimeSync {
     pub fn new() -> Self {
-        Self {
-            last_update_server_time: Duration::ZERO,
-            last_update_client_instant: Instant::now(),
-        }
+        Self { offset_nanos: None }
     }
 
-    pub fn update(
-        &mut self,
-        snapshot_server_time: Duration,
-        receive_instant: Instant,
-        rtt: Duration,
-    ) {
-        let estimated_server_now = snapshot_server_time + rtt / 2;
-        // Exp smoothing to avoid jitter
-        let alpha = 0.1;
-        self.last_update_server_time =
-            self.last_update_server_time.mul_f32(1.0 - alpha) + estimated_server_now.mul_f32(alpha);
-
-        self.last_update_client_instant = receive_instant;
-        debug!(
-            "Time sync update: serverTime {:?}, receiveInstant {:?}, offset is now {:?} ",
-            snapshot_server_time, receive_instant, self.last_update_server_time
-        );
+    /// Game time of a packet & server clock time it was sent at
+    pub fn update(&mut self, game_time: Duration, sent_at: Duration) {
+        let sample = game_time.as_nanos() as f64 - sent_at.as_nanos() as f64;
+        let offset = match self.offset_nanos {
+            Some(current) if (sample - current).abs() < RESYNC_THRESHOLD.as_nanos() as f64 => {
+                current + (sample - current) * SMOOTHING
+            }
+            _ => sample,
+        };
+        debug!("Time sync update: game time {game_time:?}, sent at {sent_at:?}");
+        self.offset_nanos = Some(offset);
+    }
+
+    /// Game time at a point in time on the server clock
+    pub fn game_time_at(&self, server_time: Duration) -> Duration {
+        let game_nanos = server_time.as_nanos() as f64 + self.offset_nanos.unwrap_or_default();
+        Duration::from_nanos(game_nanos.max(0.0) as u64)
     }
+}
 
-    pub fn server_time_at(&self, now: Instant) -> Duration {
-        self.last_update_server_time + now.duration_since(self.last_update_client_instant)
+impl Default for TimeSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resync_after_tick_reset() {
+        let mut time_sync = TimeSync::new();
+        time_sync.update(Duration::from_secs(10), Duration::from_secs(60));
+        assert_eq!(
+            time_sync.game_time_at(Duration::from_secs(61)),
+            Duration::from_secs(11)
+        );
+        // Server restarted game time at 0
+        time_sync.update(Duration::ZERO, Duration::from_secs(70));
+        assert_eq!(
+            time_sync.game_time_at(Duration::from_secs(71)),
+            Duration::from_secs(1)
+        );
     }
 }
@@ -1,8 +1,15 @@
 use std::collections::HashMap;
 
+use glam::Vec3;
 use hecs::{DynamicBundle, Entity, Query, World};
 use log::debug;
 
+use crate::systems::{
+    physics::Transform,
+    serialization::{ComponentRegistry, SerializedEntity},
+};
+
+use super::NetworkReplicated;
 
 pub type NetEntityId = u32;
 
@@ -77,6 +84,35 @@ impl NetworkWorld {
         self.network_to_local.get(&net_entity_id)
     }
 
+    /// Positions of all replicated entities, e.g. to update interest areas
+    pub fn replicated_positions(&self) -> Vec<(NetEntityId, Vec3)> {
+        self.world
+            .query::<&Transform>()
+            .with::<&NetworkReplicated>()
+            .iter()
+            .filter_map(|(entity, transform)| {
+                let net_entity_id = self.local_to_network.get(&entity)?;
+                Some((*net_entity_id, transform.0.w_axis.truncate()))
+            })
+            .collect()
+    }
+
+    /// Registered components of a replicated entity, e.g. to spawn it on a late joining client
+    pub fn serialize_entity(
+        &self,
+        registry: &ComponentRegistry,
+        net_entity_id: NetEntityId,
+    ) -> Result<Option<SerializedEntity>, String> {
+        let entity = self
+            .get_entity_id(net_entity_id)
+            .ok_or(format!("Unknown net entity {net_entity_id}"))?;
+        let entity = self
+            .world
+            .entity(*entity)
+            .map_err(|_| "Mapped entity id not found in ecs.".to_string())?;
+        registry.serialize_entity(&entity)
+    }
+
     pub fn despawn_net_id(&mut self, net_entity_id: u32) -> Result<(), String> {
         let entity = self
             .network_to_local
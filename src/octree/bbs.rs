@@ -55,9 +55,54 @@ impl AABB {
             && self.max.y >= other.max.y
             && self.max.z >= other.max.z
     }
+
+    pub fn contains_point(&self, point: &Vec3) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    /// Smallest BB containing both
+    pub fn union(&self, other: &AABB) -> AABB {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Grows the BB by amount in every direction
+    pub fn inflate(&self, amount: f32) -> AABB {
+        AABB::new(
+            self.min - Vec3::splat(amount),
+            self.max + Vec3::splat(amount),
+        )
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Full size along every axis
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Point is returned as is if inside the BB
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        point.clamp(self.min, self.max)
+    }
+
+    /// 0 if the point is inside the BB
+    pub fn distance_to_point(&self, point: &Vec3) -> f32 {
+        self.closest_point(point).distance(*point)
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl From<&IAabb> for AABB {
+    fn from(other: &IAabb) -> Self {
+        AABB::new(other.min.as_vec3(), other.max.as_vec3())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct IAabb {
     pub min: IVec3,
     pub max: IVec3,
@@ -118,6 +163,51 @@ impl IAabb {
             && self.max.z >= other.max.z
     }
 
+    /// Cell semantics: max is exclusive
+    pub fn contains_point(&self, point: &IVec3) -> bool {
+        point.cmpge(self.min).all() && point.cmplt(self.max).all()
+    }
+
+    /// Smallest BB containing both
+    pub fn union(&self, other: &IAabb) -> IAabb {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Grows the BB by amount in every direction
+    pub fn inflate(&self, amount: i32) -> IAabb {
+        IAabb::new_rect(self.min - amount, self.max + amount)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max).as_vec3() * 0.5
+    }
+
+    /// Full size along every axis
+    pub fn extent(&self) -> IVec3 {
+        self.max - self.min
+    }
+
+    /// Point is returned as is if inside the BB
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        point.clamp(self.min.as_vec3(), self.max.as_vec3())
+    }
+
+    /// 0 if the point is inside the BB
+    pub fn distance_to_point(&self, point: &Vec3) -> f32 {
+        self.closest_point(point).distance(*point)
+    }
+
+    /// All integer cells within the BB, x varying fastest
+    pub fn iter_cells(&self) -> impl Iterator<Item = IVec3> + use<> {
+        let (min, max) = (self.min, self.max);
+        (min.z..max.z).flat_map(move |z| {
+            (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+
     pub fn _area(&self) -> i32 {
         (self.max.x - self.min.x) * (self.max.y - self.min.y) * (self.max.z - self.min.z)
     }
@@ -141,9 +231,9 @@ impl From<&AABB> for IAabb {
 
 #[cfg(test)]
 mod tests {
-    use glam::Vec3;
+    use glam::{IVec3, Vec3};
 
-    use crate::octree::bbs::AABB;
+    use crate::octree::bbs::{AABB, IAabb};
 
     #[test]
     fn test_intersection_true() {
@@ -165,4 +255,71 @@ mod tests {
         let b = AABB::new_center(&(Vec3::ONE * 2.0), 1.0);
         assert!(!a.intersects(&b));
     }
+
+    #[test]
+    fn test_union_and_inflate() {
+        let a = AABB::new_center(&Vec3::ZERO, 1.0);
+        let b = AABB::new_center(&Vec3::new(3.0, 0.0, 0.0), 1.0);
+        let union = a.union(&b);
+        assert_eq!(union.min, Vec3::splat(-0.5));
+        assert_eq!(union.max, Vec3::new(3.5, 0.5, 0.5));
+        assert_eq!(union.center(), Vec3::new(1.5, 0.0, 0.0));
+        assert_eq!(union.extent(), Vec3::new(4.0, 1.0, 1.0));
+        assert!(union.contains(&a) && union.contains(&b));
+
+        let inflated = a.inflate(0.5);
+        assert_eq!(inflated.extent(), Vec3::splat(2.0));
+        assert_eq!(inflated.center(), a.center());
+
+        let i = IAabb::new(&IVec3::ZERO, 2).union(&IAabb::new(&IVec3::splat(4), 1));
+        assert_eq!(i, IAabb::new(&IVec3::ZERO, 5));
+        assert_eq!(i.inflate(1), IAabb::new(&IVec3::splat(-1), 7));
+        assert_eq!(i.extent(), IVec3::splat(5));
+        assert_eq!(i.center(), Vec3::splat(2.5));
+    }
+
+    #[test]
+    fn test_closest_point_and_distance() {
+        let a = AABB::new_center(&Vec3::ZERO, 2.0);
+        assert_eq!(
+            a.closest_point(&Vec3::new(0.5, 0.0, 0.0)),
+            Vec3::new(0.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            a.closest_point(&Vec3::new(3.0, 4.0, 0.0)),
+            Vec3::new(1.0, 1.0, 0.0)
+        );
+        assert_eq!(a.distance_to_point(&Vec3::ZERO), 0.0);
+        assert_eq!(a.distance_to_point(&Vec3::new(4.0, 5.0, 0.0)), 5.0);
+        assert!(a.contains_point(&Vec3::ONE));
+        assert!(!a.contains_point(&Vec3::new(1.1, 0.0, 0.0)));
+
+        let i = IAabb::new(&IVec3::ZERO, 2);
+        assert_eq!(i.distance_to_point(&Vec3::new(5.0, 6.0, 1.0)), 5.0);
+        assert!(i.contains_point(&IVec3::ONE));
+        assert!(!i.contains_point(&IVec3::new(2, 0, 0)));
+    }
+
+    #[test]
+    fn test_iter_cells() {
+        let i = IAabb::new_rect(IVec3::new(-1, 0, 0), IVec3::new(1, 2, 3));
+        let cells: Vec<IVec3> = i.iter_cells().collect();
+        assert_eq!(cells.len() as i32, i._area());
+        assert_eq!(cells[0], IVec3::new(-1, 0, 0));
+        assert_eq!(cells[1], IVec3::new(0, 0, 0));
+        assert!(cells.iter().all(|cell| i.contains_point(cell)));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let f = AABB::new(Vec3::new(-0.5, 0.2, 1.0), Vec3::new(1.5, 1.0, 2.7));
+        let i = IAabb::from(&f);
+        assert_eq!(
+            i,
+            IAabb::new_rect(IVec3::new(-1, 0, 1), IVec3::new(2, 1, 3))
+        );
+        let back = AABB::from(&i);
+        assert!(back.contains(&f));
+        assert_eq!(IAabb::from(&back), i);
+    }
 }
@@ -1,11 +1,88 @@
 use glam::IVec3;
 
-use super::node::OctreeNode;
+use super::{IAabb, Octree, node::OctreeNode};
 
-pub(super) struct StackItem<'a, T> {
-    pub(super) node: &'a OctreeNode<T>,
-    pub(super) origin: IVec3,
-    pub(super) size: usize,
+/// Deepest supported tree. Size 2^32 already exceeds the i32 tree space
+const MAX_DEPTH: usize = 32;
+
+struct Frame<'a, T> {
+    node: &'a OctreeNode<T>,
+    origin: IVec3,
+    size: usize,
+    // Index of the next child to visit
+    next_child: usize,
+}
+
+/// Depth first traversal of all leafs intersecting a region. Only keeps the path to the current
+/// node on a fixed size stack, so iterating does not allocate
+pub(super) struct LeafTraversal<'a, T> {
+    // Inner nodes on the path to the current node. Leafs are never pushed
+    stack: [Option<Frame<'a, T>>; MAX_DEPTH],
+    depth: usize,
+    // Set if the root itself is a leaf within the region
+    root_leaf: Option<(&'a OctreeNode<T>, IVec3)>,
+    region: IAabb,
+}
+
+impl<'a, T> LeafTraversal<'a, T> {
+    pub(super) fn new(region_tree_space: IAabb, octree: &'a Octree<T>) -> Self {
+        let mut traversal = Self {
+            stack: std::array::from_fn(|_| None),
+            depth: 0,
+            root_leaf: None,
+            region: region_tree_space,
+        };
+        traversal.root_leaf = traversal.visit(&octree.root, octree.origin, octree.size);
+        traversal
+    }
+
+    // Returns leafs within the region. Inner nodes within the region are pushed instead
+    fn visit(
+        &mut self,
+        node: &'a OctreeNode<T>,
+        origin: IVec3,
+        size: usize,
+    ) -> Option<(&'a OctreeNode<T>, IVec3)> {
+        if !IAabb::new(&origin, size).intersects(&self.region) {
+            return None;
+        }
+        if node.is_leaf() {
+            return Some((node, origin));
+        }
+        assert!(self.depth < MAX_DEPTH, "Octree exceeds max traversal depth");
+        self.stack[self.depth] = Some(Frame {
+            node,
+            origin,
+            size,
+            next_child: 0,
+        });
+        self.depth += 1;
+        None
+    }
+
+    /// Next leaf within the region & its origin in tree space
+    pub(super) fn next_leaf(&mut self) -> Option<(&'a OctreeNode<T>, IVec3)> {
+        if let Some(leaf) = self.root_leaf.take() {
+            return Some(leaf);
+        }
+        while self.depth > 0 {
+            let frame = self.stack[self.depth - 1].as_mut().unwrap();
+            if frame.next_child == 8 {
+                self.depth -= 1;
+                continue;
+            }
+            let index = frame.next_child;
+            frame.next_child += 1;
+            let node = frame.node;
+            let child_origin = get_child_origin(&frame.origin, frame.size, index);
+            let child_size = frame.size / 2;
+            let child = node.children.as_ref().unwrap()[index].as_ref();
+            if let Some(leaf) = self.visit(child, child_origin, child_size) {
+                return Some(leaf);
+            }
+        }
+        None
+    }
 }
 
 pub(super) fn get_child_origin(parent_origin: &IVec3, size: usize, index: usize) -> IVec3 {
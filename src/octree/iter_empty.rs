@@ -1,22 +1,19 @@
 use glam::IVec3;
 
-use super::{IAabb, Octree, iter_commons::StackItem, iter_commons::get_child_origin};
+use super::{IAabb, Octree, iter_commons::LeafTraversal};
 use std::fmt::Debug;
 
 pub struct OctreeEmptyNodeIterator<'a, T> {
-    stack: Vec<StackItem<'a, T>>,
-    region: IAabb,
+    traversal: LeafTraversal<'a, T>,
 }
 
 impl<'a, T> OctreeEmptyNodeIterator<'a, T> {
-    pub(super) fn new(region_tree_space: IAabb, octree: &Octree<T>) -> OctreeEmptyNodeIterator<T> {
+    pub(super) fn new(
+        region_tree_space: IAabb,
+        octree: &'a Octree<T>,
+    ) -> OctreeEmptyNodeIterator<'a, T> {
         OctreeEmptyNodeIterator {
-            region: region_tree_space,
-            stack: vec![StackItem {
-                node: &octree.root,
-                origin: octree.origin,
-                size: octree.size,
-            }],
+            traversal: LeafTraversal::new(region_tree_space, octree),
         }
     }
 }
@@ -28,26 +25,9 @@ where
     type Item = IVec3;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(item) = self.stack.pop() {
-            let current_boundary = IAabb::new(&item.origin, item.size);
-            if !current_boundary.intersects(&self.region) {
-                continue;
-            }
-            let node = item.node;
-            if node.is_leaf() {
-                if node.data.is_none() {
-                    return Some(item.origin);
-                }
-            } else {
-                // Recursion push all children to the stack
-                for (index, child) in node.children.as_ref().unwrap().iter().enumerate() {
-                    let child_origin = get_child_origin(&item.origin, item.size, index);
-                    self.stack.push(StackItem {
-                        node: child.as_ref(),
-                        origin: child_origin,
-                        size: item.size / 2,
-                    });
-                }
+        while let Some((node, origin)) = self.traversal.next_leaf() {
+            if node.data.is_none() {
+                return Some(origin);
             }
         }
         None
@@ -1,23 +1,17 @@
-use super::{
-    IAabb, Octree,
-    iter_commons::{StackItem, get_child_origin},
-};
+use super::{IAabb, Octree, iter_commons::LeafTraversal};
 use std::fmt::Debug;
 
 pub struct OctreeNodeIterator<'a, T> {
-    stack: Vec<StackItem<'a, T>>,
-    region: IAabb,
+    traversal: LeafTraversal<'a, T>,
 }
 
 impl<'a, T> OctreeNodeIterator<'a, T> {
-    pub(super) fn new(region_tree_space: IAabb, octree: &Octree<T>) -> OctreeNodeIterator<T> {
+    pub(super) fn new(
+        region_tree_space: IAabb,
+        octree: &'a Octree<T>,
+    ) -> OctreeNodeIterator<'a, T> {
         OctreeNodeIterator {
-            region: region_tree_space,
-            stack: vec![StackItem {
-                node: &octree.root,
-                origin: octree.origin,
-                size: octree.size,
-            }],
+            traversal: LeafTraversal::new(region_tree_space, octree),
         }
     }
 }
@@ -29,26 +23,9 @@ where
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(item) = self.stack.pop() {
-            let current_boundary = IAabb::new(&item.origin, item.size);
-            if !current_boundary.intersects(&self.region) {
-                continue;
-            }
-            // Recursion push all children to the stack
-            let node = item.node;
-            if node.is_leaf() {
-                if let Some(data) = node.data.as_ref() {
-                    return Some(data);
-                }
-            } else {
-                for (index, child) in node.children.as_ref().unwrap().iter().enumerate() {
-                    let child_origin = get_child_origin(&item.origin, item.size, index);
-                    self.stack.push(StackItem {
-                        node: child.as_ref(),
-                        origin: child_origin,
-                        size: item.size / 2,
-                    });
-                }
+        while let Some((node, _origin)) = self.traversal.next_leaf() {
+            if let Some(data) = node.data.as_ref() {
+                return Some(data);
             }
         }
         None
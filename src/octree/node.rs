@@ -58,6 +58,17 @@ where
         child[index].insert(x % half, y % half, z % half, half as usize, data);
     }
 
+    // These x,y,z coordinates are local to the current node
+    pub(super) fn remove(&mut self, x: i32, y: i32, z: i32, size: usize) -> Option<T> {
+        if size == 1 {
+            return self.data.take();
+        }
+        let half = (size / 2) as i32;
+        let index = get_child_index(x, y, z, half);
+        let children = self.children.as_mut()?;
+        children[index].remove(x % half, y % half, z % half, half as usize)
+    }
+
     #[cfg(test)]
     // These x,y,z coordinates are local to the current node
     pub(super) fn get(&mut self, x: i32, y: i32, z: i32, size: usize) -> Option<T> {
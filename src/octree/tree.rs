@@ -58,6 +58,15 @@ where
         );
     }
 
+    /// Removes data at tree space position. Emptied node is reported by the empty iterator again
+    pub fn remove(&mut self, pos_tree_space: IVec3) -> Option<T> {
+        let local = pos_tree_space - self.origin;
+        if local.min_element() < 0 || local.max_element() >= self.size as i32 {
+            return None;
+        }
+        self.root.remove(local.x, local.y, local.z, self.size)
+    }
+
     pub fn get_size(&self) -> usize {
         self.size
     }
@@ -105,7 +114,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::{collections::HashSet, time::Instant};
 
     use glam::IVec3;
 
@@ -189,6 +198,21 @@ mod tests {
         assert_eq!(result[0].a, 1);
     }
 
+    #[test]
+    fn test_remove() {
+        let mut root = Octree::new(4);
+        root.insert(IVec3::new(1, 1, 2), TestData { a: 4, b: false });
+        assert!(root.remove(IVec3::new(1, 1, 1)).is_none());
+        assert!(root.remove(IVec3::new(8, 0, 0)).is_none());
+        assert_eq!(root.remove(IVec3::new(1, 1, 2)).unwrap().a, 4);
+        assert!(root.get_all_depth_first().is_empty());
+        let region = IAabb::new(&IVec3::ZERO, 4);
+        assert!(
+            root.iter_empty_within_region(region)
+                .any(|p| p == IVec3::new(1, 1, 2))
+        );
+    }
+
     #[test]
     #[should_panic(expected = "x val 8 is too high for size 8")]
     fn test_add_node_outside_bounds() {
@@ -224,4 +248,59 @@ mod tests {
             1
         );
     }
+
+    // Lower half filled, upper half empty
+    fn half_filled_tree(size: usize) -> Octree<usize> {
+        let mut tree = Octree::new(size);
+        let half = size as i32 / 2;
+        for x in 0..size as i32 {
+            for y in 0..half {
+                for z in 0..size as i32 {
+                    tree.insert(IVec3::new(x, y, z), 0);
+                }
+            }
+        }
+        tree
+    }
+
+    #[test]
+    fn test_region_iterators_on_deep_tree() {
+        let tree = half_filled_tree(16);
+        let all = IAabb::new(&IVec3::ZERO, 16);
+        assert_eq!(tree.iter_region(all.clone()).count(), 16 * 8 * 16);
+        // Empty upper half is reported as 4 empty size 8 nodes
+        assert_eq!(tree.iter_empty_within_region(all).count(), 4);
+
+        let region = IAabb::new_rect(IVec3::new(3, 6, 5), IVec3::new(9, 10, 7));
+        let expected = (3..9).count() * (6..8).count() * (5..7).count();
+        assert_eq!(tree.iter_region(region).count(), expected);
+    }
+
+    // Run with `cargo test --release bench_region_iterators -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_region_iterators() {
+        let tree = half_filled_tree(64);
+        let iterations = 200;
+
+        let start = Instant::now();
+        let mut count = 0;
+        for i in 0..iterations {
+            let region = IAabb::new(&IVec3::splat(i % 32), 16);
+            count += tree.iter_region(region).count();
+        }
+        let region_query = start.elapsed();
+
+        let start = Instant::now();
+        for i in 0..iterations {
+            let region = IAabb::new(&IVec3::splat(i % 32), 16);
+            count += tree.iter_empty_within_region(region).count();
+        }
+        let empty_query = start.elapsed();
+        println!(
+            "{iterations} queries, {count} nodes. Region: {:.3}ms/query, empty: {:.3}ms/query",
+            region_query.as_secs_f64() * 1e3 / iterations as f64,
+            empty_query.as_secs_f64() * 1e3 / iterations as f64,
+        );
+    }
 }
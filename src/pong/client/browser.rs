@@ -0,0 +1,158 @@
+use std::{
+    cell::RefCell,
+    error::Error,
+    net::SocketAddr,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use glow::HasContext;
+use hecs::World;
+use imgui::Ui;
+use log::{error, info};
+
+use crate::{
+    input::InputState,
+    network::{LanBrowser, PreSharedKey},
+    scenes::{GuiScene, SceneStats, scene::BaseScene},
+};
+
+use super::scene::PongScene;
+
+/// Main menu of the pong client. Lists servers discovered in the LAN & joins the picked one
+pub struct ServerBrowserScene {
+    browser: LanBrowser,
+    player_name: String,
+    pre_shared_key: Option<PreSharedKey>,
+    input_state: Rc<RefCell<InputState>>,
+    // Last failed join attempt
+    error: Option<String>,
+    // Set once joined. Picked up by the application
+    next_scene: Option<Box<dyn GuiScene>>,
+    start: Instant,
+    last: Instant,
+    frame_count: u32,
+}
+
+impl ServerBrowserScene {
+    pub fn new(
+        player_name: String,
+        pre_shared_key: Option<PreSharedKey>,
+        input_state: Rc<RefCell<InputState>>,
+    ) -> Result<ServerBrowserScene, Box<dyn Error>> {
+        Ok(Self {
+            browser: LanBrowser::new()?,
+            player_name,
+            pre_shared_key,
+            input_state,
+            error: None,
+            next_scene: None,
+            start: Instant::now(),
+            last: Instant::now(),
+            frame_count: 0,
+        })
+    }
+
+    fn join(&mut self, address: SocketAddr) {
+        info!("Joining server {address}");
+        match PongScene::connect(
+            &address.to_string(),
+            self.player_name.clone(),
+            self.pre_shared_key.clone(),
+            self.input_state.clone(),
+        ) {
+            Ok(scene) => self.next_scene = Some(Box::new(scene)),
+            Err(err) => {
+                error!("Failed to join {address}: {err}");
+                self.error = Some(format!("Failed to join {address}: {err}"));
+            }
+        }
+    }
+}
+
+impl BaseScene for ServerBrowserScene {
+    fn get_world(&self) -> Option<&World> {
+        None
+    }
+
+    fn get_title(&self) -> String {
+        "Server browser".to_string()
+    }
+
+    fn is_networked(&self) -> bool {
+        true
+    }
+
+    fn tick(&mut self, _dt: f32) -> Result<(), Box<dyn Error>> {
+        self.browser.poll();
+        Ok(())
+    }
+
+    fn on_enter(&mut self) {}
+
+    fn on_exit(&mut self) {}
+}
+
+impl GuiScene for ServerBrowserScene {
+    fn get_stats(&self) -> SceneStats {
+        // No cubes in the menu
+        SceneStats::new(self.frame_count, self.start, self.last, self.get_title(), 0)
+    }
+
+    fn render(&mut self, gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
+        self.frame_count += 1;
+        self.last = Instant::now();
+        unsafe {
+            gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        Ok(())
+    }
+
+    fn render_ui(&mut self, ui: &mut Ui) {
+        let io = ui.io();
+        let window_size = [420.0, 260.0];
+        let centered_pos = [
+            (io.display_size[0] - window_size[0]) * 0.5,
+            (io.display_size[1] - window_size[1]) * 0.5,
+        ];
+        let mut join = None;
+        ui.window("Servers")
+            .size(window_size, imgui::Condition::FirstUseEver)
+            .position(centered_pos, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("Playing as {}", self.player_name));
+                ui.separator();
+                if self.browser.servers().is_empty() {
+                    ui.text("Searching for servers in the LAN...");
+                }
+                for server in self.browser.servers() {
+                    let announcement = &server.announcement;
+                    ui.text(format!(
+                        "{} ({}) - {} players - {}ms",
+                        announcement.name,
+                        announcement.mode,
+                        announcement.players,
+                        server.ping.as_millis()
+                    ));
+                    ui.same_line();
+                    if ui.button(format!("Join##{}", server.address)) {
+                        join = Some(server.address);
+                    }
+                }
+                ui.separator();
+                if ui.button("Refresh") {
+                    self.browser.refresh();
+                }
+                if let Some(error) = &self.error {
+                    ui.text_wrapped(error);
+                }
+            });
+        if let Some(address) = join {
+            self.join(address);
+        }
+    }
+
+    fn take_next_scene(&mut self) -> Option<Box<dyn GuiScene>> {
+        self.next_scene.take()
+    }
+}
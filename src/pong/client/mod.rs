@@ -1,5 +1,7 @@
 pub(super) mod ai;
+pub mod browser;
 pub(super) mod player;
 pub(super) mod protocol;
 pub mod scene;
+pub(super) mod spectator;
 pub(super) mod sync;
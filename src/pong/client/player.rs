@@ -8,13 +8,13 @@ use crate::{
     input::InputState,
     network::{Authority, ClientId, NetEntityId, NetworkReplicated, NetworkWorld},
     pong::{
-        common::{paddle::spawn_paddle, player::apply_input_buffer_sample},
+        common::{movement::apply_input_buffer_sample, paddle::spawn_paddle},
         network::{
             client::{ClientMessage, InputSample},
             input::{ACK_BUFFER_SIZE, ClientInputBuffer},
         },
     },
-    renderer::ecs_renderer::RenderColor,
+    renderer::material::Material,
     systems::physics::Transform,
 };
 
@@ -59,7 +59,8 @@ pub(super) fn spawn_player_client(
         .insert(
             paddle,
             (
-                RenderColor(Vec3::Y),
+                // Highlight own paddle
+                Material::new().with_color(Vec3::Y),
                 PongPlayer,
                 NetworkReplicated {
                     authority: Authority::Client(client_id),
@@ -91,7 +92,22 @@ pub(super) fn apply_player_input(world: &mut World, input: &ClientInputBuffer) {
     apply_input_buffer_sample(world, sample, entity);
 }
 
-pub(super) fn sample_input(buf: &mut ClientInputBuffer, input: &InputState, client_tick: u32) {
+/// Vertical position of the own paddle
+pub(super) fn player_height(world: &World) -> Option<f32> {
+    world
+        .query::<&Transform>()
+        .with::<&PongPlayer>()
+        .iter()
+        .next()
+        .map(|(_entity, transform)| transform.0.w_axis.y)
+}
+
+pub(super) fn sample_input(
+    buf: &mut ClientInputBuffer,
+    input: &InputState,
+    client_tick: u32,
+    position: f32,
+) {
     let mut vertical_velocity = 0.0;
     if input.is_key_pressed(&KeyCode::KeyW) {
         vertical_velocity += 1.0;
@@ -102,17 +118,22 @@ pub(super) fn sample_input(buf: &mut ClientInputBuffer, input: &InputState, clie
     let sample = InputSample {
         client_tick,
         vertical_velocity,
+        position,
     };
     buf.input_buffer.push(sample);
 }
 
-pub(super) fn assemble_input_sync_cmd(buf: &ClientInputBuffer) -> ClientMessage {
+pub(super) fn assemble_input_sync_cmd(
+    buf: &ClientInputBuffer,
+    last_received_snapshot_tick: Option<u32>,
+) -> ClientMessage {
     debug_assert!(
         buf.input_buffer.len() < ACK_BUFFER_SIZE,
         "Input buffer overflow"
     );
     ClientMessage::InputSync {
         last_acked_client_tick: buf.last_acked_client_tick,
+        last_received_snapshot_tick,
         unacked_inputs: buf.input_buffer.clone(),
     }
 }
@@ -4,8 +4,10 @@ use log::{error, trace};
 
 use crate::{
     config::SIMULATION_DT,
-    network::{ClientId, NetworkClient, TimeSync},
-    pong::network::{ServerMessage, client::ClientMessage},
+    network::{
+        ClientDownstreamPayload, ClientId, HandshakeState, NetworkClient, SpectateState, TimeSync,
+    },
+    pong::network::{BincodeCodec, ServerMessage, client::ClientMessage, protocol_info},
 };
 
 use std::sync::mpsc::Receiver;
@@ -13,24 +15,31 @@ use std::sync::mpsc::Receiver;
 /// Networking protocol layer which handles conversion of game-specific commands & messages into
 /// format that transport layer expects
 pub struct ClientProtocol {
-    downstream_bytes_rx: Receiver<Vec<u8>>,
+    downstream_bytes_rx: Receiver<ClientDownstreamPayload>,
     client: NetworkClient,
     last_ping: Instant,
     client_tick: u32,
-    pub(super) time_sync: TimeSync,
+    time_sync: TimeSync,
+    server_tick_rate: Option<f32>,
+    player_name: String,
 }
 
 impl ClientProtocol {
     pub fn new(
-        downstream_bytes_rx: Receiver<Vec<u8>>,
+        downstream_bytes_rx: Receiver<ClientDownstreamPayload>,
         client: NetworkClient,
+        player_name: String,
     ) -> Result<Self, String> {
+        // Client messages are always bincode encoded
+        client.handshake(&protocol_info::<BincodeCodec>(), &player_name);
         Ok(ClientProtocol {
             client,
             downstream_bytes_rx,
             last_ping: Instant::now(),
             time_sync: TimeSync::new(),
+            server_tick_rate: None,
             client_tick: 0,
+            player_name,
         })
     }
 
@@ -46,32 +55,55 @@ impl ClientProtocol {
         self.client.is_connected()
     }
 
-    fn update_time_sync(&mut self, server_tick: u32) {
-        let rtt = Duration::from_nanos(self.client.get_ping() as u64);
-        let server_ingame_time = server_tick * SIMULATION_DT;
-        self.time_sync
-            .update(server_ingame_time, Instant::now(), rtt);
+    /// Leaves the server. The protocol cannot be used afterwards
+    pub fn disconnect(&self) {
+        self.client.disconnect();
+    }
+
+    pub fn request_spectate(&self) {
+        self.client.request_spectate();
+    }
+
+    pub fn spectate_state(&self) -> SpectateState {
+        self.client.spectate_state()
+    }
+
+    pub fn handshake_state(&self) -> HandshakeState {
+        self.client
+            .handshake_state()
+            .unwrap_or(HandshakeState::Pending)
+    }
+
+    /// Current game time (server tick * SIMULATION_DT) on the timeline shared with the server
+    pub fn game_time_at(&self, now: Instant) -> Duration {
+        self.client
+            .server_time_at(now)
+            .map(|server_time| self.time_sync.game_time_at(server_time))
+            .unwrap_or_default()
     }
 
     pub fn approx_server_tick(&self, now: Instant) -> u32 {
-        let duration = self.time_sync.server_time_at(now);
+        let duration = self.game_time_at(now);
         (duration.as_nanos() / SIMULATION_DT.as_nanos()) as u32
     }
 
     pub fn try_recv(&mut self) -> Option<ServerMessage> {
-        while let Ok(bytes) = self.downstream_bytes_rx.try_recv() {
-            match bincode::deserialize(&bytes) {
+        while let Ok(payload) = self.downstream_bytes_rx.try_recv() {
+            match bincode::deserialize(&payload.bytes) {
                 Ok(cmd) => {
                     // If message contains a server tick -> Update time_sync
                     match &cmd {
-                        ServerMessage::SendSnapshot { server_tick, .. } => {
-                            self.update_time_sync(*server_tick);
+                        ServerMessage::SendSnapshot { server_tick, .. }
+                        | ServerMessage::StartMatch { server_tick, .. }
+                        | ServerMessage::StartRound { server_tick, .. }
+                        | ServerMessage::PointScored { server_tick, .. }
+                        | ServerMessage::EndMatch { server_tick, .. }
+                        | ServerMessage::SpectatorSync { server_tick, .. } => {
+                            self.time_sync
+                                .update(*server_tick * SIMULATION_DT, payload.server_time);
                         }
-                        ServerMessage::StartRound { server_tick, .. } => {
-                            self.update_time_sync(*server_tick);
-                        }
-                        ServerMessage::EndRound { server_tick, .. } => {
-                            self.update_time_sync(*server_tick);
+                        ServerMessage::TickRate { ticks_per_second } => {
+                            self.server_tick_rate = Some(*ticks_per_second);
                         }
                         _ => {}
                     }
@@ -86,6 +118,11 @@ impl ClientProtocol {
     pub fn tick(&mut self) {
         // Ping once a second
         if self.last_ping.elapsed() > Duration::from_secs(1) {
+            // Handshake might have been lost
+            if self.handshake_state() == HandshakeState::Pending {
+                self.client
+                    .handshake(&protocol_info::<BincodeCodec>(), &self.player_name);
+            }
             self.client.ping();
             self.last_ping = Instant::now();
         }
@@ -106,10 +143,15 @@ impl ClientProtocol {
             .position([500.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("ClientId: {:?}", self.get_client_id()));
+                ui.text(format!("Name: {}", self.player_name));
                 let connected = self.is_connected();
                 ui.text(format!("Connected: {connected}"));
                 if connected {
                     ui.text(format!("Ping: {:.1}ms", self.client.get_ping() * 1e-6,));
+                    ui.text(format!("Clock synced: {}", self.client.is_clock_synced()));
+                    if let Some(tick_rate) = self.server_tick_rate {
+                        ui.text(format!("Server tick rate: {tick_rate:.1}Hz"));
+                    }
                     ui.text(format!(
                         "Server tick: {}",
                         self.approx_server_tick(Instant::now())
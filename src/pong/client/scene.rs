@@ -1,14 +1,17 @@
 use crate::{
     collision::system_collisions,
+    config::SIMULATION_DT,
     input::InputState,
     log_err,
-    network::{NetworkWorld, SnapshotManager},
+    network::{
+        ClientDownstreamPayload, HandshakeState, NetworkClient, NetworkWorld, PreSharedKey,
+        SnapshotManager, SpectateState,
+    },
     pong::{
         ClientProtocol,
         common::{
-            ball::PongBall,
-            paddle::{PaddleControl, system_paddle_movement},
-            setup_static_entities,
+            ball::PongBall, movement::system_paddle_movement, paddle::PaddleControl,
+            score::MatchScore, setup_static_entities,
         },
         network::{client::ClientMessage, input::ClientInputBuffer},
     },
@@ -19,6 +22,7 @@ use std::{
     cell::RefCell,
     error::Error,
     rc::Rc,
+    sync::mpsc,
     time::{Duration, Instant},
 };
 
@@ -30,20 +34,27 @@ use log::{debug, info};
 use crate::scenes::GuiScene;
 
 use super::{
-    player::{apply_player_input, assemble_input_sync_cmd, sample_input},
+    player::{apply_player_input, assemble_input_sync_cmd, player_height, sample_input},
+    spectator::system_spectator_camera,
     sync::client_handle_network_cmd,
 };
 
 pub(super) struct GameOverTransition {
     server_tick: u32,
-    loosing_player_slot: usize,
+    winning_player_slot: usize,
+    score: MatchScore,
 }
 
 impl GameOverTransition {
-    pub(super) fn new(server_tick: u32, loosing_player_slot: usize) -> GameOverTransition {
+    pub(super) fn new(
+        server_tick: u32,
+        winning_player_slot: usize,
+        score: MatchScore,
+    ) -> GameOverTransition {
         Self {
             server_tick,
-            loosing_player_slot,
+            winning_player_slot,
+            score,
         }
     }
 }
@@ -51,21 +62,37 @@ impl GameOverTransition {
 pub(super) enum GameState {
     // Initial state after loading scene and joining first game
     Initial,
+    // Server denied join request. UI to retry
+    JoinRejected {
+        reason: String,
+    },
     // Player joined & waits for others to fill the lobby
     WaitingForOthers {
         player_slot: usize,
     },
-    // Game in progress
+    // Match in progress
     Running {
         player_slot: usize,
         started_at_server_tick: u32,
+        score: MatchScore,
+        // Set while waiting for the next serve
+        serve_at_server_tick: Option<u32>,
         // Once game-over signal received from server will continue simulation until transition to
         // game over
         end_signal: Option<GameOverTransition>,
     },
-    // Game over, UI to rejoin
+    // Match over, UI to rejoin
     GameOver {
         winner: bool,
+        score: MatchScore,
+    },
+    // Observing matches of others. Free camera, no inputs sent
+    Spectating {
+        // None while no match has started
+        score: Option<MatchScore>,
+        serve_at_server_tick: Option<u32>,
+        // Winner of the last finished match
+        last_winner: Option<usize>,
     },
 }
 
@@ -98,6 +125,23 @@ impl PongScene {
         })
     }
 
+    /// Sets up transport & protocol layer connected to the server
+    pub fn connect(
+        server_address: &str,
+        player_name: String,
+        pre_shared_key: Option<PreSharedKey>,
+        input_state: Rc<RefCell<InputState>>,
+    ) -> Result<PongScene, Box<dyn Error>> {
+        let (downstream_bytes_tx, downstream_bytes_rx) = mpsc::channel::<ClientDownstreamPayload>();
+        let client = NetworkClient::with_pre_shared_key(
+            server_address,
+            downstream_bytes_tx,
+            pre_shared_key,
+        )?;
+        let protocol = ClientProtocol::new(downstream_bytes_rx, client, player_name)?;
+        PongScene::new(protocol, input_state)
+    }
+
     fn request_start_round(&mut self) {
         log_err!(
             self.client_protocol.send_cmd(ClientMessage::RequestJoin),
@@ -128,7 +172,8 @@ impl PongScene {
 
         info!("Game over tick reached. Ending client simulation");
         self.game_state = GameState::GameOver {
-            winner: transition.loosing_player_slot != *player_slot,
+            winner: transition.winning_player_slot == *player_slot,
+            score: transition.score.clone(),
         };
         log_err!(
             self.world.despawn_all::<&PongBall>(),
@@ -147,11 +192,86 @@ impl PongScene {
             None => return,
         };
         ui.window("Ball")
-            .size([150.0, 100.0], imgui::Condition::FirstUseEver)
+            .size([170.0, 100.0], imgui::Condition::FirstUseEver)
             .position([300.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("Bounces: {}", ball.bounces));
-                ui.text(format!("Speed: {}", ball.speed));
+                ui.text(format!("Speed: {:.2}", ball.speed));
+                ui.text(format!("Spin: {:.2} rad/s", ball.spin));
+            });
+    }
+
+    fn scoreboard_ui(&self, ui: &mut Ui) {
+        let (player_slot, score, serve_at_server_tick) = match &self.game_state {
+            GameState::Running {
+                player_slot,
+                score,
+                serve_at_server_tick,
+                ..
+            } => (Some(*player_slot), score, serve_at_server_tick),
+            GameState::Spectating {
+                score: Some(score),
+                serve_at_server_tick,
+                ..
+            } => (None, score, serve_at_server_tick),
+            _ => return,
+        };
+        let display_width = ui.io().display_size[0];
+        let window_size = [200.0, 80.0];
+        ui.window("Score")
+            .size(window_size, imgui::Condition::FirstUseEver)
+            .position(
+                [(display_width - window_size[0]) * 0.5, 0.0],
+                imgui::Condition::FirstUseEver,
+            )
+            .build(|| {
+                let you = |slot: usize| {
+                    if player_slot == Some(slot) {
+                        " (you)"
+                    } else {
+                        ""
+                    }
+                };
+                ui.text(format!(
+                    "P0{}  {} : {}  P1{}",
+                    you(0),
+                    score.points[0],
+                    score.points[1],
+                    you(1),
+                ));
+                ui.text(format!("First to {}", score.points_to_win));
+                if let Some(serve_at) = serve_at_server_tick {
+                    let approx = self.client_protocol.approx_server_tick(Instant::now());
+                    let remaining_ticks = serve_at.saturating_sub(approx);
+                    ui.text(format!(
+                        "Serve in {:.1}s",
+                        (SIMULATION_DT * remaining_ticks).as_secs_f32()
+                    ));
+                }
+            });
+    }
+
+    fn spectator_ui(&self, ui: &mut Ui) {
+        let GameState::Spectating {
+            score, last_winner, ..
+        } = &self.game_state
+        else {
+            return;
+        };
+        ui.window("Spectating")
+            .size([250.0, 110.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 0.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                if score.is_none() {
+                    ui.text("Waiting for players...");
+                }
+                if let Some(winner) = last_winner {
+                    ui.text(format!("Player {winner} won the last match"));
+                }
+                ui.separator();
+                ui.text("Pan: Arrow keys / WASD");
+                ui.text("Zoom: Q / E");
+                ui.text("Reset view: R");
             });
     }
 
@@ -168,27 +288,65 @@ impl PongScene {
             .position(centered_pos, imgui::Condition::FirstUseEver)
             .build(|| match self.game_state {
                 GameState::Initial => {
-                    if self.client_protocol.is_connected() {
-                        let btn = ui.button_with_size("Join game [SPACE]", button_size);
-                        let keybind = ui.is_key_pressed(imgui::Key::Space);
-                        if btn || keybind {
-                            self.request_start_round();
-                        }
-                    } else {
+                    if !self.client_protocol.is_connected() {
                         ui.text("Server unavailable");
+                        return;
+                    }
+                    match self.client_protocol.handshake_state() {
+                        HandshakeState::Pending => {
+                            ui.text("Connecting...");
+                            return;
+                        }
+                        HandshakeState::Rejected(reason) => {
+                            ui.text_wrapped(format!("Server refused connection: {reason}"));
+                            return;
+                        }
+                        HandshakeState::Accepted => {}
+                    }
+                    match self.client_protocol.spectate_state() {
+                        SpectateState::Requested | SpectateState::Accepted => {
+                            ui.text("Joining as spectator...");
+                            return;
+                        }
+                        SpectateState::Rejected(reason) => {
+                            ui.text_wrapped(format!("Unable to spectate: {reason}"));
+                        }
+                        SpectateState::None => {}
+                    }
+                    let btn = ui.button_with_size("Join game [SPACE]", button_size);
+                    let keybind = ui.is_key_pressed(imgui::Key::Space);
+                    if btn || keybind {
+                        self.request_start_round();
+                    }
+                    if ui.button_with_size("Spectate [TAB]", button_size)
+                        || ui.is_key_pressed(imgui::Key::Tab)
+                    {
+                        self.client_protocol.request_spectate();
+                    }
+                }
+                GameState::JoinRejected { ref reason } => {
+                    ui.text_wrapped(format!("Unable to join: {reason}"));
+                    let btn = ui.button_with_size("Retry [SPACE]", button_size);
+                    let keybind = ui.is_key_pressed(imgui::Key::Space);
+                    if btn || keybind {
+                        self.request_start_round();
                     }
                 }
                 GameState::WaitingForOthers { player_slot } => {
                     ui.text_wrapped(format!(
-                        "Connected as Player {player_slot}, waiting for others..."
+                        "Connected as Player {player_slot}, waiting for opponent to join..."
                     ));
                 }
-                GameState::GameOver { winner } => {
+                GameState::GameOver { winner, ref score } => {
                     ui.text(if winner {
                         "You've won!".to_string()
                     } else {
                         "You've lost".to_string()
                     });
+                    ui.text(format!(
+                        "Final score {} : {}",
+                        score.points[0], score.points[1]
+                    ));
                     let btn = ui.button_with_size("Play again [SPACE]", button_size);
                     let keybind = ui.is_key_pressed(imgui::Key::Space);
                     if btn || keybind {
@@ -204,7 +362,10 @@ impl BaseScene for PongScene {
     fn get_title(&self) -> String {
         "Pong".to_string()
     }
-    fn tick(&mut self, dt: f32) {
+    fn is_networked(&self) -> bool {
+        true
+    }
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>> {
         while let Some(cmd) = self.client_protocol.try_recv() {
             client_handle_network_cmd(
                 &mut self.world,
@@ -221,12 +382,14 @@ impl BaseScene for PongScene {
                 &mut self.input_buffer,
                 &self.input_state.borrow(),
                 self.client_protocol.get_client_tick(),
+                player_height(self.world.get_world()).unwrap_or_default(),
             );
             // Send to server
-            let input_cmd = assemble_input_sync_cmd(&self.input_buffer);
-            self.client_protocol
-                .send_cmd(input_cmd)
-                .expect("Could not send client input");
+            let input_cmd = assemble_input_sync_cmd(
+                &self.input_buffer,
+                self.snapshot_manager.last_received_tick(),
+            );
+            self.client_protocol.send_cmd(input_cmd)?;
             // Apply input locally
             apply_player_input(self.world.get_world_mut(), &self.input_buffer);
 
@@ -234,12 +397,19 @@ impl BaseScene for PongScene {
             system_paddle_movement(self.world.get_world_mut(), &collisions);
             system_movement(self.world.get_world_mut(), dt);
             self.check_for_game_over();
+        } else if let GameState::Spectating { .. } = self.game_state {
+            system_spectator_camera(self.world.get_world_mut(), &self.input_state.borrow(), dt);
         }
 
         self.client_protocol.tick();
+        Ok(())
     }
 
-    fn start(&mut self) {}
+    fn on_enter(&mut self) {}
+
+    fn on_exit(&mut self) {
+        self.client_protocol.disconnect();
+    }
 
     fn get_world(&self) -> Option<&World> {
         Some(self.world.get_world())
@@ -251,27 +421,34 @@ impl GuiScene for PongScene {
         todo!()
     }
 
-    fn render(&mut self, gl: &glow::Context, dt: Duration) {
+    fn render(&mut self, gl: &glow::Context, dt: Duration) -> Result<(), Box<dyn Error>> {
         unsafe {
-            gl.clear_color(0.05, 0.05, 0.1, 1.0);
             gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
         if let Some(client_id) = self.client_protocol.get_client_id() {
             self.snapshot_manager.tick(
                 &mut self.world,
                 client_id,
-                &self.client_protocol.time_sync,
+                self.client_protocol.game_time_at(Instant::now()),
                 dt,
             );
         }
+        Ok(())
     }
 
     fn render_ui(&mut self, ui: &mut Ui) {
         self.client_protocol.render_ui(ui);
-        if !matches!(self.game_state, GameState::Running { .. }) {
-            self.overlay_ui(ui);
-        } else {
-            self.ball_ui(ui);
+        match self.game_state {
+            GameState::Running { .. } => {
+                self.scoreboard_ui(ui);
+                self.ball_ui(ui);
+            }
+            GameState::Spectating { .. } => {
+                self.spectator_ui(ui);
+                self.scoreboard_ui(ui);
+                self.ball_ui(ui);
+            }
+            _ => self.overlay_ui(ui),
         }
     }
 }
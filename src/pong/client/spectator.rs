@@ -0,0 +1,82 @@
+use glam::{Mat4, Vec3};
+use hecs::World;
+use log::error;
+use winit::keyboard::KeyCode;
+
+use crate::{
+    cameras::component::CameraComponent,
+    input::InputState,
+    pong::common::{camera_projection, default_camera_transform},
+    systems::physics::Transform,
+};
+
+// Units per second at zoom 1
+const PAN_SPEED: f32 = 3.0;
+// Zoom factor change per second
+const ZOOM_SPEED: f32 = 1.5;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.0;
+
+/// Free camera controlled by spectators
+pub(super) struct SpectatorCamera {
+    zoom: f32,
+}
+
+/// Detaches camera from player perspective & resets it to the default view
+pub(super) fn enable_spectator_camera(world: &mut World) {
+    let Some((camera, _)) = world.query_mut::<&CameraComponent>().into_iter().next() else {
+        error!("Unable to enable spectator camera: No camera found");
+        return;
+    };
+    if let Err(err) = world.insert_one(camera, SpectatorCamera { zoom: 1.0 }) {
+        error!("Unable to enable spectator camera: {err}");
+        return;
+    }
+    reset_spectator_camera(world);
+}
+
+fn reset_spectator_camera(world: &mut World) {
+    for (_, (transform, camera, spectator)) in
+        world.query_mut::<(&mut Transform, &mut CameraComponent, &mut SpectatorCamera)>()
+    {
+        spectator.zoom = 1.0;
+        transform.0 = default_camera_transform();
+        camera.projection = camera_projection(spectator.zoom);
+    }
+}
+
+/// Arrow keys / WASD pan, Q & E zoom, R resets the view
+pub(super) fn system_spectator_camera(world: &mut World, input: &InputState, dt: f32) {
+    if input.is_key_pressed(&KeyCode::KeyR) {
+        reset_spectator_camera(world);
+        return;
+    }
+    let axis = |positive: &[KeyCode], negative: &[KeyCode]| {
+        let pressed = |keys: &[KeyCode]| keys.iter().any(|key| input.is_key_pressed(key));
+        pressed(positive) as i32 as f32 - pressed(negative) as i32 as f32
+    };
+    let pan = Vec3::new(
+        axis(
+            &[KeyCode::ArrowRight, KeyCode::KeyD],
+            &[KeyCode::ArrowLeft, KeyCode::KeyA],
+        ),
+        axis(
+            &[KeyCode::ArrowUp, KeyCode::KeyW],
+            &[KeyCode::ArrowDown, KeyCode::KeyS],
+        ),
+        0.0,
+    );
+    let zoom = axis(&[KeyCode::KeyE], &[KeyCode::KeyQ]);
+    if pan == Vec3::ZERO && zoom == 0.0 {
+        return;
+    }
+    for (_, (transform, camera, spectator)) in
+        world.query_mut::<(&mut Transform, &mut CameraComponent, &mut SpectatorCamera)>()
+    {
+        spectator.zoom = (spectator.zoom * ZOOM_SPEED.powf(zoom * dt)).clamp(MIN_ZOOM, MAX_ZOOM);
+        camera.projection = camera_projection(spectator.zoom);
+        // Pan in view plane, slower when zoomed in
+        let offset = pan * PAN_SPEED * dt / spectator.zoom;
+        transform.0 *= Mat4::from_translation(offset);
+    }
+}
@@ -1,16 +1,24 @@
 use log::{error, info, trace};
 
 use crate::{
+    config::SIMULATION_DT,
     network::{NetworkWorld, SnapshotManager},
     pong::{
         ClientProtocol,
         client::{
             player::{adjust_player_camera, spawn_player_client},
             scene::GameOverTransition,
+            spectator::enable_spectator_camera,
+        },
+        common::{
+            ball::{PongBall, spawn_ball},
+            movement::replay_inputs,
+            paddle::PaddleControl,
+            player::spawn_player,
         },
-        common::{ball::spawn_ball, paddle::spawn_paddle},
         network::{ServerMessage, input::ClientInputBuffer},
     },
+    systems::physics::Transform,
 };
 
 use super::scene::GameState;
@@ -27,47 +35,144 @@ pub(super) fn client_handle_network_cmd(
     if let Err(err) = match cmd {
         ServerMessage::SendSnapshot {
             server_tick,
-            data,
+            snapshot,
             last_acked_client_tick,
         } => {
-            // Store snapshot for interpolation buffering
-            snapshot_manager.store_snapshot(server_tick, data);
-
             input_buffer.update_acked_client_tick(last_acked_client_tick);
 
+            // Store snapshot for interpolation buffering
             // TODO: Apply snapshot to authorative ecs
             // TODO: Separate rendering from simulation transform
-            Ok(())
+            snapshot_manager.store_delta(server_tick, &snapshot)
         }
-        ServerMessage::StartRound {
-            ball_net_entity,
+        ServerMessage::StartMatch {
             server_tick,
-        } => {
-            if let GameState::WaitingForOthers { player_slot } = game_state {
+            serve_at_server_tick,
+            score,
+        } => match game_state {
+            GameState::WaitingForOthers { player_slot } => {
                 *game_state = GameState::Running {
                     player_slot: *player_slot,
                     started_at_server_tick: server_tick,
+                    score,
+                    serve_at_server_tick: Some(serve_at_server_tick),
                     end_signal: None,
                 };
                 *input_buffer = ClientInputBuffer::new();
+                Ok(())
+            }
+            GameState::Spectating { .. } => {
+                *game_state = GameState::Spectating {
+                    score: Some(score),
+                    serve_at_server_tick: Some(serve_at_server_tick),
+                    last_winner: None,
+                };
+                Ok(())
+            }
+            _ => Err("Trying to start before waiting for others".to_string()),
+        },
+        ServerMessage::StartRound {
+            ball_net_entity, ..
+        } => {
+            if let GameState::Running {
+                serve_at_server_tick,
+                ..
+            }
+            | GameState::Spectating {
+                serve_at_server_tick,
+                ..
+            } = game_state
+            {
+                *serve_at_server_tick = None;
                 spawn_ball(world, Some(ball_net_entity));
                 Ok(())
             } else {
-                Err("Trying to start before waiting for others".to_string())
+                Err("Received serve while match was not running".to_string())
+            }
+        }
+        ServerMessage::PointScored {
+            server_tick,
+            serve_at_server_tick: next_serve,
+            score: new_score,
+        } => {
+            info!("Point scored at tick {server_tick}: {:?}", new_score.points);
+            match game_state {
+                GameState::Running {
+                    score,
+                    serve_at_server_tick,
+                    ..
+                } => {
+                    *score = new_score;
+                    *serve_at_server_tick = Some(next_serve);
+                    world.despawn_all::<&PongBall>()
+                }
+                GameState::Spectating {
+                    score,
+                    serve_at_server_tick,
+                    ..
+                } => {
+                    *score = Some(new_score);
+                    *serve_at_server_tick = Some(next_serve);
+                    world.despawn_all::<&PongBall>()
+                }
+                _ => Err("Received scored point while match was not running".to_string()),
+            }
+        }
+        ServerMessage::SpectatorSync {
+            score,
+            serve_at_server_tick,
+            ball_net_entity,
+            ..
+        } => {
+            info!(
+                "Spectating. Match score {:?}",
+                score.as_ref().map(|s| s.points)
+            );
+            enable_spectator_camera(world.get_world_mut());
+            *game_state = GameState::Spectating {
+                score,
+                serve_at_server_tick,
+                last_winner: None,
+            };
+            if let Some(ball_net_entity) = ball_net_entity
+                && world.get_entity_id(ball_net_entity).is_none()
+            {
+                spawn_ball(world, Some(ball_net_entity));
             }
+            Ok(())
         }
-        ServerMessage::EndRound {
+        ServerMessage::EndMatch {
             server_tick,
-            loosing_player_slot,
+            winning_player_slot,
+            score: final_score,
         } => {
             info!(
-                "Game over: Player slot {loosing_player_slot} lost the game at tick {server_tick}"
+                "Game over: Player slot {winning_player_slot} won the match at tick {server_tick}"
             );
-            if let GameState::Running { end_signal, .. } = game_state {
-                *end_signal = Some(GameOverTransition::new(server_tick, loosing_player_slot));
-                Ok(())
-            } else {
-                Err("Received end round signal while game was not running".to_string())
+            match game_state {
+                GameState::Running {
+                    score, end_signal, ..
+                } => {
+                    *score = final_score.clone();
+                    *end_signal = Some(GameOverTransition::new(
+                        server_tick,
+                        winning_player_slot,
+                        final_score,
+                    ));
+                    Ok(())
+                }
+                GameState::Spectating { .. } => {
+                    // Nothing to simulate for spectators. Clear field right away
+                    *game_state = GameState::Spectating {
+                        score: Some(final_score),
+                        serve_at_server_tick: None,
+                        last_winner: Some(winning_player_slot),
+                    };
+                    world
+                        .despawn_all::<&PongBall>()
+                        .and_then(|_| world.despawn_all::<&PaddleControl>())
+                }
+                _ => Err("Received end match signal while game was not running".to_string()),
             }
         }
         ServerMessage::SpawnPlayer {
@@ -86,11 +191,57 @@ pub(super) fn client_handle_network_cmd(
         ServerMessage::SpawnPaddle {
             net_entity_id,
             player_slot,
+            owner,
         } => {
-            spawn_paddle(world, player_slot, Some(net_entity_id));
+            // Opponent paddle: Owned by other client, interpolated from snapshots
+            spawn_player(world, player_slot, Some(net_entity_id), owner);
+            Ok(())
+        }
+        ServerMessage::JoinRejected { reason } => {
+            *game_state = GameState::JoinRejected { reason };
             Ok(())
         }
         ServerMessage::DespawnEntity { net_entity_id } => world.despawn_net_id(net_entity_id),
+        ServerMessage::CorrectPosition {
+            net_entity_id,
+            client_tick,
+            position,
+        } => match world.get_entity_id(net_entity_id).copied() {
+            Some(entity) => {
+                // Inputs since the corrected one were already applied locally. Replay them
+                let pending = input_buffer
+                    .input_buffer
+                    .iter()
+                    .filter(|sample| sample.client_tick >= client_tick);
+                let corrected = replay_inputs(position, pending, SIMULATION_DT.as_secs_f32());
+                info!("Server corrected paddle position to {corrected:.2}");
+                world
+                    .get_world_mut()
+                    .get::<&mut Transform>(entity)
+                    .map(|mut transform| transform.0.w_axis.y = corrected)
+                    .map_err(|err| format!("Unable to correct paddle position: {err}"))
+            }
+            None => Err(format!("Unknown paddle net entity {net_entity_id}")),
+        },
+        // Handled by protocol
+        ServerMessage::TickRate { .. } => Ok(()),
+        ServerMessage::BallState {
+            net_entity_id,
+            speed,
+            bounces,
+            spin,
+        } => match world.get_entity_id(net_entity_id).copied() {
+            Some(entity) => world
+                .get_world_mut()
+                .get::<&mut PongBall>(entity)
+                .map(|mut ball| {
+                    ball.speed = speed;
+                    ball.bounces = bounces;
+                    ball.spin = spin;
+                })
+                .map_err(|err| format!("Unable to update ball state: {err}")),
+            None => Err(format!("Unknown ball net entity {net_entity_id}")),
+        },
     } {
         error!("Unable to process network command: {err}");
     }
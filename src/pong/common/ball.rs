@@ -1,4 +1,4 @@
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
 use hecs::{Entity, World};
 use log::info;
 
@@ -20,6 +20,62 @@ const MAX_BOUNCES: usize = 25;
 pub struct PongBall {
     pub speed: f32,
     pub bounces: usize,
+    /// Angular velocity around the z-axis in rad/s. Curves the trajectory
+    pub spin: f32,
+}
+
+/// Tunable ball response. Simulated by the server only, clients display replicated results
+#[derive(Debug, Clone)]
+pub struct BallPhysics {
+    /// Fraction of the normal velocity kept when bouncing off boundaries
+    pub restitution: f32,
+    /// Max angle between ball direction & horizontal axis in radians
+    pub max_bounce_angle: f32,
+    /// Bounce angle added per unit of paddle velocity at contact
+    pub paddle_deflection: f32,
+    /// Spin imparted per unit of paddle velocity at contact
+    pub spin_transfer: f32,
+    /// Vertical acceleration per unit of spin
+    pub spin_curve: f32,
+    /// Exponential spin decay per second
+    pub spin_damping: f32,
+}
+
+impl Default for BallPhysics {
+    fn default() -> Self {
+        Self {
+            restitution: 1.0,
+            max_bounce_angle: 60f32.to_radians(),
+            paddle_deflection: 0.1,
+            spin_transfer: 1.0,
+            spin_curve: 0.25,
+            spin_damping: 1.5,
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl BallPhysics {
+    pub fn render_ui(&mut self, ui: &imgui::Ui) {
+        ui.slider("Restitution", 0.5, 1.0, &mut self.restitution);
+        let mut max_angle_degrees = self.max_bounce_angle.to_degrees();
+        if ui.slider("Max bounce angle", 15.0, 80.0, &mut max_angle_degrees) {
+            self.max_bounce_angle = max_angle_degrees.to_radians();
+        }
+        ui.slider("Paddle deflection", 0.0, 0.3, &mut self.paddle_deflection);
+        ui.slider("Spin transfer", 0.0, 3.0, &mut self.spin_transfer);
+        ui.slider("Spin curve", 0.0, 1.0, &mut self.spin_curve);
+        ui.slider("Spin damping", 0.0, 5.0, &mut self.spin_damping);
+    }
+}
+
+/// Outcome of a bounce_balls pass
+#[derive(Debug, Default)]
+pub struct BounceEvents {
+    /// Slot number of **loosing** player, if game over
+    pub loosing_player: Option<usize>,
+    /// Balls that bounced off a paddle
+    pub paddle_hits: Vec<Entity>,
 }
 
 pub fn spawn_ball(
@@ -30,7 +86,11 @@ pub fn spawn_ball(
     let speed = BALL_MIN_SPEED;
     let (net_id, entity) = world.spawn(
         (
-            PongBall { speed, bounces: 0 },
+            PongBall {
+                speed,
+                bounces: 0,
+                spin: 0.0,
+            },
             Transform(Mat4::from_scale_rotation_translation(
                 scale,
                 Quat::IDENTITY,
@@ -61,10 +121,15 @@ pub fn spawn_ball(
     (net_id, entity)
 }
 
-/// Returns slot_number of **loosing** player, if game over
-pub fn bounce_balls(world: &mut World, collisions: &Vec<CollisionEvent>) -> Option<usize> {
+/// Server-authoritative ball collision response
+pub fn bounce_balls(
+    world: &mut World,
+    collisions: &Vec<CollisionEvent>,
+    physics: &BallPhysics,
+) -> BounceEvents {
+    let mut events = BounceEvents::default();
     if collisions.is_empty() {
-        return None;
+        return events;
     }
     let mut ball_query = world.query::<(&mut Transform, &mut Velocity, &mut PongBall)>();
     for (ball_entity, (ball_transform, velocity, ball)) in ball_query.iter() {
@@ -82,8 +147,10 @@ pub fn bounce_balls(world: &mut World, collisions: &Vec<CollisionEvent>) -> Opti
             if let Ok(trigger) = world.get::<&PongBallTrigger>(other) {
                 info!("Game over. Player {:?} lost", trigger.player_slot);
                 ball.speed = 0.0;
+                ball.spin = 0.0;
                 velocity.0 = Vec3::ZERO;
-                return Some(trigger.player_slot);
+                events.loosing_player = Some(trigger.player_slot);
+                return events;
             }
 
             let info = collision.info;
@@ -94,29 +161,142 @@ pub fn bounce_balls(world: &mut World, collisions: &Vec<CollisionEvent>) -> Opti
             ball_transform.0.w_axis.y += d_penetration.y;
             ball_transform.0.w_axis.z += d_penetration.z;
 
-            // Reflect velocity
-            let reflected_velocity = velocity.0 - 2.0 * velocity.0.dot(info.normal) * info.normal;
-            // Alternative A: Scale to speed
-            //velocity.0 = reflected_velocity.normalize() * ball.speed;
-            // Alternative B: Fixed x-speed
-            let x_multiplier = (ball.speed / reflected_velocity.x).abs();
-            velocity.0 = reflected_velocity * x_multiplier;
-
-            // Increase speed if we've hit a paddle
             if world.get::<&PaddleControl>(other).is_ok() {
+                // Increase speed if we've hit a paddle
                 ball.bounces += 1;
                 ball.speed = exp_lerp(
                     BALL_MIN_SPEED,
                     MAX_SPEED,
                     ball.bounces as f32 / MAX_BOUNCES as f32,
                 );
-                info!("Bounce #{}: New speed = {}", ball.bounces, ball.speed);
+                let paddle = world
+                    .get::<&Transform>(other)
+                    .map(|t| t.0)
+                    .unwrap_or(Mat4::IDENTITY);
+                let (paddle_scale, _, paddle_center) = paddle.to_scale_rotation_translation();
+                let paddle_velocity = world
+                    .get::<&Velocity>(other)
+                    .map(|v| v.0)
+                    .unwrap_or(Vec3::ZERO);
+                // Where on the paddle the ball hit: -1 bottom edge, 1 top edge
+                let hit_offset = ((ball_transform.0.w_axis.y - paddle_center.y)
+                    / (paddle_scale.y * 0.5))
+                    .clamp(-1.0, 1.0);
+                // Always bounce back towards the center of the field
+                let direction_x = -paddle_center.x.signum();
+                velocity.0 = paddle_bounce_velocity(
+                    hit_offset,
+                    paddle_velocity.y,
+                    direction_x,
+                    ball.speed,
+                    physics,
+                );
+                ball.spin = paddle_velocity.y * physics.spin_transfer;
+                events.paddle_hits.push(ball_entity);
+                info!(
+                    "Bounce #{}: New speed = {}, spin = {}",
+                    ball.bounces, ball.speed, ball.spin
+                );
+            } else {
+                // Reflect velocity, dampening normal component
+                let normal_velocity = velocity.0.dot(info.normal);
+                let reflected_velocity =
+                    velocity.0 - (1.0 + physics.restitution) * normal_velocity * info.normal;
+                // Alternative A: Scale to speed
+                //velocity.0 = reflected_velocity.normalize() * ball.speed;
+                // Alternative B: Fixed x-speed
+                let x_multiplier = (ball.speed / reflected_velocity.x).abs();
+                velocity.0 =
+                    clamp_bounce_angle(reflected_velocity * x_multiplier, physics.max_bounce_angle);
             }
         }
     }
-    None
+    events
+}
+
+/// Applies spin: Curves ball trajectory & rotates ball around its z-axis. Runs on the server, the
+/// resulting transform is replicated to clients
+pub fn system_ball_spin(world: &mut World, physics: &BallPhysics, dt: f32) {
+    for (_entity, (transform, velocity, ball)) in
+        world.query_mut::<(&mut Transform, &mut Velocity, &mut PongBall)>()
+    {
+        if ball.spin.abs() < 1e-4 {
+            continue;
+        }
+        velocity.0.y += ball.spin * physics.spin_curve * dt;
+        velocity.0 = clamp_bounce_angle(velocity.0, physics.max_bounce_angle);
+        let position = transform.0.w_axis.xyz();
+        transform.0 = Mat4::from_translation(position)
+            * Mat4::from_rotation_z(ball.spin * dt)
+            * Mat4::from_translation(-position)
+            * transform.0;
+        ball.spin *= (-physics.spin_damping * dt).exp();
+    }
+}
+
+/// Outgoing velocity after hitting a paddle. Angle depends on hit offset & paddle movement,
+/// horizontal speed is kept constant
+fn paddle_bounce_velocity(
+    hit_offset: f32,
+    paddle_vertical_velocity: f32,
+    direction_x: f32,
+    speed: f32,
+    physics: &BallPhysics,
+) -> Vec3 {
+    let angle = (hit_offset * physics.max_bounce_angle
+        + paddle_vertical_velocity * physics.paddle_deflection)
+        .clamp(-physics.max_bounce_angle, physics.max_bounce_angle);
+    Vec3::new(direction_x * speed, speed * angle.tan(), 0.0)
+}
+
+/// Limits vertical velocity, so ball does not get stuck bouncing between top & bottom
+fn clamp_bounce_angle(velocity: Vec3, max_angle: f32) -> Vec3 {
+    let max_vertical = velocity.x.abs() * max_angle.tan();
+    Vec3::new(
+        velocity.x,
+        velocity.y.clamp(-max_vertical, max_vertical),
+        velocity.z,
+    )
 }
 
 fn exp_lerp(min_val: f32, max_val: f32, t: f32) -> f32 {
     min_val * (max_val / min_val).powf(t)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paddle_bounce_center_hit_is_straight() {
+        let physics = BallPhysics::default();
+        let velocity = paddle_bounce_velocity(0.0, 0.0, -1.0, 2.0, &physics);
+        assert_eq!(velocity, Vec3::new(-2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_paddle_bounce_angle_is_clamped() {
+        let physics = BallPhysics::default();
+        // Edge hit with moving paddle exceeds max angle
+        let velocity = paddle_bounce_velocity(1.0, 4.0, 1.0, 2.0, &physics);
+        let angle = (velocity.y / velocity.x).atan();
+        assert!((angle - physics.max_bounce_angle).abs() < 1e-5);
+        assert_eq!(velocity.x, 2.0);
+    }
+
+    #[test]
+    fn test_paddle_velocity_deflects_ball() {
+        let physics = BallPhysics::default();
+        let still = paddle_bounce_velocity(0.2, 0.0, 1.0, 2.0, &physics);
+        let moving_up = paddle_bounce_velocity(0.2, 4.0, 1.0, 2.0, &physics);
+        assert!(moving_up.y > still.y);
+    }
+
+    #[test]
+    fn test_clamp_bounce_angle() {
+        let clamped = clamp_bounce_angle(Vec3::new(1.0, -5.0, 0.0), 45f32.to_radians());
+        assert!((clamped.y + 1.0).abs() < 1e-5);
+        let unchanged = clamp_bounce_angle(Vec3::new(1.0, 0.5, 0.0), 45f32.to_radians());
+        assert_eq!(unchanged, Vec3::new(1.0, 0.5, 0.0));
+    }
+}
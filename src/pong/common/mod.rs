@@ -3,22 +3,45 @@ use glam::{Mat4, Vec3};
 
 pub(crate) mod ball;
 pub(super) mod boundary;
+pub(crate) mod movement;
 pub(crate) mod paddle;
 pub(crate) mod player;
+pub(crate) mod score;
 
 use crate::{
-    cameras::component::CameraComponent, network::NetworkWorld, systems::physics::Transform,
+    cameras::{camera::orthographic_reverse_z, component::CameraComponent},
+    network::NetworkWorld,
+    systems::physics::Transform,
 };
 
+// Half height of the visible area at zoom 1
+const CAMERA_SCALE: f32 = 3.5;
+
+/// Orthographic projection of the field. Zoom > 1 shows less of the field
+pub(crate) fn camera_projection(zoom: f32) -> Mat4 {
+    let scale_y = CAMERA_SCALE / zoom;
+    let scale_x = scale_y * 16.0 / 9.0;
+    orthographic_reverse_z(
+        -scale_x,
+        scale_x,
+        -scale_y,
+        scale_y,
+        -CAMERA_SCALE,
+        CAMERA_SCALE,
+    )
+}
+
+pub(crate) fn default_camera_transform() -> Mat4 {
+    Mat4::from_translation(Vec3::X * 3.5)
+}
+
 pub(crate) fn setup_static_entities(world: &mut NetworkWorld) {
     // Spawn camera directly into world -> No replication
-    let scale_y = 3.5;
-    let scale_x = scale_y * 16.0 / 9.0;
-    let projection =
-        Mat4::orthographic_rh_gl(-scale_x, scale_x, -scale_y, scale_y, -scale_y, scale_y);
     world.get_world_mut().spawn((
-        Transform(Mat4::from_translation(Vec3::X * 3.5)),
-        CameraComponent { projection },
+        Transform(default_camera_transform()),
+        CameraComponent {
+            projection: camera_projection(1.0),
+        },
     ));
 
     // Spawn boundaries directly into world -> No replication required
@@ -0,0 +1,119 @@
+// Paddle movement rules. Shared by client prediction, the server & headless simulations, so
+// all of them arrive at the same paddle positions for the same inputs
+use glam::{Vec3, Vec4Swizzles};
+use hecs::{Entity, World};
+
+use crate::{
+    collision::CollisionEvent,
+    log_err,
+    pong::network::client::InputSample,
+    systems::physics::{Transform, Velocity},
+};
+
+use super::paddle::{PaddleControl, PaddleSpeed};
+
+/// Distance between the position reported by a client & the server position, before the server
+/// snaps the client back
+pub(crate) const MAX_POSITION_ERROR: f32 = 0.1;
+
+pub(crate) fn apply_input_buffer_sample(
+    world: &mut World,
+    sample: &InputSample,
+    player_entity: Entity,
+) {
+    // Directly to max speed.
+    // Improvement: Smoothing / acceleration
+    let input_velocity = Vec3::Y * sample.vertical_velocity;
+    log_err!(
+        world.exchange_one::<PaddleControl, PaddleControl>(
+            player_entity,
+            PaddleControl { input_velocity },
+        ),
+        "Failed to update paddle input velocity: {err}"
+    );
+}
+
+/// Sample with its velocity limited to the paddle speed. None if the sample is valid
+pub(crate) fn clamp_input_speed(sample: &InputSample, max_speed: f32) -> Option<InputSample> {
+    if sample.vertical_velocity.abs() <= max_speed {
+        return None;
+    }
+    Some(InputSample {
+        vertical_velocity: sample.vertical_velocity.clamp(-max_speed, max_speed),
+        ..sample.clone()
+    })
+}
+
+/// Position after moving with the velocities of the samples for one tick each. Boundary
+/// collisions are not simulated
+#[cfg(feature = "gui")]
+pub(crate) fn replay_inputs<'a>(
+    position: f32,
+    samples: impl IntoIterator<Item = &'a InputSample>,
+    dt: f32,
+) -> f32 {
+    samples.into_iter().fold(position, |position, sample| {
+        position + sample.vertical_velocity * dt
+    })
+}
+
+/// Calculate paddle velocity based on requested velocity and collide_and_slide algorithm
+/// Integration of velocity is done in general movement system
+pub fn system_paddle_movement(world: &mut World, collisions: &[CollisionEvent]) {
+    for (entity, (transform, velocity, movement, speed)) in
+        world.query_mut::<(&Transform, &mut Velocity, &PaddleControl, &PaddleSpeed)>()
+    {
+        let mut input_velocity = movement.input_velocity;
+        debug_assert!(
+            input_velocity.length_squared() <= speed.speed * speed.speed,
+            "Too high input velocity requested {input_velocity}",
+        );
+        if input_velocity.length_squared() < 1e-4 {
+            velocity.0 = Vec3::ZERO;
+        } else {
+            // Restrict vertical movement when colliding with top or bottom boundary
+            let relevant_collisions = collisions
+                .iter()
+                .filter(|e| e.a == entity || e.b == Some(entity));
+            let current_position = transform.0.w_axis.xyz();
+            for collision in relevant_collisions {
+                if collision.info.contact_point.y > current_position.y {
+                    input_velocity.y = input_velocity.y.min(0.0);
+                } else {
+                    input_velocity.y = input_velocity.y.max(0.0);
+                }
+            }
+
+            velocity.0 = input_velocity;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(client_tick: u32, vertical_velocity: f32) -> InputSample {
+        InputSample {
+            client_tick,
+            vertical_velocity,
+            position: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_clamp_input_speed() {
+        assert!(clamp_input_speed(&sample(1, -4.0), 4.0).is_none());
+        let clamped = clamp_input_speed(&sample(1, 40.0), 4.0).unwrap();
+        assert_eq!(clamped.vertical_velocity, 4.0);
+        assert_eq!(clamped.client_tick, 1);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_replay_inputs() {
+        let samples = [sample(1, 1.0), sample(2, 1.0), sample(3, -1.0)];
+        assert_eq!(replay_inputs(0.5, &samples, 0.5), 1.0);
+        assert_eq!(replay_inputs(0.5, &[], 0.5), 0.5);
+    }
+}
@@ -1,8 +1,8 @@
-use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
-use hecs::{Entity, World};
+use glam::{Mat4, Quat, Vec3};
+use hecs::Entity;
 
 use crate::{
-    collision::{ColliderBody, CollisionEvent},
+    collision::ColliderBody,
     network::{Authority, NetEntityId, NetworkReplicated, NetworkWorld},
     systems::physics::{Transform, Velocity},
 };
@@ -71,35 +71,3 @@ pub fn spawn_paddle(
     }
     (net_id, entity)
 }
-
-/// Calculate paddle velocity based on requested velocity and collide_and_slide algorithm
-/// Integration of velocity is done in general movement system
-pub fn system_paddle_movement(world: &mut World, collisions: &[CollisionEvent]) {
-    for (entity, (transform, velocity, movement, speed)) in
-        world.query_mut::<(&Transform, &mut Velocity, &PaddleControl, &PaddleSpeed)>()
-    {
-        let mut input_velocity = movement.input_velocity;
-        debug_assert!(
-            input_velocity.length_squared() <= speed.speed * speed.speed,
-            "Too high input velocity requested {input_velocity}",
-        );
-        if input_velocity.length_squared() < 1e-4 {
-            velocity.0 = Vec3::ZERO;
-        } else {
-            // Restrict vertical movement when colliding with top or bottom boundary
-            let relevant_collisions = collisions
-                .iter()
-                .filter(|e| e.a == entity || e.b == Some(entity));
-            let current_position = transform.0.w_axis.xyz();
-            for collision in relevant_collisions {
-                if collision.info.contact_point.y > current_position.y {
-                    input_velocity.y = input_velocity.y.min(0.0);
-                } else {
-                    input_velocity.y = input_velocity.y.max(0.0);
-                }
-            }
-
-            velocity.0 = input_velocity;
-        }
-    }
-}
@@ -1,31 +1,9 @@
-use glam::Vec3;
-use hecs::{Entity, World};
+use hecs::Entity;
 
-use crate::{
-    log_err,
-    network::{Authority, ClientId, NetEntityId, NetworkReplicated, NetworkWorld},
-    pong::{common::paddle::PaddleControl, network::client::InputSample},
-};
+use crate::network::{Authority, ClientId, NetEntityId, NetworkReplicated, NetworkWorld};
 
 use super::paddle::spawn_paddle;
 
-pub(crate) fn apply_input_buffer_sample(
-    world: &mut World,
-    sample: &InputSample,
-    player_entity: Entity,
-) {
-    // Directly to max speed.
-    // Improvement: Smoothing / acceleration
-    let input_velocity = Vec3::Y * sample.vertical_velocity;
-    log_err!(
-        world.exchange_one::<PaddleControl, PaddleControl>(
-            player_entity,
-            PaddleControl { input_velocity },
-        ),
-        "Failed to update paddle input velocity: {err}"
-    );
-}
-
 pub(crate) fn spawn_player(
     world: &mut NetworkWorld,
     player_slot: usize,
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::SIMULATION_DT;
+
+pub const DEFAULT_POINTS_TO_WIN: u32 = 5;
+/// Pause between a scored point & the next serve
+const SERVE_COUNTDOWN: Duration = Duration::from_secs(2);
+
+/// Score of a match. First player to reach points_to_win wins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchScore {
+    /// Points per player slot
+    pub points: [u32; 2],
+    pub points_to_win: u32,
+}
+
+impl MatchScore {
+    pub fn new(points_to_win: u32) -> MatchScore {
+        Self {
+            points: [0; 2],
+            points_to_win: points_to_win.max(1),
+        }
+    }
+
+    /// Awards point to the opponent of the loosing player. Returns winning slot if match is
+    /// decided
+    pub fn award_point(&mut self, loosing_player_slot: usize) -> Option<usize> {
+        let scoring_slot = 1 - loosing_player_slot;
+        self.points[scoring_slot] += 1;
+        self.winner()
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.points.iter().position(|p| *p >= self.points_to_win)
+    }
+}
+
+impl Default for MatchScore {
+    fn default() -> Self {
+        Self::new(DEFAULT_POINTS_TO_WIN)
+    }
+}
+
+/// Number of server ticks between a scored point & the next serve
+pub fn serve_countdown_ticks() -> u32 {
+    (SERVE_COUNTDOWN.as_nanos() / SIMULATION_DT.as_nanos()) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_award_point_to_opponent() {
+        let mut score = MatchScore::new(3);
+        assert_eq!(score.award_point(0), None);
+        assert_eq!(score.award_point(0), None);
+        assert_eq!(score.award_point(1), None);
+        assert_eq!(score.points, [1, 2]);
+    }
+
+    #[test]
+    fn test_match_decided_at_points_to_win() {
+        let mut score = MatchScore::new(2);
+        assert_eq!(score.award_point(1), None);
+        assert_eq!(score.award_point(1), Some(0));
+        assert_eq!(score.winner(), Some(0));
+    }
+}
@@ -5,6 +5,8 @@ pub enum ClientMessage {
     RequestJoin,
     InputSync {
         last_acked_client_tick: u32,
+        // Baseline for delta compressed snapshots
+        last_received_snapshot_tick: Option<u32>,
         unacked_inputs: Vec<InputSample>,
     },
 }
@@ -13,6 +15,8 @@ pub enum ClientMessage {
 pub struct InputSample {
     pub(crate) client_tick: u32,
     pub(crate) vertical_velocity: f32,
+    // Paddle height when the input was sampled. Validated by the server
+    pub(crate) position: f32,
 }
 
 #[cfg(test)]
@@ -24,6 +28,7 @@ mod tests {
         let inputs: Vec<InputSample> = vec![];
         let cmd = ClientMessage::InputSync {
             last_acked_client_tick: 1,
+            last_received_snapshot_tick: Some(2),
             unacked_inputs: inputs.clone(),
         };
         let encoded = bincode::serialize(&cmd).unwrap();
@@ -34,6 +39,7 @@ mod tests {
                 decoded,
                 ClientMessage::InputSync {
                     last_acked_client_tick: 1,
+                    last_received_snapshot_tick: Some(2),
                     unacked_inputs: inputs,
                 },
             ),
@@ -4,6 +4,8 @@ use super::server::ServerMessage;
 
 pub trait NetworkCodec {
     type Error: Display + std::fmt::Debug;
+    /// Announced during the handshake. Client & server have to use the same codec
+    const NAME: &'static str;
 
     fn encode(cmd: &ServerMessage) -> Result<Vec<u8>, Self::Error>;
     fn decode(input: &[u8]) -> Result<ServerMessage, Self::Error>;
@@ -13,6 +15,7 @@ pub struct JsonCodec;
 
 impl NetworkCodec for JsonCodec {
     type Error = serde_json::Error;
+    const NAME: &'static str = "json";
 
     fn encode(cmd: &ServerMessage) -> Result<Vec<u8>, Self::Error> {
         todo!(
@@ -29,6 +32,7 @@ impl NetworkCodec for JsonCodec {
 pub struct BincodeCodec;
 impl NetworkCodec for BincodeCodec {
     type Error = Box<bincode::ErrorKind>;
+    const NAME: &'static str = "bincode";
 
     fn encode(cmd: &ServerMessage) -> Result<Vec<u8>, Self::Error> {
         bincode::serialize(cmd)
@@ -7,6 +7,7 @@ impl Clone for InputSample {
         Self {
             client_tick: self.client_tick,
             vertical_velocity: self.vertical_velocity,
+            position: self.position,
         }
     }
 }
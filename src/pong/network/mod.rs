@@ -3,6 +3,15 @@ pub(super) mod codec;
 pub(super) mod input;
 pub(super) mod server;
 
+use crate::network::ProtocolInfo;
+
+/// Incremented whenever client or server messages change incompatibly
+const PROTOCOL_VERSION: u32 = 1;
+
+pub(super) fn protocol_info<C: NetworkCodec>() -> ProtocolInfo {
+    ProtocolInfo::new(PROTOCOL_VERSION, C::NAME)
+}
+
 pub use codec::BincodeCodec;
 pub(super) use codec::NetworkCodec;
 pub use server::ServerMessage;
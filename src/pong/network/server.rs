@@ -1,31 +1,79 @@
 use serde::{Deserialize, Serialize};
 
-use crate::network::{EntitySnapshot, NetEntityId};
+use crate::{
+    network::{ClientId, DeltaSnapshot, NetEntityId},
+    pong::common::score::MatchScore,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ServerMessage {
     SendSnapshot {
         server_tick: u32,
         last_acked_client_tick: u32,
-        data: Vec<EntitySnapshot>,
+        snapshot: DeltaSnapshot,
     },
+    /// Lobby is full. First serve happens at serve_at_server_tick
+    StartMatch {
+        server_tick: u32,
+        serve_at_server_tick: u32,
+        score: MatchScore,
+    },
+    /// Ball has been served
     StartRound {
         ball_net_entity: NetEntityId,
         server_tick: u32,
     },
+    /// Ball left the field. Next serve happens at serve_at_server_tick
+    PointScored {
+        server_tick: u32,
+        serve_at_server_tick: u32,
+        score: MatchScore,
+    },
     SpawnPlayer {
         player_net_entity: NetEntityId,
         player_slot: usize,
     },
+    /// Paddle of another player. Owner controls it, all others interpolate snapshots
     SpawnPaddle {
         net_entity_id: NetEntityId,
         player_slot: usize,
+        owner: ClientId,
+    },
+    /// Current match state for a newly joined spectator. Paddles are sent via SpawnPaddle.
+    /// Score is None while waiting for players
+    SpectatorSync {
+        server_tick: u32,
+        score: Option<MatchScore>,
+        serve_at_server_tick: Option<u32>,
+        ball_net_entity: Option<NetEntityId>,
     },
-    EndRound {
+    /// Join request denied, e.g. lobby full or match in progress
+    JoinRejected {
+        reason: String,
+    },
+    EndMatch {
         server_tick: u32,
-        loosing_player_slot: usize,
+        winning_player_slot: usize,
+        score: MatchScore,
     },
     DespawnEntity {
         net_entity_id: NetEntityId,
     },
+    /// Owned paddle diverged from the server. Position before the input of client_tick applied
+    CorrectPosition {
+        net_entity_id: NetEntityId,
+        client_tick: u32,
+        position: f32,
+    },
+    /// Tick rate achieved by the server simulation
+    TickRate {
+        ticks_per_second: f32,
+    },
+    /// Ball state changed after paddle bounce. Transform is replicated via snapshots
+    BallState {
+        net_entity_id: NetEntityId,
+        speed: f32,
+        bounces: usize,
+        spin: f32,
+    },
 }
@@ -15,6 +15,8 @@ pub(super) struct PlayerInfo {
     pub(super) input_buffer: ClientInputBuffer,
     pub(super) client_id: ClientId,
     pub(super) player_net_id: Option<NetEntityId>,
+    // Baseline for delta compressed snapshots
+    pub(super) last_snapshot_ack: Option<u32>,
 }
 
 impl PlayerInfo {
@@ -23,6 +25,7 @@ impl PlayerInfo {
             client_id,
             input_buffer: ClientInputBuffer::new(),
             player_net_id: None,
+            last_snapshot_ack: None,
         }
     }
 }
@@ -65,6 +68,13 @@ impl Lobby {
         Ok(info)
     }
 
+    /// Player slot of client, if joined
+    pub fn get_slot(&self, client_id: ClientId) -> Option<usize> {
+        self.players
+            .iter()
+            .position(|p| p.as_ref().is_some_and(|info| info.client_id == client_id))
+    }
+
     pub fn is_full(&self) -> bool {
         self.players.iter().all(|f| f.is_some())
     }
@@ -96,3 +106,25 @@ impl Lobby {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test_client;
+
+    #[test]
+    fn test_join_rejected_if_full_or_joined() {
+        let mut lobby = Lobby::new();
+        assert_eq!(lobby.join(test_client(1)), Ok(0));
+        assert!(lobby.join(test_client(1)).is_err());
+        assert_eq!(lobby.join(test_client(2)), Ok(1));
+        assert!(lobby.is_full());
+        assert!(lobby.join(test_client(3)).is_err());
+        assert_eq!(lobby.get_slot(test_client(2)), Some(1));
+        assert_eq!(lobby.get_slot(test_client(3)), None);
+
+        // Slot of a leaving player is free again
+        lobby.remove(test_client(1)).unwrap();
+        assert_eq!(lobby.join(test_client(3)), Ok(0));
+    }
+}
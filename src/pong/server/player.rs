@@ -1,10 +1,26 @@
 use log::{debug, error, warn};
 
-use crate::{network::NetworkWorld, pong::common::player::apply_input_buffer_sample};
+use crate::{
+    network::{ClientId, NetworkWorld},
+    pong::{
+        common::{
+            movement::{MAX_POSITION_ERROR, apply_input_buffer_sample, clamp_input_speed},
+            paddle::PaddleSpeed,
+        },
+        network::ServerMessage,
+    },
+    systems::physics::Transform,
+};
 
 use super::lobby::Lobby;
 
-pub(super) fn apply_player_inputs(world: &mut NetworkWorld, lobby: &mut Lobby) {
+/// Applies the oldest buffered input of every player. Inputs exceeding the paddle speed are
+/// clamped. Returns corrections for clients whose reported position diverged from the server
+pub(super) fn apply_player_inputs(
+    world: &mut NetworkWorld,
+    lobby: &mut Lobby,
+) -> Vec<(ClientId, ServerMessage)> {
+    let mut corrections = Vec::new();
     for player in lobby.iter_players_mut() {
         let sample = match player.input_buffer.get_oldest() {
             Some(v) => v,
@@ -27,7 +43,43 @@ pub(super) fn apply_player_inputs(world: &mut NetworkWorld, lobby: &mut Lobby) {
                 continue;
             }
         };
-        apply_input_buffer_sample(world.get_world_mut(), sample, player_entity);
+        let position = match world.get_world().get::<&Transform>(player_entity) {
+            Ok(transform) => transform.0.w_axis.y,
+            Err(err) => {
+                error!("Missing transform of player {net_entity_id}: {err}");
+                continue;
+            }
+        };
+        let speed = match world.get_world().get::<&PaddleSpeed>(player_entity) {
+            Ok(speed) => speed.speed,
+            Err(err) => {
+                error!("Missing paddle speed of player {net_entity_id}: {err}");
+                continue;
+            }
+        };
+        if (sample.position - position).abs() > MAX_POSITION_ERROR {
+            warn!(
+                "Client {} reported paddle at {:.2}, server has {position:.2}. Correcting",
+                player.client_id, sample.position
+            );
+            corrections.push((
+                player.client_id,
+                ServerMessage::CorrectPosition {
+                    net_entity_id,
+                    client_tick: sample.client_tick,
+                    position,
+                },
+            ));
+        }
+        if let Some(clamped) = clamp_input_speed(sample, speed) {
+            warn!(
+                "Client {} exceeded paddle speed: {:.2} > {speed:.2}",
+                player.client_id, sample.vertical_velocity
+            );
+            apply_input_buffer_sample(world.get_world_mut(), &clamped, player_entity);
+        } else {
+            apply_input_buffer_sample(world.get_world_mut(), sample, player_entity);
+        }
         debug!(
             "Applying sample at client tick {} for player {}. {} samples remaining",
             sample.client_tick,
@@ -38,4 +90,5 @@ pub(super) fn apply_player_inputs(world: &mut NetworkWorld, lobby: &mut Lobby) {
             .input_buffer
             .update_acked_client_tick(sample.client_tick);
     }
+    corrections
 }
@@ -2,9 +2,10 @@ use log::error;
 
 use crate::{
     network::{
-        ClientId, NetworkServer, ServerDownstreamPayload, ServerEvent, ServerUpstreamPayload,
+        BandwidthUsage, ClientId, NetworkServer, PacketPriority, ServerDownstreamPayload,
+        ServerEvent, ServerUpstreamPayload,
     },
-    pong::network::{NetworkCodec, ServerMessage, client::ClientMessage},
+    pong::network::{NetworkCodec, ServerMessage, client::ClientMessage, protocol_info},
 };
 
 use std::sync::mpsc::Receiver;
@@ -20,9 +21,10 @@ pub struct ServerProtocol<C: NetworkCodec> {
 
 impl<C: NetworkCodec> ServerProtocol<C> {
     pub fn new(
-        server: NetworkServer,
+        mut server: NetworkServer,
         upstream_payload_rx: Receiver<ServerUpstreamPayload>,
     ) -> Result<Self, String> {
+        server.set_protocol(protocol_info::<C>());
         Ok(ServerProtocol {
             server,
             codec: std::marker::PhantomData,
@@ -41,10 +43,37 @@ impl<C: NetworkCodec> ServerProtocol<C> {
         None
     }
 
+    /// Stops the transport & closes the socket
+    pub fn shutdown(&self) {
+        self.server.shutdown();
+    }
+
     pub fn try_recv_event(&mut self) -> Option<ServerEvent> {
         self.server.try_recv_event()
     }
 
+    pub fn player_name(&self, client: ClientId) -> Option<String> {
+        self.server.player_name(client)
+    }
+
+    /// Clients observing the game. Receive broadcasts, but never send inputs
+    pub fn spectators(&self) -> Vec<ClientId> {
+        self.server.spectators()
+    }
+
+    pub fn bandwidth_usage(&self) -> Vec<(ClientId, BandwidthUsage)> {
+        self.server.bandwidth_usage()
+    }
+
+    /// For updates superseded by the next one. Dropped first if the client exceeds its bandwidth
+    pub fn send_unreliable_to(&self, cmd: ServerMessage, client: ClientId) -> Result<(), String> {
+        let bytes = C::encode(&cmd).map_err(|e| format!("Failed to encode: {e}"))?;
+        self.server.send_game_packet(
+            ServerDownstreamPayload::new(bytes, Some(client))
+                .with_priority(PacketPriority::Unreliable),
+        )
+    }
+
     pub fn send_to(&self, cmd: ServerMessage, client: ClientId) -> Result<(), String> {
         let bytes = C::encode(&cmd).map_err(|e| format!("Failed to encode: {e}"))?;
         self.server
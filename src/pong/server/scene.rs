@@ -10,12 +10,14 @@ use crate::{
     collision::{CollisionEvent, system_collisions},
     config::BROADCAST_DT,
     log_err,
-    network::{NetworkWorld, ServerEvent},
+    network::{ClientId, DeltaEncoder, InterestManager, NetworkWorld, ServerEvent},
     pong::{
         BincodeCodec, ServerProtocol,
         common::{
-            ball::{PongBall, bounce_balls},
-            paddle::{PaddleControl, system_paddle_movement},
+            ball::{BallPhysics, PongBall, bounce_balls, system_ball_spin},
+            movement::system_paddle_movement,
+            paddle::PaddleControl,
+            score::{MatchScore, serve_countdown_ticks},
             setup_static_entities,
         },
         network::ServerMessage,
@@ -27,11 +29,20 @@ use crate::{
 use super::{
     lobby::Lobby,
     player::apply_player_inputs,
-    sync::{server_process_client_message, server_send_snapshots},
+    sync::{
+        server_process_client_message, server_send_ball_state, server_send_snapshots,
+        server_serve_ball, server_sync_spectator,
+    },
 };
 
 pub(super) enum ServerGameState {
     WaitingForPlayers,
+    // Match in progress, countdown until ball is served
+    Serving {
+        serve_at_tick: u32,
+        receiving_slot: usize,
+    },
+    // Ball in play
     Running,
 }
 
@@ -42,6 +53,11 @@ pub struct PongServerScene {
     protocol: ServerProtocol<BincodeCodec>,
     lobby: Lobby,
     server_tick: u32,
+    ball_physics: BallPhysics,
+    score: MatchScore,
+    interest: InterestManager,
+    delta_encoder: DeltaEncoder,
+    tick_rate: Option<f32>,
 
     last_broadcast: Instant,
 }
@@ -57,28 +73,60 @@ impl PongServerScene {
             world,
             lobby: Lobby::new(),
             server_tick: 0,
+            ball_physics: BallPhysics::default(),
+            score: MatchScore::default(),
+            interest: InterestManager::default(),
+            delta_encoder: DeltaEncoder::new(),
+            tick_rate: None,
             last_broadcast: Instant::now(),
         })
     }
 
-    fn end_round(&mut self, looser_slot: usize) {
+    /// Ball left the field on the side of the loosing player
+    fn point_scored(&mut self, looser_slot: usize) {
+        log_err!(
+            self.world.despawn_all::<&PongBall>(),
+            "Could not despawn balls {err}"
+        );
+        if let Some(winner_slot) = self.score.award_point(looser_slot) {
+            self.end_match(winner_slot);
+            return;
+        }
+        info!(
+            "[T{}] Player {} lost the point. Score {:?}",
+            self.server_tick, looser_slot, self.score.points
+        );
+        let serve_at_tick = self.server_tick + serve_countdown_ticks();
+        self.game_state = ServerGameState::Serving {
+            serve_at_tick,
+            receiving_slot: looser_slot,
+        };
+        log_err!(
+            self.protocol.broadcast(ServerMessage::PointScored {
+                server_tick: self.server_tick,
+                serve_at_server_tick: serve_at_tick,
+                score: self.score.clone(),
+            }),
+            "Failed to broadcast scored point: {err}"
+        );
+    }
+
+    fn end_match(&mut self, winner_slot: usize) {
         info!(
-            "[T{}] Ending round. Player {} lost",
-            self.server_tick, looser_slot
+            "[T{}] Ending match. Player {} won {:?}",
+            self.server_tick, winner_slot, self.score.points
         );
 
         // Broadcast game over
-        self.protocol
-            .broadcast(ServerMessage::EndRound {
-                server_tick: self.server_tick,
-                loosing_player_slot: looser_slot,
-            })
-            .expect("Failed to broadcast end of round");
-        // Despawn on server
         log_err!(
-            self.world.despawn_all::<&PongBall>(),
-            "Could not despawn balls {err}"
+            self.protocol.broadcast(ServerMessage::EndMatch {
+                server_tick: self.server_tick,
+                winning_player_slot: winner_slot,
+                score: self.score.clone(),
+            }),
+            "Failed to broadcast end of match: {err}"
         );
+        // Despawn on server
         log_err!(
             self.world.despawn_all::<&PaddleControl>(),
             "Could not despawn paddles {err}"
@@ -86,26 +134,61 @@ impl PongServerScene {
         self.game_state = ServerGameState::WaitingForPlayers;
         // Reset lobby & frame
         self.lobby = Lobby::new();
+        self.interest = InterestManager::default();
+        // Server tick restarts. Previously sent snapshots are no valid baselines anymore
+        self.delta_encoder = DeltaEncoder::new();
         self.server_tick = 0;
     }
 
+    fn player_left(&mut self, id: ClientId) {
+        self.interest.remove_client(id);
+        self.delta_encoder.remove_client(id);
+        if let Some(winner_slot) = forfeit_winner(&self.game_state, &self.lobby, id) {
+            info!("Player {id} left during match. Forfeiting");
+            log_err!(
+                self.world.despawn_all::<&PongBall>(),
+                "Could not despawn balls {err}"
+            );
+            self.end_match(winner_slot);
+            return;
+        }
+        log_err!(
+            (|| {
+                let player_info = self.lobby.remove(id)?;
+                let net_entity_id = player_info
+                    .player_net_id
+                    .ok_or("Missing player net entity")?;
+                self.world.despawn_net_id(net_entity_id)?;
+                self.protocol
+                    .broadcast(ServerMessage::DespawnEntity { net_entity_id })
+            })(),
+            "Unable to remove player {id}: {err}"
+        );
+    }
+
     fn tick(&mut self, dt: f32) {
         while let Some(event) = self.protocol.try_recv_event() {
-            log_err!(
-                (|| match event {
-                    ServerEvent::ClientDisconnected(id) => {
-                        let player_info = self.lobby.remove(id)?;
-                        let net_entity_id = player_info
-                            .player_net_id
-                            .ok_or("Missing player net entity")?;
-                        self.world.despawn_net_id(net_entity_id)?;
-                        self.protocol
-                            .broadcast(ServerMessage::DespawnEntity { net_entity_id })
+            match event {
+                ServerEvent::ClientDisconnected(id) => self.player_left(id),
+                ServerEvent::SpectatorJoined(id) => {
+                    // Player switched to spectating
+                    if self.lobby.get_slot(id).is_some() {
+                        self.player_left(id);
                     }
-                    ServerEvent::ClientConnected(_id) => Ok(()),
-                })(),
-                "Unable to process server event {err}"
-            );
+                    log_err!(
+                        server_sync_spectator(
+                            &self.world,
+                            &self.protocol,
+                            &self.game_state,
+                            &self.score,
+                            id,
+                            self.server_tick
+                        ),
+                        "Unable to sync spectator {id}: {err}"
+                    );
+                }
+                ServerEvent::ClientConnected(_) | ServerEvent::SpectatorLeft(_) => {}
+            }
         }
         while let Some(message) = self.protocol.try_recv() {
             server_process_client_message(
@@ -114,25 +197,67 @@ impl PongServerScene {
                 &self.protocol,
                 &mut self.game_state,
                 &mut self.lobby,
+                &mut self.score,
                 self.server_tick,
             );
         }
-        if matches!(self.game_state, ServerGameState::Running) {
-            apply_player_inputs(&mut self.world, &mut self.lobby);
+        if let ServerGameState::Serving {
+            serve_at_tick,
+            receiving_slot,
+        } = self.game_state
+            && self.server_tick >= serve_at_tick
+        {
+            log_err!(
+                server_serve_ball(
+                    &mut self.world,
+                    &self.protocol,
+                    receiving_slot,
+                    self.server_tick
+                ),
+                "Unable to serve ball: {err}"
+            );
+            self.game_state = ServerGameState::Running;
+        }
+        // Paddles keep moving during serve countdown
+        if matches!(
+            self.game_state,
+            ServerGameState::Running | ServerGameState::Serving { .. }
+        ) {
+            for (client, correction) in apply_player_inputs(&mut self.world, &mut self.lobby) {
+                log_err!(
+                    self.protocol.send_to(correction, client),
+                    "Failed to send position correction to {client}: {err}"
+                );
+            }
             // Collision systems
             self.collisions = system_collisions(self.world.get_world_mut());
-            let loosing_player = bounce_balls(self.world.get_world_mut(), &self.collisions);
-            if let Some(loosing_player_slot) = loosing_player {
-                self.end_round(loosing_player_slot);
+            let bounce_events = bounce_balls(
+                self.world.get_world_mut(),
+                &self.collisions,
+                &self.ball_physics,
+            );
+            for ball in bounce_events.paddle_hits {
+                server_send_ball_state(&self.world, &self.protocol, ball);
+            }
+            if let Some(loosing_player_slot) = bounce_events.loosing_player {
+                self.point_scored(loosing_player_slot);
             }
             system_paddle_movement(self.world.get_world_mut(), &self.collisions);
 
             // Physics simulation
+            system_ball_spin(self.world.get_world_mut(), &self.ball_physics, dt);
             system_movement(self.world.get_world_mut(), dt);
 
             // Broadcast
             if self.last_broadcast.elapsed() >= BROADCAST_DT {
-                server_send_snapshots(&self.world, &self.protocol, &self.lobby, self.server_tick);
+                server_send_snapshots(
+                    &self.world,
+                    &self.protocol,
+                    &self.lobby,
+                    &mut self.interest,
+                    &mut self.delta_encoder,
+                    self.server_tick,
+                );
                 self.last_broadcast = Instant::now();
             }
         }
@@ -150,11 +275,29 @@ impl BaseScene for PongServerScene {
         "Pong server".to_string()
     }
 
-    fn tick(&mut self, dt: f32) {
+    fn is_networked(&self) -> bool {
+        true
+    }
+
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>> {
         PongServerScene::tick(self, dt);
+        Ok(())
+    }
+
+    fn on_enter(&mut self) {}
+
+    fn on_exit(&mut self) {
+        self.protocol.shutdown();
     }
 
-    fn start(&mut self) {}
+    fn on_tick_rate_report(&mut self, ticks_per_second: f32) {
+        self.tick_rate = Some(ticks_per_second);
+        log_err!(
+            self.protocol
+                .broadcast(ServerMessage::TickRate { ticks_per_second }),
+            "Failed to broadcast tick rate: {err}"
+        );
+    }
 }
 
 #[cfg(feature = "gui")]
@@ -163,19 +306,84 @@ impl crate::scenes::scene::GuiScene for PongServerScene {
         todo!()
     }
 
-    fn render(&mut self, gl: &glow::Context, dt: Duration) {
+    fn render(&mut self, gl: &glow::Context, dt: Duration) -> Result<(), Box<dyn Error>> {
         unsafe {
-            gl.clear_color(0.05, 0.05, 0.1, 1.0);
             gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
+        Ok(())
     }
 
     fn render_ui(&mut self, ui: &mut imgui::Ui) {
         ui.window("Scene info")
-            .size([150.0, 100.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
             .position([500.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("Tick: {}", self.server_tick));
+                if let Some(tick_rate) = self.tick_rate {
+                    ui.text(format!("Tick rate: {tick_rate:.1}Hz"));
+                }
+                ui.text(format!("Spectators: {}", self.protocol.spectators().len()));
+                ui.text(format!(
+                    "Score: {} : {}",
+                    self.score.points[0], self.score.points[1]
+                ));
+                if matches!(self.game_state, ServerGameState::WaitingForPlayers) {
+                    // Applies to next match
+                    let mut points_to_win = self.score.points_to_win as i32;
+                    if ui.slider("Points to win", 1, 21, &mut points_to_win) {
+                        self.score.points_to_win = points_to_win as u32;
+                    }
+                }
+                ui.separator();
+                for (client, usage) in self.protocol.bandwidth_usage() {
+                    ui.text(format!(
+                        "{client}: {:.1} kbps, {} dropped, {} queued",
+                        usage.bytes_per_second as f32 * 8.0 / 1000.0,
+                        usage.dropped_packets,
+                        usage.queued_packets
+                    ));
+                }
+                ui.separator();
+                self.ball_physics.render_ui(ui);
             });
     }
 }
+
+/// Slot of the remaining player if the client leaves during a match. Its opponent forfeits
+fn forfeit_winner(game_state: &ServerGameState, lobby: &Lobby, client: ClientId) -> Option<usize> {
+    if matches!(game_state, ServerGameState::WaitingForPlayers) {
+        return None;
+    }
+    lobby.get_slot(client).map(|slot| 1 - slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test_client;
+
+    #[test]
+    fn test_forfeit_on_disconnect() {
+        let mut lobby = Lobby::new();
+        lobby.join(test_client(1)).unwrap();
+        lobby.join(test_client(2)).unwrap();
+        let serving = ServerGameState::Serving {
+            serve_at_tick: 10,
+            receiving_slot: 1,
+        };
+        assert_eq!(forfeit_winner(&serving, &lobby, test_client(1)), Some(1));
+        assert_eq!(
+            forfeit_winner(&ServerGameState::Running, &lobby, test_client(2)),
+            Some(0)
+        );
+        // Spectators & players leaving between matches end nothing
+        assert_eq!(
+            forfeit_winner(&ServerGameState::Running, &lobby, test_client(3)),
+            None
+        );
+        assert_eq!(
+            forfeit_winner(&ServerGameState::WaitingForPlayers, &lobby, test_client(1)),
+            None
+        );
+    }
+}
@@ -1,15 +1,20 @@
 use glam::Vec3;
+use hecs::Entity;
 use log::{error, info, trace, warn};
 
 use crate::{
     log_err,
-    network::{ClientId, EntitySnapshot, NetworkReplicated, NetworkWorld},
+    network::{
+        Authority, ClientId, DeltaEncoder, EntitySnapshot, InterestManager, NetworkReplicated,
+        NetworkWorld,
+    },
     pong::{
         BincodeCodec,
         common::{
             ball::{BALL_MIN_SPEED, PongBall, spawn_ball},
             paddle::PaddleId,
             player::spawn_player,
+            score::{MatchScore, serve_countdown_ticks},
         },
         network::{ServerMessage, client::ClientMessage},
     },
@@ -24,19 +29,14 @@ pub(super) fn server_process_client_message(
     protocol: &ServerProtocol<BincodeCodec>,
     game_state: &mut ServerGameState,
     lobby: &mut Lobby,
+    score: &mut MatchScore,
     frame: u32,
 ) {
     let (cmd, client) = msg;
     trace!("Server received cmd {cmd:?} from {client}");
     let result: Result<(), String> = (|| match &cmd {
         ClientMessage::RequestJoin => {
-            if world.query::<&PongBall>().iter().next().is_some() {
-                return Err("Game is still in progress. Cannot spawn new ball".to_string());
-            } else if !matches!(game_state, ServerGameState::WaitingForPlayers) {
-                return Err(
-                    "Join requested, but server does not accept new players right now".to_string(),
-                );
-            }
+            check_join_allowed(world, game_state)?;
 
             // Spawn player
             let player_slot = lobby.join(client)?;
@@ -54,62 +54,56 @@ pub(super) fn server_process_client_message(
                 client,
             )?;
 
-            // Spawn paddle of new player in other clients
-            for other_player in lobby.others(client) {
+            // Spawn paddle of new player in other clients & spectators
+            for other_player in lobby
+                .others(client)
+                .into_iter()
+                .chain(protocol.spectators())
+            {
                 protocol.send_to(
                     ServerMessage::SpawnPaddle {
                         net_entity_id: player_net_entity,
                         player_slot,
+                        owner: client,
                     },
                     other_player,
                 )?;
             }
             // Spawn paddles of other client for new player
-            for (paddle_entity, paddle_id) in world.query::<&PaddleId>().iter() {
-                if paddle_entity == player_entity_id {
-                    // Skip our own paddle
-                    continue;
-                }
-                let net_entity_id = world
-                    .get_net_entity_id(&paddle_entity)
-                    .ok_or("Invalid net entity mapping for paddle".to_string())?;
-                protocol.send_to(
-                    ServerMessage::SpawnPaddle {
-                        net_entity_id: *net_entity_id,
-                        player_slot: paddle_id.slot,
-                    },
-                    client,
-                )?;
-            }
+            send_paddles(world, protocol, client, Some(player_entity_id))?;
 
-            // Start game if final player joined
+            // Start match if final player joined
+            let name = protocol.player_name(client).unwrap_or_default();
             if lobby.is_full() {
-                info!("Player {client} joined. Lobby is ready. Starting round");
-                *game_state = ServerGameState::Running;
-                let (ball_net_entity, entity) = spawn_ball(world, None);
-                let direction = Vec3::new(1.0, 0.5, 0.0).normalize();
-                log_err!(
-                    world
-                        .get_world_mut()
-                        .insert(entity, (Velocity(direction * BALL_MIN_SPEED),)),
-                    "Could not add ball speed {err}"
-                );
-                protocol.broadcast(ServerMessage::StartRound {
-                    ball_net_entity,
+                info!("Player {name} ({client}) joined. Lobby is ready. Starting match");
+                *score = MatchScore::new(score.points_to_win);
+                let serve_at_tick = frame + serve_countdown_ticks();
+                *game_state = ServerGameState::Serving {
+                    serve_at_tick,
+                    receiving_slot: 1,
+                };
+                protocol.broadcast(ServerMessage::StartMatch {
                     server_tick: frame,
+                    serve_at_server_tick: serve_at_tick,
+                    score: score.clone(),
                 })
             } else {
-                info!("Player {client} joined. Waiting for more players to join...");
+                info!("Player {name} ({client}) joined. Waiting for more players to join...");
                 Ok(())
             }
         }
-        ClientMessage::InputSync { unacked_inputs, .. } => {
+        ClientMessage::InputSync {
+            unacked_inputs,
+            last_received_snapshot_tick,
+            ..
+        } => {
             // Store client provided inputs in server-side copy
             match lobby.get_player_info_mut(client) {
                 Some(player_info) => {
                     player_info
                         .input_buffer
                         .set_buffer(unacked_inputs.to_owned());
+                    player_info.last_snapshot_ack = *last_received_snapshot_tick;
                 }
                 None => {
                     warn!("Ignoring player input from unknown client {client}");
@@ -120,13 +114,150 @@ pub(super) fn server_process_client_message(
     })();
     if let Err(err) = result {
         error!("Server failed to process cmd {cmd:?}: {err}");
+        if matches!(cmd, ClientMessage::RequestJoin) {
+            log_err!(
+                protocol.send_to(ServerMessage::JoinRejected { reason: err }, client),
+                "Unable to reject join request: {err}"
+            );
+        }
     }
 }
 
+/// Spawns ball moving towards the receiving player & notifies clients
+pub(super) fn server_serve_ball(
+    world: &mut NetworkWorld,
+    protocol: &ServerProtocol<BincodeCodec>,
+    receiving_slot: usize,
+    server_tick: u32,
+) -> Result<(), String> {
+    let (ball_net_entity, entity) = spawn_ball(world, None);
+    // Slot 0 defends the left side
+    let direction_x = if receiving_slot == 0 { -1.0 } else { 1.0 };
+    let direction = Vec3::new(direction_x, 0.5, 0.0).normalize();
+    world
+        .get_world_mut()
+        .insert(entity, (Velocity(direction * BALL_MIN_SPEED),))
+        .map_err(|err| format!("Could not add ball speed {err}"))?;
+    protocol.broadcast(ServerMessage::StartRound {
+        ball_net_entity,
+        server_tick,
+    })
+}
+
+/// Brings a newly joined spectator up to date: Paddles, score & ball in play
+pub(super) fn server_sync_spectator(
+    world: &NetworkWorld,
+    protocol: &ServerProtocol<BincodeCodec>,
+    game_state: &ServerGameState,
+    score: &MatchScore,
+    spectator: ClientId,
+    server_tick: u32,
+) -> Result<(), String> {
+    send_paddles(world, protocol, spectator, None)?;
+    let (score, serve_at_server_tick) = match game_state {
+        ServerGameState::WaitingForPlayers => (None, None),
+        ServerGameState::Serving { serve_at_tick, .. } => {
+            (Some(score.clone()), Some(*serve_at_tick))
+        }
+        ServerGameState::Running => (Some(score.clone()), None),
+    };
+    let ball_net_entity = world
+        .query::<&PongBall>()
+        .iter()
+        .next()
+        .and_then(|(ball, _)| world.get_net_entity_id(&ball).copied());
+    protocol.send_to(
+        ServerMessage::SpectatorSync {
+            server_tick,
+            score,
+            serve_at_server_tick,
+            ball_net_entity,
+        },
+        spectator,
+    )
+}
+
+// New players can only join between matches
+fn check_join_allowed(world: &NetworkWorld, game_state: &ServerGameState) -> Result<(), String> {
+    if world.query::<&PongBall>().iter().next().is_some() {
+        return Err("Game is still in progress. Cannot spawn new ball".to_string());
+    } else if !matches!(game_state, ServerGameState::WaitingForPlayers) {
+        return Err("Join requested, but server does not accept new players right now".to_string());
+    }
+    Ok(())
+}
+
+/// Spawns all paddles except `skip` in the given client
+fn send_paddles(
+    world: &NetworkWorld,
+    protocol: &ServerProtocol<BincodeCodec>,
+    client: ClientId,
+    skip: Option<Entity>,
+) -> Result<(), String> {
+    for message in paddle_spawn_messages(world, skip)? {
+        protocol.send_to(message, client)?;
+    }
+    Ok(())
+}
+
+// Spawn messages of all paddles except the skipped one, carrying their owning client
+fn paddle_spawn_messages(
+    world: &NetworkWorld,
+    skip: Option<Entity>,
+) -> Result<Vec<ServerMessage>, String> {
+    let mut messages = Vec::new();
+    for (paddle_entity, (paddle_id, replication)) in
+        world.query::<(&PaddleId, &NetworkReplicated)>().iter()
+    {
+        if Some(paddle_entity) == skip {
+            continue;
+        }
+        let Authority::Client(owner) = replication.authority else {
+            return Err("Paddle without owning client".to_string());
+        };
+        let net_entity_id = world
+            .get_net_entity_id(&paddle_entity)
+            .ok_or("Invalid net entity mapping for paddle".to_string())?;
+        messages.push(ServerMessage::SpawnPaddle {
+            net_entity_id: *net_entity_id,
+            player_slot: paddle_id.slot,
+            owner,
+        });
+    }
+    Ok(messages)
+}
+
+/// Broadcasts speed, bounces & spin of ball. Clients only display these values
+pub(super) fn server_send_ball_state(
+    world: &NetworkWorld,
+    protocol: &ServerProtocol<BincodeCodec>,
+    ball_entity: Entity,
+) {
+    let Some(net_entity_id) = world.get_net_entity_id(&ball_entity) else {
+        error!("Failed to broadcast ball state for entity {ball_entity:?}: No net entity id found");
+        return;
+    };
+    let Ok(ball) = world.get_world().get::<&PongBall>(ball_entity) else {
+        error!("Failed to broadcast ball state: Entity {ball_entity:?} is not a ball");
+        return;
+    };
+    log_err!(
+        protocol.broadcast(ServerMessage::BallState {
+            net_entity_id: *net_entity_id,
+            speed: ball.speed,
+            bounces: ball.bounces,
+            spin: ball.spin,
+        }),
+        "Failure broadcasting ball state: {err}"
+    );
+}
+
 pub(super) fn server_send_snapshots(
     world: &NetworkWorld,
     protocol: &ServerProtocol<BincodeCodec>,
     lobby: &Lobby,
+    interest: &mut InterestManager,
+    encoder: &mut DeltaEncoder,
     server_tick: u32,
 ) {
     // Create global snapshot of replicated entities to be used by all clients
@@ -154,13 +285,33 @@ pub(super) fn server_send_snapshots(
     // Sort by net entity id so we can binary search when processing snapshot
     snapshots.sort_unstable_by(|a, b| a.net_entity_id.partial_cmp(&b.net_entity_id).unwrap());
 
-    // Send to player including last acked tick
+    // Send entities within interest area to player including last acked tick
+    let positions = world.replicated_positions();
     for player in lobby.iter_players() {
+        if let Some(paddle) = player
+            .player_net_id
+            .and_then(|net_id| world.get_entity_id(net_id))
+            && let Ok(transform) = world.get_world().get::<&Transform>(*paddle)
+        {
+            interest.set_center(player.client_id, transform.0.w_axis.truncate());
+        }
+        interest.update(player.client_id, positions.iter().copied());
+        let relevant: Vec<EntitySnapshot> = snapshots
+            .iter()
+            .filter(|snapshot| interest.is_relevant(player.client_id, snapshot.net_entity_id))
+            .cloned()
+            .collect();
+        let snapshot = encoder.encode(
+            player.client_id,
+            server_tick,
+            &relevant,
+            player.last_snapshot_ack,
+        );
         log_err!(
-            protocol.send_to(
+            protocol.send_unreliable_to(
                 ServerMessage::SendSnapshot {
                     server_tick,
-                    data: snapshots.clone(),
+                    snapshot,
                     last_acked_client_tick: player.input_buffer.get_last_acked()
                 },
                 player.client_id
@@ -168,4 +319,64 @@ pub(super) fn server_send_snapshots(
             "Failure broadcasting command: {err}"
         );
     }
+    // Spectators send no inputs, so there is nothing to acknowledge. Always full snapshots
+    for spectator in protocol.spectators() {
+        let snapshot = encoder.encode(spectator, server_tick, &snapshots, None);
+        log_err!(
+            protocol.send_unreliable_to(
+                ServerMessage::SendSnapshot {
+                    server_tick,
+                    snapshot,
+                    last_acked_client_tick: 0,
+                },
+                spectator
+            ),
+            "Failure sending snapshot to spectator: {err}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::test_client;
+
+    #[test]
+    fn test_join_only_between_matches() {
+        let mut world = NetworkWorld::new();
+        assert!(check_join_allowed(&world, &ServerGameState::WaitingForPlayers).is_ok());
+        let serving = ServerGameState::Serving {
+            serve_at_tick: 10,
+            receiving_slot: 1,
+        };
+        assert!(check_join_allowed(&world, &serving).is_err());
+        assert!(check_join_allowed(&world, &ServerGameState::Running).is_err());
+
+        // Ball left over from the previous match
+        spawn_ball(&mut world, None);
+        assert!(check_join_allowed(&world, &ServerGameState::WaitingForPlayers).is_err());
+    }
+
+    #[test]
+    fn test_paddles_spawned_with_owner() {
+        let mut world = NetworkWorld::new();
+        let (first_net_id, first) = spawn_player(&mut world, 0, None, test_client(1));
+        let (second_net_id, second) = spawn_player(&mut world, 1, None, test_client(2));
+
+        // Each player receives the paddle of the other one
+        let messages = paddle_spawn_messages(&world, Some(first)).unwrap();
+        assert!(matches!(
+            messages.as_slice(),
+            [ServerMessage::SpawnPaddle { net_entity_id, player_slot: 1, owner }]
+                if *net_entity_id == second_net_id && *owner == test_client(2)
+        ));
+        let messages = paddle_spawn_messages(&world, Some(second)).unwrap();
+        assert!(matches!(
+            messages.as_slice(),
+            [ServerMessage::SpawnPaddle { net_entity_id, player_slot: 0, owner }]
+                if *net_entity_id == first_net_id && *owner == test_client(1)
+        ));
+        // Spectators receive both
+        assert_eq!(paddle_spawn_messages(&world, None).unwrap().len(), 2);
+    }
 }
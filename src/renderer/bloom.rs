@@ -0,0 +1,152 @@
+use std::{error::Error, rc::Rc};
+
+use glam::Vec2;
+use glow::{HasContext, NativeTexture};
+use imgui::Ui;
+
+use crate::systems::skybox::quad_vertex_mesh;
+
+use super::{
+    Mesh,
+    render_target::{RenderTarget, draw_fullscreen},
+    shader::Shader,
+};
+
+/// Bloom post-processing pass
+/// 1. Bright-pass extraction of the first pass color texture
+/// 2. Separable gaussian blur (ping-pong between two half resolution targets)
+///
+/// The resulting texture has to be composited additively by the caller
+pub struct BloomPass {
+    gl: Rc<glow::Context>,
+    extract_quad: Mesh,
+    blur_quad: Mesh,
+    // Ping-pong targets. Bright pass is written into the first one
+    targets: [RenderTarget; 2],
+    width: i32,
+    height: i32,
+
+    pub enabled: bool,
+    pub threshold: f32,
+    pub intensity: f32,
+    pub blur_iterations: u32,
+}
+
+impl BloomPass {
+    /// Bloom is computed at half the given resolution
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        width: i32,
+        height: i32,
+    ) -> Result<BloomPass, Box<dyn Error>> {
+        let mut extract_shader = Shader::new(
+            gl,
+            "assets/shaders/fog.vert",
+            "assets/shaders/bloom_extract.frag",
+        )?;
+        extract_shader.use_program();
+        extract_shader.set_uniform_i32("screenTexture", 0);
+        let mut blur_shader = Shader::new(
+            gl,
+            "assets/shaders/fog.vert",
+            "assets/shaders/bloom_blur.frag",
+        )?;
+        blur_shader.use_program();
+        blur_shader.set_uniform_i32("image", 0);
+
+        let width = (width / 2).max(1);
+        let height = (height / 2).max(1);
+        Ok(Self {
+            gl: Rc::clone(gl),
+            extract_quad: quad_vertex_mesh(gl, extract_shader)?,
+            blur_quad: quad_vertex_mesh(gl, blur_shader)?,
+            targets: [
+                bloom_target(gl, width, height)?,
+                bloom_target(gl, width, height)?,
+            ],
+            width,
+            height,
+            enabled: true,
+            threshold: 0.8,
+            intensity: 0.6,
+            blur_iterations: 4,
+        })
+    }
+
+    /// Intensity to be used for compositing. Zero if bloom is disabled
+    pub fn composite_intensity(&self) -> f32 {
+        if self.enabled { self.intensity } else { 0.0 }
+    }
+
+    /// Runs bright-pass & blur on the given color texture.
+    /// Returns the blurred bloom texture
+    /// NOTE: Leaves framebuffer unbound & viewport in bloom resolution. Caller has to restore
+    /// viewport
+    pub fn render(&mut self, source: NativeTexture) -> NativeTexture {
+        let gl = &self.gl;
+        unsafe {
+            gl.disable(gl::DEPTH_TEST);
+            gl.viewport(0, 0, self.width, self.height);
+        }
+        if !self.enabled {
+            // Make sure stale bloom data is not composited by accident
+            self.targets[0].clear(gl, 0.0, 0.0, 0.0);
+            return self.targets[0].texture;
+        }
+
+        // 1. Bright pass
+        self.extract_quad.shader.use_program();
+        self.extract_quad
+            .shader
+            .set_uniform_f32("threshold", self.threshold);
+        draw_fullscreen(gl, &self.extract_quad, self.targets[0].fbo, source);
+
+        // 2. Separable blur. Each iteration does one horizontal & one vertical pass
+        self.blur_quad.shader.use_program();
+        for _ in 0..self.blur_iterations {
+            for (direction, from, to) in [(Vec2::X, 0, 1), (Vec2::Y, 1, 0)] {
+                self.blur_quad
+                    .shader
+                    .set_uniform_vec2("direction", &direction);
+                draw_fullscreen(
+                    gl,
+                    &self.blur_quad,
+                    self.targets[to].fbo,
+                    self.targets[from].texture,
+                );
+            }
+        }
+        unsafe {
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        }
+        self.targets[0].texture
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Bloom")
+            .size([300.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 350.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("Enabled", &mut self.enabled);
+                ui.slider("Threshold", 0.0, 1.0, &mut self.threshold);
+                ui.slider("Intensity", 0.0, 3.0, &mut self.intensity);
+                ui.slider("Blur iterations", 1, 10, &mut self.blur_iterations);
+            });
+    }
+}
+
+impl Drop for BloomPass {
+    fn drop(&mut self) {
+        for target in &self.targets {
+            target.delete(&self.gl);
+        }
+    }
+}
+
+fn bloom_target(
+    gl: &glow::Context,
+    width: i32,
+    height: i32,
+) -> Result<RenderTarget, Box<dyn Error>> {
+    RenderTarget::new(gl, width, height, gl::RGB16F, gl::RGB, gl::FLOAT)
+}
@@ -0,0 +1,202 @@
+use std::sync::OnceLock;
+
+use glow::HasContext;
+use log::{info, warn};
+
+/// Environment variable selecting the requested context version, e.g. `VOXIE_GL_VERSION=4.1`
+pub const GL_VERSION_ENV: &str = "VOXIE_GL_VERSION";
+
+static CAPABILITIES: OnceLock<GlCapabilities> = OnceLock::new();
+
+/// Core profile versions a context can be requested with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GlVersion {
+    Gl33,
+    /// Highest version available on macOS
+    Gl41,
+    Gl45,
+}
+
+impl GlVersion {
+    const ALL: [GlVersion; 3] = [GlVersion::Gl45, GlVersion::Gl41, GlVersion::Gl33];
+
+    pub fn major_minor(self) -> (u8, u8) {
+        match self {
+            GlVersion::Gl33 => (3, 3),
+            GlVersion::Gl41 => (4, 1),
+            GlVersion::Gl45 => (4, 5),
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<GlVersion> {
+        match value.trim() {
+            "3.3" | "33" => Some(GlVersion::Gl33),
+            "4.1" | "41" => Some(GlVersion::Gl41),
+            "4.5" | "45" => Some(GlVersion::Gl45),
+            _ => None,
+        }
+    }
+
+    /// Version from [GL_VERSION_ENV]. Defaults to the highest version
+    pub fn requested() -> GlVersion {
+        match std::env::var(GL_VERSION_ENV) {
+            Ok(value) => GlVersion::parse(&value).unwrap_or_else(|| {
+                warn!("Unknown {GL_VERSION_ENV} '{value}'. Expected 3.3, 4.1 or 4.5");
+                GlVersion::Gl45
+            }),
+            Err(_) => GlVersion::Gl45,
+        }
+    }
+
+    /// This version followed by all lower ones, in the order contexts should be tried
+    pub fn with_fallbacks(self) -> impl Iterator<Item = GlVersion> {
+        GlVersion::ALL
+            .into_iter()
+            .filter(move |version| *version <= self)
+    }
+}
+
+/// True if the context version is at least `min_version` or all extensions are available
+pub fn supports(gl: &glow::Context, min_version: (u32, u32), extensions: &[&str]) -> bool {
+    let version = gl.version();
+    if (version.major, version.minor) >= min_version {
+        return true;
+    }
+    let supported = gl.supported_extensions();
+    !extensions.is_empty() && extensions.iter().all(|name| supported.contains(*name))
+}
+
+/// Highest GLSL version the context compiles, e.g. 410 for a 4.1 context
+pub fn max_glsl_version(gl: &glow::Context) -> u32 {
+    let version = gl.version();
+    match (version.major, version.minor) {
+        // GLSL versions only match the GL version from 3.3 onward
+        (3, 2) => 150,
+        (3, 1) => 140,
+        (3, 0) => 130,
+        (major, minor) => major * 100 + minor * 10,
+    }
+}
+
+/// Version & feature support of the current context, collected once after context creation
+#[derive(Debug, Clone)]
+pub struct GlCapabilities {
+    pub version: String,
+    pub renderer: String,
+    pub vendor: String,
+    pub glsl_version: String,
+    pub extensions: Vec<String>,
+    pub buffer_storage: bool,
+    pub multi_draw_indirect: bool,
+    pub clip_control: bool,
+    pub debug_output: bool,
+    /// BC1-3 compressed textures, e.g. DXT1 & DXT5 in DDS files
+    pub s3tc: bool,
+    /// BC7 compressed textures
+    pub bptc: bool,
+}
+
+impl GlCapabilities {
+    pub fn detect(gl: &glow::Context) -> GlCapabilities {
+        let mut extensions: Vec<String> = gl.supported_extensions().iter().cloned().collect();
+        extensions.sort();
+        unsafe {
+            GlCapabilities {
+                version: gl.get_parameter_string(glow::VERSION),
+                renderer: gl.get_parameter_string(glow::RENDERER),
+                vendor: gl.get_parameter_string(glow::VENDOR),
+                glsl_version: gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION),
+                extensions,
+                buffer_storage: supports(gl, (4, 4), &["GL_ARB_buffer_storage"]),
+                multi_draw_indirect: supports(
+                    gl,
+                    (4, 3),
+                    &["GL_ARB_multi_draw_indirect", "GL_ARB_base_instance"],
+                ),
+                clip_control: supports(gl, (4, 5), &["GL_ARB_clip_control"]),
+                debug_output: gl.supports_debug(),
+                s3tc: supports(gl, (u32::MAX, 0), &["GL_EXT_texture_compression_s3tc"]),
+                bptc: supports(gl, (4, 2), &["GL_ARB_texture_compression_bptc"]),
+            }
+        }
+    }
+
+    /// Detects & stores the capabilities of the context. Has to be called once after context
+    /// creation
+    pub fn init(gl: &glow::Context) -> &'static GlCapabilities {
+        let capabilities = CAPABILITIES.get_or_init(|| GlCapabilities::detect(gl));
+        info!(
+            "OpenGL {} on {} ({}), GLSL {}, {} extensions",
+            capabilities.version,
+            capabilities.renderer,
+            capabilities.vendor,
+            capabilities.glsl_version,
+            capabilities.extensions.len()
+        );
+        capabilities
+    }
+
+    /// Capabilities stored by [GlCapabilities::init]
+    pub fn get() -> Option<&'static GlCapabilities> {
+        CAPABILITIES.get()
+    }
+}
+
+/// Shows the context version & which render paths are available
+pub fn render_ui(ui: &imgui::Ui) {
+    let Some(capabilities) = GlCapabilities::get() else {
+        return;
+    };
+    ui.window("GL diagnostics")
+        .size([500.0, 300.0], imgui::Condition::FirstUseEver)
+        .position([500.0, 860.0], imgui::Condition::FirstUseEver)
+        .collapsed(true, imgui::Condition::FirstUseEver)
+        .build(|| {
+            ui.text(format!("Version: {}", capabilities.version));
+            ui.text(format!("GLSL: {}", capabilities.glsl_version));
+            ui.text(format!("Renderer: {}", capabilities.renderer));
+            ui.text(format!("Vendor: {}", capabilities.vendor));
+            ui.separator();
+            let features = [
+                ("Persistent mapping", capabilities.buffer_storage),
+                ("Indirect multi-draw", capabilities.multi_draw_indirect),
+                ("Clip control", capabilities.clip_control),
+                ("Debug output", capabilities.debug_output),
+                ("S3TC textures", capabilities.s3tc),
+                ("BPTC textures", capabilities.bptc),
+            ];
+            for (name, supported) in features {
+                let (color, state) = match supported {
+                    true => ([0.3, 1.0, 0.3, 1.0], "yes"),
+                    false => ([1.0, 0.3, 0.3, 1.0], "no"),
+                };
+                ui.text(format!("{name}:"));
+                ui.same_line();
+                ui.text_colored(color, state);
+            }
+            ui.separator();
+            if let Some(_node) = ui
+                .tree_node_config(format!("{} extensions", capabilities.extensions.len()))
+                .push()
+            {
+                for extension in &capabilities.extensions {
+                    ui.text(extension);
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_fallbacks() {
+        assert_eq!(GlVersion::parse("4.1"), Some(GlVersion::Gl41));
+        assert_eq!(GlVersion::parse(" 33 "), Some(GlVersion::Gl33));
+        assert_eq!(GlVersion::parse("4.6"), None);
+        let fallbacks: Vec<GlVersion> = GlVersion::Gl41.with_fallbacks().collect();
+        assert_eq!(fallbacks, [GlVersion::Gl41, GlVersion::Gl33]);
+        assert_eq!(GlVersion::Gl45.with_fallbacks().count(), 3);
+    }
+}
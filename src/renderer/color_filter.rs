@@ -0,0 +1,94 @@
+use glam::Mat3;
+
+// Dichromacy simulation at full severity in linear RGB (Machado et al. 2009)
+const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+// Shifts color information lost in red towards green & blue, where it can still be perceived
+const ERROR_SHIFT: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+fn from_rows(rows: [[f32; 3]; 3]) -> Mat3 {
+    Mat3::from_cols_array_2d(&rows).transpose()
+}
+
+/// Color correction applied at the end of the post-processing pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFilter {
+    #[default]
+    None,
+    /// Red-green color blindness, reduced sensitivity to green
+    Deuteranopia,
+    /// Red-green color blindness, reduced sensitivity to red
+    Protanopia,
+}
+
+impl ColorFilter {
+    pub const ALL: [ColorFilter; 3] = [
+        ColorFilter::None,
+        ColorFilter::Deuteranopia,
+        ColorFilter::Protanopia,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorFilter::None => "None",
+            ColorFilter::Deuteranopia => "Deuteranopia",
+            ColorFilter::Protanopia => "Protanopia",
+        }
+    }
+
+    /// Linear color transform, used instead of a 3D lookup texture. Daltonizes the color:
+    /// Colors that are indistinguishable with the deficiency are moved apart
+    pub fn matrix(self) -> Mat3 {
+        let simulation = match self {
+            ColorFilter::None => return Mat3::IDENTITY,
+            ColorFilter::Deuteranopia => from_rows(DEUTERANOPIA),
+            ColorFilter::Protanopia => from_rows(PROTANOPIA),
+        };
+        // corrected = color + shift * (color - simulated color)
+        Mat3::IDENTITY + from_rows(ERROR_SHIFT) * (Mat3::IDENTITY - simulation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn test_filter_keeps_grays() {
+        assert_eq!(ColorFilter::None.matrix(), Mat3::IDENTITY);
+        for filter in ColorFilter::ALL {
+            let gray = Vec3::splat(0.5);
+            assert!(
+                (filter.matrix() * gray).abs_diff_eq(gray, 1e-3),
+                "{filter:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_separates_red_and_green() {
+        let red = Vec3::new(0.8, 0.2, 0.1);
+        let green = Vec3::new(0.2, 0.8, 0.1);
+        for filter in [ColorFilter::Deuteranopia, ColorFilter::Protanopia] {
+            let simulation = match filter {
+                ColorFilter::Deuteranopia => from_rows(DEUTERANOPIA),
+                _ => from_rows(PROTANOPIA),
+            };
+            let seen = |color: Vec3| simulation * color;
+            let unfiltered = seen(red).distance(seen(green));
+            let matrix = filter.matrix();
+            let filtered = seen(matrix * red).distance(seen(matrix * green));
+            assert!(filtered > unfiltered, "{filter:?}");
+        }
+    }
+}
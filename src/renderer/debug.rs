@@ -0,0 +1,153 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use glow::HasContext;
+use log::{debug, error, info, warn};
+
+// Older messages are dropped
+const MAX_MESSAGES: usize = 100;
+
+static MESSAGES: Mutex<VecDeque<GlDebugMessage>> = Mutex::new(VecDeque::new());
+
+/// Message reported by the driver or by a glGetError check
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlDebugMessage {
+    pub severity: u32,
+    pub text: String,
+}
+
+/// Checks a GL call for errors in debug builds. Release builds only evaluate the call.
+/// Usage: `gl_check!(gl, gl.draw_arrays(gl::TRIANGLES, 0, count))`
+#[macro_export]
+macro_rules! gl_check {
+    ($gl:expr, $call:expr) => {{
+        let result = $call;
+        #[cfg(debug_assertions)]
+        $crate::renderer::debug::check_gl_errors(
+            $gl,
+            concat!(file!(), ":", line!(), ": ", stringify!($call)),
+        );
+        result
+    }};
+}
+
+fn record(severity: u32, text: String) {
+    match severity {
+        glow::DEBUG_SEVERITY_HIGH => error!("{text}"),
+        glow::DEBUG_SEVERITY_MEDIUM => warn!("{text}"),
+        glow::DEBUG_SEVERITY_LOW => info!("{text}"),
+        _ => debug!("{text}"),
+    }
+    let mut messages = MESSAGES.lock().unwrap();
+    if messages.len() == MAX_MESSAGES {
+        messages.pop_front();
+    }
+    messages.push_back(GlDebugMessage { severity, text });
+}
+
+fn error_name(error: u32) -> &'static str {
+    match error {
+        glow::INVALID_ENUM => "INVALID_ENUM",
+        glow::INVALID_VALUE => "INVALID_VALUE",
+        glow::INVALID_OPERATION => "INVALID_OPERATION",
+        glow::INVALID_FRAMEBUFFER_OPERATION => "INVALID_FRAMEBUFFER_OPERATION",
+        glow::OUT_OF_MEMORY => "OUT_OF_MEMORY",
+        _ => "UNKNOWN",
+    }
+}
+
+fn type_name(message_type: u32) -> &'static str {
+    match message_type {
+        glow::DEBUG_TYPE_ERROR => "Error",
+        glow::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "Deprecated",
+        glow::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "Undefined behavior",
+        glow::DEBUG_TYPE_PORTABILITY => "Portability",
+        glow::DEBUG_TYPE_PERFORMANCE => "Performance",
+        _ => "Other",
+    }
+}
+
+/// Logs & records all pending GL errors. Returns whether there were any
+pub fn check_gl_errors(gl: &glow::Context, context: &str) -> bool {
+    let mut found = false;
+    loop {
+        let error = unsafe { gl.get_error() };
+        if error == glow::NO_ERROR {
+            return found;
+        }
+        found = true;
+        record(
+            glow::DEBUG_SEVERITY_HIGH,
+            format!(
+                "OpenGL error {} (0x{error:X}) at {context}",
+                error_name(error)
+            ),
+        );
+    }
+}
+
+/// Routes driver messages into the log & the GL debug window, if KHR_debug is available.
+/// Debug builds receive them synchronously, so they point to the offending call.
+/// Has to be called once after context creation
+pub fn setup_debug_output(gl: &mut glow::Context) -> bool {
+    if !gl.supports_debug() {
+        info!("KHR_debug not supported. Only glGetError checks are available");
+        return false;
+    }
+    unsafe {
+        gl.enable(glow::DEBUG_OUTPUT);
+        if cfg!(debug_assertions) {
+            gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        gl.debug_message_callback(|_source, message_type, id, severity, message| {
+            record(
+                severity,
+                format!("OpenGL {} #{id}: {message}", type_name(message_type)),
+            );
+        });
+    }
+    info!("Enabled OpenGL debug output");
+    true
+}
+
+/// Lists recent GL messages
+pub fn render_ui(ui: &imgui::Ui) {
+    ui.window("GL debug")
+        .size([500.0, 200.0], imgui::Condition::FirstUseEver)
+        .position([0.0, 860.0], imgui::Condition::FirstUseEver)
+        .collapsed(true, imgui::Condition::FirstUseEver)
+        .build(|| {
+            let mut messages = MESSAGES.lock().unwrap();
+            ui.text(format!("{} messages", messages.len()));
+            ui.same_line();
+            if ui.button("Clear") {
+                messages.clear();
+            }
+            ui.separator();
+            for message in messages.iter().rev() {
+                let color = match message.severity {
+                    glow::DEBUG_SEVERITY_HIGH => [1.0, 0.3, 0.3, 1.0],
+                    glow::DEBUG_SEVERITY_MEDIUM => [1.0, 0.8, 0.3, 1.0],
+                    _ => [0.8, 0.8, 0.8, 1.0],
+                };
+                ui.text_colored(color, &message.text);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_capped() {
+        for index in 0..MAX_MESSAGES + 5 {
+            record(
+                glow::DEBUG_SEVERITY_NOTIFICATION,
+                format!("Message {index}"),
+            );
+        }
+        let messages = MESSAGES.lock().unwrap();
+        assert_eq!(messages.len(), MAX_MESSAGES);
+        assert_eq!(messages.front().unwrap().text, "Message 5");
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use glow::HasContext;
+use log::{info, warn};
+
+use super::capabilities;
+
+/// Reverse-Z: Near plane maps to depth 1, far plane to depth 0.
+/// Depth buffer has to be cleared to 0 & tested with GREATER
+pub const CLEAR_DEPTH: f32 = 0.0;
+
+static CLIP_CONTROL_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Switches clip space depth range to [0, 1] via glClipControl if supported (GL 4.5 or
+/// ARB_clip_control). Without it reverse-Z still works, but loses most of the precision gain.
+/// Has to be called once after context creation
+pub fn setup_clip_control<F>(gl: &glow::Context, loader: F) -> bool
+where
+    F: FnMut(&'static str) -> *const c_void,
+{
+    if !capabilities::supports(gl, (4, 5), &["GL_ARB_clip_control"]) {
+        warn!("glClipControl not supported. Falling back to reverse-Z with [-1, 1] clip depth");
+        return false;
+    }
+    gl::ClipControl::load_with(loader);
+    if !gl::ClipControl::is_loaded() {
+        warn!("Unable to load glClipControl");
+        return false;
+    }
+    unsafe {
+        gl::ClipControl(gl::LOWER_LEFT, gl::ZERO_TO_ONE);
+        gl.clear_depth_f32(CLEAR_DEPTH);
+    }
+    info!("Enabled clip control for reverse-Z depth");
+    CLIP_CONTROL_ENABLED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// True if clip space depth is [0, 1]. Required by shaders reconstructing positions from depth
+pub fn is_clip_depth_zero_to_one() -> bool {
+    CLIP_CONTROL_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Enables depth testing with reverse-Z depth function & clear value
+pub fn enable_depth_test(gl: &glow::Context) {
+    unsafe {
+        gl.enable(gl::DEPTH_TEST);
+        gl.depth_func(gl::GREATER);
+        gl.clear_depth_f32(CLEAR_DEPTH);
+    }
+}
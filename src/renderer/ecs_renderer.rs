@@ -1,22 +1,46 @@
-use std::{collections::HashMap, error::Error, rc::Rc};
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
-use glam::{Mat3, Vec3};
+use glam::{Mat4, Vec3, Vec4};
 use glow::HasContext;
-use hecs::World;
-use log::{debug, error};
+use hecs::{Entity, World};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cameras::{camera::Camera, component::CameraComponent},
+    gl_check,
+    meshes::icosphere::{projected_screen_size, select_lod},
     systems::{physics::Transform, skybox::quad_mesh},
 };
 
 use super::{
-    frame_uniforms::FrameUniforms,
-    meshes::{mesh_cube, player_mesh, projectile_mesh, projectile2d_mesh, squid::squid_mesh},
+    feature_flags::{Feature, FeatureFlags},
+    frame_uniforms::{FrameUniforms, SceneLighting},
+    impostors::{DEFAULT_IMPOSTOR_DISTANCE, ImpostorRenderer},
+    material::{ShaderHandle, TextureHandle, UniformValue},
+    meshes::{
+        CUBE_OBJ, FISH_OBJ,
+        character::{CHARACTER_OBJ, character_mesh},
+        mesh_cube, player_mesh, projectile2d_mesh,
+        sphere::{gizmo_sphere_mesh, projectile_mesh},
+        squid::{SQUID_OBJ, squid_mesh},
+    },
+    render_batches::RenderBatches,
+    render_target::RenderTarget,
     shader::Shader,
+    stream_buffer::StreamBuffer,
+    texture::Texture,
+    texture_camera::TextureCamera,
+    viewport::Viewport,
 };
 
-type MeshHandle = usize;
+pub type MeshHandle = usize;
+type MeshBuilder = fn(&Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>>;
 
 pub const MESH_PROJECTILE: MeshHandle = 0;
 pub const MESH_PLAYER: MeshHandle = 1;
@@ -24,6 +48,16 @@ pub const MESH_QUAD: MeshHandle = 2;
 pub const MESH_CUBE: MeshHandle = 3;
 pub const MESH_PROJECTILE_2D: MeshHandle = 4;
 pub const MESH_SQUID: MeshHandle = 5;
+pub const MESH_CHARACTER: MeshHandle = 6;
+pub const MESH_SPHERE: MeshHandle = 7;
+
+// Model matrices of all ecs entities drawn by the passes of a frame together
+const MAX_INSTANCES: usize = 16384;
+// First of the 4 consecutive attribute locations of the per instance model matrix `aModel`
+const INSTANCE_MODEL_LOCATION: u32 = 4;
+
+/// Shows a texture on the quad mesh, sampled from `uScreen`. E.g. the image of a texture camera
+pub const SHADER_SCREEN: ShaderHandle = 0;
 
 pub struct Mesh {
     pub shader: Shader,
@@ -31,7 +65,20 @@ pub struct Mesh {
     pub vertex_count: i32,
     // Interims fix / tag to distinguish between draw_element and draw_arrays mesh implementations
     use_index: bool,
+    // Lower detail variants, most detailed first
+    lods: Vec<MeshLod>,
+    // Radius of the untransformed mesh around its origin. Used to pick the detail level
+    bounding_radius: f32,
+}
+
+/// Lower detail variant of a mesh, sharing its shader
+pub struct MeshLod {
+    pub vao: <glow::Context as HasContext>::VertexArray,
+    pub vertex_count: i32,
+    /// Drawn while the mesh covers less than this share of the viewport height
+    pub max_screen_size: f32,
 }
+
 impl Mesh {
     pub fn new(
         shader: Shader,
@@ -43,12 +90,74 @@ impl Mesh {
             vao,
             vertex_count,
             use_index: false,
+            lods: Vec::new(),
+            bounding_radius: 0.0,
         }
     }
 
     pub fn enable_indexed_draw(&mut self) {
         self.use_index = true;
     }
+
+    /// Adds detail levels, picked by the screen size of the mesh. Thresholds have to decrease
+    pub fn with_lods(mut self, bounding_radius: f32, lods: Vec<MeshLod>) -> Mesh {
+        debug_assert!(
+            lods.windows(2)
+                .all(|pair| pair[0].max_screen_size > pair[1].max_screen_size)
+        );
+        self.bounding_radius = bounding_radius;
+        self.lods = lods;
+        self
+    }
+
+    // Index of the detail level & its vertex array, 0 being the mesh itself
+    fn variant(
+        &self,
+        transform: &Mat4,
+        view: &PassView,
+    ) -> (usize, <glow::Context as HasContext>::VertexArray, i32) {
+        if self.lods.is_empty() {
+            return (0, self.vao, self.vertex_count);
+        }
+        let scale = transform.x_axis.length().max(transform.y_axis.length());
+        let distance = transform.w_axis.truncate().distance(view.position);
+        let screen_size =
+            projected_screen_size(self.bounding_radius * scale, distance, &view.projection);
+        let thresholds = self.lods.iter().map(|lod| lod.max_screen_size);
+        match select_lod(screen_size, thresholds) {
+            0 => (0, self.vao, self.vertex_count),
+            level => {
+                let lod = &self.lods[level - 1];
+                (level, lod.vao, lod.vertex_count)
+            }
+        }
+    }
+}
+
+// Camera of the current pass. Detail levels are picked & impostors oriented for it
+struct PassView {
+    position: Vec3,
+    projection: Mat4,
+    right: Vec3,
+    up: Vec3,
+}
+
+// Consecutive draw items drawn by a single instanced draw call
+struct InstanceRun {
+    // Draw item providing mesh, shader & parameters of the whole run
+    item: usize,
+    level: usize,
+    vao: <glow::Context as HasContext>::VertexArray,
+    vertex_count: i32,
+    // Model matrices of the run within the transforms written for the pass
+    first: usize,
+    count: usize,
+}
+
+// File a mesh was built from & how to rebuild it
+struct MeshSource {
+    path: PathBuf,
+    build: MeshBuilder,
 }
 
 /// ECS-based renderer
@@ -57,29 +166,78 @@ impl Mesh {
 pub struct ECSRenderer {
     gl: Rc<glow::Context>,
     meshes: HashMap<MeshHandle, Mesh>,
+    // Meshes rebuilt by [ECSRenderer::reload_assets]
+    mesh_sources: HashMap<MeshHandle, MeshSource>,
+    // Shaders & textures referenced by materials
+    shaders: HashMap<ShaderHandle, Shader>,
+    textures: HashMap<TextureHandle, Texture>,
+    // Images of texture cameras. Created on first use
+    render_targets: HashMap<TextureHandle, RenderTarget>,
+    batches: RenderBatches,
     frame_uniforms: FrameUniforms,
+    pass_view: PassView,
+    impostors: ImpostorRenderer,
+    impostors_enabled: bool,
+    // Model matrices of all drawn entities, read as instance attribute
+    instance_stream: StreamBuffer,
+    // Reused by every pass
+    instance_transforms: Vec<Mat4>,
+    instance_runs: Vec<InstanceRun>,
+    /// Entities with an [super::impostors::Impostor] farther away than this are drawn as billboards
+    pub impostor_distance: f32,
+    pub lighting: SceneLighting,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RenderMeshHandle(pub usize);
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RenderColor(pub Vec3);
+/// Skipped by the renderer, e.g. the player model in first person
+pub struct Hidden;
 
 impl ECSRenderer {
     pub fn new(gl: &Rc<glow::Context>) -> Result<ECSRenderer, Box<dyn Error>> {
         let mut instance = Self {
             gl: Rc::clone(gl),
             meshes: HashMap::new(),
-            frame_uniforms: FrameUniforms::new(gl),
+            mesh_sources: HashMap::new(),
+            shaders: HashMap::new(),
+            textures: HashMap::new(),
+            render_targets: HashMap::new(),
+            batches: RenderBatches::new(),
+            frame_uniforms: FrameUniforms::new(gl)?,
+            pass_view: PassView {
+                position: Vec3::ZERO,
+                projection: Mat4::IDENTITY,
+                right: Vec3::X,
+                up: Vec3::Y,
+            },
+            impostors: ImpostorRenderer::new(gl)?,
+            impostors_enabled: true,
+            instance_stream: StreamBuffer::new(
+                gl,
+                glow::ARRAY_BUFFER,
+                MAX_INSTANCES * size_of::<Mat4>(),
+            )?,
+            instance_transforms: Vec::new(),
+            instance_runs: Vec::new(),
+            impostor_distance: DEFAULT_IMPOSTOR_DISTANCE,
+            lighting: SceneLighting::default(),
         };
 
         // Load all meshes
         instance.add_mesh(MESH_PROJECTILE, projectile_mesh(gl)?);
-        instance.add_mesh(MESH_PLAYER, player_mesh(gl)?);
+        instance.add_obj_mesh(MESH_PLAYER, FISH_OBJ, player_mesh)?;
         instance.add_mesh(MESH_QUAD, quad_mesh(gl)?);
-        instance.add_mesh(MESH_CUBE, mesh_cube(gl)?);
-        instance.add_mesh(MESH_PROJECTILE_2D, projectile2d_mesh(gl)?);
-        instance.add_mesh(MESH_SQUID, squid_mesh(gl)?);
+        instance.add_obj_mesh(MESH_CUBE, CUBE_OBJ, mesh_cube)?;
+        instance.add_obj_mesh(MESH_PROJECTILE_2D, CUBE_OBJ, projectile2d_mesh)?;
+        instance.add_obj_mesh(MESH_SQUID, SQUID_OBJ, squid_mesh)?;
+        instance.add_obj_mesh(MESH_CHARACTER, CHARACTER_OBJ, character_mesh)?;
+        instance.add_mesh(MESH_SPHERE, gizmo_sphere_mesh(gl)?);
+        instance.add_shader(
+            SHADER_SCREEN,
+            Shader::new(gl, "assets/shaders/quad.vert", "assets/shaders/screen.frag")?,
+        );
 
         Ok(instance)
     }
@@ -89,8 +247,60 @@ impl ECSRenderer {
         handle
     }
 
-    pub fn get_mesh(&mut self, handle: MeshHandle) -> Option<&mut Mesh> {
-        self.meshes.get_mut(&handle)
+    /// Builds & registers a mesh loaded from an OBJ file. The mesh is rebuilt by
+    /// [ECSRenderer::reload_assets] whenever the file changes
+    pub fn add_obj_mesh(
+        &mut self,
+        handle: MeshHandle,
+        path: impl AsRef<Path>,
+        build: MeshBuilder,
+    ) -> Result<MeshHandle, Box<dyn Error>> {
+        self.add_mesh(handle, build(&self.gl)?);
+        let path = path.as_ref().to_path_buf();
+        self.mesh_sources.insert(handle, MeshSource { path, build });
+        Ok(handle)
+    }
+
+    /// Re-uploads meshes & textures loaded from any of the changed files. Handles stay valid,
+    /// so entities & materials referencing them pick up the new assets
+    pub fn reload_assets(&mut self, changed: &[PathBuf]) {
+        for (handle, source) in &self.mesh_sources {
+            if !changed.contains(&source.path) {
+                continue;
+            }
+            match (source.build)(&self.gl) {
+                Ok(mesh) => {
+                    info!("Reloaded mesh {handle} from {}", source.path.display());
+                    if let Some(old) = self.meshes.insert(*handle, mesh) {
+                        // NOTE: Vertex buffers of the old mesh are not tracked & leak. Acceptable
+                        // for reloads during development
+                        unsafe { self.gl.delete_vertex_array(old.vao) };
+                    }
+                }
+                Err(err) => error!("Unable to reload {}: {err}", source.path.display()),
+            }
+        }
+        for texture in self.textures.values_mut() {
+            if !changed.iter().any(|path| path == texture.path()) {
+                continue;
+            }
+            match texture.reload() {
+                Ok(()) => info!("Reloaded texture {}", texture.path().display()),
+                Err(err) => error!("Unable to reload {}: {err}", texture.path().display()),
+            }
+        }
+    }
+
+    /// Registers shader that can be referenced by materials
+    pub fn add_shader(&mut self, handle: ShaderHandle, shader: Shader) -> ShaderHandle {
+        self.shaders.insert(handle, shader);
+        handle
+    }
+
+    /// Registers texture that can be referenced by materials
+    pub fn add_texture(&mut self, handle: TextureHandle, texture: Texture) -> TextureHandle {
+        self.textures.insert(handle, texture);
+        handle
     }
 
     /// Simple **batteries-included** single-pass render pipeline used by debugging scenes.
@@ -99,22 +309,17 @@ impl ECSRenderer {
     ///
     /// Future improvement: Explicit render pipeline abstraction / setup instead
     pub fn render(&mut self, world: &World, time_elapsed: f32) {
-        // Prepare rendering
+        // Prepare rendering. GL state is applied by the application
         let gl = &self.gl;
         unsafe {
-            gl.enable(gl::CULL_FACE);
-            gl.enable(gl::DEPTH_TEST);
-            gl.depth_func(gl::LESS); // Default: Pass if the incoming depth is less than the stored depth
-            gl.cull_face(gl::BACK);
-            gl.front_face(gl::CCW);
-
-            gl.clear_color(0.05, 0.05, 0.1, 1.0);
             gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
+        let viewport = Viewport::current(gl);
         match query_main_camera(world) {
             Some(cam) => {
-                self.render_camera(world, &cam, time_elapsed);
+                self.gather(world);
+                self.render_camera(&cam, viewport, &[], time_elapsed);
             }
             None => {
                 error!("Cannot render scene: No camera found");
@@ -125,51 +330,328 @@ impl ECSRenderer {
     /// Public entrypoint to render all ecs-tracked geometry within a multi-pass pipeline
     /// - Requires caller to handle frame buffer setup
     /// - Use render if you need a simple single-pass batteries included pipeline
-    pub fn render_camera(&mut self, world: &World, cam: &Camera, time_elapsed: f32) {
-        self.frame_uniforms.update_time(&self.gl, time_elapsed);
-        self.render_geometry(world, cam);
+    /// - Views of multiple cameras, e.g. splitscreen, render into separate viewports
+    /// - Draws the geometry of the last [ECSRenderer::gather], except the skipped entities
+    pub fn render_camera(
+        &mut self,
+        cam: &Camera,
+        viewport: Viewport,
+        skip: &[Entity],
+        time_elapsed: f32,
+    ) {
+        viewport.apply(&self.gl);
+        self.update_frame_uniforms(cam, time_elapsed);
+        self.render_geometry(skip);
+    }
+
+    /// Uploads camera, time & lighting into the shared frame UBO.
+    /// Has to happen before any other renderer relying on frame uniforms draws
+    pub fn update_frame_uniforms(&mut self, cam: &Camera, time_elapsed: f32) {
+        self.frame_uniforms
+            .update(&self.gl, cam, time_elapsed, &self.lighting);
+        let view = cam.get_view_matrix();
+        self.pass_view = PassView {
+            position: cam.position,
+            projection: cam.get_projection_matrix(),
+            right: view.row(0).truncate(),
+            up: view.row(1).truncate(),
+        };
+    }
+
+    /// Renders the views of all texture cameras into their render targets. Has to run before the
+    /// main views, so surfaces show the current frame. Geometry not tracked by the ecs, e.g. the
+    /// voxel world, is drawn by draw_extra. Draws the geometry of the last [ECSRenderer::gather].
+    /// Leaves the framebuffer unbound & the viewport changed
+    pub fn render_texture_cameras(
+        &mut self,
+        world: &World,
+        time_elapsed: f32,
+        mut draw_extra: impl FnMut(&Camera, Viewport),
+    ) {
+        let cameras: Vec<(TextureCamera, Camera)> = world
+            .query::<(&TextureCamera, &Transform)>()
+            .iter()
+            .map(|(_entity, (texture_camera, transform))| {
+                (texture_camera.clone(), texture_camera.camera(&transform.0))
+            })
+            .collect();
+        for (texture_camera, cam) in cameras {
+            let Some((fbo, viewport)) = self
+                .render_target(&texture_camera)
+                .map(|target| (target.fbo, Viewport::full(target.width, target.height)))
+            else {
+                continue;
+            };
+            unsafe {
+                self.gl.bind_framebuffer(gl::FRAMEBUFFER, Some(fbo));
+                self.gl
+                    .clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+            }
+            viewport.apply(&self.gl);
+            self.update_frame_uniforms(&cam, time_elapsed);
+            draw_extra(&cam, viewport);
+            // Surfaces showing the image being rendered would sample their own target
+            self.draw_batches(&[], Some(texture_camera.texture));
+        }
+        unsafe {
+            self.gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        }
     }
 
-    fn render_geometry(&mut self, world: &World, cam: &Camera) {
-        // TODO: Instanced draws for same handle
-        for (entity, (transform, handle)) in world.query::<(&Transform, &RenderMeshHandle)>().iter()
-        {
-            debug!("Rendering {entity:?} at {:?}", transform.0);
+    // Target matching the camera resolution. Recreated if the resolution changed
+    fn render_target(&mut self, texture_camera: &TextureCamera) -> Option<&RenderTarget> {
+        let outdated = self
+            .render_targets
+            .get(&texture_camera.texture)
+            .is_none_or(|target| {
+                target.width != texture_camera.width || target.height != texture_camera.height
+            });
+        if outdated {
+            let target = match RenderTarget::with_depth(
+                &self.gl,
+                texture_camera.width,
+                texture_camera.height,
+            ) {
+                Ok(target) => target,
+                Err(err) => {
+                    error!("Unable to create texture camera target: {err}");
+                    return None;
+                }
+            };
+            if let Some(old) = self.render_targets.insert(texture_camera.texture, target) {
+                old.delete(&self.gl);
+            }
+        }
+        self.render_targets.get(&texture_camera.texture)
+    }
+
+    /// Collects the visible ecs geometry drawn by all passes of the frame. Has to run once per
+    /// frame, after the world was updated & before the first pass drawing ecs geometry or
+    /// updating frame uniforms
+    pub fn gather(&mut self, world: &World) {
+        self.batches.gather(world);
+        for texture in self.textures.values_mut() {
+            texture.poll();
+        }
+        self.frame_uniforms.begin_frame();
+        self.impostors.begin_frame();
+        self.instance_stream.begin_frame();
+    }
+
+    pub fn set_features(&mut self, features: &FeatureFlags) {
+        self.impostors_enabled = features.is_enabled(Feature::Impostors);
+    }
+
+    /// Renders the gathered ecs geometry, except the skipped entities. Requires frame uniforms
+    /// to be up to date
+    pub fn render_geometry(&mut self, skip: &[Entity]) {
+        self.draw_batches(skip, None);
+    }
+
+    // Also skips entities with materials sampling the excluded texture
+    fn draw_batches(&mut self, skip: &[Entity], exclude_texture: Option<TextureHandle>) {
+        let mut transforms = std::mem::take(&mut self.instance_transforms);
+        let mut runs = std::mem::take(&mut self.instance_runs);
+        transforms.clear();
+        runs.clear();
+        let items = self.batches.items();
+        for (index, item) in items.iter().enumerate() {
+            if skip.contains(&item.entity) || exclude_texture.is_some_and(|t| item.samples(t)) {
+                continue;
+            }
+            if self.impostors_enabled
+                && let Some(impostor) =
+                    item.impostor(self.pass_view.position, self.impostor_distance)
+            {
+                self.impostors.push(impostor);
+                continue;
+            }
+            let mesh = self
+                .meshes
+                .get(&item.mesh)
+                .expect("Invalid mesh handle assigned");
+            let (level, vao, vertex_count) = mesh.variant(&item.transform, &self.pass_view);
+            // Batches are sorted, so entities sharing a draw call are mostly adjacent
+            match runs.last_mut() {
+                Some(run) if run.level == level && items[run.item].shares_draw(item) => {
+                    run.count += 1;
+                }
+                _ => runs.push(InstanceRun {
+                    item: index,
+                    level,
+                    vao,
+                    vertex_count,
+                    first: transforms.len(),
+                    count: 1,
+                }),
+            }
+            transforms.push(item.transform);
+        }
+        if !transforms.is_empty() {
+            match self
+                .instance_stream
+                .write(bytemuck::cast_slice(&transforms), size_of::<Mat4>())
+            {
+                Some(offset) => self.draw_instance_runs(&runs, offset),
+                None => warn!(
+                    "Instance stream buffer full. Skipping {} entities",
+                    transforms.len()
+                ),
+            }
+        }
+        self.instance_transforms = transforms;
+        self.instance_runs = runs;
+        // All distant impostors of the pass in one draw call
+        self.impostors
+            .flush(self.pass_view.right, self.pass_view.up);
+    }
+
+    // Model matrices of the runs start at `offset` within the instance stream buffer
+    fn draw_instance_runs(&mut self, runs: &[InstanceRun], offset: usize) {
+        let gl = &self.gl;
+        let instance_buffer = self.instance_stream.buffer();
+        let items = self.batches.items();
+        // Program & vertex array only change between batches
+        let mut bound: Option<(Option<ShaderHandle>, MeshHandle, usize)> = None;
+        for run in runs {
+            let item = &items[run.item];
+            debug!("Rendering {} instances of mesh {}", run.count, item.mesh);
             let mesh = self
-                .get_mesh(handle.0)
+                .meshes
+                .get_mut(&item.mesh)
                 .expect("Invalid mesh handle assigned");
             let use_index = mesh.use_index;
-            mesh.shader.use_program();
-            mesh.shader.set_uniform_mat4("uModel", &transform.0);
-            // TODO: Should not do this at render time. Expensive
-            let model_iv_loc = mesh.shader.get_uniform_location("uModelIV");
-            if model_iv_loc.is_some() {
-                // Only calculate IV if shader requires it
-                let model_inverse_transpose = Mat3::from_mat4(transform.0.inverse().transpose());
-                mesh.shader
-                    .set_uniform_mat3("uModelIV", &model_inverse_transpose);
-            }
-            mesh.shader
-                .set_uniform_mat4("uView", &cam.get_view_matrix());
-            mesh.shader
-                .set_uniform_mat4("uProjection", &cam.get_projection_matrix());
-            if let Ok(color) = world.get::<&RenderColor>(entity) {
-                mesh.shader.set_uniform_vec3("uColor", &color.0);
-            }
-
-            let vao = mesh.vao;
-            let count = mesh.vertex_count;
-            let gl = &self.gl;
+            // Material shader overrides mesh default shader
+            let shader = match item.shader() {
+                Some(shader_handle) => match self.shaders.get_mut(&shader_handle) {
+                    Some(shader) => shader,
+                    None => {
+                        error!("Invalid shader handle {shader_handle} assigned to material");
+                        &mut mesh.shader
+                    }
+                },
+                None => &mut mesh.shader,
+            };
+            let batch = (item.shader(), item.mesh, run.level);
+            if bound != Some(batch) {
+                shader.use_program();
+                unsafe {
+                    gl.bind_vertex_array(Some(run.vao));
+                }
+                bound = Some(batch);
+            }
+            unsafe {
+                bind_instance_transforms(
+                    gl,
+                    instance_buffer,
+                    offset + run.first * size_of::<Mat4>(),
+                );
+            }
+            // Parameters of the previous run must not leak into this one
+            shader.reset_draw_uniforms();
+            if let Some(color) = &item.color {
+                shader.set_draw_uniform("uColor", &UniformValue::Vec3(*color));
+            }
+
+            if let Some(material) = &item.material {
+                // Material parameters take precedence over legacy components like RenderColor
+                for (name, value) in &material.uniforms {
+                    shader.set_draw_uniform(name, value);
+                }
+                for material_texture in &material.textures {
+                    unsafe {
+                        gl.active_texture(gl::TEXTURE0 + material_texture.unit);
+                    }
+                    // Render targets shadow textures with the same handle
+                    if let Some(target) = self.render_targets.get(&material_texture.texture) {
+                        unsafe {
+                            gl.bind_texture(gl::TEXTURE_2D, Some(target.texture));
+                        }
+                    } else if let Some(texture) = self.textures.get(&material_texture.texture) {
+                        texture.bind();
+                    } else {
+                        error!(
+                            "Invalid texture handle {} assigned to material",
+                            material_texture.texture
+                        );
+                        continue;
+                    }
+                    shader.set_draw_uniform(
+                        &material_texture.sampler,
+                        &UniformValue::I32(material_texture.unit as i32),
+                    );
+                }
+            }
+
+            let instances = run.count as i32;
             unsafe {
-                gl.bind_vertex_array(Some(vao));
                 if use_index {
-                    gl.draw_elements(glow::TRIANGLES, count, gl::UNSIGNED_INT, 0);
+                    gl_check!(
+                        gl,
+                        gl.draw_elements_instanced(
+                            glow::TRIANGLES,
+                            run.vertex_count,
+                            gl::UNSIGNED_INT,
+                            0,
+                            instances
+                        )
+                    );
                 } else {
-                    gl.draw_arrays(gl::TRIANGLES, 0, count);
+                    gl_check!(
+                        gl,
+                        gl.draw_arrays_instanced(gl::TRIANGLES, 0, run.vertex_count, instances)
+                    );
                 }
-                gl.bind_vertex_array(None);
             }
+            if let Some(material) = &item.material
+                && !material.textures.is_empty()
+            {
+                // Entities without the texture would sample it otherwise
+                for material_texture in &material.textures {
+                    unsafe {
+                        gl.active_texture(gl::TEXTURE0 + material_texture.unit);
+                        gl.bind_texture(gl::TEXTURE_2D, None);
+                    }
+                }
+                unsafe {
+                    gl.active_texture(gl::TEXTURE0);
+                }
+            }
+        }
+        unsafe {
+            gl.bind_vertex_array(None);
+        }
+    }
+}
+
+impl Drop for ECSRenderer {
+    fn drop(&mut self) {
+        for target in self.render_targets.values() {
+            target.delete(&self.gl);
+        }
+    }
+}
+
+// Points the model matrix attribute of the bound vertex array at the instance stream buffer
+unsafe fn bind_instance_transforms(gl: &glow::Context, buffer: glow::NativeBuffer, offset: usize) {
+    let stride = size_of::<Mat4>() as i32;
+    let column_size = size_of::<Vec4>();
+    unsafe {
+        gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+        for column in 0..4 {
+            let location = INSTANCE_MODEL_LOCATION + column as u32;
+            gl.vertex_attrib_pointer_f32(
+                location,
+                4,
+                glow::FLOAT,
+                false,
+                stride,
+                (offset + column * column_size) as i32,
+            );
+            gl.vertex_attrib_divisor(location, 1);
+            gl.enable_vertex_attrib_array(location);
         }
+        gl.bind_buffer(glow::ARRAY_BUFFER, None);
     }
 }
 
@@ -0,0 +1,245 @@
+use std::{error::Error, mem::offset_of, rc::Rc};
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use glow::{HasContext, NativeVertexArray};
+use hecs::World;
+use log::warn;
+
+use crate::systems::{
+    effects::{DecalRing, MAX_DECALS, Tracer},
+    projectiles::{Lifetime, grenade::GrenadeThrower},
+};
+
+use super::{
+    shader::Shader,
+    stream_buffer::{StreamBuffer, segment_size_per_view},
+};
+
+const MAX_TRACERS: usize = 64;
+// Segments of all grenade arc previews together
+const MAX_ARC_SEGMENTS: usize = 256;
+// Debug overlays, e.g. the edges of a frozen frustum
+const MAX_DEBUG_LINES: usize = 64;
+// 2 vertices per line & arc segment, 2 triangles per decal quad
+const MAX_VERTICES: usize =
+    MAX_TRACERS * 2 + MAX_ARC_SEGMENTS * 2 + MAX_DEBUG_LINES * 2 + MAX_DECALS * 6;
+const TRACER_COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.4);
+const ARC_COLOR: Vec3 = Vec3::new(0.9, 0.95, 1.0);
+const DEBUG_LINE_COLOR: Vec3 = Vec3::new(1.0, 0.2, 0.8);
+const DECAL_COLOR: Vec3 = Vec3::new(0.05, 0.04, 0.03);
+const DECAL_SIZE: f32 = 1.2;
+// Lifts decals off the voxel face to avoid z-fighting
+const DECAL_OFFSET: f32 = 0.01;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct EffectVertex {
+    position: Vec3,
+    // Plain array: Vec4 is 16 byte aligned & would introduce padding
+    color: [f32; 4],
+}
+
+/// Batches tracer lines, grenade arcs, debug lines & impact decals into a single streamed vertex buffer per
+/// frame
+pub struct EffectsRenderer {
+    gl: Rc<glow::Context>,
+    shader: Shader,
+    vao: NativeVertexArray,
+    stream: StreamBuffer,
+}
+
+impl EffectsRenderer {
+    pub fn new(gl: &Rc<glow::Context>) -> Result<EffectsRenderer, Box<dyn Error>> {
+        let shader = Shader::new(
+            gl,
+            "assets/shaders/effects.vert",
+            "assets/shaders/effects.frag",
+        )?;
+        let stream = StreamBuffer::new(
+            gl,
+            glow::ARRAY_BUFFER,
+            segment_size_per_view(
+                MAX_VERTICES * size_of::<EffectVertex>(),
+                size_of::<EffectVertex>(),
+            ),
+        )?;
+        let vao = unsafe { gl.create_vertex_array()? };
+        Ok(Self {
+            gl: Rc::clone(gl),
+            shader,
+            vao,
+            stream,
+        })
+    }
+
+    /// Has to be called once per frame, before the first view renders effects
+    pub fn begin_frame(&mut self) {
+        self.stream.begin_frame();
+    }
+
+    /// Draws into the currently bound frame buffer. Requires frame uniforms to be up to date
+    pub fn render(&mut self, world: &World, decals: &DecalRing, debug_lines: &[[Vec3; 2]]) {
+        let (vertices, line_vertices) = effect_vertices(world, decals, debug_lines);
+        if vertices.is_empty() {
+            return;
+        }
+        let Some(offset) = self
+            .stream
+            .write(bytemuck::cast_slice(&vertices), size_of::<EffectVertex>())
+        else {
+            warn!("Effects stream buffer full. Skipping effects");
+            return;
+        };
+        let first = (offset / size_of::<EffectVertex>()) as i32;
+        let line_count = line_vertices as i32;
+        let gl = &self.gl;
+        self.shader.use_program();
+        unsafe {
+            gl.bind_vertex_array(Some(self.vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.stream.buffer()));
+            let stride = size_of::<EffectVertex>() as i32;
+            gl.vertex_attrib_pointer_f32(
+                0,
+                3,
+                glow::FLOAT,
+                false,
+                stride,
+                offset_of!(EffectVertex, position) as i32,
+            );
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(
+                1,
+                4,
+                glow::FLOAT,
+                false,
+                stride,
+                offset_of!(EffectVertex, color) as i32,
+            );
+            gl.enable_vertex_attrib_array(1);
+
+            // Transparent & drawn on top of opaque geometry: Test depth, but do not write it
+            gl.enable(glow::BLEND);
+            gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+            gl.depth_mask(false);
+            gl.disable(glow::CULL_FACE);
+            if line_count > 0 {
+                gl.draw_arrays(glow::LINES, first, line_count);
+            }
+            let triangle_count = vertices.len() as i32 - line_count;
+            if triangle_count > 0 {
+                gl.draw_arrays(glow::TRIANGLES, first + line_count, triangle_count);
+            }
+            gl.enable(glow::CULL_FACE);
+            gl.depth_mask(true);
+            gl.disable(glow::BLEND);
+
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+}
+
+impl Drop for EffectsRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
+
+/// Tracer, arc & debug lines first, decal triangles afterwards. Returns the vertices & how many
+/// of them belong to lines
+fn effect_vertices(
+    world: &World,
+    decals: &DecalRing,
+    debug_lines: &[[Vec3; 2]],
+) -> (Vec<EffectVertex>, usize) {
+    let mut vertices = Vec::new();
+    for (_entity, (tracer, lifetime)) in world
+        .query::<(&Tracer, &Lifetime)>()
+        .iter()
+        .take(MAX_TRACERS)
+    {
+        let color = TRACER_COLOR.extend(tracer.alpha(lifetime.0)).to_array();
+        vertices.push(EffectVertex {
+            position: tracer.start,
+            color,
+        });
+        vertices.push(EffectVertex {
+            position: tracer.end,
+            color,
+        });
+    }
+    let arc_color = ARC_COLOR.extend(0.8).to_array();
+    let mut throwers = world.query::<&GrenadeThrower>();
+    let arc_segments = throwers
+        .iter()
+        .flat_map(|(_entity, thrower)| thrower.arc.windows(2))
+        .take(MAX_ARC_SEGMENTS);
+    for segment in arc_segments {
+        for position in segment {
+            vertices.push(EffectVertex {
+                position: *position,
+                color: arc_color,
+            });
+        }
+    }
+    let debug_color = DEBUG_LINE_COLOR.extend(1.0).to_array();
+    for position in debug_lines.iter().take(MAX_DEBUG_LINES).flatten() {
+        vertices.push(EffectVertex {
+            position: *position,
+            color: debug_color,
+        });
+    }
+    let line_vertices = vertices.len();
+    for (decal, alpha) in decals.iter() {
+        let (u, v) = decal.normal.any_orthonormal_pair();
+        let center = decal.position + decal.normal * DECAL_OFFSET;
+        let half = DECAL_SIZE / 2.0;
+        let corners = [
+            center - u * half - v * half,
+            center + u * half - v * half,
+            center + u * half + v * half,
+            center - u * half + v * half,
+        ];
+        let color = DECAL_COLOR.extend(alpha * 0.8).to_array();
+        for index in [0, 1, 2, 0, 2, 3] {
+            vertices.push(EffectVertex {
+                position: corners[index],
+                color,
+            });
+        }
+    }
+    (vertices, line_vertices)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::effects::spawn_tracer;
+
+    use super::*;
+
+    #[test]
+    fn test_effect_vertices() {
+        let mut world = World::new();
+        spawn_tracer(&mut world, Vec3::ZERO, Vec3::X * 10.0);
+        let mut decals = DecalRing::new(2);
+        decals.push(Vec3::X * 10.0, Vec3::NEG_X);
+        let thrower = world.spawn((GrenadeThrower::default(),));
+        world.get::<&mut GrenadeThrower>(thrower).unwrap().arc = vec![Vec3::ZERO, Vec3::Y, Vec3::X];
+        let debug_lines = [[Vec3::ZERO, Vec3::NEG_Y]];
+        let (vertices, line_vertices) = effect_vertices(&world, &decals, &debug_lines);
+        assert_eq!(line_vertices, 2 + 4 + 2);
+        assert_eq!(vertices.len(), 2 + 4 + 2 + 6);
+        assert_eq!(vertices[1].position, Vec3::X * 10.0);
+        assert_eq!(vertices[5].position, Vec3::X);
+        assert_eq!(vertices[7].position, Vec3::NEG_Y);
+        // Decal lies in the plane of the face, slightly in front of it
+        assert!(
+            vertices[line_vertices..]
+                .iter()
+                .all(|vertex| (vertex.position.x - (10.0 - DECAL_OFFSET)).abs() < 1e-5)
+        );
+    }
+}
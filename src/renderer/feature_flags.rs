@@ -0,0 +1,166 @@
+use imgui::Ui;
+
+/// Render feature that can be toggled at runtime, e.g. to compare frame times with & without it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    Ssao,
+    Bloom,
+    /// Skips chunks outside the camera frustum
+    FrustumCulling,
+    /// Draws all opaque chunks with a single indirect call if the GL context supports it
+    MultiDrawIndirect,
+    /// Counts heap usage per subsystem. Needs the tracking allocator to be registered
+    MemoryTracking,
+    /// Draws distant small entities as billboards instead of their meshes
+    Impostors,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 6] = [
+        Feature::Ssao,
+        Feature::Bloom,
+        Feature::FrustumCulling,
+        Feature::MultiDrawIndirect,
+        Feature::MemoryTracking,
+        Feature::Impostors,
+    ];
+
+    /// Identifier used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Ssao => "ssao",
+            Feature::Bloom => "bloom",
+            Feature::FrustumCulling => "frustum-culling",
+            Feature::MultiDrawIndirect => "multi-draw",
+            Feature::MemoryTracking => "memory-tracking",
+            Feature::Impostors => "impostors",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::Ssao => "SSAO",
+            Feature::Bloom => "Bloom",
+            Feature::FrustumCulling => "Frustum culling",
+            Feature::MultiDrawIndirect => "Indirect multi-draw",
+            Feature::MemoryTracking => "Memory tracking",
+            Feature::Impostors => "Distant impostors",
+        }
+    }
+
+    fn enabled_by_default(self) -> bool {
+        !matches!(self, Feature::Ssao | Feature::MemoryTracking)
+    }
+
+    fn index(self) -> usize {
+        Feature::ALL.iter().position(|f| *f == self).unwrap()
+    }
+
+    pub fn from_name(name: &str) -> Option<Feature> {
+        Feature::ALL
+            .into_iter()
+            .find(|feature| feature.name() == name)
+    }
+}
+
+/// Runtime toggles for render features. Part of the render settings, so scenes pick up changes
+/// through [crate::scenes::GuiScene::apply_render_settings]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureFlags {
+    enabled: [bool; Feature::ALL.len()],
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            enabled: Feature::ALL.map(Feature::enabled_by_default),
+        }
+    }
+}
+
+impl FeatureFlags {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.enabled[feature.index()]
+    }
+
+    pub fn set(&mut self, feature: Feature, enabled: bool) {
+        self.enabled[feature.index()] = enabled;
+    }
+
+    /// Applies a command line override like `ssao=on` or `bloom=off`
+    pub fn parse_override(&mut self, value: &str) -> Result<(), String> {
+        let (name, state) = value
+            .split_once('=')
+            .ok_or(format!("Expected <feature>=on|off, got '{value}'"))?;
+        let feature = Feature::from_name(name).ok_or_else(|| {
+            let names: Vec<&str> = Feature::ALL.map(Feature::name).to_vec();
+            format!(
+                "Unknown feature '{name}'. Valid options are: {}",
+                names.join(", ")
+            )
+        })?;
+        let enabled = match state {
+            "on" | "true" | "1" => true,
+            "off" | "false" | "0" => false,
+            _ => return Err(format!("Invalid state '{state}' for feature '{name}'")),
+        };
+        self.set(feature, enabled);
+        Ok(())
+    }
+
+    /// Features whose state differs from previous, with their new state
+    pub fn changed_since<'a>(
+        &'a self,
+        previous: &'a FeatureFlags,
+    ) -> impl Iterator<Item = (Feature, bool)> + 'a {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| self.is_enabled(*feature) != previous.is_enabled(*feature))
+            .map(|feature| (feature, self.is_enabled(feature)))
+    }
+
+    /// Comma separated names of the enabled features, e.g. for benchmark logs
+    pub fn summary(&self) -> String {
+        let enabled: Vec<&str> = Feature::ALL
+            .into_iter()
+            .filter(|feature| self.is_enabled(*feature))
+            .map(Feature::name)
+            .collect();
+        enabled.join(", ")
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Features")
+            .size([220.0, 160.0], imgui::Condition::FirstUseEver)
+            .position([300.0, 200.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                for feature in Feature::ALL {
+                    ui.checkbox(feature.label(), &mut self.enabled[feature.index()]);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_override() {
+        let defaults = FeatureFlags::default();
+        let mut flags = defaults.clone();
+        flags.parse_override("ssao=on").unwrap();
+        flags.parse_override("frustum-culling=off").unwrap();
+        assert!(flags.is_enabled(Feature::Ssao));
+        assert!(!flags.is_enabled(Feature::FrustumCulling));
+        assert_eq!(
+            flags.changed_since(&defaults).collect::<Vec<_>>(),
+            vec![(Feature::Ssao, true), (Feature::FrustumCulling, false)]
+        );
+
+        assert!(flags.parse_override("shadows=on").is_err());
+        assert!(flags.parse_override("bloom").is_err());
+        assert!(flags.parse_override("bloom=maybe").is_err());
+        assert!(flags.is_enabled(Feature::Bloom));
+    }
+}
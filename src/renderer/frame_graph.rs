@@ -0,0 +1,244 @@
+use std::fmt::Debug;
+
+use imgui::Ui;
+
+/// Texture passed between passes, e.g. the color of the geometry pass
+pub type ResourceName = &'static str;
+
+/// Window framebuffer. Only passes contributing to it are executed
+pub const BACKBUFFER: ResourceName = "backbuffer";
+
+struct PassNode<P> {
+    pass: P,
+    reads: Vec<ResourceName>,
+    writes: Vec<ResourceName>,
+    enabled: bool,
+}
+
+/// Passes using a resource, as indices into the execution order. Its render target is free
+/// for other use before the first & after the last pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceLifetime {
+    pub resource: ResourceName,
+    pub first: usize,
+    pub last: usize,
+}
+
+/// Execution order resolved from the declared inputs & outputs of the passes
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledGraph<P> {
+    order: Vec<P>,
+    lifetimes: Vec<ResourceLifetime>,
+}
+
+impl<P> CompiledGraph<P> {
+    pub fn order(&self) -> &[P] {
+        &self.order
+    }
+
+    pub fn lifetimes(&self) -> &[ResourceLifetime] {
+        &self.lifetimes
+    }
+}
+
+/// Schedules the render passes of a frame. Passes declare the resources they read & write; the
+/// graph orders them so every resource is written before it is read, drops passes that do not
+/// contribute to the backbuffer & tracks how long each resource is in use.
+/// Inputs no enabled pass writes are left to the reading pass, e.g. to skip compositing bloom
+pub struct FrameGraph<P> {
+    // In declaration order. Breaks ties, e.g. between multiple writers of the same resource
+    passes: Vec<PassNode<P>>,
+    compiled: Option<CompiledGraph<P>>,
+}
+
+impl<P: Copy + PartialEq + Debug> FrameGraph<P> {
+    pub fn new() -> FrameGraph<P> {
+        Self {
+            passes: Vec::new(),
+            compiled: None,
+        }
+    }
+
+    pub fn add_pass(&mut self, pass: P, reads: &[ResourceName], writes: &[ResourceName]) {
+        self.passes.push(PassNode {
+            pass,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            enabled: true,
+        });
+        self.compiled = None;
+    }
+
+    /// Disabled passes are skipped together with passes only feeding them
+    pub fn set_enabled(&mut self, pass: P, enabled: bool) {
+        for node in self.passes.iter_mut().filter(|node| node.pass == pass) {
+            if node.enabled != enabled {
+                node.enabled = enabled;
+                self.compiled = None;
+            }
+        }
+    }
+
+    /// Resolves the execution order. Cached until passes change
+    pub fn compile(&mut self) -> Result<&CompiledGraph<P>, String> {
+        if self.compiled.is_none() {
+            self.compiled = Some(self.resolve()?);
+        }
+        Ok(self.compiled.as_ref().unwrap())
+    }
+
+    fn resolve(&self) -> Result<CompiledGraph<P>, String> {
+        let nodes: Vec<&PassNode<P>> = self.passes.iter().filter(|node| node.enabled).collect();
+        // Pass a has to run before pass b
+        let depends = |a: usize, b: usize| {
+            let (first, second) = (nodes[a], nodes[b]);
+            let feeds = first.writes.iter().any(|w| second.reads.contains(w));
+            // Later declared writers build on earlier ones
+            let overwrites = a < b && first.writes.iter().any(|w| second.writes.contains(w));
+            a != b && (feeds || overwrites)
+        };
+
+        // Culling: Keep passes the backbuffer transitively depends on
+        let mut needed: Vec<bool> = nodes
+            .iter()
+            .map(|node| node.writes.contains(&BACKBUFFER))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for a in 0..nodes.len() {
+                if !needed[a] && (0..nodes.len()).any(|b| needed[b] && depends(a, b)) {
+                    needed[a] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        // Topological sort, earliest declared pass first among the ready ones
+        let mut remaining: Vec<usize> = (0..nodes.len()).filter(|i| needed[*i]).collect();
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let ready = remaining
+                .iter()
+                .position(|b| !remaining.iter().any(|a| depends(*a, *b)))
+                .ok_or_else(|| {
+                    let passes: Vec<P> = remaining.iter().map(|i| nodes[*i].pass).collect();
+                    format!("Cyclic dependency between passes {passes:?}")
+                })?;
+            order.push(remaining.remove(ready));
+        }
+
+        let mut lifetimes: Vec<ResourceLifetime> = Vec::new();
+        for (position, node) in order.iter().map(|i| nodes[*i]).enumerate() {
+            for resource in node.reads.iter().chain(&node.writes) {
+                match lifetimes.iter_mut().find(|l| l.resource == *resource) {
+                    Some(lifetime) => lifetime.last = position,
+                    None => lifetimes.push(ResourceLifetime {
+                        resource,
+                        first: position,
+                        last: position,
+                    }),
+                }
+            }
+        }
+        Ok(CompiledGraph {
+            order: order.into_iter().map(|i| nodes[i].pass).collect(),
+            lifetimes,
+        })
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        let compiled = self.compile().cloned();
+        ui.window("Frame graph")
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 450.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let compiled = match compiled {
+                    Ok(compiled) => compiled,
+                    Err(err) => {
+                        ui.text_colored([1.0, 0.3, 0.3, 1.0], err);
+                        return;
+                    }
+                };
+                for (position, pass) in compiled.order().iter().enumerate() {
+                    ui.text(format!("{position}. {pass:?}"));
+                }
+                for node in self
+                    .passes
+                    .iter()
+                    .filter(|node| !compiled.order.contains(&node.pass))
+                {
+                    let reason = if node.enabled { "culled" } else { "disabled" };
+                    ui.text_disabled(format!("   {:?} ({reason})", node.pass));
+                }
+                ui.separator();
+                for lifetime in compiled.lifetimes() {
+                    ui.text(format!(
+                        "{}: {} - {}",
+                        lifetime.resource, lifetime.first, lifetime.last
+                    ));
+                }
+            });
+    }
+}
+
+impl<P: Copy + PartialEq + Debug> Default for FrameGraph<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Pass {
+        Geometry,
+        Effects,
+        Bloom,
+        Debug,
+        Composite,
+    }
+
+    fn graph() -> FrameGraph<Pass> {
+        let mut graph = FrameGraph::new();
+        // Declared out of order on purpose
+        graph.add_pass(Pass::Composite, &["color", "bloom"], &[BACKBUFFER]);
+        graph.add_pass(Pass::Bloom, &["color"], &["bloom"]);
+        graph.add_pass(Pass::Geometry, &[], &["color", "depth"]);
+        graph.add_pass(Pass::Effects, &["depth"], &["color"]);
+        // Nothing reads its output
+        graph.add_pass(Pass::Debug, &["depth"], &["debug"]);
+        graph
+    }
+
+    #[test]
+    fn test_order_and_culling() {
+        let mut graph = graph();
+        let compiled = graph.compile().unwrap();
+        assert_eq!(
+            compiled.order(),
+            &[Pass::Geometry, Pass::Effects, Pass::Bloom, Pass::Composite]
+        );
+        let depth = compiled
+            .lifetimes()
+            .iter()
+            .find(|lifetime| lifetime.resource == "depth")
+            .unwrap();
+        assert_eq!((depth.first, depth.last), (0, 1));
+
+        graph.set_enabled(Pass::Bloom, false);
+        assert_eq!(
+            graph.compile().unwrap().order(),
+            &[Pass::Geometry, Pass::Effects, Pass::Composite]
+        );
+    }
+
+    #[test]
+    fn test_cycle_is_rejected() {
+        let mut graph = graph();
+        graph.add_pass(Pass::Debug, &["bloom"], &["depth"]);
+        assert!(graph.compile().is_err());
+    }
+}
@@ -1,38 +1,186 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec3, Vec4};
 use glow::HasContext;
-use std::mem::size_of;
+use log::warn;
+use std::{error::Error, mem::size_of, rc::Rc};
 
+use crate::cameras::camera::Camera;
+
+use super::stream_buffer::{MAX_VIEWS, StreamBuffer, segment_size_per_view};
+
+/// Has to match MAX_LIGHTS in assets/shaders/frame_uniforms.glsl
+pub const MAX_LIGHTS: usize = 4;
+/// Uniform block binding point of the FrameUniforms block
+pub const FRAME_UNIFORMS_BINDING: u32 = 0;
+
+// Cone of lights shining in all directions
+const NO_CONE: Vec4 = Vec4::new(0.0, 0.0, 0.0, -1.0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    // xyz: direction towards light (w = 0) or position (w = 1)
+    position: Vec4,
+    // xyz: direction the cone points in, w: cosine of the cone half angle
+    cone: Vec4,
+    pub color: Vec3,
+}
+
+impl Light {
+    /// Light infinitely far away. Direction points **towards** the light in world space
+    pub fn directional(direction: Vec3, color: Vec3) -> Light {
+        Self {
+            position: direction.normalize().extend(0.0),
+            cone: NO_CONE,
+            color,
+        }
+    }
+
+    pub fn point(position: Vec3, color: Vec3) -> Light {
+        Self {
+            position: position.extend(1.0),
+            cone: NO_CONE,
+            color,
+        }
+    }
+
+    /// Point light only reaching surfaces within half_angle of direction
+    pub fn spot(position: Vec3, direction: Vec3, half_angle: f32, color: Vec3) -> Light {
+        Self {
+            position: position.extend(1.0),
+            cone: direction.normalize_or(Vec3::NEG_Y).extend(half_angle.cos()),
+            color,
+        }
+    }
+
+    /// Direction towards the light. None for point lights
+    pub fn direction(&self) -> Option<Vec3> {
+        (self.position.w == 0.0).then(|| self.position.truncate())
+    }
+}
+
+/// Lights shared by all shaders of a frame
+#[derive(Debug, Clone)]
+pub struct SceneLighting {
+    pub ambient: Vec3,
+    pub lights: Vec<Light>,
+}
+
+impl Default for SceneLighting {
+    fn default() -> Self {
+        Self {
+            ambient: Vec3::ONE * 0.5,
+            lights: vec![Light::directional(
+                Quat::from_rotation_x(20.0) * Vec3::Y,
+                Vec3::ONE,
+            )],
+        }
+    }
+}
+
+/// CPU side mirror of the std140 FrameUniforms block
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FrameUniformData {
+    view: Mat4,
+    projection: Mat4,
+    camera_position: Vec4,
+    time: f32,
+    light_count: i32,
+    // std140: Next vec4 aligned to 16 bytes
+    _padding: [f32; 2],
+    ambient_light: Vec4,
+    light_positions: [Vec4; MAX_LIGHTS],
+    light_colors: [Vec4; MAX_LIGHTS],
+    light_cones: [Vec4; MAX_LIGHTS],
+}
+
+/// Per-frame camera, time & lighting data shared via UBO.
+/// Uploaded once per frame instead of setting uniforms for every draw.
+/// Streamed through a ring buffer, so a new frame never waits on the GPU reading the last one
 pub struct FrameUniforms {
-    ubo: glow::NativeBuffer,
+    stream: StreamBuffer,
+    // Required offset alignment for binding UBO ranges
+    alignment: usize,
 }
 
 impl FrameUniforms {
-    pub fn new(gl: &glow::Context) -> Self {
-        unsafe {
-            let ubo = gl.create_buffer().expect("Failed to create UBO");
-            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(ubo));
-            // Allocate storage (std140: float = 4 bytes)
-            gl.buffer_data_size(
-                glow::UNIFORM_BUFFER,
-                size_of::<f32>() as i32,
-                glow::DYNAMIC_DRAW,
-            );
-            // Bind to binding point 0
-            gl.bind_buffer_base(glow::UNIFORM_BUFFER, 0, Some(ubo));
+    pub fn new(gl: &Rc<glow::Context>) -> Result<FrameUniforms, Box<dyn Error>> {
+        let alignment =
+            unsafe { gl.get_parameter_i32(glow::UNIFORM_BUFFER_OFFSET_ALIGNMENT) }.max(1) as usize;
+        let segment_size = segment_size_per_view(size_of::<FrameUniformData>(), alignment);
+        let stream = StreamBuffer::new(gl, glow::UNIFORM_BUFFER, segment_size)?;
+        Ok(Self { stream, alignment })
+    }
 
-            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
-            Self { ubo }
-        }
+    /// Has to be called once per frame, before the first view updates the uniforms
+    pub fn begin_frame(&mut self) {
+        self.stream.begin_frame();
     }
 
-    pub fn update_time(&self, gl: &glow::Context, time_seconds: f32) {
+    /// Binds the uniforms of a view. Every view of a frame gets its own range of the buffer
+    pub fn update(
+        &mut self,
+        gl: &glow::Context,
+        cam: &Camera,
+        time_seconds: f32,
+        lighting: &SceneLighting,
+    ) {
+        let mut data = FrameUniformData {
+            view: cam.get_view_matrix(),
+            projection: cam.get_projection_matrix(),
+            camera_position: cam.position.extend(1.0),
+            time: time_seconds,
+            light_count: lighting.lights.len().min(MAX_LIGHTS) as i32,
+            _padding: [0.0; 2],
+            ambient_light: lighting.ambient.extend(1.0),
+            light_positions: [Vec4::ZERO; MAX_LIGHTS],
+            light_colors: [Vec4::ZERO; MAX_LIGHTS],
+            light_cones: [NO_CONE; MAX_LIGHTS],
+        };
+        for (i, light) in lighting.lights.iter().take(MAX_LIGHTS).enumerate() {
+            data.light_positions[i] = light.position;
+            data.light_colors[i] = light.color.extend(1.0);
+            data.light_cones[i] = light.cone;
+        }
+        let Some(offset) = self.stream.write(bytemuck::bytes_of(&data), self.alignment) else {
+            warn!("More than {MAX_VIEWS} views this frame. Skipping frame uniform update");
+            return;
+        };
         unsafe {
-            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.ubo));
-            let bytes = std::slice::from_raw_parts(
-                &time_seconds as *const f32 as *const u8,
-                size_of::<f32>(),
+            // Multiple owners may exist. Last update wins the binding point
+            gl.bind_buffer_range(
+                glow::UNIFORM_BUFFER,
+                FRAME_UNIFORMS_BINDING,
+                Some(self.stream.buffer()),
+                offset as i32,
+                size_of::<FrameUniformData>() as i32,
             );
-            gl.buffer_sub_data_u8_slice(glow::UNIFORM_BUFFER, 0, bytes);
-            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::offset_of;
+
+    use super::*;
+
+    #[test]
+    fn test_frame_uniform_data_matches_std140_layout() {
+        assert_eq!(offset_of!(FrameUniformData, view), 0);
+        assert_eq!(offset_of!(FrameUniformData, projection), 64);
+        assert_eq!(offset_of!(FrameUniformData, camera_position), 128);
+        assert_eq!(offset_of!(FrameUniformData, time), 144);
+        assert_eq!(offset_of!(FrameUniformData, light_count), 148);
+        assert_eq!(offset_of!(FrameUniformData, ambient_light), 160);
+        assert_eq!(offset_of!(FrameUniformData, light_positions), 176);
+        assert_eq!(
+            offset_of!(FrameUniformData, light_colors),
+            176 + 16 * MAX_LIGHTS
+        );
+        assert_eq!(
+            offset_of!(FrameUniformData, light_cones),
+            176 + 32 * MAX_LIGHTS
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::{error::Error, rc::Rc};
+
+use glow::{HasContext, NativeFramebuffer, NativeRenderbuffer, NativeTexture};
+use log::{info, warn};
+
+use super::viewport::Viewport;
+
+/// Multisampled attachments. Rendered into & resolved into the sampleable textures afterwards
+struct MsaaAttachments {
+    fbo: NativeFramebuffer,
+    color: NativeRenderbuffer,
+    depth_stencil: NativeRenderbuffer,
+}
+
+/// Offscreen target for the main geometry pass
+/// - Color & depth are exposed as textures for post-processing
+/// - Optionally renders into multisampled renderbuffers and resolves via blit
+pub struct GeometryBuffer {
+    gl: Rc<glow::Context>,
+    width: i32,
+    height: i32,
+    samples: i32,
+    resolve_fbo: NativeFramebuffer,
+    color_texture: NativeTexture,
+    depth_texture: NativeTexture,
+    msaa: Option<MsaaAttachments>,
+}
+
+impl GeometryBuffer {
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        width: i32,
+        height: i32,
+        samples: i32,
+    ) -> Result<GeometryBuffer, Box<dyn Error>> {
+        let samples = clamp_samples(gl, samples);
+        unsafe {
+            // Setup resolve framebuffer
+            let resolve_fbo = gl.create_framebuffer()?;
+            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(resolve_fbo));
+            // Setup frame color texture
+            let color_texture = gl.create_texture()?;
+            gl.bind_texture(gl::TEXTURE_2D, Some(color_texture));
+            gl.tex_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB as i32,
+                width,
+                height,
+                0,
+                gl::RGB,
+                gl::UNSIGNED_BYTE,
+                None,
+            );
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            // Attach color texture to framebuffer
+            gl.framebuffer_texture_2d(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                Some(color_texture),
+                0,
+            );
+
+            // Setup depth & stencil buffer
+            let depth_texture = gl.create_texture()?;
+            gl.bind_texture(gl::TEXTURE_2D, Some(depth_texture));
+            gl.tex_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH24_STENCIL8 as i32,
+                width,
+                height,
+                0,
+                gl::DEPTH_STENCIL,
+                gl::UNSIGNED_INT_24_8,
+                None,
+            );
+            // Sample only depth values into texture
+            gl.tex_parameter_i32(
+                gl::TEXTURE_2D,
+                gl::DEPTH_STENCIL_TEXTURE_MODE,
+                gl::DEPTH_COMPONENT as i32,
+            );
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            // Attach stencil texture to framebuffer
+            gl.framebuffer_texture_2d(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::TEXTURE_2D,
+                Some(depth_texture),
+                0,
+            );
+
+            let msaa = if samples > 1 {
+                Some(create_msaa_attachments(gl, width, height, samples)?)
+            } else {
+                None
+            };
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+            info!("Created geometry buffer {width}x{height} with {samples} sample(s)");
+            Ok(Self {
+                gl: Rc::clone(gl),
+                width,
+                height,
+                samples,
+                resolve_fbo,
+                color_texture,
+                depth_texture,
+                msaa,
+            })
+        }
+    }
+
+    /// Recreates all attachments if size or sample count changed
+    pub fn resize(&mut self, width: i32, height: i32, samples: i32) -> Result<(), Box<dyn Error>> {
+        if width == self.width
+            && height == self.height
+            && clamp_samples(&self.gl, samples) == self.samples
+        {
+            return Ok(());
+        }
+        *self = GeometryBuffer::new(&self.gl, width, height, samples)?;
+        Ok(())
+    }
+
+    /// Binds the framebuffer geometry should be rendered into & sets the viewport accordingly
+    pub fn bind(&self) {
+        let fbo = match &self.msaa {
+            Some(msaa) => msaa.fbo,
+            None => self.resolve_fbo,
+        };
+        unsafe {
+            self.gl.bind_framebuffer(gl::FRAMEBUFFER, Some(fbo));
+        }
+        self.viewport().apply(&self.gl);
+    }
+
+    /// Whole buffer. Geometry buffers hold a single view
+    pub fn viewport(&self) -> Viewport {
+        Viewport::full(self.width, self.height)
+    }
+
+    /// Resolves multisampled attachments into the sampleable textures. No-op without MSAA
+    pub fn resolve(&self) {
+        let Some(msaa) = &self.msaa else {
+            return;
+        };
+        let gl = &self.gl;
+        unsafe {
+            gl.bind_framebuffer(gl::READ_FRAMEBUFFER, Some(msaa.fbo));
+            gl.bind_framebuffer(gl::DRAW_FRAMEBUFFER, Some(self.resolve_fbo));
+            gl.blit_framebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                self.width,
+                self.height,
+                gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        }
+    }
+
+    pub fn color_texture(&self) -> NativeTexture {
+        self.color_texture
+    }
+
+    pub fn depth_texture(&self) -> NativeTexture {
+        self.depth_texture
+    }
+}
+
+impl Drop for GeometryBuffer {
+    fn drop(&mut self) {
+        let gl = &self.gl;
+        unsafe {
+            if let Some(msaa) = &self.msaa {
+                gl.delete_framebuffer(msaa.fbo);
+                gl.delete_renderbuffer(msaa.color);
+                gl.delete_renderbuffer(msaa.depth_stencil);
+            }
+            gl.delete_framebuffer(self.resolve_fbo);
+            gl.delete_texture(self.color_texture);
+            gl.delete_texture(self.depth_texture);
+        }
+    }
+}
+
+/// Clamps requested sample count to what the driver supports
+fn clamp_samples(gl: &glow::Context, samples: i32) -> i32 {
+    let max_samples = unsafe { gl.get_parameter_i32(gl::MAX_SAMPLES) };
+    if samples > max_samples {
+        warn!("Requested {samples}x MSAA, but only {max_samples}x is supported");
+    }
+    samples.clamp(1, max_samples.max(1))
+}
+
+unsafe fn create_msaa_attachments(
+    gl: &glow::Context,
+    width: i32,
+    height: i32,
+    samples: i32,
+) -> Result<MsaaAttachments, Box<dyn Error>> {
+    unsafe {
+        let fbo = gl.create_framebuffer()?;
+        gl.bind_framebuffer(gl::FRAMEBUFFER, Some(fbo));
+
+        let color = gl.create_renderbuffer()?;
+        gl.bind_renderbuffer(gl::RENDERBUFFER, Some(color));
+        gl.renderbuffer_storage_multisample(gl::RENDERBUFFER, samples, gl::RGB8, width, height);
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            Some(color),
+        );
+
+        // Same format as the resolve depth texture. Required for depth blit
+        let depth_stencil = gl.create_renderbuffer()?;
+        gl.bind_renderbuffer(gl::RENDERBUFFER, Some(depth_stencil));
+        gl.renderbuffer_storage_multisample(
+            gl::RENDERBUFFER,
+            samples,
+            gl::DEPTH24_STENCIL8,
+            width,
+            height,
+        );
+        gl.framebuffer_renderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_STENCIL_ATTACHMENT,
+            gl::RENDERBUFFER,
+            Some(depth_stencil),
+        );
+        gl.bind_renderbuffer(gl::RENDERBUFFER, None);
+
+        if gl.check_framebuffer_status(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            return Err("Multisampled geometry framebuffer incomplete".into());
+        }
+        Ok(MsaaAttachments {
+            fbo,
+            color,
+            depth_stencil,
+        })
+    }
+}
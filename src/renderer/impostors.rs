@@ -0,0 +1,194 @@
+use std::{error::Error, mem::offset_of, rc::Rc};
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use glow::{HasContext, NativeVertexArray};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::{shader::Shader, stream_buffer::StreamBuffer};
+
+/// Entities farther away from the camera than this are drawn as impostors by default
+pub const DEFAULT_IMPOSTOR_DISTANCE: f32 = 40.0;
+// Billboards of all passes of a frame together
+const MAX_IMPOSTORS: usize = 4096;
+// 2 triangles per billboard
+const VERTICES_PER_IMPOSTOR: usize = 6;
+
+/// Drawn as a camera facing billboard instead of its mesh while far away from the camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Impostor {
+    /// Radius of the untransformed mesh. Scaled by the transform like the mesh
+    pub radius: f32,
+    /// Used if the entity has no RenderColor
+    pub color: Vec3,
+}
+
+/// Billboard of a single entity in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImpostorInstance {
+    pub center: Vec3,
+    pub radius: f32,
+    pub color: Vec3,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ImpostorVertex {
+    position: Vec3,
+    // From -1 to 1 across the billboard, shaded as a sphere
+    corner: [f32; 2],
+    color: [f32; 3],
+}
+
+/// Collects the impostors of a pass & draws them with a single draw call from a streamed vertex
+/// buffer
+pub struct ImpostorRenderer {
+    gl: Rc<glow::Context>,
+    shader: Shader,
+    vao: NativeVertexArray,
+    stream: StreamBuffer,
+    instances: Vec<ImpostorInstance>,
+}
+
+impl ImpostorRenderer {
+    pub fn new(gl: &Rc<glow::Context>) -> Result<ImpostorRenderer, Box<dyn Error>> {
+        let shader = Shader::new(
+            gl,
+            "assets/shaders/impostor.vert",
+            "assets/shaders/impostor.frag",
+        )?;
+        let stream = StreamBuffer::new(
+            gl,
+            glow::ARRAY_BUFFER,
+            MAX_IMPOSTORS * VERTICES_PER_IMPOSTOR * size_of::<ImpostorVertex>(),
+        )?;
+        let vao = unsafe { gl.create_vertex_array()? };
+        Ok(Self {
+            gl: Rc::clone(gl),
+            shader,
+            vao,
+            stream,
+            instances: Vec::new(),
+        })
+    }
+
+    /// Has to be called once per frame, before the first pass drawing impostors
+    pub fn begin_frame(&mut self) {
+        self.stream.begin_frame();
+    }
+
+    pub fn push(&mut self, instance: ImpostorInstance) {
+        self.instances.push(instance);
+    }
+
+    /// Draws & clears the collected impostors, facing the camera with the given right & up axes.
+    /// Requires frame uniforms to be up to date
+    pub fn flush(&mut self, right: Vec3, up: Vec3) {
+        if self.instances.is_empty() {
+            return;
+        }
+        let vertices = billboard_vertices(&self.instances, right, up);
+        self.instances.clear();
+        let Some(offset) = self
+            .stream
+            .write(bytemuck::cast_slice(&vertices), size_of::<ImpostorVertex>())
+        else {
+            warn!("Impostor stream buffer full. Skipping impostors");
+            return;
+        };
+        let gl = &self.gl;
+        self.shader.use_program();
+        unsafe {
+            gl.bind_vertex_array(Some(self.vao));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.stream.buffer()));
+            let stride = size_of::<ImpostorVertex>() as i32;
+            let attributes = [
+                (3, offset_of!(ImpostorVertex, position)),
+                (2, offset_of!(ImpostorVertex, corner)),
+                (3, offset_of!(ImpostorVertex, color)),
+            ];
+            for (location, (size, attribute_offset)) in attributes.into_iter().enumerate() {
+                gl.vertex_attrib_pointer_f32(
+                    location as u32,
+                    size,
+                    glow::FLOAT,
+                    false,
+                    stride,
+                    attribute_offset as i32,
+                );
+                gl.enable_vertex_attrib_array(location as u32);
+            }
+            let first = (offset / size_of::<ImpostorVertex>()) as i32;
+            gl.draw_arrays(glow::TRIANGLES, first, vertices.len() as i32);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+}
+
+impl Drop for ImpostorRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
+
+// Quads around the centers, counter-clockwise seen from the camera
+fn billboard_vertices(
+    instances: &[ImpostorInstance],
+    right: Vec3,
+    up: Vec3,
+) -> Vec<ImpostorVertex> {
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+    let mut vertices = Vec::with_capacity(instances.len() * VERTICES_PER_IMPOSTOR);
+    for instance in instances {
+        for index in [0, 1, 2, 0, 2, 3] {
+            let corner: [f32; 2] = corners[index];
+            let offset = (right * corner[0] + up * corner[1]) * instance.radius;
+            vertices.push(ImpostorVertex {
+                position: instance.center + offset,
+                corner,
+                color: instance.color.to_array(),
+            });
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_billboards_face_the_camera() {
+        let instances = [
+            ImpostorInstance {
+                center: Vec3::new(0.0, 0.0, -50.0),
+                radius: 0.5,
+                color: Vec3::X,
+            },
+            ImpostorInstance {
+                center: Vec3::new(10.0, 2.0, -80.0),
+                radius: 2.0,
+                color: Vec3::Y,
+            },
+        ];
+        // Camera looking down -z
+        let vertices = billboard_vertices(&instances, Vec3::X, Vec3::Y);
+        assert_eq!(vertices.len(), 2 * VERTICES_PER_IMPOSTOR);
+        for triangle in vertices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|index| triangle[index].position);
+            // Front face points towards the camera
+            assert!((b - a).cross(c - a).normalize().dot(Vec3::Z) > 0.999);
+        }
+        let second = &vertices[VERTICES_PER_IMPOSTOR..];
+        assert!(
+            second
+                .iter()
+                .all(|vertex| vertex.position.z == -80.0 && vertex.color == [0.0, 1.0, 0.0])
+        );
+        assert_eq!(second[2].position, Vec3::new(12.0, 4.0, -80.0));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    ffi::c_void,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bytemuck::{Pod, Zeroable};
+use log::{info, warn};
+
+use super::capabilities;
+
+static MULTI_DRAW_INDIRECT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Layout expected by glMultiDrawArraysIndirect
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawArraysIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    /// Offset into instanced vertex attributes. Requires GL 4.2 / ARB_base_instance
+    pub base_instance: u32,
+}
+
+/// Loads glMultiDrawArraysIndirect if supported (GL 4.3 or ARB_multi_draw_indirect together with
+/// ARB_base_instance). Renderers fall back to one draw call per batch without it.
+/// Has to be called once after context creation
+pub fn setup_multi_draw_indirect<F>(gl: &glow::Context, loader: F) -> bool
+where
+    F: FnMut(&'static str) -> *const c_void,
+{
+    let extensions = ["GL_ARB_multi_draw_indirect", "GL_ARB_base_instance"];
+    if !capabilities::supports(gl, (4, 3), &extensions) {
+        warn!("glMultiDrawArraysIndirect not supported. Falling back to per-batch draw calls");
+        return false;
+    }
+    gl::MultiDrawArraysIndirect::load_with(loader);
+    if !gl::MultiDrawArraysIndirect::is_loaded() {
+        warn!("Unable to load glMultiDrawArraysIndirect");
+        return false;
+    }
+    info!("Enabled indirect multi-draw");
+    MULTI_DRAW_INDIRECT_ENABLED.store(true, Ordering::Relaxed);
+    true
+}
+
+pub fn is_multi_draw_indirect_supported() -> bool {
+    MULTI_DRAW_INDIRECT_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Issues `draw_count` commands tightly packed at the start of the bound DRAW_INDIRECT_BUFFER
+///
+/// # Safety
+/// Requires [setup_multi_draw_indirect] to have succeeded & an indirect buffer holding at least
+/// `draw_count` commands to be bound
+pub unsafe fn multi_draw_arrays_indirect(mode: u32, draw_count: i32) {
+    debug_assert!(is_multi_draw_indirect_supported());
+    unsafe {
+        gl::MultiDrawArraysIndirect(mode, std::ptr::null(), draw_count, 0);
+    }
+}
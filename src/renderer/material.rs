@@ -0,0 +1,103 @@
+use glam::{Mat3, Mat4, Vec2, Vec3};
+
+pub type ShaderHandle = usize;
+pub type TextureHandle = usize;
+
+/// Typed uniform parameter of a material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    I32(i32),
+    F32(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Mat3(Mat3),
+    Mat4(Mat4),
+}
+
+/// Texture bound to a texture unit & exposed to the shader via sampler uniform
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialTexture {
+    pub unit: u32,
+    pub sampler: String,
+    pub texture: TextureHandle,
+}
+
+/// Per-entity render parameters
+/// Entities sharing a mesh can differ in shader, uniforms & textures without new mesh entries.
+/// Shaders & textures are referenced by handles registered at the ECSRenderer. Uniforms the
+/// material does not set keep the defaults of the shader source
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Material {
+    /// Overrides the default shader of the mesh. Has to accept the vertex layout of the mesh
+    pub shader: Option<ShaderHandle>,
+    pub uniforms: Vec<(String, UniformValue)>,
+    pub textures: Vec<MaterialTexture>,
+}
+
+impl Material {
+    pub fn new() -> Material {
+        Self::default()
+    }
+
+    pub fn with_shader(mut self, shader: ShaderHandle) -> Material {
+        self.shader = Some(shader);
+        self
+    }
+
+    /// Adds or replaces uniform with given name
+    pub fn with_uniform(mut self, name: &str, value: UniformValue) -> Material {
+        self.set_uniform(name, value);
+        self
+    }
+
+    /// Shorthand for the `uColor` uniform used by most mesh shaders
+    pub fn with_color(self, color: Vec3) -> Material {
+        self.with_uniform("uColor", UniformValue::Vec3(color))
+    }
+
+    pub fn with_texture(mut self, unit: u32, sampler: &str, texture: TextureHandle) -> Material {
+        self.textures.retain(|t| t.unit != unit);
+        self.textures.push(MaterialTexture {
+            unit,
+            sampler: sampler.to_string(),
+            texture,
+        });
+        self
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.uniforms.push((name.to_string(), value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameters_replace_previous_values() {
+        let material = Material::new()
+            .with_color(Vec3::X)
+            .with_uniform("uFade", UniformValue::F32(0.5))
+            .with_color(Vec3::Y)
+            .with_texture(0, "diffuseMap", 1)
+            .with_texture(1, "normalMap", 2)
+            .with_texture(0, "diffuseMap", 3);
+        assert_eq!(
+            material.uniforms,
+            [
+                ("uColor".to_string(), UniformValue::Vec3(Vec3::Y)),
+                ("uFade".to_string(), UniformValue::F32(0.5)),
+            ]
+        );
+        let units: Vec<(u32, TextureHandle)> = material
+            .textures
+            .iter()
+            .map(|texture| (texture.unit, texture.texture))
+            .collect();
+        assert_eq!(units, [(1, 2), (0, 3)]);
+    }
+}
@@ -0,0 +1,80 @@
+use std::{error::Error, rc::Rc};
+
+use glow::HasContext;
+
+use crate::{meshes::objmesh::ObjMesh, renderer::shader::Shader};
+
+use super::Mesh;
+
+/// OBJ file of the player character model, relative to the working directory
+pub const CHARACTER_OBJ: &str = "assets/character.obj";
+
+/// Capsule body of the player character: 1.8 units tall, centered at the origin
+pub fn character_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
+    let shader = Shader::new(
+        gl,
+        "assets/shaders/cube.vert",
+        "assets/shaders/cube-diffuse.frag",
+    )?;
+
+    // Load vertex data from mesh
+    let mut mesh = ObjMesh::new();
+    mesh.load(CHARACTER_OBJ)?;
+    let vertex_buffers = mesh.get_vertex_buffers();
+    // NOTE: /3 because we have 3 coordinates per vertex
+    let vertex_count = vertex_buffers.position_buffer.len() / 3;
+    let positions_bytes: &[u8] = bytemuck::cast_slice(&vertex_buffers.position_buffer);
+    let normals_bytes: &[u8] = bytemuck::cast_slice(&vertex_buffers.normal_buffer);
+
+    unsafe {
+        // Setup vertex array object
+        let vao = gl.create_vertex_array()?;
+        gl.bind_vertex_array(Some(vao));
+
+        // Buffer position data
+        let positions_vbo = gl.create_buffer()?;
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(positions_vbo));
+        gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, positions_bytes, gl::STATIC_DRAW);
+        // Setup position attribute
+        gl.vertex_attrib_pointer_f32(0, 3, gl::FLOAT, false, 0, 0);
+        gl.enable_vertex_array_attrib(vao, 0);
+
+        // Buffer normal data
+        let normals_vbo = gl.create_buffer()?;
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(normals_vbo));
+        gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, normals_bytes, gl::STATIC_DRAW);
+        // Setup normal attribute
+        gl.vertex_attrib_pointer_f32(1, 3, gl::FLOAT, false, 0, 0);
+        gl.enable_vertex_array_attrib(vao, 1);
+
+        // Cleanup
+        gl.bind_buffer(gl::ARRAY_BUFFER, None);
+        gl.bind_vertex_array(None);
+
+        Ok(Mesh::new(shader, vao, vertex_count as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_character_model_matches_collider() {
+        let mut mesh = ObjMesh::new();
+        mesh.load(CHARACTER_OBJ).unwrap();
+        let buffers = mesh.get_vertex_buffers();
+        assert!(!buffers.position_buffer.is_empty());
+        assert_eq!(buffers.position_buffer.len(), buffers.normal_buffer.len());
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for position in buffers.position_buffer.chunks(3) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+        // 0.4 radius around the 1.0 high cylinder section
+        assert_eq!(min, [-0.4, -0.9, -0.4]);
+        assert_eq!(max, [0.4, 0.9, 0.4]);
+    }
+}
@@ -1,3 +1,5 @@
+pub(super) mod character;
+pub(super) mod sphere;
 pub(super) mod squid;
 
 use std::{error::Error, rc::Rc};
@@ -8,47 +10,16 @@ use crate::meshes::objmesh::ObjMesh;
 
 use super::{Mesh, shader::Shader};
 
-pub(super) fn projectile_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
-    let shader = Shader::new(
-        gl,
-        "assets/shaders/projectile.vert",
-        "assets/shaders/sphere_rt.frag",
-    )?;
-    // Load vertex data from mesh
-    let mut mesh = ObjMesh::new();
-    mesh.load("assets/cube.obj").expect("Could not load mesh");
-    let vertex_positions = mesh.get_vertex_buffers().position_buffer;
-    let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertex_positions);
-    unsafe {
-        // Setup vertex & index array and buffer
-        let vao = gl.create_vertex_array()?;
-        gl.bind_vertex_array(Some(vao));
-        // Bind vertex data
-        let vbo = gl.create_buffer()?;
-        gl.bind_buffer(gl::ARRAY_BUFFER, Some(vbo));
-        gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, vertex_bytes, gl::STATIC_DRAW);
-        // Setup position attribute
-        gl.vertex_attrib_pointer_f32(
-            0,
-            3,
-            gl::FLOAT,
-            false,
-            3 * std::mem::size_of::<f32>() as i32,
-            0,
-        );
-        gl.enable_vertex_array_attrib(vao, 0);
-        gl.bind_buffer(gl::ARRAY_BUFFER, None);
-        // 3 because vertex pos has 3 coordinates for each vertex
-        Ok(Mesh::new(shader, vao, (vertex_positions.len() / 3) as i32))
-    }
-}
+// OBJ files the meshes are built from. Used to rebuild meshes when the file changes
+pub(super) const CUBE_OBJ: &str = "assets/cube.obj";
+pub(super) const FISH_OBJ: &str = "assets/fish_centered.obj";
 
 pub(super) fn mesh_cube(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
     let shader = Shader::new(gl, "assets/shaders/cube.vert", "assets/shaders/quad.frag")?;
 
     // Load vertex data from mesh
     let mut mesh = ObjMesh::new();
-    mesh.load("assets/cube.obj").expect("Could not load mesh");
+    mesh.load(CUBE_OBJ)?;
     let vertex_buffers = mesh.get_vertex_buffers();
     // NOTE: /3 because we have 3 coordinates per vertex
     let vertex_count = vertex_buffers.position_buffer.len() / 3;
@@ -93,7 +64,7 @@ pub(super) fn projectile2d_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn
     )?;
     // Load vertex data from mesh
     let mut mesh = ObjMesh::new();
-    mesh.load("assets/cube.obj").expect("Could not load mesh");
+    mesh.load(CUBE_OBJ)?;
     let vertex_positions = mesh.get_vertex_buffers().position_buffer;
     let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertex_positions);
     unsafe {
@@ -132,8 +103,7 @@ pub(super) fn player_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>
 
     // Load vertex data from mesh
     let mut mesh = ObjMesh::new().with_blender_axis_fix(true);
-    mesh.load("assets/fish_centered.obj")
-        .expect("Could not load mesh");
+    mesh.load(FISH_OBJ)?;
     let vertex_buffers = mesh.get_vertex_buffers();
     // NOTE: /3 because we have 3 coordinates per vertex
     let vertex_count = vertex_buffers.position_buffer.len() / 3;
@@ -0,0 +1,48 @@
+use std::{error::Error, rc::Rc};
+
+use crate::{
+    meshes::{
+        icosphere::{SPHERE_LOD_SCREEN_SIZES, SphereGeometry},
+        sphere::sphere_vertex_arrays,
+    },
+    renderer::{ecs_renderer::MeshLod, shader::Shader},
+};
+
+use super::Mesh;
+
+// Radius of the sphere meshes. Scale 1 matches the unit cube the meshes replaced
+const RADIUS: f32 = 0.5;
+
+/// Lit sphere with detail levels, red unless the entity has a color
+pub fn projectile_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
+    let shader = Shader::new(
+        gl,
+        "assets/shaders/cube.vert",
+        "assets/shaders/projectile.frag",
+    )?;
+    sphere_mesh(gl, shader, SphereGeometry::Icosphere)
+}
+
+/// Unlit sphere with detail levels, e.g. for gizmos. Flat colored by the color of the entity
+pub fn gizmo_sphere_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
+    let shader = Shader::new(gl, "assets/shaders/cube.vert", "assets/shaders/quad.frag")?;
+    sphere_mesh(gl, shader, SphereGeometry::Uv)
+}
+
+fn sphere_mesh(
+    gl: &Rc<glow::Context>,
+    shader: Shader,
+    geometry: SphereGeometry,
+) -> Result<Mesh, Box<dyn Error>> {
+    let mut levels = sphere_vertex_arrays(gl, geometry, RADIUS)?.into_iter();
+    let (vao, vertex_count) = levels.next().ok_or("Sphere without detail levels")?;
+    let lods = levels
+        .zip(SPHERE_LOD_SCREEN_SIZES)
+        .map(|((vao, vertex_count), max_screen_size)| MeshLod {
+            vao,
+            vertex_count,
+            max_screen_size,
+        })
+        .collect();
+    Ok(Mesh::new(shader, vao, vertex_count).with_lods(RADIUS, lods))
+}
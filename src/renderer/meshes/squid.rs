@@ -6,6 +6,8 @@ use crate::{meshes::objmesh::ObjMesh, renderer::shader::Shader};
 
 use super::Mesh;
 
+pub const SQUID_OBJ: &str = "assets/squid_centered.obj";
+
 pub fn squid_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
     let shader = Shader::new(
         gl,
@@ -15,8 +17,7 @@ pub fn squid_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
 
     // Load vertex data from mesh
     let mut mesh = ObjMesh::new().with_blender_axis_fix(true);
-    mesh.load("assets/squid_centered.obj")
-        .expect("Could not load mesh");
+    mesh.load(SQUID_OBJ)?;
     let vertex_buffers = mesh.get_vertex_buffers();
     // NOTE: /3 because we have 3 coordinates per vertex
     let vertex_count = vertex_buffers.position_buffer.len() / 3;
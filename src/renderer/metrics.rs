@@ -1,4 +1,23 @@
-use crate::util::SimpleMovingAverage;
+use std::{path::Path, time::Duration};
+
+use glam::Vec3;
+
+use crate::util::{RollingHistory, SimpleMovingAverage};
+
+use super::spikes::{DEFAULT_SPIKE_DIR, SpikeCapture};
+
+// Distance of the overlay to the top left corner of the window
+const OVERLAY_OFFSET: [f32; 2] = [8.0, 8.0];
+const OVERLAY_PADDING: f32 = 4.0;
+// Frames shown in the frame time timeline
+const TIMELINE_FRAMES: usize = 300;
+
+/// Scene specific values shown by the stats overlay. Fields left empty are omitted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverlayStats {
+    pub chunks: Option<usize>,
+    pub position: Option<Vec3>,
+}
 
 pub struct RenderMetrics {
     pub sma_dt: SimpleMovingAverage,
@@ -6,6 +25,11 @@ pub struct RenderMetrics {
     pub sma_render_time: SimpleMovingAverage,
     pub sma_swap_time: SimpleMovingAverage,
     pub sma_tick_time: SimpleMovingAverage,
+    // Milliseconds per frame, latest last
+    frame_times: RollingHistory,
+    pub spikes: SpikeCapture,
+    /// Single line overlay with the most important stats. Toggled with F3
+    pub show_overlay: bool,
 }
 
 impl RenderMetrics {
@@ -16,12 +40,55 @@ impl RenderMetrics {
             sma_render_time: SimpleMovingAverage::new(100),
             sma_swap_time: SimpleMovingAverage::new(100),
             sma_tick_time: SimpleMovingAverage::new(100),
+            frame_times: RollingHistory::new(TIMELINE_FRAMES),
+            spikes: SpikeCapture::new(Path::new(DEFAULT_SPIKE_DIR)),
+            show_overlay: true,
+        }
+    }
+
+    /// Adds the frame to the averages & the timeline. Returns true if it is a spike that should
+    /// be captured
+    pub fn record_frame(&mut self, frame_time: Duration) -> bool {
+        self.sma_dt.add(frame_time.as_secs_f32());
+        self.frame_times.push(frame_time.as_secs_f32() * 1e3);
+        self.spikes.record_frame(frame_time)
+    }
+
+    /// Averages of the application loop included in spike dumps
+    pub fn averages(&self) -> Vec<(&'static str, f32)> {
+        vec![
+            ("Frame time ms", self.sma_dt.get() * 1e3),
+            ("Tick micro-s", self.sma_tick_time.get()),
+            ("Render micro-s", self.sma_render_time.get()),
+            ("Swap micro-s", self.sma_swap_time.get()),
+            ("Render loop micro-s", self.sma_render_loop.get()),
+        ]
+    }
+
+    /// Draws the stats line on top of all windows. Uses the foreground draw list, so it does not
+    /// depend on scene specific UI
+    pub fn render_overlay(&self, ui: &imgui::Ui, stats: &OverlayStats) {
+        if !self.show_overlay {
+            return;
         }
+        let line = overlay_line(self.sma_dt.get(), stats);
+        let [x, y] = OVERLAY_OFFSET;
+        let [width, height] = ui.calc_text_size(&line);
+        let draw_list = ui.get_foreground_draw_list();
+        draw_list
+            .add_rect(
+                [x - OVERLAY_PADDING, y - OVERLAY_PADDING],
+                [x + width + OVERLAY_PADDING, y + height + OVERLAY_PADDING],
+                [0.0, 0.0, 0.0, 0.6],
+            )
+            .filled(true)
+            .build();
+        draw_list.add_text(OVERLAY_OFFSET, [1.0, 1.0, 1.0, 1.0], &line);
     }
 
     pub fn render_ui(&mut self, ui: &mut imgui::Ui) {
         ui.window("Metrics")
-            .size([300.0, 150.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 300.0], imgui::Condition::FirstUseEver)
             .position([0.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("Avg FPS: {:.1}", 1.0 / self.sma_dt.get()));
@@ -45,6 +112,91 @@ impl RenderMetrics {
                     "Avg time per render loop: {:.1} micro-s",
                     self.sma_render_loop.get()
                 ));
+                self.render_timeline(ui);
             });
     }
+
+    // Frame times with a red line for every captured spike still in view
+    fn render_timeline(&mut self, ui: &imgui::Ui) {
+        let threshold_ms = self.spikes.threshold.as_secs_f32() * 1e3;
+        let latest = self.frame_times.latest().unwrap_or(0.0);
+        ui.plot_lines("Frame ms", self.frame_times.values())
+            .values_offset(self.frame_times.offset())
+            .overlay_text(format!("{latest:.1}"))
+            .scale_min(0.0)
+            .scale_max(self.frame_times.max().max(threshold_ms))
+            .graph_size([0.0, 50.0])
+            .build();
+        let [min_x, min_y] = ui.item_rect_min();
+        let [max_x, max_y] = ui.item_rect_max();
+        let count = self.frame_times.values().len();
+        let draw_list = ui.get_window_draw_list();
+        for marker in self.spikes.markers() {
+            let age = (self.spikes.frame() - marker.frame) as usize;
+            if age >= count || count < 2 {
+                continue;
+            }
+            let x = min_x + (count - 1 - age) as f32 / (count - 1) as f32 * (max_x - min_x);
+            draw_list
+                .add_line([x, min_y], [x, max_y], [1.0, 0.2, 0.2, 1.0])
+                .build();
+        }
+        ui.checkbox("Capture spikes", &mut self.spikes.enabled);
+        let mut threshold_ms = self.spikes.threshold.as_millis() as i32;
+        if ui.slider("Spike threshold ms", 20, 500, &mut threshold_ms) {
+            self.spikes.threshold = Duration::from_millis(threshold_ms as u64);
+        }
+        ui.text(format!("Captured spikes: {}", self.spikes.markers().len()));
+        if let Some(last) = self.spikes.markers().last() {
+            ui.text(format!(
+                "Last: frame {} ({:.1} ms)",
+                last.frame,
+                last.frame_time.as_secs_f32() * 1e3
+            ));
+            if let Some(path) = &last.path {
+                ui.text_wrapped(path.display().to_string());
+            }
+        }
+    }
+}
+
+// e.g. "144 FPS | 6.94 ms | 812 chunks | 12.0, 40.5, -3.2"
+fn overlay_line(frame_time_secs: f32, stats: &OverlayStats) -> String {
+    let fps = if frame_time_secs > 0.0 {
+        1.0 / frame_time_secs
+    } else {
+        0.0
+    };
+    let mut line = format!("{fps:.0} FPS | {:.2} ms", frame_time_secs * 1000.0);
+    if let Some(chunks) = stats.chunks {
+        line.push_str(&format!(" | {chunks} chunks"));
+    }
+    if let Some(position) = stats.position {
+        line.push_str(&format!(
+            " | {:.1}, {:.1}, {:.1}",
+            position.x, position.y, position.z
+        ));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_line() {
+        assert_eq!(
+            overlay_line(0.004, &OverlayStats::default()),
+            "250 FPS | 4.00 ms"
+        );
+        let stats = OverlayStats {
+            chunks: Some(812),
+            position: Some(Vec3::new(12.0, 40.52, -3.21)),
+        };
+        assert_eq!(
+            overlay_line(0.0, &stats),
+            "0 FPS | 0.00 ms | 812 chunks | 12.0, 40.5, -3.2"
+        );
+    }
 }
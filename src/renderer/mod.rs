@@ -1,9 +1,32 @@
+pub mod bloom;
+pub mod capabilities;
+pub mod color_filter;
+pub mod debug;
+pub mod depth;
 pub mod ecs_renderer;
-mod frame_uniforms;
+pub mod effects_renderer;
+pub mod feature_flags;
+pub mod frame_graph;
+pub mod frame_uniforms;
+pub mod geometry_buffer;
+pub mod impostors;
+pub mod indirect;
+pub mod material;
 mod meshes;
 pub mod metrics;
+pub mod picture_in_picture;
+pub mod render_batches;
+pub mod render_state;
+mod render_target;
+pub mod settings;
 pub mod shader;
+pub mod spikes;
+pub mod ssao;
+pub mod stream_buffer;
 pub mod texture;
+pub mod texture_camera;
+pub mod texture_formats;
+pub mod viewport;
 
 pub use ecs_renderer::ECSRenderer;
 pub use ecs_renderer::MESH_PROJECTILE;
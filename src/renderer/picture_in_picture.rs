@@ -0,0 +1,82 @@
+use std::{error::Error, rc::Rc};
+
+use glow::HasContext;
+
+use super::{render_target::RenderTarget, viewport::Viewport};
+
+/// Offscreen view copied into a corner of the window once all views are composited, e.g. for a
+/// debug camera. Rendered without post-processing, like texture cameras
+pub struct PictureInPicture {
+    gl: Rc<glow::Context>,
+    // Created on first use & recreated whenever the corner changes size
+    target: Option<RenderTarget>,
+}
+
+impl PictureInPicture {
+    pub fn new(gl: &Rc<glow::Context>) -> PictureInPicture {
+        Self {
+            gl: Rc::clone(gl),
+            target: None,
+        }
+    }
+
+    /// Binds & clears the offscreen target for a corner of the given size. Returns the viewport
+    /// to render the view with
+    pub fn begin(&mut self, corner: Viewport) -> Result<Viewport, Box<dyn Error>> {
+        let outdated = self
+            .target
+            .as_ref()
+            .is_none_or(|target| target.width != corner.width || target.height != corner.height);
+        if outdated {
+            if let Some(old) = self.target.take() {
+                old.delete(&self.gl);
+            }
+            self.target = Some(RenderTarget::with_depth(
+                &self.gl,
+                corner.width.max(1),
+                corner.height.max(1),
+            )?);
+        }
+        let target = self.target.as_ref().ok_or("Missing render target")?;
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(target.fbo));
+            self.gl
+                .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT | glow::STENCIL_BUFFER_BIT);
+        }
+        Ok(Viewport::full(target.width, target.height))
+    }
+
+    /// Copies the view into the corner of the window framebuffer & leaves it bound
+    pub fn present(&self, corner: Viewport) {
+        let Some(target) = self.target.as_ref() else {
+            return;
+        };
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(target.fbo));
+            self.gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None);
+            self.gl.blit_framebuffer(
+                0,
+                0,
+                target.width,
+                target.height,
+                corner.x,
+                corner.y,
+                corner.x + corner.width,
+                corner.y + corner.height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+    }
+}
+
+impl Drop for PictureInPicture {
+    fn drop(&mut self) {
+        if let Some(target) = &self.target {
+            target.delete(&self.gl);
+        }
+    }
+}
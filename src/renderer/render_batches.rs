@@ -0,0 +1,166 @@
+use glam::{Mat4, Vec3};
+use hecs::{Entity, World};
+
+use crate::systems::physics::Transform;
+
+use super::{
+    RenderMeshHandle,
+    ecs_renderer::{Hidden, MeshHandle, RenderColor},
+    impostors::{Impostor, ImpostorInstance},
+    material::{Material, ShaderHandle, TextureHandle},
+};
+
+/// Everything needed to draw a single entity, copied out of the world
+pub struct DrawItem {
+    pub entity: Entity,
+    pub mesh: MeshHandle,
+    pub transform: Mat4,
+    pub color: Option<Vec3>,
+    pub material: Option<Material>,
+    pub impostor: Option<Impostor>,
+}
+
+impl DrawItem {
+    /// Material shader, None if the default shader of the mesh is used
+    pub fn shader(&self) -> Option<ShaderHandle> {
+        self.material.as_ref().and_then(|material| material.shader)
+    }
+
+    /// Whether both entities can be drawn by the same instanced draw call, given they use the
+    /// same detail level of the mesh
+    pub fn shares_draw(&self, other: &DrawItem) -> bool {
+        self.mesh == other.mesh && self.color == other.color && self.material == other.material
+    }
+
+    pub fn samples(&self, texture: TextureHandle) -> bool {
+        self.material
+            .as_ref()
+            .is_some_and(|material| material.textures.iter().any(|t| t.texture == texture))
+    }
+
+    /// Billboard replacing the mesh, if the entity has an impostor & is at least min_distance
+    /// away from the camera
+    pub fn impostor(&self, camera: Vec3, min_distance: f32) -> Option<ImpostorInstance> {
+        let impostor = self.impostor?;
+        let center = self.transform.w_axis.truncate();
+        if center.distance_squared(camera) < min_distance * min_distance {
+            return None;
+        }
+        let scale = self
+            .transform
+            .x_axis
+            .length()
+            .max(self.transform.y_axis.length());
+        Some(ImpostorInstance {
+            center,
+            radius: impostor.radius * scale,
+            color: self.color.unwrap_or(impostor.color),
+        })
+    }
+}
+
+/// Visible ecs geometry, gathered once per frame & shared by all passes drawing it. Sorted by
+/// shader & mesh, so consecutive draws can skip program & vertex array switches
+#[derive(Default)]
+pub struct RenderBatches {
+    items: Vec<DrawItem>,
+}
+
+impl RenderBatches {
+    pub fn new() -> RenderBatches {
+        Self::default()
+    }
+
+    /// Replaces the previous frame. Keeps the allocation
+    pub fn gather(&mut self, world: &World) {
+        self.items.clear();
+        self.items.extend(
+            world
+                .query::<(
+                    &Transform,
+                    &RenderMeshHandle,
+                    Option<&RenderColor>,
+                    Option<&Material>,
+                    Option<&Impostor>,
+                )>()
+                .without::<&Hidden>()
+                .iter()
+                .map(
+                    |(entity, (transform, handle, color, material, impostor))| DrawItem {
+                        entity,
+                        mesh: handle.0,
+                        transform: transform.0,
+                        color: color.map(|color| color.0),
+                        material: material.cloned(),
+                        impostor: impostor.copied(),
+                    },
+                ),
+        );
+        self.items
+            .sort_by_key(|item| (item.shader(), item.mesh, item.entity.id()));
+    }
+
+    pub fn items(&self) -> &[DrawItem] {
+        &self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(world: &mut World, mesh: MeshHandle) -> Entity {
+        world.spawn((Transform(Mat4::IDENTITY), RenderMeshHandle(mesh)))
+    }
+
+    #[test]
+    fn test_gather_sorted_by_shader_and_mesh() {
+        let mut world = World::new();
+        let textured = spawn(&mut world, 1);
+        world
+            .insert_one(textured, Material::new().with_shader(0))
+            .unwrap();
+        let second = spawn(&mut world, 2);
+        let first = spawn(&mut world, 1);
+        world.insert_one(first, RenderColor(Vec3::ONE)).unwrap();
+        let hidden = spawn(&mut world, 0);
+        world.insert_one(hidden, Hidden).unwrap();
+
+        let mut batches = RenderBatches::new();
+        batches.gather(&world);
+        let order: Vec<Entity> = batches.items().iter().map(|item| item.entity).collect();
+        // Default mesh shaders first, hidden entities skipped
+        assert_eq!(order, vec![first, second, textured]);
+        assert_eq!(batches.items()[0].color, Some(Vec3::ONE));
+
+        // Impostors only replace distant meshes, colored like the entity
+        world
+            .insert_one(
+                first,
+                Impostor {
+                    radius: 0.5,
+                    color: Vec3::X,
+                },
+            )
+            .unwrap();
+        batches.gather(&world);
+        let item = &batches.items()[0];
+        assert_eq!(item.impostor(Vec3::new(0.0, 0.0, 5.0), 10.0), None);
+        let far = item.impostor(Vec3::new(0.0, 0.0, 50.0), 10.0).unwrap();
+        assert_eq!(far.color, Vec3::ONE);
+        assert_eq!(
+            batches.items()[1].impostor(Vec3::new(0.0, 0.0, 50.0), 10.0),
+            None
+        );
+
+        // Only entities without differing parameters share a draw call
+        let items = batches.items();
+        assert!(!items[0].shares_draw(&items[1]));
+        assert!(items[0].shares_draw(&items[0]));
+
+        // Next frame replaces the items
+        world.despawn(second).unwrap();
+        batches.gather(&world);
+        assert_eq!(batches.items().len(), 2);
+    }
+}
@@ -0,0 +1,141 @@
+use glow::HasContext;
+
+use super::depth;
+
+/// Fixed function GL state a scene renders with. Applied by the application before every scene
+/// render, so scenes do not depend on state left behind by previous scenes or the UI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    /// Reverse-Z depth testing, see [depth::enable_depth_test]
+    pub depth_test: bool,
+    pub depth_write: bool,
+    /// Culls back faces of counter-clockwise triangles
+    pub cull_back_faces: bool,
+    /// Source & destination blend factors. None disables blending
+    pub blend: Option<(u32, u32)>,
+    pub scissor_test: bool,
+    /// x, y, width, height. None keeps the current viewport
+    pub viewport: Option<[i32; 4]>,
+    pub clear_color: [f32; 4],
+}
+
+impl Default for RenderState {
+    /// Opaque 3D rendering into the window
+    fn default() -> Self {
+        Self {
+            depth_test: true,
+            depth_write: true,
+            cull_back_faces: true,
+            blend: None,
+            scissor_test: false,
+            viewport: None,
+            clear_color: [0.05, 0.05, 0.1, 1.0],
+        }
+    }
+}
+
+fn set_enabled(gl: &glow::Context, capability: u32, enabled: bool) {
+    unsafe {
+        match enabled {
+            true => gl.enable(capability),
+            false => gl.disable(capability),
+        }
+    }
+}
+
+impl RenderState {
+    /// Blending with straight alpha, e.g. for UI & transparent effects
+    pub const ALPHA_BLEND: Option<(u32, u32)> = Some((glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA));
+
+    pub fn with_clear_color(mut self, r: f32, g: f32, b: f32) -> RenderState {
+        self.clear_color = [r, g, b, 1.0];
+        self
+    }
+
+    /// Viewport covering the whole surface of the given physical size. Minimized windows have
+    /// no surface, the viewport is kept then
+    pub fn with_surface_size(mut self, width: u32, height: u32) -> RenderState {
+        if width > 0 && height > 0 {
+            self.viewport = Some([0, 0, width as i32, height as i32]);
+        }
+        self
+    }
+
+    pub fn apply(&self, gl: &glow::Context) {
+        if self.depth_test {
+            depth::enable_depth_test(gl);
+        } else {
+            set_enabled(gl, glow::DEPTH_TEST, false);
+        }
+        set_enabled(gl, glow::CULL_FACE, self.cull_back_faces);
+        set_enabled(gl, glow::BLEND, self.blend.is_some());
+        set_enabled(gl, glow::SCISSOR_TEST, self.scissor_test);
+        let [r, g, b, a] = self.clear_color;
+        unsafe {
+            gl.depth_mask(self.depth_write);
+            gl.cull_face(glow::BACK);
+            gl.front_face(glow::CCW);
+            if let Some((source, destination)) = self.blend {
+                gl.blend_func(source, destination);
+            }
+            if let Some([x, y, width, height]) = self.viewport {
+                gl.viewport(x, y, width, height);
+            }
+            gl.clear_color(r, g, b, a);
+        }
+    }
+
+    /// Current GL state, e.g. to restore it after a pass that changes state freely
+    pub fn capture(gl: &glow::Context) -> RenderState {
+        unsafe {
+            let mut viewport = [0; 4];
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+            let mut clear_color = [0.0; 4];
+            gl.get_parameter_f32_slice(glow::COLOR_CLEAR_VALUE, &mut clear_color);
+            let blend = gl.is_enabled(glow::BLEND).then(|| {
+                (
+                    gl.get_parameter_i32(glow::BLEND_SRC_RGB) as u32,
+                    gl.get_parameter_i32(glow::BLEND_DST_RGB) as u32,
+                )
+            });
+            RenderState {
+                depth_test: gl.is_enabled(glow::DEPTH_TEST),
+                depth_write: gl.get_parameter_bool(glow::DEPTH_WRITEMASK),
+                cull_back_faces: gl.is_enabled(glow::CULL_FACE),
+                blend,
+                scissor_test: gl.is_enabled(glow::SCISSOR_TEST),
+                viewport: Some(viewport),
+                clear_color,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_follows_surface_size() {
+        // Scenes do not know the window size, the application sets it
+        let scene_state = RenderState::default().with_clear_color(0.0, 0.5, 1.0);
+        assert_eq!(scene_state.viewport, None);
+        assert_eq!(scene_state.clear_color, [0.0, 0.5, 1.0, 1.0]);
+
+        // E.g. 1920x1080 logical pixels at a scale factor of 1.5
+        let state = scene_state.with_surface_size(2880, 1620);
+        assert_eq!(state.viewport, Some([0, 0, 2880, 1620]));
+        let resized = state.with_surface_size(1280, 720);
+        assert_eq!(resized.viewport, Some([0, 0, 1280, 720]));
+        // Minimized
+        assert_eq!(resized.with_surface_size(0, 0), resized);
+        // Everything but the viewport is left as is
+        assert_eq!(
+            RenderState {
+                viewport: None,
+                ..resized
+            },
+            scene_state
+        );
+    }
+}
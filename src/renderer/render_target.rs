@@ -0,0 +1,129 @@
+use std::error::Error;
+
+use glow::{HasContext, NativeFramebuffer, NativeRenderbuffer, NativeTexture};
+
+use super::Mesh;
+
+/// Offscreen framebuffer with a single color texture attachment.
+/// Used for intermediate post-processing passes & scene views rendered into textures
+pub(crate) struct RenderTarget {
+    pub fbo: NativeFramebuffer,
+    pub texture: NativeTexture,
+    pub width: i32,
+    pub height: i32,
+    // Only needed when rendering geometry into the target
+    depth: Option<NativeRenderbuffer>,
+}
+
+impl RenderTarget {
+    /// Creates a linear filtered, edge clamped color target
+    pub fn new(
+        gl: &glow::Context,
+        width: i32,
+        height: i32,
+        internal_format: u32,
+        format: u32,
+        ty: u32,
+    ) -> Result<RenderTarget, Box<dyn Error>> {
+        unsafe {
+            let fbo = gl.create_framebuffer()?;
+            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(fbo));
+            let texture = gl.create_texture()?;
+            gl.bind_texture(gl::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as i32,
+                width,
+                height,
+                0,
+                format,
+                ty,
+                None,
+            );
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            // Clamp to avoid bleeding of pixels across screen borders
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl.framebuffer_texture_2d(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+            Ok(Self {
+                fbo,
+                texture,
+                width,
+                height,
+                depth: None,
+            })
+        }
+    }
+
+    /// Color target with depth & stencil renderbuffer, so whole scenes can be rendered into it
+    pub fn with_depth(
+        gl: &glow::Context,
+        width: i32,
+        height: i32,
+    ) -> Result<RenderTarget, Box<dyn Error>> {
+        let mut target = Self::new(gl, width, height, gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE)?;
+        unsafe {
+            let depth = gl.create_renderbuffer()?;
+            gl.bind_renderbuffer(gl::RENDERBUFFER, Some(depth));
+            gl.renderbuffer_storage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(target.fbo));
+            gl.framebuffer_renderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::RENDERBUFFER,
+                Some(depth),
+            );
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+            gl.bind_renderbuffer(gl::RENDERBUFFER, None);
+            target.depth = Some(depth);
+        }
+        Ok(target)
+    }
+
+    /// Fills the whole target with a single color
+    pub fn clear(&self, gl: &glow::Context, r: f32, g: f32, b: f32) {
+        unsafe {
+            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(self.fbo));
+            gl.clear_color(r, g, b, 1.0);
+            gl.clear(gl::COLOR_BUFFER_BIT);
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        }
+    }
+
+    pub fn delete(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.fbo);
+            gl.delete_texture(self.texture);
+            if let Some(depth) = self.depth {
+                gl.delete_renderbuffer(depth);
+            }
+        }
+    }
+}
+
+/// Draws a fullscreen quad into the target framebuffer with input bound to texture unit 0
+/// Requires the quad shader to be in use already
+pub(crate) fn draw_fullscreen(
+    gl: &glow::Context,
+    quad: &Mesh,
+    target: NativeFramebuffer,
+    input: NativeTexture,
+) {
+    unsafe {
+        gl.bind_framebuffer(gl::FRAMEBUFFER, Some(target));
+        gl.bind_vertex_array(Some(quad.vao));
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_texture(gl::TEXTURE_2D, Some(input));
+        gl.draw_elements(glow::TRIANGLES, quad.vertex_count, gl::UNSIGNED_INT, 0);
+        gl.bind_vertex_array(None);
+    }
+}
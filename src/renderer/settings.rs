@@ -0,0 +1,144 @@
+use imgui::Ui;
+
+use crate::{
+    cameras::camera::DEFAULT_FOV_Y_DEGREES,
+    config::{MAX_FPS_WITHOUT_VSYNC, RESOLUTION_HEIGHT, RESOLUTION_WIDTH, USE_VSYNC},
+    voxels::DEFAULT_RENDER_DISTANCE,
+};
+
+use super::{color_filter::ColorFilter, feature_flags::FeatureFlags};
+
+const MSAA_OPTIONS: [i32; 4] = [1, 2, 4, 8];
+const MSAA_LABELS: [&str; 4] = ["Off", "2x", "4x", "8x"];
+const MAX_FPS_OPTIONS: [u32; 6] = [0, 30, 60, 120, 144, 240];
+const MAX_FPS_LABELS: [&str; 6] = ["Unlimited", "30", "60", "120", "144", "240"];
+const FOV_RANGE_DEGREES: (f32, f32) = (50.0, 110.0);
+const RENDER_DISTANCE_RANGE: (i32, i32) = (2, 16);
+
+/// Options for players sensitive to motion or with color vision deficiencies
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilitySettings {
+    /// Vertical field of view of perspective cameras
+    pub fov_degrees: f32,
+    /// Scales camera motion on impacts, e.g. the dip on landing. 0 disables it
+    pub camera_shake: f32,
+    /// Scales the walking bob of the player model. 0 disables it
+    pub view_bob: f32,
+    pub color_filter: ColorFilter,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: DEFAULT_FOV_Y_DEGREES,
+            camera_shake: 1.0,
+            view_bob: 1.0,
+            color_filter: ColorFilter::None,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    fn render_ui(&mut self, ui: &Ui) {
+        let (min_fov, max_fov) = FOV_RANGE_DEGREES;
+        ui.slider("Field of view", min_fov, max_fov, &mut self.fov_degrees);
+        ui.slider("Camera shake", 0.0, 1.0, &mut self.camera_shake);
+        ui.slider("View bob", 0.0, 1.0, &mut self.view_bob);
+        let mut filter_idx = ColorFilter::ALL
+            .iter()
+            .position(|filter| *filter == self.color_filter)
+            .unwrap_or(0);
+        let labels = ColorFilter::ALL.map(ColorFilter::label);
+        if ui.combo_simple_string("Color filter", &mut filter_idx, &labels) {
+            self.color_filter = ColorFilter::ALL[filter_idx];
+        }
+    }
+}
+
+/// User facing render options. Owned by the application & propagated to the active scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    /// Sample count of the offscreen geometry buffer. 1 disables MSAA
+    pub msaa_samples: i32,
+    /// Render resolution relative to window resolution. Result is upscaled / downscaled
+    pub resolution_scale: f32,
+    /// Frame rate cap applied by the application loop. None renders as fast as possible
+    pub max_fps: Option<u32>,
+    /// Chunks around the camera that are generated & drawn along each axis
+    pub render_distance: i32,
+    /// Size of debug windows & HUD relative to the monitor's scale factor
+    pub ui_scale: f32,
+    /// Stops ticking local scenes while the window is unfocused or minimized
+    pub pause_in_background: bool,
+    pub accessibility: AccessibilitySettings,
+    pub features: FeatureFlags,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            msaa_samples: 1,
+            resolution_scale: 1.0,
+            max_fps: (!USE_VSYNC).then_some(MAX_FPS_WITHOUT_VSYNC),
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            ui_scale: 1.0,
+            pause_in_background: false,
+            accessibility: AccessibilitySettings::default(),
+            features: FeatureFlags::default(),
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Size of offscreen render targets after applying resolution scale
+    pub fn render_size(&self) -> (i32, i32) {
+        let width = (RESOLUTION_WIDTH as f32 * self.resolution_scale).round() as i32;
+        let height = (RESOLUTION_HEIGHT as f32 * self.resolution_scale).round() as i32;
+        (width.max(1), height.max(1))
+    }
+
+    /// Returns true if any setting changed
+    pub fn render_ui(&mut self, ui: &Ui) -> bool {
+        let previous = self.clone();
+        ui.window("Settings")
+            .size([300.0, 100.0], imgui::Condition::FirstUseEver)
+            .position([300.0, 0.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let mut msaa_idx = MSAA_OPTIONS
+                    .iter()
+                    .position(|s| *s == self.msaa_samples)
+                    .unwrap_or(0);
+                if ui.combo_simple_string("Anti-aliasing", &mut msaa_idx, &MSAA_LABELS) {
+                    self.msaa_samples = MSAA_OPTIONS[msaa_idx];
+                }
+                let mut scale_percent = (self.resolution_scale * 100.0).round() as i32;
+                if ui.slider("Resolution %", 50, 200, &mut scale_percent) {
+                    self.resolution_scale = scale_percent as f32 / 100.0;
+                }
+                let mut fps_idx = MAX_FPS_OPTIONS
+                    .iter()
+                    .position(|fps| *fps == self.max_fps.unwrap_or(0))
+                    .unwrap_or(0);
+                if ui.combo_simple_string("Max FPS", &mut fps_idx, &MAX_FPS_LABELS) {
+                    self.max_fps = Some(MAX_FPS_OPTIONS[fps_idx]).filter(|fps| *fps > 0);
+                }
+                let (min_distance, max_distance) = RENDER_DISTANCE_RANGE;
+                ui.slider(
+                    "Render distance",
+                    min_distance,
+                    max_distance,
+                    &mut self.render_distance,
+                );
+                let mut ui_scale_percent = (self.ui_scale * 100.0).round() as i32;
+                if ui.slider("UI scale %", 50, 300, &mut ui_scale_percent) {
+                    self.ui_scale = ui_scale_percent as f32 / 100.0;
+                }
+                ui.checkbox("Pause in background", &mut self.pause_in_background);
+                if ui.collapsing_header("Accessibility", imgui::TreeNodeFlags::empty()) {
+                    self.accessibility.render_ui(ui);
+                }
+            });
+        self.features.render_ui(ui);
+        *self != previous
+    }
+}
@@ -1,13 +1,36 @@
-use log::error;
-use std::{collections::HashMap, error::Error, fs, rc::Rc};
+use log::{error, warn};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::Path,
+    rc::Rc,
+};
 
-use glam::{Mat3, Mat4, Vec3};
+use glam::{Mat3, Mat4, Vec2, Vec3};
 use glow::{HasContext, NativeUniformLocation};
 
+use super::{capabilities, debug, material::UniformValue};
+
+// Active uniform of the linked program
+struct UniformInfo {
+    location: NativeUniformLocation,
+    gl_type: u32,
+    // Value right after linking, i.e. the initializer in the source. None for arrays & vec4s
+    default: Option<UniformValue>,
+}
+
 pub struct Shader {
     gl: Rc<glow::Context>,
     program: <glow::Context as HasContext>::Program,
-    uniforms: HashMap<String, Option<NativeUniformLocation>>,
+    // Active uniforms by name, queried once after linking
+    uniforms: HashMap<String, UniformInfo>,
+    // Uniforms declared in the sources. Unused ones are optimized out & not active
+    declared_uniforms: HashSet<String>,
+    // Misused uniform names, so every issue is only logged once
+    reported_uniforms: HashSet<String>,
+    // Uniforms ever set per draw. Restored to their default before each draw
+    draw_uniforms: HashSet<String>,
 }
 
 impl Shader {
@@ -16,8 +39,24 @@ impl Shader {
         vert_path: &str,
         frag_path: &str,
     ) -> Result<Shader, Box<dyn Error>> {
-        let vert_src = fs::read_to_string(vert_path)?;
-        let frag_src = fs::read_to_string(frag_path)?;
+        let vert_src = load_shader_source(Path::new(vert_path))?;
+        let frag_src = load_shader_source(Path::new(frag_path))?;
+        // Fail gracefully instead of on compilation, so callers can fall back
+        let max_version = capabilities::max_glsl_version(gl);
+        for (path, source) in [(vert_path, &vert_src), (frag_path, &frag_src)] {
+            if let Some(version) = glsl_version(source)
+                && version > max_version
+            {
+                return Err(format!(
+                    "Shader {path} requires GLSL {version}, context supports up to {max_version}"
+                )
+                .into());
+            }
+        }
+        let declared_uniforms = declared_uniforms(&vert_src)
+            .chain(declared_uniforms(&frag_src))
+            .map(str::to_string)
+            .collect();
         let mut shaders = [
             (glow::VERTEX_SHADER, vert_src, None),
             (glow::FRAGMENT_SHADER, frag_src, None),
@@ -52,7 +91,10 @@ impl Shader {
             let instance = Self {
                 gl: Rc::clone(gl),
                 program,
-                uniforms: HashMap::new(),
+                uniforms: active_uniforms(gl, program),
+                declared_uniforms,
+                reported_uniforms: HashSet::new(),
+                draw_uniforms: HashSet::new(),
             };
             instance.check_gl_errors();
             Ok(instance)
@@ -66,38 +108,71 @@ impl Shader {
     }
 
     pub fn check_gl_errors(&self) {
-        unsafe {
-            let mut error = self.gl.get_error();
-            while error != glow::NO_ERROR {
-                error!("OpenGL Error: 0x{error:X}");
-                error = self.gl.get_error();
+        debug::check_gl_errors(&self.gl, "shader setup");
+    }
+
+    // Cached location of the uniform. Setting undeclared uniforms & mismatching types in debug
+    // builds are reported once
+    fn uniform_location(&mut self, name: &str, value_type: u32) -> Option<NativeUniformLocation> {
+        let Some(info) = self.uniforms.get(name) else {
+            if !self.declared_uniforms.contains(name) && self.reported_uniforms.insert(name.into())
+            {
+                warn!("Setting uniform '{name}' that is not declared by the shader");
+            }
+            return None;
+        };
+        if cfg!(debug_assertions) && !accepts_value(info.gl_type, value_type) {
+            if self.reported_uniforms.insert(name.into()) {
+                error!(
+                    "Uniform '{name}' of type 0x{:X} set with value of type 0x{value_type:X}",
+                    info.gl_type
+                );
             }
+            return None;
         }
+        Some(info.location)
     }
 
-    pub fn get_uniform_location(&mut self, name: &str) -> Option<NativeUniformLocation> {
-        if let Some(loc) = self.uniforms.get(name) {
-            return *loc;
+    pub fn set_uniform(&mut self, name: &str, value: &UniformValue) {
+        match value {
+            UniformValue::I32(value) => self.set_uniform_i32(name, *value),
+            UniformValue::F32(value) => self.set_uniform_f32(name, *value),
+            UniformValue::Vec2(value) => self.set_uniform_vec2(name, value),
+            UniformValue::Vec3(value) => self.set_uniform_vec3(name, value),
+            UniformValue::Mat3(value) => self.set_uniform_mat3(name, value),
+            UniformValue::Mat4(value) => self.set_uniform_mat4(name, value),
         }
-        unsafe {
-            let loc = self.gl.get_uniform_location(self.program, name);
-            if loc.is_none() {
-                return None;
+    }
+
+    /// Sets a uniform of a single entity. Restored by [Shader::reset_draw_uniforms], so it does
+    /// not leak into the next entity drawn with this shader
+    pub fn set_draw_uniform(&mut self, name: &str, value: &UniformValue) {
+        self.set_uniform(name, value);
+        if !self.draw_uniforms.contains(name) {
+            self.draw_uniforms.insert(name.to_string());
+        }
+    }
+
+    /// Restores all uniforms set by [Shader::set_draw_uniform] to the defaults of the source
+    pub fn reset_draw_uniforms(&mut self) {
+        let names = std::mem::take(&mut self.draw_uniforms);
+        for name in &names {
+            if let Some(default) = self.uniforms.get(name).and_then(|info| info.default) {
+                self.set_uniform(name, &default);
             }
-            self.uniforms.insert(name.to_string(), loc);
-            loc
         }
+        self.draw_uniforms = names;
     }
 
     pub fn set_uniform_i32(&mut self, name: &str, value: i32) {
-        let loc = self.get_uniform_location(name);
+        let loc = self.uniform_location(name, glow::INT);
         unsafe {
             self.gl.uniform_1_i32(loc.as_ref(), value);
         }
     }
 
     pub fn set_uniform_mat3(&mut self, name: &str, value: &Mat3) {
-        let loc = self.get_uniform_location(name);
+        let loc = self.uniform_location(name, glow::FLOAT_MAT3);
         unsafe {
             self.gl
                 .uniform_matrix_3_f32_slice(loc.as_ref(), false, value.to_cols_array().as_ref());
@@ -105,7 +180,7 @@ impl Shader {
     }
 
     pub fn set_uniform_mat4(&mut self, name: &str, value: &Mat4) {
-        let loc = self.get_uniform_location(name);
+        let loc = self.uniform_location(name, glow::FLOAT_MAT4);
         unsafe {
             self.gl
                 .uniform_matrix_4_f32_slice(loc.as_ref(), false, value.to_cols_array().as_ref());
@@ -113,14 +188,22 @@ impl Shader {
     }
 
     pub fn set_uniform_f32(&mut self, name: &str, value: f32) {
-        let loc = self.get_uniform_location(name);
+        let loc = self.uniform_location(name, glow::FLOAT);
         unsafe {
             self.gl.uniform_1_f32(loc.as_ref(), value);
         }
     }
 
+    pub fn set_uniform_vec2(&mut self, name: &str, value: &Vec2) {
+        let loc = self.uniform_location(name, glow::FLOAT_VEC2);
+        unsafe {
+            self.gl
+                .uniform_2_f32_slice(loc.as_ref(), value.to_array().as_ref());
+        }
+    }
+
     pub fn set_uniform_vec3(&mut self, name: &str, value: &Vec3) {
-        let loc = self.get_uniform_location(name);
+        let loc = self.uniform_location(name, glow::FLOAT_VEC3);
         unsafe {
             self.gl
                 .uniform_3_f32_slice(loc.as_ref(), value.to_array().as_ref());
@@ -128,6 +211,127 @@ impl Shader {
     }
 }
 
+// Active uniforms outside of uniform blocks. Arrays are keyed by their name without index
+unsafe fn active_uniforms(
+    gl: &glow::Context,
+    program: <glow::Context as HasContext>::Program,
+) -> HashMap<String, UniformInfo> {
+    unsafe {
+        (0..gl.get_active_uniforms(program))
+            .filter_map(|index| gl.get_active_uniform(program, index))
+            .filter_map(|uniform| {
+                let location = gl.get_uniform_location(program, &uniform.name)?;
+                let name = uniform.name.trim_end_matches("[0]").to_string();
+                let default = (uniform.size == 1)
+                    .then(|| uniform_default(gl, program, &location, uniform.utype))
+                    .flatten();
+                let info = UniformInfo {
+                    location,
+                    gl_type: uniform.utype,
+                    default,
+                };
+                Some((name, info))
+            })
+            .collect()
+    }
+}
+
+// Current value of a non-array uniform
+unsafe fn uniform_default(
+    gl: &glow::Context,
+    program: <glow::Context as HasContext>::Program,
+    location: &NativeUniformLocation,
+    gl_type: u32,
+) -> Option<UniformValue> {
+    // Large enough for a mat4
+    let mut ints = [0; 16];
+    let mut floats = [0.0; 16];
+    unsafe {
+        if accepts_value(gl_type, glow::INT) {
+            gl.get_uniform_i32(program, location, &mut ints);
+        } else {
+            gl.get_uniform_f32(program, location, &mut floats);
+        }
+    }
+    uniform_value(gl_type, &ints, &floats)
+}
+
+/// Typed value of a uniform from the components read back from GL
+fn uniform_value(gl_type: u32, ints: &[i32; 16], floats: &[f32; 16]) -> Option<UniformValue> {
+    if accepts_value(gl_type, glow::INT) {
+        return Some(UniformValue::I32(ints[0]));
+    }
+    match gl_type {
+        glow::FLOAT => Some(UniformValue::F32(floats[0])),
+        glow::FLOAT_VEC2 => Some(UniformValue::Vec2(Vec2::from_slice(floats))),
+        glow::FLOAT_VEC3 => Some(UniformValue::Vec3(Vec3::from_slice(floats))),
+        glow::FLOAT_MAT3 => Some(UniformValue::Mat3(Mat3::from_cols_slice(floats))),
+        glow::FLOAT_MAT4 => Some(UniformValue::Mat4(Mat4::from_cols_slice(floats))),
+        _ => None,
+    }
+}
+
+/// Whether a value of the given GL type can be assigned to a uniform of the given type.
+/// Samplers are set with ints, bools with ints or floats
+fn accepts_value(uniform_type: u32, value_type: u32) -> bool {
+    match uniform_type {
+        glow::BOOL => matches!(value_type, glow::INT | glow::FLOAT),
+        glow::SAMPLER_1D
+        | glow::SAMPLER_2D
+        | glow::SAMPLER_3D
+        | glow::SAMPLER_CUBE
+        | glow::SAMPLER_2D_SHADOW
+        | glow::SAMPLER_2D_ARRAY
+        | glow::SAMPLER_2D_MULTISAMPLE
+        | glow::INT_SAMPLER_2D
+        | glow::UNSIGNED_INT_SAMPLER_2D => value_type == glow::INT,
+        _ => uniform_type == value_type,
+    }
+}
+
+/// Names of the uniforms declared in the source. Uniform blocks are skipped
+fn declared_uniforms(source: &str) -> impl Iterator<Item = &str> {
+    source.lines().filter_map(|line| {
+        let declaration = line.trim().strip_prefix("uniform ")?;
+        // Strip default value
+        let declaration = declaration.split(['=', ';']).next()?;
+        let name = declaration.split_whitespace().last()?;
+        let name = name.split('[').next()?;
+        let is_identifier = name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        (!name.is_empty() && is_identifier).then_some(name)
+    })
+}
+
+// Version of the `#version` directive, e.g. 330
+fn glsl_version(source: &str) -> Option<u32> {
+    let line = source.lines().find(|line| !line.trim().is_empty())?;
+    line.trim()
+        .strip_prefix("#version")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Reads shader source & resolves `#include "file"` directives relative to the shader directory
+fn load_shader_source(path: &Path) -> Result<String, Box<dyn Error>> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read shader {}: {err}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut resolved = String::with_capacity(source.len());
+    for line in source.lines() {
+        match line.trim().strip_prefix("#include") {
+            Some(include) => {
+                let include_path = dir.join(include.trim().trim_matches('"'));
+                resolved.push_str(&load_shader_source(&include_path)?);
+            }
+            None => resolved.push_str(line),
+        }
+        resolved.push('\n');
+    }
+    Ok(resolved)
+}
+
 impl Drop for Shader {
     fn drop(&mut self) {
         unsafe {
@@ -135,3 +339,69 @@ impl Drop for Shader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_shader_source_resolves_includes() {
+        let source = load_shader_source(Path::new("assets/shaders/voxel.vert")).unwrap();
+        assert!(source.starts_with("#version"));
+        assert!(source.contains("uniform FrameUniforms"));
+        assert!(!source.contains("#include"));
+    }
+
+    #[test]
+    fn test_uniform_defaults() {
+        let mut floats = [0.0; 16];
+        floats[..3].copy_from_slice(&[1.0, 0.5, 0.25]);
+        let ints = [3; 16];
+        assert_eq!(
+            uniform_value(glow::FLOAT_VEC3, &ints, &floats),
+            Some(UniformValue::Vec3(Vec3::new(1.0, 0.5, 0.25)))
+        );
+        assert_eq!(
+            uniform_value(glow::FLOAT, &ints, &floats),
+            Some(UniformValue::F32(1.0))
+        );
+        // Samplers restore their texture unit
+        assert_eq!(
+            uniform_value(glow::SAMPLER_2D, &ints, &floats),
+            Some(UniformValue::I32(3))
+        );
+        let identity = Mat4::IDENTITY.to_cols_array();
+        assert_eq!(
+            uniform_value(glow::FLOAT_MAT4, &ints, &identity),
+            Some(UniformValue::Mat4(Mat4::IDENTITY))
+        );
+        assert_eq!(uniform_value(glow::FLOAT_VEC4, &ints, &floats), None);
+    }
+
+    #[test]
+    fn test_declared_uniforms() {
+        let source = "#version 330 core
+uniform mat4 uModel;
+  uniform vec3 uColor = vec3(1.0, 0.8, 0.6); // Base color
+uniform sampler2D shadowMaps[4];
+uniform FrameUniforms {
+    mat4 uView;
+};
+in vec3 aNormal;
+";
+        let declared: Vec<&str> = declared_uniforms(source).collect();
+        assert_eq!(declared, ["uModel", "uColor", "shadowMaps"]);
+        assert_eq!(glsl_version(source), Some(330));
+        assert_eq!(glsl_version("\n#version 430 core\n"), Some(430));
+        assert_eq!(glsl_version("void main() {}"), None);
+    }
+
+    #[test]
+    fn test_uniform_type_checks() {
+        assert!(accepts_value(glow::FLOAT_MAT3, glow::FLOAT_MAT3));
+        assert!(!accepts_value(glow::FLOAT_MAT4, glow::FLOAT_MAT3));
+        assert!(accepts_value(glow::SAMPLER_2D, glow::INT));
+        assert!(!accepts_value(glow::SAMPLER_2D, glow::FLOAT));
+        assert!(accepts_value(glow::BOOL, glow::INT));
+    }
+}
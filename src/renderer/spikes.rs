@@ -0,0 +1,229 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, create_dir_all},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::util::recent_log_lines;
+
+/// Frames taking longer than this are captured by default
+pub const DEFAULT_SPIKE_THRESHOLD: Duration = Duration::from_millis(100);
+/// Spike dumps are written into this directory
+pub const DEFAULT_SPIKE_DIR: &str = "output/spikes";
+// Log lines included in a dump
+const DUMP_LOG_LINES: usize = 20;
+// Frames after a capture that are not captured again, so a stall of several frames is dumped once
+const CAPTURE_COOLDOWN_FRAMES: u64 = 60;
+// Markers kept for the metrics timeline
+const MAX_MARKERS: usize = 32;
+
+/// Scene state included in spike dumps. Scenes without diagnostics leave it empty
+#[derive(Debug, Clone, Default)]
+pub struct SceneDiagnostics {
+    /// Durations of the systems of the last tick, in the order they ran
+    pub system_timings: Vec<(&'static str, Duration)>,
+    /// Pending work, e.g. chunks waiting to be generated or remeshed
+    pub queues: Vec<(&'static str, usize)>,
+    pub entities: Option<usize>,
+}
+
+/// Measures consecutive sections of a tick, e.g. groups of systems
+pub struct SystemTimer {
+    last: Instant,
+    laps: Vec<(&'static str, Duration)>,
+}
+
+impl SystemTimer {
+    pub fn start() -> SystemTimer {
+        Self {
+            last: Instant::now(),
+            laps: Vec::new(),
+        }
+    }
+
+    /// Ends the section named label & starts the next one
+    pub fn lap(&mut self, label: &'static str) {
+        let now = Instant::now();
+        self.laps.push((label, now - self.last));
+        self.last = now;
+    }
+
+    pub fn finish(self) -> Vec<(&'static str, Duration)> {
+        self.laps
+    }
+}
+
+/// Captured long frame, shown in the metrics timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpikeMarker {
+    pub frame: u64,
+    pub frame_time: Duration,
+    /// Dump written for the spike. None if writing failed
+    pub path: Option<PathBuf>,
+}
+
+/// Detects frames exceeding the threshold & dumps a diagnostic snapshot for each of them
+pub struct SpikeCapture {
+    pub enabled: bool,
+    pub threshold: Duration,
+    dir: PathBuf,
+    frame: u64,
+    last_capture: Option<u64>,
+    // Oldest first
+    markers: Vec<SpikeMarker>,
+}
+
+impl SpikeCapture {
+    pub fn new(dir: &Path) -> SpikeCapture {
+        Self {
+            enabled: true,
+            threshold: DEFAULT_SPIKE_THRESHOLD,
+            dir: dir.to_path_buf(),
+            frame: 0,
+            last_capture: None,
+            markers: Vec::new(),
+        }
+    }
+
+    /// Index of the last recorded frame
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn markers(&self) -> &[SpikeMarker] {
+        &self.markers
+    }
+
+    /// Counts the frame. Returns true if it should be captured
+    pub fn record_frame(&mut self, frame_time: Duration) -> bool {
+        self.frame += 1;
+        let cooling_down = self
+            .last_capture
+            .is_some_and(|last| self.frame - last < CAPTURE_COOLDOWN_FRAMES);
+        self.enabled && frame_time > self.threshold && !cooling_down
+    }
+
+    /// Writes the snapshot of the current frame & marks it in the timeline. The marker is kept
+    /// even if writing fails
+    pub fn capture(&mut self, snapshot: &SpikeSnapshot) -> Result<PathBuf, std::io::Error> {
+        self.last_capture = Some(self.frame);
+        let result = self.write(snapshot);
+        if self.markers.len() == MAX_MARKERS {
+            self.markers.remove(0);
+        }
+        self.markers.push(SpikeMarker {
+            frame: self.frame,
+            frame_time: snapshot.frame_time,
+            path: result.as_ref().ok().cloned(),
+        });
+        result
+    }
+
+    fn write(&self, snapshot: &SpikeSnapshot) -> Result<PathBuf, std::io::Error> {
+        create_dir_all(&self.dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis());
+        let path = self
+            .dir
+            .join(format!("spike_{timestamp}_frame{}.txt", self.frame));
+        fs::write(&path, snapshot.to_text())?;
+        Ok(path)
+    }
+}
+
+/// State of the application around a long frame
+#[derive(Debug, Clone)]
+pub struct SpikeSnapshot {
+    pub frame: u64,
+    pub frame_time: Duration,
+    /// Averages of the application loop, e.g. tick & render time
+    pub averages: Vec<(&'static str, f32)>,
+    pub scene: SceneDiagnostics,
+    pub log_lines: Vec<String>,
+}
+
+impl SpikeSnapshot {
+    pub fn new(
+        frame: u64,
+        frame_time: Duration,
+        averages: Vec<(&'static str, f32)>,
+        scene: SceneDiagnostics,
+    ) -> SpikeSnapshot {
+        Self {
+            frame,
+            frame_time,
+            averages,
+            scene,
+            log_lines: recent_log_lines(DUMP_LOG_LINES),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Frame {} took {:.1} ms\n",
+            self.frame,
+            self.frame_time.as_secs_f32() * 1e3
+        );
+        text.push_str("\n[Averages]\n");
+        for (label, value) in &self.averages {
+            let _ = writeln!(text, "{label}: {value:.1}");
+        }
+        text.push_str("\n[Systems of the last tick]\n");
+        for (label, duration) in &self.scene.system_timings {
+            let _ = writeln!(text, "{label}: {:.3} ms", duration.as_secs_f32() * 1e3);
+        }
+        text.push_str("\n[Queues]\n");
+        for (label, length) in &self.scene.queues {
+            let _ = writeln!(text, "{label}: {length}");
+        }
+        if let Some(entities) = self.scene.entities {
+            let _ = writeln!(text, "\nEntities: {entities}");
+        }
+        text.push_str("\n[Log]\n");
+        for line in &self.log_lines {
+            let _ = writeln!(text, "{line}");
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captures_spikes_once_per_stall() {
+        let dir = std::env::temp_dir().join("voxie_test_spikes");
+        let _ = fs::remove_dir_all(&dir);
+        let mut capture = SpikeCapture::new(&dir);
+        let fast = Duration::from_millis(16);
+        let slow = Duration::from_millis(150);
+        assert!(!capture.record_frame(fast));
+        assert!(capture.record_frame(slow));
+
+        let scene = SceneDiagnostics {
+            system_timings: vec![("movement", Duration::from_millis(120))],
+            queues: vec![("remesh", 12)],
+            entities: Some(42),
+        };
+        let snapshot = SpikeSnapshot::new(capture.frame(), slow, vec![("FPS", 60.0)], scene);
+        let path = capture.capture(&snapshot).unwrap();
+        let dump = fs::read_to_string(&path).unwrap();
+        assert!(dump.starts_with("Frame 2 took 150.0 ms"));
+        assert!(dump.contains("movement: 120.000 ms"));
+        assert!(dump.contains("remesh: 12"));
+        assert!(dump.contains("Entities: 42"));
+
+        // Frames right after the spike belong to the same stall
+        assert!(!capture.record_frame(slow));
+        for _ in 0..CAPTURE_COOLDOWN_FRAMES {
+            capture.record_frame(fast);
+        }
+        assert!(capture.record_frame(slow));
+        assert_eq!(capture.markers().len(), 1);
+        assert_eq!(capture.markers()[0].frame, 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use std::{error::Error, rc::Rc};
+
+use glow::{HasContext, NativeTexture};
+use imgui::Ui;
+
+use crate::{cameras::camera::Camera, systems::skybox::quad_vertex_mesh};
+
+use super::{
+    Mesh, depth,
+    render_target::{RenderTarget, draw_fullscreen},
+    shader::Shader,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsaoQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl SsaoQuality {
+    const ALL: [SsaoQuality; 3] = [SsaoQuality::Low, SsaoQuality::Medium, SsaoQuality::High];
+
+    /// Number of hemisphere samples per fragment
+    pub fn sample_count(&self) -> i32 {
+        match self {
+            SsaoQuality::Low => 8,
+            SsaoQuality::Medium => 16,
+            SsaoQuality::High => 32,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            SsaoQuality::Low => "Low",
+            SsaoQuality::Medium => "Medium",
+            SsaoQuality::High => "High",
+        }
+    }
+}
+
+/// Screen-space ambient occlusion pass
+/// 1. Occlusion estimation from the first pass depth buffer. Normals are reconstructed from depth
+/// 2. Box blur to remove kernel rotation noise
+///
+/// The resulting single channel texture has to be multiplied onto the frame color by the caller
+pub struct SsaoPass {
+    gl: Rc<glow::Context>,
+    occlusion_quad: Mesh,
+    blur_quad: Mesh,
+    // Raw occlusion is written into the first target, blurred result into the second one
+    targets: [RenderTarget; 2],
+    width: i32,
+    height: i32,
+
+    pub enabled: bool,
+    pub quality: SsaoQuality,
+    pub radius: f32,
+    pub bias: f32,
+    pub strength: f32,
+}
+
+impl SsaoPass {
+    /// Occlusion is computed at half the given resolution
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        width: i32,
+        height: i32,
+    ) -> Result<SsaoPass, Box<dyn Error>> {
+        let mut occlusion_shader =
+            Shader::new(gl, "assets/shaders/fog.vert", "assets/shaders/ssao.frag")?;
+        occlusion_shader.use_program();
+        occlusion_shader.set_uniform_i32("depthTexture", 0);
+        let mut blur_shader = Shader::new(
+            gl,
+            "assets/shaders/fog.vert",
+            "assets/shaders/ssao_blur.frag",
+        )?;
+        blur_shader.use_program();
+        blur_shader.set_uniform_i32("image", 0);
+
+        let width = (width / 2).max(1);
+        let height = (height / 2).max(1);
+        Ok(Self {
+            gl: Rc::clone(gl),
+            occlusion_quad: quad_vertex_mesh(gl, occlusion_shader)?,
+            blur_quad: quad_vertex_mesh(gl, blur_shader)?,
+            targets: [
+                ssao_target(gl, width, height)?,
+                ssao_target(gl, width, height)?,
+            ],
+            width,
+            height,
+            enabled: false,
+            quality: SsaoQuality::Medium,
+            radius: 1.0,
+            bias: 0.025,
+            strength: 1.0,
+        })
+    }
+
+    /// Strength to be used for compositing. Zero if SSAO is disabled
+    pub fn composite_strength(&self) -> f32 {
+        if self.enabled { self.strength } else { 0.0 }
+    }
+
+    /// Estimates ambient occlusion from the given depth texture.
+    /// Returns the blurred occlusion texture (1.0 = not occluded)
+    /// NOTE: Leaves framebuffer unbound & viewport in SSAO resolution. Caller has to restore
+    /// viewport
+    pub fn render(&mut self, depth: NativeTexture, cam: &Camera) -> NativeTexture {
+        let gl = &self.gl;
+        unsafe {
+            gl.disable(gl::DEPTH_TEST);
+            gl.viewport(0, 0, self.width, self.height);
+        }
+        if !self.enabled {
+            self.targets[1].clear(gl, 1.0, 1.0, 1.0);
+            return self.targets[1].texture;
+        }
+
+        // 1. Occlusion estimation
+        let projection = cam.get_projection_matrix();
+        let shader = &mut self.occlusion_quad.shader;
+        shader.use_program();
+        shader.set_uniform_mat4("uProjection", &projection);
+        shader.set_uniform_mat4("uInvProjection", &projection.inverse());
+        shader.set_uniform_i32("uDepthZeroToOne", depth::is_clip_depth_zero_to_one() as i32);
+        shader.set_uniform_i32("sample_count", self.quality.sample_count());
+        shader.set_uniform_f32("radius", self.radius);
+        shader.set_uniform_f32("bias", self.bias);
+        draw_fullscreen(gl, &self.occlusion_quad, self.targets[0].fbo, depth);
+
+        // 2. Blur
+        self.blur_quad.shader.use_program();
+        draw_fullscreen(
+            gl,
+            &self.blur_quad,
+            self.targets[1].fbo,
+            self.targets[0].texture,
+        );
+        unsafe {
+            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        }
+        self.targets[1].texture
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("SSAO")
+            .size([300.0, 150.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 500.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("Enabled", &mut self.enabled);
+                let mut quality_idx = SsaoQuality::ALL
+                    .iter()
+                    .position(|q| *q == self.quality)
+                    .unwrap_or(1);
+                let labels = SsaoQuality::ALL.map(|q| q.label());
+                if ui.combo_simple_string("Quality", &mut quality_idx, &labels) {
+                    self.quality = SsaoQuality::ALL[quality_idx];
+                }
+                ui.slider("Radius", 0.1, 4.0, &mut self.radius);
+                ui.slider("Strength", 0.0, 1.0, &mut self.strength);
+            });
+    }
+}
+
+impl Drop for SsaoPass {
+    fn drop(&mut self) {
+        for target in &self.targets {
+            target.delete(&self.gl);
+        }
+    }
+}
+
+fn ssao_target(
+    gl: &glow::Context,
+    width: i32,
+    height: i32,
+) -> Result<RenderTarget, Box<dyn Error>> {
+    RenderTarget::new(gl, width, height, gl::R8, gl::RED, gl::UNSIGNED_BYTE)
+}
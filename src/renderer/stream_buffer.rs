@@ -0,0 +1,234 @@
+use std::{error::Error, rc::Rc};
+
+use glow::{HasContext, NativeBuffer, NativeFence};
+use log::{info, warn};
+
+use super::capabilities;
+
+/// Number of frames the CPU may write ahead of the GPU before having to wait
+pub const FRAMES_IN_FLIGHT: usize = 3;
+/// Upper bound of views rendered per frame, e.g. splitscreen players, texture cameras & the
+/// debug view
+pub const MAX_VIEWS: usize = 16;
+// Upper bound for waiting on the GPU to release a segment
+const FENCE_TIMEOUT_NANOS: i32 = 100_000_000;
+
+/// Segment size for data of up to `view_size` bytes written by every view of a frame
+pub fn segment_size_per_view(view_size: usize, alignment: usize) -> usize {
+    view_size.next_multiple_of(alignment.max(1)) * MAX_VIEWS
+}
+
+/// Bump allocator over [FRAMES_IN_FLIGHT] equally sized segments of a ring buffer.
+/// Every frame allocates linearly within its own segment
+#[derive(Debug)]
+struct RingSegments {
+    segment_size: usize,
+    current: usize,
+    // Write offset within current segment
+    offset: usize,
+}
+
+impl RingSegments {
+    fn new(segment_size: usize) -> RingSegments {
+        Self {
+            segment_size,
+            current: 0,
+            offset: 0,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.segment_size * FRAMES_IN_FLIGHT
+    }
+
+    /// Returns absolute buffer offset or None if the current segment is full
+    fn allocate(&mut self, size: usize, alignment: usize) -> Option<usize> {
+        let aligned = self.offset.next_multiple_of(alignment.max(1));
+        if aligned + size > self.segment_size {
+            return None;
+        }
+        self.offset = aligned + size;
+        Some(self.current * self.segment_size + aligned)
+    }
+
+    /// Moves on to the next segment. Returns its index
+    fn next_frame(&mut self) -> usize {
+        self.current = (self.current + 1) % FRAMES_IN_FLIGHT;
+        self.offset = 0;
+        self.current
+    }
+}
+
+/// Ring buffer for data rewritten every frame (uniforms, instance transforms, particles, ...).
+/// - Persistently mapped via glBufferStorage if supported (GL 4.4 / ARB_buffer_storage).
+///   Writes go straight into mapped memory; fences keep the CPU from overwriting segments the GPU
+///   still reads
+/// - Falls back to buffer_sub_data into the same ring otherwise
+///
+/// Either way the buffer is allocated once instead of reallocating storage every frame
+pub struct StreamBuffer {
+    gl: Rc<glow::Context>,
+    target: u32,
+    buffer: NativeBuffer,
+    // Start of persistently mapped memory. None if persistent mapping is not supported
+    mapped: Option<*mut u8>,
+    segments: RingSegments,
+    // Signaled once the GPU finished all commands reading from the segment
+    fences: [Option<NativeFence>; FRAMES_IN_FLIGHT],
+}
+
+impl StreamBuffer {
+    /// Creates ring buffer with room for `segment_size` bytes per frame
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        target: u32,
+        segment_size: usize,
+    ) -> Result<StreamBuffer, Box<dyn Error>> {
+        let segments = RingSegments::new(segment_size);
+        let size = segments.capacity() as i32;
+        unsafe {
+            let buffer = gl.create_buffer()?;
+            gl.bind_buffer(target, Some(buffer));
+            let mapped = if is_buffer_storage_supported(gl) {
+                let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+                gl.buffer_storage(target, size, None, flags);
+                let ptr = gl.map_buffer_range(target, 0, size, flags);
+                if ptr.is_null() {
+                    return Err("Unable to persistently map stream buffer".into());
+                }
+                Some(ptr)
+            } else {
+                gl.buffer_data_size(target, size, gl::DYNAMIC_DRAW);
+                None
+            };
+            gl.bind_buffer(target, None);
+            info!(
+                "Created stream buffer of {size} bytes (persistent mapping: {})",
+                mapped.is_some()
+            );
+            Ok(Self {
+                gl: Rc::clone(gl),
+                target,
+                buffer,
+                mapped,
+                segments,
+                fences: [None; FRAMES_IN_FLIGHT],
+            })
+        }
+    }
+
+    pub fn buffer(&self) -> NativeBuffer {
+        self.buffer
+    }
+
+    /// Fences the segment written during the previous frame & switches to the next one.
+    /// Blocks if the GPU is still reading the next segment. Has to be called once per frame
+    /// before writing. Multiple views of a frame write into the same segment
+    pub fn begin_frame(&mut self) {
+        let gl = &self.gl;
+        unsafe {
+            let previous = self.segments.current;
+            if let Some(fence) = self.fences[previous].take() {
+                gl.delete_sync(fence);
+            }
+            match gl.fence_sync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) {
+                Ok(fence) => self.fences[previous] = Some(fence),
+                Err(err) => warn!("Unable to create stream buffer fence: {err}"),
+            }
+
+            let next = self.segments.next_frame();
+            if let Some(fence) = self.fences[next].take() {
+                let status =
+                    gl.client_wait_sync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, FENCE_TIMEOUT_NANOS);
+                if status == gl::TIMEOUT_EXPIRED || status == gl::WAIT_FAILED {
+                    warn!("Waiting for stream buffer segment failed with status {status:#x}");
+                }
+                gl.delete_sync(fence);
+            }
+        }
+    }
+
+    /// Copies data into the current frame segment. Returns byte offset within the buffer or
+    /// None if the segment has no space left for this frame
+    pub fn write(&mut self, data: &[u8], alignment: usize) -> Option<usize> {
+        let offset = self.segments.allocate(data.len(), alignment)?;
+        unsafe {
+            match self.mapped {
+                Some(ptr) => {
+                    std::ptr::copy_nonoverlapping(data.as_ptr(), ptr.add(offset), data.len())
+                }
+                None => {
+                    self.gl.bind_buffer(self.target, Some(self.buffer));
+                    self.gl
+                        .buffer_sub_data_u8_slice(self.target, offset as i32, data);
+                    self.gl.bind_buffer(self.target, None);
+                }
+            }
+        }
+        Some(offset)
+    }
+}
+
+impl Drop for StreamBuffer {
+    fn drop(&mut self) {
+        let gl = &self.gl;
+        unsafe {
+            for fence in self.fences.iter_mut().filter_map(Option::take) {
+                gl.delete_sync(fence);
+            }
+            if self.mapped.is_some() {
+                gl.bind_buffer(self.target, Some(self.buffer));
+                gl.unmap_buffer(self.target);
+                gl.bind_buffer(self.target, None);
+            }
+            gl.delete_buffer(self.buffer);
+        }
+    }
+}
+
+fn is_buffer_storage_supported(gl: &glow::Context) -> bool {
+    capabilities::supports(gl, (4, 4), &["GL_ARB_buffer_storage"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_segments_align_allocations() {
+        let mut segments = RingSegments::new(1024);
+        assert_eq!(segments.allocate(100, 256), Some(0));
+        assert_eq!(segments.allocate(100, 256), Some(256));
+        assert_eq!(segments.allocate(4, 4), Some(356));
+    }
+
+    #[test]
+    fn test_ring_segments_full_segment() {
+        let mut segments = RingSegments::new(512);
+        assert_eq!(segments.allocate(300, 256), Some(0));
+        assert_eq!(segments.allocate(300, 256), None);
+        // Failed allocation does not consume space
+        assert_eq!(segments.allocate(200, 1), Some(300));
+    }
+
+    #[test]
+    fn test_ring_segments_hold_all_views_of_a_frame() {
+        let mut segments = RingSegments::new(segment_size_per_view(300, 256));
+        for view in 0..MAX_VIEWS {
+            assert_eq!(segments.allocate(300, 256), Some(view * 512));
+        }
+        assert_eq!(segments.allocate(300, 256), None);
+    }
+
+    #[test]
+    fn test_ring_segments_wrap_around() {
+        let mut segments = RingSegments::new(512);
+        assert_eq!(segments.allocate(10, 1), Some(0));
+        assert_eq!(segments.next_frame(), 1);
+        assert_eq!(segments.allocate(10, 1), Some(512));
+        assert_eq!(segments.next_frame(), 2);
+        assert_eq!(segments.allocate(10, 1), Some(1024));
+        assert_eq!(segments.next_frame(), 0);
+        assert_eq!(segments.allocate(10, 1), Some(0));
+    }
+}
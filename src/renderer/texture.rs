@@ -1,21 +1,115 @@
 use glow::{HasContext, NativeTexture};
-use std::{error::Error, path::Path, rc::Rc};
+use log::{error, info};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use super::{
+    capabilities::supports,
+    texture_formats::{PixelFormat, TextureImage, decode_image},
+};
+
+// Shown until the image of an asynchronously loaded texture is uploaded
+const PLACEHOLDER_COLOR: [u8; 4] = [128, 128, 128, 255];
 
 pub struct Texture {
     gl: Rc<glow::Context>,
     tbo: NativeTexture,
+    // Image the texture was loaded from
+    path: PathBuf,
+    // Image decoded in the background. None once it was uploaded
+    pending: Option<Receiver<Result<TextureImage, String>>>,
 }
 
 impl Texture {
+    /// Decodes & uploads the image right away. PNGs & other formats supported by the image crate
+    /// are decoded to RGBA, DDS & KTX2 files keep their block compression & mip levels
     pub fn new(gl: &Rc<glow::Context>, img_path: &Path) -> Result<Texture, Box<dyn Error>> {
-        let (image_data, width, height) = load_rgba_image_as_u8_raw(img_path)?;
-        let tbo = create_texture_from_rgba_u8(gl, &image_data, width, height);
+        let image = decode_image(img_path)?;
+        let texture = Self::empty(gl, img_path)?;
+        texture.upload(&image)?;
+        Ok(texture)
+    }
+
+    /// Decodes the image on a background thread, so loading does not stall the render thread.
+    /// Shows a placeholder until [Texture::poll] uploads the decoded image
+    pub fn load_async(gl: &Rc<glow::Context>, img_path: &Path) -> Result<Texture, Box<dyn Error>> {
+        let mut texture = Self::empty(gl, img_path)?;
+        texture.upload(&TextureImage {
+            format: PixelFormat::Rgba8 { srgb: false },
+            width: 1,
+            height: 1,
+            levels: vec![PLACEHOLDER_COLOR.to_vec()],
+        })?;
+        let (tx, rx) = mpsc::channel();
+        let path = img_path.to_path_buf();
+        thread::spawn(move || {
+            // Receiver is gone if the texture was dropped in the meantime
+            let _ = tx.send(decode_image(&path));
+        });
+        texture.pending = Some(rx);
+        Ok(texture)
+    }
+
+    fn empty(gl: &Rc<glow::Context>, img_path: &Path) -> Result<Texture, Box<dyn Error>> {
+        let tbo = unsafe { gl.create_texture()? };
         Ok(Self {
             gl: Rc::clone(gl),
             tbo,
+            path: img_path.to_path_buf(),
+            pending: None,
         })
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Swaps the placeholder for the decoded image once it is ready. Has to be called regularly
+    /// while loading, e.g. once per frame. Returns true if the image was uploaded. Failures are
+    /// logged & keep the placeholder
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.pending else {
+            return false;
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => Err("Decoding thread stopped".to_string()),
+        };
+        self.pending = None;
+        let uploaded = result.map_err(Box::<dyn Error>::from).and_then(|image| {
+            self.upload(&image)?;
+            Ok(image.size())
+        });
+        match uploaded {
+            Ok(size) => {
+                info!(
+                    "Loaded texture {} ({} KiB)",
+                    self.path.display(),
+                    size / 1024
+                );
+                true
+            }
+            Err(err) => {
+                error!("Unable to load texture {}: {err}", self.path.display());
+                false
+            }
+        }
+    }
+
+    /// Re-uploads the image from disk into the same texture object, so everything referencing
+    /// the texture picks up the change. Keeps the old image if loading fails
+    pub fn reload(&mut self) -> Result<(), Box<dyn Error>> {
+        let image = decode_image(&self.path)?;
+        self.pending = None;
+        self.upload(&image)
+    }
+
     pub fn bind(&self) {
         unsafe {
             self.gl.bind_texture(gl::TEXTURE_2D, Some(self.tbo));
@@ -26,46 +120,66 @@ impl Texture {
             self.gl.bind_texture(gl::TEXTURE_2D, None);
         }
     }
-}
-
-fn create_texture_from_rgba_u8(
-    gl: &glow::Context,
-    data: &[u8],
-    width: u32,
-    height: u32,
-) -> glow::NativeTexture {
-    unsafe {
-        let texture = gl.create_texture().unwrap();
-        gl.bind_texture(gl::TEXTURE_2D, Some(texture));
-
-        // Configure texture parameters
-        gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-        gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-        gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-        gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-
-        // Upload texture data
-        gl.tex_image_2d(
-            gl::TEXTURE_2D,
-            0,               // level
-            gl::RGBA as i32, // internal format
-            width as i32,
-            height as i32,
-            0,                 // border
-            gl::RGBA,          // format
-            gl::UNSIGNED_BYTE, // type
-            Some(data),        // raw data
-        );
 
-        gl.bind_texture(gl::TEXTURE_2D, None);
-        texture
+    // Replaces all mip levels of the texture object
+    fn upload(&self, image: &TextureImage) -> Result<(), Box<dyn Error>> {
+        let (min_version, extensions) = image.format.requirements();
+        if !supports(&self.gl, min_version, extensions) {
+            return Err(format!(
+                "{:?} textures are not supported by the context",
+                image.format
+            )
+            .into());
+        }
+        let gl = &self.gl;
+        let internal_format = image.format.gl_internal_format();
+        let min_filter = match image.levels.len() {
+            1 => gl::LINEAR,
+            _ => gl::LINEAR_MIPMAP_LINEAR,
+        };
+        unsafe {
+            gl.bind_texture(gl::TEXTURE_2D, Some(self.tbo));
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            // Levels missing from the file must not be sampled
+            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, 0);
+            gl.tex_parameter_i32(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAX_LEVEL,
+                image.levels.len() as i32 - 1,
+            );
+            for (level, data) in image.levels.iter().enumerate() {
+                let width = (image.width >> level).max(1) as i32;
+                let height = (image.height >> level).max(1) as i32;
+                if image.format.is_compressed() {
+                    gl.compressed_tex_image_2d(
+                        gl::TEXTURE_2D,
+                        level as i32,
+                        internal_format as i32,
+                        width,
+                        height,
+                        0,
+                        data.len() as i32,
+                        data,
+                    );
+                } else {
+                    gl.tex_image_2d(
+                        gl::TEXTURE_2D,
+                        level as i32,
+                        internal_format as i32,
+                        width,
+                        height,
+                        0,                 // border
+                        gl::RGBA,          // format
+                        gl::UNSIGNED_BYTE, // type
+                        Some(data),
+                    );
+                }
+            }
+            gl.bind_texture(gl::TEXTURE_2D, None);
+        }
+        Ok(())
     }
 }
-
-fn load_rgba_image_as_u8_raw<P: AsRef<Path>>(
-    path: P,
-) -> Result<(Vec<u8>, u32, u32), Box<dyn std::error::Error>> {
-    let img = image::open(path)?.to_rgba8();
-    let (width, height) = img.dimensions();
-    Ok((img.into_raw(), width, height))
-}
@@ -0,0 +1,65 @@
+use glam::Mat4;
+use serde::{Deserialize, Serialize};
+
+use crate::cameras::camera::{Camera, DEFAULT_FAR, DEFAULT_NEAR, perspective_reverse_z};
+
+use super::material::TextureHandle;
+
+/// Renders the scene from the transform of its entity into a texture, e.g. for security monitors
+/// or portals. Materials show the image by sampling the texture handle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextureCamera {
+    /// Render target of the camera. Shadows textures registered with the same handle
+    pub texture: TextureHandle,
+    pub width: i32,
+    pub height: i32,
+    pub fov_degrees: f32,
+}
+
+impl TextureCamera {
+    pub fn new(texture: TextureHandle, width: i32, height: i32) -> TextureCamera {
+        Self {
+            texture,
+            width,
+            height,
+            fov_degrees: 70.0,
+        }
+    }
+
+    /// Camera at the transform, looking along its forward axis. Scale & roll are ignored
+    pub fn camera(&self, transform: &Mat4) -> Camera {
+        let (_scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let mut camera = Camera::new();
+        camera.position = translation;
+        camera.set_rotation(rotation);
+        camera.set_projection(perspective_reverse_z(
+            self.fov_degrees.to_radians(),
+            self.width as f32 / self.height.max(1) as f32,
+            DEFAULT_NEAR,
+            DEFAULT_FAR,
+        ));
+        camera
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn test_camera_follows_transform() {
+        let texture_camera = TextureCamera::new(0, 320, 240);
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::splat(0.3),
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            Vec3::new(1.0, 2.0, 3.0),
+        );
+        let camera = texture_camera.camera(&transform);
+        assert_eq!(camera.position, Vec3::new(1.0, 2.0, 3.0));
+        // Forward axis -Z turned to -X
+        let forward = camera.get_rotation() * Vec3::NEG_Z;
+        assert!(forward.distance(Vec3::NEG_X) < 1e-5, "{forward}");
+    }
+}
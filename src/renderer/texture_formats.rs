@@ -0,0 +1,455 @@
+use std::path::Path;
+
+// S3TC formats are extension only, so glow does not define them
+const COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: u32 = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: u32 = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: u32 = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: u32 = 0x8C4F;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+// Magic, header & the optional DX10 extension
+const DDS_HEADER_SIZE: usize = 4 + 124;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+// Identifier, header & index, followed by the level index
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_SIZE: usize = 24;
+// Larger images are rejected before sizes are computed from untrusted headers
+const MAX_TEXTURE_SIZE: u32 = 16384;
+
+/// Layout of the pixels of a texture, either plain RGBA or one of the block compressed formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgba8 {
+        srgb: bool,
+    },
+    /// DXT1, opaque unless alpha
+    Bc1 {
+        srgb: bool,
+        alpha: bool,
+    },
+    /// DXT3
+    Bc2 {
+        srgb: bool,
+    },
+    /// DXT5
+    Bc3 {
+        srgb: bool,
+    },
+    /// Single channel
+    Bc4,
+    /// Two channels, e.g. normal maps
+    Bc5,
+    /// BPTC
+    Bc7 {
+        srgb: bool,
+    },
+}
+
+impl PixelFormat {
+    pub fn is_compressed(self) -> bool {
+        !matches!(self, PixelFormat::Rgba8 { .. })
+    }
+
+    pub fn gl_internal_format(self) -> u32 {
+        match self {
+            PixelFormat::Rgba8 { srgb: false } => glow::RGBA8,
+            PixelFormat::Rgba8 { srgb: true } => glow::SRGB8_ALPHA8,
+            PixelFormat::Bc1 { srgb, alpha } => match (srgb, alpha) {
+                (false, false) => COMPRESSED_RGB_S3TC_DXT1,
+                (false, true) => COMPRESSED_RGBA_S3TC_DXT1,
+                (true, false) => COMPRESSED_SRGB_S3TC_DXT1,
+                (true, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+            },
+            PixelFormat::Bc2 { srgb: false } => COMPRESSED_RGBA_S3TC_DXT3,
+            PixelFormat::Bc2 { srgb: true } => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            PixelFormat::Bc3 { srgb: false } => COMPRESSED_RGBA_S3TC_DXT5,
+            PixelFormat::Bc3 { srgb: true } => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            PixelFormat::Bc4 => glow::COMPRESSED_RED_RGTC1,
+            PixelFormat::Bc5 => glow::COMPRESSED_RG_RGTC2,
+            PixelFormat::Bc7 { srgb: false } => glow::COMPRESSED_RGBA_BPTC_UNORM,
+            PixelFormat::Bc7 { srgb: true } => glow::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+        }
+    }
+
+    /// Minimum context version & extensions required to upload the format
+    pub fn requirements(self) -> ((u32, u32), &'static [&'static str]) {
+        match self {
+            PixelFormat::Rgba8 { .. } | PixelFormat::Bc4 | PixelFormat::Bc5 => ((3, 0), &[]),
+            PixelFormat::Bc1 { .. } | PixelFormat::Bc2 { .. } | PixelFormat::Bc3 { .. } => {
+                // Never part of core
+                ((u32::MAX, 0), &["GL_EXT_texture_compression_s3tc"])
+            }
+            PixelFormat::Bc7 { .. } => ((4, 2), &["GL_ARB_texture_compression_bptc"]),
+        }
+    }
+
+    /// Bytes of a mip level of the given size. Compressed formats store blocks of 4x4 pixels
+    pub fn level_size(self, width: u32, height: u32) -> usize {
+        let blocks = |pixels: u32| pixels.div_ceil(4).max(1) as usize;
+        match self {
+            PixelFormat::Rgba8 { .. } => width as usize * height as usize * 4,
+            PixelFormat::Bc1 { .. } | PixelFormat::Bc4 => blocks(width) * blocks(height) * 8,
+            _ => blocks(width) * blocks(height) * 16,
+        }
+    }
+}
+
+/// Decoded image with its mip levels, largest first. Ready to be uploaded as is
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureImage {
+    pub format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl TextureImage {
+    /// Bytes the image occupies in video memory
+    pub fn size(&self) -> usize {
+        self.levels.iter().map(Vec::len).sum()
+    }
+}
+
+/// Decodes the image file, picking the container by extension. DDS & KTX2 files are kept block
+/// compressed, everything else is decoded to RGBA
+pub fn decode_image(path: &Path) -> Result<TextureImage, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let read = || std::fs::read(path).map_err(|err| format!("{}: {err}", path.display()));
+    let image = match extension.as_deref() {
+        Some("dds") => parse_dds(&read()?),
+        Some("ktx2") => parse_ktx2(&read()?),
+        _ => image::open(path).map_err(|err| err.to_string()).map(|img| {
+            let img = img.to_rgba8();
+            let (width, height) = img.dimensions();
+            TextureImage {
+                format: PixelFormat::Rgba8 { srgb: false },
+                width,
+                height,
+                levels: vec![img.into_raw()],
+            }
+        }),
+    };
+    image.map_err(|err| format!("Unable to decode {}: {err}", path.display()))
+}
+
+/// Parses a DirectDraw Surface with a block compressed 2D texture, including the DX10 extension
+pub fn parse_dds(bytes: &[u8]) -> Result<TextureImage, String> {
+    if bytes.len() < DDS_HEADER_SIZE || &bytes[0..4] != DDS_MAGIC {
+        return Err("Not a DDS file".to_string());
+    }
+    let height = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 16)?;
+    let mip_count = check_level_count(width, height, read_u32(bytes, 28)?)?;
+    let four_cc = &bytes[84..88];
+    let (format, data_offset) = match four_cc {
+        b"DX10" => {
+            let format = match read_u32(bytes, DDS_HEADER_SIZE)? {
+                71 => PixelFormat::Bc1 {
+                    srgb: false,
+                    alpha: true,
+                },
+                72 => PixelFormat::Bc1 {
+                    srgb: true,
+                    alpha: true,
+                },
+                74 => PixelFormat::Bc2 { srgb: false },
+                75 => PixelFormat::Bc2 { srgb: true },
+                77 => PixelFormat::Bc3 { srgb: false },
+                78 => PixelFormat::Bc3 { srgb: true },
+                80 => PixelFormat::Bc4,
+                83 => PixelFormat::Bc5,
+                98 => PixelFormat::Bc7 { srgb: false },
+                99 => PixelFormat::Bc7 { srgb: true },
+                dxgi => return Err(format!("Unsupported DXGI format {dxgi}")),
+            };
+            (format, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+        }
+        b"DXT1" => (
+            PixelFormat::Bc1 {
+                srgb: false,
+                alpha: true,
+            },
+            DDS_HEADER_SIZE,
+        ),
+        b"DXT3" => (PixelFormat::Bc2 { srgb: false }, DDS_HEADER_SIZE),
+        b"DXT5" => (PixelFormat::Bc3 { srgb: false }, DDS_HEADER_SIZE),
+        b"ATI1" | b"BC4U" => (PixelFormat::Bc4, DDS_HEADER_SIZE),
+        b"ATI2" | b"BC5U" => (PixelFormat::Bc5, DDS_HEADER_SIZE),
+        _ => {
+            return Err(format!(
+                "Unsupported DDS format '{}'",
+                String::from_utf8_lossy(four_cc)
+            ));
+        }
+    };
+
+    // Levels are stored back to back, largest first
+    let mut levels = Vec::with_capacity(mip_count as usize);
+    let mut offset = data_offset;
+    for level in 0..mip_count {
+        let size = format.level_size((width >> level).max(1), (height >> level).max(1));
+        let data = level_data(bytes, offset, size)
+            .ok_or(format!("DDS data ends within mip level {level}"))?;
+        levels.push(data.to_vec());
+        offset += size;
+    }
+    Ok(TextureImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+/// Parses a KTX2 container with a 2D texture. Supercompressed files, e.g. Basis Universal, are
+/// not supported
+pub fn parse_ktx2(bytes: &[u8]) -> Result<TextureImage, String> {
+    if bytes.len() < KTX2_HEADER_SIZE || bytes[0..12] != KTX2_IDENTIFIER {
+        return Err("Not a KTX2 file".to_string());
+    }
+    let format = match read_u32(bytes, 12)? {
+        37 => PixelFormat::Rgba8 { srgb: false },
+        43 => PixelFormat::Rgba8 { srgb: true },
+        131 => PixelFormat::Bc1 {
+            srgb: false,
+            alpha: false,
+        },
+        132 => PixelFormat::Bc1 {
+            srgb: true,
+            alpha: false,
+        },
+        133 => PixelFormat::Bc1 {
+            srgb: false,
+            alpha: true,
+        },
+        134 => PixelFormat::Bc1 {
+            srgb: true,
+            alpha: true,
+        },
+        135 => PixelFormat::Bc2 { srgb: false },
+        136 => PixelFormat::Bc2 { srgb: true },
+        137 => PixelFormat::Bc3 { srgb: false },
+        138 => PixelFormat::Bc3 { srgb: true },
+        139 => PixelFormat::Bc4,
+        141 => PixelFormat::Bc5,
+        145 => PixelFormat::Bc7 { srgb: false },
+        146 => PixelFormat::Bc7 { srgb: true },
+        0 => return Err("KTX2 files without format are not supported".to_string()),
+        vk_format => return Err(format!("Unsupported Vulkan format {vk_format}")),
+    };
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layers = read_u32(bytes, 32)?;
+    let faces = read_u32(bytes, 36)?;
+    if depth > 0 || layers > 1 || faces != 1 {
+        return Err("Only single 2D textures are supported".to_string());
+    }
+    // 0 asks the loader to generate mips, only the base level is stored then
+    let level_count = check_level_count(width, height, read_u32(bytes, 40)?)?;
+    let supercompression = read_u32(bytes, 44)?;
+    if supercompression != 0 {
+        return Err(format!(
+            "Supercompression scheme {supercompression} is not supported"
+        ));
+    }
+
+    let mut levels = Vec::with_capacity(level_count as usize);
+    for level in 0..level_count {
+        let index = KTX2_HEADER_SIZE + level as usize * KTX2_LEVEL_SIZE;
+        let offset = read_u64(bytes, index)?;
+        let length = read_u64(bytes, index + 8)?;
+        let expected = format.level_size((width >> level).max(1), (height >> level).max(1));
+        if length != expected as u64 {
+            return Err(format!(
+                "Mip level {level} has {length} bytes, expected {expected}"
+            ));
+        }
+        let data = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| level_data(bytes, offset, expected))
+            .ok_or(format!("KTX2 data ends within mip level {level}"))?;
+        levels.push(data.to_vec());
+    }
+    Ok(TextureImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+// Mip levels stored in the file. 0 means only the base level. Every level has to be at least 1
+// pixel wide, so a full chain has floor(log2(max(width, height))) + 1 levels
+fn check_level_count(width: u32, height: u32, count: u32) -> Result<u32, String> {
+    if width > MAX_TEXTURE_SIZE || height > MAX_TEXTURE_SIZE {
+        return Err(format!(
+            "Size {width}x{height} exceeds {MAX_TEXTURE_SIZE}x{MAX_TEXTURE_SIZE}"
+        ));
+    }
+    let max_levels = u32::BITS - width.max(height).max(1).leading_zeros();
+    if count > max_levels {
+        return Err(format!(
+            "{count} mip levels, a {width}x{height} image has at most {max_levels}"
+        ));
+    }
+    Ok(count.max(1))
+}
+
+// Bytes of a level, None if they exceed the file
+fn level_data(bytes: &[u8], offset: usize, length: usize) -> Option<&[u8]> {
+    bytes.get(offset..offset.checked_add(length)?)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|slice| u32::from_le_bytes(slice.try_into().unwrap()))
+        .ok_or(format!("Header ends at byte {offset}"))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, String> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|slice| u64::from_le_bytes(slice.try_into().unwrap()))
+        .ok_or(format!("Header ends at byte {offset}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DDS with the given four cc & mip count, level data filled with the level index
+    fn dds(four_cc: &[u8; 4], width: u32, height: u32, levels: &[usize]) -> Vec<u8> {
+        let mut bytes = vec![0; DDS_HEADER_SIZE];
+        bytes[0..4].copy_from_slice(DDS_MAGIC);
+        bytes[4..8].copy_from_slice(&124u32.to_le_bytes());
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes[28..32].copy_from_slice(&(levels.len() as u32).to_le_bytes());
+        bytes[84..88].copy_from_slice(four_cc);
+        for (level, size) in levels.iter().enumerate() {
+            bytes.extend(std::iter::repeat_n(level as u8, *size));
+        }
+        bytes
+    }
+
+    fn ktx2(vk_format: u32, width: u32, height: u32, levels: &[usize]) -> Vec<u8> {
+        let mut bytes = vec![0; KTX2_HEADER_SIZE + levels.len() * KTX2_LEVEL_SIZE];
+        bytes[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        bytes[12..16].copy_from_slice(&vk_format.to_le_bytes());
+        bytes[20..24].copy_from_slice(&width.to_le_bytes());
+        bytes[24..28].copy_from_slice(&height.to_le_bytes());
+        bytes[36..40].copy_from_slice(&1u32.to_le_bytes());
+        bytes[40..44].copy_from_slice(&(levels.len() as u32).to_le_bytes());
+        // Level data is stored smallest first, the index lists the largest first
+        let mut offset = bytes.len();
+        for (level, size) in levels.iter().enumerate().rev() {
+            let index = KTX2_HEADER_SIZE + level * KTX2_LEVEL_SIZE;
+            bytes[index..index + 8].copy_from_slice(&(offset as u64).to_le_bytes());
+            bytes[index + 8..index + 16].copy_from_slice(&(*size as u64).to_le_bytes());
+            offset += size;
+        }
+        for (level, size) in levels.iter().enumerate().rev() {
+            bytes.extend(std::iter::repeat_n(level as u8, *size));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_dds() {
+        // 8x8 down to 1x1: 2x2 blocks, then a single block for every smaller level
+        let bytes = dds(b"DXT1", 8, 8, &[32, 8, 8, 8]);
+        let image = parse_dds(&bytes).unwrap();
+        assert_eq!(
+            image.format,
+            PixelFormat::Bc1 {
+                srgb: false,
+                alpha: true
+            }
+        );
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.levels.len(), 4);
+        assert_eq!(image.levels[0], vec![0; 32]);
+        assert_eq!(image.levels[3], vec![3; 8]);
+        assert_eq!(image.size(), 56);
+
+        let mut bytes = dds(b"DX10", 4, 4, &[]);
+        bytes[28..32].copy_from_slice(&1u32.to_le_bytes());
+        bytes.extend(98u32.to_le_bytes());
+        bytes.extend([0; DDS_DX10_HEADER_SIZE - 4]);
+        bytes.extend([7; 16]);
+        let image = parse_dds(&bytes).unwrap();
+        assert_eq!(image.format, PixelFormat::Bc7 { srgb: false });
+        assert_eq!(image.levels, vec![vec![7; 16]]);
+
+        // Missing the last level
+        let truncated = dds(b"DXT5", 8, 8, &[64, 16, 16, 16]);
+        assert!(parse_dds(&truncated[..truncated.len() - 1]).is_err());
+        assert!(parse_dds(&dds(b"RGBG", 4, 4, &[16])).is_err());
+        assert!(parse_dds(b"PNG").is_err());
+    }
+
+    #[test]
+    fn test_hostile_headers_are_rejected() {
+        // Mip count beyond the chain of the size, which would overflow the level shifts
+        let mut bytes = dds(b"DXT1", 8, 8, &[32, 8, 8, 8]);
+        bytes[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_dds(&bytes).is_err());
+        bytes[28..32].copy_from_slice(&5u32.to_le_bytes());
+        assert!(parse_dds(&bytes).is_err());
+        // Huge size without data
+        assert!(parse_dds(&dds(b"DXT5", u32::MAX, u32::MAX, &[16])).is_err());
+        assert!(parse_dds(&dds(b"DXT5", 16384, 16384, &[16])).is_err());
+        // Truncated within the header
+        assert!(parse_dds(&bytes[..DDS_HEADER_SIZE - 1]).is_err());
+
+        let mut bytes = ktx2(145, 4, 4, &[16]);
+        bytes[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_ktx2(&bytes).is_err());
+        // Offset close to u64::MAX must not overflow when adding the length
+        let mut bytes = ktx2(145, 4, 4, &[16]);
+        bytes[KTX2_HEADER_SIZE..KTX2_HEADER_SIZE + 8]
+            .copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+        assert!(parse_ktx2(&bytes).is_err());
+        let mut bytes = ktx2(145, 4, 4, &[16]);
+        bytes[KTX2_HEADER_SIZE + 8..KTX2_HEADER_SIZE + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(parse_ktx2(&bytes).is_err());
+        // Level index cut off
+        let bytes = ktx2(145, 8, 8, &[64, 16, 16, 16]);
+        assert!(parse_ktx2(&bytes[..KTX2_HEADER_SIZE + KTX2_LEVEL_SIZE]).is_err());
+        assert!(parse_ktx2(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    fn test_parse_ktx2() {
+        let bytes = ktx2(145, 8, 4, &[32, 16, 16, 16]);
+        let image = parse_ktx2(&bytes).unwrap();
+        assert_eq!(image.format, PixelFormat::Bc7 { srgb: false });
+        assert_eq!((image.width, image.height), (8, 4));
+        assert_eq!(image.levels.len(), 4);
+        assert_eq!(image.levels[0], vec![0; 32]);
+        assert_eq!(image.levels[2], vec![2; 16]);
+
+        let image = parse_ktx2(&ktx2(43, 2, 2, &[16, 4])).unwrap();
+        assert_eq!(image.format, PixelFormat::Rgba8 { srgb: true });
+        assert!(!image.format.is_compressed());
+
+        // Level sizes have to match the format
+        assert!(parse_ktx2(&ktx2(145, 8, 8, &[32])).is_err());
+        let mut supercompressed = ktx2(145, 4, 4, &[16]);
+        supercompressed[44..48].copy_from_slice(&1u32.to_le_bytes());
+        assert!(parse_ktx2(&supercompressed).is_err());
+        assert!(parse_ktx2(&ktx2(1000, 4, 4, &[16])).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use glow::HasContext;
+
+/// Rectangle of the framebuffer a view renders into. Origin at the bottom left, like GL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Viewport {
+    pub fn full(width: i32, height: i32) -> Viewport {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// Viewport currently set on the context
+    pub fn current(gl: &glow::Context) -> Viewport {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+        }
+        let [x, y, width, height] = viewport;
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Side by side views for splitscreen, left to right. The last view takes the remainder
+    pub fn split(width: i32, height: i32, views: usize) -> Vec<Viewport> {
+        let views = views.max(1) as i32;
+        let view_width = width / views;
+        (0..views)
+            .map(|index| Viewport {
+                x: index * view_width,
+                y: 0,
+                width: match index == views - 1 {
+                    true => width - index * view_width,
+                    false => view_width,
+                },
+                height,
+            })
+            .collect()
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub fn apply(&self, gl: &glow::Context) {
+        unsafe {
+            gl.viewport(self.x, self.y, self.width, self.height);
+        }
+    }
+
+    // Same share of a frame of the given size
+    fn scaled(&self, from: (i32, i32), to: (f32, f32)) -> [f32; 4] {
+        let scale_x = to.0 / from.0.max(1) as f32;
+        let scale_y = to.1 / from.1.max(1) as f32;
+        [
+            self.x as f32 * scale_x,
+            self.y as f32 * scale_y,
+            self.width as f32 * scale_x,
+            self.height as f32 * scale_y,
+        ]
+    }
+
+    /// x, y, width & height in UI coordinates, which have their origin at the top left
+    pub fn ui_rect(&self, frame: (i32, i32), display_size: [f32; 2]) -> [f32; 4] {
+        let [x, y, width, height] = self.scaled(frame, (display_size[0], display_size[1]));
+        [x, display_size[1] - y - height, width, height]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_covers_frame() {
+        assert_eq!(
+            Viewport::split(1920, 1080, 1),
+            vec![Viewport::full(1920, 1080)]
+        );
+        let views = Viewport::split(1001, 600, 2);
+        assert_eq!(
+            views[0],
+            Viewport {
+                x: 0,
+                y: 0,
+                width: 500,
+                height: 600
+            }
+        );
+        assert_eq!(views[1].x, 500);
+        assert_eq!(views[1].width, 501);
+        assert!((views[0].aspect_ratio() - 500.0 / 600.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ui_rect_flips_y() {
+        let view = Viewport {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 50,
+        };
+        // UI at half the resolution
+        assert_eq!(
+            view.ui_rect((200, 100), [100.0, 50.0]),
+            [50.0, 25.0, 50.0, 25.0]
+        );
+    }
+}
@@ -10,13 +10,14 @@ use std::{
 
 use glam::{IVec3, Quat, Vec3};
 use glow::HasContext;
-use log::info;
+use log::{info, warn};
 
 use super::{GuiScene, Renderer, scene::BaseScene};
 use crate::{
     cameras::camera::Camera,
     cube::CubeRenderer,
     octree::IAabb,
+    renderer::{feature_flags::FeatureFlags, settings::RenderSettings},
     voxels::{CHUNK_SIZE, VoxelWorld},
 };
 
@@ -106,6 +107,8 @@ pub struct BenchmarkScene {
 
     cube_count: usize,
     frame_count: u32,
+    // Listed in the stats, so runs with different features can be told apart
+    features: FeatureFlags,
 }
 
 impl BenchmarkScene {
@@ -124,20 +127,12 @@ impl BenchmarkScene {
         let world = Rc::new(RefCell::new(VoxelWorld::new_cubic(world_size)));
         let cube_renderer = CubeRenderer::new(gl, Rc::clone(&world))?;
 
-        // Setup context
-        unsafe {
-            gl.enable(gl::CULL_FACE);
-            gl.enable(gl::DEPTH_TEST);
-            gl.depth_func(gl::LESS); // Default: Pass if the incoming depth is less than the stored depth
-            gl.cull_face(gl::BACK);
-            gl.front_face(gl::CCW);
-        }
-
         Ok(Self {
             camera: Rc::new(RefCell::new(camera)),
             cube_count: world_size * world_size * world_size,
             cube_renderer,
             frame_count: 0,
+            features: FeatureFlags::default(),
             gl: Rc::clone(gl),
             last: now,
             start: now,
@@ -148,20 +143,30 @@ impl BenchmarkScene {
 }
 
 impl BaseScene for BenchmarkScene {
-    fn tick(&mut self, dt: f32) {
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>> {
         let now = Instant::now();
         let camera_fov = IAabb::new(
             &IVec3::ZERO,
             self.world.borrow().get_size() * CHUNK_SIZE * 2,
         );
-        self.cube_renderer.tick(dt, &camera_fov);
+        self.cube_renderer.tick(dt, &camera_fov)?;
         self.last = now;
+        Ok(())
     }
 
-    fn start(&mut self) {
+    fn on_enter(&mut self) {
         self.start = Instant::now();
     }
 
+    fn on_exit(&mut self) {}
+
+    fn on_pause(&mut self) {
+        warn!(
+            "{} paused: Frame timings are not representative",
+            self.title
+        );
+    }
+
     fn get_title(&self) -> String {
         self.title.clone()
     }
@@ -174,15 +179,20 @@ impl BaseScene for BenchmarkScene {
 impl GuiScene for BenchmarkScene {
     fn render_ui(&mut self, _ui: &mut imgui::Ui) {}
 
-    fn render(&mut self, _gl: &glow::Context, _dt: Duration) {
+    fn apply_render_settings(&mut self, settings: &RenderSettings) -> Result<(), Box<dyn Error>> {
+        self.features = settings.features.clone();
+        Ok(())
+    }
+
+    fn render(&mut self, _gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
         let gl = &self.gl;
         unsafe {
-            gl.clear_color(0.05, 0.05, 0.1, 1.0);
             gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
 
         self.cube_renderer.render(&self.camera.borrow());
         self.frame_count += 1;
+        Ok(())
     }
 
     fn get_stats(&self) -> SceneStats {
@@ -190,7 +200,7 @@ impl GuiScene for BenchmarkScene {
             self.frame_count,
             self.start,
             self.last,
-            self.title.to_string(),
+            format!("{} [{}]", self.title, self.features.summary()),
             self.cube_count as u32,
         )
     }
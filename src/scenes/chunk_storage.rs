@@ -0,0 +1,213 @@
+use std::{
+    error::Error,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use glam::{IVec3, Vec3};
+use glow::HasContext;
+use log::warn;
+
+use super::{GuiScene, SceneStats, scene::BaseScene};
+use crate::{
+    octree::IAabb,
+    util::SimpleMovingAverage,
+    voxels::{
+        VoxelChunk,
+        chunk_storage::{ChunkIndexKind, ChunkStorage},
+    },
+};
+
+const REGION_QUERIES: i32 = 64;
+const RAYS: usize = 64;
+// Ray march step in chunks. Small enough to not skip cells
+const RAY_STEP: f32 = 0.25;
+
+#[derive(Default)]
+struct StorageResult {
+    // Microseconds
+    insert: Option<SimpleMovingAverage>,
+    region_query: Option<SimpleMovingAverage>,
+    ray_traversal: Option<SimpleMovingAverage>,
+    // Chunks found by the last run. Has to match between indices
+    chunks_found: usize,
+}
+
+fn record(average: &mut Option<SimpleMovingAverage>, start: Instant) {
+    average
+        .get_or_insert_with(|| SimpleMovingAverage::new(60))
+        .add_elapsed(start);
+}
+
+/// Compares chunk indices on identical workloads: Inserts, region queries & ray traversal.
+/// All chunks share the same voxels, only the index is measured
+pub struct ChunkStorageBenchmarkScene {
+    gl: Rc<glow::Context>,
+    world_size: usize,
+    chunk: Arc<VoxelChunk>,
+    // Chunk space positions of a terrain like height map
+    positions: Vec<IVec3>,
+    regions: Vec<IAabb>,
+    rays: Vec<(Vec3, Vec3)>,
+    results: Vec<(ChunkIndexKind, StorageResult)>,
+
+    start: Instant,
+    last: Instant,
+    frame_count: u32,
+}
+
+impl ChunkStorageBenchmarkScene {
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        world_size: usize,
+    ) -> Result<ChunkStorageBenchmarkScene, Box<dyn Error>> {
+        let size = world_size as i32;
+        let positions: Vec<IVec3> = IAabb::new(&IVec3::ZERO, world_size)
+            .iter_cells()
+            .filter(|p| p.y < size / 4 + (p.x * 7 + p.z * 13) % (size / 4).max(1))
+            .collect();
+        let regions = (0..REGION_QUERIES)
+            .map(|i| {
+                let min = IVec3::new(i * 5, i * 3, i * 7) % size;
+                IAabb::new_rect(min, min + IVec3::splat(1 + i % 8))
+            })
+            .collect();
+        let rays = (0..RAYS)
+            .map(|i| {
+                let angle = i as f32 / RAYS as f32 * std::f32::consts::TAU;
+                let origin = Vec3::new(0.5, 0.6, 0.5) * world_size as f32;
+                let direction = Vec3::new(angle.cos(), -0.3, angle.sin()).normalize();
+                (origin, direction)
+            })
+            .collect();
+        let now = Instant::now();
+        Ok(Self {
+            gl: Rc::clone(gl),
+            world_size,
+            chunk: Arc::new(VoxelChunk::new(IVec3::ZERO)),
+            positions,
+            regions,
+            rays,
+            results: [ChunkIndexKind::Octree, ChunkIndexKind::Morton]
+                .into_iter()
+                .map(|kind| (kind, StorageResult::default()))
+                .collect(),
+            start: now,
+            last: now,
+            frame_count: 0,
+        })
+    }
+}
+
+// Marches the ray through chunk space. Returns the number of chunks passed
+fn traverse_ray(storage: &ChunkStorage, origin: Vec3, direction: Vec3, length: f32) -> usize {
+    let mut last_cell = None;
+    let mut found = 0;
+    let mut distance = 0.0;
+    while distance < length {
+        let cell = (origin + direction * distance).floor().as_ivec3();
+        if last_cell != Some(cell) {
+            last_cell = Some(cell);
+            found += storage.get(cell).is_some() as usize;
+        }
+        distance += RAY_STEP;
+    }
+    found
+}
+
+impl BaseScene for ChunkStorageBenchmarkScene {
+    fn tick(&mut self, _dt: f32) -> Result<(), Box<dyn Error>> {
+        for (kind, result) in self.results.iter_mut() {
+            let start = Instant::now();
+            let mut storage = ChunkStorage::new(*kind, self.world_size);
+            for position in &self.positions {
+                storage.insert(*position, Arc::clone(&self.chunk));
+            }
+            record(&mut result.insert, start);
+
+            let start = Instant::now();
+            let mut found = 0;
+            for region in &self.regions {
+                found += storage.iter_region(region.clone()).count();
+            }
+            record(&mut result.region_query, start);
+
+            let start = Instant::now();
+            for (origin, direction) in &self.rays {
+                found += traverse_ray(&storage, *origin, *direction, self.world_size as f32);
+            }
+            record(&mut result.ray_traversal, start);
+            result.chunks_found = found;
+        }
+        self.last = Instant::now();
+        Ok(())
+    }
+
+    fn on_enter(&mut self) {
+        self.start = Instant::now();
+    }
+
+    fn on_exit(&mut self) {}
+
+    fn on_pause(&mut self) {
+        warn!("Chunk storage benchmark paused: Frame timings are not representative");
+    }
+
+    fn get_title(&self) -> String {
+        "Chunk storage benchmark".to_string()
+    }
+
+    fn get_world(&self) -> Option<&hecs::World> {
+        None
+    }
+}
+
+impl GuiScene for ChunkStorageBenchmarkScene {
+    fn get_stats(&self) -> SceneStats {
+        SceneStats::new(
+            self.frame_count,
+            self.start,
+            self.last,
+            self.get_title(),
+            self.positions.len() as u32,
+        )
+    }
+
+    fn render(&mut self, _gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
+        unsafe {
+            self.gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    fn render_ui(&mut self, ui: &mut imgui::Ui) {
+        let average = |average: &Option<SimpleMovingAverage>| {
+            average.as_ref().map_or(0.0, SimpleMovingAverage::get)
+        };
+        ui.window("Chunk storage")
+            .size([420.0, 220.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 0.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!(
+                    "{} chunks, {REGION_QUERIES} region queries, {RAYS} rays",
+                    self.positions.len()
+                ));
+                for (kind, result) in &self.results {
+                    ui.separator();
+                    ui.text(format!("{kind:?}"));
+                    ui.text(format!("Insert: {:.1}micro-s", average(&result.insert)));
+                    ui.text(format!(
+                        "Region queries: {:.1}micro-s",
+                        average(&result.region_query)
+                    ));
+                    ui.text(format!(
+                        "Ray traversal: {:.1}micro-s",
+                        average(&result.ray_traversal)
+                    ));
+                    ui.text(format!("Chunks found: {}", result.chunks_found));
+                }
+            });
+    }
+}
@@ -1,11 +1,15 @@
 use std::{cell::RefCell, error::Error, rc::Rc, time::Duration};
 
-use glam::{IVec3, Quat, Vec3};
+use glam::{IVec3, Vec2, Vec3};
 use glow::HasContext;
+use imgui::MouseButton;
 
 use crate::{
-    cameras::camera::Camera,
-    collision::CollisionInfo,
+    cameras::{
+        camera::{Camera, DEFAULT_FAR, DEFAULT_FOV_Y_DEGREES, DEFAULT_NEAR, perspective_reverse_z},
+        orbit::BlenderOrbitCamera,
+    },
+    collision::{CollisionInfo, sphere::ray_sphere_intersection},
     cube::CubeRenderer,
     meshes::sphere::SphereMesh,
     octree::IAabb,
@@ -14,11 +18,32 @@ use crate::{
     voxels::{CHUNK_SIZE, VoxelWorld, iter_sphere_collision},
 };
 
-use super::scene::BaseScene;
+use super::{
+    collision_scenarios::{CheckResult, CollisionScenario, SCENARIO_RADIUS},
+    scene::BaseScene,
+};
+
+// Initial orbit, looking down at the test chunk from the front left
+const ORBIT_DISTANCE: f32 = 30.0;
+const ORBIT_YAW: f32 = -0.6;
+const ORBIT_PITCH: f32 = -0.5;
+// Share of the distance to the focus point zoomed per scroll step
+const ZOOM_STEP: f32 = 0.1;
+const PASSED_COLOR: [f32; 4] = [0.3, 1.0, 0.3, 1.0];
+const FAILED_COLOR: [f32; 4] = [1.0, 0.3, 0.3, 1.0];
+
+// Sphere grabbed with the mouse. It moves within the plane through its center facing the camera
+struct SphereDrag {
+    normal: Vec3,
+    // From the grabbed point to the sphere center
+    offset: Vec3,
+}
 
 /// Used to debug & visualize collision tests
 pub struct CollisionScene {
     camera: Rc<RefCell<Camera>>,
+    orbit: BlenderOrbitCamera,
+    drag: Option<SphereDrag>,
     sphere: SphereMesh,
     cube_renderer: CubeRenderer,
     world: Rc<RefCell<VoxelWorld>>,
@@ -32,6 +57,13 @@ pub struct CollisionScene {
     render_sphere: bool,
     render_collision_points: bool,
 
+    // Scripted paths of the sphere
+    scenarios: Vec<CollisionScenario>,
+    scenario_index: usize,
+    // Seconds into the playing scenario. None if the sphere is moved by hand
+    scenario_time: Option<f32>,
+    scenario_results: Vec<CheckResult>,
+
     // DEBUG
     // Only test collision if sphere collider moved
     last_tested_position: Vec3,
@@ -41,22 +73,11 @@ pub struct CollisionScene {
 
 impl CollisionScene {
     pub fn new(gl: &Rc<glow::Context>) -> Result<CollisionScene, Box<dyn Error>> {
+        let world = Rc::new(RefCell::new(VoxelWorld::new_cubic(1)));
         let mut camera = Camera::new();
-        camera.position = Vec3::new(0.0, 3.0, -15.0);
-        camera.set_rotation(
-            Quat::from_rotation_y(-90f32.to_radians()) * Quat::from_rotation_x(-10f32.to_radians()),
-        );
+        let orbit = default_orbit(world.borrow().get_size());
+        orbit.apply(&mut camera);
 
-        // Setup context
-        unsafe {
-            gl.enable(gl::CULL_FACE);
-            gl.enable(gl::DEPTH_TEST);
-            gl.depth_func(gl::LESS);
-            gl.cull_face(gl::BACK);
-            gl.front_face(gl::CCW);
-        }
-
-        let world = Rc::new(RefCell::new(VoxelWorld::new_cubic(1)));
         let mut cube_renderer = CubeRenderer::new(gl, world.clone())?;
         cube_renderer.color = Vec3::new(0.0, 1.0, 0.0);
         let mut sphere = SphereMesh::new(gl)?;
@@ -81,13 +102,135 @@ impl CollisionScene {
             cube_renderer,
             sphere,
             camera: Rc::new(RefCell::new(camera)),
+            orbit,
+            drag: None,
             world,
             gl: Rc::clone(gl),
             render_cubes: true,
             render_sphere: true,
             render_collision_points: true,
+            scenarios: CollisionScenario::all(),
+            scenario_index: 0,
+            scenario_time: None,
+            scenario_results: Vec::new(),
         })
     }
+
+    // Checks the selected scenario & starts moving the sphere along its path
+    fn play_scenario(&mut self) {
+        let scenario = &self.scenarios[self.scenario_index];
+        self.scenario_results = scenario.evaluate(&self.world.borrow());
+        self.sphere.radius = SCENARIO_RADIUS;
+        self.sphere.position = scenario.start;
+        self.scenario_time = Some(0.0);
+    }
+
+    fn render_scenario_ui(&mut self, ui: &imgui::Ui) {
+        let names: Vec<&str> = self
+            .scenarios
+            .iter()
+            .map(|scenario| scenario.name)
+            .collect();
+        if ui.combo_simple_string("Scenario", &mut self.scenario_index, &names) {
+            self.scenario_time = None;
+            self.scenario_results.clear();
+        }
+        ui.text_wrapped(self.scenarios[self.scenario_index].description);
+        match self.scenario_time {
+            Some(_) if ui.button("Stop") => self.scenario_time = None,
+            Some(_) => {}
+            None if ui.button("Play") => self.play_scenario(),
+            None => {}
+        }
+        for result in &self.scenario_results {
+            let (color, state) = match result.passed {
+                true => (PASSED_COLOR, "PASS"),
+                false => (FAILED_COLOR, "FAIL"),
+            };
+            ui.text_colored(color, format!("{state} {}", result.label));
+        }
+    }
+
+    // Left drag moves the sphere if it was grabbed & orbits otherwise, scrolling zooms. Ignores
+    // the mouse while it is over a UI window
+    fn process_mouse(&mut self, ui: &imgui::Ui) {
+        let io = ui.io();
+        let [width, height] = io.display_size;
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        let mut camera = self.camera.borrow_mut();
+        // Keep picking in sync with the window shape
+        camera.set_projection(perspective_reverse_z(
+            DEFAULT_FOV_Y_DEGREES.to_radians(),
+            width / height,
+            DEFAULT_NEAR,
+            DEFAULT_FAR,
+        ));
+        let [x, y] = io.mouse_pos;
+        let ndc = Vec2::new(2.0 * x / width - 1.0, 1.0 - 2.0 * y / height);
+        let (origin, direction) = camera.ndc_ray(ndc);
+        let over_ui = io.want_capture_mouse;
+
+        if !over_ui && ui.is_mouse_clicked(MouseButton::Left) {
+            let normal = camera.get_rotation() * Vec3::Z;
+            let center = self.sphere.position;
+            self.drag = ray_sphere_intersection(origin, direction, center, self.sphere.radius)
+                .and_then(|_| ray_plane_intersection(origin, direction, center, normal))
+                .map(|grabbed| SphereDrag {
+                    normal,
+                    offset: center - grabbed,
+                });
+            // Grabbing the sphere takes it off the scenario path
+            if self.drag.is_some() {
+                self.scenario_time = None;
+            }
+        }
+        if !ui.is_mouse_down(MouseButton::Left) {
+            self.drag = None;
+        }
+        match &self.drag {
+            Some(drag) => {
+                if let Some(grabbed) =
+                    ray_plane_intersection(origin, direction, self.sphere.position, drag.normal)
+                {
+                    self.sphere.position = grabbed + drag.offset;
+                }
+            }
+            None if !over_ui && ui.is_mouse_dragging(MouseButton::Left) => {
+                let [dx, dy] = io.mouse_delta;
+                self.orbit.orbit(dx, dy);
+            }
+            None => {}
+        }
+        if !over_ui && io.mouse_wheel != 0.0 {
+            self.orbit
+                .dolly(-io.mouse_wheel * self.orbit.distance * ZOOM_STEP);
+        }
+        self.orbit.apply(&mut camera);
+    }
+}
+
+fn default_orbit(world_size: usize) -> BlenderOrbitCamera {
+    let center = Vec3::splat((world_size * CHUNK_SIZE) as f32 / 2.0);
+    let mut orbit = BlenderOrbitCamera::new(center, ORBIT_DISTANCE);
+    orbit.set_yaw_pitch(ORBIT_YAW, ORBIT_PITCH);
+    orbit
+}
+
+// Point where the ray crosses the plane. None if the ray runs parallel to or away from it
+fn ray_plane_intersection(
+    origin: Vec3,
+    direction: Vec3,
+    point: Vec3,
+    normal: Vec3,
+) -> Option<Vec3> {
+    let denominator = direction.dot(normal);
+    if denominator.abs() < 1e-6 {
+        return None;
+    }
+    let t = (point - origin).dot(normal) / denominator;
+    (t >= 0.0).then(|| origin + direction * t)
 }
 
 impl BaseScene for CollisionScene {
@@ -95,12 +238,17 @@ impl BaseScene for CollisionScene {
         "Collision Test".to_string()
     }
 
-    fn tick(&mut self, dt: f32) {
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>> {
         let camera_fov = IAabb::new(
             &IVec3::ZERO,
             self.world.borrow().get_size() * CHUNK_SIZE * 2,
         );
-        self.cube_renderer.tick(dt, &camera_fov);
+        self.cube_renderer.tick(dt, &camera_fov)?;
+        if let Some(time) = &mut self.scenario_time {
+            let scenario = &self.scenarios[self.scenario_index];
+            *time = (*time + dt) % scenario.duration;
+            self.sphere.position = scenario.position(*time / scenario.duration);
+        }
         // Update sphere
         if self.last_tested_position != self.sphere.position {
             self.collisions = iter_sphere_collision(
@@ -119,9 +267,11 @@ impl BaseScene for CollisionScene {
                 }
             }
         }
+        Ok(())
     }
 
-    fn start(&mut self) {}
+    fn on_enter(&mut self) {}
+    fn on_exit(&mut self) {}
     fn get_world(&self) -> Option<&hecs::World> {
         None
     }
@@ -132,10 +282,9 @@ impl GuiScene for CollisionScene {
         todo!()
     }
 
-    fn render(&mut self, _gl: &glow::Context, _dt: Duration) {
+    fn render(&mut self, _gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
         let gl = &self.gl;
         unsafe {
-            gl.clear_color(0.05, 0.05, 0.1, 1.0);
             gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
         if self.render_cubes {
@@ -149,11 +298,12 @@ impl GuiScene for CollisionScene {
                 sphere.render(&self.camera.borrow_mut());
             }
         }
+        Ok(())
     }
 
     fn render_ui(&mut self, ui: &mut imgui::Ui) {
         ui.window("Collisions")
-            .size([300.0, 200.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 320.0], imgui::Condition::FirstUseEver)
             .position([400.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("Current hits: {}", self.collisions.len(),));
@@ -162,7 +312,18 @@ impl GuiScene for CollisionScene {
                     self.sma_collision_check_time.get()
                 ));
                 ui.separator();
-                ui.text("Cube position");
+                ui.text_wrapped(
+                    "Drag the sphere to move it, drag elsewhere to orbit, scroll to zoom",
+                );
+                if ui.button("Focus sphere") {
+                    self.orbit.pivot = self.sphere.position;
+                }
+                ui.same_line();
+                if ui.button("Reset camera") {
+                    self.orbit = default_orbit(self.world.borrow().get_size());
+                }
+                ui.separator();
+                ui.text("Sphere position");
                 ui.slider(
                     "x",
                     -1.0,
@@ -186,5 +347,10 @@ impl GuiScene for CollisionScene {
                 ui.checkbox("Render sphere", &mut self.render_sphere);
                 ui.checkbox("Render Contact points", &mut self.render_collision_points);
             });
+        ui.window("Collision scenarios")
+            .size([300.0, 220.0], imgui::Condition::FirstUseEver)
+            .position([400.0, 330.0], imgui::Condition::FirstUseEver)
+            .build(|| self.render_scenario_ui(ui));
+        self.process_mouse(ui);
     }
 }
@@ -0,0 +1,216 @@
+use std::ops::RangeInclusive;
+
+use glam::Vec3;
+
+use crate::voxels::{VoxelWorld, iter_sphere_collision};
+
+/// Radius of the test sphere the scenarios are laid out for
+pub const SCENARIO_RADIUS: f32 = 0.49;
+// Height of the sphere center above a face it rests on: Slightly penetrating, but touching only
+// the voxels right below it
+const RESTING_HEIGHT: f32 = 0.4;
+// Minimum dot product between a contact normal & the expected one
+const NORMAL_TOLERANCE: f32 = 0.99;
+
+/// Outcome expected from the collision module along a scenario path
+#[derive(Debug, Clone)]
+pub enum Expectation {
+    /// Overlap test at the point of the path given as progress from 0 to 1. Every contact normal
+    /// has to point along normal, if given
+    Contacts {
+        at: f32,
+        count: RangeInclusive<usize>,
+        normal: Option<Vec3>,
+    },
+    /// Sphere cast from the start to the end of the path has to hit a face with this normal
+    SweptHit { normal: Vec3 },
+}
+
+/// Result of a single expectation, shown next to the scenario
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub label: String,
+    pub passed: bool,
+}
+
+/// Scripted movement of the test sphere through the cubic test chunk, which is solid from -0.5
+/// to 15.5 on every axis
+#[derive(Debug, Clone)]
+pub struct CollisionScenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub start: Vec3,
+    pub end: Vec3,
+    /// Seconds per pass along the path
+    pub duration: f32,
+    /// Positions are snapped to this many steps, like a fast body that moves far per tick
+    pub steps: Option<u32>,
+    pub expectations: Vec<Expectation>,
+}
+
+impl CollisionScenario {
+    pub fn all() -> Vec<CollisionScenario> {
+        let top = 15.5 + RESTING_HEIGHT;
+        let corner = Vec3::splat(15.5);
+        vec![
+            CollisionScenario {
+                name: "Corner",
+                description: "Approaches the top corner diagonally. Only the corner voxel is hit",
+                start: corner + Vec3::splat(2.0),
+                end: corner + Vec3::splat(0.2),
+                duration: 3.0,
+                steps: None,
+                expectations: vec![
+                    Expectation::Contacts {
+                        at: 0.0,
+                        count: 0..=0,
+                        normal: None,
+                    },
+                    Expectation::Contacts {
+                        at: 1.0,
+                        count: 1..=1,
+                        normal: Some(Vec3::ONE.normalize()),
+                    },
+                ],
+            },
+            CollisionScenario {
+                name: "Seam crossing",
+                description: "Rolls diagonally over the top face, across the corners of 4 voxels. \
+                              Seams must not produce sideways normals",
+                start: Vec3::new(2.0, top, 2.0),
+                end: Vec3::new(4.0, top, 4.0),
+                duration: 4.0,
+                steps: None,
+                expectations: [(0.0, 1), (0.25, 4), (0.5, 1), (0.75, 4), (1.0, 1)]
+                    .into_iter()
+                    .map(|(at, count)| Expectation::Contacts {
+                        at,
+                        count: count..=count,
+                        normal: Some(Vec3::Y),
+                    })
+                    .collect(),
+            },
+            CollisionScenario {
+                name: "Tunneling",
+                description: "Falls through the chunk in a single step. Overlap tests at both \
+                              ends miss the chunk, a sphere cast has to catch the top face",
+                start: Vec3::new(7.0, 25.0, 7.0),
+                end: Vec3::new(7.0, -10.0, 7.0),
+                duration: 2.0,
+                steps: Some(1),
+                expectations: vec![
+                    Expectation::Contacts {
+                        at: 0.0,
+                        count: 0..=0,
+                        normal: None,
+                    },
+                    Expectation::Contacts {
+                        at: 1.0,
+                        count: 0..=0,
+                        normal: None,
+                    },
+                    Expectation::SweptHit { normal: Vec3::Y },
+                ],
+            },
+            CollisionScenario {
+                name: "Wall slide",
+                description: "Slides along the +X wall while pressed into it. Contacts keep \
+                              pointing out of the wall, also across seams",
+                start: Vec3::new(15.5 + RESTING_HEIGHT, 8.0, 3.0),
+                end: Vec3::new(15.5 + RESTING_HEIGHT, 8.0, 12.0),
+                duration: 4.0,
+                steps: None,
+                expectations: [(0.0, 1), (0.5, 2), (1.0, 1)]
+                    .into_iter()
+                    .map(|(at, count)| Expectation::Contacts {
+                        at,
+                        count: count..=count,
+                        normal: Some(Vec3::X),
+                    })
+                    .collect(),
+            },
+        ]
+    }
+
+    /// Position of the sphere at progress from 0 to 1
+    pub fn position(&self, progress: f32) -> Vec3 {
+        let progress = progress.clamp(0.0, 1.0);
+        let progress = match self.steps {
+            Some(steps) => (progress * steps as f32).floor() / steps as f32,
+            None => progress,
+        };
+        self.start.lerp(self.end, progress)
+    }
+
+    /// Runs every expectation against the world
+    pub fn evaluate(&self, world: &VoxelWorld) -> Vec<CheckResult> {
+        self.expectations
+            .iter()
+            .map(|expectation| self.check(world, expectation))
+            .collect()
+    }
+
+    fn check(&self, world: &VoxelWorld, expectation: &Expectation) -> CheckResult {
+        match expectation {
+            Expectation::Contacts { at, count, normal } => {
+                let contacts: Vec<_> =
+                    iter_sphere_collision(world, self.position(*at), SCENARIO_RADIUS).collect();
+                let normals_match = normal.is_none_or(|expected| {
+                    contacts
+                        .iter()
+                        .all(|contact| contact.normal.dot(expected) > NORMAL_TOLERANCE)
+                });
+                CheckResult {
+                    label: format!(
+                        "{:.0}%: {} contacts, expected {count:?}",
+                        at * 100.0,
+                        contacts.len()
+                    ),
+                    passed: count.contains(&contacts.len()) && normals_match,
+                }
+            }
+            Expectation::SweptHit { normal } => {
+                let path = self.end - self.start;
+                let hit = world.query_sphere_cast(
+                    self.start,
+                    SCENARIO_RADIUS,
+                    path.normalize(),
+                    path.length(),
+                );
+                let label = match hit {
+                    Some(hit) => format!("Sweep hit with normal {:.2}", hit.normal),
+                    None => "Sweep missed".to_string(),
+                };
+                CheckResult {
+                    label,
+                    passed: hit.is_some_and(|hit| hit.normal.dot(*normal) > NORMAL_TOLERANCE),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenarios_pass() {
+        let world = VoxelWorld::new_cubic(1);
+        for scenario in CollisionScenario::all() {
+            for result in scenario.evaluate(&world) {
+                assert!(result.passed, "{}: {}", scenario.name, result.label);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stepped_path_skips_the_middle() {
+        let scenario = CollisionScenario::all()
+            .into_iter()
+            .find(|scenario| scenario.steps.is_some())
+            .unwrap();
+        assert_eq!(scenario.position(0.6), scenario.start);
+        assert_eq!(scenario.position(1.0), scenario.end);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    error::Error,
+    fs::{self, File, create_dir_all},
+    io::BufWriter,
+    path::Path,
+};
+
+use glam::Vec3;
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::renderer::frame_uniforms::{Light, MAX_LIGHTS, SceneLighting};
+
+/// Rig loaded by the lighting scene on start, if it exists
+pub const DEFAULT_LIGHT_RIG_PATH: &str = "assets/light_rigs/default.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RigLightKind {
+    Point,
+    /// Cone around direction, half_angle in degrees
+    Spot {
+        direction: Vec3,
+        half_angle: f32,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RigLight {
+    pub kind: RigLightKind,
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl RigLight {
+    fn point(position: Vec3) -> RigLight {
+        Self {
+            kind: RigLightKind::Point,
+            position,
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+
+    fn spot(position: Vec3) -> RigLight {
+        Self {
+            kind: RigLightKind::Spot {
+                direction: -position,
+                half_angle: 25.0,
+            },
+            ..Self::point(position)
+        }
+    }
+
+    fn light(&self) -> Light {
+        let color = self.color * self.intensity;
+        match self.kind {
+            RigLightKind::Point => Light::point(self.position, color),
+            RigLightKind::Spot {
+                direction,
+                half_angle,
+            } => Light::spot(self.position, direction, half_angle.to_radians(), color),
+        }
+    }
+}
+
+/// Editable set of lights, saved as json to reproduce lighting setups
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LightRig {
+    pub ambient: Vec3,
+    pub lights: Vec<RigLight>,
+}
+
+impl Default for LightRig {
+    fn default() -> Self {
+        Self {
+            ambient: Vec3::splat(0.2),
+            lights: vec![RigLight::point(Vec3::new(5.0, 5.0, 5.0))],
+        }
+    }
+}
+
+impl LightRig {
+    pub fn load(path: &Path) -> Result<LightRig, Box<dyn Error>> {
+        let rig: LightRig = serde_json::from_slice(&fs::read(path)?)?;
+        if rig.lights.len() > MAX_LIGHTS {
+            return Err(format!(
+                "Rig has {} lights, shaders support up to {MAX_LIGHTS}",
+                rig.lights.len()
+            )
+            .into());
+        }
+        Ok(rig)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn scene_lighting(&self) -> SceneLighting {
+        SceneLighting {
+            ambient: self.ambient,
+            lights: self.lights.iter().map(RigLight::light).collect(),
+        }
+    }
+
+    /// Editor controls for ambient light & every light of the rig
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.color_edit3("Ambient", self.ambient.as_mut());
+        let can_add = self.lights.len() < MAX_LIGHTS;
+        ui.disabled(!can_add, || {
+            if ui.button("Add point light") {
+                self.lights.push(RigLight::point(Vec3::new(0.0, 5.0, 5.0)));
+            }
+            ui.same_line();
+            if ui.button("Add spot light") {
+                self.lights.push(RigLight::spot(Vec3::new(0.0, 8.0, 0.0)));
+            }
+        });
+        let mut removed = None;
+        for (index, light) in self.lights.iter_mut().enumerate() {
+            let _id = ui.push_id_usize(index);
+            let label = match light.kind {
+                RigLightKind::Point => format!("Point light {index}"),
+                RigLightKind::Spot { .. } => format!("Spot light {index}"),
+            };
+            if !ui.collapsing_header(&label, imgui::TreeNodeFlags::DEFAULT_OPEN) {
+                continue;
+            }
+            ui.input_float3("Position", light.position.as_mut()).build();
+            ui.color_edit3("Color", light.color.as_mut());
+            ui.slider("Intensity", 0.0, 4.0, &mut light.intensity);
+            if let RigLightKind::Spot {
+                direction,
+                half_angle,
+            } = &mut light.kind
+            {
+                ui.input_float3("Direction", direction.as_mut()).build();
+                ui.slider("Half angle", 1.0, 89.0, half_angle);
+                if ui.button("Aim at origin") {
+                    *direction = -light.position;
+                }
+                ui.same_line();
+            }
+            if ui.button("Remove") {
+                removed = Some(index);
+            }
+        }
+        if let Some(index) = removed {
+            self.lights.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rig_round_trip() {
+        let path = std::env::temp_dir().join("voxie_test_light_rig.json");
+        let mut rig = LightRig::default();
+        rig.lights.push(RigLight::spot(Vec3::new(1.0, 4.0, 0.0)));
+        rig.lights[0].intensity = 2.0;
+        rig.save(&path).unwrap();
+        assert_eq!(LightRig::load(&path).unwrap(), rig);
+
+        let lighting = rig.scene_lighting();
+        assert_eq!(lighting.lights.len(), 2);
+        assert_eq!(lighting.lights[0].color, Vec3::splat(2.0));
+        assert!(
+            lighting
+                .lights
+                .iter()
+                .all(|light| light.direction().is_none())
+        );
+
+        rig.lights = vec![RigLight::point(Vec3::ZERO); MAX_LIGHTS + 1];
+        rig.save(&path).unwrap();
+        assert!(LightRig::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
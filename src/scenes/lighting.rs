@@ -1,9 +1,15 @@
-use std::{cell::RefCell, error::Error, rc::Rc, time::Duration};
+use std::{
+    cell::RefCell,
+    error::Error,
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use glam::{Mat4, Vec3};
-use glow::HasContext;
-use hecs::World;
-use log::error;
+use hecs::{Entity, World};
+use log::{error, info};
+use winit::event::MouseButton;
 
 use crate::{
     cameras::{
@@ -11,12 +17,27 @@ use crate::{
         orbit::BlenderOrbitCamera,
     },
     input::InputState,
+    renderer::{
+        ecs_renderer::{ECSRenderer, MESH_CUBE, MESH_SPHERE, RenderColor, RenderMeshHandle},
+        material::{Material, ShaderHandle, TextureHandle},
+        shader::Shader,
+        texture::Texture,
+    },
     scenes::GuiScene,
     systems::physics::Transform,
     voxie::player::squid::spawn_squid,
 };
 
-use super::scene::BaseScene;
+use super::{
+    light_rig::{DEFAULT_LIGHT_RIG_PATH, LightRig},
+    scene::BaseScene,
+};
+
+// Diameter of the spheres marking the lights of the rig
+const GIZMO_SIZE: f32 = 0.3;
+// Lit by the rig, textured or with flat color
+const SHADER_DIFFUSE: ShaderHandle = 1;
+const TEXTURE_DIRT: TextureHandle = 0;
 
 /// Used to debug & visualize lighting shaders & algorithms
 pub struct LightingScene {
@@ -24,11 +45,20 @@ pub struct LightingScene {
     input_state: Rc<RefCell<InputState>>,
     last_mouse_position: (f32, f32),
     world: World,
+    // Own renderer, so the lights of the rig reach the frame uniforms
+    ecs_renderer: ECSRenderer,
+    started_at: Instant,
+    rig: LightRig,
+    rig_path: String,
+    // Result of the last save or load
+    rig_status: String,
+    // Sphere marking each light of the rig, in the same order
+    gizmos: Vec<Entity>,
 }
 
 impl LightingScene {
     pub fn new(
-        _gl: &Rc<glow::Context>,
+        gl: &Rc<glow::Context>,
         input_state: Rc<RefCell<InputState>>,
     ) -> Result<LightingScene, Box<dyn Error>> {
         let mut world = World::new();
@@ -36,20 +66,117 @@ impl LightingScene {
         // Spawn something to look at
         let player_pos = Vec3::ZERO;
         spawn_squid(&mut world, player_pos);
+        // Textured floor & a flat colored block sharing its shader
+        let floor =
+            Material::new()
+                .with_shader(SHADER_DIFFUSE)
+                .with_texture(0, "diffuseMap", TEXTURE_DIRT);
+        world.spawn((
+            Transform(
+                Mat4::from_translation(Vec3::NEG_Y * 3.0)
+                    * Mat4::from_scale(Vec3::new(12.0, 0.5, 12.0)),
+            ),
+            RenderMeshHandle(MESH_CUBE),
+            floor,
+        ));
+        world.spawn((
+            Transform(Mat4::from_translation(Vec3::new(4.0, -2.0, 0.0))),
+            RenderMeshHandle(MESH_CUBE),
+            Material::new()
+                .with_shader(SHADER_DIFFUSE)
+                .with_color(Vec3::new(0.2, 0.4, 0.8)),
+        ));
 
         // Setup camera
         spawn_camera(&mut world, Mat4::IDENTITY);
         let cam = BlenderOrbitCamera::new(Vec3::ZERO, 15.0);
 
+        let rig_path = DEFAULT_LIGHT_RIG_PATH.to_string();
+        let (rig, rig_status) = match Path::new(&rig_path).exists() {
+            true => match LightRig::load(Path::new(&rig_path)) {
+                Ok(rig) => (rig, format!("Loaded {rig_path}")),
+                Err(err) => (
+                    LightRig::default(),
+                    format!("Could not load {rig_path}: {err}"),
+                ),
+            },
+            false => (LightRig::default(), String::new()),
+        };
+
+        let mut ecs_renderer = ECSRenderer::new(gl)?;
+        ecs_renderer.add_shader(
+            SHADER_DIFFUSE,
+            Shader::new(
+                gl,
+                "assets/shaders/cube.vert",
+                "assets/shaders/cube-diffuse.frag",
+            )?,
+        );
+        ecs_renderer.add_texture(
+            TEXTURE_DIRT,
+            Texture::new(gl, Path::new("assets/textures/dirt.png"))?,
+        );
+
         Ok(Self {
             cam,
             input_state,
             last_mouse_position: (0.0, 0.0),
             world,
+            ecs_renderer,
+            started_at: Instant::now(),
+            rig,
+            rig_path,
+            rig_status,
+            gizmos: Vec::new(),
         })
     }
 
-    // Orbit camera around origin
+    // Matches the light gizmos to the rig, which may have changed in the editor
+    fn sync_gizmos(&mut self) {
+        while self.gizmos.len() > self.rig.lights.len() {
+            if let Some(entity) = self.gizmos.pop() {
+                let _ = self.world.despawn(entity);
+            }
+        }
+        for (index, light) in self.rig.lights.iter().enumerate() {
+            let transform = Transform(
+                Mat4::from_translation(light.position) * Mat4::from_scale(Vec3::splat(GIZMO_SIZE)),
+            );
+            let color = RenderColor(light.color);
+            match self.gizmos.get(index) {
+                Some(entity) => {
+                    let _ = self.world.insert(*entity, (transform, color));
+                }
+                None => {
+                    let entity =
+                        self.world
+                            .spawn((transform, color, RenderMeshHandle(MESH_SPHERE)));
+                    self.gizmos.push(entity);
+                }
+            }
+        }
+    }
+
+    fn save_rig(&mut self) {
+        self.rig_status = match self.rig.save(Path::new(&self.rig_path)) {
+            Ok(()) => format!("Saved {}", self.rig_path),
+            Err(err) => format!("Could not save {}: {err}", self.rig_path),
+        };
+        info!("{}", self.rig_status);
+    }
+
+    fn load_rig(&mut self) {
+        self.rig_status = match LightRig::load(Path::new(&self.rig_path)) {
+            Ok(rig) => {
+                self.rig = rig;
+                format!("Loaded {}", self.rig_path)
+            }
+            Err(err) => format!("Could not load {}: {err}", self.rig_path),
+        };
+        info!("{}", self.rig_status);
+    }
+
+    // Orbit camera around origin while the right mouse button is held
     fn process_mouse_movement(&mut self) {
         let input_state = self.input_state.borrow();
         let current = input_state.get_mouse_position_f32();
@@ -58,6 +185,9 @@ impl LightingScene {
             self.last_mouse_position.1 - current.1,
         );
         self.last_mouse_position = current;
+        if !input_state.is_mouse_button_pressed(&MouseButton::Right) {
+            return;
+        }
 
         if let Some((_entity, (_cam, transform))) = self
             .world
@@ -78,13 +208,16 @@ impl BaseScene for LightingScene {
         "Lighting Test".to_string()
     }
 
-    fn tick(&mut self, _dt: f32) {
+    fn tick(&mut self, _dt: f32) -> Result<(), Box<dyn Error>> {
         self.process_mouse_movement();
+        Ok(())
     }
 
-    fn start(&mut self) {}
+    fn on_enter(&mut self) {}
+    fn on_exit(&mut self) {}
+    // Rendered by the scene itself, so lights can be edited
     fn get_world(&self) -> Option<&hecs::World> {
-        Some(&self.world)
+        None
     }
 }
 impl GuiScene for LightingScene {
@@ -92,7 +225,33 @@ impl GuiScene for LightingScene {
         todo!()
     }
 
-    fn render(&mut self, _gl: &glow::Context, _dt: Duration) {}
+    fn render(&mut self, _gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
+        self.sync_gizmos();
+        self.ecs_renderer.lighting = self.rig.scene_lighting();
+        self.ecs_renderer
+            .render(&self.world, self.started_at.elapsed().as_secs_f32());
+        Ok(())
+    }
 
-    fn render_ui(&mut self, _ui: &mut imgui::Ui) {}
+    fn render_ui(&mut self, ui: &mut imgui::Ui) {
+        ui.window("Light rig")
+            .size([320.0, 480.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 0.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text("Hold the right mouse button to orbit");
+                ui.input_text("File", &mut self.rig_path).build();
+                if ui.button("Save") {
+                    self.save_rig();
+                }
+                ui.same_line();
+                if ui.button("Load") {
+                    self.load_rig();
+                }
+                if !self.rig_status.is_empty() {
+                    ui.text_wrapped(&self.rig_status);
+                }
+                ui.separator();
+                self.rig.render_ui(ui);
+            });
+    }
 }
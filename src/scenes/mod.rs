@@ -1,8 +1,14 @@
 #[cfg(feature = "gui")]
 pub mod benchmark;
 #[cfg(feature = "gui")]
+pub mod chunk_storage;
+#[cfg(feature = "gui")]
 pub mod collision;
 #[cfg(feature = "gui")]
+pub mod collision_scenarios;
+#[cfg(feature = "gui")]
+pub mod light_rig;
+#[cfg(feature = "gui")]
 pub mod lighting;
 pub mod scene;
 
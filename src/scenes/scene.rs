@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{error::Error, time::Duration};
 
 use hecs::World;
 
@@ -12,14 +12,56 @@ pub trait BaseScene {
     /// If returns ECS, simple single-pass ecs render pipeline will be used
     fn get_world(&self) -> Option<&World>;
     fn get_title(&self) -> String;
-    fn tick(&mut self, dt: f32);
-    // Perform any initialization logic the scene might need
-    fn start(&mut self);
+    /// Errors are shown by the application, which stops ticking the scene until dismissed
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>>;
+    /// Called once the scene becomes active, right before its first tick
+    fn on_enter(&mut self);
+    /// Called before the scene is replaced or the application exits. The scene is dropped right
+    /// after, so GPU resources & connections should not outlive this call
+    fn on_exit(&mut self);
+    /// Called when the window loses focus or is minimized. Ticks continue at a lower wake-up
+    /// rate, unless the player chose to pause local scenes in the background
+    fn on_pause(&mut self) {}
+    /// Called when the window regains focus after [BaseScene::on_pause]
+    fn on_resume(&mut self) {}
+    /// Networked scenes keep ticking in the background, since the server does not wait for them
+    fn is_networked(&self) -> bool {
+        false
+    }
+    /// Called periodically by headless simulation with the achieved tick rate in Hz
+    fn on_tick_rate_report(&mut self, _ticks_per_second: f32) {}
 }
 
 #[cfg(feature = "gui")]
 pub trait GuiScene: BaseScene {
     fn get_stats(&self) -> super::SceneStats;
-    fn render(&mut self, gl: &glow::Context, dt: Duration);
+    fn render(&mut self, gl: &glow::Context, dt: Duration) -> Result<(), Box<dyn Error>>;
     fn render_ui(&mut self, ui: &mut imgui::Ui);
+    /// GL state applied by the application right before [GuiScene::render]
+    fn render_state(&self) -> crate::renderer::render_state::RenderState {
+        crate::renderer::render_state::RenderState::default()
+    }
+    /// Called on scene start & whenever render settings change.
+    /// Scenes owning offscreen render targets should recreate them here
+    fn apply_render_settings(
+        &mut self,
+        _settings: &crate::renderer::settings::RenderSettings,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+    /// Scene specific values for the compact stats overlay
+    fn overlay_stats(&self) -> crate::renderer::metrics::OverlayStats {
+        crate::renderer::metrics::OverlayStats::default()
+    }
+    /// Scene state dumped when a frame takes unusually long
+    fn diagnostics(&self) -> crate::renderer::spikes::SceneDiagnostics {
+        crate::renderer::spikes::SceneDiagnostics::default()
+    }
+    /// Called with files below the asset directory that changed on disk.
+    /// Scenes should re-upload meshes & textures they loaded from any of them
+    fn reload_assets(&mut self, _changed: &[std::path::PathBuf]) {}
+    /// Scene to switch to, e.g. once a server was picked in a menu. Checked after every frame
+    fn take_next_scene(&mut self) -> Option<Box<dyn GuiScene>> {
+        None
+    }
 }
@@ -0,0 +1,220 @@
+use glam::{Mat4, Quat, Vec3};
+use hecs::World;
+use log::debug;
+
+use crate::{
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, RenderColor},
+    },
+    systems::{
+        physics::{Transform, Velocity},
+        projectiles::Lifetime,
+    },
+    voxels::VoxelWorld,
+};
+
+pub const TRACER_DURATION: f32 = 0.15;
+pub const DECAL_LIFETIME: f32 = 10.0;
+pub const MAX_DECALS: usize = 64;
+// Decals are searched for this far behind the impact, explosions dig craters
+const DECAL_SEARCH_DISTANCE: f32 = 8.0;
+const DUST_LIFETIME: f32 = 0.35;
+const DUST_COLOR: Vec3 = Vec3::new(0.55, 0.45, 0.35);
+
+/// Short-lived line from muzzle to impact. Fades out with its Lifetime
+pub struct Tracer {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub duration: f32,
+    // Set once the impact decal was placed
+    pub decal_placed: bool,
+}
+
+impl Tracer {
+    pub fn alpha(&self, remaining: f32) -> f32 {
+        (remaining / self.duration).clamp(0.0, 1.0)
+    }
+}
+
+pub fn spawn_tracer(world: &mut World, start: Vec3, end: Vec3) {
+    world.spawn((
+        Tracer {
+            start,
+            end,
+            duration: TRACER_DURATION,
+            decal_placed: false,
+        },
+        Lifetime(TRACER_DURATION),
+    ));
+}
+
+/// Ring of small particles drifting outwards & up from the position, e.g. footsteps & landings
+pub fn spawn_dust_puff(world: &mut World, position: Vec3, count: usize, speed: f32) {
+    for index in 0..count {
+        let angle = index as f32 / count as f32 * std::f32::consts::TAU;
+        let direction = Vec3::new(angle.cos(), 0.5, angle.sin()).normalize();
+        let transform =
+            Mat4::from_scale_rotation_translation(Vec3::splat(0.1), Quat::IDENTITY, position);
+        world.spawn((
+            Transform(transform),
+            Velocity(direction * speed),
+            Lifetime(DUST_LIFETIME),
+            RenderMeshHandle(MESH_CUBE),
+            RenderColor(DUST_COLOR),
+        ));
+    }
+}
+
+/// Scorch mark lying flat on a voxel face
+#[derive(Debug, Clone, Copy)]
+pub struct Decal {
+    pub position: Vec3,
+    /// Axis aligned normal of the voxel face
+    pub normal: Vec3,
+    pub age: f32,
+}
+
+/// Fixed amount of decals. Oldest ones are overwritten once full
+pub struct DecalRing {
+    decals: Vec<Decal>,
+    capacity: usize,
+    // Slot overwritten next once full
+    next: usize,
+    pub lifetime: f32,
+}
+
+impl DecalRing {
+    pub fn new(capacity: usize) -> DecalRing {
+        Self {
+            decals: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+            lifetime: DECAL_LIFETIME,
+        }
+    }
+
+    pub fn push(&mut self, position: Vec3, normal: Vec3) {
+        let decal = Decal {
+            position,
+            normal,
+            age: 0.0,
+        };
+        if self.decals.len() < self.capacity {
+            self.decals.push(decal);
+        } else {
+            self.decals[self.next] = decal;
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    /// Ages decals & drops faded ones
+    pub fn tick(&mut self, dt: f32) {
+        for decal in self.decals.iter_mut() {
+            decal.age += dt;
+        }
+        let lifetime = self.lifetime;
+        if self.decals.iter().any(|decal| decal.age >= lifetime) {
+            // Decals are ordered by age starting at next. Keep that order when removing
+            self.decals.rotate_left(self.next);
+            self.decals.retain(|decal| decal.age < lifetime);
+            self.next = 0;
+        }
+    }
+
+    /// Decals with their opacity
+    pub fn iter(&self) -> impl Iterator<Item = (&Decal, f32)> {
+        self.decals
+            .iter()
+            .map(|decal| (decal, 1.0 - decal.age / self.lifetime))
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.decals.len()
+    }
+}
+
+/// Places decals where tracers hit the world. Has to run after voxel edits were applied, so
+/// decals end up on the crater instead of faces that were just blown away
+pub fn system_impact_decals(world: &mut World, voxel_world: &VoxelWorld, decals: &mut DecalRing) {
+    for (_entity, tracer) in world.query_mut::<&mut Tracer>() {
+        if tracer.decal_placed {
+            continue;
+        }
+        tracer.decal_placed = true;
+        let Some(direction) = (tracer.end - tracer.start).try_normalize() else {
+            continue;
+        };
+        // Start a bit in front of the impact, the projectile collided with its radius
+        let origin = tracer.end - direction;
+        let Some(hit) =
+            voxel_world.query_sphere_cast(origin, 0.05, direction, DECAL_SEARCH_DISTANCE)
+        else {
+            debug!("No surface found for impact decal at {}", tracer.end);
+            continue;
+        };
+        decals.push(hit.contact_point + direction * 0.05, hit.normal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::projectiles::system_lifetime;
+
+    use super::*;
+
+    #[test]
+    fn test_decal_ring_overwrites_oldest() {
+        let mut decals = DecalRing::new(2);
+        decals.push(Vec3::X, Vec3::Y);
+        decals.tick(1.0);
+        decals.push(Vec3::Y, Vec3::Y);
+        decals.tick(1.0);
+        decals.push(Vec3::Z, Vec3::Y);
+        assert_eq!(decals.len(), 2);
+        let positions: Vec<Vec3> = decals.iter().map(|(decal, _)| decal.position).collect();
+        assert!(!positions.contains(&Vec3::X));
+        assert!(positions.contains(&Vec3::Y) && positions.contains(&Vec3::Z));
+    }
+
+    #[test]
+    fn test_decals_fade_out() {
+        let mut decals = DecalRing::new(4);
+        decals.push(Vec3::X, Vec3::Y);
+        decals.tick(DECAL_LIFETIME / 2.0);
+        decals.push(Vec3::Y, Vec3::Y);
+        let alpha: Vec<f32> = decals.iter().map(|(_, alpha)| alpha).collect();
+        assert_eq!(alpha, vec![0.5, 1.0]);
+
+        decals.tick(DECAL_LIFETIME / 2.0);
+        assert_eq!(decals.len(), 1);
+        // Freed slot is reused before overwriting
+        decals.push(Vec3::Z, Vec3::Y);
+        assert_eq!(decals.len(), 2);
+    }
+
+    #[test]
+    fn test_impact_decal_on_voxel_face() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        let mut world = World::new();
+        let mut decals = DecalRing::new(MAX_DECALS);
+        // Shot straight down onto the top of the world
+        spawn_tracer(
+            &mut world,
+            Vec3::new(4.2, 30.0, 4.7),
+            Vec3::new(4.2, 16.0, 4.7),
+        );
+        system_impact_decals(&mut world, &voxel_world, &mut decals);
+        // Only placed once
+        system_impact_decals(&mut world, &voxel_world, &mut decals);
+        assert_eq!(decals.len(), 1);
+        let (decal, _) = decals.iter().next().unwrap();
+        assert_eq!(decal.normal, Vec3::Y);
+        // Top face of the topmost voxel layer
+        assert!((decal.position.y - 15.5).abs() < 0.1, "{}", decal.position);
+
+        system_lifetime(&mut world, TRACER_DURATION).unwrap();
+        assert_eq!(world.query::<&Tracer>().iter().count(), 0);
+    }
+}
@@ -1,22 +1,35 @@
 use glam::{Mat4, Vec3, Vec4Swizzles};
-use hecs::World;
+use hecs::{Entity, World};
 use log::debug;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     command_queue::{Command, CommandQueue},
     systems::physics::Transform,
+    util::SeededRng,
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct Gun {
     // Remaining cooldown in s until we can fire again
     pub cooldown: f32,
     // Projectiles per s
     pub fire_rate: f32,
     pub triggered: bool,
+    // Max angle in radians between a shot & the aim direction
+    #[serde(default)]
+    pub spread: f32,
 }
 
-pub fn system_gun_fire(world: &mut World, command_queue: &mut CommandQueue, dt: f32) {
-    for (_entity, (transform_component, gun)) in world.query_mut::<(&Transform, &mut Gun)>() {
+/// Queues projectiles of triggered guns. Returns the entities that fired
+pub fn system_gun_fire(
+    world: &mut World,
+    command_queue: &mut CommandQueue,
+    rng: &mut SeededRng,
+    dt: f32,
+) -> Vec<Entity> {
+    let mut fired = Vec::new();
+    for (entity, (transform_component, gun)) in world.query_mut::<(&Transform, &mut Gun)>() {
         gun.cooldown = 0.0f32.max(gun.cooldown - dt);
         if !gun.triggered {
             continue;
@@ -24,10 +37,10 @@ pub fn system_gun_fire(world: &mut World, command_queue: &mut CommandQueue, dt:
         gun.triggered = false;
         if gun.cooldown > 0.0 {
             debug!("Reloading! {}ms cooldown remaining", gun.cooldown * 1e3);
-            return;
+            return fired;
         }
         let transform = transform_component.0;
-        let forward = (-transform.z_axis.xyz()).normalize();
+        let forward = scatter((-transform.z_axis.xyz()).normalize(), gun.spread, rng);
         let mut projectile_transform = transform;
         // Offset toward front of player
         projectile_transform.w_axis.x += forward.x * 2.0;
@@ -43,5 +56,19 @@ pub fn system_gun_fire(world: &mut World, command_queue: &mut CommandQueue, dt:
             velocity,
         });
         gun.cooldown = 1.0 / gun.fire_rate;
+        fired.push(entity);
     }
+    fired
+}
+
+// Random direction within the cone of the given half angle around forward
+fn scatter(forward: Vec3, half_angle: f32, rng: &mut SeededRng) -> Vec3 {
+    if half_angle <= 0.0 {
+        return forward;
+    }
+    let (side, up) = forward.any_orthonormal_pair();
+    let angle = rng.next_f32() * std::f32::consts::TAU;
+    // Square root spreads shots evenly over the cone
+    let offset = rng.next_f32().sqrt() * half_angle.tan();
+    (forward + (side * angle.cos() + up * angle.sin()) * offset).normalize()
 }
@@ -1,8 +1,13 @@
 #[cfg(feature = "gui")]
+pub mod effects;
+#[cfg(feature = "gui")]
 pub mod gun;
+#[cfg(feature = "gui")]
+pub mod names;
 pub mod physics;
 #[cfg(feature = "gui")]
 pub mod projectiles;
+pub mod serialization;
 #[cfg(feature = "gui")]
 pub mod skybox;
 #[cfg(feature = "gui")]
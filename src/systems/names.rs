@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+use hecs::{Entity, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{physics::Transform, serialization::ComponentRegistry};
+
+/// Name of the first local player & tag of all local players
+pub const PLAYER: &str = "player";
+
+/// Unique name to look an entity up by, e.g. "player"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Name(pub String);
+
+impl Name {
+    pub fn new(name: &str) -> Name {
+        Name(name.to_string())
+    }
+}
+
+/// Groups an entity belongs to, e.g. "enemy". Unlike names, tags are shared
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tags(pub Vec<String>);
+
+impl Tags {
+    pub fn new(tags: &[&str]) -> Tags {
+        Tags(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Name>("Name");
+    registry.register::<Tags>("Tags");
+}
+
+/// Lookup of entities by [Name] & [Tags]. Rebuilt once per tick, so entities spawned or renamed
+/// since are found after the next [EntityRegistry::sync]. Despawned entities are never returned
+#[derive(Default)]
+pub struct EntityRegistry {
+    names: HashMap<String, Entity>,
+    // Sorted by id, so the order does not depend on archetypes
+    tags: HashMap<String, Vec<Entity>>,
+}
+
+impl EntityRegistry {
+    pub fn new() -> EntityRegistry {
+        Self::default()
+    }
+
+    pub fn from_world(world: &World) -> EntityRegistry {
+        let mut registry = Self::new();
+        registry.sync(world);
+        registry
+    }
+
+    pub fn sync(&mut self, world: &World) {
+        self.names.clear();
+        self.tags.clear();
+        for (entity, name) in world.query::<&Name>().iter() {
+            let existing = self.names.entry(name.0.clone()).or_insert(entity);
+            if *existing != entity {
+                // Oldest entity keeps the name
+                debug!(
+                    "Entities {existing:?} & {entity:?} share the name '{}'",
+                    name.0
+                );
+                if entity.id() < existing.id() {
+                    *existing = entity;
+                }
+            }
+        }
+        for (entity, tags) in world.query::<&Tags>().iter() {
+            for tag in &tags.0 {
+                self.tags.entry(tag.clone()).or_default().push(entity);
+            }
+        }
+        for entities in self.tags.values_mut() {
+            entities.sort_by_key(|entity| entity.id());
+        }
+    }
+
+    pub fn get(&self, world: &World, name: &str) -> Option<Entity> {
+        self.names
+            .get(name)
+            .copied()
+            .filter(|entity| world.contains(*entity))
+    }
+
+    pub fn tagged<'a>(&'a self, world: &'a World, tag: &str) -> impl Iterator<Item = Entity> + 'a {
+        self.tags
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|entity| world.contains(*entity))
+    }
+
+    /// World space position of the named entity
+    pub fn position(&self, world: &World, name: &str) -> Option<Vec3> {
+        let entity = self.get(world, name)?;
+        let transform = world.get::<&Transform>(entity).ok()?;
+        Some(transform.0.w_axis.truncate())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_name_and_tag() {
+        let mut world = World::new();
+        let player = world.spawn((
+            Name::new(PLAYER),
+            Tags::new(&[PLAYER]),
+            Transform(Mat4::from_translation(Vec3::X)),
+        ));
+        let second = world.spawn((Name::new("player 2"), Tags::new(&[PLAYER])));
+        let enemy = world.spawn((Tags::new(&["enemy"]),));
+
+        let registry = EntityRegistry::from_world(&world);
+        assert_eq!(registry.get(&world, PLAYER), Some(player));
+        assert_eq!(registry.position(&world, PLAYER), Some(Vec3::X));
+        assert_eq!(registry.position(&world, "player 2"), None);
+        assert_eq!(
+            registry.tagged(&world, PLAYER).collect::<Vec<_>>(),
+            vec![player, second]
+        );
+        assert_eq!(registry.tagged(&world, "enemy").next(), Some(enemy));
+        assert_eq!(registry.get(&world, "sun"), None);
+
+        // Despawned entities are dropped right away, even before the next sync
+        world.despawn(player).unwrap();
+        assert_eq!(registry.get(&world, PLAYER), None);
+        assert_eq!(
+            registry.tagged(&world, PLAYER).collect::<Vec<_>>(),
+            vec![second]
+        );
+    }
+}
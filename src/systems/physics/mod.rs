@@ -4,19 +4,31 @@ use hierarchy_cache::HierarchyCache;
 use log::error;
 use serde::{Deserialize, Serialize};
 
+use super::serialization::{ComponentRegistry, entity_bits, remap_entity};
+
 pub mod hierarchy_cache;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 /// Transform in **world** coordinates
 pub struct Transform(pub Mat4);
+#[derive(Serialize, Deserialize)]
 pub struct Velocity(pub Vec3);
 
 /// Transform in **local** coordinates (relative to parent node)
+#[derive(Serialize, Deserialize)]
 pub struct LocalTransform {
     pub local: Mat4,
 }
 
-pub struct Parent(pub hecs::Entity);
+#[derive(Serialize, Deserialize)]
+pub struct Parent(#[serde(with = "entity_bits")] pub hecs::Entity);
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Transform>("Transform");
+    registry.register::<Velocity>("Velocity");
+    registry.register::<LocalTransform>("LocalTransform");
+    registry.register_mapped::<Parent>("Parent", |parent, map| remap_entity(&mut parent.0, map));
+}
 
 // Update hierarchical transforms
 pub fn system_update_world_transforms(world: &mut hecs::World, cache: &mut HierarchyCache) {
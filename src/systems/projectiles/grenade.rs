@@ -0,0 +1,254 @@
+use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
+use hecs::{Entity, NoSuchEntity, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    command_queue::{Command, CommandQueue},
+    renderer::{MESH_PROJECTILE, RenderMeshHandle},
+    systems::physics::Transform,
+    voxels::{VoxelWorld, edits::VoxelEditQueue},
+};
+
+use super::{PROJECTILE_IMPOSTOR, queue_explosion};
+
+/// Downward acceleration of thrown grenades
+pub const GRENADE_GRAVITY: f32 = 20.0;
+pub const GRENADE_RADIUS: f32 = 0.2;
+/// Seconds from the throw until the grenade explodes
+pub const GRENADE_FUSE: f32 = 2.5;
+/// Share of the speed along the surface normal kept per bounce
+const RESTITUTION: f32 = 0.45;
+/// Share of the speed along the surface kept per bounce
+const BOUNCE_FRICTION: f32 = 0.75;
+/// Grenades bouncing off slower than this come to rest
+const REST_SPEED: f32 = 1.0;
+// Gap kept to the surface after a bounce, so the next cast does not start inside the voxel
+const SKIN_WIDTH: f32 = 0.01;
+const THROW_SPEED: f32 = 18.0;
+/// Upwards speed added to the throw, so grenades fly further than aimed
+const THROW_LIFT: f32 = 5.0;
+// Distance in front of the player grenades are released at
+const THROW_OFFSET: f32 = 1.5;
+const THROW_COOLDOWN: f32 = 1.0;
+/// Time step of the arc preview
+const ARC_TIME_STEP: f32 = 0.05;
+
+/// Live grenade. Moved by system_grenades instead of Velocity, it bounces off the voxel world
+#[derive(Serialize, Deserialize)]
+pub struct Grenade {
+    pub velocity: Vec3,
+    /// Remaining seconds until the explosion
+    pub fuse: f32,
+}
+
+/// Throws a grenade when the throw button is released. The arc is previewed while it is held
+#[derive(Serialize, Deserialize, Default)]
+pub struct GrenadeThrower {
+    // Remaining cooldown in s until we can throw again
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub held: bool,
+    // Held during the last tick
+    #[serde(skip)]
+    aiming: bool,
+    /// Predicted path until the first impact while aiming
+    #[serde(skip)]
+    pub arc: Vec<Vec3>,
+}
+
+/// Release position & velocity of a grenade thrown by the player
+pub fn throw_from(transform: &Mat4) -> (Vec3, Vec3) {
+    let forward = (-transform.z_axis.xyz()).normalize();
+    let origin = transform.w_axis.xyz() + forward * THROW_OFFSET;
+    (origin, forward * THROW_SPEED + Vec3::Y * THROW_LIFT)
+}
+
+pub fn spawn_grenade(world: &mut World, position: Vec3, velocity: Vec3) {
+    let transform =
+        Mat4::from_scale_rotation_translation(Vec3::splat(0.4), Quat::IDENTITY, position);
+    world.spawn((
+        Transform(transform),
+        Grenade {
+            velocity,
+            fuse: GRENADE_FUSE,
+        },
+        RenderMeshHandle(MESH_PROJECTILE),
+        PROJECTILE_IMPOSTOR,
+    ));
+    debug!("Grenade thrown from {position} with {velocity}");
+}
+
+/// Advances a grenade by dt. Returns the new position & velocity and whether it bounced
+pub fn step_grenade(
+    voxel_world: &VoxelWorld,
+    position: Vec3,
+    velocity: Vec3,
+    dt: f32,
+) -> (Vec3, Vec3, bool) {
+    let velocity = velocity - Vec3::Y * GRENADE_GRAVITY * dt;
+    let travel = velocity * dt;
+    let distance = travel.length();
+    let Some(direction) = travel.try_normalize() else {
+        return (position, velocity, false);
+    };
+    let Some(hit) = voxel_world.query_sphere_cast(position, GRENADE_RADIUS, direction, distance)
+    else {
+        return (position + travel, velocity, false);
+    };
+    // Cast normals may face either way
+    let normal = if hit.normal.dot(direction) > 0.0 {
+        -hit.normal
+    } else {
+        hit.normal
+    };
+    let position = position + direction * (hit.penetration_depth - SKIN_WIDTH).max(0.0);
+    let normal_velocity = normal * velocity.dot(normal);
+    let mut velocity =
+        (velocity - normal_velocity) * BOUNCE_FRICTION - normal_velocity * RESTITUTION;
+    if velocity.length() < REST_SPEED {
+        velocity = Vec3::ZERO;
+    }
+    (position, velocity, true)
+}
+
+/// Positions of a grenade thrown with the velocity, up to the first impact or the end of its
+/// fuse
+pub fn grenade_arc(voxel_world: &VoxelWorld, origin: Vec3, velocity: Vec3) -> Vec<Vec3> {
+    let mut arc = vec![origin];
+    let (mut position, mut velocity) = (origin, velocity);
+    let steps = (GRENADE_FUSE / ARC_TIME_STEP) as usize;
+    for _ in 0..steps {
+        let bounced;
+        (position, velocity, bounced) =
+            step_grenade(voxel_world, position, velocity, ARC_TIME_STEP);
+        arc.push(position);
+        if bounced {
+            break;
+        }
+    }
+    arc
+}
+
+/// Updates the arc preview while aiming & queues a grenade once the throw button is released.
+/// Returns the entities that threw
+pub fn system_grenade_throw(
+    world: &mut World,
+    voxel_world: &VoxelWorld,
+    command_queue: &mut CommandQueue,
+    dt: f32,
+) -> Vec<Entity> {
+    let mut thrown = Vec::new();
+    for (entity, (transform, thrower)) in world.query_mut::<(&Transform, &mut GrenadeThrower)>() {
+        thrower.cooldown = (thrower.cooldown - dt).max(0.0);
+        let released = thrower.aiming && !thrower.held;
+        thrower.aiming = thrower.held && thrower.cooldown == 0.0;
+        let (origin, velocity) = throw_from(&transform.0);
+        thrower.arc = match thrower.aiming {
+            true => grenade_arc(voxel_world, origin, velocity),
+            false => Vec::new(),
+        };
+        if released {
+            command_queue.enqueue(Command::ThrowGrenade {
+                position: origin,
+                velocity,
+            });
+            thrower.cooldown = THROW_COOLDOWN;
+            thrown.push(entity);
+        }
+    }
+    thrown
+}
+
+/// Moves & bounces grenades. Grenades whose fuse ran out explode like projectiles. Returns the
+/// positions of the explosions
+pub fn system_grenades(
+    world: &mut World,
+    voxel_world: &VoxelWorld,
+    edit_queue: &mut VoxelEditQueue,
+    dt: f32,
+) -> Result<Vec<Vec3>, NoSuchEntity> {
+    let mut exploded = Vec::new();
+    for (entity, (transform, grenade)) in world.query_mut::<(&mut Transform, &mut Grenade)>() {
+        let position = transform.0.w_axis.xyz();
+        let (position, velocity, _) = step_grenade(voxel_world, position, grenade.velocity, dt);
+        transform.0.w_axis = position.extend(1.0);
+        grenade.velocity = velocity;
+        grenade.fuse -= dt;
+        if grenade.fuse <= 0.0 {
+            exploded.push((entity, position));
+        }
+    }
+    for (entity, position) in &exploded {
+        debug!("Grenade exploded at {position}");
+        world.despawn(*entity)?;
+        queue_explosion(edit_queue, *position);
+    }
+    Ok(exploded.into_iter().map(|(_, position)| position).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxels::edits::VoxelEdit;
+
+    use super::*;
+
+    // Solid cube from 0 to 31
+    fn cube() -> VoxelWorld {
+        VoxelWorld::new_cubic(2)
+    }
+
+    #[test]
+    fn test_grenade_bounces_and_comes_to_rest() {
+        let world = cube();
+        let (mut position, mut velocity) = (Vec3::new(16.0, 40.0, 16.0), Vec3::X * 2.0);
+        let mut bounces = Vec::new();
+        for _ in 0..400 {
+            let bounced;
+            (position, velocity, bounced) = step_grenade(&world, position, velocity, 0.01);
+            if bounced && velocity != Vec3::ZERO {
+                bounces.push(velocity.y);
+            }
+        }
+        // Each bounce loses height, the grenade ends up resting on top of the cube
+        assert!(bounces.len() >= 2, "{bounces:?}");
+        assert!(bounces.windows(2).all(|pair| pair[1] < pair[0]));
+        assert_eq!(velocity, Vec3::ZERO);
+        assert!(
+            (position.y - (31.5 + GRENADE_RADIUS)).abs() < 0.05,
+            "{position}"
+        );
+    }
+
+    #[test]
+    fn test_fuse_queues_explosion() {
+        let voxel_world = cube();
+        let mut world = World::new();
+        let mut edits = VoxelEditQueue::new();
+        spawn_grenade(&mut world, Vec3::new(16.0, 32.0, 16.0), Vec3::ZERO);
+        let exploded =
+            system_grenades(&mut world, &voxel_world, &mut edits, GRENADE_FUSE - 0.1).unwrap();
+        assert!(exploded.is_empty());
+        assert!(edits.drain().next().is_none());
+        let exploded = system_grenades(&mut world, &voxel_world, &mut edits, 0.2).unwrap();
+        assert_eq!(exploded.len(), 1);
+        assert_eq!(world.len(), 0);
+        let queued: Vec<VoxelEdit> = edits.drain().collect();
+        assert!(matches!(queued[..], [VoxelEdit::ClearSphere { .. }]));
+    }
+
+    #[test]
+    fn test_arc_ends_at_first_impact() {
+        let world = cube();
+        // Thrown from the side towards the cube
+        let origin = Vec3::new(-10.0, 20.0, 16.0);
+        let arc = grenade_arc(&world, origin, Vec3::new(THROW_SPEED, THROW_LIFT, 0.0));
+        assert!(arc.len() > 2);
+        assert_eq!(arc[0], origin);
+        let impact = *arc.last().unwrap();
+        assert!(
+            (impact.x - (-0.5 - GRENADE_RADIUS)).abs() < 0.05,
+            "{impact}"
+        );
+    }
+}
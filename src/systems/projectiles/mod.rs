@@ -1,15 +1,37 @@
 use glam::{Mat4, Vec3};
-use hecs::World;
+use hecs::{NoSuchEntity, World};
 use log::debug;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     collision::{ColliderBody, CollisionEvent},
-    renderer::{MESH_PROJECTILE, RenderMeshHandle},
-    systems::physics::{Transform, Velocity},
-    voxels::{VoxelCollider, VoxelWorld},
+    renderer::{MESH_PROJECTILE, RenderMeshHandle, impostors::Impostor},
+    systems::{
+        effects::spawn_tracer,
+        physics::{Transform, Velocity},
+    },
+    voxels::{
+        VoxelCollider,
+        edits::{VoxelEdit, VoxelEditQueue},
+    },
+};
+
+pub mod grenade;
+
+/// Radius of the voxels cleared by exploding projectiles & grenades
+pub const EXPLOSION_RADIUS: f32 = 3.0;
+/// Billboard replacing the projectile mesh at a distance. Matches its red sphere
+pub const PROJECTILE_IMPOSTOR: Impostor = Impostor {
+    radius: 0.5,
+    color: Vec3::X,
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct Projectile;
+/// Muzzle position the projectile was fired from
+#[derive(Serialize, Deserialize)]
+pub struct ProjectileOrigin(pub Vec3);
+#[derive(Serialize, Deserialize)]
 pub struct Lifetime(pub f32);
 
 pub fn spawn_projectile(world: &mut World, transform: Mat4, velocity: Vec3) {
@@ -19,13 +41,15 @@ pub fn spawn_projectile(world: &mut World, transform: Mat4, velocity: Vec3) {
         VoxelCollider,
         ColliderBody::SphereCollider { radius: 0.25 },
         Projectile,
+        ProjectileOrigin(transform.w_axis.truncate()),
         RenderMeshHandle(MESH_PROJECTILE),
+        PROJECTILE_IMPOSTOR,
         Lifetime(2.0),
     ));
     debug!("Projectile spawned {transform:?}, {velocity}");
 }
 
-pub fn system_lifetime(world: &mut World, dt: f32) {
+pub fn system_lifetime(world: &mut World, dt: f32) -> Result<(), NoSuchEntity> {
     let mut to_delete = Vec::new();
     for (entity, lifetime) in world.query_mut::<&mut Lifetime>() {
         lifetime.0 -= dt;
@@ -35,17 +59,19 @@ pub fn system_lifetime(world: &mut World, dt: f32) {
         }
     }
     for entity in to_delete {
-        world
-            .despawn(entity)
-            .expect("Could not delete scheduled entity");
+        world.despawn(entity)?;
     }
+    Ok(())
 }
 
+/// Despawns projectiles that hit the world. Queues explosions to be validated & applied by the
+/// edit authority & spawns tracers from muzzle to impact. Returns the impact positions
 pub fn system_projectile_collisions(
     world: &mut World,
-    voxel_world: &mut VoxelWorld,
     collision_events: &[CollisionEvent],
-) {
+    edit_queue: &mut VoxelEditQueue,
+) -> Result<Vec<Vec3>, NoSuchEntity> {
+    let mut impacts = Vec::new();
     for collision in collision_events {
         if world.get::<&Projectile>(collision.a).is_ok() {
             // Projectile involved
@@ -53,12 +79,25 @@ pub fn system_projectile_collisions(
                 "Projectile hit the world at {}. Removing",
                 collision.info.contact_point
             );
-            world
-                .despawn(collision.a)
-                .expect("Unable to remove projectile");
-            // Explosion
-            let explosion_radius = 3.0;
-            voxel_world.clear_sphere(&collision.info.contact_point, explosion_radius);
+            let origin = world
+                .get::<&ProjectileOrigin>(collision.a)
+                .map(|origin| origin.0)
+                .ok();
+            world.despawn(collision.a)?;
+            if let Some(origin) = origin {
+                spawn_tracer(world, origin, collision.info.contact_point);
+            }
+            queue_explosion(edit_queue, collision.info.contact_point);
+            impacts.push(collision.info.contact_point);
         }
     }
+    Ok(impacts)
+}
+
+/// Explosions are validated & applied by the edit authority like any other edit
+pub fn queue_explosion(edit_queue: &mut VoxelEditQueue, center: Vec3) {
+    edit_queue.push(VoxelEdit::ClearSphere {
+        center,
+        radius: EXPLOSION_RADIUS,
+    });
 }
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use hecs::{Component, Entity, EntityBuilder, EntityRef, World};
+use log::warn;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Maps entity ids of a snapshot to the entities spawned when restoring it
+pub type EntityMap = HashMap<u64, Entity>;
+
+type SerializeFn = Box<dyn Fn(&EntityRef) -> Option<Result<Vec<u8>, String>>>;
+type DeserializeFn = Box<dyn Fn(&[u8], &mut EntityBuilder) -> Result<(), String>>;
+type RemapFn = Box<dyn Fn(&World, Entity, &EntityMap) -> Result<(), String>>;
+
+struct ComponentEntry {
+    name: &'static str,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    // Components referencing other entities need their ids translated after restore
+    remap: Option<RemapFn>,
+}
+
+/// Components of a single entity, keyed by registered component name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializedEntity {
+    pub id: u64,
+    components: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub entities: Vec<SerializedEntity>,
+}
+
+/// Serializers of all components that should survive a save / be replicated.
+/// Components that are not registered are runtime only & skipped
+#[derive(Default)]
+pub struct ComponentRegistry {
+    components: Vec<ComponentEntry>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> ComponentRegistry {
+        Self::default()
+    }
+
+    /// Name identifies the component in snapshots & must stay stable across versions
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self, name: &'static str) {
+        self.register_entry::<T>(name, None);
+    }
+
+    /// For components holding entity references. Remap translates snapshot ids via the map
+    pub fn register_mapped<T: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+        remap: fn(&mut T, &EntityMap) -> Result<(), String>,
+    ) {
+        let remap: RemapFn =
+            Box::new(
+                move |world, entity, map| match world.get::<&mut T>(entity) {
+                    Ok(mut component) => remap(&mut component, map),
+                    Err(_) => Ok(()),
+                },
+            );
+        self.register_entry::<T>(name, Some(remap));
+    }
+
+    fn register_entry<T: Component + Serialize + DeserializeOwned>(
+        &mut self,
+        name: &'static str,
+        remap: Option<RemapFn>,
+    ) {
+        debug_assert!(
+            self.components.iter().all(|entry| entry.name != name),
+            "Component {name} registered twice"
+        );
+        self.components.push(ComponentEntry {
+            name,
+            serialize: Box::new(|entity| {
+                entity
+                    .get::<&T>()
+                    .map(|component| bincode::serialize(&*component).map_err(|err| err.to_string()))
+            }),
+            deserialize: Box::new(|bytes, builder| {
+                let component: T = bincode::deserialize(bytes).map_err(|err| err.to_string())?;
+                builder.add(component);
+                Ok(())
+            }),
+            remap,
+        });
+    }
+
+    /// None if the entity has no registered components
+    pub fn serialize_entity(&self, entity: &EntityRef) -> Result<Option<SerializedEntity>, String> {
+        let mut components = Vec::new();
+        for entry in &self.components {
+            if let Some(bytes) = (entry.serialize)(entity) {
+                let bytes =
+                    bytes.map_err(|err| format!("Unable to serialize {}: {err}", entry.name))?;
+                components.push((entry.name.to_string(), bytes));
+            }
+        }
+        Ok((!components.is_empty()).then(|| SerializedEntity {
+            id: entity.entity().to_bits().get(),
+            components,
+        }))
+    }
+
+    pub fn serialize_world(&self, world: &World) -> Result<WorldSnapshot, String> {
+        let mut entities = Vec::new();
+        for entity in world.iter() {
+            if let Some(serialized) = self.serialize_entity(&entity)? {
+                entities.push(serialized);
+            }
+        }
+        Ok(WorldSnapshot { entities })
+    }
+
+    /// Spawns all entities of the snapshot into world. Unknown components are skipped
+    pub fn restore(
+        &self,
+        world: &mut World,
+        snapshot: &WorldSnapshot,
+    ) -> Result<EntityMap, String> {
+        let mut map = EntityMap::with_capacity(snapshot.entities.len());
+        let mut builder = EntityBuilder::new();
+        for serialized in &snapshot.entities {
+            for (name, bytes) in &serialized.components {
+                match self.components.iter().find(|entry| entry.name == name) {
+                    Some(entry) => (entry.deserialize)(bytes, &mut builder)
+                        .map_err(|err| format!("Unable to deserialize {name}: {err}"))?,
+                    None => warn!("Skipping unknown component {name}"),
+                }
+            }
+            map.insert(serialized.id, world.spawn(builder.build()));
+        }
+
+        // Entity references can only be resolved once all entities exist
+        for remap in self
+            .components
+            .iter()
+            .filter_map(|entry| entry.remap.as_ref())
+        {
+            for entity in map.values() {
+                remap(world, *entity, &map)?;
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Translates an entity reference of a snapshot to the restored entity
+pub fn remap_entity(entity: &mut Entity, map: &EntityMap) -> Result<(), String> {
+    let id = entity.to_bits().get();
+    *entity = *map
+        .get(&id)
+        .ok_or(format!("Referenced entity {id} missing in snapshot"))?;
+    Ok(())
+}
+
+/// Serde helper for entity references, e.g. `#[serde(with = "entity_bits")]`
+pub mod entity_bits {
+    use hecs::Entity;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error> {
+        entity.to_bits().get().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Entity, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+        Entity::from_bits(bits).ok_or(D::Error::custom(format!("Invalid entity id {bits}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+
+    use crate::systems::physics::{self, Parent, Transform, Velocity};
+
+    use super::*;
+
+    struct RuntimeOnly;
+
+    #[test]
+    fn test_world_roundtrip_remaps_parents() {
+        let mut registry = ComponentRegistry::new();
+        physics::register_components(&mut registry);
+
+        let mut world = World::new();
+        // Offset entity ids between source & target world
+        world.spawn((RuntimeOnly,));
+        let root = world.spawn((
+            Transform(Mat4::from_translation(Vec3::X)),
+            Velocity(Vec3::Y),
+        ));
+        world.spawn((Transform(Mat4::IDENTITY), Parent(root)));
+
+        let snapshot = registry.serialize_world(&world).unwrap();
+        assert_eq!(snapshot.entities.len(), 2);
+
+        let bytes = bincode::serialize(&snapshot).unwrap();
+        let snapshot: WorldSnapshot = bincode::deserialize(&bytes).unwrap();
+        let mut restored = World::new();
+        let map = registry.restore(&mut restored, &snapshot).unwrap();
+        assert_eq!(restored.len(), 2);
+
+        let new_root = map[&root.to_bits().get()];
+        assert_eq!(restored.get::<&Velocity>(new_root).unwrap().0, Vec3::Y);
+        let mut query = restored.query::<&Parent>();
+        let (_child, parent) = query.iter().next().unwrap();
+        assert_eq!(parent.0, new_root);
+    }
+}
@@ -92,10 +92,15 @@ pub fn fog_mesh(gl: &Rc<glow::Context>) -> Result<Mesh, Box<dyn Error>> {
     shader.use_program();
     shader.set_uniform_i32("screenTexture", 0);
     shader.set_uniform_i32("depthTexture", 1);
+    shader.set_uniform_i32("bloomTexture", 2);
+    shader.set_uniform_i32("aoTexture", 3);
     quad_vertex_mesh(gl, shader)
 }
 
-fn quad_vertex_mesh(gl: &Rc<glow::Context>, shader: Shader) -> Result<Mesh, Box<dyn Error>> {
+pub(crate) fn quad_vertex_mesh(
+    gl: &Rc<glow::Context>,
+    shader: Shader,
+) -> Result<Mesh, Box<dyn Error>> {
     let vertex_positions: [f32; 2 * 4] = [-1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, -1.0];
     let vertex_bytes: &[u8] = bytemuck::cast_slice(&vertex_positions);
     let tex_coordinates: [f32; 2 * 4] = [0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0];
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use log::warn;
+
+/// Detects created & modified files below a directory by polling modification times.
+/// Cheap enough for small asset directories, no OS specific notification API needed
+pub struct FileWatcher {
+    root: PathBuf,
+    interval: Duration,
+    last_poll: Instant,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl FileWatcher {
+    /// Files existing at creation are not reported until they change
+    pub fn new(root: impl Into<PathBuf>, interval: Duration) -> FileWatcher {
+        let root = root.into();
+        let mut modified = HashMap::new();
+        scan(&root, &mut modified);
+        Self {
+            root,
+            interval,
+            last_poll: Instant::now(),
+            modified,
+        }
+    }
+
+    /// Files created or modified since the last poll. Paths start with the watched root.
+    /// Empty without touching the file system if polled more often than the interval
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+        let mut current = HashMap::with_capacity(self.modified.len());
+        scan(&self.root, &mut current);
+        let mut changed: Vec<PathBuf> = current
+            .iter()
+            .filter(|(path, modified)| self.modified.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.sort();
+        self.modified = current;
+        changed
+    }
+}
+
+fn scan(dir: &Path, modified: &mut HashMap<PathBuf, SystemTime>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!("Unable to watch {}: {err}", dir.display());
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            scan(&path, modified);
+        } else if let Ok(time) = metadata.modified() {
+            modified.insert(path, time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    #[test]
+    fn test_poll_reports_changed_files() {
+        let root = std::env::temp_dir().join(format!("voxie_watcher_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("textures")).unwrap();
+        let texture = root.join("textures/atlas.png");
+        fs::write(&texture, "a").unwrap();
+        fs::write(root.join("cube.obj"), "v").unwrap();
+
+        let mut watcher = FileWatcher::new(&root, Duration::ZERO);
+        assert!(watcher.poll().is_empty());
+
+        // Explicit timestamps, file system time resolution might be too coarse otherwise
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&texture)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let mesh = root.join("fish.obj");
+        fs::write(&mesh, "v").unwrap();
+        assert_eq!(watcher.poll(), vec![mesh, texture]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_poll_interval() {
+        let mut watcher = FileWatcher::new("does/not/exist", Duration::from_secs(60));
+        assert!(watcher.poll().is_empty());
+    }
+}
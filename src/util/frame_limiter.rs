@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+// Sleeping overshoots by up to a scheduler tick, so the rest is spent spinning
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// How to wait for the next frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameLimitStrategy {
+    /// Cheapest, but frame pacing depends on the OS scheduler
+    Sleep,
+    /// Sleeps most of the remaining time & spins shortly before the deadline
+    #[default]
+    Hybrid,
+    /// Most precise, keeps one core busy
+    Spin,
+}
+
+/// Caps the frame rate by waiting until the next frame is due. Independent of vsync
+pub struct FrameLimiter {
+    pub strategy: FrameLimitStrategy,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub fn new(strategy: FrameLimitStrategy) -> FrameLimiter {
+        Self {
+            strategy,
+            next_frame: None,
+        }
+    }
+
+    /// Blocks until the next frame is due. None disables the limit
+    pub fn wait(&mut self, max_fps: Option<u32>) {
+        let Some(interval) = max_fps.filter(|fps| *fps > 0).map(frame_interval) else {
+            self.next_frame = None;
+            return;
+        };
+        let now = Instant::now();
+        let deadline = self.next_frame.unwrap_or(now);
+        if deadline > now {
+            self.wait_until(deadline);
+        }
+        self.next_frame = Some(next_deadline(deadline, Instant::now(), interval));
+    }
+
+    fn wait_until(&self, deadline: Instant) {
+        let sleep_until = match self.strategy {
+            FrameLimitStrategy::Sleep => deadline,
+            FrameLimitStrategy::Hybrid => deadline - SPIN_THRESHOLD,
+            FrameLimitStrategy::Spin => Instant::now(),
+        };
+        let now = Instant::now();
+        if sleep_until > now {
+            std::thread::sleep(sleep_until - now);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+fn frame_interval(fps: u32) -> Duration {
+    Duration::from_secs_f64(1.0 / fps as f64)
+}
+
+// Keeps a steady cadence, but does not try to catch up after falling behind by a whole frame
+fn next_deadline(deadline: Instant, now: Instant, interval: Duration) -> Instant {
+    let next = deadline + interval;
+    if next < now { now + interval } else { next }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_deadline() {
+        let interval = frame_interval(100);
+        let start = Instant::now();
+        // On time: Cadence is kept, even if the frame finished slightly late
+        let late = start + Duration::from_millis(3);
+        assert_eq!(next_deadline(start, late, interval), start + interval);
+        // More than a frame behind: Restart from now instead of rendering a burst of frames
+        let behind = start + Duration::from_millis(25);
+        assert_eq!(next_deadline(start, behind, interval), behind + interval);
+    }
+
+    #[test]
+    fn test_wait_limits_frame_rate() {
+        let mut limiter = FrameLimiter::new(FrameLimitStrategy::Hybrid);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.wait(Some(200));
+        }
+        // First frame is not delayed
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    }
+}
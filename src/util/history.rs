@@ -0,0 +1,82 @@
+/// Fixed capacity ring buffer of samples. Used to plot rolling graphs of debug counters
+pub struct RollingHistory {
+    values: Vec<f32>,
+    capacity: usize,
+    // Index of the next value to be overwritten. Equals the oldest value once full
+    next: usize,
+}
+
+impl RollingHistory {
+    pub fn new(capacity: usize) -> Self {
+        debug_assert!(capacity > 0, "History capacity needs to be > 0");
+        Self {
+            values: Vec::with_capacity(capacity),
+            capacity,
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: f32) {
+        if self.values.len() < self.capacity {
+            self.values.push(value);
+        } else {
+            self.values[self.next] = value;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    /// Raw ring buffer storage. Combine with `offset` to read in chronological order
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Index of the oldest value within `values`
+    pub fn offset(&self) -> usize {
+        if self.values.len() < self.capacity {
+            0
+        } else {
+            self.next
+        }
+    }
+
+    pub fn latest(&self) -> Option<f32> {
+        if self.values.is_empty() {
+            return None;
+        }
+        let idx = (self.next + self.capacity - 1) % self.capacity;
+        self.values.get(idx).copied()
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_wraps_around() {
+        let mut history = RollingHistory::new(3);
+        for i in 0..5 {
+            history.push(i as f32);
+        }
+        assert_eq!(history.values(), &[3.0, 4.0, 2.0]);
+        // Oldest value is 2.0
+        assert_eq!(history.offset(), 2);
+        assert_eq!(history.latest(), Some(4.0));
+        assert_eq!(history.max(), 4.0);
+    }
+
+    #[test]
+    fn test_history_partially_filled() {
+        let mut history = RollingHistory::new(4);
+        assert_eq!(history.latest(), None);
+        history.push(1.0);
+        history.push(2.0);
+        assert_eq!(history.values(), &[1.0, 2.0]);
+        assert_eq!(history.offset(), 0);
+        assert_eq!(history.latest(), Some(2.0));
+    }
+}
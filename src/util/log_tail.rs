@@ -0,0 +1,86 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use log::{Log, Metadata, Record};
+
+// Lines kept for diagnostic dumps
+const CAPACITY: usize = 64;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// Forwards to env_logger & remembers every line it prints
+struct TailLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for TailLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            remember(format!(
+                "[{} {}] {}",
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the logger built by the given env_logger builder. The latest lines are kept in memory
+/// & can be read back with `recent_log_lines`, e.g. for diagnostic dumps
+pub fn init_logger(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let max_level = inner.filter();
+    match log::set_boxed_logger(Box::new(TailLogger { inner })) {
+        Ok(()) => log::set_max_level(max_level),
+        Err(err) => eprintln!("Unable to install logger: {err}"),
+    }
+}
+
+/// Up to count of the latest log lines, oldest first
+pub fn recent_log_lines(count: usize) -> Vec<String> {
+    let lines = RECENT_LINES.lock().unwrap_or_else(|err| err.into_inner());
+    lines
+        .iter()
+        .skip(lines.len().saturating_sub(count))
+        .cloned()
+        .collect()
+}
+
+fn remember(line: String) {
+    let mut lines = RECENT_LINES.lock().unwrap_or_else(|err| err.into_inner());
+    if lines.len() == CAPACITY {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_latest_lines() {
+        for i in 0..CAPACITY + 10 {
+            remember(format!("line {i}"));
+        }
+        let latest = recent_log_lines(3);
+        assert_eq!(
+            latest,
+            vec![
+                format!("line {}", CAPACITY + 7),
+                format!("line {}", CAPACITY + 8),
+                format!("line {}", CAPACITY + 9)
+            ]
+        );
+        assert_eq!(recent_log_lines(usize::MAX).len(), CAPACITY);
+    }
+}
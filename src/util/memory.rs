@@ -0,0 +1,269 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// Subsystem heap allocations are attributed to. Set per thread with [MemoryTag::enter]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryTag {
+    Other,
+    Chunks,
+    Meshes,
+    Network,
+    Ecs,
+}
+
+impl MemoryTag {
+    pub const ALL: [MemoryTag; 5] = [
+        MemoryTag::Other,
+        MemoryTag::Chunks,
+        MemoryTag::Meshes,
+        MemoryTag::Network,
+        MemoryTag::Ecs,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MemoryTag::Other => "Other",
+            MemoryTag::Chunks => "Chunks",
+            MemoryTag::Meshes => "Meshes",
+            MemoryTag::Network => "Network buffers",
+            MemoryTag::Ecs => "ECS",
+        }
+    }
+
+    /// Attributes allocations of the current thread to this tag until the scope is dropped.
+    /// Scopes nest, the innermost wins
+    pub fn enter(self) -> MemoryScope {
+        let previous = CURRENT_TAG.try_with(|tag| tag.replace(self as u8));
+        MemoryScope {
+            previous: previous.unwrap_or(MemoryTag::Other as u8),
+        }
+    }
+}
+
+/// Restores the previous tag of the thread once dropped
+#[must_use]
+pub struct MemoryScope {
+    previous: u8,
+}
+
+impl Drop for MemoryScope {
+    fn drop(&mut self) {
+        let _ = CURRENT_TAG.try_with(|tag| tag.set(self.previous));
+    }
+}
+
+/// Live heap usage of a tag since tracking was enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub bytes: usize,
+    pub allocations: usize,
+    pub peak_bytes: usize,
+}
+
+const TAG_COUNT: usize = MemoryTag::ALL.len();
+// Stored in the header of allocations made while tracking was disabled
+const UNTRACKED: u8 = u8::MAX;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static BYTES: [AtomicUsize; TAG_COUNT] = [const { AtomicUsize::new(0) }; TAG_COUNT];
+static ALLOCATIONS: [AtomicUsize; TAG_COUNT] = [const { AtomicUsize::new(0) }; TAG_COUNT];
+static PEAK_BYTES: [AtomicUsize; TAG_COUNT] = [const { AtomicUsize::new(0) }; TAG_COUNT];
+
+thread_local! {
+    static CURRENT_TAG: Cell<u8> = const { Cell::new(MemoryTag::Other as u8) };
+}
+
+/// Wraps the system allocator & counts live bytes per [MemoryTag]. Only active in binaries that
+/// register it as global allocator:
+///
+/// `#[global_allocator] static ALLOCATOR: TrackingAllocator = TrackingAllocator;`
+///
+/// Every allocation carries a small header with its tag, so frees are attributed correctly even
+/// if they happen on another thread. Counting itself only happens while tracking is enabled
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.allocate(layout, |outer| System.alloc(outer)) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        unsafe { self.allocate(layout, |outer| System.alloc_zeroed(outer)) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let (outer, offset) = with_header(layout);
+        unsafe {
+            record_free(ptr.sub(1).read(), layout.size());
+            System.dealloc(ptr.sub(offset), outer);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let (outer, offset) = with_header(layout);
+        unsafe {
+            let tag = ptr.sub(1).read();
+            let base = System.realloc(ptr.sub(offset), outer, new_size + offset);
+            if base.is_null() {
+                return base;
+            }
+            record_free(tag, layout.size());
+            if tag != UNTRACKED {
+                record_alloc(tag, new_size);
+            }
+            base.add(offset)
+        }
+    }
+}
+
+impl TrackingAllocator {
+    unsafe fn allocate(&self, layout: Layout, alloc: impl FnOnce(Layout) -> *mut u8) -> *mut u8 {
+        INSTALLED.store(true, Ordering::Relaxed);
+        let (outer, offset) = with_header(layout);
+        let base = alloc(outer);
+        if base.is_null() {
+            return base;
+        }
+        let tag = match ENABLED.load(Ordering::Relaxed) {
+            true => CURRENT_TAG
+                .try_with(|tag| tag.get())
+                .unwrap_or(MemoryTag::Other as u8),
+            false => UNTRACKED,
+        };
+        if tag != UNTRACKED {
+            record_alloc(tag, layout.size());
+        }
+        unsafe {
+            let ptr = base.add(offset);
+            ptr.sub(1).write(tag);
+            ptr
+        }
+    }
+}
+
+// Layout including the header & the offset of the user pointer. The offset keeps the alignment
+// & leaves at least one byte for the tag
+fn with_header(layout: Layout) -> (Layout, usize) {
+    let offset = layout.align();
+    // Size stays below isize::MAX for every layout the allocator API hands out
+    let outer = unsafe { Layout::from_size_align_unchecked(layout.size() + offset, offset) };
+    (outer, offset)
+}
+
+fn record_alloc(tag: u8, size: usize) {
+    let index = tag as usize;
+    let bytes = BYTES[index].fetch_add(size, Ordering::Relaxed) + size;
+    ALLOCATIONS[index].fetch_add(1, Ordering::Relaxed);
+    PEAK_BYTES[index].fetch_max(bytes, Ordering::Relaxed);
+}
+
+fn record_free(tag: u8, size: usize) {
+    if tag == UNTRACKED {
+        return;
+    }
+    let index = tag as usize;
+    BYTES[index].fetch_sub(size, Ordering::Relaxed);
+    ALLOCATIONS[index].fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Whether the running binary registered the [TrackingAllocator]
+pub fn is_tracking_installed() -> bool {
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+pub fn is_tracking_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Starts or stops counting. Allocations made while disabled are never counted, so usage only
+/// covers memory allocated since tracking was last enabled
+pub fn set_tracking_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn memory_usage(tag: MemoryTag) -> MemoryUsage {
+    let index = tag as usize;
+    MemoryUsage {
+        bytes: BYTES[index].load(Ordering::Relaxed),
+        allocations: ALLOCATIONS[index].load(Ordering::Relaxed),
+        peak_bytes: PEAK_BYTES[index].load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(feature = "gui")]
+pub fn render_memory_ui(ui: &imgui::Ui) {
+    ui.window("Memory")
+        .size([320.0, 170.0], imgui::Condition::FirstUseEver)
+        .position([0.0, 520.0], imgui::Condition::FirstUseEver)
+        .collapsed(true, imgui::Condition::FirstUseEver)
+        .build(|| {
+            if !is_tracking_installed() {
+                ui.text("Tracking allocator not registered");
+                return;
+            }
+            if !is_tracking_enabled() {
+                ui.text("Enable the memory tracking feature to count allocations");
+            }
+            ui.columns(4, "memory_usage", false);
+            for header in ["Tag", "MiB", "Peak MiB", "Allocations"] {
+                ui.text(header);
+                ui.next_column();
+            }
+            for tag in MemoryTag::ALL {
+                let usage = memory_usage(tag);
+                ui.text(tag.label());
+                ui.next_column();
+                ui.text(format!("{:.2}", mebibytes(usage.bytes)));
+                ui.next_column();
+                ui.text(format!("{:.2}", mebibytes(usage.peak_bytes)));
+                ui.next_column();
+                ui.text(usage.allocations.to_string());
+                ui.next_column();
+            }
+            ui.columns(1, "memory_usage_end", false);
+        });
+}
+
+#[cfg(feature = "gui")]
+fn mebibytes(bytes: usize) -> f32 {
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocations_follow_their_tag() {
+        let allocator = TrackingAllocator;
+        let layout = Layout::from_size_align(100, 16).unwrap();
+        let untracked = unsafe { allocator.alloc(layout) };
+        assert_eq!(untracked as usize % 16, 0);
+
+        set_tracking_enabled(true);
+        let before = memory_usage(MemoryTag::Meshes);
+        let tracked = {
+            let _scope = MemoryTag::Meshes.enter();
+            let tracked = unsafe { allocator.alloc_zeroed(layout) };
+            assert_eq!(CURRENT_TAG.with(|tag| tag.get()), MemoryTag::Meshes as u8);
+            tracked
+        };
+        assert_eq!(CURRENT_TAG.with(|tag| tag.get()), MemoryTag::Other as u8);
+        assert_eq!(memory_usage(MemoryTag::Meshes).bytes, before.bytes + 100);
+
+        // Grows within the original tag, even outside of its scope
+        let tracked = unsafe { allocator.realloc(tracked, layout, 300) };
+        assert_eq!(memory_usage(MemoryTag::Meshes).bytes, before.bytes + 300);
+        unsafe {
+            allocator.dealloc(tracked, Layout::from_size_align(300, 16).unwrap());
+            allocator.dealloc(untracked, layout);
+        }
+        assert_eq!(memory_usage(MemoryTag::Meshes).bytes, before.bytes);
+        assert!(memory_usage(MemoryTag::Meshes).peak_bytes >= before.bytes + 300);
+        set_tracking_enabled(false);
+    }
+}
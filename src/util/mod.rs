@@ -1,7 +1,41 @@
 use glam::Vec3;
 
+#[cfg(feature = "gui")]
+mod file_watcher;
+#[cfg(feature = "gui")]
+mod frame_limiter;
+#[cfg(feature = "gui")]
+mod history;
+#[cfg(feature = "gui")]
+mod log_tail;
+// Tags are set by the network threads of headless builds too, but only counted by the gui
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+mod memory;
+#[cfg(feature = "gui")]
+mod range_allocator;
+#[cfg(feature = "gui")]
+mod rng;
+#[cfg(feature = "gui")]
+mod scratch_pool;
 mod sma;
 
+#[cfg(feature = "gui")]
+pub use file_watcher::FileWatcher;
+#[cfg(feature = "gui")]
+pub use frame_limiter::{FrameLimitStrategy, FrameLimiter};
+#[cfg(feature = "gui")]
+pub use history::RollingHistory;
+#[cfg(feature = "gui")]
+pub use log_tail::{init_logger, recent_log_lines};
+pub use memory::MemoryTag;
+#[cfg(feature = "gui")]
+pub use memory::{TrackingAllocator, render_memory_ui, set_tracking_enabled};
+#[cfg(feature = "gui")]
+pub use range_allocator::RangeAllocator;
+#[cfg(feature = "gui")]
+pub use rng::{DEFAULT_SEED, SeededRng};
+#[cfg(feature = "gui")]
+pub use scratch_pool::{ScratchPool, ScratchPoolStats};
 pub use sma::SimpleMovingAverage;
 
 #[macro_export]
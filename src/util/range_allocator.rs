@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+/// First-fit allocator handing out ranges of a fixed size pool, e.g. slots in a GPU buffer.
+/// Freed ranges are merged with adjacent free ranges
+#[derive(Debug)]
+pub struct RangeAllocator {
+    capacity: u32,
+    // Sorted by start & never adjacent
+    free: Vec<Range<u32>>,
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: u32) -> RangeAllocator {
+        let mut free = Vec::new();
+        if capacity > 0 {
+            free.push(0..capacity);
+        }
+        Self { capacity, free }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Returns start of the allocated range or None if no free range is large enough
+    pub fn allocate(&mut self, size: u32) -> Option<u32> {
+        debug_assert!(size > 0);
+        let idx = self
+            .free
+            .iter()
+            .position(|range| range.len() as u32 >= size)?;
+        let range = &mut self.free[idx];
+        let start = range.start;
+        range.start += size;
+        if range.start == range.end {
+            self.free.remove(idx);
+        }
+        Some(start)
+    }
+
+    pub fn free(&mut self, start: u32, size: u32) {
+        debug_assert!(start + size <= self.capacity);
+        let end = start + size;
+        let idx = self.free.partition_point(|range| range.start < start);
+        debug_assert!(idx == 0 || self.free[idx - 1].end <= start, "Double free");
+        let merge_prev = idx > 0 && self.free[idx - 1].end == start;
+        let merge_next = idx < self.free.len() && self.free[idx].start == end;
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                self.free[idx - 1].end = self.free[idx].end;
+                self.free.remove(idx);
+            }
+            (true, false) => self.free[idx - 1].end = end,
+            (false, true) => self.free[idx].start = start,
+            (false, false) => self.free.insert(idx, start..end),
+        }
+    }
+
+    /// Extends the pool. Existing allocations stay valid
+    pub fn grow(&mut self, new_capacity: u32) {
+        debug_assert!(new_capacity >= self.capacity);
+        let old_capacity = self.capacity;
+        self.capacity = new_capacity;
+        if new_capacity > old_capacity {
+            self.free(old_capacity, new_capacity - old_capacity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_until_full() {
+        let mut allocator = RangeAllocator::new(10);
+        assert_eq!(allocator.allocate(4), Some(0));
+        assert_eq!(allocator.allocate(4), Some(4));
+        assert_eq!(allocator.allocate(4), None);
+        assert_eq!(allocator.allocate(2), Some(8));
+        assert_eq!(allocator.allocate(1), None);
+    }
+
+    #[test]
+    fn test_free_merges_adjacent_ranges() {
+        let mut allocator = RangeAllocator::new(12);
+        let a = allocator.allocate(4).unwrap();
+        let b = allocator.allocate(4).unwrap();
+        let c = allocator.allocate(4).unwrap();
+        allocator.free(a, 4);
+        allocator.free(c, 4);
+        // Fragmented: no range of 8 available
+        assert_eq!(allocator.allocate(8), None);
+        allocator.free(b, 4);
+        assert_eq!(allocator.allocate(12), Some(0));
+    }
+
+    #[test]
+    fn test_grow_keeps_allocations() {
+        let mut allocator = RangeAllocator::new(4);
+        assert_eq!(allocator.allocate(3), Some(0));
+        assert_eq!(allocator.allocate(3), None);
+        allocator.grow(8);
+        assert_eq!(allocator.capacity(), 8);
+        // Remaining slot at 3 merges with grown region
+        assert_eq!(allocator.allocate(5), Some(3));
+    }
+}
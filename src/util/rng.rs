@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+/// Seed of simulations that do not pick their own, so runs with the same inputs match
+pub const DEFAULT_SEED: u64 = 0x5EED_5EED_5EED_5EED;
+
+/// Deterministic random numbers shared by the systems of a simulation, e.g. gun spread or
+/// particle emission. xoshiro256** seeded through splitmix64
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeededRng {
+    state: [u64; 4],
+}
+
+impl Default for SeededRng {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> SeededRng {
+        let mut splitmix = seed;
+        Self {
+            state: std::array::from_fn(|_| splitmix64(&mut splitmix)),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+        result
+    }
+
+    /// Uniform value from 0 (inclusive) to 1 (exclusive)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform value from min (inclusive) to max (exclusive)
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// True with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Independent generator seeded from this one, e.g. for a subsystem that should not shift
+    /// the sequence of others when it draws more or fewer values
+    pub fn fork(&mut self) -> SeededRng {
+        Self::new(self.next_u64())
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = SeededRng::new(7);
+        let mut b = SeededRng::new(7);
+        let mut other = SeededRng::new(8);
+        let sequence: Vec<u64> = (0..16).map(|_| a.next_u64()).collect();
+        assert_eq!(sequence, (0..16).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(
+            sequence,
+            (0..16).map(|_| other.next_u64()).collect::<Vec<_>>()
+        );
+
+        let mut forked = a.fork();
+        assert_eq!(forked, b.fork());
+        assert_ne!(forked.next_u64(), a.next_u64());
+    }
+
+    #[test]
+    fn test_floats_within_range() {
+        let mut rng = SeededRng::default();
+        let values: Vec<f32> = (0..1000).map(|_| rng.range(-2.0, 3.0)).collect();
+        assert!(values.iter().all(|value| (-2.0..3.0).contains(value)));
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        assert!((mean - 0.5).abs() < 0.2, "{mean}");
+        assert!(!rng.chance(0.0));
+        assert!(rng.chance(1.0));
+    }
+}
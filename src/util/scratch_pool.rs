@@ -0,0 +1,99 @@
+/// Allocation counters of a scratch pool since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScratchPoolStats {
+    /// Buffers created because the pool was empty
+    pub allocations: usize,
+    /// Buffers that had to reallocate to fit their contents
+    pub grows: usize,
+    /// Buffers handed out without allocating
+    pub reuses: usize,
+    /// Capacity held by idle buffers in bytes
+    pub reserved_bytes: usize,
+}
+
+/// Reusable buffers for short lived data, e.g. while building meshes. Buffers are cleared, but
+/// keep their capacity when returned, so steady use does not allocate at all
+pub struct ScratchPool<T> {
+    buffers: Vec<Vec<T>>,
+    // Capacity of new buffers
+    initial_capacity: usize,
+    stats: ScratchPoolStats,
+}
+
+impl<T> ScratchPool<T> {
+    pub fn new(initial_capacity: usize) -> ScratchPool<T> {
+        Self {
+            buffers: Vec::new(),
+            initial_capacity,
+            stats: ScratchPoolStats::default(),
+        }
+    }
+
+    // Empty buffer with at least the initial capacity
+    fn take(&mut self) -> Vec<T> {
+        match self.buffers.pop() {
+            Some(buffer) => {
+                self.stats.reuses += 1;
+                self.stats.reserved_bytes -= buffer.capacity() * size_of::<T>();
+                buffer
+            }
+            None => {
+                self.stats.allocations += 1;
+                Vec::with_capacity(self.initial_capacity)
+            }
+        }
+    }
+
+    fn give_back(&mut self, mut buffer: Vec<T>) {
+        buffer.clear();
+        self.stats.reserved_bytes += buffer.capacity() * size_of::<T>();
+        self.buffers.push(buffer);
+    }
+
+    /// Runs f with a scratch buffer, which is returned to the pool afterwards
+    pub fn with<R>(&mut self, f: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        let mut buffer = self.take();
+        let capacity = buffer.capacity();
+        let result = f(&mut buffer);
+        if buffer.capacity() > capacity {
+            self.stats.grows += 1;
+        }
+        self.give_back(buffer);
+        result
+    }
+
+    pub fn stats(&self) -> ScratchPoolStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let mut pool: ScratchPool<u32> = ScratchPool::new(4);
+        let len = pool.with(|buffer| {
+            buffer.extend([1, 2, 3]);
+            buffer.len()
+        });
+        assert_eq!(len, 3);
+        pool.with(|buffer| assert!(buffer.is_empty()));
+        assert_eq!(
+            pool.stats(),
+            ScratchPoolStats {
+                allocations: 1,
+                grows: 0,
+                reuses: 1,
+                reserved_bytes: 16,
+            }
+        );
+
+        // Exceeding the initial capacity keeps the larger buffer around
+        pool.with(|buffer| buffer.extend(0..10));
+        pool.with(|buffer| buffer.extend(0..10));
+        assert_eq!(pool.stats().grows, 1);
+        assert!(pool.stats().reserved_bytes >= 40);
+    }
+}
@@ -0,0 +1,82 @@
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+
+use super::{CHUNK_SIZE, VoxelKind, VoxelWorld};
+
+/// Horizontal direction a voxel faces, e.g. the rising side of stairs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Facing {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+/// Extra state of voxels that need more than their kind. Most voxels have none, so chunks only
+/// store it for the voxels that do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoxelMetadata {
+    pub facing: Facing,
+    /// Growth of crops & plants, starting at 0
+    pub growth_stage: u8,
+}
+
+/// Single voxel as seen by world queries. Assembled from the chunk on lookup, so data that is
+/// only known per chunk, like baked light, does not have to be stored with every voxel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockView {
+    /// World space cell of the voxel
+    pub position: IVec3,
+    pub kind: VoxelKind,
+    /// Baked sky & bounce light from 0 to 1. Full light until the chunk is baked
+    pub light: f32,
+    pub metadata: Option<VoxelMetadata>,
+}
+
+impl BlockView {
+    pub fn is_air(&self) -> bool {
+        matches!(self.kind, VoxelKind::Air)
+    }
+}
+
+impl VoxelWorld {
+    /// Attaches metadata to the voxel or removes it. Replacing the voxel drops its metadata.
+    /// Returns false if the chunk is not loaded
+    pub fn set_metadata(&mut self, position: IVec3, metadata: Option<VoxelMetadata>) -> bool {
+        let chunk_position = position.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        let Some(chunk) = self.chunks.get(chunk_position) else {
+            return false;
+        };
+        chunk.set_metadata(&position, metadata);
+        let origin = chunk.position;
+        self.modified_chunks.insert(chunk_position);
+        self.chunks_edited([origin]);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_follows_voxel() {
+        let mut world = VoxelWorld::new_cubic(2);
+        let stairs = IVec3::new(20, 31, 4);
+        let metadata = VoxelMetadata {
+            facing: Facing::West,
+            growth_stage: 0,
+        };
+        let changes = world.subscribe_chunk_changes();
+        assert!(world.set_metadata(stairs, Some(metadata)));
+        assert_eq!(world.block(stairs).unwrap().metadata, Some(metadata));
+        assert_eq!(world.block(stairs + IVec3::X).unwrap().metadata, None);
+        assert_eq!(changes.edited_chunks(), vec![IVec3::new(16, 16, 0)]);
+        assert!(!world.set_metadata(IVec3::splat(-100), Some(metadata)));
+
+        // Gone with the voxel
+        world.clear_sphere(&stairs.as_vec3(), 1.0);
+        assert_eq!(world.block(stairs).unwrap().metadata, None);
+    }
+}
@@ -0,0 +1,289 @@
+use std::{error::Error, mem::offset_of, rc::Rc};
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use glow::{HasContext, NativeBuffer, NativeVertexArray};
+use log::info;
+
+use crate::{
+    renderer::indirect::{self, DrawArraysIndirectCommand},
+    util::RangeAllocator,
+    voxels::CHUNK_SIZE,
+};
+
+// Initial capacity of the shared instance buffer: 64 completely filled chunks
+const INITIAL_INSTANCE_CAPACITY: u32 = 64 * (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as u32;
+
+/// Per-voxel instance data of a chunk mesh
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(super) struct ChunkVertexData {
+    pub position: Vec3,
+    pub material_index: u32,
+    /// Baked sky & bounce light that does not reach the voxel. 0 until the chunk is baked, so
+    /// meshes without the attribute stay fully lit
+    pub shade: f32,
+}
+
+/// Shared cube vertex buffers every chunk draw is instanced from
+#[derive(Clone, Copy)]
+pub(super) struct CubeVertexBuffers {
+    pub position_vbo: NativeBuffer,
+    pub normal_vbo: NativeBuffer,
+    pub tex_coord_vbo: NativeBuffer,
+    pub vertex_count: usize,
+}
+
+/// Binds cube vertex buffers & chunk instance buffer to the attribute locations of voxel.vert
+///
+/// # Safety
+/// `vao` has to be bound
+pub(super) unsafe fn setup_chunk_vertex_attributes(
+    gl: &glow::Context,
+    vao: NativeVertexArray,
+    cube: &CubeVertexBuffers,
+    instance_vbo: NativeBuffer,
+) {
+    unsafe {
+        // Setup position attribute
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(cube.position_vbo));
+        gl.vertex_attrib_pointer_f32(0, 3, gl::FLOAT, false, 0, 0);
+        gl.enable_vertex_array_attrib(vao, 0);
+        // Setup normal attribute
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(cube.normal_vbo));
+        gl.vertex_attrib_pointer_f32(1, 3, gl::FLOAT, false, 0, 0);
+        gl.enable_vertex_array_attrib(vao, 1);
+        // Setup tex_coords attribute
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(cube.tex_coord_vbo));
+        gl.vertex_attrib_pointer_f32(3, 2, gl::FLOAT, false, 0, 0);
+        gl.enable_vertex_array_attrib(vao, 3);
+
+        // Setup vertex instance buffer
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(instance_vbo));
+        let stride = size_of::<ChunkVertexData>() as i32;
+        // location attribute
+        gl.vertex_attrib_pointer_f32(2, 3, gl::FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(2);
+        // Update vertex attribute at index 2 on every new instance
+        gl.vertex_attrib_divisor(2, 1);
+        // material index attribute
+        gl.vertex_attrib_pointer_i32(
+            4,
+            1,
+            gl::INT,
+            stride,
+            offset_of!(ChunkVertexData, material_index) as i32,
+        );
+        gl.enable_vertex_attrib_array(4);
+        // Update vertex attribute at index 4 on every new instance
+        gl.vertex_attrib_divisor(4, 1);
+        // shade attribute
+        gl.vertex_attrib_pointer_f32(
+            5,
+            1,
+            gl::FLOAT,
+            false,
+            stride,
+            offset_of!(ChunkVertexData, shade) as i32,
+        );
+        gl.enable_vertex_attrib_array(5);
+        gl.vertex_attrib_divisor(5, 1);
+        gl.bind_buffer(gl::ARRAY_BUFFER, None);
+    }
+}
+
+/// Instance data of all chunk meshes in one buffer, drawn with a single
+/// glMultiDrawArraysIndirect call. Each chunk occupies a range of instances; its draw command
+/// addresses it via base instance
+pub(super) struct ChunkInstanceBuffer {
+    gl: Rc<glow::Context>,
+    cube: CubeVertexBuffers,
+    vao: NativeVertexArray,
+    instance_vbo: NativeBuffer,
+    allocator: RangeAllocator,
+
+    indirect_buffer: NativeBuffer,
+    // Commands currently uploaded to the indirect buffer
+    commands: Vec<DrawArraysIndirectCommand>,
+    indirect_capacity: usize,
+}
+
+impl ChunkInstanceBuffer {
+    pub fn new(
+        gl: &Rc<glow::Context>,
+        cube: CubeVertexBuffers,
+    ) -> Result<ChunkInstanceBuffer, Box<dyn Error>> {
+        unsafe {
+            let instance_vbo = create_instance_vbo(gl, INITIAL_INSTANCE_CAPACITY)?;
+            let vao = gl.create_vertex_array()?;
+            gl.bind_vertex_array(Some(vao));
+            setup_chunk_vertex_attributes(gl, vao, &cube, instance_vbo);
+            gl.bind_vertex_array(None);
+
+            let indirect_buffer = gl.create_buffer()?;
+            Ok(Self {
+                gl: Rc::clone(gl),
+                cube,
+                vao,
+                instance_vbo,
+                allocator: RangeAllocator::new(INITIAL_INSTANCE_CAPACITY),
+                indirect_buffer,
+                commands: vec![],
+                indirect_capacity: 0,
+            })
+        }
+    }
+
+    /// Uploads instances into a free range & returns the first instance of that range.
+    /// Grows the buffer if no free range is large enough
+    pub fn allocate(&mut self, instances: &[ChunkVertexData]) -> Result<u32, Box<dyn Error>> {
+        let size = instances.len() as u32;
+        let first_instance = match self.allocator.allocate(size) {
+            Some(first_instance) => first_instance,
+            None => {
+                let capacity = self.allocator.capacity();
+                self.grow((capacity * 2).max(capacity + size))?;
+                self.allocator
+                    .allocate(size)
+                    .ok_or("Unable to allocate chunk instances after growing")?
+            }
+        };
+        unsafe {
+            self.gl
+                .bind_buffer(gl::ARRAY_BUFFER, Some(self.instance_vbo));
+            self.gl.buffer_sub_data_u8_slice(
+                gl::ARRAY_BUFFER,
+                (first_instance as usize * size_of::<ChunkVertexData>()) as i32,
+                bytemuck::cast_slice(instances),
+            );
+            self.gl.bind_buffer(gl::ARRAY_BUFFER, None);
+        }
+        Ok(first_instance)
+    }
+
+    pub fn free(&mut self, first_instance: u32, instance_count: u32) {
+        self.allocator.free(first_instance, instance_count);
+    }
+
+    /// Updates draw commands to the given (first instance, instance count) ranges.
+    /// Only re-uploads if the visible set changed since the last call
+    pub fn set_draws(&mut self, ranges: impl Iterator<Item = (u32, u32)>) {
+        let vertex_count = self.cube.vertex_count as u32;
+        let commands: Vec<DrawArraysIndirectCommand> = ranges
+            .map(
+                |(first_instance, instance_count)| DrawArraysIndirectCommand {
+                    count: vertex_count,
+                    instance_count,
+                    first: 0,
+                    base_instance: first_instance,
+                },
+            )
+            .collect();
+        if commands == self.commands {
+            return;
+        }
+        let bytes: &[u8] = bytemuck::cast_slice(&commands);
+        unsafe {
+            self.gl
+                .bind_buffer(gl::DRAW_INDIRECT_BUFFER, Some(self.indirect_buffer));
+            if commands.len() > self.indirect_capacity {
+                self.gl
+                    .buffer_data_u8_slice(gl::DRAW_INDIRECT_BUFFER, bytes, gl::DYNAMIC_DRAW);
+                self.indirect_capacity = commands.len();
+            } else {
+                self.gl
+                    .buffer_sub_data_u8_slice(gl::DRAW_INDIRECT_BUFFER, 0, bytes);
+            }
+            self.gl.bind_buffer(gl::DRAW_INDIRECT_BUFFER, None);
+        }
+        self.commands = commands;
+    }
+
+    /// Draws all chunks passed to the last [Self::set_draws] call. Returns number of draw calls
+    pub fn draw(&self) -> usize {
+        if self.commands.is_empty() {
+            return 0;
+        }
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl
+                .bind_buffer(gl::DRAW_INDIRECT_BUFFER, Some(self.indirect_buffer));
+            indirect::multi_draw_arrays_indirect(gl::TRIANGLES, self.commands.len() as i32);
+            self.gl.bind_buffer(gl::DRAW_INDIRECT_BUFFER, None);
+            self.gl.bind_vertex_array(None);
+        }
+        1
+    }
+
+    /// Draws a single range outside of the indirect commands, e.g. to change uniforms per chunk.
+    /// Base instance is supported whenever this buffer exists
+    pub fn draw_range(&self, first_instance: u32, instance_count: u32) {
+        unsafe {
+            self.gl.bind_vertex_array(Some(self.vao));
+            self.gl.draw_arrays_instanced_base_instance(
+                gl::TRIANGLES,
+                0,
+                self.cube.vertex_count as i32,
+                instance_count as i32,
+                first_instance,
+            );
+            self.gl.bind_vertex_array(None);
+        }
+    }
+
+    /// Moves instance data into a larger buffer. Existing ranges stay valid
+    fn grow(&mut self, new_capacity: u32) -> Result<(), Box<dyn Error>> {
+        let gl = &self.gl;
+        let old_capacity = self.allocator.capacity();
+        unsafe {
+            let instance_vbo = create_instance_vbo(gl, new_capacity)?;
+            gl.bind_buffer(gl::COPY_READ_BUFFER, Some(self.instance_vbo));
+            gl.bind_buffer(gl::COPY_WRITE_BUFFER, Some(instance_vbo));
+            gl.copy_buffer_sub_data(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                0,
+                0,
+                (old_capacity as usize * size_of::<ChunkVertexData>()) as i32,
+            );
+            gl.bind_buffer(gl::COPY_READ_BUFFER, None);
+            gl.bind_buffer(gl::COPY_WRITE_BUFFER, None);
+            gl.delete_buffer(self.instance_vbo);
+            self.instance_vbo = instance_vbo;
+
+            gl.bind_vertex_array(Some(self.vao));
+            setup_chunk_vertex_attributes(gl, self.vao, &self.cube, instance_vbo);
+            gl.bind_vertex_array(None);
+        }
+        self.allocator.grow(new_capacity);
+        info!("Grew chunk instance buffer from {old_capacity} to {new_capacity} instances");
+        Ok(())
+    }
+}
+
+impl Drop for ChunkInstanceBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_buffer(self.instance_vbo);
+            self.gl.delete_buffer(self.indirect_buffer);
+            self.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
+
+unsafe fn create_instance_vbo(
+    gl: &glow::Context,
+    capacity: u32,
+) -> Result<NativeBuffer, Box<dyn Error>> {
+    unsafe {
+        let vbo = gl.create_buffer()?;
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(vbo));
+        gl.buffer_data_size(
+            gl::ARRAY_BUFFER,
+            (capacity as usize * size_of::<ChunkVertexData>()) as i32,
+            gl::STATIC_DRAW,
+        );
+        gl.bind_buffer(gl::ARRAY_BUFFER, None);
+        Ok(vbo)
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use crate::octree::IAabb;
+
+use super::{VoxelChunk, chunk_storage::ChunkStorage};
+
+// Entries are dropped all at once when exceeded. Moving entities rarely reuse old regions
+const MAX_CACHED_REGIONS: usize = 512;
+
+type ChunkList = Arc<[Arc<VoxelChunk>]>;
+
+/// Chunks found per chunk space region. Nearby entities query the same chunk regions, so
+/// repeated index lookups are skipped. Has to be invalidated whenever chunks are inserted into
+/// or removed from the storage. Edits within chunks are fine, as chunks are shared
+#[derive(Default)]
+pub(super) struct ChunkLookupCache {
+    regions: Mutex<HashMap<IAabb, ChunkList>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ChunkLookupCache {
+    pub fn get_or_insert(&self, chunks: &ChunkStorage, bb_chunk_space: IAabb) -> ChunkList {
+        let mut regions = self.regions.lock().unwrap();
+        if let Some(chunks) = regions.get(&bb_chunk_space) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return chunks.clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        if regions.len() >= MAX_CACHED_REGIONS {
+            regions.clear();
+        }
+        let found: ChunkList = chunks
+            .iter_region(bb_chunk_space.clone())
+            .cloned()
+            .collect();
+        regions.insert(bb_chunk_space, found.clone());
+        found
+    }
+
+    pub fn invalidate(&self) {
+        self.regions.lock().unwrap().clear();
+    }
+
+    /// Cache hits & misses since creation
+    pub fn stats(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+    use crate::voxels::chunk_storage::ChunkIndexKind;
+
+    #[test]
+    fn test_lookup_cached_until_invalidated() {
+        let mut chunks = ChunkStorage::new(ChunkIndexKind::Octree, 2);
+        chunks.insert(IVec3::ZERO, Arc::new(VoxelChunk::new(IVec3::ZERO)));
+        let cache = ChunkLookupCache::default();
+        let region = IAabb::new(&IVec3::ZERO, 2);
+
+        assert_eq!(cache.get_or_insert(&chunks, region.clone()).len(), 1);
+        assert_eq!(cache.get_or_insert(&chunks, region.clone()).len(), 1);
+        assert_eq!(cache.stats(), (1, 1));
+
+        chunks.insert(IVec3::ONE, Arc::new(VoxelChunk::new(IVec3::ONE)));
+        cache.invalidate();
+        assert_eq!(cache.get_or_insert(&chunks, region).len(), 2);
+        assert_eq!(cache.stats(), (1, 2));
+    }
+}
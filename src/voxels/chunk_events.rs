@@ -0,0 +1,107 @@
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+use glam::IVec3;
+
+/// What happened to a chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkChangeKind {
+    /// Generated or restored & inserted into the world
+    Loaded,
+    /// Voxels changed, e.g. by an edit, a rollback or a resync with the server
+    Edited,
+    /// Evicted from the world
+    Unloaded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkChange {
+    /// Minimum corner of the chunk in world space
+    pub position: IVec3,
+    pub kind: ChunkChangeKind,
+}
+
+/// Fans chunk changes of the world out to every subscriber. Subscribers that were dropped are
+/// forgotten with the next change
+#[derive(Default)]
+pub struct ChunkChangePublisher {
+    subscribers: Vec<Sender<ChunkChange>>,
+}
+
+impl ChunkChangePublisher {
+    pub fn subscribe(&mut self) -> ChunkSubscription {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        ChunkSubscription { receiver }
+    }
+
+    pub fn publish(&mut self, kind: ChunkChangeKind, positions: impl IntoIterator<Item = IVec3>) {
+        for position in positions {
+            let change = ChunkChange { position, kind };
+            self.subscribers
+                .retain(|subscriber| subscriber.send(change).is_ok());
+        }
+    }
+}
+
+/// Chunk changes of the world since last drained, in the order they happened
+pub struct ChunkSubscription {
+    receiver: Receiver<ChunkChange>,
+}
+
+impl ChunkSubscription {
+    pub fn drain(&self) -> Vec<ChunkChange> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// World space positions of the chunks edited since last drained, without duplicates.
+    /// Other changes are dropped
+    pub fn edited_chunks(&self) -> Vec<IVec3> {
+        let mut positions: Vec<IVec3> = self
+            .receiver
+            .try_iter()
+            .filter(|change| change.kind == ChunkChangeKind::Edited)
+            .map(|change| change.position)
+            .collect();
+        positions.sort_by_key(|position| position.to_array());
+        positions.dedup();
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use crate::voxels::{CHUNK_SIZE, VoxelWorld};
+
+    use super::*;
+
+    #[test]
+    fn test_every_subscriber_sees_edits() {
+        let mut world = VoxelWorld::new_cubic(2);
+        let renderer = world.subscribe_chunk_changes();
+        let navigation = world.subscribe_chunk_changes();
+        let dropped = world.subscribe_chunk_changes();
+        drop(dropped);
+
+        let changed = world.clear_sphere(&Vec3::splat(16.0), 2.0);
+        let mut expected: Vec<IVec3> = changed.into_iter().collect();
+        expected.sort_by_key(|position| position.to_array());
+        assert_eq!(expected.len(), 8);
+        assert_eq!(renderer.edited_chunks(), expected);
+        // Draining one subscriber leaves the others untouched
+        assert_eq!(navigation.drain().len(), expected.len());
+        assert!(renderer.edited_chunks().is_empty());
+        assert_eq!(world.chunk_changes.subscribers.len(), 2);
+
+        world.evict_distant_chunks(&Vec3::splat(1000.0), 1).unwrap();
+        let unloaded = navigation.drain();
+        assert_eq!(unloaded.len(), 8);
+        assert!(
+            unloaded
+                .iter()
+                .all(|change| change.kind == ChunkChangeKind::Unloaded
+                    && change.position % CHUNK_SIZE as i32 == IVec3::ZERO)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use glam::IVec3;
+
+use crate::octree::{IAabb, Octree};
+
+use super::{VoxelChunk, morton::MortonChunkMap};
+
+/// Index used to look up chunks by their chunk space position
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChunkIndexKind {
+    #[default]
+    Octree,
+    /// Flat hash map keyed by morton encoded positions
+    Morton,
+}
+
+/// Chunks of a voxel world, indexed by chunk space position
+pub enum ChunkStorage {
+    Octree(Octree<Arc<VoxelChunk>>),
+    Morton(MortonChunkMap),
+}
+
+// Either of two iterators, so both indices can be iterated without boxing
+enum EitherIter<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Iterator for EitherIter<A, B>
+where
+    A: Iterator,
+    B: Iterator<Item = A::Item>,
+{
+    type Item = A::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            EitherIter::Left(iter) => iter.next(),
+            EitherIter::Right(iter) => iter.next(),
+        }
+    }
+}
+
+impl ChunkStorage {
+    /// Empty storage covering size chunks along each axis
+    pub fn new(kind: ChunkIndexKind, size: usize) -> ChunkStorage {
+        match kind {
+            ChunkIndexKind::Octree => ChunkStorage::Octree(Octree::new(size)),
+            ChunkIndexKind::Morton => ChunkStorage::Morton(MortonChunkMap::new(size)),
+        }
+    }
+
+    pub fn kind(&self) -> ChunkIndexKind {
+        match self {
+            ChunkStorage::Octree(_) => ChunkIndexKind::Octree,
+            ChunkStorage::Morton(_) => ChunkIndexKind::Morton,
+        }
+    }
+
+    pub fn insert(&mut self, position: IVec3, chunk: Arc<VoxelChunk>) {
+        match self {
+            ChunkStorage::Octree(tree) => tree.insert(position, chunk),
+            ChunkStorage::Morton(map) => map.insert(position, chunk),
+        }
+    }
+
+    pub fn remove(&mut self, position: IVec3) -> Option<Arc<VoxelChunk>> {
+        match self {
+            ChunkStorage::Octree(tree) => tree.remove(position),
+            ChunkStorage::Morton(map) => map.remove(position),
+        }
+    }
+
+    /// Chunk at the chunk space position
+    pub fn get(&self, position: IVec3) -> Option<&Arc<VoxelChunk>> {
+        match self {
+            ChunkStorage::Octree(tree) => tree.iter_region(IAabb::new(&position, 1)).next(),
+            ChunkStorage::Morton(map) => map.get(position),
+        }
+    }
+
+    /// Chunks per axis
+    pub fn get_size(&self) -> usize {
+        match self {
+            ChunkStorage::Octree(tree) => tree.get_size(),
+            ChunkStorage::Morton(map) => map.get_size(),
+        }
+    }
+
+    pub fn all_chunks(&self) -> Vec<Arc<VoxelChunk>> {
+        match self {
+            ChunkStorage::Octree(tree) => tree.get_all_depth_first(),
+            ChunkStorage::Morton(map) => map.values().cloned().collect(),
+        }
+    }
+
+    pub fn get_total_region_world_space(&self, chunk_size: usize) -> IAabb {
+        IAabb::new(&IVec3::ZERO, self.get_size() * chunk_size)
+    }
+
+    /// Doubles the covered region
+    pub fn grow(&mut self, chunk_size: usize) {
+        match self {
+            ChunkStorage::Octree(tree) => tree.grow(chunk_size),
+            ChunkStorage::Morton(map) => map.grow(),
+        }
+    }
+
+    /// Chunks within the region in **chunk space**
+    pub fn iter_region(&self, region_chunk_space: IAabb) -> impl Iterator<Item = &Arc<VoxelChunk>> {
+        match self {
+            ChunkStorage::Octree(tree) => EitherIter::Left(tree.iter_region(region_chunk_space)),
+            ChunkStorage::Morton(map) => EitherIter::Right(map.iter_region(region_chunk_space)),
+        }
+    }
+
+    /// Positions of missing chunks within the region in **chunk space**
+    pub fn iter_empty_within_region(
+        &self,
+        region_chunk_space: IAabb,
+    ) -> impl Iterator<Item = IVec3> {
+        match self {
+            ChunkStorage::Octree(tree) => {
+                EitherIter::Left(tree.iter_empty_within_region(region_chunk_space))
+            }
+            ChunkStorage::Morton(map) => {
+                EitherIter::Right(map.iter_empty_within_region(region_chunk_space))
+            }
+        }
+    }
+}
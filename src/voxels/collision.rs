@@ -1,5 +1,6 @@
 use glam::{Mat4, Vec3, Vec4Swizzles};
 use hecs::World;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     collision::{
@@ -15,6 +16,7 @@ use super::{Voxel, VoxelWorld};
 
 /// Tag component. Only entities that have both a ColliderBody and this tag component
 /// will be check for collision with the voxel world
+#[derive(Serialize, Deserialize)]
 pub struct VoxelCollider;
 
 pub fn iter_sphere_collision(
@@ -27,10 +29,6 @@ pub fn iter_sphere_collision(
     // BB test
     let sphere_box_region_f = AABB::new_center(&center, radius * 2.0);
     let sphere_box_region_i = IAabb::from(&sphere_box_region_f);
-    // WARN: Known issue: When an oject is coming from **negative** x,y,z values
-    // we will not return correct voxels in the region check. More specifically this
-    // should only happen at the 'edge' of the world.
-    // Accepted risk
     let iter = world.iter_region_voxels(sphere_box_region_i);
     iter.filter_map(move |voxel| {
         let vox_collider = voxel.get_collider()?;
@@ -173,4 +171,23 @@ mod tests {
             iter_sphere_collision(&world, sphere_position, sphere_radius).collect();
         assert_eq!(collisions.len(), 4);
     }
+    #[test]
+    fn test_sphere_collision_from_negative_side() {
+        let world = VoxelWorld::new_cubic(1);
+        // Sphere bb is fully at negative x, but reaches into the voxel at the origin
+        let sphere_position = Vec3::new(-0.6, 8.0, 8.0);
+        let sphere_radius = 0.49;
+        let collisions: Vec<CollisionInfo> =
+            iter_sphere_collision(&world, sphere_position, sphere_radius).collect();
+        assert_eq!(collisions.len(), 1);
+    }
+    #[test]
+    fn test_sphere_collision_outside_world() {
+        let world = VoxelWorld::new_cubic(1);
+        let sphere_position = Vec3::new(-2.0, -2.0, -2.0);
+        let sphere_radius = 0.49;
+        let collisions: Vec<CollisionInfo> =
+            iter_sphere_collision(&world, sphere_position, sphere_radius).collect();
+        assert!(collisions.is_empty());
+    }
 }
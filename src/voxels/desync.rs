@@ -0,0 +1,264 @@
+use std::collections::{HashSet, VecDeque};
+
+use glam::IVec3;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::octree::IAabb;
+
+use super::{
+    CHUNK_SIZE, VoxelWorld, determinism::chunk_hash, edits::EditPrediction, regions::StoredChunk,
+};
+
+// Most recent desyncs listed in the diagnostics window
+const MAX_WARNINGS: usize = 8;
+
+/// Content hashes of the chunks around a client, sent by the server in regular intervals.
+/// Chunks are keyed by their origin in world space
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerChunkChecksums {
+    pub server_tick: u32,
+    pub chunks: Vec<(IVec3, u64)>,
+}
+
+/// Client asks for the server state of chunks whose checksum did not match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientRequestResync {
+    pub chunks: Vec<IVec3>,
+}
+
+/// Authoritative content of a single chunk. Encoded like region files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerChunkData {
+    pub position: IVec3,
+    chunk: StoredChunk,
+}
+
+impl VoxelWorld {
+    /// Server side: Checksums of all loaded chunks within the region
+    pub fn chunk_checksums(&self, server_tick: u32, region: &IAabb) -> ServerChunkChecksums {
+        ServerChunkChecksums {
+            server_tick,
+            chunks: self
+                .iter_region_chunks(region)
+                .map(|chunk| (chunk.position, chunk_hash(chunk)))
+                .collect(),
+        }
+    }
+
+    /// Server side: Current state of the requested chunks. Chunks not loaded are skipped
+    pub fn chunk_data(&self, request: &ClientRequestResync) -> Vec<ServerChunkData> {
+        request
+            .chunks
+            .iter()
+            .filter_map(|position| self.chunks.get(position / CHUNK_SIZE as i32))
+            .map(|chunk| ServerChunkData {
+                position: chunk.position,
+                chunk: StoredChunk::of(chunk),
+            })
+            .collect()
+    }
+
+    /// Client side: Overrides the local chunk with the server state. Returns the chunk position
+    fn apply_chunk_data(&mut self, data: &ServerChunkData) -> Result<IVec3, String> {
+        let chunk_position = data.position / CHUNK_SIZE as i32;
+        let chunk = self
+            .chunks
+            .get(chunk_position)
+            .ok_or(format!("Chunk {} is not loaded", data.position))?;
+        data.chunk.restore(chunk)?;
+        // Differs from generated terrain, so it has to be persisted
+        self.modified_chunks.insert(chunk_position);
+        self.chunks_edited([data.position]);
+        Ok(data.position)
+    }
+}
+
+/// Chunk that differed from the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesyncWarning {
+    pub server_tick: u32,
+    pub chunk: IVec3,
+}
+
+/// Client side comparison of local chunks against server checksums. Mismatching chunks are
+/// requested from the server & replaced once it answers
+#[derive(Default)]
+pub struct DesyncDetector {
+    // Chunks compared since start
+    checked: usize,
+    mismatches: usize,
+    resynced: usize,
+    // Requested from the server, not answered yet
+    pending: HashSet<IVec3>,
+    warnings: VecDeque<DesyncWarning>,
+}
+
+impl DesyncDetector {
+    pub fn new() -> DesyncDetector {
+        Self::default()
+    }
+
+    /// Compares local chunks against the checksums. Chunks that are not loaded locally, already
+    /// requested or touched by own edits the server did not confirm yet are skipped. Returns the
+    /// resync request for all mismatching chunks, if any
+    pub fn compare(
+        &mut self,
+        world: &VoxelWorld,
+        prediction: &EditPrediction,
+        checksums: &ServerChunkChecksums,
+    ) -> Option<ClientRequestResync> {
+        let mut mismatching = Vec::new();
+        for (position, server_hash) in &checksums.chunks {
+            if self.pending.contains(position) {
+                continue;
+            }
+            let Some(chunk) = world.chunks.get(position / CHUNK_SIZE as i32) else {
+                continue;
+            };
+            if prediction.overlaps(&chunk.get_bb_i()) {
+                continue;
+            }
+            self.checked += 1;
+            if chunk_hash(chunk) == *server_hash {
+                continue;
+            }
+            warn!(
+                "Chunk {position} desynced at server tick {}",
+                checksums.server_tick
+            );
+            self.mismatches += 1;
+            if self.warnings.len() == MAX_WARNINGS {
+                self.warnings.pop_front();
+            }
+            self.warnings.push_back(DesyncWarning {
+                server_tick: checksums.server_tick,
+                chunk: *position,
+            });
+            self.pending.insert(*position);
+            mismatching.push(*position);
+        }
+        (!mismatching.is_empty()).then_some(ClientRequestResync {
+            chunks: mismatching,
+        })
+    }
+
+    /// Replaces local chunks with the server state. Returns positions of changed chunks, which
+    /// need to be remeshed
+    pub fn apply_resync(&mut self, world: &mut VoxelWorld, data: &[ServerChunkData]) -> Vec<IVec3> {
+        let mut changed = Vec::with_capacity(data.len());
+        for chunk in data {
+            self.pending.remove(&chunk.position);
+            match world.apply_chunk_data(chunk) {
+                Ok(position) => {
+                    debug!("Resynced chunk {position}");
+                    self.resynced += 1;
+                    changed.push(position);
+                }
+                Err(err) => warn!("Unable to resync chunk: {err}"),
+            }
+        }
+        changed
+    }
+
+    pub fn warnings(&self) -> impl DoubleEndedIterator<Item = &DesyncWarning> {
+        self.warnings.iter()
+    }
+
+    /// Voxel sync section of the network diagnostics
+    pub fn render_ui(&self, ui: &imgui::Ui) {
+        ui.window("Network")
+            .size([300.0, 180.0], imgui::Condition::FirstUseEver)
+            .position([600.0, 350.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("Chunks checked: {}", self.checked));
+                ui.text(format!(
+                    "Desynced: {} (resynced {}, pending {})",
+                    self.mismatches,
+                    self.resynced,
+                    self.pending.len()
+                ));
+                if self.warnings.is_empty() {
+                    return;
+                }
+                ui.separator();
+                for warning in self.warnings().rev() {
+                    ui.text_colored(
+                        [1.0, 0.6, 0.2, 1.0],
+                        format!(
+                            "Tick {}: chunk {} desynced",
+                            warning.server_tick, warning.chunk
+                        ),
+                    );
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use crate::voxels::{Voxel, VoxelKind, edits::ClientRequestEdit, edits::VoxelEdit};
+
+    use super::*;
+
+    fn region() -> IAabb {
+        IAabb::new(&IVec3::ZERO, 2 * CHUNK_SIZE)
+    }
+
+    #[test]
+    fn test_desynced_chunk_is_resynced() {
+        let server = VoxelWorld::new_cubic(2);
+        let mut client = VoxelWorld::new_cubic(2);
+        let prediction = EditPrediction::new();
+        let mut detector = DesyncDetector::new();
+
+        let checksums = server.chunk_checksums(1, &region());
+        assert_eq!(checksums.chunks.len(), 8);
+        assert!(detector.compare(&client, &prediction, &checksums).is_none());
+
+        // Voxel lost on the client only
+        let position = IVec3::new(CHUNK_SIZE as i32 + 1, 2, 3);
+        let chunk = client.chunks.get(IVec3::new(1, 0, 0)).unwrap();
+        chunk.insert(
+            &position,
+            Voxel {
+                position: position.as_vec3(),
+                kind: VoxelKind::Air,
+            },
+        );
+        let request = detector.compare(&client, &prediction, &checksums).unwrap();
+        let expected = vec![IVec3::new(CHUNK_SIZE as i32, 0, 0)];
+        assert_eq!(request.chunks, expected);
+        // Requested once until the server answers
+        assert!(detector.compare(&client, &prediction, &checksums).is_none());
+        assert_eq!(detector.warnings().count(), 1);
+
+        let changed = detector.apply_resync(&mut client, &server.chunk_data(&request));
+        assert_eq!(changed, expected);
+        let checksums = server.chunk_checksums(2, &region());
+        assert!(detector.compare(&client, &prediction, &checksums).is_none());
+    }
+
+    #[test]
+    fn test_predicted_chunks_are_skipped() {
+        let server = VoxelWorld::new_cubic(2);
+        let mut client = VoxelWorld::new_cubic(2);
+        let mut prediction = EditPrediction::new();
+        let request = ClientRequestEdit {
+            edit_id: 0,
+            edit: VoxelEdit::ClearSphere {
+                center: Vec3::splat(4.0),
+                radius: 2.0,
+            },
+        };
+        client.predict_edit(&mut prediction, &request);
+        let mut detector = DesyncDetector::new();
+        let checksums = server.chunk_checksums(1, &region());
+        assert!(detector.compare(&client, &prediction, &checksums).is_none());
+
+        client.rollback_edit(&mut prediction, 0);
+        assert!(detector.compare(&client, &prediction, &checksums).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use glam::IVec3;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use super::{CHUNK_SIZE, VoxelChunk, generators::ChunkGenerator};
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Content hash of a chunk, stable across runs, threads & machines
+pub fn chunk_hash(chunk: &VoxelChunk) -> u64 {
+    let origin = chunk.position.to_array().map(i32::to_le_bytes);
+    let kinds = chunk.kinds().into_iter().map(|kind| kind as u8);
+    // Chunks without metadata hash the same as before metadata existed
+    let metadata = chunk
+        .metadata_entries()
+        .into_iter()
+        .flat_map(|(index, metadata)| {
+            let [low, high] = index.to_le_bytes();
+            [low, high, metadata.facing as u8, metadata.growth_stage]
+        });
+    // FNV-1a. std's hasher is not guaranteed to be stable across releases
+    origin
+        .into_iter()
+        .flatten()
+        .chain(kinds)
+        .chain(metadata)
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// Origins in **world** space of a cube of chunks centered around the world origin
+pub fn chunk_origins(chunks_per_axis: i32) -> Vec<IVec3> {
+    let min = -chunks_per_axis / 2;
+    let max = min + chunks_per_axis;
+    let mut origins = Vec::new();
+    for x in min..max {
+        for y in min..max {
+            for z in min..max {
+                origins.push(IVec3::new(x, y, z) * CHUNK_SIZE as i32);
+            }
+        }
+    }
+    origins
+}
+
+/// Generates all chunks in parallel. Hashes are returned in the order of the origins
+pub fn generate_hashes(generator: &dyn ChunkGenerator, origins: &[IVec3]) -> Vec<u64> {
+    origins
+        .par_iter()
+        .map(|origin| chunk_hash(&generator.generate_chunk(*origin)))
+        .collect()
+}
+
+/// Generates the chunks twice using two generator instances, the second time in reverse order.
+/// Returns the number of verified chunks, or the first chunk that differs
+pub fn verify_generation(
+    create_generator: impl Fn() -> Box<dyn ChunkGenerator>,
+    origins: &[IVec3],
+) -> Result<usize, String> {
+    let first = generate_hashes(create_generator().as_ref(), origins);
+    let reversed: Vec<IVec3> = origins.iter().rev().copied().collect();
+    let mut second = generate_hashes(create_generator().as_ref(), &reversed);
+    second.reverse();
+    for ((origin, first), second) in origins.iter().zip(first).zip(second) {
+        if first != second {
+            return Err(format!(
+                "Chunk at {origin} differs between runs: {first:016x} != {second:016x}"
+            ));
+        }
+    }
+    Ok(origins.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::voxels::{
+        Voxel, VoxelKind,
+        generators::{
+            cubic::CubicGenerator, debug_generator::DebugGenerator, heightmap::HeightmapGenerator,
+            noise3d::Noise3DGenerator,
+        },
+    };
+
+    use super::*;
+
+    // Fills a different voxel per instance, like noise with a random seed would
+    struct UnseededGenerator(u32);
+
+    impl ChunkGenerator for UnseededGenerator {
+        fn generate_chunk(&self, chunk_origin: IVec3) -> VoxelChunk {
+            let chunk = VoxelChunk::new(chunk_origin);
+            let offset = self.0 as i32 % CHUNK_SIZE as i32;
+            let mut voxel = Voxel::new();
+            voxel.kind = VoxelKind::Dirt;
+            chunk.insert(&(chunk_origin + IVec3::splat(offset)), voxel);
+            chunk
+        }
+    }
+
+    #[test]
+    fn test_generators_are_deterministic() {
+        let origins = chunk_origins(4);
+        assert_eq!(origins.len(), 64);
+        assert_eq!(
+            verify_generation(|| Box::new(Noise3DGenerator::new(CHUNK_SIZE)), &origins),
+            Ok(64)
+        );
+        assert!(
+            verify_generation(|| Box::new(HeightmapGenerator::new(CHUNK_SIZE)), &origins).is_ok()
+        );
+        assert!(verify_generation(|| Box::new(CubicGenerator::new(CHUNK_SIZE)), &origins).is_ok());
+        assert!(verify_generation(|| Box::new(DebugGenerator::new(CHUNK_SIZE)), &origins).is_ok());
+    }
+
+    #[test]
+    fn test_detects_unseeded_generator() {
+        let instances = AtomicU32::new(0);
+        let create = || -> Box<dyn ChunkGenerator> {
+            Box::new(UnseededGenerator(instances.fetch_add(1, Ordering::Relaxed)))
+        };
+        assert!(verify_generation(create, &chunk_origins(2)).is_err());
+    }
+
+    #[test]
+    fn test_hash_depends_on_position_and_content() {
+        let generator = CubicGenerator::new(CHUNK_SIZE);
+        let hash = |origin| chunk_hash(&generator.generate_chunk(origin));
+        assert_eq!(hash(IVec3::ZERO), hash(IVec3::ZERO));
+        assert_ne!(hash(IVec3::ZERO), hash(IVec3::X * CHUNK_SIZE as i32));
+        let empty = chunk_hash(&VoxelChunk::new(IVec3::ZERO));
+        assert_ne!(hash(IVec3::ZERO), empty);
+    }
+}
@@ -0,0 +1,573 @@
+use std::{collections::HashSet, sync::Arc};
+
+use glam::{IVec3, Vec3};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{network::ClientId, octree::IAabb};
+
+use super::{CHUNK_SIZE, Voxel, VoxelChunk, VoxelWorld};
+
+/// Covers projectile speed * lifetime plus some player movement
+pub const DEFAULT_MAX_EDIT_REACH: f32 = 100.0;
+pub const DEFAULT_MAX_EDIT_RADIUS: f32 = 5.0;
+// Spheres sticking out less than this from an accepted edit within the same tick are dropped
+const DEDUPE_TOLERANCE: f32 = 0.5;
+
+/// Modification of the voxel world. Applied by all clients once accepted by the server
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VoxelEdit {
+    /// Explosion removing all voxels within radius
+    ClearSphere { center: Vec3, radius: f32 },
+}
+
+impl VoxelEdit {
+    fn bounding_sphere(&self) -> (Vec3, f32) {
+        match *self {
+            VoxelEdit::ClearSphere { center, radius } => (center, radius),
+        }
+    }
+
+    fn intersects(&self, region: &IAabb) -> bool {
+        let (center, radius) = self.bounding_sphere();
+        region.distance_to_point(&center) <= radius
+    }
+
+    /// Octree space positions of all chunks the edit may change
+    pub(super) fn chunk_positions(&self) -> impl Iterator<Item = IVec3> + use<> {
+        let edit = *self;
+        let (center, radius) = self.bounding_sphere();
+        // World space starts at the origin, there are no chunks below
+        let min = ((center - radius) / CHUNK_SIZE as f32)
+            .floor()
+            .as_ivec3()
+            .max(IVec3::ZERO);
+        let max = ((center + radius) / CHUNK_SIZE as f32)
+            .floor()
+            .as_ivec3()
+            .max(min);
+        IAabb::new_rect(min, max + IVec3::ONE)
+            .iter_cells()
+            .filter(move |position| {
+                edit.intersects(&IAabb::new(&(position * CHUNK_SIZE as i32), CHUNK_SIZE))
+            })
+    }
+
+    /// True if applying other after self does not change the world any further
+    fn covers(&self, other: &VoxelEdit) -> bool {
+        let (center, radius) = self.bounding_sphere();
+        let (other_center, other_radius) = other.bounding_sphere();
+        center.distance(other_center) + other_radius <= radius + DEDUPE_TOLERANCE
+    }
+}
+
+/// Client asks the server to apply an edit. Edit id is chosen by the client to match rejections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientRequestEdit {
+    pub edit_id: u32,
+    pub edit: VoxelEdit,
+}
+
+/// Accepted edits of one server tick. Clients apply them in order. Only the requesting client
+/// predicts its own edits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerApplyEdit {
+    pub server_tick: u32,
+    pub edits: Vec<VoxelEdit>,
+}
+
+/// Sent to the requesting client only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerRejectEdit {
+    pub edit_id: u32,
+    pub reason: String,
+}
+
+/// Server side validation of edit requests. Collects accepted edits of a tick & resolves
+/// conflicts between them before they are broadcast
+pub struct EditAuthority {
+    pub max_reach: f32,
+    pub max_radius: f32,
+    pending: Vec<VoxelEdit>,
+}
+
+impl EditAuthority {
+    pub fn new() -> EditAuthority {
+        Self {
+            max_reach: DEFAULT_MAX_EDIT_REACH,
+            max_radius: DEFAULT_MAX_EDIT_RADIUS,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Validates edit against the server side position of the requesting player & queues it for
+    /// this tick. Returns rejection to be sent to the client otherwise
+    pub fn request(
+        &mut self,
+        client: ClientId,
+        request: ClientRequestEdit,
+        player_position: Vec3,
+    ) -> Result<(), ServerRejectEdit> {
+        if let Err(reason) = self.validate(&request.edit, player_position) {
+            warn!("Rejecting edit {} of {client}: {reason}", request.edit_id);
+            return Err(ServerRejectEdit {
+                edit_id: request.edit_id,
+                reason,
+            });
+        }
+        self.pending.push(request.edit);
+        Ok(())
+    }
+
+    fn validate(&self, edit: &VoxelEdit, player_position: Vec3) -> Result<(), String> {
+        let (center, radius) = edit.bounding_sphere();
+        if !center.is_finite() || !radius.is_finite() || radius <= 0.0 {
+            return Err("Invalid edit".to_string());
+        }
+        if radius > self.max_radius {
+            return Err(format!(
+                "Radius {radius:.1} exceeds maximum of {:.1}",
+                self.max_radius
+            ));
+        }
+        let distance = center.distance(player_position);
+        if distance > self.max_reach {
+            return Err(format!(
+                "Out of range: {distance:.1} > {:.1}",
+                self.max_reach
+            ));
+        }
+        Ok(())
+    }
+
+    /// Accepted edits of this tick. Edits completely covered by a larger one, e.g. the same
+    /// explosion reported by multiple clients, are dropped. Returns None if nothing to broadcast
+    pub fn drain_tick(&mut self, server_tick: u32) -> Option<ServerApplyEdit> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let mut pending = std::mem::take(&mut self.pending);
+        // Largest first so smaller edits get merged into them
+        pending.sort_by(|a, b| b.bounding_sphere().1.total_cmp(&a.bounding_sphere().1));
+        let mut edits: Vec<VoxelEdit> = Vec::with_capacity(pending.len());
+        for edit in pending {
+            if !edits.iter().any(|accepted| accepted.covers(&edit)) {
+                edits.push(edit);
+            }
+        }
+        Some(ServerApplyEdit { server_tick, edits })
+    }
+}
+
+impl Default for EditAuthority {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Edits requested by systems during a tick. Applied at a single point of the frame, so no
+/// system observes a partially edited world
+#[derive(Debug, Default)]
+pub struct VoxelEditQueue {
+    edits: Vec<VoxelEdit>,
+}
+
+impl VoxelEditQueue {
+    pub fn new() -> VoxelEditQueue {
+        Self { edits: Vec::new() }
+    }
+
+    pub fn push(&mut self, edit: VoxelEdit) {
+        debug!("Queueing voxel edit {edit:?}");
+        self.edits.push(edit);
+    }
+
+    pub fn drain(&mut self) -> std::vec::Drain<'_, VoxelEdit> {
+        self.edits.drain(..)
+    }
+}
+
+// Edit applied by the client before the server responded
+struct PredictedEdit {
+    edit_id: u32,
+    edit: VoxelEdit,
+    // Voxels as they were before the edit. Restored on rejection
+    overwritten: Vec<(Voxel, Arc<VoxelChunk>)>,
+    // Server edits from this sequence number on are replayed on rollback
+    edit_sequence: u64,
+}
+
+/// Client side edits awaiting the server response. Applied locally right away, so edits feel
+/// instant at high latency, and rolled back if the server rejects them
+#[derive(Default)]
+pub struct EditPrediction {
+    pending: Vec<PredictedEdit>,
+}
+
+impl EditPrediction {
+    pub fn new() -> EditPrediction {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn is_pending(&self, edit_id: u32) -> bool {
+        self.pending
+            .iter()
+            .any(|predicted| predicted.edit_id == edit_id)
+    }
+
+    /// True if any unconfirmed edit touches the region
+    pub fn overlaps(&self, region: &IAabb) -> bool {
+        self.pending
+            .iter()
+            .any(|predicted| predicted.edit.intersects(region))
+    }
+
+    /// Sequence number of the first server edit a rollback may have to replay
+    pub fn oldest_sequence(&self) -> Option<u64> {
+        self.pending
+            .iter()
+            .map(|predicted| predicted.edit_sequence)
+            .min()
+    }
+
+    fn take(&mut self, edit_id: u32) -> Option<PredictedEdit> {
+        let index = self
+            .pending
+            .iter()
+            .position(|predicted| predicted.edit_id == edit_id)?;
+        Some(self.pending.remove(index))
+    }
+}
+
+impl VoxelWorld {
+    /// Applies a requested edit before the server accepted it. Returns changed chunks
+    pub fn predict_edit(
+        &mut self,
+        prediction: &mut EditPrediction,
+        request: &ClientRequestEdit,
+    ) -> Vec<IVec3> {
+        let (changed, overwritten) = self.apply_edit(&request.edit);
+        prediction.pending.push(PredictedEdit {
+            edit_id: request.edit_id,
+            edit: request.edit,
+            overwritten,
+            edit_sequence: self.edit_sequence,
+        });
+        changed.into_iter().collect()
+    }
+
+    /// Server accepted the predicted edit. Voxels removed by it are counted now, as applying
+    /// the broadcast edit does not change the world any further
+    pub fn confirm_edit(&mut self, prediction: &mut EditPrediction, edit_id: u32) {
+        if let Some(predicted) = prediction.take(edit_id) {
+            self.removed_voxels += predicted.overwritten.len();
+        }
+    }
+
+    /// Restores the voxels overwritten by a rejected edit. Server edits applied since & other
+    /// pending predictions are replayed on top. Returns changed chunks
+    pub fn rollback_edit(&mut self, prediction: &mut EditPrediction, edit_id: u32) -> Vec<IVec3> {
+        let Some(rejected) = prediction.take(edit_id) else {
+            return Vec::new();
+        };
+        debug!(
+            "Rolling back edit {edit_id}, restoring {} voxels",
+            rejected.overwritten.len()
+        );
+        let mut changed_chunks = HashSet::new();
+        for (voxel, chunk) in &rejected.overwritten {
+            chunk.insert(&voxel.position.as_ivec3(), *voxel);
+            changed_chunks.insert(chunk.position);
+        }
+        self.modified_chunks.extend(
+            changed_chunks
+                .iter()
+                .map(|position| position / CHUNK_SIZE as i32),
+        );
+        self.chunks_edited(changed_chunks.iter().copied());
+
+        let (center, radius) = rejected.edit.bounding_sphere();
+        let overlaps = |edit: &VoxelEdit| {
+            let (other_center, other_radius) = edit.bounding_sphere();
+            center.distance(other_center) < radius + other_radius
+        };
+        let accepted: Vec<VoxelEdit> = self
+            .edit_log
+            .iter()
+            .filter(|(sequence, edit)| *sequence >= rejected.edit_sequence && overlaps(edit))
+            .map(|(_, edit)| *edit)
+            .collect();
+        for edit in &accepted {
+            changed_chunks.extend(self.apply_edit(edit).0);
+        }
+        for predicted in &mut prediction.pending {
+            if overlaps(&predicted.edit) {
+                let (changed, overwritten) = self.apply_edit(&predicted.edit);
+                changed_chunks.extend(changed);
+                // Restored by the rollback, so owned by this prediction now
+                predicted.overwritten.extend(overwritten);
+            }
+        }
+        changed_chunks.into_iter().collect()
+    }
+
+    /// Applies edits accepted by the server. Returns world positions of all chunks that changed &
+    /// need to be remeshed
+    pub fn apply_edits(&mut self, apply: &ServerApplyEdit) -> Vec<IVec3> {
+        debug!(
+            "Applying {} voxel edits of tick {}",
+            apply.edits.len(),
+            apply.server_tick
+        );
+        let mut changed_chunks = HashSet::new();
+        for edit in &apply.edits {
+            let (changed, removed) = self.apply_edit(edit);
+            changed_chunks.extend(changed);
+            self.removed_voxels += removed.len();
+        }
+        for edit in &apply.edits {
+            self.edit_log.push((self.edit_sequence, *edit));
+            self.edit_sequence += 1;
+        }
+        changed_chunks.into_iter().collect()
+    }
+
+    // Returns changed chunks & the overwritten voxels
+    fn apply_edit(&mut self, edit: &VoxelEdit) -> (HashSet<IVec3>, Vec<(Voxel, Arc<VoxelChunk>)>) {
+        match edit {
+            VoxelEdit::ClearSphere { center, radius } => {
+                self.clear_sphere_recorded(center, *radius)
+            }
+        }
+    }
+
+    /// Solid voxels removed by edits applied since the last call
+    pub fn take_removed_voxels(&mut self) -> usize {
+        std::mem::take(&mut self.removed_voxels)
+    }
+
+    /// Edits applied since the terrain was generated, that are not persisted in all chunks yet
+    pub fn edit_log(&self) -> impl Iterator<Item = &VoxelEdit> {
+        self.edit_log.iter().map(|(_, edit)| edit)
+    }
+
+    /// Chunks generated after an edit was applied would otherwise miss it
+    pub(super) fn reapply_edits(&mut self, regions: &[IAabb]) {
+        let edits: Vec<VoxelEdit> = self
+            .edit_log()
+            .filter(|edit| regions.iter().any(|region| edit.intersects(region)))
+            .copied()
+            .collect();
+        for edit in &edits {
+            self.apply_edit(edit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use crate::{
+        network::test_client,
+        voxels::{CHUNK_SIZE, VoxelKind},
+    };
+
+    use super::*;
+
+    fn explosion(center: Vec3, radius: f32) -> ClientRequestEdit {
+        ClientRequestEdit {
+            edit_id: 0,
+            edit: VoxelEdit::ClearSphere { center, radius },
+        }
+    }
+
+    #[test]
+    fn test_reject_out_of_range_edits() {
+        let mut authority = EditAuthority::new();
+        let far = Vec3::X * (DEFAULT_MAX_EDIT_REACH + 1.0);
+        assert!(
+            authority
+                .request(test_client(1), explosion(far, 3.0), Vec3::ZERO)
+                .is_err()
+        );
+        assert!(
+            authority
+                .request(test_client(1), explosion(far, 3.0), far)
+                .is_ok()
+        );
+        assert!(
+            authority
+                .request(test_client(1), explosion(Vec3::ZERO, 50.0), Vec3::ZERO)
+                .is_err()
+        );
+        assert!(
+            authority
+                .request(test_client(1), explosion(Vec3::NAN, 3.0), Vec3::ZERO)
+                .is_err()
+        );
+        assert_eq!(authority.drain_tick(0).unwrap().edits.len(), 1);
+    }
+
+    #[test]
+    fn test_dedupe_overlapping_explosions() {
+        let mut authority = EditAuthority::new();
+        // Same explosion reported twice, slightly off
+        authority
+            .request(test_client(1), explosion(Vec3::ZERO, 3.0), Vec3::ZERO)
+            .unwrap();
+        authority
+            .request(test_client(1), explosion(Vec3::X * 0.2, 3.0), Vec3::ZERO)
+            .unwrap();
+        // Smaller explosion inside the first one
+        authority
+            .request(test_client(1), explosion(Vec3::Y, 1.0), Vec3::ZERO)
+            .unwrap();
+        // Overlapping, but reaching further
+        authority
+            .request(test_client(1), explosion(Vec3::X * 4.0, 3.0), Vec3::ZERO)
+            .unwrap();
+        let apply = authority.drain_tick(7).unwrap();
+        assert_eq!(apply.server_tick, 7);
+        assert_eq!(apply.edits.len(), 2);
+        // Tick drained
+        assert!(authority.drain_tick(8).is_none());
+    }
+
+    #[test]
+    fn test_queued_edits_report_changed_chunks() {
+        let mut world = VoxelWorld::new_cubic(2);
+        let mut queue = VoxelEditQueue::new();
+        // Crosses the border between the first two chunks in x direction
+        queue.push(VoxelEdit::ClearSphere {
+            center: Vec3::new(CHUNK_SIZE as f32, 4.0, 4.0),
+            radius: 2.0,
+        });
+        // Solely in the air above the world
+        queue.push(VoxelEdit::ClearSphere {
+            center: Vec3::splat(100.0),
+            radius: 2.0,
+        });
+        let mut changed = world.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: queue.drain().collect(),
+        });
+        assert_eq!(queue.drain().count(), 0);
+        changed.sort_by_key(|position| position.x);
+        assert_eq!(changed, vec![IVec3::ZERO, IVec3::X * CHUNK_SIZE as i32]);
+    }
+
+    #[test]
+    fn test_rollback_rejected_prediction() {
+        let solid_count = |world: &VoxelWorld| {
+            world
+                .get_all_voxels()
+                .iter()
+                .filter(|voxel| !matches!(voxel.kind, VoxelKind::Air))
+                .count()
+        };
+        let mut world = VoxelWorld::new_cubic(1);
+        let initial = solid_count(&world);
+        let mut prediction = EditPrediction::new();
+        let request = |edit_id, center| ClientRequestEdit {
+            edit_id,
+            edit: VoxelEdit::ClearSphere {
+                center,
+                radius: 3.0,
+            },
+        };
+
+        let changed = world.predict_edit(&mut prediction, &request(0, Vec3::splat(8.0)));
+        assert_eq!(changed, vec![IVec3::ZERO]);
+        assert!(solid_count(&world) < initial);
+        // Overlapping prediction & server edit of another client arrive before the rejection
+        world.predict_edit(&mut prediction, &request(1, Vec3::new(10.0, 8.0, 8.0)));
+        let accepted = VoxelEdit::ClearSphere {
+            center: Vec3::new(6.0, 8.0, 8.0),
+            radius: 2.0,
+        };
+        world.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![accepted],
+        });
+
+        let changed = world.rollback_edit(&mut prediction, 0);
+        assert_eq!(changed, vec![IVec3::ZERO]);
+        assert!(!prediction.is_pending(0));
+        assert!(prediction.is_pending(1));
+        // Only the edits that were not rejected remain
+        let mut expected = VoxelWorld::new_cubic(1);
+        expected.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![accepted, request(1, Vec3::new(10.0, 8.0, 8.0)).edit],
+        });
+        assert_eq!(solid_count(&world), solid_count(&expected));
+
+        // Rejecting the second one as well restores everything but the server edit
+        world.rollback_edit(&mut prediction, 1);
+        let mut expected = VoxelWorld::new_cubic(1);
+        expected.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![accepted],
+        });
+        assert_eq!(solid_count(&world), solid_count(&expected));
+        assert!(world.rollback_edit(&mut prediction, 1).is_empty());
+    }
+
+    #[test]
+    fn test_confirmed_prediction_counts_removed_voxels() {
+        let mut world = VoxelWorld::new_cubic(1);
+        let mut prediction = EditPrediction::new();
+        let request = ClientRequestEdit {
+            edit_id: 3,
+            edit: VoxelEdit::ClearSphere {
+                center: Vec3::splat(8.0),
+                radius: 3.0,
+            },
+        };
+        world.predict_edit(&mut prediction, &request);
+        assert_eq!(world.take_removed_voxels(), 0);
+        // Broadcast edit finds the voxels already removed
+        world.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![request.edit],
+        });
+        world.confirm_edit(&mut prediction, 3);
+        assert!(!prediction.is_pending(3));
+        assert!(world.take_removed_voxels() > 0);
+    }
+
+    #[test]
+    fn test_restore_edit_log() {
+        let air_count = |world: &VoxelWorld| {
+            world
+                .get_all_voxels()
+                .iter()
+                .filter(|voxel| matches!(voxel.kind, VoxelKind::Air))
+                .count()
+        };
+        let mut world = VoxelWorld::new_cubic(1);
+        world.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![VoxelEdit::ClearSphere {
+                center: Vec3::splat(8.0),
+                radius: 3.0,
+            }],
+        });
+        assert_eq!(world.edit_log().count(), 1);
+        let removed = world.take_removed_voxels();
+        assert_eq!(removed, air_count(&world));
+        assert_eq!(world.take_removed_voxels(), 0);
+
+        // Chunk generated after the edit was applied
+        let mut restored = VoxelWorld::new_cubic(1);
+        restored.edit_log = world.edit_log.clone();
+        restored.reapply_edits(&[IAabb::new(&IVec3::ZERO, CHUNK_SIZE)]);
+        assert!(air_count(&restored) > 0);
+        // Replays were already counted when first applied
+        assert_eq!(restored.take_removed_voxels(), 0);
+        assert_eq!(air_count(&restored), air_count(&world));
+    }
+}
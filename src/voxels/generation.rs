@@ -0,0 +1,107 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::octree::IAabb;
+
+/// Progress of a background chunk generation batch. Shared with the worker thread
+pub struct GenerationProgress {
+    /// Region in **world space** the batch was started for
+    pub region: IAabb,
+    /// Missing chunks in the region when the batch was started
+    pub queued: usize,
+    /// Chunks generated by this batch. At most queued
+    pub batch_size: usize,
+    generated: AtomicUsize,
+    cancelled: AtomicBool,
+    started: Instant,
+}
+
+impl GenerationProgress {
+    pub fn new(region: IAabb, queued: usize, batch_size: usize) -> GenerationProgress {
+        Self {
+            region,
+            queued,
+            batch_size,
+            generated: AtomicUsize::new(0),
+            cancelled: AtomicBool::new(false),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn generated(&self) -> usize {
+        self.generated.load(Ordering::Relaxed)
+    }
+
+    pub fn record_generated(&self) {
+        self.generated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Asks the worker to stop after the current chunk
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn chunks_per_second(&self) -> f32 {
+        chunks_per_second(self.generated(), self.elapsed())
+    }
+
+    /// Estimated time until all chunks queued for the region are generated.
+    /// None until the first chunk is done
+    pub fn eta(&self) -> Option<Duration> {
+        eta(self.queued, self.generated(), self.elapsed())
+    }
+}
+
+fn chunks_per_second(generated: usize, elapsed: Duration) -> f32 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    generated as f32 / elapsed.as_secs_f32()
+}
+
+fn eta(queued: usize, generated: usize, elapsed: Duration) -> Option<Duration> {
+    let rate = chunks_per_second(generated, elapsed);
+    if rate <= 0.0 {
+        return None;
+    }
+    let remaining = queued.saturating_sub(generated);
+    Some(Duration::from_secs_f32(remaining as f32 / rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use super::*;
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(100, 0, Duration::from_secs(1)), None);
+        assert_eq!(chunks_per_second(50, Duration::from_secs(2)), 25.0);
+        assert_eq!(
+            eta(100, 50, Duration::from_secs(2)),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(eta(10, 20, Duration::from_secs(1)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let progress = GenerationProgress::new(IAabb::new(&IVec3::ZERO, 16), 10, 5);
+        assert!(!progress.is_cancelled());
+        progress.record_generated();
+        progress.cancel();
+        assert!(progress.is_cancelled());
+        assert_eq!(progress.generated(), 1);
+    }
+}
@@ -1,6 +1,6 @@
 use glam::IVec3;
 
-use crate::voxels::VoxelChunk;
+use crate::voxels::{CHUNK_SIZE, VoxelChunk, VoxelKind};
 
 pub mod cubic;
 pub mod debug_generator;
@@ -10,4 +10,14 @@ pub mod noise3d;
 pub trait ChunkGenerator: Sync + Send {
     /// Generates voxel chunk for given origin position in **world** space
     fn generate_chunk(&self, chunk_origin: IVec3) -> VoxelChunk;
+
+    /// Kind of the voxel generated at the **world** space position. Generates the whole chunk by
+    /// default. Generators able to evaluate single positions should override this
+    fn sample(&self, position: IVec3) -> VoxelKind {
+        let chunk_size = IVec3::splat(CHUNK_SIZE as i32);
+        let origin = position.div_euclid(chunk_size) * chunk_size;
+        self.generate_chunk(origin)
+            .get(&position)
+            .map_or(VoxelKind::Air, |voxel| voxel.kind)
+    }
 }
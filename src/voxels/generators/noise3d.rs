@@ -21,6 +21,24 @@ impl Noise3DGenerator {
         }
     }
 }
+impl Noise3DGenerator {
+    fn kind_at(&self, x: i32, y: i32, z: i32) -> VoxelKind {
+        // [-1; 1]
+        let noise_val = self.perlin.get([
+            x as f64 * self.scale,
+            y as f64 * self.scale,
+            z as f64 * self.scale,
+        ]);
+        // Noise band -> Hollow caves
+        match noise_val {
+            v if v <= 0.1 || v >= 0.25 => VoxelKind::Air,
+            v if v < 0.15 => VoxelKind::Granite,
+            v if v < 0.2 => VoxelKind::Coal,
+            _ => VoxelKind::Sand,
+        }
+    }
+}
+
 impl ChunkGenerator for Noise3DGenerator {
     fn generate_chunk(&self, chunk_origin: IVec3) -> VoxelChunk {
         let mut chunk = VoxelChunk::new(chunk_origin);
@@ -30,24 +48,13 @@ impl ChunkGenerator for Noise3DGenerator {
 
         // TUNING
         for x in lower_bound.x..upper_bound.x {
-            let fx = x as f64 * self.scale;
             for z in lower_bound.z..upper_bound.z {
-                let fz = z as f64 * self.scale;
                 for y in lower_bound.y..upper_bound.y {
-                    let fy = y as f64 * self.scale;
-                    // [-1; 1]
-                    let noise_val = self.perlin.get([fx, fy, fz]);
-                    // Noise band -> Hollow caves
-                    if noise_val > 0.1 && noise_val < 0.25 {
+                    let kind = self.kind_at(x, y, z);
+                    if kind != VoxelKind::Air {
                         let mut voxel = Voxel::new();
                         voxel.position = Vec3::new(x as f32, y as f32, z as f32);
-                        if noise_val < 0.15 {
-                            voxel.kind = VoxelKind::Granite;
-                        } else if noise_val < 0.2 {
-                            voxel.kind = VoxelKind::Coal;
-                        } else {
-                            voxel.kind = VoxelKind::Sand;
-                        }
+                        voxel.kind = kind;
                         chunk.insert(&IVec3::new(x, y, z), voxel);
                         nodes += 1;
                     }
@@ -60,4 +67,8 @@ impl ChunkGenerator for Noise3DGenerator {
         );
         chunk
     }
+
+    fn sample(&self, position: IVec3) -> VoxelKind {
+        self.kind_at(position.x, position.y, position.z)
+    }
 }
@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap};
+
+use glam::{IVec2, IVec3, Vec3};
+
+use super::{CHUNK_SIZE, VoxelChunk, VoxelKind};
+
+const COLUMNS_PER_CHUNK: usize = CHUNK_SIZE * CHUNK_SIZE;
+
+// Highest solid voxel per column of a single chunk, relative to the chunk origin
+type ChunkTops = [Option<u8>; COLUMNS_PER_CHUNK];
+
+/// Highest solid voxel per XZ column of the loaded chunks. Maintained by the world whenever
+/// chunks are inserted, edited or evicted
+#[derive(Default)]
+pub struct Heightmap {
+    // Keyed by chunk origin x & z, then by chunk origin y
+    columns: HashMap<IVec2, BTreeMap<i32, ChunkTops>>,
+}
+
+fn column_index(x: i32, z: i32) -> usize {
+    let size = CHUNK_SIZE as i32;
+    (x.rem_euclid(size) * size + z.rem_euclid(size)) as usize
+}
+
+fn chunk_column(x: i32, z: i32) -> IVec2 {
+    IVec2::new(x, z).div_euclid(IVec2::splat(CHUNK_SIZE as i32)) * CHUNK_SIZE as i32
+}
+
+impl Heightmap {
+    pub fn new() -> Heightmap {
+        Self::default()
+    }
+
+    /// Recomputes the columns of the chunk, e.g. after it was generated or edited
+    pub fn update_chunk(&mut self, chunk: &VoxelChunk) {
+        let mut tops: ChunkTops = [None; COLUMNS_PER_CHUNK];
+        // Voxels are stored x, y, z major
+        for (index, voxel) in chunk.voxel_slice().iter().enumerate() {
+            if matches!(voxel.kind, VoxelKind::Air) {
+                continue;
+            }
+            let x = index / COLUMNS_PER_CHUNK;
+            let y = index / CHUNK_SIZE % CHUNK_SIZE;
+            let z = index % CHUNK_SIZE;
+            let top = &mut tops[x * CHUNK_SIZE + z];
+            *top = (*top).max(Some(y as u8));
+        }
+        let origin = chunk.position;
+        self.columns
+            .entry(IVec2::new(origin.x, origin.z))
+            .or_default()
+            .insert(origin.y, tops);
+    }
+
+    /// Forgets the chunk at the world space origin, e.g. once it got evicted
+    pub fn remove_chunk(&mut self, origin: IVec3) {
+        let key = IVec2::new(origin.x, origin.z);
+        if let Some(chunks) = self.columns.get_mut(&key) {
+            chunks.remove(&origin.y);
+            if chunks.is_empty() {
+                self.columns.remove(&key);
+            }
+        }
+    }
+
+    /// Y of the highest solid voxel in the column. None if no loaded chunk has one
+    pub fn height(&self, x: i32, z: i32) -> Option<i32> {
+        let index = column_index(x, z);
+        self.columns
+            .get(&chunk_column(x, z))?
+            .iter()
+            .rev()
+            .find_map(|(chunk_y, tops)| tops[index].map(|top| chunk_y + top as i32))
+    }
+
+    /// True if no solid voxel is above the position, i.e. the sky is visible
+    pub fn is_outdoors(&self, position: &Vec3) -> bool {
+        // Voxels are centered on their position
+        let column = (*position + 0.5).floor().as_ivec3();
+        self.height(column.x, column.z)
+            .is_none_or(|height| position.y > height as f32 + 0.5)
+    }
+
+    /// Point on top of the highest solid voxel in the column of the position
+    pub fn surface_point(&self, position: &Vec3) -> Option<Vec3> {
+        let column = (*position + 0.5).floor().as_ivec3();
+        let height = self.height(column.x, column.z)?;
+        Some(Vec3::new(
+            column.x as f32,
+            height as f32 + 0.5,
+            column.z as f32,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxels::Voxel;
+
+    use super::*;
+
+    fn solid(position: IVec3) -> Voxel {
+        Voxel {
+            position: position.as_vec3(),
+            kind: VoxelKind::Granite,
+        }
+    }
+
+    #[test]
+    fn test_highest_voxel_across_chunks() {
+        let size = CHUNK_SIZE as i32;
+        let ground = VoxelChunk::new(IVec3::new(size, 0, 0));
+        ground.insert(
+            &IVec3::new(size + 2, 3, 5),
+            solid(IVec3::new(size + 2, 3, 5)),
+        );
+        let roof = VoxelChunk::new(IVec3::new(size, size, 0));
+        roof.insert(
+            &IVec3::new(size + 2, size + 1, 5),
+            solid(IVec3::new(size + 2, size + 1, 5)),
+        );
+
+        let mut heightmap = Heightmap::new();
+        heightmap.update_chunk(&ground);
+        assert_eq!(heightmap.height(size + 2, 5), Some(3));
+        assert_eq!(heightmap.height(size + 3, 5), None);
+        assert!(heightmap.is_outdoors(&Vec3::new(size as f32 + 2.0, 4.0, 5.0)));
+
+        heightmap.update_chunk(&roof);
+        assert_eq!(heightmap.height(size + 2, 5), Some(size + 1));
+        assert!(!heightmap.is_outdoors(&Vec3::new(size as f32 + 2.0, 4.0, 5.0)));
+        assert_eq!(
+            heightmap.surface_point(&Vec3::new(size as f32 + 2.2, 0.0, 4.9)),
+            Some(Vec3::new(size as f32 + 2.0, size as f32 + 1.5, 5.0))
+        );
+
+        heightmap.remove_chunk(roof.position);
+        assert_eq!(heightmap.height(size + 2, 5), Some(3));
+    }
+}
@@ -0,0 +1,333 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use glam::{IVec3, Vec3};
+use imgui::Ui;
+use log::debug;
+
+use crate::octree::IAabb;
+
+use super::{
+    CHUNK_SIZE, VoxelChunk, VoxelKind, VoxelWorld, chunk_events::ChunkSubscription,
+    chunk_region_around, heightmap::Heightmap, lookup::NEIGHBOR_OFFSETS_6,
+};
+
+const VOXELS_PER_CHUNK: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+// Ticks a chunk has to stay unchanged before it is (re)baked, so chunks being dug into are not
+// rebaked after every single edit
+const BAKE_DELAY_TICKS: u32 = 60;
+// Outdated chunks are collected every few ticks only, scanning the whole view is not free
+const SCAN_INTERVAL_TICKS: u32 = 10;
+// Chunks baked per scan, closest to the player first
+const MAX_BAKES_PER_SCAN: usize = 8;
+// Cells around a voxel that reflect sky light onto it
+const BOUNCE_RADIUS: i32 = 3;
+// Sky visible from two thirds of the surrounding cells lights a voxel fully
+const BOUNCE_STRENGTH: f32 = 1.5;
+// Light of voxels neither sky nor bounce light reach, so caves are not pitch black
+const MIN_LIGHT: f32 = 0.15;
+// Chunks below an edit whose sky light may change. Deeper chunks are rebaked once they are
+// edited themselves
+const SHADOW_DEPTH_CHUNKS: i32 = 4;
+
+/// Precomputed sky & bounce light per voxel of a chunk, in storage order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedLight {
+    revision: u32,
+    levels: Box<[u8]>,
+}
+
+impl BakedLight {
+    /// Light reaching the voxel at the storage index, from 0 to 1
+    pub fn level(&self, index: usize) -> f32 {
+        self.levels[index] as f32 / 255.0
+    }
+
+    /// Revision of the chunk the light was baked for
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+}
+
+// Heights of the columns around a chunk, so bounce light does not query the heightmap per cell
+struct ColumnHeights {
+    min: IVec3,
+    size: i32,
+    heights: Vec<Option<i32>>,
+}
+
+impl ColumnHeights {
+    fn around(heightmap: &Heightmap, chunk: &VoxelChunk, margin: i32) -> ColumnHeights {
+        let min = chunk.position - margin;
+        let size = CHUNK_SIZE as i32 + 2 * margin;
+        let heights = (0..size * size)
+            .map(|index| heightmap.height(min.x + index / size, min.z + index % size))
+            .collect();
+        Self { min, size, heights }
+    }
+
+    // True if nothing solid is above the cell. Cells outside of the table count as open sky
+    fn is_sky(&self, cell: IVec3) -> bool {
+        let x = cell.x - self.min.x;
+        let z = cell.z - self.min.z;
+        if x < 0 || z < 0 || x >= self.size || z >= self.size {
+            return true;
+        }
+        self.heights[(x * self.size + z) as usize].is_none_or(|height| cell.y > height)
+    }
+}
+
+/// Bakes the light of every solid voxel of the chunk: Full sky light if one of its open faces
+/// sees the sky, otherwise light bounced off the surrounding cells that do
+pub fn bake_chunk(world: &VoxelWorld, chunk: &VoxelChunk) -> BakedLight {
+    // Read before the voxels, an edit in between only makes the bake outdated
+    let revision = chunk.revision();
+    let heights = ColumnHeights::around(world.heightmap(), chunk, BOUNCE_RADIUS);
+    let mut lookup = world.lookup();
+    let levels = chunk
+        .voxel_slice()
+        .iter()
+        .enumerate()
+        .map(|(index, voxel)| {
+            if matches!(voxel.kind, VoxelKind::Air) {
+                return 0;
+            }
+            // Inverse of storage_index
+            let position = chunk.position
+                + IVec3::new(
+                    (index / (CHUNK_SIZE * CHUNK_SIZE)) as i32,
+                    (index / CHUNK_SIZE % CHUNK_SIZE) as i32,
+                    (index % CHUNK_SIZE) as i32,
+                );
+            let mut open_faces = NEIGHBOR_OFFSETS_6
+                .iter()
+                .map(|offset| position + offset)
+                .filter(|neighbor| {
+                    lookup
+                        .kind(*neighbor)
+                        .is_none_or(|kind| matches!(kind, VoxelKind::Air))
+                })
+                .peekable();
+            let light = if open_faces.peek().is_none() {
+                // Hidden, never drawn
+                0.0
+            } else if open_faces.any(|neighbor| heights.is_sky(neighbor)) {
+                1.0
+            } else {
+                bounce_light(&heights, position)
+            };
+            (light.max(MIN_LIGHT) * 255.0).round() as u8
+        })
+        .collect();
+    BakedLight { revision, levels }
+}
+
+fn bounce_light(heights: &ColumnHeights, position: IVec3) -> f32 {
+    let cells = IAabb::new_rect(position - BOUNCE_RADIUS, position + BOUNCE_RADIUS + 1);
+    let mut total = 0;
+    let mut lit = 0;
+    for cell in cells.iter_cells() {
+        total += 1;
+        if heights.is_sky(cell) {
+            lit += 1;
+        }
+    }
+    (lit as f32 / total as f32 * BOUNCE_STRENGTH).min(1.0)
+}
+
+#[derive(Default)]
+struct LightBakerStats {
+    baked_chunks: usize,
+    outdated_chunks: usize,
+    last_bake_ms: f32,
+}
+
+/// Bakes the light of the chunks around the player once they stopped changing. Bakes are stored
+/// on the chunks & reused until an edit makes them outdated, so lighting costs nothing per frame
+pub struct LightBaker {
+    pub enabled: bool,
+    tick: u32,
+    chunk_changes: ChunkSubscription,
+    // Chunks in world space that have to be rebaked although their own voxels did not change,
+    // e.g. because an edit above opened them to the sky. Mapped to the tick they were queued at
+    queued: HashMap<IVec3, u32>,
+    // Revision of outdated chunks & the tick it was first seen at. Reset by every edit
+    changed: HashMap<IVec3, (u32, u32)>,
+    stats: LightBakerStats,
+}
+
+impl LightBaker {
+    pub fn new(world: &mut VoxelWorld) -> LightBaker {
+        Self {
+            enabled: true,
+            tick: 0,
+            chunk_changes: world.subscribe_chunk_changes(),
+            queued: HashMap::new(),
+            changed: HashMap::new(),
+            stats: LightBakerStats::default(),
+        }
+    }
+
+    // Queues the chunks whose light the changes of the given chunks may affect: their
+    // neighbors & the chunks below them
+    fn invalidate(&mut self, world: &VoxelWorld, changed_chunks: &[IVec3]) {
+        let size = CHUNK_SIZE as i32;
+        for origin in changed_chunks {
+            let affected = IAabb::new_rect(
+                *origin - IVec3::new(size, size * SHADOW_DEPTH_CHUNKS, size),
+                *origin + 2 * size,
+            );
+            for chunk in world.iter_region_chunks(&affected) {
+                if chunk.baked_light().is_some() {
+                    self.queued.insert(chunk.position, self.tick);
+                }
+            }
+        }
+    }
+
+    /// Bakes chunks within chunk_radius of position that have been outdated for a while
+    pub fn update(&mut self, world: &VoxelWorld, position: &Vec3, chunk_radius: i32) {
+        self.tick += 1;
+        let edited = self.chunk_changes.edited_chunks();
+        self.invalidate(world, &edited);
+        if !self.enabled || !self.tick.is_multiple_of(SCAN_INTERVAL_TICKS) {
+            return;
+        }
+        let region = chunk_region_around(position, chunk_radius);
+        let mut ready: Vec<&Arc<VoxelChunk>> = Vec::new();
+        let mut baked_chunks = 0;
+        let mut outdated_chunks = 0;
+        for chunk in world.iter_region_chunks(&region) {
+            let revision = chunk.revision();
+            let bake_revision = chunk.baked_light().map(|light| light.revision());
+            let since = match (bake_revision, self.queued.get(&chunk.position)) {
+                (Some(baked), None) if baked == revision => {
+                    baked_chunks += 1;
+                    continue;
+                }
+                (Some(baked), Some(queued)) if baked == revision => *queued,
+                _ => {
+                    let (seen_revision, since) = self
+                        .changed
+                        .entry(chunk.position)
+                        .or_insert((revision, self.tick));
+                    if *seen_revision != revision {
+                        *seen_revision = revision;
+                        *since = self.tick;
+                    }
+                    *since
+                }
+            };
+            outdated_chunks += 1;
+            if self.tick - since >= BAKE_DELAY_TICKS {
+                ready.push(chunk);
+            }
+        }
+
+        ready.sort_by_key(|chunk| {
+            let center = chunk.position.as_vec3() + CHUNK_SIZE as f32 / 2.0;
+            center.distance_squared(*position) as u32
+        });
+        let start = Instant::now();
+        let mut baked = 0;
+        for chunk in ready.into_iter().take(MAX_BAKES_PER_SCAN) {
+            chunk.set_baked_light(Some(Arc::new(bake_chunk(world, chunk))));
+            self.queued.remove(&chunk.position);
+            self.changed.remove(&chunk.position);
+            baked += 1;
+        }
+        if baked > 0 {
+            self.stats.last_bake_ms = start.elapsed().as_secs_f32() * 1000.0;
+            debug!(
+                "Baked light of {baked} chunks in {:.2}ms",
+                self.stats.last_bake_ms
+            );
+        }
+        // Forget chunks that left the region or got evicted
+        self.queued
+            .retain(|position, _| region.contains_point(position));
+        self.changed
+            .retain(|position, _| region.contains_point(position));
+        self.stats.baked_chunks = baked_chunks + baked;
+        self.stats.outdated_chunks = outdated_chunks - baked;
+    }
+
+    /// Chunks near the player whose light is outdated, as of the last update
+    pub fn outdated_chunks(&self) -> usize {
+        self.stats.outdated_chunks
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Light baking")
+            .size([250.0, 130.0], imgui::Condition::FirstUseEver)
+            .position([300.0, 500.0], imgui::Condition::FirstUseEver)
+            .collapsed(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.checkbox("Enabled", &mut self.enabled);
+                ui.text(format!("Baked chunks: {}", self.stats.baked_chunks));
+                ui.text(format!("Outdated chunks: {}", self.stats.outdated_chunks));
+                ui.text(format!(
+                    "Memory: {} KiB",
+                    self.stats.baked_chunks * VOXELS_PER_CHUNK / 1024
+                ));
+                ui.text(format!("Last bake: {:.2}ms", self.stats.last_bake_ms));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxels::voxel::storage_index;
+
+    fn level(world: &VoxelWorld, position: IVec3) -> f32 {
+        let chunk_position = position.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        let chunk = world.chunks.get(chunk_position).unwrap();
+        bake_chunk(world, chunk).level(storage_index(position - chunk.position))
+    }
+
+    #[test]
+    fn test_sky_and_bounce_light() {
+        let min_light = (MIN_LIGHT * 255.0).round() / 255.0;
+        let mut world = VoxelWorld::new_cubic(2);
+        // Top of the cube sees the sky, its inside is hidden
+        assert_eq!(level(&world, IVec3::new(10, 31, 10)), 1.0);
+        assert_eq!(level(&world, IVec3::new(10, 20, 10)), min_light);
+
+        // Cave far below the surface only gets the minimum
+        world.clear_sphere(&Vec3::new(16.0, 10.0, 16.0), 3.0);
+        assert_eq!(level(&world, IVec3::new(16, 7, 16)), min_light);
+
+        // Dent in the side of the cube gets bounce light from outside, but less than open ground
+        world.clear_sphere(&Vec3::new(0.0, 16.0, 16.0), 2.5);
+        let dent_floor = IVec3::new(1, 13, 16);
+        let light = level(&world, dent_floor);
+        assert!(light > min_light && light < 1.0, "{light}");
+    }
+
+    #[test]
+    fn test_bake_after_changes_settle() {
+        let mut world = VoxelWorld::new_cubic(1);
+        let mut baker = LightBaker::new(&mut world);
+        let center = Vec3::splat(8.0);
+        let chunk = Arc::clone(world.chunks.get(IVec3::ZERO).unwrap());
+        let tick = |baker: &mut LightBaker, world: &VoxelWorld, ticks: u32| {
+            for _ in 0..ticks {
+                baker.update(world, &center, 1);
+            }
+        };
+        tick(&mut baker, &world, BAKE_DELAY_TICKS);
+        assert!(chunk.baked_light().is_none());
+        tick(&mut baker, &world, SCAN_INTERVAL_TICKS);
+        let first = chunk.baked_light().unwrap();
+        assert_eq!(first.revision(), chunk.revision());
+
+        // Edits keep the old bake until the chunk settled again
+        world.clear_sphere(&Vec3::new(8.0, 15.0, 8.0), 2.0);
+        tick(&mut baker, &world, SCAN_INTERVAL_TICKS);
+        assert_eq!(chunk.baked_light(), Some(Arc::clone(&first)));
+        tick(&mut baker, &world, BAKE_DELAY_TICKS);
+        let second = chunk.baked_light().unwrap();
+        assert_ne!(second, first);
+        assert_eq!(second.revision(), chunk.revision());
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use glam::IVec3;
+
+use super::{BlockView, CHUNK_SIZE, VoxelChunk, VoxelKind, VoxelWorld};
+
+/// Offsets of the face neighbors: -X, +X, -Y, +Y, -Z, +Z
+pub const NEIGHBOR_OFFSETS_6: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::NEG_Y,
+    IVec3::Y,
+    IVec3::NEG_Z,
+    IVec3::Z,
+];
+
+/// Single voxel lookups that keep the last chunk around. Consecutive lookups within the same
+/// chunk skip the chunk index, e.g. when walking over neighboring voxels
+pub struct VoxelLookup<'a> {
+    world: &'a VoxelWorld,
+    // Last looked up chunk position in chunk space & the chunk, if loaded
+    chunk: Option<(IVec3, Option<Arc<VoxelChunk>>)>,
+}
+
+impl VoxelLookup<'_> {
+    /// Voxel at the world position. None if its chunk is not loaded
+    pub fn block(&mut self, position: IVec3) -> Option<BlockView> {
+        self.chunk(position)?.block(&position)
+    }
+
+    /// Only the kind of the voxel, skipping the baked light, e.g. for the bake itself
+    pub fn kind(&mut self, position: IVec3) -> Option<VoxelKind> {
+        Some(self.chunk(position)?.get(&position)?.kind)
+    }
+
+    fn chunk(&mut self, position: IVec3) -> Option<&VoxelChunk> {
+        let chunk_position = position.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        let cached =
+            matches!(&self.chunk, Some((cached_position, _)) if *cached_position == chunk_position);
+        if !cached {
+            let chunk = self.world.chunks.get(chunk_position).cloned();
+            self.chunk = Some((chunk_position, chunk));
+        }
+        self.chunk.as_ref()?.1.as_deref()
+    }
+}
+
+impl VoxelWorld {
+    /// Voxel at the world position. None if its chunk is not loaded
+    pub fn block(&self, position: IVec3) -> Option<BlockView> {
+        let chunk_position = position.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+        self.chunks.get(chunk_position)?.block(&position)
+    }
+
+    /// Face neighbors of the voxel in the order of NEIGHBOR_OFFSETS_6
+    pub fn neighbors6(&self, position: IVec3) -> [Option<BlockView>; 6] {
+        let mut lookup = self.lookup();
+        NEIGHBOR_OFFSETS_6.map(|offset| lookup.block(position + offset))
+    }
+
+    /// Cursor for many single voxel lookups close to each other
+    pub fn lookup(&self) -> VoxelLookup<'_> {
+        VoxelLookup {
+            world: self,
+            chunk: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{octree::IAabb, voxels::lighting::bake_chunk};
+
+    #[test]
+    fn test_block_matches_region_query() {
+        let world = VoxelWorld::new_cubic(2);
+        let region = IAabb::new_rect(IVec3::splat(-2), IVec3::splat(34));
+        let mut lookup = world.lookup();
+        for position in region.iter_cells() {
+            let expected = world
+                .iter_region_voxels(IAabb::new_rect(position, position + IVec3::ONE))
+                .find(|voxel| voxel.position.as_ivec3() == position)
+                .map(|voxel| voxel.kind);
+            assert_eq!(
+                world.block(position).map(|block| block.kind),
+                expected,
+                "{position}"
+            );
+            assert_eq!(lookup.block(position).map(|block| block.kind), expected);
+            assert_eq!(lookup.kind(position), expected);
+        }
+        assert_eq!(
+            world.block(IVec3::new(3, 4, 5)).unwrap().position,
+            IVec3::new(3, 4, 5)
+        );
+    }
+
+    #[test]
+    fn test_block_light_follows_bake() {
+        let world = VoxelWorld::new_cubic(1);
+        let hidden = IVec3::new(8, 8, 8);
+        assert_eq!(world.block(hidden).unwrap().light, 1.0);
+
+        let chunk = world.chunks.get(IVec3::ZERO).unwrap();
+        let baked = bake_chunk(&world, chunk);
+        chunk.set_baked_light(Some(Arc::new(baked)));
+        assert!(world.block(hidden).unwrap().light < 1.0);
+        assert_eq!(world.block(IVec3::new(8, 15, 8)).unwrap().light, 1.0);
+    }
+
+    #[test]
+    fn test_neighbors_across_chunks() {
+        let world = VoxelWorld::new_cubic(2);
+        let neighbors = world.neighbors6(IVec3::new(15, 0, 31));
+        // -X & +X in different chunks, -Y & +Z outside of the world
+        let expected = [true, true, false, true, true, false];
+        for (neighbor, loaded) in neighbors.iter().zip(expected) {
+            assert_eq!(neighbor.is_some(), loaded);
+        }
+        assert!(neighbors.iter().flatten().all(|block| !block.is_air()));
+    }
+}
@@ -1,10 +1,29 @@
+mod chunk_buffer;
+mod chunk_cache;
+pub mod block;
+pub mod chunk_events;
+pub mod chunk_storage;
 mod collision;
+pub mod desync;
+pub mod determinism;
+pub mod edits;
+pub mod generation;
 pub mod generators;
+pub mod heightmap;
+pub mod lighting;
+pub mod lookup;
+mod morton;
+pub mod navigation;
+pub mod regions;
+pub mod spawn;
 pub mod voxel;
 pub mod voxel_renderer;
 pub mod world;
 
+pub use crate::voxels::block::BlockView;
 pub use crate::voxels::voxel::CHUNK_SIZE;
+pub use crate::voxels::voxel::DEFAULT_RENDER_DISTANCE;
+pub use crate::voxels::voxel::chunk_region_around;
 pub use crate::voxels::voxel::Voxel;
 pub use crate::voxels::voxel::VoxelChunk;
 pub use crate::voxels::voxel::VoxelKind;
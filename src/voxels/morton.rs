@@ -0,0 +1,198 @@
+use std::{collections::HashMap, sync::Arc};
+
+use glam::IVec3;
+
+use crate::octree::IAabb;
+
+use super::VoxelChunk;
+
+// Bits per axis, 3 * 21 fit into an u64
+const AXIS_BITS: u32 = 21;
+
+// Inserts two zero bits between each of the lower 21 bits
+fn spread_bits(value: u32) -> u64 {
+    let mut x = value as u64 & ((1 << AXIS_BITS) - 1);
+    x = (x | x << 32) & 0x1f00000000ffff;
+    x = (x | x << 16) & 0x1f0000ff0000ff;
+    x = (x | x << 8) & 0x100f00f00f00f00f;
+    x = (x | x << 4) & 0x10c30c30c30c30c3;
+    (x | x << 2) & 0x1249249249249249
+}
+
+fn compact_bits(value: u64) -> u32 {
+    let mut x = value & 0x1249249249249249;
+    x = (x | x >> 2) & 0x10c30c30c30c30c3;
+    x = (x | x >> 4) & 0x100f00f00f00f00f;
+    x = (x | x >> 8) & 0x1f0000ff0000ff;
+    x = (x | x >> 16) & 0x1f00000000ffff;
+    ((x | x >> 32) & ((1 << AXIS_BITS) - 1)) as u32
+}
+
+/// Interleaves the bits of the coordinates, so nearby positions get nearby keys.
+/// Coordinates have to be within [0, 2^21)
+pub fn morton_encode(position: IVec3) -> u64 {
+    debug_assert!(
+        position.min_element() >= 0 && position.max_element() < 1 << AXIS_BITS,
+        "Position {position} can not be morton encoded"
+    );
+    spread_bits(position.x as u32)
+        | spread_bits(position.y as u32) << 1
+        | spread_bits(position.z as u32) << 2
+}
+
+pub fn morton_decode(key: u64) -> IVec3 {
+    IVec3::new(
+        compact_bits(key) as i32,
+        compact_bits(key >> 1) as i32,
+        compact_bits(key >> 2) as i32,
+    )
+}
+
+/// Flat alternative to the octree: Chunks keyed by their morton encoded chunk space position.
+/// Covers the same cubic region starting at the origin & grows the same way
+pub struct MortonChunkMap {
+    chunks: HashMap<u64, Arc<VoxelChunk>>,
+    // Chunks per axis
+    size: usize,
+}
+
+impl MortonChunkMap {
+    pub fn new(size: usize) -> MortonChunkMap {
+        Self {
+            chunks: HashMap::new(),
+            size,
+        }
+    }
+
+    pub fn insert(&mut self, position: IVec3, chunk: Arc<VoxelChunk>) {
+        debug_assert!(
+            position.min_element() >= 0 && position.max_element() < self.size as i32,
+            "Position {position} out of bounds for size {}",
+            self.size
+        );
+        self.chunks.insert(morton_encode(position), chunk);
+    }
+
+    pub fn remove(&mut self, position: IVec3) -> Option<Arc<VoxelChunk>> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.chunks.remove(&morton_encode(position))
+    }
+
+    pub fn get(&self, position: IVec3) -> Option<&Arc<VoxelChunk>> {
+        if !self.contains(position) {
+            return None;
+        }
+        self.chunks.get(&morton_encode(position))
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    pub fn grow(&mut self) {
+        self.size *= 2;
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Arc<VoxelChunk>> {
+        self.chunks.values()
+    }
+
+    fn contains(&self, position: IVec3) -> bool {
+        position.min_element() >= 0 && position.max_element() < self.size as i32
+    }
+
+    // Region clamped to the covered bounds. None if there is no overlap
+    fn clamp_region(&self, region: IAabb) -> Option<IAabb> {
+        let min = region.min.max(IVec3::ZERO);
+        let max = region.max.min(IVec3::splat(self.size as i32));
+        min.cmplt(max).all().then(|| IAabb::new_rect(min, max))
+    }
+
+    /// Chunks within the region. Small regions look up each cell, regions with more cells than
+    /// chunks scan all chunks instead
+    pub fn iter_region(&self, region: IAabb) -> impl Iterator<Item = &Arc<VoxelChunk>> {
+        let region = self.clamp_region(region);
+        let cells = region.as_ref().map_or(0, |region| region._area() as usize);
+        let (lookup, scan) = match region {
+            Some(region) if cells <= self.chunks.len() => (Some(region), None),
+            Some(region) => (None, Some(region)),
+            None => (None, None),
+        };
+        let looked_up = lookup
+            .into_iter()
+            .flat_map(|region| region.iter_cells())
+            .filter_map(|position| self.chunks.get(&morton_encode(position)));
+        let scanned = scan.into_iter().flat_map(|region| {
+            self.chunks.iter().filter_map(move |(key, chunk)| {
+                region.contains_point(&morton_decode(*key)).then_some(chunk)
+            })
+        });
+        looked_up.chain(scanned)
+    }
+
+    /// Positions within the region without a chunk
+    pub fn iter_empty_within_region(&self, region: IAabb) -> impl Iterator<Item = IVec3> {
+        self.clamp_region(region)
+            .into_iter()
+            .flat_map(|region| region.iter_cells())
+            .filter(|position| !self.chunks.contains_key(&morton_encode(*position)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morton_roundtrip() {
+        assert_eq!(morton_encode(IVec3::ZERO), 0);
+        assert_eq!(morton_encode(IVec3::X), 1);
+        assert_eq!(morton_encode(IVec3::Y), 2);
+        assert_eq!(morton_encode(IVec3::Z), 4);
+        assert_eq!(morton_encode(IVec3::ONE), 7);
+        assert_eq!(morton_encode(IVec3::new(2, 0, 0)), 8);
+        for position in [
+            IVec3::new(5, 17, 300),
+            IVec3::new(1023, 0, 77),
+            IVec3::splat((1 << AXIS_BITS) - 1),
+        ] {
+            assert_eq!(morton_decode(morton_encode(position)), position);
+        }
+    }
+
+    #[test]
+    fn test_region_lookup_and_scan_agree() {
+        let mut map = MortonChunkMap::new(8);
+        for position in IAabb::new(&IVec3::ZERO, 8).iter_cells() {
+            if (position.x + position.y + position.z) % 3 == 0 {
+                map.insert(position, Arc::new(VoxelChunk::new(position)));
+            }
+        }
+        let count = |region: IAabb| map.iter_region(region).count();
+        let expected = |region: IAabb| {
+            region
+                .iter_cells()
+                .filter(|p| (p.x + p.y + p.z) % 3 == 0)
+                .count()
+        };
+        // Lookup of each cell
+        let small = IAabb::new_rect(IVec3::new(1, 2, 3), IVec3::new(4, 4, 5));
+        assert_eq!(count(small.clone()), expected(small));
+        // Scan of all chunks, clamped to the covered region
+        let large = IAabb::new_rect(IVec3::splat(-4), IVec3::new(8, 6, 12));
+        assert_eq!(
+            count(large),
+            expected(IAabb::new_rect(IVec3::ZERO, IVec3::new(8, 6, 8)))
+        );
+
+        let empty = map
+            .iter_empty_within_region(IAabb::new(&IVec3::ZERO, 2))
+            .count();
+        assert_eq!(empty, 6);
+        assert!(map.remove(IVec3::ZERO).is_some());
+        assert!(map.get(IVec3::ZERO).is_none());
+        assert!(map.get(IVec3::splat(-1)).is_none());
+    }
+}
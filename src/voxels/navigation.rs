@@ -0,0 +1,309 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use glam::{IVec3, Vec3};
+
+use crate::octree::IAabb;
+
+use super::{CHUNK_SIZE, VoxelKind, VoxelWorld};
+
+/// Air cells required above the ground voxel for a cell to be walkable
+pub const NAV_HEADROOM: i32 = 2;
+/// Highest ledge agents can step onto
+pub const MAX_STEP_UP: i32 = 1;
+/// Deepest ledge agents can drop down from
+pub const MAX_DROP: i32 = 2;
+// Bounds the cost of a single path query
+const MAX_SEARCH_NODES: usize = 4096;
+// How far below a position walkable ground is searched for, e.g. for flying targets
+const MAX_SNAP_DEPTH: i32 = 16;
+const HORIZONTAL_DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// Walkable surface of the voxel world: Air cells on top of a solid voxel with enough headroom.
+/// Built per chunk, so edits only rebuild the affected chunks
+pub struct NavGraph {
+    // Walkable cells by world position of the chunk containing their ground voxel
+    chunks: HashMap<IVec3, Vec<IVec3>>,
+    walkable: HashSet<IVec3>,
+}
+
+impl NavGraph {
+    pub fn new() -> NavGraph {
+        Self {
+            chunks: HashMap::new(),
+            walkable: HashSet::new(),
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.walkable.len()
+    }
+
+    pub fn is_walkable(&self, cell: IVec3) -> bool {
+        self.walkable.contains(&cell)
+    }
+
+    /// Builds chunks loaded since the last sync & drops evicted ones
+    pub fn sync(&mut self, voxel_world: &VoxelWorld) {
+        let loaded: HashSet<IVec3> = voxel_world.chunk_positions().into_iter().collect();
+        let mut changed: Vec<IVec3> = loaded
+            .iter()
+            .filter(|position| !self.chunks.contains_key(position))
+            .copied()
+            .collect();
+        changed.extend(
+            self.chunks
+                .keys()
+                .filter(|position| !loaded.contains(position)),
+        );
+        if !changed.is_empty() {
+            self.rebuild_chunks(voxel_world, &changed);
+        }
+    }
+
+    /// Rebuilds walkable cells after voxels of the given chunks (world positions) changed
+    pub fn rebuild_chunks(&mut self, voxel_world: &VoxelWorld, chunk_positions: &[IVec3]) {
+        let below = IVec3::Y * CHUNK_SIZE as i32;
+        // Headroom of the top layer reaches into the chunk above
+        let affected: HashSet<IVec3> = chunk_positions
+            .iter()
+            .flat_map(|position| [*position, position - below])
+            .collect();
+        for position in affected {
+            self.remove_chunk(position);
+            if is_chunk_loaded(voxel_world, position) {
+                self.build_chunk(voxel_world, position);
+            }
+        }
+    }
+
+    fn remove_chunk(&mut self, chunk_position: IVec3) {
+        if let Some(cells) = self.chunks.remove(&chunk_position) {
+            for cell in cells {
+                self.walkable.remove(&cell);
+            }
+        }
+    }
+
+    fn build_chunk(&mut self, voxel_world: &VoxelWorld, chunk_position: IVec3) {
+        let size = CHUNK_SIZE as i32;
+        let height = size + NAV_HEADROOM;
+        let region = IAabb::new_rect(
+            chunk_position,
+            chunk_position + IVec3::new(size - 1, height - 1, size - 1),
+        );
+        // Voxels outside of the loaded world count as air
+        let index = |local: IVec3| ((local.x * height + local.y) * size + local.z) as usize;
+        let mut solid = vec![false; (size * height * size) as usize];
+        for voxel in voxel_world.iter_region_voxels(region) {
+            if !matches!(voxel.kind, VoxelKind::Air) {
+                solid[index(voxel.position.round().as_ivec3() - chunk_position)] = true;
+            }
+        }
+
+        let mut cells = Vec::new();
+        for x in 0..size {
+            for z in 0..size {
+                for y in 0..size {
+                    let has_headroom =
+                        (1..=NAV_HEADROOM).all(|above| !solid[index(IVec3::new(x, y + above, z))]);
+                    if solid[index(IVec3::new(x, y, z))] && has_headroom {
+                        cells.push(chunk_position + IVec3::new(x, y + 1, z));
+                    }
+                }
+            }
+        }
+        self.walkable.extend(cells.iter().copied());
+        self.chunks.insert(chunk_position, cells);
+    }
+
+    /// Walkable cell at or below the position
+    pub fn nearest_node(&self, position: Vec3) -> Option<IVec3> {
+        let cell = position.round().as_ivec3();
+        (-1..=MAX_SNAP_DEPTH)
+            .flat_map(|depth| {
+                [IVec3::ZERO]
+                    .into_iter()
+                    .chain(HORIZONTAL_DIRECTIONS)
+                    .map(move |offset| cell - IVec3::Y * depth + offset)
+            })
+            .find(|candidate| self.walkable.contains(candidate))
+    }
+
+    fn neighbors(&self, cell: IVec3) -> impl Iterator<Item = IVec3> + '_ {
+        HORIZONTAL_DIRECTIONS
+            .into_iter()
+            .flat_map(move |direction| {
+                (-MAX_DROP..=MAX_STEP_UP).map(move |step| cell + direction + IVec3::Y * step)
+            })
+            .filter(|neighbor| self.walkable.contains(neighbor))
+    }
+
+    /// A* search between walkable cells. Path includes start & goal.
+    /// If the goal is unreachable, leads to the explored cell closest to the goal instead
+    pub fn find_path(&self, start: IVec3, goal: IVec3) -> Option<Vec<IVec3>> {
+        if !self.walkable.contains(&start) {
+            return None;
+        }
+        let distance = |cell: IVec3| (goal - cell).as_vec3().length();
+        let mut open = BinaryHeap::from([OpenCell {
+            estimate: distance(start),
+            cell: start,
+        }]);
+        let mut costs = HashMap::from([(start, 0.0)]);
+        let mut came_from: HashMap<IVec3, IVec3> = HashMap::new();
+        let mut closed = HashSet::new();
+        let mut closest = (distance(start), start);
+        while let Some(OpenCell { cell, .. }) = open.pop() {
+            if !closed.insert(cell) {
+                continue;
+            }
+            if distance(cell) < closest.0 {
+                closest = (distance(cell), cell);
+            }
+            if cell == goal || closed.len() > MAX_SEARCH_NODES {
+                break;
+            }
+            let cost = costs[&cell];
+            for neighbor in self.neighbors(cell) {
+                let neighbor_cost = cost + (neighbor - cell).as_vec3().length();
+                if costs
+                    .get(&neighbor)
+                    .is_none_or(|&known| neighbor_cost < known)
+                {
+                    costs.insert(neighbor, neighbor_cost);
+                    came_from.insert(neighbor, cell);
+                    open.push(OpenCell {
+                        estimate: neighbor_cost + distance(neighbor),
+                        cell: neighbor,
+                    });
+                }
+            }
+        }
+
+        let mut path = vec![closest.1];
+        while let Some(previous) = came_from.get(path.last().unwrap()) {
+            path.push(*previous);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+fn is_chunk_loaded(voxel_world: &VoxelWorld, chunk_position: IVec3) -> bool {
+    voxel_world
+        .iter_region_chunks(&IAabb::new(&chunk_position, CHUNK_SIZE))
+        .next()
+        .is_some()
+}
+
+// Min-heap entry ordered by estimated total cost
+#[derive(PartialEq)]
+struct OpenCell {
+    estimate: f32,
+    cell: IVec3,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Removes single voxels. Returns modified chunks
+    fn dig(voxel_world: &mut VoxelWorld, cells: impl Iterator<Item = IVec3>) -> Vec<IVec3> {
+        let mut changed = HashSet::new();
+        for cell in cells {
+            changed.extend(voxel_world.clear_sphere(&cell.as_vec3(), 0.6));
+        }
+        changed.into_iter().collect()
+    }
+
+    fn assert_walkable_path(nav: &NavGraph, path: &[IVec3]) {
+        assert!(path.iter().all(|cell| nav.is_walkable(*cell)));
+        for step in path.windows(2) {
+            let delta = step[1] - step[0];
+            assert_eq!(delta.x.abs() + delta.z.abs(), 1, "{delta}");
+            assert!((-MAX_DROP..=MAX_STEP_UP).contains(&delta.y), "{delta}");
+        }
+    }
+
+    #[test]
+    fn test_walkable_surface() {
+        let voxel_world = VoxelWorld::new_cubic(2);
+        let mut nav = NavGraph::new();
+        nav.sync(&voxel_world);
+        let size = CHUNK_SIZE as i32;
+        // Only the top of the world, chunk boundary at y = 16 is covered by the chunk above
+        assert_eq!(nav.node_count(), (2 * size * 2 * size) as usize);
+        assert!(nav.is_walkable(IVec3::new(0, 2 * size, 0)));
+        assert!(!nav.is_walkable(IVec3::new(0, size, 0)));
+        assert_eq!(
+            nav.nearest_node(Vec3::new(3.2, 40.0, 5.8)),
+            Some(IVec3::new(3, 32, 6))
+        );
+    }
+
+    #[test]
+    fn test_rebuild_after_edit() {
+        let mut voxel_world = VoxelWorld::new_cubic(1);
+        let mut nav = NavGraph::new();
+        nav.sync(&voxel_world);
+        let changed = dig(&mut voxel_world, [IVec3::new(4, 15, 4)].into_iter());
+        nav.rebuild_chunks(&voxel_world, &changed);
+        assert!(!nav.is_walkable(IVec3::new(4, 16, 4)));
+        assert!(nav.is_walkable(IVec3::new(4, 15, 4)));
+        assert_eq!(nav.node_count(), CHUNK_SIZE * CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_path_around_trench() {
+        let mut voxel_world = VoxelWorld::new_cubic(1);
+        let mut nav = NavGraph::new();
+        nav.sync(&voxel_world);
+        // Too deep to climb out of, open at the far end
+        let trench = (0..13).flat_map(|z| (13..16).map(move |y| IVec3::new(8, y, z)));
+        let changed = dig(&mut voxel_world, trench);
+        nav.rebuild_chunks(&voxel_world, &changed);
+
+        let start = IVec3::new(2, 16, 2);
+        let goal = IVec3::new(14, 16, 2);
+        let path = nav.find_path(start, goal).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert_walkable_path(&nav, &path);
+        assert!(path.iter().all(|cell| cell.x != 8 || cell.z >= 13));
+        assert!(path.len() > 13);
+    }
+
+    #[test]
+    fn test_unreachable_goal_leads_closest() {
+        let mut voxel_world = VoxelWorld::new_cubic(1);
+        let mut nav = NavGraph::new();
+        nav.sync(&voxel_world);
+        let trench = (0..16).flat_map(|z| (13..16).map(move |y| IVec3::new(8, y, z)));
+        let changed = dig(&mut voxel_world, trench);
+        nav.rebuild_chunks(&voxel_world, &changed);
+
+        let path = nav
+            .find_path(IVec3::new(2, 16, 2), IVec3::new(14, 16, 2))
+            .unwrap();
+        assert_walkable_path(&nav, &path);
+        assert_eq!(path.last(), Some(&IVec3::new(7, 16, 2)));
+        assert_eq!(nav.find_path(IVec3::new(2, 10, 2), IVec3::ZERO), None);
+    }
+}
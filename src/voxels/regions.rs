@@ -0,0 +1,490 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use glam::{IVec3, Vec3};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CHUNK_SIZE, VoxelChunk, VoxelKind, VoxelWorld, block::VoxelMetadata,
+    chunk_events::ChunkChangeKind, edits::EditPrediction,
+};
+
+/// Chunks per region file along each axis
+pub const REGION_SIZE: i32 = 32;
+/// Chunks further away from the player are persisted & dropped from memory.
+/// Has to exceed the generation radius, otherwise chunks are evicted right after generation
+pub const DEFAULT_CHUNK_KEEP_RADIUS: i32 = 16;
+/// Keep radius for the given render distance. Leaves a margin, so chunks leaving the render
+/// distance are not evicted & reloaded right away when turning back
+pub fn chunk_keep_radius(render_distance: i32) -> i32 {
+    DEFAULT_CHUNK_KEEP_RADIUS.max(2 * render_distance)
+}
+// Regions further away than this many regions from the player are unloaded
+const REGION_KEEP_RADIUS: i32 = 1;
+
+// Voxel kinds of a chunk as (kind, run length). Chunks are mostly air
+pub(super) type EncodedChunk = Vec<(u8, u16)>;
+
+/// Everything of a chunk that differs from generated terrain: Its voxel kinds & the sparse
+/// metadata of its voxels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredChunk {
+    runs: EncodedChunk,
+    metadata: Vec<(u16, VoxelMetadata)>,
+}
+
+impl StoredChunk {
+    pub fn of(chunk: &VoxelChunk) -> StoredChunk {
+        Self {
+            runs: encode_chunk(&chunk.kinds()),
+            metadata: chunk.metadata_entries(),
+        }
+    }
+
+    /// Overrides the voxels & metadata of the chunk
+    pub fn restore(&self, chunk: &VoxelChunk) -> Result<(), String> {
+        chunk.restore_kinds(&decode_chunk(&self.runs)?)?;
+        chunk.restore_metadata(&self.metadata)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Region {
+    // Keyed by chunk position in octree space
+    chunks: HashMap<IVec3, StoredChunk>,
+}
+
+struct LoadedRegion {
+    region: Region,
+    dirty: bool,
+}
+
+pub(super) fn encode_chunk(kinds: &[VoxelKind]) -> EncodedChunk {
+    let mut runs: EncodedChunk = Vec::new();
+    for kind in kinds {
+        let value = *kind as u8;
+        match runs.last_mut() {
+            Some((last, count)) if *last == value && *count < u16::MAX => *count += 1,
+            _ => runs.push((value, 1)),
+        }
+    }
+    runs
+}
+
+pub(super) fn decode_chunk(runs: &EncodedChunk) -> Result<Vec<VoxelKind>, String> {
+    let mut kinds = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
+    for (value, count) in runs {
+        let kind = VoxelKind::from_u8(*value).ok_or(format!("Invalid voxel kind {value}"))?;
+        kinds.extend(std::iter::repeat_n(kind, *count as usize));
+    }
+    Ok(kinds)
+}
+
+/// Persists modified chunks in region files of REGION_SIZE³ chunks. Region files are loaded
+/// lazily once a chunk within them is requested & unloaded again when the player moves away
+pub struct RegionStore {
+    dir: PathBuf,
+    loaded: HashMap<IVec3, LoadedRegion>,
+}
+
+impl RegionStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<RegionStore, Box<dyn Error>> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            loaded: HashMap::new(),
+        })
+    }
+
+    fn region_of(chunk_position: IVec3) -> IVec3 {
+        chunk_position.div_euclid(IVec3::splat(REGION_SIZE))
+    }
+
+    fn region_path(&self, region: IVec3) -> PathBuf {
+        self.dir
+            .join(format!("r.{}.{}.{}.bin", region.x, region.y, region.z))
+    }
+
+    fn region(&mut self, region: IVec3) -> Result<&mut LoadedRegion, Box<dyn Error>> {
+        if !self.loaded.contains_key(&region) {
+            let path = self.region_path(region);
+            let loaded = match path.exists() {
+                true => {
+                    debug!("Loading region file {}", path.display());
+                    bincode::deserialize(&fs::read(&path)?)?
+                }
+                false => Region::default(),
+            };
+            self.loaded.insert(
+                region,
+                LoadedRegion {
+                    region: loaded,
+                    dirty: false,
+                },
+            );
+        }
+        Ok(self.loaded.get_mut(&region).expect("Region loaded above"))
+    }
+
+    /// Stored state of a chunk. None if the chunk has never been modified
+    pub fn load_chunk(
+        &mut self,
+        chunk_position: IVec3,
+    ) -> Result<Option<StoredChunk>, Box<dyn Error>> {
+        let loaded = self.region(Self::region_of(chunk_position))?;
+        Ok(loaded.region.chunks.get(&chunk_position).cloned())
+    }
+
+    /// Kept in memory until flushed
+    pub fn store_chunk(
+        &mut self,
+        chunk_position: IVec3,
+        chunk: &VoxelChunk,
+    ) -> Result<(), Box<dyn Error>> {
+        let loaded = self.region(Self::region_of(chunk_position))?;
+        loaded
+            .region
+            .chunks
+            .insert(chunk_position, StoredChunk::of(chunk));
+        loaded.dirty = true;
+        Ok(())
+    }
+
+    /// Writes all modified regions to disk
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let dirty: Vec<IVec3> = self
+            .loaded
+            .iter()
+            .filter(|(_, loaded)| loaded.dirty)
+            .map(|(region, _)| *region)
+            .collect();
+        for region in dirty {
+            self.write_region(region)?;
+        }
+        Ok(())
+    }
+
+    // Temp file & rename, so a crash never leaves a half written region behind
+    fn write_region(&mut self, region: IVec3) -> Result<(), Box<dyn Error>> {
+        let path = self.region_path(region);
+        let tmp_path = path.with_extension("tmp");
+        let Some(loaded) = self.loaded.get_mut(&region) else {
+            return Ok(());
+        };
+        let bytes = bincode::serialize(&loaded.region)?;
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, &path)?;
+        loaded.dirty = false;
+        debug!(
+            "Wrote region file {} ({} bytes)",
+            path.display(),
+            bytes.len()
+        );
+        Ok(())
+    }
+
+    /// Flushes & unloads regions too far away from the chunk
+    pub fn unload_distant(&mut self, chunk_position: IVec3) -> Result<(), Box<dyn Error>> {
+        let center = Self::region_of(chunk_position);
+        let distant: Vec<IVec3> = self
+            .loaded
+            .keys()
+            .filter(|region| (**region - center).abs().max_element() > REGION_KEEP_RADIUS)
+            .copied()
+            .collect();
+        for region in distant {
+            self.write_region(region)?;
+            self.loaded.remove(&region);
+            debug!("Unloaded region {region}");
+        }
+        Ok(())
+    }
+
+    pub fn loaded_regions(&self) -> usize {
+        self.loaded.len()
+    }
+}
+
+impl VoxelWorld {
+    /// Restores all stored chunks that are already generated & persists future modifications
+    pub fn attach_region_store(&mut self, mut store: RegionStore) -> Result<(), Box<dyn Error>> {
+        for chunk in self.chunks.all_chunks() {
+            restore_chunk(&mut store, &chunk)?;
+        }
+        info!("Attached region store {}", store.dir.display());
+        self.regions = Some(store);
+        Ok(())
+    }
+
+    /// Overrides a freshly generated chunk with its stored state
+    pub(super) fn restore_stored_chunk(
+        &mut self,
+        chunk: &VoxelChunk,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.regions.as_mut() {
+            Some(store) => restore_chunk(store, chunk),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes all chunks modified since the last call to their region files. Edits the pending
+    /// predictions may replay on rollback stay in the edit log
+    pub fn persist_regions(&mut self, prediction: &EditPrediction) -> Result<(), Box<dyn Error>> {
+        let Some(store) = self.regions.as_mut() else {
+            return Err("No region store attached".into());
+        };
+        let modified: Vec<Arc<VoxelChunk>> = self
+            .modified_chunks
+            .iter()
+            .filter_map(|position| self.chunks.get(*position).cloned())
+            .collect();
+        for chunk in &modified {
+            store.store_chunk(chunk.position / CHUNK_SIZE as i32, chunk)?;
+        }
+        store.flush()?;
+        debug!("Persisted {} modified chunks", modified.len());
+        self.modified_chunks.clear();
+        // Stored chunks contain their edits. Edits reaching chunks never generated are kept, so
+        // they are replayed once those chunks are generated
+        let chunks = &self.chunks;
+        let keep_from = prediction.oldest_sequence().unwrap_or(u64::MAX);
+        self.edit_log.retain(|(sequence, edit)| {
+            *sequence >= keep_from
+                || edit
+                    .chunk_positions()
+                    .any(|position| chunks.get(position).is_none())
+        });
+        Ok(())
+    }
+
+    /// Drops chunks out of range of the player from memory. Modified chunks are persisted first,
+    /// all others are generated again once the player returns
+    pub fn evict_distant_chunks(
+        &mut self,
+        player_position: &Vec3,
+        keep_radius: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let center = self.world_space_pos_to_chunk_space_pos(player_position);
+        let distant: Vec<IVec3> = self
+            .chunks
+            .all_chunks()
+            .iter()
+            .map(|chunk| chunk.position / CHUNK_SIZE as i32)
+            .filter(|position| (*position - center).abs().max_element() > keep_radius)
+            .collect();
+        if distant.is_empty() {
+            return Ok(());
+        }
+        let distant_modified: HashSet<IVec3> = distant
+            .iter()
+            .filter(|position| self.modified_chunks.contains(position))
+            .copied()
+            .collect();
+        if let Some(store) = self.regions.as_mut() {
+            for position in &distant_modified {
+                if let Some(chunk) = self.chunks.get(*position) {
+                    store.store_chunk(*position, chunk)?;
+                }
+            }
+            store.unload_distant(center)?;
+            self.modified_chunks
+                .retain(|p| !distant_modified.contains(p));
+        } else if !distant_modified.is_empty() {
+            // Without store edits are replayed from the edit log on regeneration
+            debug!(
+                "Evicting {} modified chunks without region store",
+                distant_modified.len()
+            );
+        }
+        for position in &distant {
+            self.chunks.remove(*position);
+            self.heightmap.remove_chunk(position * CHUNK_SIZE as i32);
+        }
+        self.chunk_cache.invalidate();
+        self.chunk_changes.publish(
+            ChunkChangeKind::Unloaded,
+            distant.iter().map(|position| position * CHUNK_SIZE as i32),
+        );
+        debug!("Evicted {} distant chunks", distant.len());
+        Ok(())
+    }
+}
+
+fn restore_chunk(store: &mut RegionStore, chunk: &VoxelChunk) -> Result<(), Box<dyn Error>> {
+    if let Some(stored) = store.load_chunk(chunk.position / CHUNK_SIZE as i32)? {
+        stored.restore(chunk)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::voxels::{
+        edits::{ClientRequestEdit, ServerApplyEdit, VoxelEdit},
+        generators::cubic::CubicGenerator,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_keep_radius_exceeds_render_distance() {
+        assert_eq!(chunk_keep_radius(4), DEFAULT_CHUNK_KEEP_RADIUS);
+        assert!(chunk_keep_radius(16) > 16);
+    }
+
+    fn air_count(world: &VoxelWorld) -> usize {
+        world
+            .get_all_voxels()
+            .iter()
+            .filter(|voxel| matches!(voxel.kind, VoxelKind::Air))
+            .count()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_chunk_encoding_roundtrip() {
+        let mut kinds = vec![VoxelKind::Air; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE];
+        kinds[7] = VoxelKind::Granite;
+        let encoded = encode_chunk(&kinds);
+        assert_eq!(encoded.len(), 3);
+        assert!(
+            decode_chunk(&encoded)
+                .unwrap()
+                .iter()
+                .zip(&kinds)
+                .all(|(a, b)| *a as u8 == *b as u8)
+        );
+    }
+
+    #[test]
+    fn test_persisted_edits_survive_regeneration() {
+        let dir = test_dir("voxie_test_regions_persist");
+        let mut world = VoxelWorld::new_cubic(2);
+        world
+            .attach_region_store(RegionStore::open(&dir).unwrap())
+            .unwrap();
+        world.apply_edits(&crate::voxels::edits::ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![VoxelEdit::ClearSphere {
+                center: Vec3::splat(8.0),
+                radius: 3.0,
+            }],
+        });
+        let edited_air = air_count(&world);
+        let crop = IVec3::new(2, 2, 2);
+        let metadata = VoxelMetadata {
+            growth_stage: 3,
+            ..Default::default()
+        };
+        world.set_metadata(crop, Some(metadata));
+        world.persist_regions(&EditPrediction::new()).unwrap();
+        assert!(dir.join("r.0.0.0.bin").exists());
+
+        // Fresh terrain restored from region file
+        let mut restored = VoxelWorld::new(2, Arc::new(CubicGenerator::new(CHUNK_SIZE)));
+        assert!(air_count(&restored) < edited_air);
+        restored
+            .attach_region_store(RegionStore::open(&dir).unwrap())
+            .unwrap();
+        assert_eq!(air_count(&restored), edited_air);
+        assert_eq!(restored.block(crop).unwrap().metadata, Some(metadata));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_persist_keeps_unwritten_edits() {
+        let dir = test_dir("voxie_test_regions_edit_log");
+        let mut world = VoxelWorld::new_cubic(1);
+        world
+            .attach_region_store(RegionStore::open(&dir).unwrap())
+            .unwrap();
+        let sphere = |center| VoxelEdit::ClearSphere {
+            center,
+            radius: 3.0,
+        };
+        // Reaches into the chunk next to the only generated one
+        let outside = sphere(Vec3::new(16.0, 8.0, 8.0));
+        let inside = sphere(Vec3::new(4.0, 8.0, 8.0));
+        world.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![outside, inside],
+        });
+        let mut prediction = EditPrediction::new();
+        let request = ClientRequestEdit {
+            edit_id: 0,
+            edit: sphere(Vec3::splat(8.0)),
+        };
+        world.predict_edit(&mut prediction, &request);
+        let accepted = sphere(Vec3::new(10.0, 8.0, 8.0));
+        world.apply_edits(&ServerApplyEdit {
+            server_tick: 1,
+            edits: vec![accepted],
+        });
+
+        world.persist_regions(&prediction).unwrap();
+        assert_eq!(
+            world.edit_log().copied().collect::<Vec<_>>(),
+            vec![outside, accepted]
+        );
+        // Server edit applied after the prediction is still replayed
+        world.rollback_edit(&mut prediction, 0);
+        let mut expected = VoxelWorld::new_cubic(1);
+        expected.apply_edits(&ServerApplyEdit {
+            server_tick: 0,
+            edits: vec![outside, inside, accepted],
+        });
+        assert_eq!(air_count(&world), air_count(&expected));
+
+        world.persist_regions(&prediction).unwrap();
+        assert_eq!(world.edit_log().copied().collect::<Vec<_>>(), vec![outside]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_evict_distant_chunks() {
+        let dir = test_dir("voxie_test_regions_evict");
+        let mut world = VoxelWorld::new_cubic(4);
+        world
+            .attach_region_store(RegionStore::open(&dir).unwrap())
+            .unwrap();
+        world.clear_sphere(&Vec3::splat(56.0), 3.0);
+        world.evict_distant_chunks(&Vec3::ZERO, 1).unwrap();
+        // 2x2x2 chunks around the origin are kept
+        assert_eq!(world.chunks.all_chunks().len(), 8);
+        assert!(world.modified_chunks.is_empty());
+        // Edited chunk persisted before eviction
+        let stored = world
+            .regions
+            .as_mut()
+            .unwrap()
+            .load_chunk(IVec3::splat(3))
+            .unwrap()
+            .unwrap();
+        assert!(
+            decode_chunk(&stored.runs)
+                .unwrap()
+                .iter()
+                .any(|kind| matches!(kind, VoxelKind::Air))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
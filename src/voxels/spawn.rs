@@ -0,0 +1,139 @@
+use glam::{IVec2, IVec3, Vec3};
+
+use super::{VoxelKind, generators::ChunkGenerator};
+
+/// Air voxels required above the ground, so the player does not spawn with the head in rock
+pub const SPAWN_CLEARANCE: i32 = 3;
+
+/// Where & how far to look for a spawn point
+#[derive(Debug, Clone)]
+pub struct SpawnSearch {
+    /// World space position around which the column is searched. Only x & z are used
+    pub desired: Vec3,
+    /// Highest ground considered, e.g. the top of the generated world
+    pub max_height: i32,
+    /// Columns up to this horizontal distance in voxels are tried
+    pub radius: i32,
+    /// Distance in voxels between tried columns
+    pub step: i32,
+}
+
+impl SpawnSearch {
+    pub fn new(desired: Vec3, max_height: i32) -> SpawnSearch {
+        Self {
+            desired,
+            max_height,
+            radius: 64,
+            step: 4,
+        }
+    }
+}
+
+// Highest solid voxel in the column with enough air above it
+fn highest_ground(generator: &dyn ChunkGenerator, column: IVec2, max_height: i32) -> Option<i32> {
+    let mut air_above = SPAWN_CLEARANCE;
+    for y in (0..max_height + SPAWN_CLEARANCE).rev() {
+        let is_air = generator.sample(IVec3::new(column.x, y, column.y)) == VoxelKind::Air;
+        if !is_air && air_above >= SPAWN_CLEARANCE && y <= max_height {
+            return Some(y);
+        }
+        air_above = if is_air { air_above + 1 } else { 0 };
+    }
+    None
+}
+
+// Column offsets ordered by rings of growing distance
+fn column_offsets(radius: i32, step: i32) -> impl Iterator<Item = IVec2> {
+    let step = step.max(1);
+    std::iter::once(IVec2::ZERO).chain((1..=radius / step).flat_map(move |ring| {
+        let r = ring * step;
+        (-ring..=ring).flat_map(move |i| {
+            let i = i * step;
+            let sides = [
+                IVec2::new(i, -r),
+                IVec2::new(i, r),
+                IVec2::new(-r, i),
+                IVec2::new(r, i),
+            ];
+            // Corners are part of the top & bottom rows already
+            let count = if i.abs() == r { 2 } else { 4 };
+            sides.into_iter().take(count)
+        })
+    }))
+}
+
+/// Position right on top of the highest solid ground near the desired position, sampled
+/// from the generator. Returns the surface point the player's feet should be placed at.
+/// None if no column within the radius has ground with enough room above
+pub fn find_spawn_point(generator: &dyn ChunkGenerator, search: &SpawnSearch) -> Option<Vec3> {
+    let center = IVec2::new(
+        search.desired.x.floor() as i32,
+        search.desired.z.floor() as i32,
+    );
+    column_offsets(search.radius, search.step)
+        .map(|offset| center + offset)
+        .filter(|column| column.min_element() >= 0)
+        .find_map(|column| {
+            let y = highest_ground(generator, column, search.max_height)?;
+            // Voxels are centered on their position
+            Some(Vec3::new(column.x as f32, y as f32 + 0.5, column.y as f32))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxels::{CHUNK_SIZE, VoxelChunk};
+
+    use super::*;
+
+    // Ground up to y = 10 for x < 8, solid rock up to the sky above
+    struct StepGenerator;
+
+    impl ChunkGenerator for StepGenerator {
+        fn generate_chunk(&self, chunk_origin: IVec3) -> VoxelChunk {
+            VoxelChunk::new(chunk_origin)
+        }
+
+        fn sample(&self, position: IVec3) -> VoxelKind {
+            match position.x < 8 && position.y > 10 {
+                true => VoxelKind::Air,
+                false => VoxelKind::Granite,
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_on_surface() {
+        let max_height = 2 * CHUNK_SIZE as i32;
+        let search = SpawnSearch::new(Vec3::new(3.2, 50.0, 5.0), max_height);
+        let spawn = find_spawn_point(&StepGenerator, &search).unwrap();
+        assert_eq!(spawn, Vec3::new(3.0, 10.5, 5.0));
+
+        // Solid up to the top: Searches neighboring columns
+        let search = SpawnSearch::new(Vec3::new(12.0, 0.0, 5.0), max_height);
+        let spawn = find_spawn_point(&StepGenerator, &search).unwrap();
+        assert_eq!(spawn, Vec3::new(4.0, 10.5, 13.0));
+
+        let search = SpawnSearch {
+            radius: 2,
+            ..SpawnSearch::new(Vec3::new(12.0, 0.0, 5.0), max_height)
+        };
+        assert_eq!(find_spawn_point(&StepGenerator, &search), None);
+    }
+
+    #[test]
+    fn test_column_offsets_are_unique_rings() {
+        let offsets: Vec<IVec2> = column_offsets(4, 2).collect();
+        // Center, 3x3 ring & 5x5 ring of step 2
+        assert_eq!(offsets.len(), 1 + 8 + 16);
+        let mut unique = offsets.clone();
+        unique.sort_by_key(|o| (o.x, o.y));
+        unique.dedup();
+        assert_eq!(unique.len(), offsets.len());
+        assert!(
+            offsets
+                .windows(2)
+                .all(|w| { w[0].abs().max_element() <= w[1].abs().max_element() })
+        );
+    }
+}
@@ -1,14 +1,23 @@
-use std::sync::{
-    RwLock,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
 };
 
 use glam::{IVec3, Vec3};
 
 use crate::octree::{AABB, IAabb};
 
+use super::{
+    block::{BlockView, VoxelMetadata},
+    lighting::BakedLight,
+};
+
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum VoxelKind {
     Coal = 0,
     Granite = 1,
@@ -18,9 +27,46 @@ pub enum VoxelKind {
 }
 
 impl VoxelKind {
+    pub const SOLID: [VoxelKind; 4] = [
+        VoxelKind::Coal,
+        VoxelKind::Granite,
+        VoxelKind::Dirt,
+        VoxelKind::Sand,
+    ];
+
     pub fn material_index(self) -> u32 {
         self as u32
     }
+
+    pub fn from_u8(value: u8) -> Option<VoxelKind> {
+        match value {
+            0 => Some(VoxelKind::Coal),
+            1 => Some(VoxelKind::Granite),
+            2 => Some(VoxelKind::Dirt),
+            3 => Some(VoxelKind::Sand),
+            99 => Some(VoxelKind::Air),
+            _ => None,
+        }
+    }
+
+    /// Leaves & grass sway in the wind. Exhaustive, so new kinds have to decide
+    pub fn is_vegetation(self) -> bool {
+        match self {
+            VoxelKind::Coal
+            | VoxelKind::Granite
+            | VoxelKind::Dirt
+            | VoxelKind::Sand
+            | VoxelKind::Air => false,
+        }
+    }
+
+    /// Share of fall damage taken when landing on this kind. Loose ground cushions the impact
+    pub fn landing_damage_factor(self) -> f32 {
+        match self {
+            VoxelKind::Sand => 0.5,
+            _ => 1.0,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -51,11 +97,40 @@ pub struct VoxelChunk {
     voxels: RwLock<Box<[[[Voxel; CHUNK_SIZE]; CHUNK_SIZE]; CHUNK_SIZE]>>, // owned, contiguous memory
     /// Minimum corner (world pos)
     pub position: IVec3,
-    is_dirty: AtomicBool,
+    // Bumped by every change of the voxels
+    revision: AtomicU32,
+    version: AtomicU32,
+    baked_light: RwLock<Option<Arc<BakedLight>>>,
+    // Keyed by storage index. Only voxels that have any
+    metadata: RwLock<HashMap<u16, VoxelMetadata>>,
+}
+
+/// Index into the flattened voxels of a chunk. Voxels are stored x, y, z major
+pub fn storage_index(relative_pos: IVec3) -> usize {
+    (relative_pos.x as usize * CHUNK_SIZE + relative_pos.y as usize) * CHUNK_SIZE
+        + relative_pos.z as usize
 }
 
 // TODO: Would be cleaner to have this as a world parameter
 pub const CHUNK_SIZE: usize = 16;
+/// Chunks around the camera that are generated & drawn along each axis
+pub const DEFAULT_RENDER_DISTANCE: i32 = 8;
+
+/// Chunk-grid snapped region reaching chunk_radius chunks from position along each axis
+pub fn chunk_region_around(position: &Vec3, chunk_radius: i32) -> IAabb {
+    let chunk = (*position / CHUNK_SIZE as f32).as_ivec3();
+    IAabb::new_rect(
+        (chunk - chunk_radius) * CHUNK_SIZE as i32,
+        (chunk + chunk_radius) * CHUNK_SIZE as i32,
+    )
+}
+
+// Source of chunk versions. Shared by all chunks
+static NEXT_VERSION: AtomicU32 = AtomicU32::new(0);
+
+fn next_version() -> u32 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
 
 impl VoxelChunk {
     // New chunk at **world_pos**
@@ -65,20 +140,15 @@ impl VoxelChunk {
                 .map(|_| [(); CHUNK_SIZE].map(|_| [(); CHUNK_SIZE].map(|_| Voxel::new()))),
         );
         Self {
-            is_dirty: AtomicBool::new(true),
             position,
             voxels: RwLock::new(voxels),
+            revision: AtomicU32::new(0),
+            version: AtomicU32::new(next_version()),
+            baked_light: RwLock::new(None),
+            metadata: RwLock::new(HashMap::new()),
         }
     }
 
-    pub fn set_clean(&self) {
-        self.is_dirty.store(false, Ordering::Relaxed);
-    }
-
-    pub fn is_dirty(&self) -> bool {
-        self.is_dirty.load(Ordering::Relaxed)
-    }
-
     pub fn insert(&self, world_pos: &IVec3, voxel: Voxel) {
         let relative_pos = world_pos - self.position;
         debug_assert!(
@@ -94,7 +164,116 @@ impl VoxelChunk {
         debug_assert!(y < CHUNK_SIZE);
         debug_assert!(z < CHUNK_SIZE);
         self.voxels.write().unwrap()[x][y][z] = voxel;
-        self.is_dirty.store(true, Ordering::Relaxed);
+        self.metadata
+            .write()
+            .unwrap()
+            .remove(&(storage_index(relative_pos) as u16));
+        self.revision.fetch_add(1, Ordering::Relaxed);
+        self.version.store(next_version(), Ordering::Relaxed);
+    }
+
+    /// Changes with every edit of the voxels, e.g. to tell whether a bake is outdated
+    pub fn revision(&self) -> u32 {
+        self.revision.load(Ordering::Relaxed)
+    }
+
+    /// Changes with every edit of the voxels & every new bake. Consumers store the version they
+    /// last processed & compare, e.g. to tell whether a mesh is outdated. Unique across chunks,
+    /// so a chunk generated again never matches the version of its evicted predecessor
+    pub fn version(&self) -> u32 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// Last light bake. Kept after edits until the chunk is rebaked, so edited chunks do not
+    /// flash while they are being dug into
+    pub fn baked_light(&self) -> Option<Arc<BakedLight>> {
+        self.baked_light.read().unwrap().clone()
+    }
+
+    /// Stores the bake & bumps the version, so the light shows up once the chunk is remeshed
+    pub fn set_baked_light(&self, light: Option<Arc<BakedLight>>) {
+        *self.baked_light.write().unwrap() = light;
+        self.version.store(next_version(), Ordering::Relaxed);
+    }
+
+    /// Voxel at the world position. None if the position is outside of the chunk
+    pub fn get(&self, world_pos: &IVec3) -> Option<Voxel> {
+        let relative_pos = world_pos - self.position;
+        if relative_pos.min_element() < 0 || relative_pos.max_element() >= CHUNK_SIZE as i32 {
+            return None;
+        }
+        let x = relative_pos.x as usize;
+        let y = relative_pos.y as usize;
+        let z = relative_pos.z as usize;
+        Some(self.voxels.read().unwrap()[x][y][z])
+    }
+
+    /// Voxel at the world position with its baked light. None if the position is outside of the
+    /// chunk
+    pub fn block(&self, world_pos: &IVec3) -> Option<BlockView> {
+        let voxel = self.get(world_pos)?;
+        let light = self
+            .baked_light
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(1.0, |light| {
+                light.level(storage_index(*world_pos - self.position))
+            });
+        Some(BlockView {
+            position: *world_pos,
+            kind: voxel.kind,
+            light,
+            metadata: self.metadata(world_pos),
+        })
+    }
+
+    /// Metadata of the voxel at the world position, if it has any
+    pub fn metadata(&self, world_pos: &IVec3) -> Option<VoxelMetadata> {
+        let index = storage_index(*world_pos - self.position) as u16;
+        self.metadata.read().unwrap().get(&index).copied()
+    }
+
+    /// Attaches metadata to the voxel at the world position or removes it
+    pub fn set_metadata(&self, world_pos: &IVec3, metadata: Option<VoxelMetadata>) {
+        let relative_pos = world_pos - self.position;
+        debug_assert!(
+            relative_pos.min_element() >= 0 && relative_pos.max_element() < CHUNK_SIZE as i32,
+            "relative_pos out of bounds {relative_pos}"
+        );
+        let index = storage_index(relative_pos) as u16;
+        let mut entries = self.metadata.write().unwrap();
+        match metadata {
+            Some(metadata) => entries.insert(index, metadata),
+            None => entries.remove(&index),
+        };
+        self.version.store(next_version(), Ordering::Relaxed);
+    }
+
+    /// All metadata as (storage index, metadata), sorted by index, e.g. to persist the chunk
+    pub fn metadata_entries(&self) -> Vec<(u16, VoxelMetadata)> {
+        let mut entries: Vec<(u16, VoxelMetadata)> = self
+            .metadata
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(index, metadata)| (*index, *metadata))
+            .collect();
+        entries.sort_by_key(|(index, _)| *index);
+        entries
+    }
+
+    /// Replaces all metadata with the entries of metadata_entries
+    pub fn restore_metadata(&self, entries: &[(u16, VoxelMetadata)]) -> Result<(), String> {
+        if let Some((index, _)) = entries
+            .iter()
+            .find(|(index, _)| *index as usize >= CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE)
+        {
+            return Err(format!("Invalid metadata index {index}"));
+        }
+        *self.metadata.write().unwrap() = entries.iter().copied().collect();
+        self.version.store(next_version(), Ordering::Relaxed);
+        Ok(())
     }
 
     /// Returns flattened list of voxels
@@ -105,6 +284,31 @@ impl VoxelChunk {
         unsafe { std::slice::from_raw_parts(ptr, len) }
     }
 
+    /// Voxel kinds in storage order, e.g. to persist the chunk
+    pub fn kinds(&self) -> Vec<VoxelKind> {
+        self.voxel_slice().iter().map(|voxel| voxel.kind).collect()
+    }
+
+    /// Overrides all voxels with kinds in storage order
+    pub fn restore_kinds(&self, kinds: &[VoxelKind]) -> Result<(), String> {
+        if kinds.len() != CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+            return Err(format!("Invalid chunk size {}", kinds.len()));
+        }
+        let mut voxels = self.voxels.write().unwrap();
+        for (index, kind) in kinds.iter().enumerate() {
+            let x = index / (CHUNK_SIZE * CHUNK_SIZE);
+            let y = index / CHUNK_SIZE % CHUNK_SIZE;
+            let z = index % CHUNK_SIZE;
+            voxels[x][y][z] = Voxel {
+                position: (self.position + IVec3::new(x as i32, y as i32, z as i32)).as_vec3(),
+                kind: *kind,
+            };
+        }
+        self.revision.fetch_add(1, Ordering::Relaxed);
+        self.version.store(next_version(), Ordering::Relaxed);
+        Ok(())
+    }
+
     pub fn get_bb_i(&self) -> IAabb {
         IAabb::new(&self.position, CHUNK_SIZE)
     }
@@ -127,45 +331,22 @@ impl VoxelChunk {
         })
     }
 
-    pub fn iter_region(&self, region_world_space: &IAabb) -> VoxelChunkIterator {
-        let chunk_bb = self.get_bb_i();
-        let optional_overlap = chunk_bb.intersection(region_world_space);
-        // Will only check indices within overlap
-        if let Some(overlap) = optional_overlap {
-            let min_x = (overlap.min.x - 1 - self.position.x).max(0) as usize;
-            let max_x = (overlap.max.x + 1 - self.position.x).min(CHUNK_SIZE as i32) as usize;
-            let min_y = (overlap.min.y - 1 - self.position.y).max(0) as usize;
-            let max_y = (overlap.max.y + 1 - self.position.y).min(CHUNK_SIZE as i32) as usize;
-            let min_z = (overlap.min.z - 1 - self.position.z).max(0) as usize;
-            let max_z = (overlap.max.z + 1 - self.position.z).min(CHUNK_SIZE as i32) as usize;
-            VoxelChunkIterator {
-                x: min_x,
-                y: min_y,
-                z: min_z,
-                min_y,
-                min_z,
-                max_x,
-                max_y,
-                max_z,
-                chunk: self,
-            }
-        } else {
-            VoxelChunkIterator {
-                x: 0,
-                y: 0,
-                z: 0,
-                min_y: 0,
-                min_z: 0,
-                max_x: 0,
-                max_y: 0,
-                max_z: 0,
-                chunk: self,
-            }
-        }
+    /// Yields exactly the voxels whose AABB (position +- 0.5) intersects the region. As region
+    /// bounds are integers, these are all voxels with min <= position <= max
+    pub fn iter_region(&self, region_world_space: &IAabb) -> VoxelChunkIterator<&VoxelChunk> {
+        VoxelChunkIterator::new(self, region_world_space)
+    }
+
+    /// Same as iter_region, but the iterator keeps the chunk alive instead of borrowing it
+    pub fn iter_region_shared(
+        self: &Arc<Self>,
+        region_world_space: &IAabb,
+    ) -> VoxelChunkIterator<Arc<VoxelChunk>> {
+        VoxelChunkIterator::new(Arc::clone(self), region_world_space)
     }
 }
 
-pub struct VoxelChunkIterator<'a> {
+pub struct VoxelChunkIterator<C: Deref<Target = VoxelChunk>> {
     x: usize,
     y: usize,
     z: usize,
@@ -174,10 +355,36 @@ pub struct VoxelChunkIterator<'a> {
     max_x: usize,
     max_y: usize,
     max_z: usize,
-    chunk: &'a VoxelChunk,
+    chunk: C,
+}
+
+impl<C: Deref<Target = VoxelChunk>> VoxelChunkIterator<C> {
+    fn new(chunk: C, region_world_space: &IAabb) -> Self {
+        // Voxels are centered on their position, so they reach half a voxel past the chunk bb.
+        // Clamp indices instead of intersecting with the chunk bb to not miss them at the edges
+        let min = (region_world_space.min - chunk.position).max(IVec3::ZERO);
+        let max =
+            (region_world_space.max + 1 - chunk.position).min(IVec3::splat(CHUNK_SIZE as i32));
+        // Will only check indices within overlap
+        let (min, max) = match min.cmplt(max).all() {
+            true => (min.as_uvec3(), max.as_uvec3()),
+            false => Default::default(),
+        };
+        Self {
+            x: min.x as usize,
+            y: min.y as usize,
+            z: min.z as usize,
+            min_y: min.y as usize,
+            min_z: min.z as usize,
+            max_x: max.x as usize,
+            max_y: max.y as usize,
+            max_z: max.z as usize,
+            chunk,
+        }
+    }
 }
 
-impl<'a> Iterator for VoxelChunkIterator<'a> {
+impl<C: Deref<Target = VoxelChunk>> Iterator for VoxelChunkIterator<C> {
     type Item = Voxel;
 
     #[allow(clippy::never_loop)]
@@ -204,14 +411,47 @@ impl<'a> Iterator for VoxelChunkIterator<'a> {
 
 #[cfg(test)]
 mod test {
-    use glam::IVec3;
+    use glam::{IVec3, Vec3};
 
     use crate::{
         octree::IAabb,
         voxels::{CHUNK_SIZE, VoxelChunk},
     };
 
-    use super::{Voxel, VoxelKind};
+    use super::{Voxel, VoxelKind, chunk_region_around};
+
+    #[test]
+    fn test_version_changes_with_voxels_and_light() {
+        let chunk = VoxelChunk::new(IVec3::ZERO);
+        let other = VoxelChunk::new(IVec3::ZERO);
+        assert_ne!(chunk.version(), other.version());
+
+        // Consumers compare against the version they processed last
+        let meshed = chunk.version();
+        assert_eq!(chunk.version(), meshed);
+        chunk.insert(
+            &IVec3::ONE,
+            Voxel {
+                position: Vec3::ONE,
+                kind: VoxelKind::Dirt,
+            },
+        );
+        let edited = chunk.version();
+        assert_ne!(edited, meshed);
+        assert_eq!(chunk.revision(), 1);
+
+        // New light changes the version, but not the revision of the voxels
+        chunk.set_baked_light(None);
+        assert_ne!(chunk.version(), edited);
+        assert_eq!(chunk.revision(), 1);
+    }
+
+    #[test]
+    fn test_chunk_region_around() {
+        let region = chunk_region_around(&Vec3::new(40.0, 8.0, 0.0), 2);
+        assert_eq!(region.min, IVec3::new(0, -32, -32));
+        assert_eq!(region.max, IVec3::new(64, 32, 32));
+    }
 
     fn query_region(chunk: &VoxelChunk, bbi_world_space: &IAabb, res: &mut Vec<Voxel>) {
         res.extend(
@@ -268,6 +508,21 @@ mod test {
         assert_eq!(res.len(), 1);
     }
 
+    #[test]
+    fn query_region_touching_edge_from_negative() {
+        let chunk = VoxelChunk::new(IVec3::ZERO);
+
+        chunk.voxels.write().unwrap()[0][0][0] = solid_voxel();
+
+        // Voxel at the origin reaches half a voxel into negative space
+        let region = IAabb::new_rect(IVec3::new(-2, -2, -2), IVec3::new(0, 0, 0));
+
+        let mut res = Vec::new();
+        query_region(&chunk, &region, &mut res);
+
+        assert_eq!(res.len(), 1);
+    }
+
     #[test]
     fn query_region_only_air_voxels() {
         let chunk = VoxelChunk::new(IVec3::ZERO);
@@ -1,26 +1,69 @@
-use std::{collections::HashMap, error::Error, mem::offset_of, path::Path, rc::Rc, time::Instant};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use bytemuck::{Pod, Zeroable};
-use glam::{IVec3, Quat, Vec3};
-use glow::{HasContext, NativeBuffer};
-use log::{debug, error, trace};
+use glam::{IVec3, Vec3};
+use glow::{HasContext, NativeBuffer, NativeVertexArray};
+use log::{debug, error, info, trace};
 
 use crate::{
     cameras::camera::Camera,
     meshes::objmesh::ObjMesh,
     octree::IAabb,
-    renderer::{shader::Shader, texture::Texture},
-    util::SimpleMovingAverage,
-    voxels::{CHUNK_SIZE, VoxelChunk, VoxelKind, VoxelWorld},
+    renderer::{
+        feature_flags::{Feature, FeatureFlags},
+        indirect,
+        shader::Shader,
+        texture::Texture,
+        viewport::Viewport,
+    },
+    util::{MemoryTag, RollingHistory, ScratchPool, ScratchPoolStats, SimpleMovingAverage},
+    voxels::{
+        CHUNK_SIZE, DEFAULT_RENDER_DISTANCE, VoxelChunk, VoxelKind, VoxelWorld,
+        chunk_buffer::{
+            ChunkInstanceBuffer, ChunkVertexData, CubeVertexBuffers, setup_chunk_vertex_attributes,
+        },
+        chunk_region_around,
+    },
 };
 
-const CAMERA_FOV_RADIUS: i32 = 8;
+// Newly streamed chunks dither in over this duration instead of popping into view
+const FADE_IN_DURATION: Duration = Duration::from_millis(600);
+// Number of frames kept for debug graphs
+const DEBUG_HISTORY_FRAMES: usize = 300;
+
+thread_local! {
+    // Instance data is only needed until uploaded, so meshing reuses the same buffers instead of
+    // allocating one per chunk rebuild
+    static INSTANCE_SCRATCH: RefCell<ScratchPool<ChunkVertexData>> =
+        RefCell::new(ScratchPool::new(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE));
+}
+
+/// Allocation counters of the mesh building scratch buffers of the current thread
+pub fn mesh_scratch_stats() -> ScratchPoolStats {
+    INSTANCE_SCRATCH.with_borrow(|pool| pool.stats())
+}
 
 struct VoxelRendererDebugInfo {
     visible_voxels: i32,
     visible_chunks: usize,
     chunks_within_render_bb: usize,
+    // Chunk meshes (re-)built during the current frame
+    meshed_chunks: usize,
+    // Chunks within render region that are dirty or not meshed yet (e.g. outside of frustum)
+    pending_remesh: usize,
+    draw_calls: usize,
     render_time: SimpleMovingAverage,
+
+    visible_chunks_history: RollingHistory,
+    meshed_chunks_history: RollingHistory,
+    pending_remesh_history: RollingHistory,
+    render_time_history: RollingHistory,
 }
 
 impl VoxelRendererDebugInfo {
@@ -29,9 +72,36 @@ impl VoxelRendererDebugInfo {
             visible_voxels: 0,
             visible_chunks: 0,
             chunks_within_render_bb: 0,
+            meshed_chunks: 0,
+            pending_remesh: 0,
+            draw_calls: 0,
             render_time: SimpleMovingAverage::new(100),
+            visible_chunks_history: RollingHistory::new(DEBUG_HISTORY_FRAMES),
+            meshed_chunks_history: RollingHistory::new(DEBUG_HISTORY_FRAMES),
+            pending_remesh_history: RollingHistory::new(DEBUG_HISTORY_FRAMES),
+            render_time_history: RollingHistory::new(DEBUG_HISTORY_FRAMES),
         }
     }
+
+    /// Records counters of the current frame into the rolling graphs
+    fn push_frame(&mut self, render_time_micros: f32) {
+        self.visible_chunks_history.push(self.visible_chunks as f32);
+        self.meshed_chunks_history.push(self.meshed_chunks as f32);
+        self.pending_remesh_history.push(self.pending_remesh as f32);
+        self.render_time_history.push(render_time_micros);
+    }
+}
+
+/// Plots a rolling graph with the latest value as overlay
+fn plot_history(ui: &imgui::Ui, label: &str, history: &RollingHistory) {
+    let latest = history.latest().unwrap_or(0.0);
+    ui.plot_lines(label, history.values())
+        .values_offset(history.offset())
+        .overlay_text(format!("{latest:.0}"))
+        .scale_min(0.0)
+        .scale_max(history.max().max(1.0))
+        .graph_size([0.0, 40.0])
+        .build();
 }
 
 pub struct VoxelWorldRenderer {
@@ -39,14 +109,22 @@ pub struct VoxelWorldRenderer {
     gl: Rc<glow::Context>,
     texture: Texture,
     shader: Shader,
-    vertex_position_vbo: NativeBuffer,
-    vertex_normal_vbo: NativeBuffer,
-    vertex_tex_coord_vbo: NativeBuffer,
-    vertex_count: usize,
+    cube: CubeVertexBuffers,
+    // Shared instance buffer for indirect multi-draw. None if not supported by the GL context
+    chunk_buffer: Option<ChunkInstanceBuffer>,
 
     // Hash map so we can easily access and replace chunk meshes at given position
     // Contains only chunks within current FoV
     chunk_meshes: HashMap<IVec3, Rc<VoxelChunkMesh>>,
+    // Chunks around the camera that are drawn along each axis
+    render_distance: i32,
+    frustum_culling: bool,
+    // Opaque chunks are drawn one by one if disabled, even if the shared buffer is available
+    multi_draw: bool,
+    // Darkens voxels open to the sky, from 0 to 1
+    wetness: f32,
+    // Offset of the top of vegetation voxels in world space
+    wind_sway: Vec3,
 
     debug_info: VoxelRendererDebugInfo,
 }
@@ -54,11 +132,7 @@ pub struct VoxelWorldRenderer {
 impl VoxelWorldRenderer {
     pub fn new(gl: &Rc<glow::Context>) -> Result<VoxelWorldRenderer, Box<dyn Error>> {
         // Setup shader
-        let shader = Shader::new(
-            gl,
-            "assets/shaders/voxel.vert",
-            "assets/shaders/cube-diffuse.frag",
-        )?;
+        let shader = Shader::new(gl, "assets/shaders/voxel.vert", "assets/shaders/voxel.frag")?;
 
         // Load vertex data from mesh
         let mut mesh = ObjMesh::new();
@@ -86,27 +160,82 @@ impl VoxelWorldRenderer {
             gl.buffer_data_u8_slice(gl::ARRAY_BUFFER, tex_coords_bytes, gl::STATIC_DRAW);
             gl.bind_buffer(gl::ARRAY_BUFFER, None);
             // Load texture
-            let texture = Texture::new(gl, Path::new("assets/textures/atlas.png"))
+            // Decoded in the background, chunks are drawn with a placeholder until then
+            let texture = Texture::load_async(gl, Path::new("assets/textures/atlas.png"))
                 .expect("Could not load texture");
 
+            let cube = CubeVertexBuffers {
+                position_vbo: positions_vbo,
+                normal_vbo: normals_vbo,
+                tex_coord_vbo: tex_coords_vbo,
+                vertex_count,
+            };
+            let chunk_buffer = if indirect::is_multi_draw_indirect_supported() {
+                Some(ChunkInstanceBuffer::new(gl, cube)?)
+            } else {
+                None
+            };
+
             Ok(Self {
+                chunk_buffer,
                 chunk_meshes: HashMap::new(),
+                render_distance: DEFAULT_RENDER_DISTANCE,
+                frustum_culling: true,
+                multi_draw: true,
+                wetness: 0.0,
+                wind_sway: Vec3::ZERO,
+                cube,
                 debug_info: VoxelRendererDebugInfo::new(),
                 gl: Rc::clone(gl),
                 shader,
                 texture,
-                vertex_count,
-                vertex_normal_vbo: normals_vbo,
-                vertex_position_vbo: positions_vbo,
-                vertex_tex_coord_vbo: tex_coords_vbo,
             })
         }
     }
 
+    /// Number of chunks drawn around the camera along each axis
+    pub fn set_render_distance(&mut self, render_distance: i32) {
+        self.render_distance = render_distance.max(1);
+    }
+
+    pub fn set_wetness(&mut self, wetness: f32) {
+        self.wetness = wetness.clamp(0.0, 1.0);
+    }
+
+    pub fn set_wind_sway(&mut self, sway: Vec3) {
+        self.wind_sway = sway;
+    }
+
+    pub fn set_features(&mut self, features: &FeatureFlags) {
+        self.frustum_culling = features.is_enabled(Feature::FrustumCulling);
+        self.multi_draw = features.is_enabled(Feature::MultiDrawIndirect);
+    }
+
+    /// Chunk meshes drawn in the last frame
+    pub fn visible_chunks(&self) -> usize {
+        self.debug_info.visible_chunks
+    }
+
+    /// Chunks in view waiting to be remeshed, as of the last frame
+    pub fn pending_remesh(&self) -> usize {
+        self.debug_info.pending_remesh
+    }
+
+    /// Re-uploads the texture atlas if it is among the changed files
+    pub fn reload_assets(&mut self, changed: &[PathBuf]) {
+        if !changed.iter().any(|path| path == self.texture.path()) {
+            return;
+        }
+        match self.texture.reload() {
+            Ok(()) => info!("Reloaded texture {}", self.texture.path().display()),
+            Err(err) => error!("Unable to reload {}: {err}", self.texture.path().display()),
+        }
+    }
+
     pub fn render_ui(&mut self, ui: &mut imgui::Ui) {
         // Get display size
         let display_size = ui.io().display_size;
-        let window_size = [200.0, 100.0];
+        let window_size = [350.0, 320.0];
         // Compute top-right position
         let pos = [display_size[0] - window_size[0], 0.0];
         ui.window("Voxels")
@@ -126,9 +255,32 @@ impl VoxelWorldRenderer {
                     format_with_commas(self.debug_info.visible_voxels as u64)
                 ));
                 ui.text(format!(
-                    "Time to render: {:.0}ns",
+                    "Draw calls: {} ({})",
+                    self.debug_info.draw_calls,
+                    if self.chunk_buffer.is_some() && self.multi_draw {
+                        "indirect"
+                    } else {
+                        "per chunk"
+                    }
+                ));
+                ui.text(format!(
+                    "Time to render: {:.0} micro-s",
                     self.debug_info.render_time.get(),
                 ));
+                let scratch = mesh_scratch_stats();
+                ui.text(format!(
+                    "Mesh scratch: {} reused, {} allocated, {} grown ({} KiB)",
+                    format_with_commas(scratch.reuses as u64),
+                    scratch.allocations,
+                    scratch.grows,
+                    scratch.reserved_bytes / 1024,
+                ));
+                ui.separator();
+                let info = &self.debug_info;
+                plot_history(ui, "Visible chunks", &info.visible_chunks_history);
+                plot_history(ui, "Meshed chunks", &info.meshed_chunks_history);
+                plot_history(ui, "Remesh queue", &info.pending_remesh_history);
+                plot_history(ui, "Render micro-s", &info.render_time_history);
             });
     }
 
@@ -137,33 +289,21 @@ impl VoxelWorldRenderer {
         cam: &Camera,
         world: &VoxelWorld,
     ) -> impl Iterator<Item = Rc<VoxelChunkMesh>> {
-        // Chunk-grid snapped camera pos
-        let camera_pos = cam.position;
-        let render_bb_min = IVec3::new(
-            ((camera_pos.x / CHUNK_SIZE as f32) as i32 - CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-            ((camera_pos.y / CHUNK_SIZE as f32) as i32 - CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-            ((camera_pos.z / CHUNK_SIZE as f32) as i32 - CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-        );
-        let render_bb_max = IVec3::new(
-            ((camera_pos.x / CHUNK_SIZE as f32) as i32 + CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-            ((camera_pos.y / CHUNK_SIZE as f32) as i32 + CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-            ((camera_pos.z / CHUNK_SIZE as f32) as i32 + CAMERA_FOV_RADIUS) * CHUNK_SIZE as i32,
-        );
-        let render_bb = IAabb::new_rect(render_bb_min, render_bb_max);
+        let render_bb = chunk_region_around(&cam.position, self.render_distance);
         let camera_frustum = cam.get_frustum();
+        let frustum_culling = self.frustum_culling;
 
         world
             .iter_region_chunks(&render_bb)
             .filter(move |chunk| {
                 // Frustum culling
                 let chunk_bb = chunk.get_bb_i();
-                camera_frustum.contains_aabb(&chunk_bb)
+                !frustum_culling || camera_frustum.contains_aabb(&chunk_bb)
             })
             .filter_map(|chunk| {
-                // Optimization: Do not generate meshes for already meshed chunks that are **not**
-                // dirty
-                if !chunk.is_dirty()
-                    && let Some(mesh) = self.chunk_meshes.get(&chunk.position)
+                // Optimization: Do not generate meshes for chunks that did not change since
+                if let Some(mesh) = self.chunk_meshes.get(&chunk.position)
+                    && mesh.version == chunk.version()
                 {
                     // Skip empty meshes
                     if mesh.instance_count == 0 {
@@ -171,75 +311,141 @@ impl VoxelWorldRenderer {
                     }
                     return Some(Rc::clone(mesh));
                 }
-                match VoxelChunkMesh::new(
-                    &self.gl,
-                    self.vertex_position_vbo,
-                    self.vertex_normal_vbo,
-                    self.vertex_tex_coord_vbo,
-                    chunk,
-                ) {
-                    Ok(mesh) => {
-                        let rc_mesh = Rc::new(mesh);
-                        self.chunk_meshes
-                            .insert(chunk.position, Rc::clone(&rc_mesh));
-                        chunk.set_clean();
-                        Some(rc_mesh)
-                    }
-                    Err(err) => {
-                        error!("Unable to generate voxel chunk mesh: {err}");
-                        None
-                    }
-                }
+                self.mesh_chunk(chunk)
             })
     }
 
-    pub fn render(&mut self, cam: &Camera, world: &VoxelWorld) {
-        let start_timestamp = Instant::now();
-        let view = cam.get_view_matrix();
-        let projection = cam.get_projection_matrix();
+    fn mesh_chunk(&mut self, chunk: &VoxelChunk) -> Option<Rc<VoxelChunkMesh>> {
+        let _memory = MemoryTag::Meshes.enter();
+        // Remeshed chunks keep fading from their first appearance, so edits do not flicker
+        let first_meshed = self
+            .chunk_meshes
+            .get(&chunk.position)
+            .map_or_else(Instant::now, |mesh| mesh.first_meshed);
+        // Read before the voxels, an edit in between only makes the mesh outdated
+        let version = chunk.version();
+        let mesh = match self.chunk_buffer.as_mut() {
+            Some(buffer) => VoxelChunkMesh::new_shared(&self.gl, buffer, chunk),
+            None => VoxelChunkMesh::new(&self.gl, &self.cube, chunk),
+        };
+        match mesh {
+            Ok(mut mesh) => {
+                self.debug_info.meshed_chunks += 1;
+                mesh.first_meshed = first_meshed;
+                mesh.version = version;
+                let rc_mesh = Rc::new(mesh);
+                if let Some(old_mesh) = self
+                    .chunk_meshes
+                    .insert(chunk.position, Rc::clone(&rc_mesh))
+                    && let Some(buffer) = self.chunk_buffer.as_mut()
+                {
+                    old_mesh.release(buffer);
+                }
+                Some(rc_mesh)
+            }
+            Err(err) => {
+                error!("Unable to generate voxel chunk mesh: {err}");
+                None
+            }
+        }
+    }
+
+    /// Rebuilds meshes of edited chunks right away, so no stale mesh is drawn once they come
+    /// into view. Chunks that were never meshed are left for the visibility pass
+    pub fn remesh_chunks(&mut self, world: &VoxelWorld, chunk_positions: &[IVec3]) {
+        for position in chunk_positions {
+            if !self.chunk_meshes.contains_key(position) {
+                continue;
+            }
+            let region = IAabb::new(position, CHUNK_SIZE);
+            if let Some(chunk) = world.iter_region_chunks(&region).next() {
+                self.mesh_chunk(chunk);
+            }
+        }
+    }
 
-        // Calculate light attributes
-        let world_space_light_dir = Quat::from_rotation_x(20.0) * Vec3::Y;
-        let ambient_light_col = Vec3::ONE * 0.5;
+    /// Counts chunks within render region that still need a new mesh
+    fn update_pending_remesh(&mut self, cam: &Camera, world: &VoxelWorld) {
+        let mut within_region = 0;
+        let mut pending = 0;
+        let region = chunk_region_around(&cam.position, self.render_distance);
+        for chunk in world.iter_region_chunks(&region) {
+            within_region += 1;
+            let outdated = self
+                .chunk_meshes
+                .get(&chunk.position)
+                .is_none_or(|mesh| mesh.version != chunk.version());
+            if outdated {
+                pending += 1;
+            }
+        }
+        self.debug_info.chunks_within_render_bb = within_region;
+        self.debug_info.pending_remesh = pending;
+    }
+
+    // Single draw call for the mesh, wherever its instances live
+    fn draw_mesh(&self, mesh: &VoxelChunkMesh) {
+        match (self.chunk_buffer.as_ref(), mesh.first_instance()) {
+            (Some(buffer), Some(first)) => buffer.draw_range(first, mesh.instance_count as u32),
+            _ => mesh.draw(&self.gl, self.cube.vertex_count),
+        }
+    }
 
+    /// Draws the chunks visible to the camera into the viewport. Called once per view
+    pub fn render(&mut self, cam: &Camera, viewport: Viewport, world: &VoxelWorld) {
+        let start_timestamp = Instant::now();
+        self.texture.poll();
+        viewport.apply(&self.gl);
+        // Camera & lighting are read from the shared frame uniforms
         self.shader.use_program();
-        self.shader.set_uniform_mat4("uView", &view);
-        self.shader.set_uniform_mat4("uProjection", &projection);
-        self.shader
-            .set_uniform_vec3("uLightDir", &world_space_light_dir);
-        self.shader
-            .set_uniform_vec3("uAmbientLightColor", &ambient_light_col);
 
         // Bind texture
         unsafe {
             self.gl.active_texture(gl::TEXTURE0);
         }
         self.texture.bind();
+        self.shader.set_uniform_f32("uWetness", self.wetness);
+        self.shader.set_uniform_vec3("uWindSway", &self.wind_sway);
+        self.shader
+            .set_uniform_i32("uVegetationMask", vegetation_material_mask());
 
-        let gl = Rc::clone(&self.gl);
-        let vertex_count = self.vertex_count;
-        let visible_meshes = self.get_visible_chunks(cam, world);
-        let mut count_voxels = 0;
-        let mut count_chunks = 0;
-        for mesh in visible_meshes {
-            unsafe {
-                gl.bind_vertex_array(Some(mesh.vao));
-                gl.draw_arrays_instanced(
-                    glow::TRIANGLES,
-                    0,
-                    vertex_count as i32,
-                    mesh.instance_count,
-                );
-                gl.bind_vertex_array(None);
+        self.debug_info.meshed_chunks = 0;
+        let visible_meshes: Vec<Rc<VoxelChunkMesh>> = self.get_visible_chunks(cam, world).collect();
+        let now = Instant::now();
+        let (opaque, fading): (Vec<_>, Vec<_>) = visible_meshes
+            .iter()
+            .partition(|mesh| mesh.fade_in(now) >= 1.0);
+        self.shader.set_uniform_f32("uFade", 1.0);
+        let mut draw_calls = match self.chunk_buffer.as_mut() {
+            Some(buffer) if self.multi_draw => {
+                buffer.set_draws(opaque.iter().filter_map(|mesh| {
+                    mesh.first_instance()
+                        .map(|first| (first, mesh.instance_count as u32))
+                }));
+                buffer.draw()
+            }
+            _ => {
+                for mesh in &opaque {
+                    self.draw_mesh(mesh);
+                }
+                opaque.len()
             }
-            count_voxels += mesh.instance_count;
-            count_chunks += 1;
+        };
+        // Fading chunks need their own uniform, so they are drawn one by one
+        for mesh in &fading {
+            self.shader.set_uniform_f32("uFade", mesh.fade_in(now));
+            self.draw_mesh(mesh);
         }
+        draw_calls += fading.len();
+        self.shader.set_uniform_f32("uFade", 1.0);
         self.texture.unbind();
 
-        self.debug_info.visible_voxels = count_voxels;
-        self.debug_info.visible_chunks = count_chunks;
-        self.debug_info.render_time.add_elapsed(start_timestamp);
+        self.debug_info.draw_calls = draw_calls;
+        self.debug_info.visible_voxels = visible_meshes.iter().map(|m| m.instance_count).sum();
+        self.debug_info.visible_chunks = visible_meshes.len();
+        self.update_pending_remesh(cam, world);
+        let render_time = self.debug_info.render_time.add_elapsed(start_timestamp);
+        self.debug_info.push_frame(render_time);
         debug!(
             "Voxel render took {}ms",
             start_timestamp.elapsed().as_secs_f32() * 1e3
@@ -250,48 +456,58 @@ impl VoxelWorldRenderer {
 impl Drop for VoxelWorldRenderer {
     fn drop(&mut self) {
         unsafe {
-            self.gl.delete_buffer(self.vertex_position_vbo);
-            self.gl.delete_buffer(self.vertex_normal_vbo);
-            self.gl.delete_buffer(self.vertex_tex_coord_vbo);
+            self.gl.delete_buffer(self.cube.position_vbo);
+            self.gl.delete_buffer(self.cube.normal_vbo);
+            self.gl.delete_buffer(self.cube.tex_coord_vbo);
         }
     }
 }
 
+/// Where the instance data of a chunk mesh lives
+enum ChunkInstances {
+    // Own vertex array & instance buffer. Fallback without indirect multi-draw
+    Owned {
+        vao: NativeVertexArray,
+        instance_vbo: NativeBuffer,
+    },
+    // Range within the shared chunk instance buffer
+    Shared {
+        first_instance: u32,
+    },
+    // Chunk without solid voxels. Nothing to draw
+    Empty,
+}
+
 struct VoxelChunkMesh {
     gl: Rc<glow::Context>,
-    vao: <glow::Context as HasContext>::VertexArray,
-    // Voxel position buffer in this chunk
-    instance_vbo: NativeBuffer,
+    instances: ChunkInstances,
     // Number of voxels rendered
     pub instance_count: i32,
+    // First time the chunk got a mesh. Drives the fade in
+    first_meshed: Instant,
+    // Version of the chunk the mesh was built from. Outdated once the chunk version differs
+    version: u32,
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Pod, Zeroable)]
-struct ChunkVertexData {
-    position: Vec3,
-    material_index: u32,
-}
 impl VoxelChunkMesh {
     pub fn new(
         gl: &Rc<glow::Context>,
-        vertex_position_vbo: NativeBuffer,
-        vertex_normal_vbo: NativeBuffer,
-        vertex_tex_coords_vbo: NativeBuffer,
+        cube: &CubeVertexBuffers,
         chunk: &VoxelChunk,
     ) -> Result<VoxelChunkMesh, Box<dyn Error>> {
-        let mut vertex_data: Vec<ChunkVertexData> =
-            Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
-        for voxel in chunk.voxel_slice() {
-            if matches!(voxel.kind, VoxelKind::Air) {
-                continue;
-            }
-            vertex_data.push(ChunkVertexData {
-                position: voxel.position,
-                material_index: voxel.kind.material_index(),
-            });
+        with_chunk_instance_data(chunk, |vertex_data| Self::upload(gl, cube, vertex_data))
+    }
+
+    // Own vertex array & instance buffer holding the instances
+    fn upload(
+        gl: &Rc<glow::Context>,
+        cube: &CubeVertexBuffers,
+        vertex_data: &[ChunkVertexData],
+    ) -> Result<VoxelChunkMesh, Box<dyn Error>> {
+        if vertex_data.is_empty() {
+            return Ok(Self::empty(gl));
         }
-        let vertex_data_bytes: &[u8] = bytemuck::cast_slice(&vertex_data);
+        let vertex_data_bytes: &[u8] = bytemuck::cast_slice(vertex_data);
 
         // Setup buffers and vertex attributes
         unsafe {
@@ -305,42 +521,10 @@ impl VoxelChunkMesh {
             let vao = gl
                 .create_vertex_array()
                 .expect("Cannot create vertex array");
-            // Setup position attribute
             gl.bind_vertex_array(Some(vao));
-            gl.bind_buffer(gl::ARRAY_BUFFER, Some(vertex_position_vbo));
-            gl.vertex_attrib_pointer_f32(0, 3, gl::FLOAT, false, 0, 0);
-            gl.enable_vertex_array_attrib(vao, 0);
-            // Setup normal attribute
-            gl.bind_buffer(gl::ARRAY_BUFFER, Some(vertex_normal_vbo));
-            gl.vertex_attrib_pointer_f32(1, 3, gl::FLOAT, false, 0, 0);
-            gl.enable_vertex_array_attrib(vao, 1);
-            // Setup tex_coords attribute
-            gl.bind_buffer(gl::ARRAY_BUFFER, Some(vertex_tex_coords_vbo));
-            gl.vertex_attrib_pointer_f32(3, 2, gl::FLOAT, false, 0, 0);
-            gl.enable_vertex_array_attrib(vao, 3);
-
-            // Setup vertex instance buffer
-            gl.bind_buffer(gl::ARRAY_BUFFER, Some(instance_vbo));
-            let stride = size_of::<ChunkVertexData>() as i32;
-            // location attribute
-            gl.vertex_attrib_pointer_f32(2, 3, gl::FLOAT, false, stride, 0);
-            gl.enable_vertex_attrib_array(2);
-            // Update vertex attribute at index 2 on every new instance
-            gl.vertex_attrib_divisor(2, 1);
-            // material index attribute
-            gl.vertex_attrib_pointer_i32(
-                4,
-                1,
-                gl::INT,
-                stride,
-                offset_of!(ChunkVertexData, material_index) as i32,
-            );
-            gl.enable_vertex_attrib_array(4);
-            // Update vertex attribute at index 4 on every new instance
-            gl.vertex_attrib_divisor(4, 1);
+            setup_chunk_vertex_attributes(gl, vao, cube, instance_vbo);
 
             // Cleanup
-            gl.bind_buffer(gl::ARRAY_BUFFER, None);
             gl.bind_vertex_array(None);
 
             trace!(
@@ -351,21 +535,119 @@ impl VoxelChunkMesh {
             Ok(Self {
                 gl: Rc::clone(gl),
                 instance_count: vertex_data.len() as i32,
-                instance_vbo,
-                vao,
+                instances: ChunkInstances::Owned { vao, instance_vbo },
+                first_meshed: Instant::now(),
+                version: 0,
             })
         }
     }
+
+    /// Uploads chunk instances into the shared buffer used for indirect multi-draw
+    pub fn new_shared(
+        gl: &Rc<glow::Context>,
+        buffer: &mut ChunkInstanceBuffer,
+        chunk: &VoxelChunk,
+    ) -> Result<VoxelChunkMesh, Box<dyn Error>> {
+        with_chunk_instance_data(chunk, |vertex_data| {
+            if vertex_data.is_empty() {
+                return Ok(Self::empty(gl));
+            }
+            let first_instance = buffer.allocate(vertex_data)?;
+            Ok(Self {
+                gl: Rc::clone(gl),
+                instance_count: vertex_data.len() as i32,
+                instances: ChunkInstances::Shared { first_instance },
+                first_meshed: Instant::now(),
+                version: 0,
+            })
+        })
+    }
+
+    fn empty(gl: &Rc<glow::Context>) -> VoxelChunkMesh {
+        Self {
+            gl: Rc::clone(gl),
+            instance_count: 0,
+            instances: ChunkInstances::Empty,
+            first_meshed: Instant::now(),
+            version: 0,
+        }
+    }
+
+    /// Opacity from 0 right after the first mesh up to 1 once fully faded in
+    fn fade_in(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.first_meshed);
+        (elapsed.as_secs_f32() / FADE_IN_DURATION.as_secs_f32()).min(1.0)
+    }
+
+    /// Start of this chunk's range within the shared instance buffer
+    fn first_instance(&self) -> Option<u32> {
+        match self.instances {
+            ChunkInstances::Shared { first_instance } => Some(first_instance),
+            _ => None,
+        }
+    }
+
+    /// Returns the shared buffer range. Has to be called once the mesh gets replaced
+    fn release(&self, buffer: &mut ChunkInstanceBuffer) {
+        if let Some(first_instance) = self.first_instance() {
+            buffer.free(first_instance, self.instance_count as u32);
+        }
+    }
+
+    /// Single instanced draw call. Only used without indirect multi-draw
+    fn draw(&self, gl: &glow::Context, vertex_count: usize) {
+        let ChunkInstances::Owned { vao, .. } = self.instances else {
+            return;
+        };
+        unsafe {
+            gl.bind_vertex_array(Some(vao));
+            gl.draw_arrays_instanced(glow::TRIANGLES, 0, vertex_count as i32, self.instance_count);
+            gl.bind_vertex_array(None);
+        }
+    }
 }
 impl Drop for VoxelChunkMesh {
     fn drop(&mut self) {
-        unsafe {
-            self.gl.delete_buffer(self.instance_vbo);
-            self.gl.delete_vertex_array(self.vao);
+        if let ChunkInstances::Owned { vao, instance_vbo } = self.instances {
+            unsafe {
+                self.gl.delete_buffer(instance_vbo);
+                self.gl.delete_vertex_array(vao);
+            }
         }
     }
 }
 
+/// Runs f with the instance data of all solid voxels within chunk. The data lives in a scratch
+/// buffer that is reused by the next chunk
+fn with_chunk_instance_data<R>(chunk: &VoxelChunk, f: impl FnOnce(&[ChunkVertexData]) -> R) -> R {
+    let light = chunk.baked_light();
+    INSTANCE_SCRATCH.with_borrow_mut(|pool| {
+        pool.with(|vertex_data| {
+            vertex_data.extend(
+                chunk
+                    .voxel_slice()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, voxel)| !matches!(voxel.kind, VoxelKind::Air))
+                    .map(|(index, voxel)| ChunkVertexData {
+                        position: voxel.position,
+                        material_index: voxel.kind.material_index(),
+                        shade: light.as_ref().map_or(0.0, |light| 1.0 - light.level(index)),
+                    }),
+            );
+            f(vertex_data)
+        })
+    })
+}
+
+// Bit per material index of the voxel kinds swaying in the wind
+fn vegetation_material_mask() -> i32 {
+    VoxelKind::SOLID
+        .into_iter()
+        .filter(|kind| kind.is_vegetation())
+        .fold(0, |mask, kind| mask | 1 << kind.material_index())
+}
+
 fn format_with_commas(n: u64) -> String {
     let s = n.to_string();
     let mut result = String::new();
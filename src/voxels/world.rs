@@ -1,6 +1,7 @@
 use log::{debug, error, info, trace};
 use rayon::prelude::*;
 use std::{
+    collections::HashSet,
     sync::{
         Arc,
         atomic::{AtomicUsize, Ordering},
@@ -16,22 +17,31 @@ use crate::{
     collision::{
         CollisionInfo,
         capsule::{Capsule, capsule_cast},
-        sphere::sphere_cast,
+        sphere::{sphere_cast, sphere_cast_entry_distance},
     },
-    octree::{AABB, IAabb, Octree, OctreeNodeIterator},
+    octree::{AABB, IAabb},
+    util::MemoryTag,
     voxels::{
         CHUNK_SIZE, Voxel, VoxelChunk,
+        chunk_cache::ChunkLookupCache,
+        chunk_events::{ChunkChangeKind, ChunkChangePublisher, ChunkSubscription},
+        chunk_storage::{ChunkIndexKind, ChunkStorage},
         collision::coarse_collision_voxel_world_capsule,
+        edits::VoxelEdit,
+        generation::GenerationProgress,
         generators::{ChunkGenerator, cubic::CubicGenerator},
+        heightmap::Heightmap,
+        regions::RegionStore,
     },
 };
 
-use super::{VoxelKind, voxel::VoxelChunkIterator};
+use super::VoxelKind;
 
 fn generate_chunk_world(
     tree_size: usize,
     generator: Arc<dyn ChunkGenerator>,
-) -> Octree<Arc<VoxelChunk>> {
+    index: ChunkIndexKind,
+) -> ChunkStorage {
     info!("Generating world size {tree_size}");
     let start_world_generation = Instant::now();
     // Precalculate positions to be able to distribute them amongst worker threads
@@ -64,13 +74,13 @@ fn generate_chunk_world(
         .collect();
 
     // Insert all chunks into the world
-    let mut world: Octree<Arc<VoxelChunk>> = Octree::new(tree_size);
+    let mut world = ChunkStorage::new(index, tree_size);
     for (pos, chunk) in chunks {
         world.insert(pos, chunk);
     }
     info!(
         "World generation: Generated {} chunks in {}ms",
-        world.get_all_depth_first().len(),
+        world.all_chunks().len(),
         start_world_generation.elapsed().as_secs_f32() * 1000.0,
     );
     world
@@ -82,11 +92,28 @@ struct ChunkGenerationResult {
 }
 
 pub struct VoxelWorld {
-    tree: Octree<Arc<VoxelChunk>>,
+    pub(super) chunks: ChunkStorage,
     generator: Arc<dyn ChunkGenerator>,
+    // Applied edits & their sequence numbers. Replayed onto chunks generated later
+    pub(super) edit_log: Vec<(u64, VoxelEdit)>,
+    // Sequence number of the next applied edit. Keeps counting when the log is pruned
+    pub(super) edit_sequence: u64,
+    // Chunk positions in octree space that differ from generated terrain & are not persisted yet
+    pub(super) modified_chunks: HashSet<IVec3>,
+    pub(super) regions: Option<RegionStore>,
+    // Chunks found per queried region. Must be invalidated when chunks are inserted or removed
+    pub(super) chunk_cache: ChunkLookupCache,
+    // Solid voxels removed by applied edits since last taken. Replayed edits are not counted
+    pub(super) removed_voxels: usize,
+    pub(super) heightmap: Heightmap,
+    pub(super) chunk_changes: ChunkChangePublisher,
 
     // Channel for async chunk generation
     generated_chunk_receiver: Option<Receiver<Vec<ChunkGenerationResult>>>,
+    // Progress of the running batch. Shared with the worker thread
+    generation: Option<Arc<GenerationProgress>>,
+    // Throughput of the last finished batch
+    last_chunks_per_second: f32,
 }
 
 impl VoxelWorld {
@@ -98,29 +125,96 @@ impl VoxelWorld {
     }
 
     pub fn new(initial_size: usize, generator: Arc<dyn ChunkGenerator>) -> VoxelWorld {
-        let tree = generate_chunk_world(initial_size, generator.clone());
+        VoxelWorld::with_index(initial_size, generator, ChunkIndexKind::default())
+    }
+
+    /// Same as new, but chunks are looked up using the given index
+    pub fn with_index(
+        initial_size: usize,
+        generator: Arc<dyn ChunkGenerator>,
+        index: ChunkIndexKind,
+    ) -> VoxelWorld {
+        let chunks = generate_chunk_world(initial_size, generator.clone(), index);
+        let mut heightmap = Heightmap::new();
+        for chunk in chunks.all_chunks() {
+            heightmap.update_chunk(&chunk);
+        }
         Self {
             generator,
-            tree,
+            chunks,
+            edit_log: Vec::new(),
+            edit_sequence: 0,
+            modified_chunks: HashSet::new(),
+            regions: None,
+            chunk_cache: ChunkLookupCache::default(),
+            removed_voxels: 0,
+            heightmap,
+            chunk_changes: ChunkChangePublisher::default(),
             generated_chunk_receiver: None,
+            generation: None,
+            last_chunks_per_second: 0.0,
         }
     }
 
+    pub fn chunk_index_kind(&self) -> ChunkIndexKind {
+        self.chunks.kind()
+    }
+
     pub fn get_size(&self) -> usize {
-        self.tree.get_size()
+        self.chunks.get_size()
+    }
+
+    /// Highest solid voxel per column of the loaded chunks
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    /// Recomputes heightmap columns of the chunks edited at the given world space positions &
+    /// tells subscribers about the edits
+    pub(super) fn chunks_edited(&mut self, chunk_positions: impl IntoIterator<Item = IVec3>) {
+        let chunk_positions: Vec<IVec3> = chunk_positions.into_iter().collect();
+        for position in &chunk_positions {
+            if let Some(chunk) = self.chunks.get(position / CHUNK_SIZE as i32) {
+                self.heightmap.update_chunk(chunk);
+            }
+        }
+        self.chunk_changes
+            .publish(ChunkChangeKind::Edited, chunk_positions);
     }
 
-    /// Removes all voxels in a radius around the center.
-    pub fn clear_sphere(&mut self, center: &Vec3, radius: f32) {
+    /// Receives every chunk loaded, edited or unloaded from now on
+    pub fn subscribe_chunk_changes(&mut self) -> ChunkSubscription {
+        self.chunk_changes.subscribe()
+    }
+
+    /// Keeps the subscribers of the world this one replaces, e.g. after loading a save
+    pub fn inherit_chunk_subscribers(&mut self, previous: &mut VoxelWorld) {
+        self.chunk_changes = std::mem::take(&mut previous.chunk_changes);
+    }
+
+    /// World space positions of all loaded chunks
+    pub fn chunk_positions(&self) -> Vec<IVec3> {
+        self.chunks
+            .all_chunks()
+            .iter()
+            .map(|chunk| chunk.position)
+            .collect()
+    }
+
+    /// Removes all voxels in a radius around the center. Returns positions of modified chunks
+    pub fn clear_sphere(&mut self, center: &Vec3, radius: f32) -> HashSet<IVec3> {
+        self.clear_sphere_recorded(center, radius).0
+    }
+
+    /// Same as clear_sphere, additionally returns the removed voxels as they were before & their
+    /// chunks
+    pub(super) fn clear_sphere_recorded(
+        &mut self,
+        center: &Vec3,
+        radius: f32,
+    ) -> (HashSet<IVec3>, Vec<(Voxel, Arc<VoxelChunk>)>) {
         // Query list of colliding voxels + their parent chunk
-        let collider = IAabb::new(
-            &IVec3::new(
-                (center.x - radius / 2.0).round() as i32,
-                (center.y - radius / 2.0).round() as i32,
-                (center.z - radius / 2.0).round() as i32,
-            ),
-            radius.next_up() as usize,
-        );
+        let collider = IAabb::from(&AABB::new_center(center, radius * 2.0));
         let iter = self
             .iter_region_voxels_with_chunk(collider)
             // Solid
@@ -129,7 +223,8 @@ impl VoxelWorld {
             .filter(|(voxel, _)| voxel.position.distance_squared(*center) < radius * radius);
 
         // Iterate and set voxel kind to Air to remove
-        let mut voxels_removed = 0;
+        let mut removed = Vec::new();
+        let mut modified_chunks = HashSet::new();
         for (voxel, chunk) in iter {
             let mut new_voxel = voxel;
             new_voxel.kind = VoxelKind::Air;
@@ -141,16 +236,24 @@ impl VoxelWorld {
                 ),
                 new_voxel,
             );
-            voxels_removed += 1;
+            modified_chunks.insert(chunk.position);
+            removed.push((voxel, chunk));
         }
-        if voxels_removed > 0 {
-            debug!("Removed {voxels_removed} colliding voxels ");
+        self.modified_chunks.extend(
+            modified_chunks
+                .iter()
+                .map(|position| position / CHUNK_SIZE as i32),
+        );
+        self.chunks_edited(modified_chunks.iter().copied());
+        if !removed.is_empty() {
+            debug!("Removed {} colliding voxels ", removed.len());
         }
+        (modified_chunks, removed)
     }
 
     #[cfg(test)]
     pub fn get_all_voxels(&self) -> Vec<Voxel> {
-        let chunks = self.tree.get_all_depth_first();
+        let chunks = self.chunks.all_chunks();
         let mut voxels = Vec::with_capacity(chunks.len() * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
         for chunk in &chunks {
             voxels.extend_from_slice(chunk.voxel_slice());
@@ -158,29 +261,29 @@ impl VoxelWorld {
         voxels
     }
 
+    // Floor division, so negative coordinates map to negative chunks
     fn world_space_bb_to_chunk_space_bb(&self, world_space_bb: &IAabb) -> IAabb {
+        let chunk_size = CHUNK_SIZE as i32;
         IAabb::new_rect(
-            IVec3::new(
-                (world_space_bb.min.x as f32 / CHUNK_SIZE as f32).floor() as i32,
-                (world_space_bb.min.y as f32 / CHUNK_SIZE as f32).floor() as i32,
-                (world_space_bb.min.z as f32 / CHUNK_SIZE as f32).floor() as i32,
-            ),
-            IVec3::new(
-                (world_space_bb.max.x as f32 / CHUNK_SIZE as f32).ceil() as i32,
-                (world_space_bb.max.y as f32 / CHUNK_SIZE as f32).ceil() as i32,
-                (world_space_bb.max.z as f32 / CHUNK_SIZE as f32).ceil() as i32,
-            ),
+            world_space_bb.min.div_euclid(IVec3::splat(chunk_size)),
+            // Ceil division
+            -(-world_space_bb.max).div_euclid(IVec3::splat(chunk_size)),
         )
     }
 
-    pub fn world_space_pos_to_chunk_space_pos(&self, world_space_pos: &Vec3) -> IVec3 {
-        IVec3::new(
-            (world_space_pos.x / CHUNK_SIZE as f32) as i32,
-            (world_space_pos.y / CHUNK_SIZE as f32) as i32,
-            (world_space_pos.z / CHUNK_SIZE as f32) as i32,
+    // Voxels are centered on their position & reach half a voxel into neighboring chunks
+    fn voxel_region_to_chunk_space_bb(&self, region_world_space: &IAabb) -> IAabb {
+        let chunk_size = IVec3::splat(CHUNK_SIZE as i32);
+        IAabb::new_rect(
+            region_world_space.min.div_euclid(chunk_size),
+            region_world_space.max.div_euclid(chunk_size) + IVec3::ONE,
         )
     }
 
+    pub fn world_space_pos_to_chunk_space_pos(&self, world_space_pos: &Vec3) -> IVec3 {
+        (*world_space_pos / CHUNK_SIZE as f32).floor().as_ivec3()
+    }
+
     /// Needs to be called every tick to insert generated chunks once generation is done
     pub fn receive_chunks(&mut self) {
         if self.generated_chunk_receiver.is_none() {
@@ -191,11 +294,12 @@ impl VoxelWorld {
         match batch_channel.try_recv() {
             Ok(chunks) => {
                 debug!("Received {} chunks", chunks.len());
-                for result in chunks {
-                    self.tree
-                        .insert(result.position_octree_space, Arc::new(result.chunk));
-                }
+                let _memory = MemoryTag::Chunks.enter();
+                self.insert_generated_chunks(chunks);
                 self.generated_chunk_receiver = None;
+                if let Some(generation) = self.generation.take() {
+                    self.last_chunks_per_second = generation.chunks_per_second();
+                }
             }
             Err(std::sync::mpsc::TryRecvError::Empty) => {
                 // println!("Task still running...");
@@ -206,17 +310,91 @@ impl VoxelWorld {
         }
     }
 
+    // Inserts freshly generated chunks & restores persisted or edited state on top
+    fn insert_generated_chunks(&mut self, chunks: Vec<ChunkGenerationResult>) {
+        let regions: Vec<IAabb> = chunks.iter().map(|r| r.chunk.get_bb_i()).collect();
+        let loaded: Vec<IVec3> = chunks.iter().map(|r| r.chunk.position).collect();
+        for result in chunks {
+            if let Err(err) = self.restore_stored_chunk(&result.chunk) {
+                error!("Unable to restore stored chunk: {err}");
+            }
+            // Edits replayed below update the heightmap again where they apply
+            self.heightmap.update_chunk(&result.chunk);
+            self.chunks
+                .insert(result.position_octree_space, Arc::new(result.chunk));
+        }
+        self.chunk_cache.invalidate();
+        self.chunk_changes.publish(ChunkChangeKind::Loaded, loaded);
+        self.reapply_edits(&regions);
+    }
+
+    /// Generates the 3x3x3 chunks around the destination right away, so a teleported player does
+    /// not end up in ungenerated space. Background generation for the previous region is
+    /// cancelled. Returns the number of chunks generated
+    pub fn prepare_teleport(&mut self, destination: &Vec3) -> usize {
+        let center = self.world_space_pos_to_chunk_space_pos(destination);
+        let region_chunk_space = IAabb::new_rect(
+            (center - IVec3::ONE).max(IVec3::ZERO),
+            (center + IVec3::splat(2)).max(IVec3::ZERO),
+        );
+        while !IAabb::new(&IVec3::ZERO, self.get_size()).contains(&region_chunk_space) {
+            info!("Growing world to fit teleport destination");
+            self.chunks.grow(CHUNK_SIZE);
+            self.chunk_cache.invalidate();
+        }
+        self.cancel_chunk_generation();
+        let missing: Vec<IVec3> = region_chunk_space
+            .iter_cells()
+            .filter(|position| self.chunks.get(*position).is_none())
+            .collect();
+        let generator = Arc::clone(&self.generator);
+        let chunks: Vec<ChunkGenerationResult> = missing
+            .into_par_iter()
+            .map(|position| ChunkGenerationResult {
+                position_octree_space: position,
+                chunk: generator.generate_chunk(position * CHUNK_SIZE as i32),
+            })
+            .collect();
+        let count = chunks.len();
+        debug!("Generated {count} chunks around teleport destination {destination}");
+        self.insert_generated_chunks(chunks);
+        count
+    }
+
+    /// Progress of the running background generation batch
+    pub fn generation_progress(&self) -> Option<&GenerationProgress> {
+        self.generation.as_deref()
+    }
+
+    /// Stops the running background generation & drops its chunks, e.g. after the player moved
+    /// somewhere else. Missing chunks are queued again on the next growth check
+    pub fn cancel_chunk_generation(&mut self) {
+        if let Some(generation) = self.generation.take() {
+            info!(
+                "Cancelled chunk generation after {}/{} chunks",
+                generation.generated(),
+                generation.batch_size
+            );
+            generation.cancel();
+        }
+        self.generated_chunk_receiver = None;
+    }
+
     /// Checks world for uninitialized chunks within region. Should be called in regular intervals
     /// but not necessarily every tick
     fn spawn_chunk_generation(&mut self, region_world_space: IAabb, center: &Vec3) {
         const MAX_CHUNKS: usize = 200;
-        if self.generated_chunk_receiver.is_some() {
-            // Already running. Wait for finish first
-            return;
+        if let Some(generation) = self.generation.as_ref() {
+            if generation.region.intersects(&region_world_space) {
+                // Already running. Wait for finish first
+                return;
+            }
+            // Region moved away entirely. Chunks of the running batch would be stale
+            self.cancel_chunk_generation();
         }
         let ivec_center = self.world_space_pos_to_chunk_space_pos(center);
         let mut all_empty_chunk_positions: Vec<IVec3> = self
-            .iter_empty_chunk_positions(region_world_space)
+            .iter_empty_chunk_positions(region_world_space.clone())
             .collect::<Vec<IVec3>>();
         let size = all_empty_chunk_positions.len();
         if size == 0 {
@@ -227,8 +405,15 @@ impl VoxelWorld {
         }
         let (tx, rx) = mpsc::channel();
         self.generated_chunk_receiver = Some(rx);
+        let progress = Arc::new(GenerationProgress::new(
+            region_world_space,
+            size,
+            size.min(MAX_CHUNKS),
+        ));
+        self.generation = Some(Arc::clone(&progress));
         let generator = Arc::clone(&self.generator);
         thread::spawn(move || {
+            let _memory = MemoryTag::Chunks.enter();
             let mut generated_chunks: Vec<ChunkGenerationResult> = Vec::new();
             if size > MAX_CHUNKS {
                 debug!("Max size exceeded. Sorting first...",);
@@ -240,15 +425,21 @@ impl VoxelWorld {
                 });
             }
             for chunk_origin in all_empty_chunk_positions.iter().take(MAX_CHUNKS) {
+                if progress.is_cancelled() {
+                    return;
+                }
                 let chunk_origin_world_space = chunk_origin * CHUNK_SIZE as i32;
                 let chunk = generator.generate_chunk(chunk_origin_world_space);
                 generated_chunks.push(ChunkGenerationResult {
                     position_octree_space: *chunk_origin,
                     chunk,
                 });
+                progress.record_generated();
             }
             debug!("Sending {size} chunks",);
-            tx.send(generated_chunks).unwrap();
+            if tx.send(generated_chunks).is_err() {
+                debug!("Chunk generation was cancelled before finishing");
+            }
         });
     }
 
@@ -257,65 +448,120 @@ impl VoxelWorld {
         debug_assert!(bounded_region.min.y >= 0);
         debug_assert!(bounded_region.min.z >= 0);
         let should_grow = !self
-            .tree
+            .chunks
             .get_total_region_world_space(CHUNK_SIZE)
             .contains(&bounded_region);
-        // Grow tree if required
+        // Grow world if required
         if should_grow {
-            info!("Growing world tree");
-            self.tree.grow(CHUNK_SIZE);
+            info!("Growing world");
+            self.chunks.grow(CHUNK_SIZE);
+            self.chunk_cache.invalidate();
         }
         self.spawn_chunk_generation(bounded_region, center);
     }
 
     pub fn render_ui(&mut self, ui: &mut imgui::Ui) {
         ui.window("World")
-            .size([300.0, 100.0], imgui::Condition::FirstUseEver)
+            .size([300.0, 180.0], imgui::Condition::FirstUseEver)
             .position([900.0, 0.0], imgui::Condition::FirstUseEver)
             .build(|| {
-                let region = self.tree.get_total_region_world_space(CHUNK_SIZE);
+                let region = self.chunks.get_total_region_world_space(CHUNK_SIZE);
                 ui.text(format!("Total chunks: {}", self.get_size().pow(3)));
+                ui.text(format!("Chunk index: {:?}", self.chunks.kind()));
                 ui.text(format!(
                     "Region covered; [{}] - [{}]",
                     region.min, region.max
                 ));
-                ui.text(format!(
-                    "Generating: {}",
-                    self.generated_chunk_receiver.is_some()
-                ));
+                let mut cancel = false;
+                match self.generation.as_ref() {
+                    Some(generation) => {
+                        let generated = generation.generated();
+                        ui.text(format!(
+                            "Generating: {generated}/{} chunks",
+                            generation.batch_size
+                        ));
+                        ui.text(format!(
+                            "Queued in region: {}",
+                            generation.queued.saturating_sub(generated)
+                        ));
+                        ui.text(format!("Chunks/s: {:.1}", generation.chunks_per_second()));
+                        match generation.eta() {
+                            Some(eta) => ui.text(format!("Region ETA: {:.1}s", eta.as_secs_f32())),
+                            None => ui.text("Region ETA: -"),
+                        }
+                        cancel = ui.button("Cancel generation");
+                    }
+                    None => {
+                        ui.text("Generating: idle");
+                        ui.text(format!(
+                            "Last batch chunks/s: {:.1}",
+                            self.last_chunks_per_second
+                        ));
+                    }
+                }
+                if cancel {
+                    self.cancel_chunk_generation();
+                }
+                if let Some(regions) = self.regions.as_ref() {
+                    ui.text(format!("Loaded regions: {}", regions.loaded_regions()));
+                }
+                let (hits, misses) = self.chunk_cache.stats();
+                ui.text(format!("Chunk lookups cached: {hits}/{}", hits + misses));
             });
     }
 
+    // Chunks overlapping the voxel region. Cached, as nearby entities query the same chunks
+    fn region_chunks(&self, region_world_space: &IAabb) -> Arc<[Arc<VoxelChunk>]> {
+        let bb_chunk_space = self.voxel_region_to_chunk_space_bb(region_world_space);
+        self.chunk_cache.get_or_insert(&self.chunks, bb_chunk_space)
+    }
+
+    /// Regions partially or fully outside of the world only yield the voxels within the world
     pub fn iter_region_voxels_with_chunk(
         &self,
         region_world_space: IAabb,
-    ) -> impl Iterator<Item = (Voxel, &Arc<VoxelChunk>)> {
-        let bb_chunk_space = self.world_space_bb_to_chunk_space_bb(&region_world_space);
-        let chunk_iterator = self.tree.iter_region(bb_chunk_space);
-        VoxelWorldIterator {
-            chunk_iterator,
-            current_chunk: None,
-            voxel_iterator: None,
-            region: region_world_space,
-        }
+    ) -> impl Iterator<Item = (Voxel, Arc<VoxelChunk>)> + use<> {
+        let chunks = self.region_chunks(&region_world_space);
+        (0..chunks.len()).flat_map(move |index| {
+            let chunk = chunks[index].clone();
+            chunk
+                .iter_region_shared(&region_world_space)
+                .map(move |voxel| (voxel, chunk.clone()))
+        })
     }
 
-    pub fn iter_region_voxels(&self, region_world_space: IAabb) -> impl Iterator<Item = Voxel> {
+    /// Yields exactly the voxels whose AABB intersects the region, no re-filtering needed
+    pub fn iter_region_voxels(
+        &self,
+        region_world_space: IAabb,
+    ) -> impl Iterator<Item = Voxel> + use<> {
         self.iter_region_voxels_with_chunk(region_world_space)
             .map(|tuple| tuple.0)
     }
 
+    /// Same voxels as iter_region_voxels, but chunks are processed in parallel.
+    /// Order of voxels is not deterministic
+    pub fn par_iter_region_voxels(
+        &self,
+        region_world_space: IAabb,
+    ) -> impl ParallelIterator<Item = Voxel> + '_ {
+        let chunks = self.region_chunks(&region_world_space);
+        (0..chunks.len())
+            .into_par_iter()
+            .flat_map_iter(move |index| chunks[index].iter_region_shared(&region_world_space))
+    }
+
     pub fn iter_region_chunks(
         &self,
         region_world_space: &IAabb,
-    ) -> OctreeNodeIterator<Arc<VoxelChunk>> {
+    ) -> impl Iterator<Item = &Arc<VoxelChunk>> + use<'_> {
         let bb_chunk_space = self.world_space_bb_to_chunk_space_bb(region_world_space);
-        self.tree.iter_region(bb_chunk_space)
+        self.chunks.iter_region(bb_chunk_space)
     }
 
     fn iter_empty_chunk_positions(&self, region_world_space: IAabb) -> impl Iterator<Item = IVec3> {
         let bb_chunk_space = self.world_space_bb_to_chunk_space_bb(&region_world_space);
-        self.tree.iter_empty_within_region(bb_chunk_space)
+        self.chunks.iter_empty_within_region(bb_chunk_space)
     }
 
     pub fn query_sphere_cast(
@@ -327,17 +573,49 @@ impl VoxelWorld {
     ) -> Option<CollisionInfo> {
         let start = Instant::now();
         // BB test
-        let sphere_box_region_f = AABB::new(
-            origin - radius * Vec3::ONE,
-            origin + (radius + max_distance) * Vec3::ONE,
-        );
+        let end = origin + direction * max_distance;
+        let sphere_box_region_f =
+            AABB::new_center(&origin, radius * 2.0).union(&AABB::new_center(&end, radius * 2.0));
         let sphere_box_region_i = IAabb::from(&sphere_box_region_f);
-        let bbs = self
-            .iter_region_voxels(sphere_box_region_i)
-            .filter_map(|voxel| voxel.get_collider());
-        let res = sphere_cast(origin, radius, direction, max_distance, bbs);
-        trace!("Sphere cast took {}ms", start.elapsed().as_secs_f64() * 1e3);
-        res
+        let region_chunks = self.region_chunks(&sphere_box_region_i);
+        // Visit chunks near to far along the ray, skipping chunks the sphere never touches
+        let mut chunks: Vec<(f32, &Arc<VoxelChunk>)> = region_chunks
+            .iter()
+            .filter_map(|chunk| {
+                let voxel_bounds = AABB::new(
+                    chunk.position.as_vec3() - Vec3::splat(0.5),
+                    (chunk.position + CHUNK_SIZE as i32).as_vec3() - Vec3::splat(0.5),
+                );
+                let entry = sphere_cast_entry_distance(origin, radius, direction, &voxel_bounds)?;
+                (entry <= max_distance).then_some((entry, chunk))
+            })
+            .collect();
+        chunks.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut closest_hit: Option<CollisionInfo> = None;
+        let mut chunks_visited = 0;
+        for (entry, chunk) in chunks.iter() {
+            // Hits in all remaining chunks are further away than the closest hit
+            if closest_hit.is_some_and(|hit| *entry > hit.penetration_depth) {
+                break;
+            }
+            chunks_visited += 1;
+            let bbs = chunk
+                .iter_region(&sphere_box_region_i)
+                .filter_map(|voxel| voxel.get_collider());
+            if let Some(hit) = sphere_cast(origin, radius, direction, max_distance, bbs)
+                && closest_hit
+                    .is_none_or(|closest| hit.penetration_depth < closest.penetration_depth)
+            {
+                closest_hit = Some(hit);
+            }
+        }
+        trace!(
+            "Sphere cast took {}ms, visited {chunks_visited}/{} chunks",
+            start.elapsed().as_secs_f64() * 1e3,
+            chunks.len()
+        );
+        closest_hit
     }
 
     pub fn query_capsule_cast(
@@ -364,43 +642,20 @@ impl VoxelWorld {
     }
 }
 
-pub struct VoxelWorldIterator<'a> {
-    chunk_iterator: OctreeNodeIterator<'a, Arc<VoxelChunk>>,
-    current_chunk: Option<&'a Arc<VoxelChunk>>,
-    voxel_iterator: Option<VoxelChunkIterator<'a>>,
-    /// Region in **world space**
-    region: IAabb,
-}
-
-impl<'a> Iterator for VoxelWorldIterator<'a> {
-    type Item = (Voxel, &'a Arc<VoxelChunk>);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            // If we have a current voxel iterator, try to yield from it
-            if let Some(vox_iter) = self.voxel_iterator.as_mut() {
-                if let Some(v) = vox_iter.next() {
-                    return Some((v, self.current_chunk.unwrap()));
-                }
-            }
-
-            // Current voxel iterator is exhausted; move to next chunk
-            let next_chunk = self.chunk_iterator.next()?;
-            self.current_chunk = Some(next_chunk);
-            self.voxel_iterator = Some(next_chunk.iter_region(&self.region));
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{collections::HashSet, sync::Arc, time::Instant};
 
-    use glam::IVec3;
+    use glam::{IVec3, Vec3};
+    use rayon::prelude::*;
 
     use crate::{
-        octree::IAabb,
-        voxels::{CHUNK_SIZE, Voxel, VoxelWorld, generators::cubic::CubicGenerator},
+        collision::sphere::sphere_cast,
+        octree::{AABB, IAabb},
+        voxels::{
+            CHUNK_SIZE, Voxel, VoxelWorld, chunk_storage::ChunkIndexKind,
+            generators::cubic::CubicGenerator,
+        },
     };
 
     use super::generate_chunk_world;
@@ -408,8 +663,8 @@ mod tests {
     #[test]
     fn test_chunk_generation() {
         let generator = Arc::new(CubicGenerator::new(CHUNK_SIZE));
-        let world = generate_chunk_world(2, generator);
-        let chunks = world.get_all_depth_first();
+        let world = generate_chunk_world(2, generator, ChunkIndexKind::Octree);
+        let chunks = world.all_chunks();
         // Size 2 -> 8 chunks
         assert_eq!(chunks.len(), 8);
 
@@ -426,6 +681,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cancelled_generation_drops_chunks() {
+        // Morton index reports every missing chunk, the octree whole empty nodes
+        let generator = Arc::new(CubicGenerator::new(CHUNK_SIZE));
+        let mut world = VoxelWorld::with_index(2, generator, ChunkIndexKind::Morton);
+        let size = CHUNK_SIZE as i32;
+        let region = IAabb::new_rect(IVec3::ZERO, IVec3::splat(3 * size));
+        world.expand_to_fit_region(region, &Vec3::ZERO);
+        let progress = world.generation_progress().unwrap();
+        // World grew to 4x4x4 chunks, 3x3x3 requested of which 2x2x2 exist
+        assert_eq!(progress.queued, 27 - 8);
+
+        world.cancel_chunk_generation();
+        assert!(world.generation_progress().is_none());
+        world.receive_chunks();
+        assert_eq!(world.chunks.all_chunks().len(), 8);
+
+        // Requesting a region far away replaces the running batch
+        world.expand_to_fit_region(IAabb::new(&IVec3::ZERO, 2 * CHUNK_SIZE), &Vec3::ZERO);
+        assert!(world.generation_progress().is_none());
+        let far = IAabb::new_rect(IVec3::new(2 * size, 0, 0), IVec3::new(4 * size, size, size));
+        world.expand_to_fit_region(far.clone(), &Vec3::ZERO);
+        assert_eq!(world.generation_progress().unwrap().region, far);
+    }
+
+    #[test]
+    fn test_prepare_teleport_generates_surrounding_chunks() {
+        let mut world = VoxelWorld::new_cubic(2);
+        let destination = Vec3::new(5.5, 2.5, 3.5) * CHUNK_SIZE as f32;
+        assert_eq!(world.prepare_teleport(&destination), 27);
+        assert_eq!(world.get_size(), 8);
+        let center = world.world_space_pos_to_chunk_space_pos(&destination);
+        for position in IAabb::new(&(center - IVec3::ONE), 3).iter_cells() {
+            assert!(world.chunks.get(position).is_some(), "{position}");
+        }
+        // Nothing left to generate
+        assert_eq!(world.prepare_teleport(&destination), 0);
+    }
+
+    #[test]
+    fn test_heightmap_follows_edits() {
+        let mut world = VoxelWorld::new_cubic(2);
+        let top = 2 * CHUNK_SIZE as i32 - 1;
+        assert_eq!(world.heightmap().height(5, 5), Some(top));
+        world.clear_sphere(&Vec3::new(5.0, top as f32, 5.0), 3.0);
+        assert_eq!(world.heightmap().height(5, 5), Some(top - 3));
+        assert_eq!(world.heightmap().height(9, 5), Some(top));
+    }
+
     #[test]
     fn test_chunk_region_size_2() {
         // 2x2x2 chunks
@@ -463,4 +767,225 @@ mod tests {
         // -> 3*2*2 = 12
         assert_eq!(voxels.len(), 12);
     }
+
+    #[test]
+    fn test_voxel_region_query_matches_brute_force() {
+        let world = VoxelWorld::new_cubic(2);
+        let all_voxels = world.get_all_voxels();
+        // Deterministic LCG, regions overlapping the world from all sides
+        let mut seed: u64 = 42;
+        let mut next = |range: i32| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % range as u64) as i32
+        };
+        for _ in 0..200 {
+            let min = IVec3::new(next(48), next(48), next(48)) - 8;
+            let size = IVec3::new(next(12), next(12), next(12)) + 1;
+            let region = IAabb::new_rect(min, min + size);
+            let region_f = AABB::from(&region);
+
+            let expected: HashSet<IVec3> = all_voxels
+                .iter()
+                .filter(|voxel| AABB::new_center(&voxel.position, 1.0).intersects(&region_f))
+                .map(|voxel| voxel.position.as_ivec3())
+                .collect();
+            let actual: Vec<IVec3> = world
+                .iter_region_voxels(region.clone())
+                .map(|voxel| voxel.position.as_ivec3())
+                .collect();
+            // No duplicates
+            assert_eq!(actual.len(), expected.len(), "Region {region:?}");
+            assert_eq!(
+                actual.into_iter().collect::<HashSet<IVec3>>(),
+                expected,
+                "Region {region:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_par_voxel_region_query() {
+        let world = VoxelWorld::new_cubic(2);
+        let region = IAabb::new_rect(IVec3::new(-3, 5, 10), IVec3::new(20, 30, 17));
+        let serial: HashSet<IVec3> = world
+            .iter_region_voxels(region.clone())
+            .map(|voxel| voxel.position.as_ivec3())
+            .collect();
+        let parallel: Vec<IVec3> = world
+            .par_iter_region_voxels(region)
+            .map(|voxel| voxel.position.as_ivec3())
+            .collect();
+        assert_eq!(parallel.len(), serial.len());
+        assert_eq!(parallel.into_iter().collect::<HashSet<IVec3>>(), serial);
+    }
+
+    #[test]
+    fn test_morton_index_matches_octree() {
+        let generator = Arc::new(CubicGenerator::new(CHUNK_SIZE));
+        let octree = VoxelWorld::new(2, generator.clone());
+        let morton = VoxelWorld::with_index(2, generator, ChunkIndexKind::Morton);
+        assert_eq!(morton.chunk_index_kind(), ChunkIndexKind::Morton);
+        let positions = |world: &VoxelWorld, region: IAabb| {
+            world
+                .iter_region_voxels(region)
+                .map(|voxel| voxel.position.as_ivec3())
+                .collect::<HashSet<IVec3>>()
+        };
+        for region in [
+            IAabb::new_rect(IVec3::new(-3, 5, 10), IVec3::new(20, 30, 17)),
+            IAabb::new_rect(IVec3::splat(-8), IVec3::splat(40)),
+            IAabb::new_rect(IVec3::new(15, 15, 15), IVec3::new(17, 16, 18)),
+        ] {
+            assert_eq!(
+                positions(&octree, region.clone()),
+                positions(&morton, region)
+            );
+        }
+        let origin = Vec3::new(-5.0, 4.0, 4.0);
+        let distance = |world: &VoxelWorld| {
+            world
+                .query_sphere_cast(origin, 0.4, Vec3::X, 10.0)
+                .map(|hit| hit.penetration_depth)
+        };
+        assert!(distance(&octree).is_some());
+        assert_eq!(distance(&octree), distance(&morton));
+    }
+
+    // Solid world with a spherical cavity in the center
+    fn cavity_world() -> VoxelWorld {
+        let mut world = VoxelWorld::new_cubic(4);
+        world.clear_sphere(&Vec3::splat(32.0), 10.0);
+        world
+    }
+
+    fn cast_directions() -> Vec<Vec3> {
+        let mut directions = Vec::new();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    if (x, y, z) != (0, 0, 0) {
+                        directions.push(Vec3::new(x as f32, y as f32, z as f32).normalize());
+                    }
+                }
+            }
+        }
+        directions
+    }
+
+    #[test]
+    fn test_sphere_cast_matches_brute_force() {
+        let world = cavity_world();
+        let colliders: Vec<AABB> = world
+            .get_all_voxels()
+            .iter()
+            .filter_map(|voxel| voxel.get_collider())
+            .collect();
+        let origin = Vec3::new(32.3, 31.8, 32.1);
+        for direction in cast_directions() {
+            let expected = sphere_cast(origin, 0.4, direction, 30.0, colliders.iter().cloned());
+            let actual = world.query_sphere_cast(origin, 0.4, direction, 30.0);
+            assert_eq!(
+                actual.map(|hit| hit.penetration_depth),
+                expected.map(|hit| hit.penetration_depth),
+                "Direction {direction}"
+            );
+        }
+        // Too short to reach the cavity wall
+        assert!(world.query_sphere_cast(origin, 0.4, Vec3::X, 5.0).is_none());
+    }
+
+    // Run with `cargo test --release --features gui bench_sphere_cast -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_sphere_cast_long() {
+        let world = cavity_world();
+        let origin = Vec3::splat(32.0);
+        let directions = cast_directions();
+        let iterations = 20;
+        let max_distance = 100.0;
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for direction in &directions {
+                // Previous implementation: Every voxel within the swept bb
+                let end = origin + direction * max_distance;
+                let region = IAabb::from(
+                    &AABB::new_center(&origin, 0.2).union(&AABB::new_center(&end, 0.2)),
+                );
+                let bbs = world
+                    .iter_region_voxels(region)
+                    .filter_map(|voxel| voxel.get_collider());
+                sphere_cast(origin, 0.1, *direction, max_distance, bbs);
+            }
+        }
+        let swept_bb = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..iterations {
+            for direction in &directions {
+                world.query_sphere_cast(origin, 0.1, *direction, max_distance);
+            }
+        }
+        let near_to_far = start.elapsed();
+        let casts = iterations * directions.len();
+        println!(
+            "{casts} casts of {max_distance} units. Swept bb: {:.3}ms/cast, near to far: {:.3}ms/cast",
+            swept_bb.as_secs_f64() * 1e3 / casts as f64,
+            near_to_far.as_secs_f64() * 1e3 / casts as f64,
+        );
+        assert!(near_to_far < swept_bb);
+    }
+
+    #[test]
+    fn test_voxel_region_query_at_negative_boundary() {
+        let world = VoxelWorld::new_cubic(1);
+        // Touches voxels at x = 0 from the negative side
+        let test_bb_world_space = IAabb::new_rect(IVec3::new(-1, 0, 0), IVec3::new(0, 1, 1));
+        let voxels: Vec<Voxel> = world.iter_region_voxels(test_bb_world_space).collect();
+        assert_eq!(voxels.len(), 4);
+        assert!(voxels.iter().all(|voxel| voxel.position.x == 0.0));
+    }
+
+    #[test]
+    fn test_voxel_region_query_across_negative_boundary() {
+        let world = VoxelWorld::new_cubic(1);
+        let test_bb_world_space = IAabb::new_rect(IVec3::splat(-4), IVec3::ONE);
+        let voxels: Vec<Voxel> = world.iter_region_voxels(test_bb_world_space).collect();
+        // Only voxels within the world: 0 & 1 on every axis
+        assert_eq!(voxels.len(), 8);
+        assert!(
+            voxels
+                .iter()
+                .all(|voxel| voxel.position.min_element() >= 0.0)
+        );
+    }
+
+    #[test]
+    fn test_voxel_region_query_out_of_bounds() {
+        let world = VoxelWorld::new_cubic(1);
+        let negative = IAabb::new_rect(IVec3::splat(-5), IVec3::splat(-1));
+        assert_eq!(world.iter_region_voxels(negative).count(), 0);
+        let positive = IAabb::new_rect(IVec3::splat(CHUNK_SIZE as i32 + 1), IVec3::splat(40));
+        assert_eq!(world.iter_region_voxels(positive).count(), 0);
+        let chunk_space = IAabb::new_rect(IVec3::splat(-5), IVec3::splat(-1));
+        assert_eq!(world.iter_region_chunks(&chunk_space).count(), 0);
+    }
+
+    #[test]
+    fn test_world_to_chunk_space_floors_negatives() {
+        let world = VoxelWorld::new_cubic(1);
+        assert_eq!(
+            world.world_space_pos_to_chunk_space_pos(&Vec3::new(-0.5, 0.5, 15.9)),
+            IVec3::new(-1, 0, 0)
+        );
+        assert_eq!(
+            world.world_space_bb_to_chunk_space_bb(&IAabb::new_rect(
+                IVec3::new(-17, -1, 0),
+                IVec3::new(-16, 1, 17)
+            )),
+            IAabb::new_rect(IVec3::new(-2, -1, 0), IVec3::new(-1, 1, 2))
+        );
+    }
 }
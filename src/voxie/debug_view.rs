@@ -0,0 +1,285 @@
+use glam::Vec3;
+use imgui::Ui;
+
+use crate::{
+    cameras::camera::{
+        Camera, DEFAULT_FAR, DEFAULT_FOV_Y_DEGREES, DEFAULT_NEAR, orthographic_reverse_z,
+        perspective_reverse_z,
+    },
+    renderer::viewport::Viewport,
+};
+
+// Share of the window height covered by the picture-in-picture view
+const VIEW_SCALE: f32 = 0.3;
+// Gap between the view & the window border in pixels
+const VIEW_MARGIN: i32 = 16;
+// Distance of the light camera from the focus point, along the light direction
+const LIGHT_DISTANCE: f32 = 200.0;
+// Depth up to which the frozen frustum is drawn
+const FROZEN_FRUSTUM_DEPTH: f32 = 96.0;
+
+/// Where the picture-in-picture view looks from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugCameraMode {
+    Off,
+    /// Orthographic view along the directional light, like a shadow map would see the scene
+    Light,
+    /// Perspective camera moved independently of the players
+    Free,
+}
+
+impl DebugCameraMode {
+    pub const ALL: [DebugCameraMode; 3] = [
+        DebugCameraMode::Off,
+        DebugCameraMode::Light,
+        DebugCameraMode::Free,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DebugCameraMode::Off => "Off",
+            DebugCameraMode::Light => "Directional light",
+            DebugCameraMode::Free => "Free camera",
+        }
+    }
+}
+
+/// Second view of the scene in the top right corner of the window, e.g. to check which chunks
+/// the player camera culls or what the directional light covers
+pub struct DebugView {
+    pub mode: DebugCameraMode,
+    /// Chunks are culled against the frustum of the first player instead of the debug camera
+    pub cull_with_player: bool,
+    /// Keeps culling with the player camera of the moment the frustum was frozen, so the player
+    /// can walk around & inspect which chunks it drops
+    pub freeze_frustum: bool,
+    /// Half the width & height of the area covered by the light camera
+    pub light_extent: f32,
+    free_position: Vec3,
+    free_yaw: f32,
+    free_pitch: f32,
+    // Set from the UI, applied with the next player camera
+    copy_player_camera: bool,
+    frozen_camera: Option<Camera>,
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        Self {
+            mode: DebugCameraMode::Off,
+            cull_with_player: true,
+            freeze_frustum: false,
+            light_extent: 64.0,
+            free_position: Vec3::ZERO,
+            free_yaw: 0.0,
+            free_pitch: 0.0,
+            copy_player_camera: true,
+            frozen_camera: None,
+        }
+    }
+}
+
+impl DebugView {
+    pub fn new() -> DebugView {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != DebugCameraMode::Off
+    }
+
+    /// Corner of the window the view is drawn into
+    pub fn viewport(&self, window: Viewport) -> Viewport {
+        let height = (window.height as f32 * VIEW_SCALE).round() as i32;
+        let width = (height as f32 * window.aspect_ratio()).round() as i32;
+        Viewport {
+            x: window.x + window.width - width - VIEW_MARGIN,
+            y: window.y + window.height - height - VIEW_MARGIN,
+            width,
+            height,
+        }
+    }
+
+    /// Freezes the player camera once the frustum is frozen & releases it again once unfrozen
+    pub fn update_frozen_camera(&mut self, player_camera: &Camera) {
+        match self.freeze_frustum {
+            true if self.frozen_camera.is_none() => {
+                self.frozen_camera = Some(player_camera.clone());
+            }
+            true => {}
+            false => self.frozen_camera = None,
+        }
+    }
+
+    /// Camera chunks are culled with instead of the player camera while the frustum is frozen
+    pub fn frozen_camera(&self) -> Option<&Camera> {
+        self.frozen_camera.as_ref()
+    }
+
+    /// Edges of the frozen frustum as line segments. Empty if the frustum is not frozen
+    pub fn frustum_lines(&self) -> Vec<[Vec3; 2]> {
+        let Some(camera) = &self.frozen_camera else {
+            return Vec::new();
+        };
+        let corners = camera.frustum_corners(FROZEN_FRUSTUM_DEPTH);
+        (0..4)
+            .flat_map(|i| {
+                let next = (i + 1) % 4;
+                [
+                    [corners[i], corners[next]],
+                    [corners[i + 4], corners[next + 4]],
+                    [corners[i], corners[i + 4]],
+                ]
+            })
+            .collect()
+    }
+
+    /// Camera of the current mode. The light camera is centered on focus & looks along
+    /// light_direction, which points towards the light. None if the view is off or the scene has
+    /// no directional light
+    pub fn camera(
+        &mut self,
+        player_camera: &Camera,
+        focus: Vec3,
+        light_direction: Option<Vec3>,
+        aspect_ratio: f32,
+    ) -> Option<Camera> {
+        if self.copy_player_camera {
+            self.free_position = player_camera.position;
+            self.free_yaw = player_camera.yaw();
+            self.free_pitch = player_camera.pitch();
+            self.copy_player_camera = false;
+        }
+        let mut camera = Camera::new();
+        match self.mode {
+            DebugCameraMode::Off => return None,
+            DebugCameraMode::Light => {
+                let direction = light_direction?.normalize();
+                camera.position = focus + direction * LIGHT_DISTANCE;
+                camera.look_at(focus);
+                let extent = self.light_extent;
+                camera.set_projection(orthographic_reverse_z(
+                    -extent * aspect_ratio,
+                    extent * aspect_ratio,
+                    -extent,
+                    extent,
+                    DEFAULT_NEAR,
+                    LIGHT_DISTANCE * 2.0,
+                ));
+            }
+            DebugCameraMode::Free => {
+                camera.position = self.free_position;
+                camera.set_yaw_pitch(self.free_yaw, self.free_pitch);
+                camera.set_projection(perspective_reverse_z(
+                    DEFAULT_FOV_Y_DEGREES.to_radians(),
+                    aspect_ratio,
+                    DEFAULT_NEAR,
+                    DEFAULT_FAR,
+                ));
+            }
+        }
+        Some(camera)
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Debug view")
+            .size([300.0, 190.0], imgui::Condition::FirstUseEver)
+            .position([300.0, 350.0], imgui::Condition::FirstUseEver)
+            .collapsed(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                let mut mode_idx = DebugCameraMode::ALL
+                    .iter()
+                    .position(|mode| *mode == self.mode)
+                    .unwrap_or(0);
+                let labels = DebugCameraMode::ALL.map(DebugCameraMode::label);
+                if ui.combo_simple_string("Camera", &mut mode_idx, &labels) {
+                    self.mode = DebugCameraMode::ALL[mode_idx];
+                }
+                ui.checkbox("Cull with player camera", &mut self.cull_with_player);
+                ui.checkbox("Freeze frustum", &mut self.freeze_frustum);
+                match self.mode {
+                    DebugCameraMode::Off => {}
+                    DebugCameraMode::Light => {
+                        ui.slider("Extent", 8.0, 256.0, &mut self.light_extent);
+                    }
+                    DebugCameraMode::Free => {
+                        let mut position = self.free_position.to_array();
+                        if ui.input_float3("Position", &mut position).build() {
+                            self.free_position = Vec3::from_array(position);
+                        }
+                        let mut yaw = self.free_yaw.to_degrees();
+                        if ui.slider("Yaw", -180.0, 180.0, &mut yaw) {
+                            self.free_yaw = yaw.to_radians();
+                        }
+                        let mut pitch = self.free_pitch.to_degrees();
+                        if ui.slider("Pitch", -89.0, 89.0, &mut pitch) {
+                            self.free_pitch = pitch.to_radians();
+                        }
+                        if ui.button("Copy player camera") {
+                            self.copy_player_camera = true;
+                        }
+                    }
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_camera_looks_at_focus() {
+        let mut view = DebugView::new();
+        let player = Camera::new();
+        let focus = Vec3::new(10.0, 20.0, 30.0);
+        assert!(view.camera(&player, focus, Some(Vec3::Y), 1.0).is_none());
+
+        view.mode = DebugCameraMode::Light;
+        assert!(view.camera(&player, focus, None, 1.0).is_none());
+        let light = Vec3::new(1.0, 2.0, 0.5);
+        let camera = view.camera(&player, focus, Some(light), 1.0).unwrap();
+        let forward = camera.get_rotation() * Vec3::NEG_Z;
+        assert!(forward.distance(-light.normalize()) < 1e-4, "{forward}");
+        // Focus lies in the center of the view
+        let clip = camera.get_view_projection_matrix().project_point3(focus);
+        assert!(clip.truncate().length() < 1e-4, "{clip}");
+    }
+
+    #[test]
+    fn test_frozen_frustum_stays_behind() {
+        let mut view = DebugView::new();
+        let mut player = Camera::new();
+        view.update_frozen_camera(&player);
+        assert!(view.frozen_camera().is_none());
+        assert!(view.frustum_lines().is_empty());
+
+        view.freeze_frustum = true;
+        view.update_frozen_camera(&player);
+        player.position = Vec3::new(100.0, 0.0, 0.0);
+        view.update_frozen_camera(&player);
+        assert_eq!(view.frozen_camera().unwrap().position, Vec3::ZERO);
+        let lines = view.frustum_lines();
+        assert_eq!(lines.len(), 12);
+        // Side edges run from the near plane to the far end of the drawn volume
+        assert!(
+            (lines[2][1].z + FROZEN_FRUSTUM_DEPTH).abs() < 1e-2,
+            "{:?}",
+            lines[2]
+        );
+
+        view.freeze_frustum = false;
+        view.update_frozen_camera(&player);
+        assert!(view.frozen_camera().is_none());
+    }
+
+    #[test]
+    fn test_viewport_in_top_right_corner() {
+        let view = DebugView::new();
+        let corner = view.viewport(Viewport::full(1920, 1080));
+        assert_eq!(corner.height, 324);
+        assert_eq!(corner.width, 576);
+        assert_eq!(corner.x + corner.width, 1920 - VIEW_MARGIN);
+        assert_eq!(corner.y + corner.height, 1080 - VIEW_MARGIN);
+    }
+}
@@ -0,0 +1,140 @@
+use glam::{IVec3, Mat4, Vec3};
+use hecs::World;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, RenderColor},
+        impostors::Impostor,
+    },
+    systems::{
+        names::{EntityRegistry, PLAYER, Tags},
+        physics::{Transform, Velocity},
+        serialization::ComponentRegistry,
+    },
+    voxels::navigation::NavGraph,
+};
+
+// Seconds between path queries. Targets move, so paths go stale quickly
+const REPATH_INTERVAL: f32 = 0.5;
+// Waypoints closer than this are considered reached
+const WAYPOINT_RADIUS: f32 = 0.3;
+
+#[derive(Serialize, Deserialize)]
+pub struct Enemy {
+    pub speed: f32,
+}
+
+/// Path following state of an entity walking on the nav graph
+#[derive(Serialize, Deserialize, Default)]
+pub struct NavAgent {
+    // Remaining waypoints, next one last. Recomputed after loading
+    #[serde(skip)]
+    path: Vec<IVec3>,
+    #[serde(skip)]
+    repath_cooldown: f32,
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Enemy>("Enemy");
+    registry.register::<NavAgent>("NavAgent");
+}
+
+pub fn spawn_enemy(world: &mut World, position: Vec3) -> hecs::Entity {
+    world.spawn((
+        Enemy { speed: 6.0 },
+        NavAgent::default(),
+        Transform(Mat4::from_translation(position)),
+        Velocity(Vec3::ZERO),
+        RenderMeshHandle(MESH_CUBE),
+        RenderColor(Vec3::new(0.8, 0.1, 0.1)),
+        // Colored by RenderColor
+        Impostor {
+            radius: 0.5,
+            color: Vec3::ONE,
+        },
+        Tags::new(&["enemy"]),
+    ))
+}
+
+// Walkable cells are the air voxel right above the ground, centered on the voxel
+fn waypoint_position(cell: IVec3) -> Vec3 {
+    cell.as_vec3()
+}
+
+/// Sets enemy velocities to walk along the nav graph towards the player
+pub fn system_enemy_chase(
+    world: &mut World,
+    entities: &EntityRegistry,
+    nav_graph: &NavGraph,
+    dt: f32,
+) {
+    let Some(target) = entities.position(world, PLAYER) else {
+        return;
+    };
+    let goal = nav_graph.nearest_node(target);
+    for (_entity, (enemy, agent, transform, velocity)) in
+        world.query_mut::<(&Enemy, &mut NavAgent, &Transform, &mut Velocity)>()
+    {
+        let position = transform.0.w_axis.truncate();
+        agent.repath_cooldown -= dt;
+        if agent.repath_cooldown <= 0.0 {
+            agent.repath_cooldown = REPATH_INTERVAL;
+            agent.path = match (nav_graph.nearest_node(position), goal) {
+                (Some(start), Some(goal)) => nav_graph.find_path(start, goal).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            agent.path.reverse();
+        }
+        while agent
+            .path
+            .last()
+            .is_some_and(|cell| waypoint_position(*cell).distance(position) < WAYPOINT_RADIUS)
+        {
+            agent.path.pop();
+        }
+        velocity.0 = match agent.path.last() {
+            Some(cell) => (waypoint_position(*cell) - position).normalize_or_zero() * enemy.speed,
+            None => Vec3::ZERO,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        systems::{names::Name, physics::system_movement},
+        voxels::{VoxelWorld, navigation::NavGraph},
+        voxie::player::Player,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_enemy_walks_to_player() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        let mut nav_graph = NavGraph::new();
+        nav_graph.sync(&voxel_world);
+        let mut world = World::new();
+        world.spawn((
+            Player,
+            Name::new(PLAYER),
+            Transform(Mat4::from_translation(Vec3::new(12.0, 20.0, 3.0))),
+        ));
+        let enemy = spawn_enemy(&mut world, Vec3::new(2.0, 16.0, 3.0));
+        let entities = EntityRegistry::from_world(&world);
+
+        let dt = 1.0 / 60.0;
+        for _ in 0..180 {
+            system_enemy_chase(&mut world, &entities, &nav_graph, dt);
+            system_movement(&mut world, dt);
+        }
+        let position = world.get::<&Transform>(enemy).unwrap().0.w_axis.truncate();
+        // Stays on the ground below the flying player
+        assert!(
+            position.distance(Vec3::new(12.0, 16.0, 3.0)) < 0.5,
+            "{position}"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+use imgui::Ui;
+
+/// Seconds an entry stays in the on-screen feed
+const FEED_DURATION: f32 = 6.0;
+/// Seconds at the end of FEED_DURATION the entry fades out
+const FEED_FADE: f32 = 1.5;
+const MAX_FEED_ENTRIES: usize = 6;
+/// Oldest entries are dropped from the history beyond this
+const MAX_HISTORY: usize = 500;
+
+/// Something that happened in the game that players or developers want to know about
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// Health lost by a named entity
+    Damage {
+        target: String,
+        amount: f32,
+        cause: String,
+    },
+    Died {
+        name: String,
+    },
+    /// Entity removed from the game, e.g. an exploding grenade
+    Destroyed {
+        name: String,
+        position: Vec3,
+    },
+    /// Voxels removed by edits within a tick
+    BlocksMined {
+        count: usize,
+    },
+    PlayerJoined {
+        name: String,
+    },
+    PlayerLeft {
+        name: String,
+    },
+}
+
+impl GameEvent {
+    pub fn describe(&self) -> String {
+        match self {
+            GameEvent::Damage {
+                target,
+                amount,
+                cause,
+            } => format!("{target} took {amount:.0} {cause} damage"),
+            GameEvent::Died { name } => format!("{name} died"),
+            GameEvent::Destroyed { name, position } => {
+                format!("{name} destroyed at {:.0}", position)
+            }
+            GameEvent::BlocksMined { count } => format!("{count} blocks mined"),
+            GameEvent::PlayerJoined { name } => format!("{name} joined"),
+            GameEvent::PlayerLeft { name } => format!("{name} left"),
+        }
+    }
+}
+
+/// Events published by systems during a tick. Drained once the tick is done
+#[derive(Default)]
+pub struct EventBus {
+    events: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        Self::default()
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn drain(&mut self) -> std::vec::Drain<'_, GameEvent> {
+        self.events.drain(..)
+    }
+}
+
+struct FeedEntry {
+    text: String,
+    // Feed time the event was recorded at
+    time: f32,
+}
+
+/// Recent events fading out on screen & the full history in a scrollable window
+pub struct EventFeed {
+    entries: VecDeque<FeedEntry>,
+    time: f32,
+    /// Newest entry is scrolled into view
+    pub follow: bool,
+}
+
+impl Default for EventFeed {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            time: 0.0,
+            follow: true,
+        }
+    }
+}
+
+impl EventFeed {
+    pub fn new() -> EventFeed {
+        Self::default()
+    }
+
+    pub fn record(&mut self, events: impl IntoIterator<Item = GameEvent>) {
+        for event in events {
+            if self.entries.len() == MAX_HISTORY {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(FeedEntry {
+                text: event.describe(),
+                time: self.time,
+            });
+        }
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// Entries still shown on screen, oldest first, with their opacity
+    pub fn visible(&self) -> Vec<(&str, f32)> {
+        let recent: Vec<(&str, f32)> = self
+            .entries
+            .iter()
+            .rev()
+            .take(MAX_FEED_ENTRIES)
+            .map(|entry| {
+                let remaining = FEED_DURATION - (self.time - entry.time);
+                (entry.text.as_str(), (remaining / FEED_FADE).min(1.0))
+            })
+            .take_while(|(_, alpha)| *alpha > 0.0)
+            .collect();
+        recent.into_iter().rev().collect()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Shown at the top right of the view given as x, y, width & height. Window names have to
+    /// be unique per view
+    pub fn render_hud(&self, window: &str, view: [f32; 4], ui: &Ui) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            return;
+        }
+        let [x, y, width, _height] = view;
+        ui.window(window)
+            .position([x + width - 10.0, y + 10.0], imgui::Condition::Always)
+            .position_pivot([1.0, 0.0])
+            .always_auto_resize(true)
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.0)
+            .build(|| {
+                for (text, alpha) in visible {
+                    ui.text_colored([1.0, 1.0, 1.0, alpha], text);
+                }
+            });
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Event log")
+            .size([350.0, 250.0], imgui::Condition::FirstUseEver)
+            .position([300.0, 520.0], imgui::Condition::FirstUseEver)
+            .collapsed(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("{} events", self.entries.len()));
+                ui.same_line();
+                ui.checkbox("Follow", &mut self.follow);
+                ui.same_line();
+                if ui.button("Clear") {
+                    self.entries.clear();
+                }
+                ui.child_window("History").build(|| {
+                    for entry in &self.entries {
+                        ui.text(format!("[{:7.1}s] {}", entry.time, entry.text));
+                    }
+                    if self.follow {
+                        ui.set_scroll_here_y_with_ratio(1.0);
+                    }
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joined(name: &str) -> GameEvent {
+        GameEvent::PlayerJoined {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_feed_entries_fade_out() {
+        let mut bus = EventBus::new();
+        bus.publish(joined("player 2"));
+        bus.publish(GameEvent::BlocksMined { count: 12 });
+        let mut feed = EventFeed::new();
+        feed.record(bus.drain());
+        assert!(bus.drain().next().is_none());
+        assert_eq!(
+            feed.visible(),
+            vec![("player 2 joined", 1.0), ("12 blocks mined", 1.0)]
+        );
+
+        feed.tick(FEED_DURATION - FEED_FADE / 2.0);
+        feed.record([joined("player 3")]);
+        let visible = feed.visible();
+        assert_eq!(visible.len(), 3);
+        assert!((visible[0].1 - 0.5).abs() < 1e-5);
+        assert_eq!(visible[2], ("player 3 joined", 1.0));
+
+        // Faded entries stay in the history
+        feed.tick(FEED_FADE);
+        assert_eq!(feed.visible(), vec![("player 3 joined", 1.0)]);
+        assert_eq!(feed.history_len(), 3);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut feed = EventFeed::new();
+        feed.record((0..MAX_HISTORY + 10).map(|count| GameEvent::BlocksMined { count }));
+        assert_eq!(feed.history_len(), MAX_HISTORY);
+        assert_eq!(feed.entries[0].text, "10 blocks mined");
+        assert_eq!(feed.visible().len(), MAX_FEED_ENTRIES);
+    }
+}
@@ -0,0 +1,206 @@
+use glam::Vec3;
+use hecs::World;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{
+        names::{Name, PLAYER},
+        serialization::ComponentRegistry,
+    },
+    voxels::{VoxelKind, VoxelWorld},
+};
+
+use super::{
+    events::{EventBus, GameEvent},
+    player::{Player, locomotion::MovementEvent},
+};
+
+/// Landings below this impact speed are harmless
+pub const SAFE_FALL_SPEED: f32 = 12.0;
+/// Damage per unit of impact speed above the safe speed
+const FALL_DAMAGE_PER_SPEED: f32 = 5.0;
+
+#[derive(Serialize, Deserialize)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Health {
+        Self { current: max, max }
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Health>("Health");
+}
+
+/// Damage of a landing on the given ground. Kinematic bodies & unloaded ground count as solid
+pub fn fall_damage(impact_speed: f32, ground: Option<VoxelKind>) -> f32 {
+    let factor = ground.map_or(1.0, VoxelKind::landing_damage_factor);
+    (impact_speed - SAFE_FALL_SPEED).max(0.0) * FALL_DAMAGE_PER_SPEED * factor
+}
+
+// Kind of the voxel right below the position
+fn ground_kind(voxel_world: &VoxelWorld, feet: Vec3) -> Option<VoxelKind> {
+    let cell = (feet - Vec3::Y * 0.5).round().as_ivec3();
+    voxel_world.block(cell).map(|block| block.kind)
+}
+
+// Name shown in the event feed
+fn player_name(name: Option<&Name>) -> String {
+    name.map_or(PLAYER.to_string(), |name| name.0.clone())
+}
+
+/// Applies fall damage of hard landings to the player that landed
+pub fn system_fall_damage(
+    world: &mut World,
+    voxel_world: &VoxelWorld,
+    events: &[MovementEvent],
+    bus: &mut EventBus,
+) {
+    for event in events {
+        let MovementEvent::Landed {
+            entity,
+            position,
+            impact_speed,
+        } = *event
+        else {
+            continue;
+        };
+        let damage = fall_damage(impact_speed, ground_kind(voxel_world, position));
+        if damage <= 0.0 {
+            continue;
+        }
+        let Ok((_player, health, name)) =
+            world.query_one_mut::<(&Player, &mut Health, Option<&Name>)>(entity)
+        else {
+            continue;
+        };
+        health.damage(damage);
+        info!(
+            "Player took {damage:.0} fall damage, {:.0} health left",
+            health.current
+        );
+        bus.publish(GameEvent::Damage {
+            target: player_name(name),
+            amount: damage,
+            cause: "fall".to_string(),
+        });
+    }
+}
+
+/// Restores the health of a dead player. Returns true if the player has to be respawned
+pub fn system_revive_player(world: &mut World, bus: &mut EventBus) -> bool {
+    let mut revived = false;
+    for (_entity, (_player, health, name)) in
+        world.query_mut::<(&Player, &mut Health, Option<&Name>)>()
+    {
+        if health.is_dead() {
+            info!("Player died. Respawning");
+            bus.publish(GameEvent::Died {
+                name: player_name(name),
+            });
+            health.current = health.max;
+            revived = true;
+        }
+    }
+    revived
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use crate::systems::physics::Transform;
+
+    use super::*;
+
+    #[test]
+    fn test_fall_damage_scales_with_impact() {
+        assert_eq!(fall_damage(SAFE_FALL_SPEED, None), 0.0);
+        assert_eq!(
+            fall_damage(SAFE_FALL_SPEED + 2.0, Some(VoxelKind::Dirt)),
+            10.0
+        );
+        // Soft ground
+        assert_eq!(
+            fall_damage(SAFE_FALL_SPEED + 2.0, Some(VoxelKind::Sand)),
+            5.0
+        );
+    }
+
+    #[test]
+    fn test_landing_damages_player() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        let mut world = World::new();
+        let player = world.spawn((Player, Transform(Mat4::IDENTITY), Health::new(100.0)));
+        // Top face of the world is at y = 15.5
+        let landing = |impact_speed| MovementEvent::Landed {
+            entity: player,
+            position: Vec3::new(4.0, 15.5, 4.0),
+            impact_speed,
+        };
+        assert!(matches!(
+            ground_kind(&voxel_world, Vec3::new(4.0, 15.5, 4.0)),
+            Some(VoxelKind::Dirt)
+        ));
+
+        let mut bus = EventBus::new();
+        system_fall_damage(&mut world, &voxel_world, &[landing(5.0)], &mut bus);
+        assert_eq!(world.get::<&Health>(player).unwrap().current, 100.0);
+        assert!(bus.drain().next().is_none());
+        system_fall_damage(
+            &mut world,
+            &voxel_world,
+            &[landing(SAFE_FALL_SPEED + 30.0)],
+            &mut bus,
+        );
+        let health = world.get::<&Health>(player).unwrap();
+        assert_eq!(health.current, 0.0);
+        assert!(health.is_dead());
+        drop(health);
+
+        assert!(system_revive_player(&mut world, &mut bus));
+        assert_eq!(world.get::<&Health>(player).unwrap().current, 100.0);
+        assert!(!system_revive_player(&mut world, &mut bus));
+        let events: Vec<GameEvent> = bus.drain().collect();
+        assert!(matches!(
+            &events[..],
+            [GameEvent::Damage { .. }, GameEvent::Died { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_landing_damages_only_landed_player() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        let mut world = World::new();
+        let falling = world.spawn((Player, Health::new(100.0), Name("Falling".to_string())));
+        let standing = world.spawn((Player, Health::new(100.0), Name("Standing".to_string())));
+        let landing = MovementEvent::Landed {
+            entity: falling,
+            position: Vec3::new(4.0, 15.5, 4.0),
+            impact_speed: SAFE_FALL_SPEED + 2.0,
+        };
+
+        let mut bus = EventBus::new();
+        system_fall_damage(&mut world, &voxel_world, &[landing], &mut bus);
+        assert_eq!(world.get::<&Health>(falling).unwrap().current, 90.0);
+        assert_eq!(world.get::<&Health>(standing).unwrap().current, 100.0);
+        let events: Vec<GameEvent> = bus.drain().collect();
+        assert!(matches!(
+            &events[..],
+            [GameEvent::Damage { target, .. }] if target == "Falling"
+        ));
+    }
+}
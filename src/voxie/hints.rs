@@ -0,0 +1,188 @@
+use std::collections::BTreeSet;
+
+use glam::Vec3Swizzles;
+use hecs::{Entity, World};
+use imgui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::physics::Velocity;
+
+use super::{
+    enemy::Enemy,
+    player::{Player, local_players},
+};
+
+// Horizontal speed above which a player counts as walking
+const MOVE_SPEED_THRESHOLD: f32 = 1.0;
+// Enemies alive at once before grenades are suggested
+const GRENADE_HINT_ENEMIES: usize = 3;
+
+/// One-time prompt teaching a control of the first player
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Hint {
+    Move,
+    Fire,
+    ThrowGrenade,
+}
+
+impl Hint {
+    /// In order of priority
+    pub const ALL: [Hint; 3] = [Hint::Move, Hint::Fire, Hint::ThrowGrenade];
+
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Hint::Move => "Move with W / S, look around with the mouse",
+            Hint::Fire => "Fire with the left mouse button",
+            Hint::ThrowGrenade => "Hold G to aim a grenade, release to throw it",
+        }
+    }
+
+    // Whether the game state calls for the hint
+    fn triggered(self, world: &World) -> bool {
+        match self {
+            Hint::Move => !local_players(world).is_empty(),
+            Hint::Fire => world.query::<&Enemy>().iter().next().is_some(),
+            Hint::ThrowGrenade => world.query::<&Enemy>().iter().count() >= GRENADE_HINT_ENEMIES,
+        }
+    }
+
+    fn performed(self, actions: HintActions) -> bool {
+        match self {
+            Hint::Move => actions.moved,
+            Hint::Fire => actions.fired,
+            Hint::ThrowGrenade => actions.threw,
+        }
+    }
+}
+
+/// What the local players did within a tick
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HintActions {
+    pub moved: bool,
+    pub fired: bool,
+    pub threw: bool,
+}
+
+impl HintActions {
+    /// Actions of local players, given the entities that fired & threw grenades this tick
+    pub fn observe(world: &World, shooters: &[Entity], throwers: &[Entity]) -> HintActions {
+        let is_player = |entity: &Entity| world.get::<&Player>(*entity).is_ok();
+        let moved = world
+            .query::<(&Player, &Velocity)>()
+            .iter()
+            .any(|(_, (_, velocity))| velocity.0.xz().length() > MOVE_SPEED_THRESHOLD);
+        HintActions {
+            moved,
+            fired: shooters.iter().any(is_player),
+            threw: throwers.iter().any(is_player),
+        }
+    }
+}
+
+/// Picks the hint shown in the HUD. Hints are completed once their action is performed, even if
+/// they were not shown yet
+#[derive(Default)]
+pub struct HintTracker {
+    active: Option<Hint>,
+}
+
+impl HintTracker {
+    pub fn new() -> HintTracker {
+        Self::default()
+    }
+
+    pub fn active(&self) -> Option<Hint> {
+        self.active
+    }
+
+    /// Completes performed hints & shows the next hint the game state calls for. Returns true if
+    /// a hint was completed, so the settings need to be saved
+    pub fn update(
+        &mut self,
+        world: &World,
+        actions: HintActions,
+        completed: &mut BTreeSet<Hint>,
+    ) -> bool {
+        let mut changed = false;
+        for hint in Hint::ALL {
+            if hint.performed(actions) {
+                changed |= completed.insert(hint);
+            }
+        }
+        if self.active.is_some_and(|hint| completed.contains(&hint)) {
+            self.active = None;
+        }
+        if self.active.is_none() {
+            self.active = Hint::ALL
+                .into_iter()
+                .find(|hint| !completed.contains(hint) && hint.triggered(world));
+        }
+        changed
+    }
+
+    /// Prompt at the bottom of the view given as x, y, width & height
+    pub fn render_hud(&self, view: [f32; 4], ui: &Ui) {
+        let Some(hint) = self.active else {
+            return;
+        };
+        let [x, y, width, height] = view;
+        ui.window("Hint")
+            .position(
+                [x + width / 2.0, y + height * 0.85],
+                imgui::Condition::Always,
+            )
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .no_decoration()
+            .no_inputs()
+            .build(|| {
+                ui.text(hint.prompt());
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use crate::voxie::player::spawn_player;
+
+    use super::*;
+
+    #[test]
+    fn test_hints_follow_game_state() {
+        let mut world = World::new();
+        let mut tracker = HintTracker::new();
+        let mut completed = BTreeSet::new();
+        let idle = HintActions::default();
+        assert!(!tracker.update(&world, idle, &mut completed));
+        assert_eq!(tracker.active(), None);
+
+        let player = spawn_player(&mut world, Vec3::ZERO);
+        tracker.update(&world, idle, &mut completed);
+        assert_eq!(tracker.active(), Some(Hint::Move));
+        // Firing early completes the fire hint before it is shown
+        let fired = HintActions {
+            fired: true,
+            ..idle
+        };
+        assert!(tracker.update(&world, fired, &mut completed));
+        assert_eq!(tracker.active(), Some(Hint::Move));
+
+        world.get::<&mut Velocity>(player).unwrap().0 = Vec3::X * 5.0;
+        let actions = HintActions::observe(&world, &[], &[]);
+        assert!(actions.moved && !actions.fired);
+        assert!(tracker.update(&world, actions, &mut completed));
+        assert_eq!(tracker.active(), None);
+
+        for _ in 0..GRENADE_HINT_ENEMIES {
+            world.spawn((Enemy { speed: 1.0 },));
+        }
+        tracker.update(&world, idle, &mut completed);
+        assert_eq!(tracker.active(), Some(Hint::ThrowGrenade));
+        let actions = HintActions::observe(&world, &[], &[player]);
+        assert!(tracker.update(&world, actions, &mut completed));
+        assert_eq!(tracker.active(), None);
+        assert_eq!(completed.len(), Hint::ALL.len());
+    }
+}
@@ -0,0 +1,275 @@
+use glam::{Mat4, Vec3};
+use hecs::{Entity, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+
+use crate::{
+    cameras::camera::Camera,
+    input::InputState,
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, RenderColor},
+    },
+    systems::{
+        names::{EntityRegistry, PLAYER},
+        physics::Transform,
+        serialization::ComponentRegistry,
+    },
+};
+
+/// Max distance between player & interactable
+pub const INTERACTION_RANGE: f32 = 5.0;
+const USE_KEY: KeyCode = KeyCode::KeyE;
+const TOGGLE_ON_COLOR: Vec3 = Vec3::new(0.1, 0.8, 0.1);
+const TOGGLE_OFF_COLOR: Vec3 = Vec3::new(0.8, 0.5, 0.1);
+
+/// World object the player can use, e.g. doors, levers & chests
+#[derive(Serialize, Deserialize)]
+pub struct Interactable {
+    /// Action shown in the HUD, e.g. "open chest"
+    pub prompt: String,
+    /// Radius of the sphere targeted by the camera ray
+    pub radius: f32,
+}
+
+/// Binary state flipped on every interaction, e.g. levers & doors
+#[derive(Serialize, Deserialize)]
+pub struct Toggle {
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InteractionEvent {
+    pub interactor: Entity,
+    pub target: Entity,
+}
+
+/// Currently targeted interactable. Recomputed every tick
+#[derive(Default)]
+pub struct InteractionState {
+    pub target: Option<Entity>,
+    // Interactions trigger on key press, not while holding the key
+    use_key_down: bool,
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Interactable>("Interactable");
+    registry.register::<Toggle>("Toggle");
+}
+
+pub fn spawn_lever(world: &mut World, position: Vec3) -> Entity {
+    world.spawn((
+        Interactable {
+            prompt: "pull lever".to_string(),
+            radius: 0.75,
+        },
+        Toggle { active: false },
+        Transform(Mat4::from_translation(position)),
+        RenderMeshHandle(MESH_CUBE),
+        RenderColor(TOGGLE_OFF_COLOR),
+    ))
+}
+
+// Distance along the normalized direction to the first intersection
+fn ray_sphere_distance(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let far = -b + discriminant.sqrt();
+    if far < 0.0 {
+        // Sphere behind the ray
+        return None;
+    }
+    Some((-b - discriminant.sqrt()).max(0.0))
+}
+
+/// Targets the closest interactable along the camera view ray that is within range of the
+/// player. Returns an event once the use key is pressed
+pub fn system_interaction(
+    world: &World,
+    entities: &EntityRegistry,
+    camera: &Camera,
+    input: &InputState,
+    state: &mut InteractionState,
+) -> Vec<InteractionEvent> {
+    let use_pressed = input.is_key_pressed(&USE_KEY) && !state.use_key_down;
+    state.use_key_down = input.is_key_pressed(&USE_KEY);
+    state.target = None;
+    let (Some(player), Some(player_position)) = (
+        entities.get(world, PLAYER),
+        entities.position(world, PLAYER),
+    ) else {
+        return Vec::new();
+    };
+
+    let direction = camera.get_rotation() * Vec3::NEG_Z;
+    state.target = world
+        .query::<(&Interactable, &Transform)>()
+        .iter()
+        .filter_map(|(entity, (interactable, transform))| {
+            let center = transform.0.w_axis.truncate();
+            if center.distance(player_position) > INTERACTION_RANGE + interactable.radius {
+                return None;
+            }
+            ray_sphere_distance(camera.position, direction, center, interactable.radius)
+                .map(|distance| (entity, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(entity, _)| entity);
+
+    match state.target {
+        Some(target) if use_pressed => {
+            debug!("Player interacting with {target:?}");
+            vec![InteractionEvent {
+                interactor: player,
+                target,
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+pub fn system_toggle_interactions(world: &mut World, events: &[InteractionEvent]) {
+    for event in events {
+        let Ok((toggle, color)) =
+            world.query_one_mut::<(&mut Toggle, Option<&mut RenderColor>)>(event.target)
+        else {
+            continue;
+        };
+        toggle.active = !toggle.active;
+        if let Some(color) = color {
+            color.0 = match toggle.active {
+                true => TOGGLE_ON_COLOR,
+                false => TOGGLE_OFF_COLOR,
+            };
+        }
+    }
+}
+
+/// "Press E" prompt for the targeted interactable
+/// Shown in the lower center of the view given as x, y, width & height
+pub fn render_interaction_prompt(
+    world: &World,
+    state: &InteractionState,
+    view: [f32; 4],
+    ui: &imgui::Ui,
+) {
+    let Some(interactable) = state
+        .target
+        .and_then(|target| world.get::<&Interactable>(target).ok())
+    else {
+        return;
+    };
+    let [x, y, width, height] = view;
+    ui.window("Interaction")
+        .position(
+            [x + width / 2.0, y + height * 0.75],
+            imgui::Condition::Always,
+        )
+        .position_pivot([0.5, 0.5])
+        .always_auto_resize(true)
+        .no_decoration()
+        .no_inputs()
+        .build(|| {
+            ui.text(format!("Press E to {}", interactable.prompt));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{systems::names::Name, voxie::player::Player};
+
+    use super::*;
+
+    fn spawn_target(world: &mut World, position: Vec3) -> Entity {
+        world.spawn((
+            Interactable {
+                prompt: "open chest".to_string(),
+                radius: 0.5,
+            },
+            Transform(Mat4::from_translation(position)),
+        ))
+    }
+
+    #[test]
+    fn test_ray_sphere_distance() {
+        let hit = ray_sphere_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::NEG_Z * 5.0, 1.0);
+        assert_eq!(hit, Some(4.0));
+        // Inside the sphere
+        assert_eq!(
+            ray_sphere_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::ZERO, 1.0),
+            Some(0.0)
+        );
+        assert_eq!(
+            ray_sphere_distance(Vec3::ZERO, Vec3::Z, Vec3::NEG_Z * 5.0, 1.0),
+            None
+        );
+        assert_eq!(
+            ray_sphere_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::new(2.0, 0.0, -5.0), 1.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_targets_closest_in_range() {
+        let mut world = World::new();
+        world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        let entities = EntityRegistry::from_world(&world);
+        // Camera behind the player, looking along -Z
+        let mut camera = Camera::new();
+        camera.position = Vec3::Z * 5.0;
+        let behind_player = spawn_target(&mut world, Vec3::Z * 2.0);
+        spawn_target(&mut world, Vec3::NEG_Z * 3.0);
+        spawn_target(&mut world, Vec3::NEG_Z * 10.0);
+        let mut state = InteractionState::default();
+
+        let events = system_interaction(&world, &entities, &camera, &InputState::new(), &mut state);
+        assert!(events.is_empty());
+        assert_eq!(state.target, Some(behind_player));
+
+        world.despawn(behind_player).unwrap();
+        system_interaction(&world, &entities, &camera, &InputState::new(), &mut state);
+        let target = state.target.unwrap();
+        assert_eq!(
+            world.get::<&Transform>(target).unwrap().0.w_axis.z,
+            -3.0,
+            "Target out of range of the player"
+        );
+
+        camera.position = Vec3::new(3.0, 0.0, 5.0);
+        system_interaction(&world, &entities, &camera, &InputState::new(), &mut state);
+        assert_eq!(state.target, None);
+    }
+
+    #[test]
+    fn test_use_key_toggles_once_per_press() {
+        let mut world = World::new();
+        world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        let entities = EntityRegistry::from_world(&world);
+        let mut camera = Camera::new();
+        camera.position = Vec3::Z * 5.0;
+        let lever = spawn_lever(&mut world, Vec3::NEG_Z * 2.0);
+        let mut state = InteractionState::default();
+        let mut input = InputState::new();
+        input.key_pressed(USE_KEY);
+
+        for _ in 0..3 {
+            let events = system_interaction(&world, &entities, &camera, &input, &mut state);
+            system_toggle_interactions(&mut world, &events);
+        }
+        assert!(world.get::<&Toggle>(lever).unwrap().active);
+
+        input.key_released(&USE_KEY);
+        system_interaction(&world, &entities, &camera, &input, &mut state);
+        input.key_pressed(USE_KEY);
+        let events = system_interaction(&world, &entities, &camera, &input, &mut state);
+        assert_eq!(events.len(), 1);
+        system_toggle_interactions(&mut world, &events);
+        assert!(!world.get::<&Toggle>(lever).unwrap().active);
+    }
+}
@@ -0,0 +1,287 @@
+use glam::{Mat4, Quat, Vec3};
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    octree::AABB,
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, RenderColor},
+    },
+    systems::{physics::Transform, projectiles::Lifetime, serialization::ComponentRegistry},
+};
+
+use super::interaction::{Interactable, Toggle};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PathMode {
+    /// Moves towards the last waypoint while toggled on & back to the first while off, e.g. doors
+    Toggled,
+    /// Back & forth between first & last waypoint, e.g. elevators
+    PingPong,
+    /// Last waypoint connects back to the first, e.g. moving platforms
+    Loop,
+}
+
+/// Solid box moved by script instead of physics. Pushes & carries the player, but is never
+/// pushed back
+#[derive(Serialize, Deserialize)]
+pub struct KinematicBody {
+    pub half_extents: Vec3,
+    /// Velocity of the last tick, transferred to the player on contact
+    #[serde(skip)]
+    pub velocity: Vec3,
+}
+
+impl KinematicBody {
+    pub fn new(half_extents: Vec3) -> KinematicBody {
+        Self {
+            half_extents,
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    pub fn aabb(&self, position: Vec3) -> AABB {
+        AABB::new(position - self.half_extents, position + self.half_extents)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KinematicPath {
+    pub waypoints: Vec<Vec3>,
+    pub speed: f32,
+    pub mode: PathMode,
+    // Index of the waypoint currently moved towards
+    target: usize,
+    // Moving towards the first waypoint
+    reverse: bool,
+}
+
+impl KinematicPath {
+    /// Starts at the first waypoint
+    pub fn new(waypoints: Vec<Vec3>, speed: f32, mode: PathMode) -> KinematicPath {
+        debug_assert!(!waypoints.is_empty(), "Path needs at least one waypoint");
+        Self {
+            waypoints,
+            speed,
+            mode,
+            target: 0,
+            reverse: mode == PathMode::Toggled,
+        }
+    }
+
+    // Picks the waypoint after the current target. Returns false if the path ends here
+    fn advance(&mut self) -> bool {
+        let last = self.waypoints.len() - 1;
+        let previous = self.target;
+        self.target = match self.mode {
+            PathMode::Toggled if self.reverse => self.target.saturating_sub(1),
+            PathMode::Toggled => (self.target + 1).min(last),
+            PathMode::PingPong => {
+                if (self.reverse && self.target == 0) || (!self.reverse && self.target == last) {
+                    self.reverse = !self.reverse;
+                }
+                match self.reverse {
+                    true => self.target.saturating_sub(1),
+                    false => (self.target + 1).min(last),
+                }
+            }
+            PathMode::Loop => (self.target + 1) % self.waypoints.len(),
+        };
+        self.target != previous
+    }
+
+    /// Moves position along the path by speed * dt
+    fn step(&mut self, mut position: Vec3, active: bool, dt: f32) -> Vec3 {
+        match self.mode {
+            // Turn around when toggled mid-way
+            PathMode::Toggled if self.reverse == active => {
+                self.reverse = !active;
+                self.advance();
+            }
+            PathMode::PingPong | PathMode::Loop if !active => return position,
+            _ => {}
+        }
+        let mut remaining = self.speed * dt;
+        // Bounded, so a path of identical waypoints cannot loop forever
+        for _ in 0..=self.waypoints.len() {
+            let offset = self.waypoints[self.target] - position;
+            let distance = offset.length();
+            if distance > remaining {
+                return position + offset / distance * remaining;
+            }
+            position = self.waypoints[self.target];
+            remaining -= distance;
+            if !self.advance() {
+                break;
+            }
+        }
+        position
+    }
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<KinematicBody>("KinematicBody");
+    registry.register::<KinematicPath>("KinematicPath");
+}
+
+fn spawn_kinematic(world: &mut World, half_extents: Vec3, path: KinematicPath) -> Entity {
+    let transform = Mat4::from_scale_rotation_translation(
+        half_extents * 2.0,
+        Quat::IDENTITY,
+        path.waypoints[0],
+    );
+    world.spawn((
+        KinematicBody::new(half_extents),
+        path,
+        Transform(transform),
+        RenderMeshHandle(MESH_CUBE),
+        RenderColor(Vec3::splat(0.6)),
+    ))
+}
+
+/// Follows its path without stopping, e.g. elevators
+pub fn spawn_platform(
+    world: &mut World,
+    half_extents: Vec3,
+    waypoints: Vec<Vec3>,
+    speed: f32,
+    mode: PathMode,
+) -> Entity {
+    spawn_kinematic(
+        world,
+        half_extents,
+        KinematicPath::new(waypoints, speed, mode),
+    )
+}
+
+/// Slides from closed to open position when used
+pub fn spawn_door(world: &mut World, half_extents: Vec3, closed: Vec3, open: Vec3) -> Entity {
+    let path = KinematicPath::new(vec![closed, open], 4.0, PathMode::Toggled);
+    let door = spawn_kinematic(world, half_extents, path);
+    world
+        .insert(
+            door,
+            (
+                Interactable {
+                    prompt: "open door".to_string(),
+                    radius: half_extents.max_element(),
+                },
+                Toggle { active: false },
+            ),
+        )
+        .expect("Door vanished");
+    door
+}
+
+/// Static platform that despawns after its lifetime, e.g. to catch the player after teleporting
+/// until the surrounding terrain is loaded. Top is placed at the given height
+pub fn spawn_temporary_floor(
+    world: &mut World,
+    top_center: Vec3,
+    half_extents: Vec3,
+    lifetime: f32,
+) -> Entity {
+    let center = top_center - Vec3::Y * half_extents.y;
+    world.spawn((
+        KinematicBody::new(half_extents),
+        Transform(Mat4::from_scale_rotation_translation(
+            half_extents * 2.0,
+            Quat::IDENTITY,
+            center,
+        )),
+        Lifetime(lifetime),
+        RenderMeshHandle(MESH_CUBE),
+        RenderColor(Vec3::new(0.3, 0.6, 0.9)),
+    ))
+}
+
+/// Moves kinematic bodies along their paths. Has to run before player movement, which reacts to
+/// the new body positions. Bodies with a Toggle only move while it is active
+pub fn system_kinematic_bodies(world: &mut World, dt: f32) {
+    for (_entity, (body, path, transform, toggle)) in world.query_mut::<(
+        &mut KinematicBody,
+        &mut KinematicPath,
+        &mut Transform,
+        Option<&Toggle>,
+    )>() {
+        let active = toggle.is_none_or(|toggle| toggle.active);
+        let position = transform.0.w_axis.truncate();
+        let next = path.step(position, active, dt);
+        body.velocity = (next - position) / dt;
+        transform.0.w_axis = next.extend(1.0);
+    }
+}
+
+/// World space boxes & velocities of all kinematic bodies
+pub fn kinematic_obstacles(world: &World) -> Vec<(AABB, Vec3)> {
+    world
+        .query::<(&KinematicBody, &Transform)>()
+        .iter()
+        .map(|(_entity, (body, transform))| {
+            (body.aabb(transform.0.w_axis.truncate()), body.velocity)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(path: &mut KinematicPath, mut position: Vec3, active: bool, steps: usize) -> Vec3 {
+        for _ in 0..steps {
+            position = path.step(position, active, 0.25);
+        }
+        position
+    }
+
+    #[test]
+    fn test_path_modes() {
+        let waypoints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)];
+        let mut looping = KinematicPath::new(waypoints.clone(), 1.0, PathMode::Loop);
+        // Once around the loop (2 + sqrt(2)), then continues towards the second waypoint
+        let position = run(&mut looping, Vec3::ZERO, true, 14);
+        let expected = Vec3::X * (3.5 - 2.0 - 2.0_f32.sqrt());
+        assert!(position.distance(expected) < 1e-4, "{position}");
+
+        let mut ping_pong = KinematicPath::new(waypoints, 1.0, PathMode::PingPong);
+        let position = run(&mut ping_pong, Vec3::ZERO, true, 4 * 3);
+        assert!(position.distance(Vec3::X) < 1e-5, "{position}");
+        // Inactive paths stand still
+        assert_eq!(run(&mut ping_pong, position, false, 4), position);
+    }
+
+    #[test]
+    fn test_toggled_path() {
+        let mut door = KinematicPath::new(vec![Vec3::ZERO, Vec3::Y * 2.0], 1.0, PathMode::Toggled);
+        assert_eq!(run(&mut door, Vec3::ZERO, false, 4), Vec3::ZERO);
+        let position = run(&mut door, Vec3::ZERO, true, 4);
+        assert_eq!(position, Vec3::Y);
+        // Turns around mid-way
+        let position = run(&mut door, position, false, 2);
+        assert_eq!(position, Vec3::Y * 0.5);
+        // Stays open
+        assert_eq!(run(&mut door, position, true, 20), Vec3::Y * 2.0);
+    }
+
+    #[test]
+    fn test_toggled_body_velocity() {
+        let mut world = World::new();
+        let door = spawn_door(&mut world, Vec3::ONE, Vec3::ZERO, Vec3::Y * 4.0);
+        system_kinematic_bodies(&mut world, 0.5);
+        assert_eq!(
+            world.get::<&KinematicBody>(door).unwrap().velocity,
+            Vec3::ZERO
+        );
+
+        world.get::<&mut Toggle>(door).unwrap().active = true;
+        system_kinematic_bodies(&mut world, 0.5);
+        assert_eq!(
+            world.get::<&KinematicBody>(door).unwrap().velocity,
+            Vec3::Y * 4.0
+        );
+        let obstacles = kinematic_obstacles(&world);
+        assert_eq!(obstacles.len(), 1);
+        assert_eq!(obstacles[0].0.min, Vec3::new(-1.0, 1.0, -1.0));
+    }
+}
@@ -1,3 +1,20 @@
+pub mod debug_view;
+pub mod enemy;
+pub mod events;
 pub mod game_context;
+pub mod health;
+pub mod hints;
+pub mod interaction;
+pub mod kinematic;
+pub mod monitor;
+pub mod objectives;
 pub mod player;
+pub mod save;
 pub mod scene;
+pub mod settings;
+pub mod spawner;
+pub mod sound;
+pub mod splitscreen;
+pub mod stats;
+pub mod waves;
+pub mod weather;
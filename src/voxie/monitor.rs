@@ -0,0 +1,104 @@
+use glam::{Mat4, Quat, Vec3};
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cameras::camera::Camera,
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, MESH_QUAD, RenderColor, SHADER_SCREEN},
+        material::{Material, TextureHandle},
+        texture_camera::TextureCamera,
+    },
+    systems::{physics::Transform, serialization::ComponentRegistry},
+};
+
+/// Render target of the security camera placed in the level
+pub const SECURITY_FEED: TextureHandle = 0;
+const FEED_WIDTH: i32 = 480;
+const FEED_HEIGHT: i32 = 270;
+const CAMERA_COLOR: Vec3 = Vec3::splat(0.2);
+
+/// Quad showing the image of a texture camera. The material is runtime only & rebuilt from this
+#[derive(Serialize, Deserialize)]
+pub struct SecurityMonitor {
+    pub feed: TextureHandle,
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<SecurityMonitor>("SecurityMonitor");
+}
+
+/// Camera at position looking at target, streaming into the feed texture
+pub fn spawn_security_camera(
+    world: &mut World,
+    feed: TextureHandle,
+    position: Vec3,
+    target: Vec3,
+) -> Entity {
+    let mut camera = Camera::new();
+    camera.position = position;
+    camera.look_at(target);
+    world.spawn((
+        TextureCamera::new(feed, FEED_WIDTH, FEED_HEIGHT),
+        Transform(Mat4::from_scale_rotation_translation(
+            Vec3::splat(0.3),
+            camera.get_rotation(),
+            position,
+        )),
+        RenderMeshHandle(MESH_CUBE),
+        RenderColor(CAMERA_COLOR),
+    ))
+}
+
+/// Screen facing along the yaw rotation, sized to the aspect ratio of the feed
+pub fn spawn_monitor(world: &mut World, feed: TextureHandle, position: Vec3, yaw: f32) -> Entity {
+    // Quad mesh spans [-1, 1]
+    let half_height = 1.0;
+    let half_width = half_height * FEED_WIDTH as f32 / FEED_HEIGHT as f32;
+    world.spawn((
+        SecurityMonitor { feed },
+        Transform(Mat4::from_scale_rotation_translation(
+            Vec3::new(half_width, half_height, 1.0),
+            Quat::from_rotation_y(yaw),
+            position,
+        )),
+        RenderMeshHandle(MESH_QUAD),
+    ))
+}
+
+/// Attaches the screen material to monitors missing it, e.g. after loading a save
+pub fn system_security_monitors(world: &mut World) {
+    let missing: Vec<(Entity, TextureHandle)> = world
+        .query::<&SecurityMonitor>()
+        .without::<&Material>()
+        .iter()
+        .map(|(entity, monitor)| (entity, monitor.feed))
+        .collect();
+    for (entity, feed) in missing {
+        let material = Material::new()
+            .with_shader(SHADER_SCREEN)
+            .with_texture(0, "uScreen", feed);
+        let _ = world.insert_one(entity, material);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_material_restored() {
+        let mut world = World::new();
+        let monitor = spawn_monitor(&mut world, SECURITY_FEED, Vec3::ZERO, 0.0);
+        system_security_monitors(&mut world);
+        let material = Material::clone(&world.get::<&Material>(monitor).unwrap());
+        assert_eq!(material.shader, Some(SHADER_SCREEN));
+        assert_eq!(material.textures[0].texture, SECURITY_FEED);
+
+        // Restored saves only hold the monitor component
+        let _ = world.remove_one::<Material>(monitor);
+        system_security_monitors(&mut world);
+        assert_eq!(*world.get::<&Material>(monitor).unwrap(), material);
+    }
+}
@@ -0,0 +1,301 @@
+use std::{error::Error, fs, path::Path};
+
+use glam::Vec3;
+use hecs::{Entity, World};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::{
+    names::{EntityRegistry, PLAYER},
+    serialization::ComponentRegistry,
+};
+
+/// Objective plan loaded by the game scene. The built-in plan is used if missing
+pub const DEFAULT_OBJECTIVES_PATH: &str = "assets/objectives.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ObjectiveKind {
+    /// Player root within the radius around the position
+    ReachLocation { position: Vec3, radius: f32 },
+    /// Solid voxels removed by the player's edits
+    MineBlocks { count: usize },
+    /// Seconds without dying. Restarts on death
+    Survive { seconds: f32 },
+}
+
+/// Applied by the game scene once its objective is completed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ObjectiveReward {
+    RestoreHealth,
+    Teleport { position: Vec3 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Objective {
+    /// Shown in the HUD
+    pub description: String,
+    pub kind: ObjectiveKind,
+    pub reward: Option<ObjectiveReward>,
+}
+
+/// Objectives completed one after another
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ObjectivePlan {
+    pub objectives: Vec<Objective>,
+}
+
+impl ObjectivePlan {
+    /// Reads a plan from a json file
+    pub fn load(path: &Path) -> Result<ObjectivePlan, Box<dyn Error>> {
+        let plan = serde_json::from_slice(&fs::read(path)?)?;
+        info!("Loaded objectives from {}", path.display());
+        Ok(plan)
+    }
+}
+
+impl Default for ObjectivePlan {
+    /// Dig in, survive the first waves, then climb the platform
+    fn default() -> Self {
+        Self {
+            objectives: vec![
+                Objective {
+                    description: "Mine 200 blocks".to_string(),
+                    kind: ObjectiveKind::MineBlocks { count: 200 },
+                    reward: Some(ObjectiveReward::RestoreHealth),
+                },
+                Objective {
+                    description: "Survive for 2 minutes".to_string(),
+                    kind: ObjectiveKind::Survive { seconds: 120.0 },
+                    reward: None,
+                },
+                Objective {
+                    description: "Reach the top of the platform".to_string(),
+                    kind: ObjectiveKind::ReachLocation {
+                        position: Vec3::new(44.0, 66.5, 50.0),
+                        radius: 3.0,
+                    },
+                    reward: Some(ObjectiveReward::Teleport {
+                        position: Vec3::new(64.0, 48.0, 64.0),
+                    }),
+                },
+            ],
+        }
+    }
+}
+
+/// Tracks the active objective of a plan
+#[derive(Serialize, Deserialize)]
+pub struct ObjectiveTracker {
+    pub plan: ObjectivePlan,
+    /// Index of the active objective. Past the end once all are completed
+    pub current: usize,
+    /// Mined blocks or survived seconds of the active objective
+    pub progress: f32,
+}
+
+impl ObjectiveTracker {
+    pub fn active(&self) -> Option<&Objective> {
+        self.plan.objectives.get(self.current)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveCompleted {
+    pub index: usize,
+    pub reward: Option<ObjectiveReward>,
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<ObjectiveTracker>("ObjectiveTracker");
+}
+
+pub fn spawn_objective_tracker(world: &mut World, plan: ObjectivePlan) -> Entity {
+    world.spawn((ObjectiveTracker {
+        plan,
+        current: 0,
+        progress: 0.0,
+    },))
+}
+
+/// Advances the active objectives. Mined voxels & deaths are reported by the caller
+pub fn system_objectives(
+    world: &mut World,
+    entities: &EntityRegistry,
+    dt: f32,
+    mined_voxels: usize,
+    player_died: bool,
+) -> Vec<ObjectiveCompleted> {
+    let player_position = entities.position(world, PLAYER);
+    let mut completed = Vec::new();
+    for (_entity, tracker) in world.query_mut::<&mut ObjectiveTracker>() {
+        // Plan borrowed separately from the progress
+        let Some(objective) = tracker.plan.objectives.get(tracker.current) else {
+            continue;
+        };
+        let done = match objective.kind {
+            ObjectiveKind::ReachLocation { position, radius } => player_position
+                .is_some_and(|player| player.distance_squared(position) <= radius * radius),
+            ObjectiveKind::MineBlocks { count } => {
+                tracker.progress += mined_voxels as f32;
+                tracker.progress >= count as f32
+            }
+            ObjectiveKind::Survive { seconds } => {
+                tracker.progress = match player_died {
+                    true => 0.0,
+                    false => tracker.progress + dt,
+                };
+                tracker.progress >= seconds
+            }
+        };
+        if !done {
+            continue;
+        }
+        info!("Objective completed: {}", objective.description);
+        completed.push(ObjectiveCompleted {
+            index: tracker.current,
+            reward: objective.reward,
+        });
+        tracker.current += 1;
+        tracker.progress = 0.0;
+    }
+    completed
+}
+
+/// Shown at the top center of the view given as x, y, width & height. Window names have to be
+/// unique per view
+pub fn render_objective_hud(world: &World, window: &str, view: [f32; 4], ui: &imgui::Ui) {
+    let [x, y, width, _height] = view;
+    for (_entity, tracker) in world.query::<&ObjectiveTracker>().iter() {
+        ui.window(window)
+            .position([x + width / 2.0, y + 10.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.0])
+            .always_auto_resize(true)
+            .no_decoration()
+            .no_inputs()
+            .build(|| {
+                let Some(objective) = tracker.active() else {
+                    ui.text("All objectives completed");
+                    return;
+                };
+                ui.text(&objective.description);
+                match objective.kind {
+                    ObjectiveKind::ReachLocation { .. } => {}
+                    ObjectiveKind::MineBlocks { count } => {
+                        ui.text(format!("{:.0} / {count}", tracker.progress));
+                    }
+                    ObjectiveKind::Survive { seconds } => {
+                        ui.text(format!("{:.0}s left", seconds - tracker.progress));
+                    }
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use crate::{
+        systems::{names::Name, physics::Transform},
+        voxie::player::Player,
+    };
+
+    use super::*;
+
+    fn objective(kind: ObjectiveKind, reward: Option<ObjectiveReward>) -> Objective {
+        Objective {
+            description: String::new(),
+            kind,
+            reward,
+        }
+    }
+
+    fn test_plan() -> ObjectivePlan {
+        ObjectivePlan {
+            objectives: vec![
+                objective(
+                    ObjectiveKind::MineBlocks { count: 10 },
+                    Some(ObjectiveReward::RestoreHealth),
+                ),
+                objective(ObjectiveKind::Survive { seconds: 2.0 }, None),
+                objective(
+                    ObjectiveKind::ReachLocation {
+                        position: Vec3::X * 10.0,
+                        radius: 1.0,
+                    },
+                    None,
+                ),
+            ],
+        }
+    }
+
+    fn current(world: &World) -> usize {
+        world
+            .query::<&ObjectiveTracker>()
+            .iter()
+            .next()
+            .unwrap()
+            .1
+            .current
+    }
+
+    #[test]
+    fn test_objectives_complete_in_order() {
+        let mut world = World::new();
+        let player = world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        spawn_objective_tracker(&mut world, test_plan());
+        let entities = EntityRegistry::from_world(&world);
+
+        assert!(system_objectives(&mut world, &entities, 0.1, 6, false).is_empty());
+        assert_eq!(
+            system_objectives(&mut world, &entities, 0.1, 6, false),
+            vec![ObjectiveCompleted {
+                index: 0,
+                reward: Some(ObjectiveReward::RestoreHealth),
+            }]
+        );
+
+        // Dying restarts the survival timer
+        system_objectives(&mut world, &entities, 1.5, 0, false);
+        system_objectives(&mut world, &entities, 0.1, 0, true);
+        system_objectives(&mut world, &entities, 1.5, 0, false);
+        assert_eq!(current(&world), 1);
+        assert_eq!(
+            system_objectives(&mut world, &entities, 0.5, 0, false).len(),
+            1
+        );
+
+        system_objectives(&mut world, &entities, 0.1, 0, false);
+        assert_eq!(current(&world), 2);
+        world
+            .insert_one(player, Transform(Mat4::from_translation(Vec3::X * 9.5)))
+            .unwrap();
+        assert_eq!(
+            system_objectives(&mut world, &entities, 0.1, 0, false).len(),
+            1
+        );
+        // Plan finished
+        assert!(system_objectives(&mut world, &entities, 0.1, 100, false).is_empty());
+        assert_eq!(current(&world), 3);
+    }
+
+    #[test]
+    fn test_plan_from_json() {
+        let json = serde_json::to_string(&test_plan()).unwrap();
+        let plan: ObjectivePlan = serde_json::from_str(&json).unwrap();
+        assert_eq!(plan.objectives.len(), 3);
+        assert_eq!(
+            plan.objectives[2].kind,
+            ObjectiveKind::ReachLocation {
+                position: Vec3::X * 10.0,
+                radius: 1.0,
+            }
+        );
+
+        let shipped = ObjectivePlan::load(Path::new(DEFAULT_OBJECTIVES_PATH)).unwrap();
+        assert_eq!(
+            shipped.objectives.len(),
+            ObjectivePlan::default().objectives.len()
+        );
+    }
+}
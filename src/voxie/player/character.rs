@@ -0,0 +1,164 @@
+use glam::{Mat4, Quat, Vec3};
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    renderer::ecs_renderer::Hidden,
+    systems::physics::{LocalTransform, Parent, Transform, Velocity},
+};
+
+use super::{MousePanConfig, Player, PlayerMovement};
+
+/// Walk cycles per second at full speed. Each cycle has two steps
+const BOB_FREQUENCY: f32 = 1.5;
+/// Vertical offset at the peak of a step at full speed
+const BOB_HEIGHT: f32 = 0.12;
+
+/// Visible model of the player. Only follows the yaw of the player, not the pitch, bobs while
+/// moving & is hidden in first person
+#[derive(Serialize, Deserialize, Default)]
+pub struct CharacterModel {
+    #[serde(skip)]
+    walk_phase: f32,
+}
+
+// Player root the entity belongs to & its transform relative to the root, following the parent
+// chain
+fn relative_to_root(world: &World, entity: Entity) -> Option<(Entity, Mat4)> {
+    let mut relative = Mat4::IDENTITY;
+    let mut current = entity;
+    while !world.satisfies::<&Player>(current).unwrap_or(false) {
+        relative = world.get::<&LocalTransform>(current).ok()?.local * relative;
+        current = world.get::<&Parent>(current).ok()?.0;
+    }
+    Some((current, relative))
+}
+
+/// Model entities of the given player. Splitscreen views skip their own player in first person,
+/// while the other players stay visible
+pub fn character_models(world: &World, player: Entity) -> Vec<Entity> {
+    world
+        .query::<&CharacterModel>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .filter(|&entity| relative_to_root(world, entity).is_some_and(|(root, _)| root == player))
+        .collect()
+}
+
+/// Orients & bobs character models. Has to run after the world transforms were updated, as it
+/// overrides the transform inherited from the player. Bob height is scaled by bob_scale
+pub fn system_character_model(world: &mut World, dt: f32, first_person: bool, bob_scale: f32) {
+    let roots: Vec<(Entity, Mat4, f32, f32)> = world
+        .query::<(
+            &Player,
+            &Transform,
+            &Velocity,
+            &MousePanConfig,
+            &PlayerMovement,
+        )>()
+        .iter()
+        .map(
+            |(entity, (_player, transform, velocity, mouse, movement))| {
+                let horizontal_speed = (velocity.0 * Vec3::new(1.0, 0.0, 1.0)).length();
+                let speed_ratio = (horizontal_speed / movement.speed).clamp(0.0, 1.0);
+                (entity, transform.0, speed_ratio, mouse.yaw)
+            },
+        )
+        .collect();
+
+    let models: Vec<Entity> = world
+        .query::<&CharacterModel>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in models {
+        let Some((root, relative)) = relative_to_root(world, entity) else {
+            continue;
+        };
+        let Some(&(_root, root_transform, speed_ratio, yaw)) =
+            roots.iter().find(|(entity, ..)| *entity == root)
+        else {
+            continue;
+        };
+        let Ok((model, transform)) =
+            world.query_one_mut::<(&mut CharacterModel, &mut Transform)>(entity)
+        else {
+            continue;
+        };
+        model.walk_phase = (model.walk_phase
+            + dt * BOB_FREQUENCY * std::f32::consts::TAU * speed_ratio)
+            % std::f32::consts::TAU;
+        // Two steps per cycle
+        let bob = model.walk_phase.sin().abs() * BOB_HEIGHT * speed_ratio * bob_scale;
+        let position = root_transform.w_axis.truncate() + Vec3::Y * bob;
+        transform.0 =
+            Mat4::from_rotation_translation(Quat::from_rotation_y(yaw), position) * relative;
+
+        let is_hidden = world.satisfies::<&Hidden>(entity).unwrap_or(false);
+        if first_person && !is_hidden {
+            let _ = world.insert_one(entity, Hidden);
+        } else if !first_person && is_hidden {
+            let _ = world.remove_one::<Hidden>(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxie::player::spawn_player;
+
+    fn model_transform(world: &World) -> Mat4 {
+        let mut query = world.query::<(&CharacterModel, &Transform)>();
+        query.iter().next().unwrap().1.1.0
+    }
+
+    #[test]
+    fn test_model_follows_yaw_only() {
+        let mut world = World::new();
+        let player = spawn_player(&mut world, Vec3::ZERO);
+        {
+            let mut mouse = world.get::<&mut MousePanConfig>(player).unwrap();
+            mouse.yaw = 0.5;
+            mouse.pitch = 1.0;
+        }
+        let rotation = Quat::from_euler(glam::EulerRot::YXZ, 0.5, 1.0, 0.0);
+        world.get::<&mut Transform>(player).unwrap().0 =
+            Mat4::from_rotation_translation(rotation, Vec3::X);
+
+        system_character_model(&mut world, 0.1, false, 1.0);
+        let (_scale, model_rotation, translation) =
+            model_transform(&world).to_scale_rotation_translation();
+        assert!(translation.abs_diff_eq(Vec3::X, 1e-5), "{translation}");
+        assert!(model_rotation.angle_between(Quat::from_rotation_y(0.5)) < 1e-4);
+    }
+
+    #[test]
+    fn test_walk_bob_and_first_person() {
+        let mut world = World::new();
+        let player = spawn_player(&mut world, Vec3::ZERO);
+        let height = |world: &World| model_transform(world).w_axis.y;
+
+        // Standing still
+        system_character_model(&mut world, 0.1, false, 1.0);
+        assert_eq!(height(&world), 0.0);
+
+        world.get::<&mut Velocity>(player).unwrap().0 = Vec3::X * 15.0;
+        system_character_model(&mut world, 0.1, false, 1.0);
+        assert!(height(&world) > 0.0);
+        assert!(height(&world) <= BOB_HEIGHT);
+        // Reduced motion
+        system_character_model(&mut world, 0.1, false, 0.0);
+        assert_eq!(height(&world), 0.0);
+        // Vertical movement, e.g. flying up, does not bob
+        world.get::<&mut Velocity>(player).unwrap().0 = Vec3::Y * 15.0;
+        system_character_model(&mut world, 0.1, false, 1.0);
+        assert_eq!(height(&world), 0.0);
+
+        let hidden = |world: &World| world.query::<(&CharacterModel, &Hidden)>().iter().count();
+        system_character_model(&mut world, 0.1, true, 1.0);
+        assert_eq!(hidden(&world), 1);
+        system_character_model(&mut world, 0.1, false, 1.0);
+        assert_eq!(hidden(&world), 0);
+    }
+}
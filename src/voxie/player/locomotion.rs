@@ -0,0 +1,310 @@
+use glam::{IVec3, Vec3};
+use hecs::{Entity, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    octree::{AABB, IAabb},
+    systems::{
+        effects::spawn_dust_puff,
+        names::{EntityRegistry, PLAYER},
+        physics::Velocity,
+    },
+    voxels::{VoxelKind, VoxelWorld},
+    voxie::sound::loudest_occlusion,
+};
+
+use super::{PlayerMovement, collider_bounds, player_collider};
+
+/// Horizontal speed above which the player counts as walking
+const WALK_SPEED: f32 = 1.0;
+/// Distance walked per footstep
+const STRIDE_LENGTH: f32 = 2.5;
+/// How far below the collider ground is detected
+const GROUND_PROBE_DISTANCE: f32 = 0.1;
+/// Slower falls end without a landing
+const MIN_LANDING_SPEED: f32 = 3.0;
+const LANDING_DURATION: f32 = 0.3;
+/// Camera dip per unit of impact speed
+const CAMERA_DIP_PER_SPEED: f32 = 0.04;
+const MAX_CAMERA_DIP: f32 = 0.8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum MovementState {
+    #[default]
+    Idle,
+    Walking,
+    Airborne,
+    /// Recovering from a fall. Footsteps resume afterwards
+    Landing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MovementEvent {
+    Footstep {
+        position: Vec3,
+    },
+    Landed {
+        entity: Entity,
+        position: Vec3,
+        impact_speed: f32,
+    },
+}
+
+/// Movement state of the player, derived from velocity & ground contact every tick
+#[derive(Serialize, Deserialize, Default)]
+pub struct Locomotion {
+    pub state: MovementState,
+    // Distance walked since the last footstep
+    #[serde(skip)]
+    stride: f32,
+    // Fastest downward speed of the current fall
+    #[serde(skip)]
+    fall_speed: f32,
+    #[serde(skip)]
+    impact_speed: f32,
+    // Seconds until the landing is over
+    #[serde(skip)]
+    landing_timer: f32,
+}
+
+impl Locomotion {
+    /// Advances the state machine of the entity. Feet is the bottom of the collider, where events
+    /// are placed
+    pub fn update(
+        &mut self,
+        entity: Entity,
+        grounded: bool,
+        velocity: Vec3,
+        feet: Vec3,
+        dt: f32,
+    ) -> Option<MovementEvent> {
+        if !grounded {
+            if self.state != MovementState::Airborne {
+                self.fall_speed = 0.0;
+            }
+            self.state = MovementState::Airborne;
+            self.fall_speed = self.fall_speed.max(-velocity.y);
+            return None;
+        }
+        match self.state {
+            MovementState::Airborne if self.fall_speed >= MIN_LANDING_SPEED => {
+                self.state = MovementState::Landing;
+                self.impact_speed = self.fall_speed;
+                self.landing_timer = LANDING_DURATION;
+                self.stride = 0.0;
+                return Some(MovementEvent::Landed {
+                    entity,
+                    position: feet,
+                    impact_speed: self.impact_speed,
+                });
+            }
+            MovementState::Landing => {
+                self.landing_timer -= dt;
+                if self.landing_timer > 0.0 {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+
+        let horizontal_speed = (velocity * Vec3::new(1.0, 0.0, 1.0)).length();
+        if horizontal_speed < WALK_SPEED {
+            self.state = MovementState::Idle;
+            self.stride = 0.0;
+            return None;
+        }
+        self.state = MovementState::Walking;
+        self.stride += horizontal_speed * dt;
+        if self.stride < STRIDE_LENGTH {
+            return None;
+        }
+        self.stride -= STRIDE_LENGTH;
+        Some(MovementEvent::Footstep { position: feet })
+    }
+
+    /// Downward camera offset while landing: Dips proportional to the impact speed & eases back
+    pub fn camera_dip(&self) -> f32 {
+        if self.state != MovementState::Landing {
+            return 0.0;
+        }
+        let depth = (self.impact_speed * CAMERA_DIP_PER_SPEED).min(MAX_CAMERA_DIP);
+        let progress = 1.0 - (self.landing_timer / LANDING_DURATION).clamp(0.0, 1.0);
+        depth * (progress * std::f32::consts::PI).sin()
+    }
+}
+
+/// Checks for solid voxels & kinematic bodies right below the bounds
+fn is_grounded(bounds: &AABB, voxel_world: &VoxelWorld, kinematic_bodies: &[(AABB, Vec3)]) -> bool {
+    let probe = AABB::new(
+        bounds.min - Vec3::Y * GROUND_PROBE_DISTANCE,
+        Vec3::new(bounds.max.x, bounds.min.y, bounds.max.z),
+    );
+    if kinematic_bodies
+        .iter()
+        .any(|(aabb, _velocity)| aabb.intersects(&probe))
+    {
+        return true;
+    }
+    let min = probe.min.floor().as_ivec3();
+    let max = probe.max.ceil().as_ivec3().max(min + IVec3::ONE);
+    voxel_world
+        .iter_region_voxels(IAabb::new_rect(min, max))
+        .any(|voxel| {
+            !matches!(voxel.kind, VoxelKind::Air)
+                && AABB::new_center(&voxel.position, 1.0).intersects(&probe)
+        })
+}
+
+/// Updates the movement state of the player & returns footsteps & landings of this tick.
+/// Has to run after player movement
+pub fn system_locomotion(
+    world: &mut World,
+    entities: &EntityRegistry,
+    dt: f32,
+    voxel_world: &VoxelWorld,
+    kinematic_bodies: &[(AABB, Vec3)],
+) -> Vec<MovementEvent> {
+    let Some(player) = entities.get(world, PLAYER) else {
+        return Vec::new();
+    };
+    let Some((collider, transform)) = player_collider(world, player) else {
+        return Vec::new();
+    };
+    let bounds = collider_bounds(&collider, transform);
+    let grounded = is_grounded(&bounds, voxel_world, kinematic_bodies);
+    let feet = Vec3::new(bounds.center().x, bounds.min.y, bounds.center().z);
+
+    let Ok((locomotion, velocity, movement)) =
+        world.query_one_mut::<(&mut Locomotion, &Velocity, &PlayerMovement)>(player)
+    else {
+        return Vec::new();
+    };
+    // Riding a platform is no walking
+    let own_velocity = velocity.0 - movement.carried_velocity;
+    locomotion
+        .update(player, grounded, own_velocity, feet, dt)
+        .into_iter()
+        .collect()
+}
+
+/// Dust particles for footsteps & landings. Audio cues are only logged with their occlusion
+/// towards the least occluded listener, there is no audio backend yet
+pub fn system_movement_effects(
+    world: &mut World,
+    voxel_world: &VoxelWorld,
+    listeners: &[Vec3],
+    events: &[MovementEvent],
+) {
+    for event in events {
+        match *event {
+            MovementEvent::Footstep { position } => {
+                let occlusion = loudest_occlusion(voxel_world, position, listeners);
+                debug!("Footstep at {position}, {occlusion:?}");
+                spawn_dust_puff(world, position, 4, 1.5);
+            }
+            MovementEvent::Landed {
+                position,
+                impact_speed,
+                ..
+            } => {
+                let occlusion = loudest_occlusion(voxel_world, position, listeners);
+                debug!(
+                    "Landing thud at {position} with impact speed {impact_speed:.1}, {occlusion:?}"
+                );
+                let count = 6 + (impact_speed / 2.0) as usize;
+                spawn_dust_puff(world, position, count.min(16), 1.0 + impact_speed * 0.2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footsteps_at_stride_intervals() {
+        let mut locomotion = Locomotion::default();
+        let walk = Vec3::X * 5.0;
+        let steps: usize = (0..20)
+            .filter_map(|_| locomotion.update(Entity::DANGLING, true, walk, Vec3::ZERO, 0.1))
+            .count();
+        // 10 units walked
+        assert_eq!(steps, 4);
+        assert_eq!(locomotion.state, MovementState::Walking);
+
+        // Vertical & tiny movement is standing still
+        assert_eq!(
+            locomotion.update(Entity::DANGLING, true, Vec3::Y * 5.0, Vec3::ZERO, 0.1),
+            None
+        );
+        assert_eq!(locomotion.state, MovementState::Idle);
+    }
+
+    #[test]
+    fn test_landing_proportional_to_fall_speed() {
+        let mut locomotion = Locomotion::default();
+        locomotion.update(Entity::DANGLING, false, Vec3::NEG_Y * 4.0, Vec3::ZERO, 0.1);
+        locomotion.update(Entity::DANGLING, false, Vec3::NEG_Y * 10.0, Vec3::ZERO, 0.1);
+        assert_eq!(locomotion.state, MovementState::Airborne);
+        let event = locomotion.update(Entity::DANGLING, true, Vec3::ZERO, Vec3::Y, 0.1);
+        assert_eq!(
+            event,
+            Some(MovementEvent::Landed {
+                entity: Entity::DANGLING,
+                position: Vec3::Y,
+                impact_speed: 10.0
+            })
+        );
+        assert_eq!(locomotion.state, MovementState::Landing);
+
+        // Dips down & recovers within the landing duration
+        locomotion.update(
+            Entity::DANGLING,
+            true,
+            Vec3::ZERO,
+            Vec3::Y,
+            LANDING_DURATION / 2.0,
+        );
+        let deep_dip = locomotion.camera_dip();
+        assert!(
+            (deep_dip - 10.0 * CAMERA_DIP_PER_SPEED).abs() < 1e-5,
+            "{deep_dip}"
+        );
+        locomotion.update(
+            Entity::DANGLING,
+            true,
+            Vec3::ZERO,
+            Vec3::Y,
+            LANDING_DURATION / 2.0,
+        );
+        assert_eq!(locomotion.state, MovementState::Idle);
+        assert_eq!(locomotion.camera_dip(), 0.0);
+
+        // Slow descent touches down silently
+        locomotion.update(Entity::DANGLING, false, Vec3::NEG_Y, Vec3::ZERO, 0.1);
+        assert_eq!(
+            locomotion.update(Entity::DANGLING, true, Vec3::ZERO, Vec3::ZERO, 0.1),
+            None
+        );
+        assert_eq!(locomotion.state, MovementState::Idle);
+    }
+
+    #[test]
+    fn test_ground_check() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        // Top face of the world is at y = 15.5
+        let standing = AABB::new_center(&Vec3::new(4.0, 16.0, 4.0), 1.0);
+        assert!(is_grounded(&standing, &voxel_world, &[]));
+        let flying = AABB::new_center(&Vec3::new(4.0, 17.0, 4.0), 1.0);
+        assert!(!is_grounded(&flying, &voxel_world, &[]));
+
+        let platform = AABB::new(Vec3::new(3.0, 15.0, 3.0), Vec3::new(5.0, 16.5, 5.0));
+        assert!(is_grounded(
+            &flying,
+            &voxel_world,
+            &[(platform, Vec3::ZERO)]
+        ));
+    }
+}
@@ -3,18 +3,28 @@ use std::ops::Deref;
 use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
 use hecs::World;
 use log::{debug, error};
+use serde::{Deserialize, Serialize};
 use winit::keyboard::KeyCode;
 
 use crate::{
-    collision::ColliderBody,
+    cameras::camera::{CameraTarget, MAX_PITCH, wrap_angle, yaw_pitch_rotation},
+    collision::{
+        ColliderBody, CollisionInfo,
+        capsule::{Capsule, capsule_cast},
+        get_aabb_aabb_collision_info,
+        sphere::sphere_cast,
+    },
     input::InputState,
+    octree::AABB,
     renderer::{
         RenderMeshHandle,
-        ecs_renderer::{MESH_PLAYER, RenderColor},
+        ecs_renderer::{MESH_CHARACTER, RenderColor},
     },
     systems::{
         gun::Gun,
         physics::{LocalTransform, Parent, hierarchy_cache::find_descendants},
+        projectiles::grenade::GrenadeThrower,
+        serialization::ComponentRegistry,
     },
     voxels::{VoxelCollider, VoxelWorld},
 };
@@ -22,21 +32,117 @@ use crate::{
 use crate::systems::physics::Transform;
 use crate::systems::physics::Velocity;
 
+use character::CharacterModel;
+use locomotion::Locomotion;
+
+use super::health::Health;
+use stance::{CrouchCollider, Stamina, Stance, crouch_camera_drop, update_stance};
+
+pub mod character;
+pub mod locomotion;
 pub mod squid;
+pub mod stance;
 
+#[derive(Serialize, Deserialize)]
 pub struct Player;
+
+/// Input devices controlling a local player. Players without a mapping use keyboard & mouse
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputMapping {
+    /// W/S to move, mouse to look, left shift to sprint, left ctrl to crouch, left click to fire
+    #[default]
+    KeyboardMouse,
+    /// Up/down to move, left/right to turn, right shift to sprint, right ctrl to crouch, enter to
+    /// fire. Lets a second local player share the keyboard
+    ArrowKeys,
+}
+
+struct KeyBindings {
+    forward: KeyCode,
+    backward: KeyCode,
+    sprint: KeyCode,
+    crouch: KeyCode,
+    /// Held to aim a grenade, released to throw it
+    throw: KeyCode,
+    // Mouse look if None
+    turn: Option<(KeyCode, KeyCode)>,
+}
+
+impl InputMapping {
+    fn bindings(&self) -> KeyBindings {
+        match self {
+            InputMapping::KeyboardMouse => KeyBindings {
+                forward: KeyCode::KeyW,
+                backward: KeyCode::KeyS,
+                sprint: KeyCode::ShiftLeft,
+                crouch: KeyCode::ControlLeft,
+                throw: KeyCode::KeyG,
+                turn: None,
+            },
+            InputMapping::ArrowKeys => KeyBindings {
+                forward: KeyCode::ArrowUp,
+                backward: KeyCode::ArrowDown,
+                sprint: KeyCode::ShiftRight,
+                crouch: KeyCode::ControlRight,
+                throw: KeyCode::Slash,
+                turn: Some((KeyCode::ArrowLeft, KeyCode::ArrowRight)),
+            },
+        }
+    }
+
+    fn fire_pressed(&self, input: &InputState) -> bool {
+        match self {
+            InputMapping::KeyboardMouse => {
+                input.is_mouse_button_pressed(&winit::event::MouseButton::Left)
+            }
+            InputMapping::ArrowKeys => input.is_key_pressed(&KeyCode::Enter),
+        }
+    }
+}
+
+// Radians per second while a turn key is held
+const KEYBOARD_TURN_SPEED: f32 = 2.5;
+#[derive(Serialize, Deserialize)]
 struct MousePanConfig {
     pub sensitivity: f32,
+    // Not persisted to avoid a jump on the first mouse movement after loading
+    #[serde(skip)]
     pub last_mouse_position: (f32, f32),
     pub yaw: f32,
     pub pitch: f32,
 }
+#[derive(Serialize, Deserialize)]
 struct PlayerMovement {
     // Max absolute velocity
     pub speed: f32,
     // Flat acceleration applied until max speed is reached
     pub acceleration: f32,
     pub input_velocity: Vec3,
+    // Share of the velocity caused by kinematic bodies. Not smoothed by acceleration
+    #[serde(skip)]
+    pub carried_velocity: Vec3,
+    // Requested by input, resolved against stamina during player movement
+    #[serde(skip)]
+    pub sprint_requested: bool,
+    #[serde(skip)]
+    pub crouch_requested: bool,
+    #[serde(skip)]
+    pub stance: Stance,
+    // 0 while standing, 1 when fully crouched
+    #[serde(skip)]
+    pub crouch_blend: f32,
+}
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Player>("Player");
+    registry.register::<InputMapping>("InputMapping");
+    registry.register::<MousePanConfig>("MousePanConfig");
+    registry.register::<PlayerMovement>("PlayerMovement");
+    registry.register::<CharacterModel>("CharacterModel");
+    registry.register::<Locomotion>("Locomotion");
+    registry.register::<Stamina>("Stamina");
+    registry.register::<CrouchCollider>("CrouchCollider");
+    squid::register_components(registry);
 }
 
 pub fn spawn_player(world: &mut hecs::World, position: Vec3) -> hecs::Entity {
@@ -60,50 +166,111 @@ pub fn spawn_player(world: &mut hecs::World, position: Vec3) -> hecs::Entity {
             speed: 15.0,
             acceleration: 5.0,
             input_velocity: Vec3::ZERO,
+            carried_velocity: Vec3::ZERO,
+            sprint_requested: false,
+            crouch_requested: false,
+            stance: Stance::Standing,
+            crouch_blend: 0.0,
         },
+        Locomotion::default(),
+        Health::new(100.0),
         Gun {
             cooldown: 0.0,
             fire_rate: 2.5,
             triggered: false,
+            spread: 0.01,
         },
+        GrenadeThrower::default(),
     ));
 
-    // Mesh entity: child of root, static 180° Y rotation
+    // Mesh entity: child of root
     world.spawn((
         LocalTransform {
-            local: Mat4::from_rotation_y(std::f32::consts::PI),
+            local: Mat4::IDENTITY,
         },
-        Transform(Mat4::from_rotation_y(std::f32::consts::PI)),
-        RenderMeshHandle(MESH_PLAYER),
+        Transform(Mat4::IDENTITY),
+        RenderMeshHandle(MESH_CHARACTER),
         RenderColor(Vec3::splat(0.85)),
+        CharacterModel::default(),
         Parent(root),
     ));
 
     root
 }
 
+/// Moves the player & drops all momentum, so the teleport does not count as a fall.
+/// Returns the new position of the bottom of the player collider
+pub fn teleport_player(world: &mut World, player: hecs::Entity, position: Vec3) -> Option<Vec3> {
+    // Collider transforms are only updated by the hierarchy system, so keep the current offset
+    let feet_offset = match player_collider(world, player) {
+        Some((body, collider_transform)) => {
+            let bounds = collider_bounds(&body, collider_transform);
+            let root = world.get::<&Transform>(player).ok()?.0.w_axis.truncate();
+            Vec3::Y * (bounds.min.y - root.y)
+        }
+        None => Vec3::ZERO,
+    };
+    let Ok((transform, velocity, movement, locomotion)) = world.query_one_mut::<(
+        &mut Transform,
+        &mut Velocity,
+        &mut PlayerMovement,
+        &mut Locomotion,
+    )>(player) else {
+        error!("Unable to teleport: {player:?} is not a player entity");
+        return None;
+    };
+    transform.0.w_axis = position.extend(1.0);
+    velocity.0 = Vec3::ZERO;
+    movement.carried_velocity = Vec3::ZERO;
+    *locomotion = Locomotion::default();
+    Some(position + feet_offset)
+}
+
+/// All local player entities. Sorted by id, so views keep following the same player
+pub fn local_players(world: &World) -> Vec<hecs::Entity> {
+    let mut players: Vec<hecs::Entity> = world
+        .query::<&Player>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    players.sort_by_key(|entity| entity.id());
+    players
+}
+
 pub fn system_player_mouse_control(world: &mut World, input: &InputState) {
-    for (_entity, (transform, mouse_pan)) in
-        world.query_mut::<(&mut Transform, &mut MousePanConfig)>()
+    for (_entity, (transform, mouse_pan, mapping)) in
+        world.query_mut::<(&mut Transform, &mut MousePanConfig, Option<&InputMapping>)>()
     {
+        if mapping.is_some_and(|mapping| *mapping != InputMapping::KeyboardMouse) {
+            continue;
+        }
         let current_mouse_position = input.get_mouse_position_f32();
         let dx = mouse_pan.last_mouse_position.0 - current_mouse_position.0;
         let dy = mouse_pan.last_mouse_position.1 - current_mouse_position.1;
         mouse_pan.last_mouse_position = current_mouse_position;
 
-        // Update yaw and pitch
-        mouse_pan.yaw -= dx * mouse_pan.sensitivity;
-        mouse_pan.pitch -= dy * mouse_pan.sensitivity;
+        // Update yaw and pitch. Wrapped & clamped to prevent flipping
+        mouse_pan.yaw = wrap_angle(mouse_pan.yaw - dx * mouse_pan.sensitivity);
+        mouse_pan.pitch =
+            (mouse_pan.pitch - dy * mouse_pan.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
 
-        // Clamp pitch to [-89°, 89°] to prevent flipping
-        let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01; // ~89.4°
-        mouse_pan.pitch = mouse_pan.pitch.clamp(-pitch_limit, pitch_limit);
-
-        let rotation = Quat::from_euler(glam::EulerRot::YXZ, mouse_pan.yaw, mouse_pan.pitch, 0.0);
+        let rotation = yaw_pitch_rotation(mouse_pan.yaw, mouse_pan.pitch);
         transform.0 = override_rotation(transform.0, rotation);
     }
 }
 
+/// Pose the camera follows. Uses the stored yaw & pitch instead of the player transform, which
+/// may carry scale or numeric drift. Drop lowers the camera, e.g. while crouching
+pub fn camera_target(world: &World, player: hecs::Entity, drop: f32) -> Option<CameraTarget> {
+    let transform = world.get::<&Transform>(player).ok()?;
+    let mouse_pan = world.get::<&MousePanConfig>(player).ok()?;
+    Some(CameraTarget {
+        position: transform.0.w_axis.truncate() + Vec3::NEG_Y * drop,
+        yaw: mouse_pan.yaw,
+        pitch: mouse_pan.pitch,
+    })
+}
+
 fn override_rotation(mat: Mat4, rotation: Quat) -> Mat4 {
     let translation = mat.w_axis.truncate(); // extract translation
     let scale = Vec3::new(
@@ -116,94 +283,165 @@ fn override_rotation(mat: Mat4, rotation: Quat) -> Mat4 {
     Mat4::from_scale_rotation_translation(scale, rotation, translation)
 }
 
-pub fn render_player_ui(world: &mut World, ui: &mut imgui::Ui) {
-    for (_entity, (transform, velocity, mouse, movement)) in world.query_mut::<(
+/// Player stats window, placed at the top right of the view given as x, y, width & height
+pub fn render_player_ui(
+    world: &mut World,
+    player: hecs::Entity,
+    title: &str,
+    view: [f32; 4],
+    ui: &mut imgui::Ui,
+) {
+    let [x, y, width, _height] = view;
+    if let Ok((transform, velocity, mouse, movement, stamina, health)) = world.query_one_mut::<(
         &Transform,
         &Velocity,
         &mut MousePanConfig,
         &mut PlayerMovement,
-    )>() {
-        ui.window("Player")
+        Option<&Stamina>,
+        Option<&Health>,
+    )>(player)
+    {
+        ui.window(title)
             .size([300.0, 150.0], imgui::Condition::FirstUseEver)
-            .position([600.0, 0.0], imgui::Condition::FirstUseEver)
+            .position([x + width - 300.0, y], imgui::Condition::FirstUseEver)
             .build(|| {
                 ui.text(format!("Position: {:.2}", transform.0.w_axis.xyz()));
                 ui.text(format!("Velocity: {:.2}", velocity.0));
+                ui.text(format!("Stance: {:?}", movement.stance));
+                if let Some(health) = health {
+                    ui.text(format!("Health: {:.0} / {:.0}", health.current, health.max));
+                }
+                if let Some(stamina) = stamina {
+                    ui.text(format!(
+                        "Stamina: {:.1} / {:.1}",
+                        stamina.current, stamina.max
+                    ));
+                }
                 ui.slider("Player speed", 5.0, 50.0, &mut movement.speed);
                 ui.slider("Mouse sensitivity", 0.001, 0.003, &mut mouse.sensitivity);
             });
     }
 }
 
-/// Parse keyboard inputs and update affected systems
-pub fn system_player_keyboard_control(world: &mut World, input: &InputState) {
-    for (_entity, (transform, movement, gun)) in
-        world.query_mut::<(&Transform, &mut PlayerMovement, &mut Gun)>()
-    {
+/// Downward camera offset of the player from crouching & landing. The landing dip is scaled by
+/// shake_scale, crouching is not, as it moves the eye height
+pub fn camera_drop(world: &World, player: hecs::Entity, shake_scale: f32) -> f32 {
+    let crouch = world
+        .get::<&PlayerMovement>(player)
+        .map_or(0.0, |movement| crouch_camera_drop(&movement));
+    let dip = world
+        .get::<&Locomotion>(player)
+        .map_or(0.0, |locomotion| locomotion.camera_dip());
+    crouch + dip * shake_scale
+}
+
+/// Parse keyboard inputs and update affected systems. Each player reads the keys of its mapping
+pub fn system_player_keyboard_control(world: &mut World, input: &InputState, dt: f32) {
+    for (_entity, (transform, mouse_pan, movement, gun, thrower, mapping)) in world.query_mut::<(
+        &mut Transform,
+        &mut MousePanConfig,
+        &mut PlayerMovement,
+        &mut Gun,
+        Option<&mut GrenadeThrower>,
+        Option<&InputMapping>,
+    )>() {
+        let mapping = mapping.copied().unwrap_or_default();
+        let bindings = mapping.bindings();
+        if let Some((left, right)) = bindings.turn {
+            let mut turn = 0.0;
+            if input.is_key_pressed(&left) {
+                turn += 1.0;
+            }
+            if input.is_key_pressed(&right) {
+                turn -= 1.0;
+            }
+            mouse_pan.yaw = wrap_angle(mouse_pan.yaw + turn * KEYBOARD_TURN_SPEED * dt);
+            let rotation = yaw_pitch_rotation(mouse_pan.yaw, mouse_pan.pitch);
+            transform.0 = override_rotation(transform.0, rotation);
+        }
+
         // Parse inputs
         let mut input_velocity = Vec3::ZERO;
         let forward = (-transform.0.z_axis.xyz()).normalize();
-        if input.is_key_pressed(&KeyCode::KeyW) {
+        if input.is_key_pressed(&bindings.forward) {
             input_velocity += forward;
         }
-        if input.is_key_pressed(&KeyCode::KeyS) {
+        if input.is_key_pressed(&bindings.backward) {
             input_velocity -= forward;
         }
-        if input.is_mouse_button_pressed(&winit::event::MouseButton::Left) {
+        movement.sprint_requested = input.is_key_pressed(&bindings.sprint);
+        movement.crouch_requested = input.is_key_pressed(&bindings.crouch);
+        if mapping.fire_pressed(input) {
             debug!("Gun fire requested");
             gun.triggered = true;
         }
+        if let Some(thrower) = thrower {
+            thrower.held = input.is_key_pressed(&bindings.throw);
+        }
         movement.input_velocity = input_velocity;
     }
 }
 
 /// Calculate player velocity based on requested valocity and collide_and_slide algorithm
-/// Integration of velocity is done in general movement system
-pub fn system_player_movement(world: &mut World, dt: f32, voxel_world: &VoxelWorld) {
-    // Retrieve player entity
-    let player_entity = world
-        .query::<&Player>()
+/// Integration of velocity is done in general movement system. Kinematic bodies block movement &
+/// push or carry the player
+pub fn system_player_movement(
+    world: &mut World,
+    dt: f32,
+    voxel_world: &VoxelWorld,
+    kinematic_bodies: &[(AABB, Vec3)],
+) {
+    let obstacles: Vec<AABB> = kinematic_bodies
         .iter()
-        .map(|(entity, _)| entity)
-        .next();
-    if player_entity.is_none() {
-        error!("Unable to process player movement: Could not find player entity");
-        return;
-    }
-    let player_entity = player_entity.unwrap();
+        .map(|(aabb, _velocity)| aabb.clone())
+        .collect();
+    for player_entity in local_players(world) {
+        // Retrieve player collider information
+        let Some((collider_body, collider_transform)) = player_collider(world, player_entity)
+        else {
+            error!("Unable to retrieve collider info of {player_entity:?}");
+            continue;
+        };
+        let carried_velocity = kinematic_contact_velocity(
+            &collider_bounds(&collider_body, collider_transform),
+            kinematic_bodies,
+            dt,
+        );
+        let Ok((velocity, movement, stamina)) =
+            world.query_one_mut::<(&mut Velocity, &mut PlayerMovement, Option<&mut Stamina>)>(
+                player_entity,
+            )
+        else {
+            continue;
+        };
+        let is_moving = movement.input_velocity.length_squared() > 1e-4;
+        update_stance(movement, stamina, is_moving, dt);
 
-    // Retrieve player collider information
-    let descendant_entities = find_descendants::<(&ColliderBody, &Transform)>(world, player_entity);
-    let collider_info: Option<(ColliderBody, Mat4)> = descendant_entities
-        .iter()
-        .filter_map(|&entity| {
-            match (
-                world.get::<&ColliderBody>(entity),
-                world.get::<&Transform>(entity),
-            ) {
-                (Ok(collider), Ok(transform)) => Some((collider.deref().clone(), transform.0)),
-                _ => None,
-            }
-        })
-        .next();
-    if collider_info.is_none() {
-        error!("Unable to retrieve collider info");
-        return;
-    }
-    let (collider_body, collider_transform) = collider_info.unwrap();
+        // Carried velocity is not blocked by the carrying body itself
+        let mut carried = Vec3::ZERO;
+        if carried_velocity.length_squared() > 1e-8 {
+            carried = collide_and_slide(
+                carried_velocity * dt,
+                collider_transform,
+                0,
+                voxel_world,
+                &collider_body,
+                &[],
+            ) / dt;
+        }
 
-    for (_entity, (velocity, movement)) in world.query_mut::<(&mut Velocity, &mut PlayerMovement)>()
-    {
         // Figure out target velocity based on collide and slide algorithm with collider body & transform
         let mut target_velocity = Vec3::ZERO;
-        if movement.input_velocity.length_squared() > 1e-4 {
-            let requested_velocity = movement.input_velocity * movement.speed * dt;
+        if is_moving {
+            let speed = movement.speed * movement.stance.speed_factor();
+            let requested_velocity = movement.input_velocity * speed * dt;
             let collision_adjusted_velocity = collide_and_slide(
                 requested_velocity,
                 collider_transform,
                 0,
                 voxel_world,
                 &collider_body,
+                &obstacles,
             );
             // * dt will be applied again in movement system
             target_velocity = collision_adjusted_velocity / dt;
@@ -211,8 +449,76 @@ pub fn system_player_movement(world: &mut World, dt: f32, voxel_world: &VoxelWor
 
         // Apply acceleration towards target velocity
         // NOTE: This is not physical acceleration by integration, just a simplification
-        let velocity_diff = target_velocity - velocity.0;
-        velocity.0 += velocity_diff * movement.acceleration * dt;
+        let mut own_velocity = velocity.0 - movement.carried_velocity;
+        own_velocity += (target_velocity - own_velocity) * movement.acceleration * dt;
+        velocity.0 = own_velocity + carried;
+        movement.carried_velocity = carried;
+    }
+}
+
+/// Collider body & world transform of the first collider below the player entity
+fn player_collider(world: &World, player: hecs::Entity) -> Option<(ColliderBody, Mat4)> {
+    let descendant_entities = find_descendants::<(&ColliderBody, &Transform)>(world, player);
+    descendant_entities
+        .iter()
+        .filter_map(|&entity| {
+            match (
+                world.get::<&ColliderBody>(entity),
+                world.get::<&Transform>(entity),
+            ) {
+                (Ok(collider), Ok(transform)) => Some((collider.deref().clone(), transform.0)),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+// Player touches kinematic bodies within this distance
+const CONTACT_MARGIN: f32 = 0.05;
+
+/// Velocity transferred by kinematic bodies: Carried along while standing on top, pushed while a
+/// body moves into the player. Also resolves overlaps caused by bodies moving into the player
+fn kinematic_contact_velocity(bounds: &AABB, bodies: &[(AABB, Vec3)], dt: f32) -> Vec3 {
+    let mut velocity = Vec3::ZERO;
+    for (aabb, body_velocity) in bodies {
+        // Normal points from the body towards the player
+        let Some(contact) = get_aabb_aabb_collision_info(bounds, &aabb.inflate(CONTACT_MARGIN))
+        else {
+            continue;
+        };
+        if contact.normal.y > 0.5 {
+            velocity += *body_velocity;
+        } else {
+            velocity += contact.normal * body_velocity.dot(contact.normal).max(0.0);
+        }
+        let overlap = contact.penetration_depth - CONTACT_MARGIN;
+        if overlap > 0.0 {
+            velocity += contact.normal * overlap / dt;
+        }
+    }
+    velocity
+}
+
+fn collider_bounds(collider: &ColliderBody, transform: Mat4) -> AABB {
+    let center = transform.w_axis.xyz();
+    match collider {
+        ColliderBody::SphereCollider { radius } => AABB::new_center(&center, radius * 2.0),
+        ColliderBody::AabbCollider { scale } => AABB::from_center_and_scale(&center, scale),
+        ColliderBody::CapsuleCollider { radius, height } => {
+            let capsule = Capsule::from_transform(transform, *radius, *height);
+            AABB::new(
+                capsule.endpoint_a.min(capsule.endpoint_b) - Vec3::splat(*radius),
+                capsule.endpoint_a.max(capsule.endpoint_b) + Vec3::splat(*radius),
+            )
+        }
+    }
+}
+
+fn closest_hit(a: Option<CollisionInfo>, b: Option<CollisionInfo>) -> Option<CollisionInfo> {
+    match (a, b) {
+        (Some(a), Some(b)) if b.penetration_depth < a.penetration_depth => Some(b),
+        (Some(a), _) => Some(a),
+        (None, b) => b,
     }
 }
 
@@ -227,6 +533,7 @@ fn collide_and_slide(
     depth: u32,
     voxel_world: &VoxelWorld,
     collider: &ColliderBody,
+    obstacles: &[AABB],
 ) -> Vec3 {
     if depth >= MAX_COLLIDE_BOUNCES {
         return Vec3::ZERO;
@@ -238,16 +545,31 @@ fn collide_and_slide(
     let collision_test = match collider {
         ColliderBody::SphereCollider { radius } => {
             let pos = transform.w_axis.xyz();
-            voxel_world.query_sphere_cast(pos, radius - SKIN_WIDTH, vel_normalized, dist)
+            closest_hit(
+                voxel_world.query_sphere_cast(pos, radius - SKIN_WIDTH, vel_normalized, dist),
+                sphere_cast(
+                    pos,
+                    radius - SKIN_WIDTH,
+                    vel_normalized,
+                    dist,
+                    obstacles.iter().cloned(),
+                ),
+            )
         }
         ColliderBody::AabbCollider { .. } => {
             todo!(
                 "Missing implementation: Voxel world collide and slide with aabb collider character controller"
             )
         }
-        ColliderBody::CapsuleCollider { radius, height } => {
-            voxel_world.query_capsule_cast(transform, *radius, *height, vel_normalized, dist)
-        }
+        ColliderBody::CapsuleCollider { radius, height } => closest_hit(
+            voxel_world.query_capsule_cast(transform, *radius, *height, vel_normalized, dist),
+            capsule_cast(
+                &Capsule::from_transform(transform, *radius, *height),
+                vel_normalized,
+                dist,
+                obstacles.iter().cloned(),
+            ),
+        ),
     };
 
     if let Some(collision) = collision_test {
@@ -269,7 +591,65 @@ fn collide_and_slide(
                 depth + 1,
                 voxel_world,
                 collider,
+                obstacles,
             );
     }
     vel
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::systems::physics::{
+        hierarchy_cache::HierarchyCache, system_update_world_transforms,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_kinematic_bodies_carry_and_push() {
+        let voxel_world = VoxelWorld::new_cubic(1);
+        let mut world = World::new();
+        let player = squid::spawn_squid(&mut world, Vec3::splat(50.0));
+        system_update_world_transforms(&mut world, &mut HierarchyCache::new());
+        let velocity = |world: &World| world.get::<&Velocity>(player).unwrap().0;
+
+        // Capsule collider reaches down to y = 49
+        let platform = AABB::new(Vec3::new(45.0, 47.0, 45.0), Vec3::new(55.0, 48.99, 55.0));
+        let platform_velocity = Vec3::new(2.0, 0.0, 0.0);
+        system_player_movement(
+            &mut world,
+            0.1,
+            &voxel_world,
+            &[(platform, platform_velocity)],
+        );
+        assert!(velocity(&world).distance(platform_velocity) < 1e-4);
+
+        // Side contact only transfers the share moving into the player
+        let wall = AABB::new(Vec3::new(40.0, 45.0, 45.0), Vec3::new(49.48, 55.0, 55.0));
+        system_player_movement(
+            &mut world,
+            0.1,
+            &voxel_world,
+            &[(wall, Vec3::new(1.0, 0.0, 3.0))],
+        );
+        assert!(velocity(&world).distance(Vec3::X) < 1e-4);
+
+        // Body moving away
+        let wall = AABB::new(Vec3::new(40.0, 45.0, 45.0), Vec3::new(49.48, 55.0, 55.0));
+        system_player_movement(&mut world, 0.1, &voxel_world, &[(wall, Vec3::NEG_X)]);
+        assert_eq!(velocity(&world), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_teleport_keeps_collider_offset() {
+        let mut world = World::new();
+        let player = squid::spawn_squid(&mut world, Vec3::splat(50.0));
+        system_update_world_transforms(&mut world, &mut HierarchyCache::new());
+        world.get::<&mut Velocity>(player).unwrap().0 = Vec3::NEG_Y * 30.0;
+
+        let feet = teleport_player(&mut world, player, Vec3::new(100.0, 80.0, 20.0)).unwrap();
+        // Capsule collider reaches 1 below the root
+        assert!(feet.distance(Vec3::new(100.0, 79.0, 20.0)) < 1e-4, "{feet}");
+        assert_eq!(world.get::<&Velocity>(player).unwrap().0, Vec3::ZERO);
+    }
+}
@@ -1,5 +1,6 @@
 use glam::{Mat4, Quat, Vec3};
 use log::error;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     collision::ColliderBody,
@@ -9,21 +10,38 @@ use crate::{
     },
     systems::{
         gun::Gun,
+        names::{Name, PLAYER, Tags},
         physics::{LocalTransform, Parent, Transform, Velocity},
+        projectiles::grenade::GrenadeThrower,
+        serialization::ComponentRegistry,
     },
     voxels::VoxelCollider,
+    voxie::health::Health,
 };
 
-use super::{MousePanConfig, Player, PlayerMovement};
+use super::{
+    MousePanConfig, Player, PlayerMovement,
+    character::CharacterModel,
+    locomotion::Locomotion,
+    stance::{CrouchCollider, Stamina, Stance},
+};
 
+#[derive(Serialize, Deserialize)]
 struct SquidPivot {
     smoothened_tilt: f32,
 }
 
+pub(super) fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<SquidPivot>("SquidPivot");
+}
+
 pub fn spawn_squid(world: &mut hecs::World, position: Vec3) -> hecs::Entity {
     // Root entity: controls movement, mouse rotation
     let root = world.spawn((
         Player,
+        // Renamed when joining as another local player
+        Name::new(PLAYER),
+        Tags::new(&[PLAYER]),
         LocalTransform {
             local: Mat4::from_translation(position),
         },
@@ -39,12 +57,22 @@ pub fn spawn_squid(world: &mut hecs::World, position: Vec3) -> hecs::Entity {
             speed: 15.0,
             acceleration: 5.0,
             input_velocity: Vec3::ZERO,
+            carried_velocity: Vec3::ZERO,
+            sprint_requested: false,
+            crouch_requested: false,
+            stance: Stance::Standing,
+            crouch_blend: 0.0,
         },
+        Locomotion::default(),
+        Health::new(100.0),
+        Stamina::new(4.0, 1.0, 0.5),
         Gun {
             cooldown: 0.0,
             fire_rate: 2.5,
             triggered: false,
+            spread: 0.01,
         },
+        GrenadeThrower::default(),
     ));
 
     let pivot = world.spawn((
@@ -121,6 +149,7 @@ fn spawn_squid_mesh(world: &mut hecs::World, root: hecs::Entity) {
         Transform(transform),
         RenderMeshHandle(MESH_SQUID),
         RenderColor(Vec3::splat(0.85)),
+        CharacterModel::default(),
         Parent(root),
     ));
 }
@@ -146,6 +175,10 @@ fn spawn_squid_capsule_collider(world: &mut hecs::World, root: hecs::Entity) {
             radius: 0.5,
             height: 5.0,
         },
+        CrouchCollider {
+            standing_height: 5.0,
+            standing_offset: collider_transform.w_axis.truncate(),
+        },
         Parent(root),
     ));
 }
@@ -0,0 +1,226 @@
+use glam::{Mat4, Vec3};
+use hecs::World;
+use serde::{Deserialize, Serialize};
+
+use crate::{collision::ColliderBody, systems::physics::LocalTransform};
+
+use super::{Player, PlayerMovement};
+
+pub const SPRINT_SPEED_FACTOR: f32 = 1.6;
+pub const CROUCH_SPEED_FACTOR: f32 = 0.5;
+/// Collider height while fully crouched, relative to standing
+const CROUCH_HEIGHT_FACTOR: f32 = 0.6;
+/// Camera drop while fully crouched
+const CROUCH_CAMERA_DROP: f32 = 1.0;
+/// Seconds to crouch down or stand up
+const CROUCH_TRANSITION_TIME: f32 = 0.2;
+/// Share of max stamina required to sprint again after running out
+const STAMINA_RECOVERY: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Stance {
+    #[default]
+    Standing,
+    Sprinting,
+    Crouching,
+}
+
+impl Stance {
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            Stance::Standing => 1.0,
+            Stance::Sprinting => SPRINT_SPEED_FACTOR,
+            Stance::Crouching => CROUCH_SPEED_FACTOR,
+        }
+    }
+}
+
+/// Limits sprint duration. Players without stamina sprint indefinitely
+#[derive(Serialize, Deserialize)]
+pub struct Stamina {
+    pub max: f32,
+    pub current: f32,
+    /// Per second while sprinting
+    pub drain: f32,
+    /// Per second while not sprinting
+    pub regen: f32,
+    // Set once depleted. Sprinting is blocked until partially recovered
+    #[serde(skip)]
+    exhausted: bool,
+}
+
+impl Stamina {
+    pub fn new(max: f32, drain: f32, regen: f32) -> Stamina {
+        Self {
+            max,
+            current: max,
+            drain,
+            regen,
+            exhausted: false,
+        }
+    }
+
+    /// Drains while sprinting, regenerates otherwise. Returns whether sprinting is possible
+    fn update(&mut self, sprinting: bool, dt: f32) -> bool {
+        let can_sprint = sprinting && !self.exhausted;
+        if can_sprint {
+            self.current = (self.current - self.drain * dt).max(0.0);
+            self.exhausted = self.current == 0.0;
+        } else {
+            self.current = (self.current + self.regen * dt).min(self.max);
+            self.exhausted &= self.current < self.max * STAMINA_RECOVERY;
+        }
+        can_sprint
+    }
+}
+
+/// Capsule collider shrinking while the player crouches. Its bottom stays in place
+#[derive(Serialize, Deserialize)]
+pub struct CrouchCollider {
+    pub standing_height: f32,
+    /// Local offset while standing
+    pub standing_offset: Vec3,
+}
+
+/// Resolves requested stance against stamina & eases the crouch transition
+pub(super) fn update_stance(
+    movement: &mut PlayerMovement,
+    stamina: Option<&mut Stamina>,
+    is_moving: bool,
+    dt: f32,
+) {
+    let wants_sprint = movement.sprint_requested && !movement.crouch_requested && is_moving;
+    let can_sprint = match stamina {
+        Some(stamina) => stamina.update(wants_sprint, dt),
+        None => wants_sprint,
+    };
+    movement.stance = match (movement.crouch_requested, can_sprint) {
+        (true, _) => Stance::Crouching,
+        (false, true) => Stance::Sprinting,
+        (false, false) => Stance::Standing,
+    };
+    let target_blend = match movement.stance {
+        Stance::Crouching => 1.0,
+        _ => 0.0,
+    };
+    let max_step = dt / CROUCH_TRANSITION_TIME;
+    movement.crouch_blend += (target_blend - movement.crouch_blend).clamp(-max_step, max_step);
+}
+
+/// Current collider height for the given standing height
+fn crouch_height(standing_height: f32, crouch_blend: f32) -> f32 {
+    standing_height * (1.0 + (CROUCH_HEIGHT_FACTOR - 1.0) * crouch_blend)
+}
+
+/// Applies the crouch transition to the player capsule. Has to run before world transforms are
+/// updated
+pub fn system_crouch_collider(world: &mut World) {
+    let Some(crouch_blend) = world
+        .query::<(&Player, &PlayerMovement)>()
+        .iter()
+        .map(|(_entity, (_player, movement))| movement.crouch_blend)
+        .next()
+    else {
+        return;
+    };
+    for (_entity, (crouch, collider, local_transform)) in
+        world.query_mut::<(&CrouchCollider, &mut ColliderBody, &mut LocalTransform)>()
+    {
+        let ColliderBody::CapsuleCollider { height, .. } = collider else {
+            continue;
+        };
+        let new_height = crouch_height(crouch.standing_height, crouch_blend);
+        let (mut scale, rotation, _translation) =
+            local_transform.local.to_scale_rotation_translation();
+        // Visualization scale follows the collider
+        scale.y *= new_height / *height;
+        *height = new_height;
+        let bottom_shift = (crouch.standing_height - new_height) / 2.0;
+        let translation = crouch.standing_offset - rotation * Vec3::Y * bottom_shift;
+        local_transform.local = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+    }
+}
+
+/// Lowers the camera while crouching
+pub(super) fn crouch_camera_drop(movement: &PlayerMovement) -> f32 {
+    movement.crouch_blend * CROUCH_CAMERA_DROP
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voxie::player::squid::spawn_squid;
+
+    use super::*;
+
+    fn player(world: &World) -> hecs::Entity {
+        world
+            .query::<&Player>()
+            .iter()
+            .map(|(entity, _)| entity)
+            .next()
+            .unwrap()
+    }
+
+    fn movement(world: &World) -> hecs::Ref<'_, PlayerMovement> {
+        let player = player(world);
+        world.get::<&PlayerMovement>(player).unwrap()
+    }
+
+    #[test]
+    fn test_stamina_limits_sprint() {
+        let mut world = World::new();
+        spawn_squid(&mut world, Vec3::ZERO);
+        let mut stamina = Stamina::new(1.0, 1.0, 0.5);
+        let mut movement = world.get::<&mut PlayerMovement>(player(&world)).unwrap();
+        movement.sprint_requested = true;
+
+        for _ in 0..4 {
+            update_stance(&mut movement, Some(&mut stamina), true, 0.25);
+            assert_eq!(movement.stance, Stance::Sprinting);
+        }
+        // Exhausted until 30% recovered
+        update_stance(&mut movement, Some(&mut stamina), true, 0.25);
+        assert_eq!(movement.stance, Stance::Standing);
+        update_stance(&mut movement, Some(&mut stamina), true, 0.25);
+        assert_eq!(movement.stance, Stance::Standing);
+        assert_eq!(stamina.current, 0.25);
+        update_stance(&mut movement, Some(&mut stamina), true, 0.25);
+        update_stance(&mut movement, Some(&mut stamina), true, 0.25);
+        assert_eq!(movement.stance, Stance::Sprinting);
+
+        // Standing still or crouching does not sprint
+        update_stance(&mut movement, None, false, 0.25);
+        assert_eq!(movement.stance, Stance::Standing);
+        movement.crouch_requested = true;
+        update_stance(&mut movement, None, true, 0.25);
+        assert_eq!(movement.stance, Stance::Crouching);
+    }
+
+    #[test]
+    fn test_crouch_shrinks_collider_smoothly() {
+        let mut world = World::new();
+        spawn_squid(&mut world, Vec3::ZERO);
+        let capsule = |world: &World| {
+            let mut query = world.query::<(&CrouchCollider, &ColliderBody, &LocalTransform)>();
+            let (_entity, (_crouch, collider, local)) = query.iter().next().unwrap();
+            let ColliderBody::CapsuleCollider { height, .. } = collider else {
+                panic!("Crouch collider is no capsule");
+            };
+            (*height, local.local.w_axis.truncate())
+        };
+        let (standing_height, standing_offset) = capsule(&world);
+        {
+            let mut movement = world.get::<&mut PlayerMovement>(player(&world)).unwrap();
+            movement.crouch_requested = true;
+            update_stance(&mut movement, None, false, CROUCH_TRANSITION_TIME / 2.0);
+        }
+        system_crouch_collider(&mut world);
+        let (height, offset) = capsule(&world);
+        let expected = standing_height * (1.0 + CROUCH_HEIGHT_FACTOR) / 2.0;
+        assert!((height - expected).abs() < 1e-5, "{height}");
+        // Bottom of the capsule stays in place
+        let bottom_shift = standing_offset.y - offset.y;
+        assert!((bottom_shift - (standing_height - height) / 2.0).abs() < 1e-5);
+        assert!((crouch_camera_drop(&movement(&world)) - CROUCH_CAMERA_DROP / 2.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collision::ColliderBody,
+    renderer::{
+        RenderMeshHandle, ecs_renderer::RenderColor, impostors::Impostor,
+        texture_camera::TextureCamera,
+    },
+    systems::{
+        gun::Gun,
+        names, physics,
+        projectiles::{
+            Lifetime, Projectile, ProjectileOrigin,
+            grenade::{Grenade, GrenadeThrower},
+        },
+        serialization::{ComponentRegistry, WorldSnapshot},
+    },
+    voxels::VoxelCollider,
+    voxie::{enemy, health, interaction, kinematic, monitor, objectives, player, spawner, waves},
+};
+
+pub const DEFAULT_SAVE_PATH: &str = "voxie.save";
+// Bump whenever the layout of SaveGame or its region files changes
+const SAVE_VERSION: u32 = 5;
+const SAVE_MAGIC: [u8; 4] = *b"VOXS";
+// Magic, version & payload checksum
+const HEADER_SIZE: usize = 16;
+
+/// Terrain is generated deterministically, so only modified chunks are stored in region files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldSave {
+    pub region_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub world: WorldSave,
+    /// Player, projectiles & all other entities with registered components
+    pub entities: WorldSnapshot,
+}
+
+/// All components of the voxie scene that are persisted in save games
+pub fn component_registry() -> ComponentRegistry {
+    let mut registry = ComponentRegistry::new();
+    physics::register_components(&mut registry);
+    player::register_components(&mut registry);
+    enemy::register_components(&mut registry);
+    health::register_components(&mut registry);
+    interaction::register_components(&mut registry);
+    kinematic::register_components(&mut registry);
+    spawner::register_components(&mut registry);
+    waves::register_components(&mut registry);
+    objectives::register_components(&mut registry);
+    monitor::register_components(&mut registry);
+    names::register_components(&mut registry);
+    registry.register::<Gun>("Gun");
+    registry.register::<Projectile>("Projectile");
+    registry.register::<ProjectileOrigin>("ProjectileOrigin");
+    registry.register::<Lifetime>("Lifetime");
+    registry.register::<Grenade>("Grenade");
+    registry.register::<GrenadeThrower>("GrenadeThrower");
+    registry.register::<ColliderBody>("ColliderBody");
+    registry.register::<VoxelCollider>("VoxelCollider");
+    registry.register::<RenderMeshHandle>("RenderMeshHandle");
+    registry.register::<RenderColor>("RenderColor");
+    registry.register::<Impostor>("Impostor");
+    registry.register::<TextureCamera>("TextureCamera");
+    registry
+}
+
+impl SaveGame {
+    pub fn new(world: WorldSave, entities: WorldSnapshot) -> SaveGame {
+        Self { world, entities }
+    }
+
+    /// Writes to a temp file first & swaps it in once complete. The previous save is kept as
+    /// backup, so a crash mid-save never leaves us without a valid save
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let bytes = encode(self)?;
+        let tmp_path = with_suffix(path, ".tmp");
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+        }
+        if path.exists() {
+            fs::rename(path, backup_path(path))?;
+        }
+        fs::rename(&tmp_path, path)?;
+        info!("Saved game to {} ({} bytes)", path.display(), bytes.len());
+        Ok(())
+    }
+
+    /// Reads & validates a single save file
+    pub fn read(path: &Path) -> Result<SaveGame, Box<dyn Error>> {
+        let save = decode(&fs::read(path)?)?;
+        info!("Loaded game from {}", path.display());
+        Ok(save)
+    }
+
+    /// Reads save, falls back to the backup if the save is missing or corrupt
+    pub fn load(path: &Path) -> Result<SaveGame, Box<dyn Error>> {
+        match SaveGame::read(path) {
+            Ok(save) => Ok(save),
+            Err(err) => {
+                let backup = backup_path(path);
+                warn!(
+                    "Unable to read {}: {err}. Trying backup {}",
+                    path.display(),
+                    backup.display()
+                );
+                SaveGame::read(&backup)
+            }
+        }
+    }
+
+    pub fn exists(path: &Path) -> bool {
+        path.exists() || backup_path(path).exists()
+    }
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Region files of a save are kept in a directory next to it
+pub fn region_dir(path: &Path) -> PathBuf {
+    with_suffix(path, ".regions")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn encode(save: &SaveGame) -> Result<Vec<u8>, Box<dyn Error>> {
+    let payload = bincode::serialize(save)?;
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(&SAVE_MAGIC);
+    bytes.extend_from_slice(&SAVE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&checksum(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+fn decode(bytes: &[u8]) -> Result<SaveGame, Box<dyn Error>> {
+    if bytes.len() < HEADER_SIZE || bytes[0..4] != SAVE_MAGIC {
+        return Err("Not a save file".into());
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into()?);
+    if version != SAVE_VERSION {
+        return Err(format!("Unsupported save version {version}, expected {SAVE_VERSION}").into());
+    }
+    let expected = u64::from_le_bytes(bytes[8..16].try_into()?);
+    let payload = &bytes[HEADER_SIZE..];
+    if checksum(payload) != expected {
+        return Err("Checksum mismatch, save file is corrupt".into());
+    }
+    Ok(bincode::deserialize(payload)?)
+}
+
+/// FNV-1a. Detects truncated & corrupted files, not tampering
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+    use hecs::World;
+
+    use crate::{
+        systems::physics::{Parent, Transform},
+        voxie::player::{Player, squid::spawn_squid},
+    };
+
+    use super::*;
+
+    fn save_game() -> SaveGame {
+        let mut world = World::new();
+        spawn_squid(&mut world, Vec3::new(1.0, 2.0, 3.0));
+        SaveGame::new(
+            WorldSave {
+                region_dir: region_dir(Path::new("test.save")),
+            },
+            component_registry().serialize_world(&world).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_save_roundtrip() {
+        let path = std::env::temp_dir().join("voxie_test_save_roundtrip.save");
+        save_game().write(&path).unwrap();
+        let loaded = SaveGame::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.world.region_dir, save_game().world.region_dir);
+
+        let mut world = World::new();
+        component_registry()
+            .restore(&mut world, &loaded.entities)
+            .unwrap();
+        let mut query = world.query::<(&Player, &Transform)>();
+        let (player, (_, transform)) = query.iter().next().unwrap();
+        assert_eq!(
+            transform.0,
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0))
+        );
+        // Squid hierarchy: pivot is child of player, mesh & collider of pivot
+        assert_eq!(world.query::<&Parent>().iter().count(), 3);
+        assert!(
+            world
+                .query::<&Parent>()
+                .iter()
+                .any(|(_, parent)| parent.0 == player)
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_version() {
+        let mut bytes = encode(&save_game()).unwrap();
+        bytes[4..8].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        assert!(decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_save_falls_back_to_backup() {
+        let path = std::env::temp_dir().join("voxie_test_save_backup.save");
+        save_game().write(&path).unwrap();
+        // Second save rotates the first one into the backup
+        save_game().write(&path).unwrap();
+        assert!(backup_path(&path).exists());
+        assert!(!with_suffix(&path, ".tmp").exists());
+
+        // Simulate crash mid write of a non-atomic writer
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(SaveGame::read(&path).is_err());
+        let loaded = SaveGame::load(&path);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(backup_path(&path)).unwrap();
+        assert_eq!(
+            loaded.unwrap().world.region_dir,
+            save_game().world.region_dir
+        );
+    }
+}
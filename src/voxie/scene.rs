@@ -1,70 +1,250 @@
 use crate::{
-    cameras::{camera::CameraController, thirdpersoncam::ThirdPersonCam},
     command_queue::{Command, CommandQueue},
     config::{RESOLUTION_HEIGHT, RESOLUTION_WIDTH},
     input::InputState,
-    renderer::{ECSRenderer, Mesh},
+    log_err,
+    network::ClientId,
+    renderer::{
+        ECSRenderer, Mesh,
+        bloom::BloomPass,
+        depth,
+        effects_renderer::EffectsRenderer,
+        feature_flags::{Feature, FeatureFlags},
+        frame_graph::{BACKBUFFER, FrameGraph, ResourceName},
+        frame_uniforms::Light,
+        geometry_buffer::GeometryBuffer,
+        metrics::OverlayStats,
+        picture_in_picture::PictureInPicture,
+        render_state::RenderState,
+        settings::{AccessibilitySettings, RenderSettings},
+        spikes::{SceneDiagnostics, SystemTimer},
+        ssao::SsaoPass,
+        texture_camera::TextureCamera,
+        viewport::Viewport,
+    },
     scenes::scene::BaseScene,
     systems::{
+        effects::{DecalRing, MAX_DECALS, system_impact_decals},
         gun::system_gun_fire,
+        names::{EntityRegistry, Name, PLAYER, Tags},
         physics::{
             Transform, hierarchy_cache::HierarchyCache, system_movement_with_hierarchy_nodes,
         },
-        projectiles::{spawn_projectile, system_lifetime, system_projectile_collisions},
+        projectiles::{
+            grenade::{spawn_grenade, system_grenade_throw, system_grenades},
+            spawn_projectile, system_lifetime, system_projectile_collisions,
+        },
+        serialization::ComponentRegistry,
         skybox::fog_mesh,
         voxels::system_voxel_world_growth,
     },
+    util::{DEFAULT_SEED, MemoryTag, SeededRng},
     voxels::{
-        CHUNK_SIZE, VoxelWorld, VoxelWorldRenderer, generators::noise3d::Noise3DGenerator,
+        CHUNK_SIZE, DEFAULT_RENDER_DISTANCE, VoxelWorld, VoxelWorldRenderer,
+        chunk_events::ChunkSubscription,
+        chunk_region_around,
+        desync::DesyncDetector,
+        determinism::{chunk_origins, verify_generation},
+        edits::{ClientRequestEdit, EditAuthority, EditPrediction, VoxelEditQueue},
+        generators::{ChunkGenerator, noise3d::Noise3DGenerator},
+        lighting::LightBaker,
+        navigation::NavGraph,
+        regions::{RegionStore, chunk_keep_radius},
+        spawn::{SpawnSearch, find_spawn_point},
         system_voxel_world_collisions,
     },
-    voxie::player::{
-        Player, render_player_ui, system_player_mouse_control, system_player_movement,
+    voxie::{
+        debug_view::DebugView,
+        enemy::system_enemy_chase,
+        events::{EventBus, EventFeed, GameEvent},
+        health::{Health, system_fall_damage, system_revive_player},
+        hints::{HintActions, HintTracker},
+        interaction::{
+            InteractionState, render_interaction_prompt, spawn_lever, system_interaction,
+            system_toggle_interactions,
+        },
+        kinematic::{
+            PathMode, kinematic_obstacles, spawn_door, spawn_platform, spawn_temporary_floor,
+            system_kinematic_bodies,
+        },
+        monitor::{SECURITY_FEED, spawn_monitor, spawn_security_camera, system_security_monitors},
+        objectives::{
+            DEFAULT_OBJECTIVES_PATH, ObjectivePlan, ObjectiveReward, ObjectiveTracker,
+            render_objective_hud, spawn_objective_tracker, system_objectives,
+        },
+        player::{
+            Player, local_players, render_player_ui, system_player_mouse_control,
+            system_player_movement, teleport_player,
+        },
+        save::{DEFAULT_SAVE_PATH, SaveGame, WorldSave, component_registry, region_dir},
+        settings::{DEFAULT_SETTINGS_PATH, GameSettings},
+        spawner::system_spawners,
+        splitscreen::{
+            MAX_LOCAL_PLAYERS, PlayerView, camera_controller, join_local_player,
+            leave_local_player, sync_views,
+        },
+        stats::{DEFAULT_STATS_PATH, SessionStats},
+        waves::{
+            WaveDirector, WavePlan, render_wave_ui, spawn_wave_director, system_wave_director,
+        },
+        weather::{Weather, WeatherKind},
     },
 };
-use std::{cell::RefCell, error::Error, rc::Rc, sync::Arc, time::Duration};
+use std::{
+    cell::RefCell,
+    error::Error,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use glam::Vec3;
-use glow::{HasContext, NativeFramebuffer, NativeTexture};
+use glow::{HasContext, NativeTexture};
 use hecs::World;
 use imgui::Ui;
-use log::info;
+use log::{error, info, warn};
 
-use crate::{cameras::camera::Camera, scenes::GuiScene};
+use crate::{
+    cameras::camera::{DEFAULT_FAR, DEFAULT_NEAR, perspective_reverse_z},
+    scenes::GuiScene,
+};
 
 use super::{
     game_context::GameContext,
     player::{
+        camera_drop, camera_target,
+        character::{character_models, system_character_model},
+        locomotion::{system_locomotion, system_movement_effects},
         squid::{spawn_squid, system_squid_velocity_tilt},
+        stance::system_crouch_collider,
         system_player_keyboard_control,
     },
 };
 
 const INITIAL_WORLD_SIZE: usize = 4;
+// Single player acts as its own server
+const LOCAL_CLIENT: ClientId = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+// Catches the player in case the destination has no ground within the generated chunks
+const TELEPORT_FLOOR_HALF_EXTENTS: Vec3 = Vec3::new(2.0, 0.25, 2.0);
+const TELEPORT_FLOOR_LIFETIME: f32 = 5.0;
+// Spawn is searched around this position. Used as is, if no ground is found
+const DESIRED_SPAWN: Vec3 = Vec3::splat(50.0);
+// Player root above the ground. The collider reaches 1 below the root
+const SPAWN_HEIGHT_ABOVE_GROUND: f32 = 1.5;
+// Fog is tinted like the sky outdoors & fades to dark in caves
+const SKY_FOG_COLOR: Vec3 = Vec3::new(0.0, 0.411, 0.58);
+const CAVE_FOG_COLOR: Vec3 = Vec3::new(0.02, 0.02, 0.03);
+// Rate at which the fog tint follows the camera entering or leaving caves, per second
+const OUTDOORS_BLEND_SPEED: f32 = 2.0;
+// Frames between chunk checksum comparisons & chunks around the player that are compared
+const DESYNC_CHECK_INTERVAL: u32 = 300;
+const DESYNC_CHECK_RADIUS: i32 = 2;
+
+// Resources passed between the render passes of a view
+const SCENE_COLOR: ResourceName = "scene_color";
+const SCENE_DEPTH: ResourceName = "scene_depth";
+const BLOOM: ResourceName = "bloom";
+const AMBIENT_OCCLUSION: ResourceName = "ambient_occlusion";
+
+/// Render passes of a single view, ordered by the frame graph
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ViewPass {
+    Geometry,
+    Bloom,
+    Ssao,
+    Composite,
+}
+
+fn view_frame_graph() -> FrameGraph<ViewPass> {
+    let mut graph = FrameGraph::new();
+    graph.add_pass(ViewPass::Geometry, &[], &[SCENE_COLOR, SCENE_DEPTH]);
+    graph.add_pass(ViewPass::Bloom, &[SCENE_COLOR], &[BLOOM]);
+    graph.add_pass(ViewPass::Ssao, &[SCENE_DEPTH], &[AMBIENT_OCCLUSION]);
+    graph.add_pass(
+        ViewPass::Composite,
+        &[SCENE_COLOR, SCENE_DEPTH, BLOOM, AMBIENT_OCCLUSION],
+        &[BACKBUFFER],
+    );
+    graph
+}
 
 pub struct GameScene {
     ecs: World,
     hierarchy_cache: HierarchyCache,
+    // Named & tagged entities, synced at the start of every tick
+    entities: EntityRegistry,
 
     // TODO: Probably no longer need to wrap in refcell
     world: Rc<RefCell<VoxelWorld>>,
     context: Rc<RefCell<GameContext>>,
+    // Local stand-in for the server: Edits take the same validation path as in networked games
+    edit_authority: EditAuthority,
+    next_edit_id: u32,
+    // Own edits applied before the authority responded
+    edit_prediction: EditPrediction,
+    edit_queue: VoxelEditQueue,
+    // Compares chunks against checksums of the authority
+    desync: DesyncDetector,
+    // Chunks edited since the last frame was rendered & since the last nav graph update
+    remesh_changes: ChunkSubscription,
+    nav_changes: ChunkSubscription,
+    light_baker: LightBaker,
+    weather: Weather,
+    // Shared by all gameplay systems, so the same seed & inputs reproduce a session. Cosmetic
+    // particles draw from their own stream to leave gameplay untouched
+    rng: SeededRng,
+    particle_rng: SeededRng,
+    // Gameplay events of the current tick & the feed showing them
+    events: EventBus,
+    event_feed: EventFeed,
+    stats: SessionStats,
+    // Durations of the system groups of the last tick, dumped on frame spikes
+    system_timings: Vec<(&'static str, Duration)>,
+    settings: GameSettings,
+    hints: HintTracker,
+    nav_graph: NavGraph,
+    decals: DecalRing,
+    interaction: InteractionState,
+    save_path: PathBuf,
+    last_save: Instant,
+    components: ComponentRegistry,
 
     command_queue: Rc<RefCell<CommandQueue>>,
 
-    camera: Rc<RefCell<Camera>>,
-    camera_controller: Box<dyn CameraController>,
+    // One per local player, side by side on screen. The first view drives world streaming
+    views: Vec<PlayerView>,
+    // Player model is hidden in first person
+    first_person: bool,
+    // Destination entered in the teleport window
+    teleport_target: [f32; 3],
+    // Player root position on start & after dying
+    spawn_point: Vec3,
 
     // Rendering
     ecs_renderer: ECSRenderer,
+    effects_renderer: EffectsRenderer,
     voxel_renderer: VoxelWorldRenderer,
-    geometry_fbo: NativeFramebuffer,
+    geometry_buffer: GeometryBuffer,
     post_process_quad: Mesh,
-    first_pass_texture: NativeTexture,
-    first_pass_depth_texture: NativeTexture,
+    bloom_pass: BloomPass,
+    ssao_pass: SsaoPass,
+    frame_graph: FrameGraph<ViewPass>,
+    debug_view: DebugView,
+    picture_in_picture: PictureInPicture,
+    // Render resolution of the whole window, split across views
+    render_size: (i32, i32),
+    msaa_samples: i32,
+    // Chunks around the first player that are generated & kept loaded
+    render_distance: i32,
+    // Last applied, so pass windows can still toggle passes until a flag changes
+    features: FeatureFlags,
 
     min_fog_distance: f32,
     max_fog_distance: f32,
+    accessibility: AccessibilitySettings,
 }
 
 impl GameScene {
@@ -72,10 +252,6 @@ impl GameScene {
         gl: &Rc<glow::Context>,
         input_state: Rc<RefCell<InputState>>,
     ) -> Result<GameScene, Box<dyn Error>> {
-        // Camera setup
-        let camera = Rc::new(RefCell::new(Camera::new()));
-        let camera_controller = ThirdPersonCam::new();
-
         // Setup context
         let context_instance = GameContext::new(input_state);
         let context = Rc::new(RefCell::new(context_instance));
@@ -83,102 +259,493 @@ impl GameScene {
         // Initialize game mechanics
         let command_queue = Rc::new(RefCell::new(CommandQueue::new()));
         let generator = Arc::new(Noise3DGenerator::new(CHUNK_SIZE));
-        let world = Rc::new(RefCell::new(VoxelWorld::new(INITIAL_WORLD_SIZE, generator)));
+        let spawn_point = spawn_point(generator.as_ref());
+        let mut voxel_world = VoxelWorld::new(INITIAL_WORLD_SIZE, generator);
+        let save_path = PathBuf::from(DEFAULT_SAVE_PATH);
+        voxel_world.attach_region_store(RegionStore::open(region_dir(&save_path))?)?;
+        let remesh_changes = voxel_world.subscribe_chunk_changes();
+        let nav_changes = voxel_world.subscribe_chunk_changes();
+        let light_baker = LightBaker::new(&mut voxel_world);
+        let world = Rc::new(RefCell::new(voxel_world));
 
         // Initialize ECS world
         let mut ecs = World::new();
-        spawn_squid(&mut ecs, Vec3::splat(50.0));
+        spawn_squid(&mut ecs, spawn_point);
+        spawn_wave_director(&mut ecs, WavePlan::default());
+        spawn_objective_tracker(&mut ecs, objective_plan());
+        spawn_lever(&mut ecs, Vec3::new(50.0, 50.0, 44.0));
+        spawn_door(
+            &mut ecs,
+            Vec3::new(2.0, 3.0, 0.25),
+            Vec3::new(56.0, 50.0, 44.0),
+            Vec3::new(56.0, 56.0, 44.0),
+        );
+        spawn_platform(
+            &mut ecs,
+            Vec3::new(2.0, 0.25, 2.0),
+            vec![Vec3::new(44.0, 45.0, 50.0), Vec3::new(44.0, 65.0, 50.0)],
+            3.0,
+            PathMode::PingPong,
+        );
+        spawn_security_feed(&mut ecs);
         //spawn_skybox(&mut ecs);
+        let views = sync_views(&ecs, Vec::new(), false);
+        let mut rng = SeededRng::new(DEFAULT_SEED);
+        let particle_rng = rng.fork();
+        let settings = GameSettings::load(Path::new(DEFAULT_SETTINGS_PATH)).unwrap_or_else(|err| {
+            error!("Unable to load settings: {err}");
+            GameSettings::default()
+        });
 
         // Setup rendering
         let post_process_quad = fog_mesh(gl)?;
         let voxel_renderer = VoxelWorldRenderer::new(gl)?;
-        unsafe {
-            let width = RESOLUTION_WIDTH as i32;
-            let height = RESOLUTION_HEIGHT as i32;
-
-            // Setup geometry pass framebuffer
-            let geometry_fbo = gl.create_framebuffer()?;
-            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(geometry_fbo));
-            // Setup frame color texture
-            let frame_color_tex = gl.create_texture()?;
-            gl.bind_texture(gl::TEXTURE_2D, Some(frame_color_tex));
-            gl.tex_image_2d(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGB as i32,
-                width,
-                height,
-                0,
-                gl::RGB,
-                gl::UNSIGNED_BYTE,
-                None,
-            );
-            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-            // Attach color texture to framebuffer
-            gl.framebuffer_texture_2d(
-                gl::FRAMEBUFFER,
-                gl::COLOR_ATTACHMENT0,
-                gl::TEXTURE_2D,
-                Some(frame_color_tex),
-                0,
-            );
+        let width = RESOLUTION_WIDTH as i32;
+        let height = RESOLUTION_HEIGHT as i32;
+        let mut scene = Self {
+            bloom_pass: BloomPass::new(gl, width, height)?,
+            ssao_pass: SsaoPass::new(gl, width, height)?,
+            frame_graph: view_frame_graph(),
+            debug_view: DebugView::new(),
+            picture_in_picture: PictureInPicture::new(gl),
+            geometry_buffer: GeometryBuffer::new(gl, width, height, 1)?,
+            post_process_quad,
+            views,
+            render_size: (width, height),
+            msaa_samples: 1,
+            render_distance: DEFAULT_RENDER_DISTANCE,
+            features: FeatureFlags::default(),
+            first_person: false,
+            teleport_target: [64.0, 48.0, 64.0],
+            spawn_point,
+            command_queue: Rc::clone(&command_queue),
+            context,
+            edit_authority: EditAuthority::new(),
+            next_edit_id: 0,
+            edit_prediction: EditPrediction::new(),
+            edit_queue: VoxelEditQueue::new(),
+            desync: DesyncDetector::new(),
+            remesh_changes,
+            nav_changes,
+            light_baker,
+            weather: Weather::new(),
+            rng,
+            particle_rng,
+            events: EventBus::new(),
+            event_feed: EventFeed::new(),
+            stats: SessionStats::new(),
+            system_timings: Vec::new(),
+            settings,
+            hints: HintTracker::new(),
+            nav_graph: NavGraph::new(),
+            decals: DecalRing::new(MAX_DECALS),
+            interaction: InteractionState::default(),
+            save_path,
+            last_save: Instant::now(),
+            components: component_registry(),
+            ecs,
+            hierarchy_cache: HierarchyCache::new(),
+            entities: EntityRegistry::new(),
+            ecs_renderer: ECSRenderer::new(gl)?,
+            effects_renderer: EffectsRenderer::new(gl)?,
+            voxel_renderer,
+            world,
+            min_fog_distance: 33.0,
+            max_fog_distance: 150.0,
+            accessibility: AccessibilitySettings::default(),
+        };
+        // Continue previous session
+        if SaveGame::exists(&scene.save_path) {
+            log_err!(scene.load_game(), "Unable to load save game: {err}");
+        }
+        scene.nav_graph.sync(&scene.world.borrow());
+        Ok(scene)
+    }
 
-            // Setup depth & stencil buffer
-            let ds_texture = gl.create_texture()?;
-            gl.bind_texture(gl::TEXTURE_2D, Some(ds_texture));
-            gl.tex_image_2d(
-                gl::TEXTURE_2D,
-                0,
-                gl::DEPTH24_STENCIL8 as i32,
-                width,
-                height,
-                0,
-                gl::DEPTH_STENCIL,
-                gl::UNSIGNED_INT_24_8,
-                None,
-            );
-            // Sample only depth values into texture
-            gl.tex_parameter_i32(
-                gl::TEXTURE_2D,
-                gl::DEPTH_STENCIL_TEXTURE_MODE,
-                gl::DEPTH_COMPONENT as i32,
-            );
-            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl.tex_parameter_i32(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            // Attach stencil texture to framebuffer
-            gl.framebuffer_texture_2d(
-                gl::FRAMEBUFFER,
-                gl::DEPTH_STENCIL_ATTACHMENT,
-                gl::TEXTURE_2D,
-                Some(ds_texture),
-                0,
-            );
+    /// Generates the chunks around the world origin twice across all threads & compares their
+    /// content. Returns the number of verified chunks
+    pub fn verify_world_generation() -> Result<usize, String> {
+        let origins = chunk_origins(INITIAL_WORLD_SIZE as i32 * 2);
+        verify_generation(|| Box::new(Noise3DGenerator::new(CHUNK_SIZE)), &origins)
+    }
+
+    /// Replaces the weather schedule, e.g. from the command line. None returns to the schedule
+    pub fn set_weather_override(&mut self, kind: Option<WeatherKind>) {
+        self.weather.override_kind = kind;
+    }
+
+    pub fn save_game(&mut self) -> Result<(), Box<dyn Error>> {
+        let entities = self.components.serialize_world(&self.ecs)?;
+        let world = WorldSave {
+            region_dir: region_dir(&self.save_path),
+        };
+        self.world
+            .borrow_mut()
+            .persist_regions(&self.edit_prediction)?;
+        SaveGame::new(world, entities).write(&self.save_path)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+
+    pub fn load_game(&mut self) -> Result<(), Box<dyn Error>> {
+        let save = SaveGame::load(&self.save_path)?;
+        let mut ecs = World::new();
+        self.components.restore(&mut ecs, &save.entities)?;
+        // Saves from before waves existed
+        if ecs.query::<&WaveDirector>().iter().next().is_none() {
+            spawn_wave_director(&mut ecs, WavePlan::default());
+        }
+        if ecs.query::<&ObjectiveTracker>().iter().next().is_none() {
+            spawn_objective_tracker(&mut ecs, objective_plan());
+        }
+        if ecs.query::<&TextureCamera>().iter().next().is_none() {
+            spawn_security_feed(&mut ecs);
+        }
+        // Saves from before entities were named
+        if ecs.query::<&Name>().iter().next().is_none() {
+            for (index, player) in local_players(&ecs).into_iter().enumerate() {
+                let name = match index {
+                    0 => PLAYER.to_string(),
+                    _ => format!("{PLAYER} {}", index + 1),
+                };
+                ecs.insert(player, (Name(name), Tags::new(&[PLAYER])))?;
+            }
+        }
+        self.ecs = ecs;
+        self.hierarchy_cache = HierarchyCache::new();
+        self.sync_views()?;
+        // Stored chunks override generated terrain. Regenerate to drop edits made since saving
+        let generator = Arc::new(Noise3DGenerator::new(CHUNK_SIZE));
+        let mut world = VoxelWorld::new(INITIAL_WORLD_SIZE, generator);
+        world.attach_region_store(RegionStore::open(save.world.region_dir)?)?;
+        world.inherit_chunk_subscribers(&mut self.world.borrow_mut());
+        *self.world.borrow_mut() = world;
+        self.nav_graph = NavGraph::new();
+        self.nav_graph.sync(&self.world.borrow());
+        Ok(())
+    }
+
+    /// Switches all views between first & third person cameras
+    fn toggle_first_person(&mut self) {
+        self.first_person = !self.first_person;
+        for view in &mut self.views {
+            view.camera_controller = camera_controller(self.first_person);
+        }
+    }
+
+    fn viewports(&self) -> Vec<Viewport> {
+        Viewport::split(
+            RESOLUTION_WIDTH as i32,
+            RESOLUTION_HEIGHT as i32,
+            self.views.len(),
+        )
+    }
+
+    /// Matches views to the local players, e.g. after joining or loading
+    fn sync_views(&mut self) -> Result<(), Box<dyn Error>> {
+        let views = std::mem::take(&mut self.views);
+        self.views = sync_views(&self.ecs, views, self.first_person);
+        self.update_projections();
+        self.resize_geometry_buffer()
+    }
+
+    fn update_projections(&mut self) {
+        let fov = self.accessibility.fov_degrees.to_radians();
+        let viewports = self.viewports();
+        for (view, viewport) in self.views.iter_mut().zip(viewports) {
+            view.camera.set_projection(perspective_reverse_z(
+                fov,
+                viewport.aspect_ratio(),
+                DEFAULT_NEAR,
+                DEFAULT_FAR,
+            ));
+        }
+    }
+
+    // Views are rendered one after another, so the geometry buffer only has to fit one
+    fn resize_geometry_buffer(&mut self) -> Result<(), Box<dyn Error>> {
+        let view = self.viewports()[0];
+        let (width, height) = self.render_size;
+        self.geometry_buffer.resize(
+            (width * view.width / RESOLUTION_WIDTH as i32).max(1),
+            (height * view.height / RESOLUTION_HEIGHT as i32).max(1),
+            self.msaa_samples,
+        )
+    }
+
+    fn join_local_player(&mut self) {
+        let Ok(position) = self
+            .ecs
+            .get::<&Transform>(self.views[0].player)
+            .map(|transform| transform.0.w_axis.truncate())
+        else {
+            return;
+        };
+        if let Some(player) = join_local_player(&mut self.ecs, position) {
+            info!("Second player joined");
+            self.events.publish(GameEvent::PlayerJoined {
+                name: player_name(&self.ecs, player),
+            });
+            log_err!(self.sync_views(), "Unable to add view: {err}");
+        }
+    }
+
+    fn leave_local_player(&mut self) {
+        let name = local_players(&self.ecs)
+            .last()
+            .map(|player| player_name(&self.ecs, *player));
+        if leave_local_player(&mut self.ecs).is_some() {
+            info!("Second player left");
+            self.events.publish(GameEvent::PlayerLeft {
+                name: name.unwrap_or_default(),
+            });
+            log_err!(self.sync_views(), "Unable to remove view: {err}");
+        }
+    }
+
+    /// Full pipeline for the view of a single player, composited into its viewport of the window.
+    /// Passes run in the order resolved by the frame graph
+    fn render_view(
+        &mut self,
+        gl: &glow::Context,
+        index: usize,
+        viewport: Viewport,
+        time_elapsed: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.frame_graph
+            .set_enabled(ViewPass::Bloom, self.bloom_pass.enabled);
+        self.frame_graph
+            .set_enabled(ViewPass::Ssao, self.ssao_pass.enabled);
+        let order = self
+            .frame_graph
+            .compile()
+            .map_err(|err| format!("Unable to schedule render passes: {err}"))?
+            .order()
+            .to_vec();
+        // Outputs of skipped passes stay None
+        let mut bloom_texture = None;
+        let mut ao_texture = None;
+        for pass in order {
+            match pass {
+                ViewPass::Geometry => self.render_geometry_pass(gl, index, time_elapsed),
+                ViewPass::Bloom => {
+                    bloom_texture =
+                        Some(self.bloom_pass.render(self.geometry_buffer.color_texture()));
+                }
+                ViewPass::Ssao => {
+                    let cam = &self.views[index].camera;
+                    ao_texture = Some(
+                        self.ssao_pass
+                            .render(self.geometry_buffer.depth_texture(), cam),
+                    );
+                }
+                ViewPass::Composite => {
+                    self.render_composite_pass(gl, index, viewport, bloom_texture, ao_texture)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Debug camera over the top right corner of the window, drawn after all views
+    fn render_debug_view(
+        &mut self,
+        gl: &glow::Context,
+        time_elapsed: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        let window = Viewport::full(RESOLUTION_WIDTH as i32, RESOLUTION_HEIGHT as i32);
+        let corner = self.debug_view.viewport(window);
+        let light_direction = self
+            .ecs_renderer
+            .lighting
+            .lights
+            .iter()
+            .find_map(Light::direction);
+        let player_camera = &self.views[0].camera;
+        let Some(cam) = self.debug_view.camera(
+            player_camera,
+            player_camera.position,
+            light_direction,
+            corner.aspect_ratio(),
+        ) else {
+            return Ok(());
+        };
+        let culling = match self.debug_view.cull_with_player {
+            true => self.debug_view.frozen_camera().unwrap_or(player_camera),
+            false => &cam,
+        };
+        // Composite pass left depth testing disabled
+        self.render_state().apply(gl);
+        let viewport = self.picture_in_picture.begin(corner)?;
+        self.ecs_renderer
+            .render_camera(&cam, viewport, &[], time_elapsed);
+        // Voxels are drawn with the camera of the frame uniforms, the given one only culls
+        self.voxel_renderer
+            .render(culling, viewport, &self.world.borrow());
+        self.picture_in_picture.present(corner);
+        Ok(())
+    }
+
+    // Opaque geometry & transparent effects into the geometry buffer
+    fn render_geometry_pass(&mut self, gl: &glow::Context, index: usize, time_elapsed: f32) {
+        // Own model is only hidden in the own view
+        let player = self.views[index].player;
+        let skip = match self.first_person {
+            true => character_models(&self.ecs, player),
+            false => Vec::new(),
+        };
+        unsafe {
+            self.geometry_buffer.bind();
+            gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let cam = &self.views[index].camera;
+        // A frozen frustum only replaces culling of the first player
+        let culling = match index {
+            0 => self.debug_view.frozen_camera().unwrap_or(cam),
+            _ => cam,
+        };
+        let geometry_viewport = self.geometry_buffer.viewport();
+        // Frame uniforms are shared by voxel & ecs shaders
+        self.ecs_renderer
+            .render_camera(cam, geometry_viewport, &skip, time_elapsed);
+        self.voxel_renderer
+            .render(culling, geometry_viewport, &self.world.borrow());
+        // Transparent effects last, on top of opaque geometry
+        let debug_lines = self.debug_view.frustum_lines();
+        self.effects_renderer
+            .render(&self.ecs, &self.decals, &debug_lines);
+        self.geometry_buffer.resolve();
+    }
 
+    // Fog, ambient occlusion & bloom composited into the viewport of the window
+    fn render_composite_pass(
+        &mut self,
+        gl: &glow::Context,
+        index: usize,
+        viewport: Viewport,
+        bloom_texture: Option<NativeTexture>,
+        ao_texture: Option<NativeTexture>,
+    ) {
+        // Previous passes changed viewport & blending
+        self.render_state().apply(gl);
+        unsafe {
             gl.bind_framebuffer(gl::FRAMEBUFFER, None);
-            Ok(Self {
-                first_pass_depth_texture: ds_texture,
-                geometry_fbo,
-                first_pass_texture: frame_color_tex,
-                post_process_quad,
-                camera,
-                camera_controller: Box::new(camera_controller),
-                command_queue: Rc::clone(&command_queue),
-                context,
-                ecs,
-                hierarchy_cache: HierarchyCache::new(),
-                ecs_renderer: ECSRenderer::new(gl)?,
-                voxel_renderer,
-                world,
-                min_fog_distance: 33.0,
-                max_fog_distance: 150.0,
-            })
+            if index == 0 {
+                gl.clear(gl::COLOR_BUFFER_BIT);
+            }
+
+            // Wireframe mode
+            //gl.polygon_mode(gl::FRONT_AND_BACK, gl::LINE);
+        }
+        viewport.apply(gl);
+        let cam = &self.views[index].camera;
+        let outdoors = self.views[index].outdoors;
+        let shader = &mut self.post_process_quad.shader;
+        shader.use_program();
+        // Sync uniforms with UI controls
+        shader.set_uniform_f32("min_fog_distance", self.min_fog_distance);
+        shader.set_uniform_vec3("fog_color", &CAVE_FOG_COLOR.lerp(SKY_FOG_COLOR, outdoors));
+        shader.set_uniform_mat4("uInvProjection", &cam.get_projection_matrix().inverse());
+        shader.set_uniform_i32("uDepthZeroToOne", depth::is_clip_depth_zero_to_one() as i32);
+        // Calculate fog density at cpu to avoid per fragment
+        const LN_0_01: f32 = -4.605_170_2;
+        shader.set_uniform_f32(
+            "fog_density",
+            LN_0_01 / (self.max_fog_distance - self.min_fog_distance) * self.weather.fog_scale(),
+        );
+        // Skipped passes are not composited
+        let bloom_intensity = match bloom_texture {
+            Some(_) => self.bloom_pass.composite_intensity(),
+            None => 0.0,
+        };
+        let ao_strength = match ao_texture {
+            Some(_) => self.ssao_pass.composite_strength(),
+            None => 0.0,
+        };
+        shader.set_uniform_f32("bloom_intensity", bloom_intensity);
+        shader.set_uniform_f32("ao_strength", ao_strength);
+        shader.set_uniform_mat3("uColorFilter", &self.accessibility.color_filter.matrix());
+
+        let vao = self.post_process_quad.vao;
+        let count = self.post_process_quad.vertex_count;
+        unsafe {
+            gl.disable(gl::DEPTH_TEST);
+            gl.bind_vertex_array(Some(vao));
+            // Bind first pass color texture
+            gl.active_texture(gl::TEXTURE0);
+            gl.bind_texture(gl::TEXTURE_2D, Some(self.geometry_buffer.color_texture()));
+            // Bind first pass depth texture
+            gl.active_texture(gl::TEXTURE1);
+            gl.bind_texture(gl::TEXTURE_2D, Some(self.geometry_buffer.depth_texture()));
+            // Bind blurred bloom texture
+            gl.active_texture(gl::TEXTURE2);
+            gl.bind_texture(gl::TEXTURE_2D, bloom_texture);
+            // Bind ambient occlusion texture
+            gl.active_texture(gl::TEXTURE3);
+            gl.bind_texture(gl::TEXTURE_2D, ao_texture);
+            gl.draw_elements(glow::TRIANGLES, count, gl::UNSIGNED_INT, 0);
+            gl.bind_vertex_array(None);
         }
     }
 
+    /// Single point in the frame where the voxel world is modified
+    // Changed chunks reach renderer, light baker & nav graph through their subscriptions
+    fn apply_voxel_edits(&mut self, player_position: Vec3) {
+        let mut accepted = Vec::new();
+        for edit in self.edit_queue.drain() {
+            let request = ClientRequestEdit {
+                edit_id: self.next_edit_id,
+                edit,
+            };
+            let edit_id = request.edit_id;
+            self.next_edit_id += 1;
+            self.world
+                .borrow_mut()
+                .predict_edit(&mut self.edit_prediction, &request);
+            // Rejections are logged by the authority
+            match self
+                .edit_authority
+                .request(LOCAL_CLIENT, request, player_position)
+            {
+                Ok(()) => accepted.push(edit_id),
+                Err(_) => {
+                    self.world
+                        .borrow_mut()
+                        .rollback_edit(&mut self.edit_prediction, edit_id);
+                }
+            }
+        }
+        let frame = self.context.borrow().current_frame;
+        if let Some(apply) = self.edit_authority.drain_tick(frame) {
+            self.world.borrow_mut().apply_edits(&apply);
+        }
+        for edit_id in accepted {
+            self.world
+                .borrow_mut()
+                .confirm_edit(&mut self.edit_prediction, edit_id);
+        }
+    }
+
+    // Local authority & client share the world, so this only exercises the networked path
+    fn check_voxel_sync(&mut self, player_position: Vec3) {
+        let frame = self.context.borrow().current_frame;
+        let region = chunk_region_around(&player_position, DESYNC_CHECK_RADIUS);
+        let checksums = self.world.borrow().chunk_checksums(frame, &region);
+        let Some(request) =
+            self.desync
+                .compare(&self.world.borrow(), &self.edit_prediction, &checksums)
+        else {
+            return;
+        };
+        let data = self.world.borrow().chunk_data(&request);
+        self.desync
+            .apply_resync(&mut self.world.borrow_mut(), &data);
+    }
+
     fn process_command_queue(&mut self) {
-        for cmd in self.command_queue.borrow_mut().iter() {
+        // Collected first, as commands may need the whole scene
+        let commands: Vec<Command> = self.command_queue.borrow_mut().iter().collect();
+        for cmd in commands {
             match cmd {
                 Command::SpawnProjectile {
                     transform,
@@ -186,9 +753,85 @@ impl GameScene {
                 } => {
                     spawn_projectile(&mut self.ecs, transform, velocity);
                 }
+                Command::ThrowGrenade { position, velocity } => {
+                    spawn_grenade(&mut self.ecs, position, velocity);
+                }
+                Command::Teleport { position } => self.teleport(position),
             }
         }
     }
+
+    fn apply_objective_reward(&mut self, reward: ObjectiveReward) {
+        match reward {
+            ObjectiveReward::RestoreHealth => {
+                for (_entity, (_player, health)) in self.ecs.query_mut::<(&Player, &mut Health)>() {
+                    health.current = health.max;
+                }
+            }
+            ObjectiveReward::Teleport { position } => self.teleport(position),
+        }
+    }
+
+    // Generates the destination synchronously before moving the players there. Local players
+    // travel together
+    fn teleport(&mut self, position: Vec3) {
+        let generated = self.world.borrow_mut().prepare_teleport(&position);
+        let mut floor = None;
+        for player in local_players(&self.ecs) {
+            floor = floor.or(teleport_player(&mut self.ecs, player, position));
+        }
+        let Some(feet) = floor else {
+            return;
+        };
+        spawn_temporary_floor(
+            &mut self.ecs,
+            feet,
+            TELEPORT_FLOOR_HALF_EXTENTS,
+            TELEPORT_FLOOR_LIFETIME,
+        );
+        self.nav_graph.sync(&self.world.borrow());
+        info!("Teleported to {position}, generated {generated} chunks");
+    }
+}
+
+fn player_name(ecs: &World, player: hecs::Entity) -> String {
+    ecs.get::<&Name>(player)
+        .map_or(PLAYER.to_string(), |name| name.0.clone())
+}
+
+// Camera overlooking the spawn & a monitor next to the lever showing its image
+fn spawn_security_feed(ecs: &mut World) {
+    spawn_security_camera(
+        ecs,
+        SECURITY_FEED,
+        Vec3::new(60.0, 62.0, 60.0),
+        DESIRED_SPAWN,
+    );
+    spawn_monitor(ecs, SECURITY_FEED, Vec3::new(50.0, 52.5, 42.0), 0.0);
+}
+
+// Data defined objectives, the built-in plan if unavailable
+fn objective_plan() -> ObjectivePlan {
+    let path = Path::new(DEFAULT_OBJECTIVES_PATH);
+    if !path.exists() {
+        return ObjectivePlan::default();
+    }
+    ObjectivePlan::load(path).unwrap_or_else(|err| {
+        warn!("Unable to load objectives from {}: {err}", path.display());
+        ObjectivePlan::default()
+    })
+}
+
+// Player root position on top of the terrain near the desired spawn
+fn spawn_point(generator: &dyn ChunkGenerator) -> Vec3 {
+    let max_height = (INITIAL_WORLD_SIZE * CHUNK_SIZE) as i32;
+    match find_spawn_point(generator, &SpawnSearch::new(DESIRED_SPAWN, max_height)) {
+        Some(ground) => ground + Vec3::Y * SPAWN_HEIGHT_ABOVE_GROUND,
+        None => {
+            warn!("No ground found near {DESIRED_SPAWN}. Spawning at the desired position");
+            DESIRED_SPAWN
+        }
+    }
 }
 
 impl BaseScene for GameScene {
@@ -196,47 +839,241 @@ impl BaseScene for GameScene {
         "Voxie".to_string()
     }
 
-    fn tick(&mut self, dt: f32) {
+    fn tick(&mut self, dt: f32) -> Result<(), Box<dyn Error>> {
+        let _memory = MemoryTag::Ecs.enter();
+        let mut timer = SystemTimer::start();
         // Entity lifetime (as early as possible to avoid simulating dead entities)
-        system_lifetime(&mut self.ecs, dt);
+        system_lifetime(&mut self.ecs, dt)?;
 
         self.context.borrow_mut().tick();
+        self.entities.sync(&self.ecs);
 
         system_player_mouse_control(&mut self.ecs, &self.context.borrow().input_state.borrow());
-        system_player_keyboard_control(&mut self.ecs, &self.context.borrow().input_state.borrow());
-        system_player_movement(&mut self.ecs, dt, &self.world.borrow());
+        system_player_keyboard_control(
+            &mut self.ecs,
+            &self.context.borrow().input_state.borrow(),
+            dt,
+        );
+        system_kinematic_bodies(&mut self.ecs, dt);
+        let kinematic_bodies = kinematic_obstacles(&self.ecs);
+        system_player_movement(&mut self.ecs, dt, &self.world.borrow(), &kinematic_bodies);
         system_squid_velocity_tilt(&mut self.ecs, dt);
-        system_gun_fire(&mut self.ecs, &mut self.command_queue.borrow_mut(), dt);
+        system_crouch_collider(&mut self.ecs);
+        timer.lap("Player movement");
+        system_wave_director(&mut self.ecs, &self.entities, dt)?;
+        system_spawners(&mut self.ecs, &self.entities, &self.nav_graph, dt)?;
+        system_enemy_chase(&mut self.ecs, &self.entities, &self.nav_graph, dt);
+        timer.lap("Enemies");
+        let shooters = system_gun_fire(
+            &mut self.ecs,
+            &mut self.command_queue.borrow_mut(),
+            &mut self.rng,
+            dt,
+        );
+        for shooter in &shooters {
+            let name = player_name(&self.ecs, *shooter);
+            self.stats.player(&name).shots_fired += 1;
+        }
+        let throwers = system_grenade_throw(
+            &mut self.ecs,
+            &self.world.borrow(),
+            &mut self.command_queue.borrow_mut(),
+            dt,
+        );
+        timer.lap("Weapons");
         system_movement_with_hierarchy_nodes(&mut self.ecs, dt, &mut self.hierarchy_cache);
+        // Own model is skipped per view while rendering, so other views still show it
+        system_character_model(&mut self.ecs, dt, false, self.accessibility.view_bob);
+        let movement_events = system_locomotion(
+            &mut self.ecs,
+            &self.entities,
+            dt,
+            &self.world.borrow(),
+            &kinematic_bodies,
+        );
+        let listeners: Vec<Vec3> = self.views.iter().map(|view| view.camera.position).collect();
+        system_movement_effects(
+            &mut self.ecs,
+            &self.world.borrow(),
+            &listeners,
+            &movement_events,
+        );
+        system_fall_damage(
+            &mut self.ecs,
+            &self.world.borrow(),
+            &movement_events,
+            &mut self.events,
+        );
+        let player_died = system_revive_player(&mut self.ecs, &mut self.events);
+        if player_died {
+            // Terrain at the spawn point may have been dug away since
+            let respawn = self
+                .world
+                .borrow()
+                .heightmap()
+                .surface_point(&self.spawn_point)
+                .map_or(self.spawn_point, |ground| {
+                    ground + Vec3::Y * SPAWN_HEIGHT_ABOVE_GROUND
+                });
+            self.teleport(respawn);
+        }
 
+        timer.lap("Locomotion & damage");
         // System camera controller
-        {
-            let mut query = self.ecs.query::<(&Player, &Transform)>();
-
-            let (_entity, (_player, transform)) =
-                query.iter().next().expect("No player found to follow");
-            self.camera_controller
-                .tick(dt, &mut self.camera.borrow_mut(), &transform.0);
+        for view in &mut self.views {
+            // Crouching & landings move the camera, not the player
+            let drop = camera_drop(&self.ecs, view.player, self.accessibility.camera_shake);
+            if let Some(target) = camera_target(&self.ecs, view.player, drop) {
+                view.camera_controller.tick(dt, &mut view.camera, &target);
+            }
+            let outdoors = self
+                .world
+                .borrow()
+                .heightmap()
+                .is_outdoors(&view.camera.position) as i32 as f32;
+            view.outdoors += (outdoors - view.outdoors) * (OUTDOORS_BLEND_SPEED * dt).min(1.0);
         }
+        let player_position = self
+            .entities
+            .position(&self.ecs, PLAYER)
+            .unwrap_or(self.views[0].camera.position);
+        // Interactions stay with the first player, who owns the use key
+        let interactions = system_interaction(
+            &self.ecs,
+            &self.entities,
+            &self.views[0].camera,
+            &self.context.borrow().input_state.borrow(),
+            &mut self.interaction,
+        );
+        system_toggle_interactions(&mut self.ecs, &interactions);
+        system_security_monitors(&mut self.ecs);
+        timer.lap("Cameras & interaction");
+        self.weather.tick(dt);
+        self.weather.spawn_particles(
+            &mut self.ecs,
+            self.world.borrow().heightmap(),
+            player_position,
+            &mut self.particle_rng,
+            dt,
+        );
 
+        timer.lap("Weather");
         let collision_events = system_voxel_world_collisions(&mut self.ecs, &self.world.borrow());
-        system_projectile_collisions(
+        let impacts =
+            system_projectile_collisions(&mut self.ecs, &collision_events, &mut self.edit_queue)?;
+        let explosions = system_grenades(
             &mut self.ecs,
-            &mut self.world.borrow_mut(),
-            &collision_events,
-        );
+            &self.world.borrow(),
+            &mut self.edit_queue,
+            dt,
+        )?;
+        for (name, position) in impacts
+            .into_iter()
+            .map(|position| ("Projectile", position))
+            .chain(explosions.into_iter().map(|position| ("Grenade", position)))
+        {
+            self.events.publish(GameEvent::Destroyed {
+                name: name.to_string(),
+                position,
+            });
+        }
+        timer.lap("Collisions");
+        // All systems reading the voxel world are done for this tick
+        self.apply_voxel_edits(player_position);
+        timer.lap("Voxel edits");
+        let mined_voxels = self.world.borrow_mut().take_removed_voxels();
+        if mined_voxels > 0 {
+            self.events.publish(GameEvent::BlocksMined {
+                count: mined_voxels,
+            });
+        }
+        for completed in
+            system_objectives(&mut self.ecs, &self.entities, dt, mined_voxels, player_died)
+        {
+            if let Some(reward) = completed.reward {
+                self.apply_objective_reward(reward);
+            }
+        }
+        system_impact_decals(&mut self.ecs, &self.world.borrow(), &mut self.decals);
+        self.decals.tick(dt);
+        timer.lap("Objectives & decals");
         if self.context.borrow().current_frame % 60 == 0 {
             // Check for world expansion once a second
-            system_voxel_world_growth(&mut self.world.borrow_mut(), &self.camera.borrow().position);
+            system_voxel_world_growth(
+                &mut self.world.borrow_mut(),
+                &self.views[0].camera.position,
+                self.render_distance,
+            );
+            log_err!(
+                self.world.borrow_mut().evict_distant_chunks(
+                    &self.views[0].camera.position,
+                    chunk_keep_radius(self.render_distance)
+                ),
+                "Unable to evict distant chunks: {err}"
+            );
+            self.nav_graph.sync(&self.world.borrow());
+        }
+        if self
+            .context
+            .borrow()
+            .current_frame
+            .is_multiple_of(DESYNC_CHECK_INTERVAL)
+        {
+            self.check_voxel_sync(player_position);
         }
+        let edited = self.nav_changes.edited_chunks();
+        if !edited.is_empty() {
+            self.nav_graph.rebuild_chunks(&self.world.borrow(), &edited);
+        }
+        timer.lap("World streaming");
         self.world.borrow_mut().receive_chunks();
+        self.light_baker
+            .update(&self.world.borrow(), &player_position, self.render_distance);
+        timer.lap("Lighting");
         self.process_command_queue();
+        let events: Vec<GameEvent> = self.events.drain().collect();
+        self.stats.observe(&events);
+        self.stats.track_players(&self.ecs, dt);
+        self.event_feed.record(events);
+        self.event_feed.tick(dt);
+        let actions = HintActions::observe(&self.ecs, &shooters, &throwers);
+        if self
+            .hints
+            .update(&self.ecs, actions, &mut self.settings.completed_hints)
+        {
+            log_err!(
+                self.settings.save(Path::new(DEFAULT_SETTINGS_PATH)),
+                "Unable to save settings: {err}"
+            );
+        }
+        timer.lap("Commands & events");
+        if self.last_save.elapsed() >= AUTOSAVE_INTERVAL {
+            log_err!(self.save_game(), "Autosave failed: {err}");
+        }
+        timer.lap("Autosave");
+        self.system_timings = timer.finish();
+        Ok(())
     }
 
-    fn start(&mut self) {
+    fn on_enter(&mut self) {
         info!("Starting game scene...");
     }
 
+    fn on_exit(&mut self) {
+        info!("Leaving game scene...");
+        self.world.borrow_mut().cancel_chunk_generation();
+        log_err!(self.save_game(), "Unable to save game on exit: {err}");
+        log_err!(
+            self.stats.export_csv(Path::new(DEFAULT_STATS_PATH)),
+            "Unable to export session stats: {err}"
+        );
+    }
+
+    fn on_pause(&mut self) {
+        // Player might not come back, e.g. when the window is closed from the task bar
+        log_err!(self.save_game(), "Unable to save game on pause: {err}");
+    }
+
     fn get_world(&self) -> Option<&World> {
         None
     }
@@ -245,7 +1082,95 @@ impl BaseScene for GameScene {
 impl GuiScene for GameScene {
     fn render_ui(&mut self, ui: &mut Ui) {
         self.voxel_renderer.render_ui(ui);
-        render_player_ui(&mut self.ecs, ui);
+        self.bloom_pass.render_ui(ui);
+        self.ssao_pass.render_ui(ui);
+        self.frame_graph.render_ui(ui);
+        self.debug_view.render_ui(ui);
+        self.light_baker.render_ui(ui);
+        self.weather.render_ui(ui);
+        self.event_feed.render_ui(ui);
+        if ui.is_key_down(imgui::Key::Tab) {
+            self.stats.render_scoreboard(ui);
+        }
+        render_wave_ui(&mut self.ecs, ui);
+        self.desync.render_ui(ui);
+        // HUD is repeated in every view
+        let display_size = ui.io().display_size;
+        let frame = (RESOLUTION_WIDTH as i32, RESOLUTION_HEIGHT as i32);
+        let players: Vec<hecs::Entity> = self.views.iter().map(|view| view.player).collect();
+        for (index, (player, viewport)) in players.into_iter().zip(self.viewports()).enumerate() {
+            let rect = viewport.ui_rect(frame, display_size);
+            render_player_ui(
+                &mut self.ecs,
+                player,
+                &format!("Player {}", index + 1),
+                rect,
+                ui,
+            );
+            render_objective_hud(&self.ecs, &format!("Objective##{index}"), rect, ui);
+            self.event_feed
+                .render_hud(&format!("Events##{index}"), rect, ui);
+            if index == 0 {
+                render_interaction_prompt(&self.ecs, &self.interaction, rect, ui);
+                self.hints.render_hud(rect, ui);
+            }
+        }
+        let save_path = self.save_path.display().to_string();
+        ui.window("Save game")
+            .size([300.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 350.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("File: {save_path}"));
+                if ui.button("Save [F5]") || ui.is_key_pressed(imgui::Key::F5) {
+                    log_err!(self.save_game(), "Unable to save game: {err}");
+                }
+                ui.same_line();
+                if ui.button("Load [F9]") || ui.is_key_pressed(imgui::Key::F9) {
+                    log_err!(self.load_game(), "Unable to load save game: {err}");
+                }
+            });
+        ui.window("Teleport")
+            .size([300.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 510.0], imgui::Condition::FirstUseEver)
+            .collapsed(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.input_float3("Position", &mut self.teleport_target)
+                    .build();
+                if ui.button("Teleport") {
+                    self.command_queue.borrow_mut().enqueue(Command::Teleport {
+                        position: Vec3::from_array(self.teleport_target),
+                    });
+                }
+            });
+        ui.window("Camera")
+            .size([300.0, 60.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 440.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let label = match self.first_person {
+                    true => "Third person [V]",
+                    false => "First person [V]",
+                };
+                if ui.button(label) || ui.is_key_pressed(imgui::Key::V) {
+                    self.toggle_first_person();
+                }
+            });
+        let mut toggle_coop = false;
+        ui.window("Co-op")
+            .size([300.0, 60.0], imgui::Condition::FirstUseEver)
+            .position([0.0, 590.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                let label = match self.views.len() < MAX_LOCAL_PLAYERS {
+                    true => "Join with arrow keys [F2]",
+                    false => "Leave player 2 [F2]",
+                };
+                toggle_coop = ui.button(label) || ui.is_key_pressed(imgui::Key::F2);
+            });
+        if toggle_coop {
+            match self.views.len() < MAX_LOCAL_PLAYERS {
+                true => self.join_local_player(),
+                false => self.leave_local_player(),
+            }
+        }
         self.world.borrow_mut().render_ui(ui);
         ui.window("Fog")
             .size([300.0, 150.0], imgui::Condition::FirstUseEver)
@@ -256,66 +1181,90 @@ impl GuiScene for GameScene {
             });
     }
 
-    fn render(&mut self, gl: &glow::Context, _dt: Duration) {
-        // Prepare rendering
-        unsafe {
-            gl.enable(gl::CULL_FACE);
-            gl.enable(gl::DEPTH_TEST);
-            gl.depth_func(gl::LESS); // Default: Pass if the incoming depth is less than the stored depth
-            gl.cull_face(gl::BACK);
-            gl.front_face(gl::CCW);
-        }
+    fn render_state(&self) -> RenderState {
+        RenderState::default().with_clear_color(0.0, 0.411, 0.58)
+    }
 
-        // 1. Main render pass
-        unsafe {
-            gl.bind_framebuffer(gl::FRAMEBUFFER, Some(self.geometry_fbo));
-            gl.clear_color(0.0, 0.411, 0.58, 1.0);
-            gl.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    fn render(&mut self, gl: &glow::Context, _dt: Duration) -> Result<(), Box<dyn Error>> {
+        self.voxel_renderer
+            .remesh_chunks(&self.world.borrow(), &self.remesh_changes.edited_chunks());
+        self.voxel_renderer.set_wetness(self.weather.wetness());
+        self.voxel_renderer.set_wind_sway(self.weather.wind_sway());
+        let time_elapsed = self.context.borrow().start_time.elapsed().as_secs_f32();
+        self.ecs_renderer.gather(&self.ecs);
+        self.effects_renderer.begin_frame();
+        self.debug_view.update_frozen_camera(&self.views[0].camera);
+        // Texture cameras first, so monitors show the current frame. Without post-processing
+        {
+            let world = self.world.borrow();
+            let voxel_renderer = &mut self.voxel_renderer;
+            self.ecs_renderer
+                .render_texture_cameras(&self.ecs, time_elapsed, |cam, viewport| {
+                    voxel_renderer.render(cam, viewport, &world);
+                });
         }
-        let cam = self.camera.borrow();
-        self.voxel_renderer.render(&cam, &self.world.borrow());
-        self.ecs_renderer.render_camera(
-            &self.ecs,
-            &cam,
-            self.context.borrow().start_time.elapsed().as_secs_f32(),
-        );
+        for (index, viewport) in self.viewports().into_iter().enumerate() {
+            self.render_view(gl, index, viewport, time_elapsed)?;
+        }
+        if self.debug_view.is_enabled() {
+            self.render_debug_view(gl, time_elapsed)?;
+        }
+        Ok(())
+    }
 
-        // 2. Render pass for post-processing
-        unsafe {
-            gl.bind_framebuffer(gl::FRAMEBUFFER, None);
-            gl.clear_color(0.0, 0.411, 0.58, 1.0);
-            gl.clear(gl::COLOR_BUFFER_BIT);
+    fn apply_render_settings(&mut self, settings: &RenderSettings) -> Result<(), Box<dyn Error>> {
+        self.accessibility = settings.accessibility.clone();
+        self.update_projections();
+        self.render_size = settings.render_size();
+        self.msaa_samples = settings.msaa_samples;
+        self.render_distance = settings.render_distance;
+        self.voxel_renderer
+            .set_render_distance(settings.render_distance);
+        self.voxel_renderer.set_features(&settings.features);
+        self.ecs_renderer.set_features(&settings.features);
+        for (feature, enabled) in settings.features.changed_since(&self.features) {
+            match feature {
+                Feature::Ssao => self.ssao_pass.enabled = enabled,
+                Feature::Bloom => self.bloom_pass.enabled = enabled,
+                Feature::FrustumCulling
+                | Feature::MultiDrawIndirect
+                | Feature::MemoryTracking
+                | Feature::Impostors => {}
+            }
+        }
+        self.features = settings.features.clone();
+        self.resize_geometry_buffer()
+    }
 
-            // Wireframe mode
-            //gl.polygon_mode(gl::FRONT_AND_BACK, gl::LINE);
+    fn diagnostics(&self) -> SceneDiagnostics {
+        let world = self.world.borrow();
+        let generating = world.generation_progress().map_or(0, |generation| {
+            generation.batch_size.saturating_sub(generation.generated())
+        });
+        SceneDiagnostics {
+            system_timings: self.system_timings.clone(),
+            queues: vec![
+                ("Chunks generating", generating),
+                ("Chunks loaded", world.get_size()),
+                ("Remesh pending", self.voxel_renderer.pending_remesh()),
+                ("Light outdated", self.light_baker.outdated_chunks()),
+            ],
+            entities: Some(self.ecs.len() as usize),
         }
-        let shader = &mut self.post_process_quad.shader;
-        shader.use_program();
-        // Sync uniforms with UI controls
-        shader.set_uniform_f32("min_fog_distance", self.min_fog_distance);
-        // Calculate fog density at cpu to avoid per fragment
-        const LN_0_01: f32 = -4.605_170_2;
-        shader.set_uniform_f32(
-            "fog_density",
-            LN_0_01 / (self.max_fog_distance - self.min_fog_distance),
-        );
+    }
 
-        let vao = self.post_process_quad.vao;
-        let count = self.post_process_quad.vertex_count;
-        unsafe {
-            gl.disable(gl::DEPTH_TEST);
-            gl.bind_vertex_array(Some(vao));
-            // Bind first pass color texture
-            gl.active_texture(gl::TEXTURE0);
-            gl.bind_texture(gl::TEXTURE_2D, Some(self.first_pass_texture));
-            // Bind first pass depth texture
-            gl.active_texture(gl::TEXTURE1);
-            gl.bind_texture(gl::TEXTURE_2D, Some(self.first_pass_depth_texture));
-            gl.draw_elements(glow::TRIANGLES, count, gl::UNSIGNED_INT, 0);
-            gl.bind_vertex_array(None);
+    fn overlay_stats(&self) -> OverlayStats {
+        OverlayStats {
+            chunks: Some(self.voxel_renderer.visible_chunks()),
+            position: Some(self.views[0].camera.position),
         }
     }
 
+    fn reload_assets(&mut self, changed: &[PathBuf]) {
+        self.ecs_renderer.reload_assets(changed);
+        self.voxel_renderer.reload_assets(changed);
+    }
+
     fn get_stats(&self) -> crate::scenes::SceneStats {
         todo!()
     }
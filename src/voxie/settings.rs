@@ -0,0 +1,56 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::hints::Hint;
+
+/// Player preferences kept across sessions. Written next to the save game
+pub const DEFAULT_SETTINGS_PATH: &str = "voxie.settings.json";
+
+/// Settings of the game scene persisted as json
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GameSettings {
+    /// Hints the player already followed. Never shown again
+    #[serde(default)]
+    pub completed_hints: BTreeSet<Hint>,
+}
+
+impl GameSettings {
+    /// Reads settings from a json file. Defaults if the file does not exist yet
+    pub fn load(path: &Path) -> Result<GameSettings, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let path = std::env::temp_dir().join("voxie_test_settings.json");
+        let _ = fs::remove_file(&path);
+        assert_eq!(GameSettings::load(&path).unwrap(), GameSettings::default());
+
+        let mut settings = GameSettings::default();
+        settings.completed_hints.insert(Hint::Fire);
+        settings.save(&path).unwrap();
+        assert_eq!(GameSettings::load(&path).unwrap(), settings);
+        fs::remove_file(&path).unwrap();
+    }
+}
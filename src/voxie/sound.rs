@@ -0,0 +1,136 @@
+use glam::Vec3;
+
+use crate::voxels::{VoxelKind, VoxelWorld};
+
+/// Rays cast besides the direct one, offset sideways & up/down by this distance, so sound leaks
+/// around corners & through openings
+const RAY_SPREAD: f32 = 1.0;
+/// Distance between the samples along a ray. Below the voxel size, so no wall is skipped
+const RAY_STEP: f32 = 0.25;
+/// Solid voxels along a ray that block it completely
+const BLOCKING_THICKNESS: f32 = 3.0;
+/// Share of the volume lost behind a complete wall
+const MAX_ATTENUATION: f32 = 0.85;
+const OPEN_CUTOFF_HZ: f32 = 22_000.0;
+const OCCLUDED_CUTOFF_HZ: f32 = 600.0;
+/// Sounds further away are too quiet to be worth the raycasts
+const MAX_DISTANCE: f32 = 64.0;
+
+/// How a sound reaches the listener through the voxel geometry in between
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoundOcclusion {
+    /// Volume factor from 0 to 1
+    pub gain: f32,
+    /// Frequencies above are filtered out, muffling sounds behind walls
+    pub low_pass_hz: f32,
+}
+
+impl SoundOcclusion {
+    pub const OPEN: SoundOcclusion = SoundOcclusion {
+        gain: 1.0,
+        low_pass_hz: OPEN_CUTOFF_HZ,
+    };
+
+    // Occlusion from 0 for open air to 1 behind thick walls
+    fn from_occlusion(occlusion: f32) -> SoundOcclusion {
+        let occlusion = occlusion.clamp(0.0, 1.0);
+        Self {
+            gain: 1.0 - occlusion * MAX_ATTENUATION,
+            // Interpolated in octaves, which is how pitch is perceived
+            low_pass_hz: OPEN_CUTOFF_HZ * (OCCLUDED_CUTOFF_HZ / OPEN_CUTOFF_HZ).powf(occlusion),
+        }
+    }
+}
+
+/// Occlusion of a sound at source heard at listener. Casts a few rays between both & averages
+/// how much each is blocked. Unloaded chunks count as open air
+pub fn sound_occlusion(world: &VoxelWorld, source: Vec3, listener: Vec3) -> SoundOcclusion {
+    let offset = listener - source;
+    let distance = offset.length();
+    if !(1.0..=MAX_DISTANCE).contains(&distance) {
+        return SoundOcclusion::OPEN;
+    }
+    let direction = offset / distance;
+    let side = direction.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X);
+    let up = side.cross(direction);
+    let spreads = [
+        Vec3::ZERO,
+        side * RAY_SPREAD,
+        -side * RAY_SPREAD,
+        up * RAY_SPREAD,
+        -up * RAY_SPREAD,
+    ];
+    let occlusion = spreads
+        .iter()
+        .map(|spread| {
+            let blocking = solid_cells_between(world, source + *spread, listener + *spread);
+            (blocking as f32 / BLOCKING_THICKNESS).min(1.0)
+        })
+        .sum::<f32>()
+        / spreads.len() as f32;
+    SoundOcclusion::from_occlusion(occlusion)
+}
+
+/// Loudest occlusion of the sound among all listeners, e.g. the players of a split screen
+pub fn loudest_occlusion(world: &VoxelWorld, source: Vec3, listeners: &[Vec3]) -> SoundOcclusion {
+    listeners
+        .iter()
+        .map(|listener| sound_occlusion(world, source, *listener))
+        .max_by(|a, b| a.gain.total_cmp(&b.gain))
+        .unwrap_or(SoundOcclusion::OPEN)
+}
+
+// Distinct solid voxels the segment passes through. The cells of both ends are skipped, sources
+// often sit right on the ground
+fn solid_cells_between(world: &VoxelWorld, from: Vec3, to: Vec3) -> usize {
+    let mut lookup = world.lookup();
+    let first = from.round().as_ivec3();
+    let last = to.round().as_ivec3();
+    let steps = (from.distance(to) / RAY_STEP).ceil() as usize;
+    let mut previous = first;
+    let mut solid = 0;
+    for step in 1..steps {
+        let cell = from.lerp(to, step as f32 / steps as f32).round().as_ivec3();
+        if cell == previous || cell == first || cell == last {
+            continue;
+        }
+        previous = cell;
+        if lookup
+            .kind(cell)
+            .is_some_and(|kind| !matches!(kind, VoxelKind::Air))
+        {
+            solid += 1;
+        }
+    }
+    solid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walls_muffle_sound() {
+        // Cube of solid voxels from 0 to 31
+        let world = VoxelWorld::new_cubic(2);
+        let outside = Vec3::new(-4.0, 20.0, 10.0);
+
+        let open = sound_occlusion(&world, outside, Vec3::new(-4.0, 20.0, 20.0));
+        assert_eq!(open, SoundOcclusion::OPEN);
+
+        let buried = sound_occlusion(&world, outside, Vec3::new(10.0, 20.0, 10.0));
+        assert!(buried.gain < 0.2, "{buried:?}");
+        assert!(buried.low_pass_hz < 1000.0, "{buried:?}");
+
+        // Only grazing the edge of the cube is muffled less
+        let edge = sound_occlusion(&world, outside, Vec3::new(0.5, 33.0, 10.0));
+        assert!(edge.gain > buried.gain, "{edge:?}");
+        assert!(edge.gain < 1.0, "{edge:?}");
+
+        let listeners = [Vec3::new(10.0, 20.0, 10.0), Vec3::new(-4.0, 20.0, 20.0)];
+        assert_eq!(
+            loudest_occlusion(&world, outside, &listeners),
+            SoundOcclusion::OPEN
+        );
+    }
+}
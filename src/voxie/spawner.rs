@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3};
+use hecs::{Entity, NoSuchEntity, World};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    systems::{
+        names::{EntityRegistry, PLAYER},
+        physics::Transform,
+        serialization::{ComponentRegistry, entity_bits, remap_entity},
+    },
+    voxels::navigation::NavGraph,
+};
+
+use super::enemy::spawn_enemy;
+
+/// Entity types spawners can produce
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SpawnKind {
+    Enemy,
+}
+
+impl SpawnKind {
+    pub fn spawn(self, world: &mut World, position: Vec3) -> Entity {
+        match self {
+            SpawnKind::Enemy => spawn_enemy(world, position),
+        }
+    }
+}
+
+/// Periodically spawns entities at its Transform while the player is close
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Spawner {
+    pub kind: SpawnKind,
+    /// Seconds between spawns
+    pub interval: f32,
+    /// No spawns while this many spawned entities are alive
+    pub max_alive: usize,
+    /// Only spawns while the player is within this distance
+    pub activation_radius: f32,
+    // Seconds until the next spawn
+    pub cooldown: f32,
+}
+
+impl Spawner {
+    pub fn new(
+        kind: SpawnKind,
+        interval: f32,
+        max_alive: usize,
+        activation_radius: f32,
+    ) -> Spawner {
+        Self {
+            kind,
+            interval,
+            max_alive,
+            activation_radius,
+            cooldown: 0.0,
+        }
+    }
+}
+
+/// Links spawned entities to their spawner to limit the alive count
+#[derive(Serialize, Deserialize)]
+pub struct SpawnedBy(#[serde(with = "entity_bits")] pub Entity);
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<Spawner>("Spawner");
+    registry.register_mapped::<SpawnedBy>("SpawnedBy", |spawned_by, map| {
+        remap_entity(&mut spawned_by.0, map)
+    });
+}
+
+pub fn spawn_spawner(world: &mut World, position: Vec3, spawner: Spawner) -> Entity {
+    world.spawn((spawner, Transform(Mat4::from_translation(position))))
+}
+
+/// Removes the spawner. Entities it spawned stay alive, but no longer reference it
+pub fn despawn_spawner(world: &mut World, spawner: Entity) {
+    let spawned: Vec<Entity> = world
+        .query::<&SpawnedBy>()
+        .iter()
+        .filter(|(_entity, spawned_by)| spawned_by.0 == spawner)
+        .map(|(entity, _)| entity)
+        .collect();
+    for entity in spawned {
+        let _ = world.remove_one::<SpawnedBy>(entity);
+    }
+    let _ = world.despawn(spawner);
+}
+
+/// Spawns entities of active spawners. Spawned entities are placed on walkable ground below the
+/// spawner if there is any
+pub fn system_spawners(
+    world: &mut World,
+    entities: &EntityRegistry,
+    nav_graph: &NavGraph,
+    dt: f32,
+) -> Result<(), NoSuchEntity> {
+    let Some(player_position) = entities.position(world, PLAYER) else {
+        return Ok(());
+    };
+    let mut alive: HashMap<Entity, usize> = HashMap::new();
+    for (_entity, spawned_by) in world.query::<&SpawnedBy>().iter() {
+        *alive.entry(spawned_by.0).or_default() += 1;
+    }
+
+    let mut requests = Vec::new();
+    for (entity, (spawner, transform)) in world.query_mut::<(&mut Spawner, &Transform)>() {
+        spawner.cooldown = (spawner.cooldown - dt).max(0.0);
+        let position = transform.0.w_axis.truncate();
+        let is_active = position.distance(player_position) <= spawner.activation_radius;
+        let is_full = alive.get(&entity).copied().unwrap_or_default() >= spawner.max_alive;
+        if spawner.cooldown > 0.0 || !is_active || is_full {
+            continue;
+        }
+        spawner.cooldown = spawner.interval;
+        let position = nav_graph
+            .nearest_node(position)
+            .map_or(position, |cell| cell.as_vec3());
+        requests.push((entity, spawner.kind, position));
+    }
+    for (spawner, kind, position) in requests {
+        debug!("Spawner {spawner:?} spawning {kind:?} at {position}");
+        let spawned = kind.spawn(world, position);
+        world.insert_one(spawned, SpawnedBy(spawner))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{systems::names::Name, voxie::player::Player};
+
+    use super::*;
+
+    fn spawned_count(world: &World, spawner: Entity) -> usize {
+        world
+            .query::<&SpawnedBy>()
+            .iter()
+            .filter(|(_entity, spawned_by)| spawned_by.0 == spawner)
+            .count()
+    }
+
+    #[test]
+    fn test_spawner_interval_and_max_alive() {
+        let nav_graph = NavGraph::new();
+        let mut world = World::new();
+        world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        let spawner = spawn_spawner(
+            &mut world,
+            Vec3::X * 5.0,
+            Spawner::new(SpawnKind::Enemy, 1.0, 2, 10.0),
+        );
+        let entities = EntityRegistry::from_world(&world);
+
+        // First spawn is immediate, next one after the interval
+        system_spawners(&mut world, &entities, &nav_graph, 0.5).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 1);
+        system_spawners(&mut world, &entities, &nav_graph, 0.5).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 1);
+        system_spawners(&mut world, &entities, &nav_graph, 0.5).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 2);
+        for _ in 0..5 {
+            system_spawners(&mut world, &entities, &nav_graph, 1.0).unwrap();
+        }
+        assert_eq!(spawned_count(&world, spawner), 2);
+
+        // Killing one frees a slot
+        let (killed, _) = world.query::<&SpawnedBy>().iter().next().unwrap();
+        world.despawn(killed).unwrap();
+        system_spawners(&mut world, &entities, &nav_graph, 1.0).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 2);
+
+        // Despawned spawner leaves its entities alive
+        despawn_spawner(&mut world, spawner);
+        assert_eq!(spawned_count(&world, spawner), 0);
+        assert_eq!(world.query::<&Transform>().iter().count(), 3);
+    }
+
+    #[test]
+    fn test_spawner_activation_radius() {
+        let nav_graph = NavGraph::new();
+        let mut world = World::new();
+        let player = world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        let spawner = spawn_spawner(
+            &mut world,
+            Vec3::X * 20.0,
+            Spawner::new(SpawnKind::Enemy, 1.0, 2, 10.0),
+        );
+        let entities = EntityRegistry::from_world(&world);
+        system_spawners(&mut world, &entities, &nav_graph, 1.0).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 0);
+
+        world.get::<&mut Transform>(player).unwrap().0 = Mat4::from_translation(Vec3::X * 12.0);
+        system_spawners(&mut world, &entities, &nav_graph, 1.0).unwrap();
+        assert_eq!(spawned_count(&world, spawner), 1);
+    }
+}
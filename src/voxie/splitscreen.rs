@@ -0,0 +1,120 @@
+use glam::Vec3;
+use hecs::{Entity, World};
+
+use crate::{
+    cameras::{
+        camera::{Camera, CameraController},
+        fpscam::FirstPersonCam,
+        thirdpersoncam::ThirdPersonCam,
+    },
+    systems::{
+        names::{Name, PLAYER},
+        physics::{Parent, hierarchy_cache::find_descendants},
+    },
+};
+
+use super::player::{InputMapping, local_players, squid::spawn_squid};
+
+/// Local players sharing the screen
+pub const MAX_LOCAL_PLAYERS: usize = 2;
+// Joining players spawn next to the first player
+const JOIN_OFFSET: Vec3 = Vec3::new(2.0, 0.0, 0.0);
+
+/// Camera following a single local player. Splitscreen renders one view per player
+pub struct PlayerView {
+    pub player: Entity,
+    pub camera: Camera,
+    pub camera_controller: Box<dyn CameraController>,
+    /// 1 while the camera sees the sky, eased towards 0 underground
+    pub outdoors: f32,
+}
+
+impl PlayerView {
+    pub fn new(player: Entity, first_person: bool) -> PlayerView {
+        Self {
+            player,
+            camera: Camera::new(),
+            camera_controller: camera_controller(first_person),
+            outdoors: 1.0,
+        }
+    }
+}
+
+pub fn camera_controller(first_person: bool) -> Box<dyn CameraController> {
+    match first_person {
+        true => Box::new(FirstPersonCam::new()),
+        false => Box::new(ThirdPersonCam::new()),
+    }
+}
+
+/// One view per local player. Views of remaining players are kept, so their cameras do not jump
+pub fn sync_views(world: &World, views: Vec<PlayerView>, first_person: bool) -> Vec<PlayerView> {
+    let mut views = views;
+    local_players(world)
+        .into_iter()
+        .map(
+            |player| match views.iter().position(|view| view.player == player) {
+                Some(index) => views.swap_remove(index),
+                None => PlayerView::new(player, first_person),
+            },
+        )
+        .collect()
+}
+
+/// Spawns another local player controlled by the arrow keys next to the given position. None if
+/// the screen is already shared by the max amount of players
+pub fn join_local_player(world: &mut World, position: Vec3) -> Option<Entity> {
+    if local_players(world).len() >= MAX_LOCAL_PLAYERS {
+        return None;
+    }
+    let name = format!("{PLAYER} {}", local_players(world).len() + 1);
+    let player = spawn_squid(world, position + JOIN_OFFSET);
+    let _ = world.insert(player, (InputMapping::ArrowKeys, Name(name)));
+    Some(player)
+}
+
+/// Removes the most recently joined player including its model & colliders. The first player
+/// always stays
+pub fn leave_local_player(world: &mut World) -> Option<Entity> {
+    let players = local_players(world);
+    if players.len() < 2 {
+        return None;
+    }
+    let player = *players.last()?;
+    for entity in find_descendants::<&Parent>(world, player) {
+        let _ = world.despawn(entity);
+    }
+    let _ = world.despawn(player);
+    Some(player)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_and_leave() {
+        let mut world = World::new();
+        let first = spawn_squid(&mut world, Vec3::ZERO);
+        let entities = world.len();
+        let views = sync_views(&world, Vec::new(), false);
+        assert_eq!(views.len(), 1);
+
+        let second = join_local_player(&mut world, Vec3::ZERO).unwrap();
+        assert_eq!(
+            *world.get::<&InputMapping>(second).unwrap(),
+            InputMapping::ArrowKeys
+        );
+        assert_eq!(world.get::<&Name>(second).unwrap().0, "player 2");
+        assert!(join_local_player(&mut world, Vec3::ZERO).is_none());
+        let views = sync_views(&world, views, false);
+        let players: Vec<Entity> = views.iter().map(|view| view.player).collect();
+        assert_eq!(players, vec![first, second]);
+
+        assert_eq!(leave_local_player(&mut world), Some(second));
+        assert_eq!(leave_local_player(&mut world), None);
+        // Model & pivot of the second player are gone as well
+        assert_eq!(world.len(), entities);
+        assert_eq!(sync_views(&world, views, false).len(), 1);
+    }
+}
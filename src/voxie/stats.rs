@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions, create_dir_all},
+    io::{BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use glam::{Vec3, Vec4Swizzles};
+use hecs::{Entity, World};
+use imgui::Ui;
+
+use crate::systems::{
+    names::{Name, PLAYER},
+    physics::Transform,
+};
+
+use super::{events::GameEvent, player::Player};
+
+/// Session statistics are appended to this file when leaving the game
+pub const DEFAULT_STATS_PATH: &str = "output/session_stats.csv";
+/// Moves further than this within a tick are teleports & do not count as traveled
+const MAX_STEP_DISTANCE: f32 = 10.0;
+
+/// Statistics of a single player within the session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerStats {
+    pub shots_fired: u32,
+    pub deaths: u32,
+    pub distance_traveled: f32,
+}
+
+/// Counters of the current session. Shown on the scoreboard & exported once the session ends
+#[derive(Default)]
+pub struct SessionStats {
+    /// Seconds played
+    pub duration: f32,
+    /// Projectiles that hit the world
+    pub hits: u32,
+    pub blocks_mined: usize,
+    // In order of appearance
+    players: Vec<(String, PlayerStats)>,
+    // Player positions of the last tick
+    last_positions: HashMap<Entity, Vec3>,
+}
+
+impl SessionStats {
+    pub fn new() -> SessionStats {
+        Self::default()
+    }
+
+    pub fn player(&mut self, name: &str) -> &mut PlayerStats {
+        let index = match self.players.iter().position(|(player, _)| player == name) {
+            Some(index) => index,
+            None => {
+                self.players
+                    .push((name.to_string(), PlayerStats::default()));
+                self.players.len() - 1
+            }
+        };
+        &mut self.players[index].1
+    }
+
+    pub fn players(&self) -> &[(String, PlayerStats)] {
+        &self.players
+    }
+
+    /// Sum over all players
+    pub fn totals(&self) -> PlayerStats {
+        self.players
+            .iter()
+            .fold(PlayerStats::default(), |total, (_, stats)| PlayerStats {
+                shots_fired: total.shots_fired + stats.shots_fired,
+                deaths: total.deaths + stats.deaths,
+                distance_traveled: total.distance_traveled + stats.distance_traveled,
+            })
+    }
+
+    /// Share of the shots that hit something, None before the first shot
+    pub fn accuracy(&self) -> Option<f32> {
+        let shots = self.totals().shots_fired;
+        (shots > 0).then(|| self.hits as f32 / shots as f32)
+    }
+
+    /// Picks the counters of the session out of the events of a tick
+    pub fn observe(&mut self, events: &[GameEvent]) {
+        for event in events {
+            match event {
+                GameEvent::Died { name } => self.player(name).deaths += 1,
+                GameEvent::Destroyed { name, .. } if name == "Projectile" => self.hits += 1,
+                GameEvent::BlocksMined { count } => self.blocks_mined += count,
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds the distance every player moved since the last tick & the tick to the duration
+    pub fn track_players(&mut self, world: &World, dt: f32) {
+        self.duration += dt;
+        let mut positions = HashMap::new();
+        for (entity, (_player, transform, name)) in
+            world.query::<(&Player, &Transform, Option<&Name>)>().iter()
+        {
+            let position = transform.0.w_axis.xyz();
+            positions.insert(entity, position);
+            let Some(last) = self.last_positions.get(&entity) else {
+                continue;
+            };
+            let step = position.distance(*last);
+            if step <= MAX_STEP_DISTANCE {
+                let name = name.map_or(PLAYER, |name| name.0.as_str());
+                self.player(name).distance_traveled += step;
+            }
+        }
+        self.last_positions = positions;
+    }
+
+    /// Appends the totals of the session as a row to the CSV file, writing a header first if the
+    /// file is new
+    pub fn export_csv(&self, path: &Path) -> Result<(), std::io::Error> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        if !path.exists() {
+            let mut file = File::create(path)?;
+            writeln!(
+                file,
+                "SessionEnd,DurationSeconds,Players,ShotsFired,Hits,BlocksMined,DistanceTraveled,Deaths"
+            )?;
+        }
+        let file = OpenOptions::new().append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        let end = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let totals = self.totals();
+        writeln!(
+            writer,
+            "{end},{:.1},{},{},{},{},{:.1},{}",
+            self.duration,
+            self.players.len(),
+            totals.shots_fired,
+            self.hits,
+            self.blocks_mined,
+            totals.distance_traveled,
+            totals.deaths
+        )?;
+        Ok(())
+    }
+
+    /// Centered table of all players, e.g. while Tab is held
+    pub fn render_scoreboard(&self, ui: &Ui) {
+        let [width, height] = ui.io().display_size;
+        ui.window("Scoreboard")
+            .position([width / 2.0, height / 3.0], imgui::Condition::Always)
+            .position_pivot([0.5, 0.5])
+            // Columns span the window, so only the height fits the content
+            .size([360.0, 0.0], imgui::Condition::Always)
+            .no_decoration()
+            .no_inputs()
+            .build(|| {
+                let minutes = (self.duration / 60.0).floor();
+                let seconds = self.duration - minutes * 60.0;
+                ui.text(format!("Session {minutes:.0}:{seconds:02.0}"));
+                ui.separator();
+                ui.columns(4, "Players", false);
+                for header in ["Player", "Shots", "Deaths", "Distance"] {
+                    ui.text(header);
+                    ui.next_column();
+                }
+                for (name, stats) in &self.players {
+                    ui.text(name);
+                    ui.next_column();
+                    ui.text(stats.shots_fired.to_string());
+                    ui.next_column();
+                    ui.text(stats.deaths.to_string());
+                    ui.next_column();
+                    ui.text(format!("{:.0}", stats.distance_traveled));
+                    ui.next_column();
+                }
+                ui.columns(1, "Players", false);
+                ui.separator();
+                let accuracy = self.accuracy().map_or("-".to_string(), |accuracy| {
+                    format!("{:.0}%", accuracy * 100.0)
+                });
+                ui.text(format!("Hits: {} ({accuracy})", self.hits));
+                ui.text(format!("Blocks mined: {}", self.blocks_mined));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+
+    #[test]
+    fn test_stats_from_events_and_movement() {
+        let mut stats = SessionStats::new();
+        let mut world = World::new();
+        let player = world.spawn((Player, Transform(Mat4::IDENTITY), Name::new(PLAYER)));
+        stats.track_players(&world, 0.5);
+        world.get::<&mut Transform>(player).unwrap().0 = Mat4::from_translation(Vec3::X * 3.0);
+        stats.track_players(&world, 0.5);
+        // Teleport
+        world.get::<&mut Transform>(player).unwrap().0 = Mat4::from_translation(Vec3::X * 100.0);
+        stats.track_players(&world, 0.5);
+
+        stats.player(PLAYER).shots_fired += 4;
+        stats.observe(&[
+            GameEvent::Died {
+                name: PLAYER.to_string(),
+            },
+            GameEvent::Destroyed {
+                name: "Projectile".to_string(),
+                position: Vec3::ZERO,
+            },
+            GameEvent::Destroyed {
+                name: "Grenade".to_string(),
+                position: Vec3::ZERO,
+            },
+            GameEvent::BlocksMined { count: 7 },
+        ]);
+        assert_eq!(
+            stats.totals(),
+            PlayerStats {
+                shots_fired: 4,
+                deaths: 1,
+                distance_traveled: 3.0
+            }
+        );
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.accuracy(), Some(0.25));
+        assert_eq!(stats.blocks_mined, 7);
+        assert_eq!(stats.duration, 1.5);
+    }
+
+    #[test]
+    fn test_export_appends_rows() {
+        let path = std::env::temp_dir().join("voxie_test_session_stats.csv");
+        let _ = std::fs::remove_file(&path);
+        let mut stats = SessionStats::new();
+        stats.player(PLAYER).shots_fired = 2;
+        stats.export_csv(&path).unwrap();
+        stats.export_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("SessionEnd,"));
+        assert!(lines[1].ends_with(",0.0,1,2,0,0,0.0,0"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
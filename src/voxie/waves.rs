@@ -0,0 +1,272 @@
+use glam::Vec3;
+use hecs::{Entity, NoSuchEntity, World};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::systems::{
+    names::{EntityRegistry, PLAYER},
+    serialization::ComponentRegistry,
+};
+
+use super::spawner::{SpawnKind, Spawner, despawn_spawner, spawn_spawner};
+
+/// Spawner placed relative to the player when its wave starts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpawnerPlacement {
+    pub offset: Vec3,
+    pub spawner: Spawner,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Wave {
+    /// Seconds after the director started
+    pub start: f32,
+    pub spawners: Vec<SpawnerPlacement>,
+}
+
+/// Repeats the last scripted wave with increasing difficulty
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DifficultyRamp {
+    /// Seconds between ramped waves
+    pub wave_interval: f32,
+    /// Added to max alive count of every spawner per ramped wave
+    pub max_alive_step: usize,
+    /// Spawn intervals are multiplied by this per ramped wave
+    pub interval_factor: f32,
+    pub min_interval: f32,
+}
+
+/// Encounter script: Scripted waves, optionally followed by endlessly ramped ones
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WavePlan {
+    pub waves: Vec<Wave>,
+    pub ramp: Option<DifficultyRamp>,
+}
+
+impl WavePlan {
+    /// Ramped waves continue after the scripted ones
+    pub fn wave(&self, index: usize) -> Option<Wave> {
+        if let Some(wave) = self.waves.get(index) {
+            return Some(wave.clone());
+        }
+        let ramp = self.ramp.as_ref()?;
+        let last = self.waves.last()?;
+        let step = (index + 1 - self.waves.len()) as i32;
+        let spawners = last
+            .spawners
+            .iter()
+            .map(|placement| {
+                let mut spawner = placement.spawner.clone();
+                spawner.max_alive += ramp.max_alive_step * step as usize;
+                spawner.interval =
+                    (spawner.interval * ramp.interval_factor.powi(step)).max(ramp.min_interval);
+                SpawnerPlacement {
+                    offset: placement.offset,
+                    spawner,
+                }
+            })
+            .collect();
+        Some(Wave {
+            start: last.start + ramp.wave_interval * step as f32,
+            spawners,
+        })
+    }
+}
+
+impl Default for WavePlan {
+    /// Enemies closing in from two, then four sides
+    fn default() -> Self {
+        let enemies = |offset: Vec3, max_alive: usize| SpawnerPlacement {
+            offset,
+            spawner: Spawner::new(SpawnKind::Enemy, 5.0, max_alive, 80.0),
+        };
+        Self {
+            waves: vec![
+                Wave {
+                    start: 10.0,
+                    spawners: vec![
+                        enemies(Vec3::new(30.0, 0.0, 0.0), 1),
+                        enemies(Vec3::new(-30.0, 0.0, 0.0), 1),
+                    ],
+                },
+                Wave {
+                    start: 70.0,
+                    spawners: vec![
+                        enemies(Vec3::new(30.0, 0.0, 0.0), 2),
+                        enemies(Vec3::new(-30.0, 0.0, 0.0), 2),
+                        enemies(Vec3::new(0.0, 0.0, 30.0), 2),
+                        enemies(Vec3::new(0.0, 0.0, -30.0), 2),
+                    ],
+                },
+            ],
+            ramp: Some(DifficultyRamp {
+                wave_interval: 60.0,
+                max_alive_step: 1,
+                interval_factor: 0.85,
+                min_interval: 1.0,
+            }),
+        }
+    }
+}
+
+/// Runs a wave plan. Spawners of the previous wave are removed when the next one starts
+#[derive(Serialize, Deserialize)]
+pub struct WaveDirector {
+    pub plan: WavePlan,
+    pub elapsed: f32,
+    /// Index of the next wave to start
+    pub next_wave: usize,
+}
+
+/// Marks spawners placed by the wave director
+#[derive(Serialize, Deserialize)]
+pub struct WaveSpawner;
+
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<WaveDirector>("WaveDirector");
+    registry.register::<WaveSpawner>("WaveSpawner");
+}
+
+pub fn spawn_wave_director(world: &mut World, plan: WavePlan) -> Entity {
+    world.spawn((WaveDirector {
+        plan,
+        elapsed: 0.0,
+        next_wave: 0,
+    },))
+}
+
+pub fn system_wave_director(
+    world: &mut World,
+    entities: &EntityRegistry,
+    dt: f32,
+) -> Result<(), NoSuchEntity> {
+    let Some(player_position) = entities.position(world, PLAYER) else {
+        return Ok(());
+    };
+    let mut started = Vec::new();
+    for (_entity, director) in world.query_mut::<&mut WaveDirector>() {
+        director.elapsed += dt;
+        while let Some(wave) = director
+            .plan
+            .wave(director.next_wave)
+            .filter(|wave| wave.start <= director.elapsed)
+        {
+            started.push((director.next_wave, wave));
+            director.next_wave += 1;
+        }
+    }
+    for (index, wave) in started {
+        info!("Starting wave {}", index + 1);
+        let previous: Vec<Entity> = world
+            .query::<&WaveSpawner>()
+            .iter()
+            .map(|(entity, _)| entity)
+            .collect();
+        for spawner in previous {
+            despawn_spawner(world, spawner);
+        }
+        for placement in wave.spawners {
+            let spawner =
+                spawn_spawner(world, player_position + placement.offset, placement.spawner);
+            world.insert_one(spawner, WaveSpawner)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn render_wave_ui(world: &mut World, ui: &mut imgui::Ui) {
+    for (_entity, director) in world.query_mut::<&WaveDirector>() {
+        ui.window("Waves")
+            .size([300.0, 80.0], imgui::Condition::FirstUseEver)
+            .position([600.0, 150.0], imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!("Wave: {}", director.next_wave));
+                if let Some(next) = director.plan.wave(director.next_wave) {
+                    ui.text(format!(
+                        "Next wave in: {:.0}s",
+                        next.start - director.elapsed
+                    ));
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use crate::{
+        systems::{names::Name, physics::Transform},
+        voxels::navigation::NavGraph,
+        voxie::{enemy::Enemy, player::Player, spawner::system_spawners},
+    };
+
+    use super::*;
+
+    fn test_plan() -> WavePlan {
+        let placement = |max_alive| SpawnerPlacement {
+            offset: Vec3::X * 5.0,
+            spawner: Spawner::new(SpawnKind::Enemy, 2.0, max_alive, 10.0),
+        };
+        WavePlan {
+            waves: vec![
+                Wave {
+                    start: 1.0,
+                    spawners: vec![placement(1)],
+                },
+                Wave {
+                    start: 3.0,
+                    spawners: vec![placement(2), placement(2)],
+                },
+            ],
+            ramp: Some(DifficultyRamp {
+                wave_interval: 10.0,
+                max_alive_step: 1,
+                interval_factor: 0.5,
+                min_interval: 0.75,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_ramped_waves() {
+        let plan = test_plan();
+        let wave = plan.wave(2).unwrap();
+        assert_eq!(wave.start, 13.0);
+        assert_eq!(wave.spawners.len(), 2);
+        assert_eq!(wave.spawners[0].spawner.max_alive, 3);
+        assert_eq!(wave.spawners[0].spawner.interval, 1.0);
+        // Clamped to min interval
+        assert_eq!(plan.wave(4).unwrap().spawners[0].spawner.interval, 0.75);
+
+        let scripted = WavePlan {
+            ramp: None,
+            ..test_plan()
+        };
+        assert!(scripted.wave(2).is_none());
+    }
+
+    #[test]
+    fn test_director_replaces_spawners() {
+        let nav_graph = NavGraph::new();
+        let mut world = World::new();
+        world.spawn((Player, Name::new(PLAYER), Transform(Mat4::IDENTITY)));
+        spawn_wave_director(&mut world, test_plan());
+        let entities = EntityRegistry::from_world(&world);
+        let spawners = |world: &World| world.query::<&WaveSpawner>().iter().count();
+        let enemies = |world: &World| world.query::<&Enemy>().iter().count();
+
+        system_wave_director(&mut world, &entities, 0.5).unwrap();
+        assert_eq!(spawners(&world), 0);
+        system_wave_director(&mut world, &entities, 0.5).unwrap();
+        system_spawners(&mut world, &entities, &nav_graph, 0.5).unwrap();
+        assert_eq!(spawners(&world), 1);
+        assert_eq!(enemies(&world), 1);
+
+        // Enemies of the previous wave stay alive
+        system_wave_director(&mut world, &entities, 2.0).unwrap();
+        system_spawners(&mut world, &entities, &nav_graph, 0.5).unwrap();
+        assert_eq!(spawners(&world), 2);
+        assert_eq!(enemies(&world), 3);
+    }
+}
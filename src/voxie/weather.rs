@@ -0,0 +1,406 @@
+use glam::{Mat4, Quat, Vec3};
+use hecs::World;
+use imgui::Ui;
+
+use crate::{
+    renderer::{
+        RenderMeshHandle,
+        ecs_renderer::{MESH_CUBE, RenderColor},
+    },
+    systems::{
+        physics::{Transform, Velocity},
+        projectiles::Lifetime,
+    },
+    util::SeededRng,
+    voxels::heightmap::Heightmap,
+};
+
+// Rate at which precipitation fades in & out, per second
+const INTENSITY_SPEED: f32 = 0.1;
+// Seconds of full rain until surfaces are soaked & until they dried again
+const SOAK_TIME: f32 = 30.0;
+const DRY_TIME: f32 = 90.0;
+// Particles fall in a cylinder around the player
+const PARTICLE_RADIUS: f32 = 20.0;
+const PARTICLE_HEIGHT: f32 = 15.0;
+// Radians the wind direction wanders around its base angle & how fast
+const WIND_WANDER: f32 = 0.5;
+const WIND_WANDER_SPEED: f32 = 0.05;
+
+/// Precipitation of the current weather
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 3] = [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Snow];
+
+    /// Identifier used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Snow => "snow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<WeatherKind> {
+        WeatherKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    // Particles spawned per second at full intensity
+    fn particle_rate(self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => 300.0,
+            WeatherKind::Snow => 120.0,
+        }
+    }
+
+    // Falling speed, size & color of the particles
+    fn particle(self) -> (f32, Vec3, Vec3) {
+        match self {
+            WeatherKind::Clear | WeatherKind::Rain => {
+                (18.0, Vec3::new(0.03, 0.4, 0.03), Vec3::new(0.55, 0.6, 0.7))
+            }
+            WeatherKind::Snow => (2.0, Vec3::splat(0.08), Vec3::splat(0.95)),
+        }
+    }
+
+    // Fog density at full intensity, relative to clear weather
+    fn fog_scale(self) -> f32 {
+        match self {
+            WeatherKind::Clear => 1.0,
+            WeatherKind::Rain => 2.0,
+            WeatherKind::Snow => 3.0,
+        }
+    }
+}
+
+/// Part of the repeating weather schedule
+#[derive(Debug, Clone, Copy)]
+pub struct WeatherPhase {
+    pub kind: WeatherKind,
+    /// Seconds
+    pub duration: f32,
+}
+
+/// Weather of a single cycle, repeated endlessly
+pub const DEFAULT_SCHEDULE: [WeatherPhase; 4] = [
+    WeatherPhase {
+        kind: WeatherKind::Clear,
+        duration: 240.0,
+    },
+    WeatherPhase {
+        kind: WeatherKind::Rain,
+        duration: 120.0,
+    },
+    WeatherPhase {
+        kind: WeatherKind::Clear,
+        duration: 180.0,
+    },
+    WeatherPhase {
+        kind: WeatherKind::Snow,
+        duration: 120.0,
+    },
+];
+
+/// Global wind, animated over time. Sways vegetation voxels
+#[derive(Debug, Clone)]
+pub struct Wind {
+    /// Direction the wind blows towards in the XZ plane, in radians
+    pub angle: f32,
+    /// Steady sway of vegetation tops in world units
+    pub strength: f32,
+    /// Additional sway at the peak of a gust
+    pub gust_strength: f32,
+    time: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            strength: 0.05,
+            gust_strength: 0.15,
+            time: 0.0,
+        }
+    }
+}
+
+impl Wind {
+    pub fn tick(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// Current gust from 0 to 1. Two slow waves, so gusts come irregularly
+    pub fn gust(&self) -> f32 {
+        ((self.time * 0.8).sin() * (self.time * 0.31 + 1.7).sin()).max(0.0)
+    }
+
+    /// Offset of vegetation tops in world space
+    pub fn sway(&self) -> Vec3 {
+        let angle = self.angle + (self.time * WIND_WANDER_SPEED).sin() * WIND_WANDER;
+        let direction = Vec3::new(angle.cos(), 0.0, angle.sin());
+        direction * (self.strength + self.gust_strength * self.gust())
+    }
+}
+
+/// Rain & snow around the player. Follows a repeating schedule unless overridden, e.g. with
+/// `--weather rain`. Precipitation fades in & out, so switching weather is never abrupt
+pub struct Weather {
+    schedule: Vec<WeatherPhase>,
+    // Seconds into the current cycle of the schedule
+    cycle_time: f32,
+    /// Replaces the schedule while set
+    pub override_kind: Option<WeatherKind>,
+    // Precipitation currently falling. Only switches once the previous one faded out
+    kind: WeatherKind,
+    intensity: f32,
+    wetness: f32,
+    // Fractional particles carried over to the next tick
+    pending_particles: f32,
+    pub wind: Wind,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self {
+            schedule: DEFAULT_SCHEDULE.to_vec(),
+            cycle_time: 0.0,
+            override_kind: None,
+            kind: WeatherKind::Clear,
+            intensity: 0.0,
+            wetness: 0.0,
+            pending_particles: 0.0,
+            wind: Wind::default(),
+        }
+    }
+}
+
+impl Weather {
+    pub fn new() -> Weather {
+        Self::default()
+    }
+
+    fn cycle_length(&self) -> f32 {
+        self.schedule.iter().map(|phase| phase.duration).sum()
+    }
+
+    /// Weather the schedule or the override asks for
+    pub fn target(&self) -> WeatherKind {
+        if let Some(kind) = self.override_kind {
+            return kind;
+        }
+        let mut start = 0.0;
+        for phase in &self.schedule {
+            if self.cycle_time < start + phase.duration {
+                return phase.kind;
+            }
+            start += phase.duration;
+        }
+        WeatherKind::Clear
+    }
+
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    /// Strength of the precipitation from 0 to 1
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// How soaked surfaces are from 0 to 1. Darkens voxels open to the sky
+    pub fn wetness(&self) -> f32 {
+        self.wetness
+    }
+
+    /// Wind sway of vegetation. Rain & snow come with stronger wind
+    pub fn wind_sway(&self) -> Vec3 {
+        self.wind.sway() * (1.0 + self.intensity)
+    }
+
+    /// Factor applied to the fog density
+    pub fn fog_scale(&self) -> f32 {
+        1.0 + (self.kind.fog_scale() - 1.0) * self.intensity
+    }
+
+    pub fn tick(&mut self, dt: f32) {
+        self.wind.tick(dt);
+        let cycle_length = self.cycle_length();
+        if cycle_length > 0.0 {
+            self.cycle_time = (self.cycle_time + dt) % cycle_length;
+        }
+        let target = self.target();
+        if target == self.kind {
+            let step = INTENSITY_SPEED * dt;
+            self.intensity = match target {
+                WeatherKind::Clear => (self.intensity - step).max(0.0),
+                _ => (self.intensity + step).min(1.0),
+            };
+        } else {
+            // Previous precipitation stops before the next one starts
+            self.intensity = (self.intensity - INTENSITY_SPEED * dt).max(0.0);
+            if self.intensity == 0.0 {
+                self.kind = target;
+            }
+        }
+        self.wetness = match self.kind {
+            WeatherKind::Rain => self.wetness + self.intensity * dt / SOAK_TIME,
+            _ => self.wetness - dt / DRY_TIME,
+        }
+        .clamp(0.0, 1.0);
+    }
+
+    /// Spawns the rain drops or snow flakes of this tick around the position. Particles are only
+    /// spawned above open sky & expire once they reached the ground
+    pub fn spawn_particles(
+        &mut self,
+        world: &mut World,
+        heightmap: &Heightmap,
+        position: Vec3,
+        rng: &mut SeededRng,
+        dt: f32,
+    ) {
+        self.pending_particles += self.kind.particle_rate() * self.intensity * dt;
+        let (speed, size, color) = self.kind.particle();
+        while self.pending_particles >= 1.0 {
+            self.pending_particles -= 1.0;
+            let angle = rng.next_f32() * std::f32::consts::TAU;
+            // Square root spreads particles evenly over the disc
+            let distance = rng.next_f32().sqrt() * PARTICLE_RADIUS;
+            let start = position
+                + Vec3::new(
+                    angle.cos() * distance,
+                    PARTICLE_HEIGHT * rng.range(0.5, 1.0),
+                    angle.sin() * distance,
+                );
+            let column = start.round().as_ivec3();
+            let ground = heightmap
+                .height(column.x, column.z)
+                .map_or(position.y - PARTICLE_HEIGHT, |height| height as f32 + 0.5);
+            if ground >= start.y {
+                // Covered, e.g. in a cave
+                continue;
+            }
+            let transform = Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, start);
+            world.spawn((
+                Transform(transform),
+                Velocity(Vec3::NEG_Y * speed),
+                Lifetime((start.y - ground) / speed),
+                RenderMeshHandle(MESH_CUBE),
+                RenderColor(color),
+            ));
+        }
+    }
+
+    pub fn render_ui(&mut self, ui: &Ui) {
+        ui.window("Environment")
+            .size([260.0, 230.0], imgui::Condition::FirstUseEver)
+            .position([600.0, 230.0], imgui::Condition::FirstUseEver)
+            .collapsed(true, imgui::Condition::FirstUseEver)
+            .build(|| {
+                ui.text(format!(
+                    "{} ({:.0}%)",
+                    self.kind.name(),
+                    self.intensity * 100.0
+                ));
+                ui.text(format!("Wetness: {:.0}%", self.wetness * 100.0));
+                let cycle_length = self.cycle_length();
+                ui.slider("Cycle time", 0.0, cycle_length, &mut self.cycle_time);
+                let mut labels = vec!["schedule"];
+                labels.extend(WeatherKind::ALL.map(WeatherKind::name));
+                let mut selected = self
+                    .override_kind
+                    .and_then(|kind| WeatherKind::ALL.iter().position(|k| *k == kind))
+                    .map_or(0, |index| index + 1);
+                if ui.combo_simple_string("Override", &mut selected, &labels) {
+                    self.override_kind = selected.checked_sub(1).map(|i| WeatherKind::ALL[i]);
+                }
+                ui.separator();
+                let mut direction = self.wind.angle.to_degrees();
+                if ui.slider("Wind direction", -180.0, 180.0, &mut direction) {
+                    self.wind.angle = direction.to_radians();
+                }
+                ui.slider("Wind strength", 0.0, 0.5, &mut self.wind.strength);
+                ui.slider("Gust strength", 0.0, 0.5, &mut self.wind.gust_strength);
+                ui.text(format!("Gust: {:.0}%", self.wind.gust() * 100.0));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_and_transitions() {
+        let mut weather = Weather::new();
+        assert_eq!(weather.target(), WeatherKind::Clear);
+        weather.tick(DEFAULT_SCHEDULE[0].duration + 1.0);
+        assert_eq!(weather.target(), WeatherKind::Rain);
+        for _ in 0..20 * 60 {
+            weather.tick(1.0 / 60.0);
+        }
+        assert_eq!(weather.kind(), WeatherKind::Rain);
+        assert!(weather.intensity() > 0.99);
+        assert!(weather.wetness() > 0.0);
+        assert!(weather.fog_scale() > 1.9);
+
+        // Rain stops before the overridden snow starts
+        weather.override_kind = Some(WeatherKind::Snow);
+        weather.tick(1.0);
+        assert_eq!(weather.kind(), WeatherKind::Rain);
+        for _ in 0..20 * 60 {
+            weather.tick(1.0 / 60.0);
+        }
+        assert_eq!(weather.kind(), WeatherKind::Snow);
+        assert!(weather.intensity() > 0.0);
+    }
+
+    #[test]
+    fn test_wind_sway_stays_in_bounds() {
+        let mut wind = Wind::default();
+        let max = wind.strength + wind.gust_strength;
+        let mut gusted = false;
+        for _ in 0..60 * 60 {
+            wind.tick(1.0 / 60.0);
+            let sway = wind.sway();
+            assert_eq!(sway.y, 0.0);
+            assert!(sway.length() >= wind.strength - 1e-5 && sway.length() <= max + 1e-5);
+            gusted |= wind.gust() > 0.5;
+        }
+        assert!(gusted);
+    }
+
+    #[test]
+    fn test_particles_only_fall_outdoors() {
+        let mut world = World::new();
+        let mut weather = Weather::new();
+        weather.override_kind = Some(WeatherKind::Rain);
+        for _ in 0..20 * 60 {
+            weather.tick(1.0 / 60.0);
+        }
+        weather.spawn_particles(
+            &mut world,
+            &Heightmap::new(),
+            Vec3::ZERO,
+            &mut SeededRng::default(),
+            0.1,
+        );
+        let spawned = world.query::<&Lifetime>().iter().count();
+        assert_eq!(spawned, 30);
+        for (_entity, (transform, lifetime)) in world.query::<(&Transform, &Lifetime)>().iter() {
+            let position = transform.0.w_axis;
+            assert!(position.truncate().length() <= PARTICLE_RADIUS + PARTICLE_HEIGHT);
+            // Expires once it fell back to the height of the position
+            assert!((position.y + PARTICLE_HEIGHT - lifetime.0 * 18.0).abs() < 1e-3);
+        }
+    }
+}